@@ -32,10 +32,12 @@ use jmap_client::client::{Client, Credentials};
 use modules::{
     cli::{Cli, Commands},
     database::cmd_database,
+    directory::{cmd_domain, cmd_principal},
     export::cmd_export,
     get,
     import::cmd_import,
     is_localhost, post,
+    quarantine::cmd_quarantine,
     queue::cmd_queue,
     report::cmd_report,
 };
@@ -71,12 +73,17 @@ async fn main() -> std::io::Result<()> {
                 cmd_export(build_client(&args.url, credentials).await, command).await
             }
             Commands::Database(command) => cmd_database(&args.url, credentials, command).await,
-            Commands::Queue(_) | Commands::Report(_) => unreachable!(),
+            Commands::Principal(command) => cmd_principal(&args.url, credentials, command).await,
+            Commands::Domain(command) => cmd_domain(&args.url, credentials, command).await,
+            Commands::Queue(_) | Commands::Report(_) | Commands::Quarantine(_) => {
+                unreachable!()
+            }
         }
     } else {
         match args.command {
             Commands::Queue(command) => cmd_queue(&args.url, credentials, command).await,
             Commands::Report(command) => cmd_report(&args.url, credentials, command).await,
+            Commands::Quarantine(command) => cmd_quarantine(&args.url, credentials, command).await,
             _ => unreachable!(),
         }
     }
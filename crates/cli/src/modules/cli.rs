@@ -23,7 +23,7 @@
 
 use clap::{Parser, Subcommand, ValueEnum};
 use mail_parser::DateTime;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Parser)]
 #[clap(version, about, long_about = None)]
@@ -41,11 +41,11 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Import JMAP accounts and Maildir/mbox mailboxes
+    /// Import JMAP accounts, Maildir/mbox mailboxes and remote IMAP accounts
     #[clap(subcommand)]
     Import(ImportCommands),
 
-    /// Export JMAP accounts
+    /// Export JMAP accounts and Maildir/mbox mailboxes
     #[clap(subcommand)]
     Export(ExportCommands),
 
@@ -53,6 +53,14 @@ pub enum Commands {
     #[clap(subcommand)]
     Database(DatabaseCommands),
 
+    /// Manage principals in the internal directory
+    #[clap(subcommand)]
+    Principal(PrincipalCommands),
+
+    /// Manage local domains in the internal directory
+    #[clap(subcommand)]
+    Domain(DomainCommands),
+
     /// Manage SMTP message queue
     #[clap(subcommand)]
     Queue(QueueCommands),
@@ -60,6 +68,10 @@ pub enum Commands {
     /// Manage SMTP DMARC/TLS report queue
     #[clap(subcommand)]
     Report(ReportCommands),
+
+    /// Manage SMTP quarantined messages
+    #[clap(subcommand)]
+    Quarantine(QuarantineCommands),
 }
 
 #[derive(Subcommand)]
@@ -92,6 +104,37 @@ pub enum ImportCommands {
         /// Path to the exported account directory
         path: String,
     },
+    /// Import messages and folders from a remote IMAP server
+    Imap {
+        /// Number of messages to import concurrently, defaults to the number of CPUs.
+        #[clap(short, long)]
+        num_concurrent: Option<usize>,
+
+        /// IMAP server port, defaults to 993 (or 143 when TLS is disabled)
+        #[clap(long)]
+        port: Option<u16>,
+
+        /// Connect to the IMAP server without TLS
+        #[clap(long)]
+        no_tls: bool,
+
+        /// Password of the source account, prompted for when not provided
+        #[clap(short, long)]
+        password: Option<String>,
+
+        /// Path to the file used to resume interrupted imports, defaults to 'imap-<username>@<host>.json'
+        #[clap(long)]
+        checkpoint: Option<String>,
+
+        /// Account name or email to import messages into
+        account: String,
+
+        /// IMAP server hostname
+        host: String,
+
+        /// Username of the source account
+        username: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -108,6 +151,22 @@ pub enum ExportCommands {
         /// Path to export the account to
         path: String,
     },
+    /// Export messages and folders to Maildir or mbox
+    Messages {
+        #[clap(value_enum)]
+        #[clap(short, long)]
+        format: MailboxFormat,
+
+        /// Number of concurrent blob downloads to perform, defaults to the number of CPUs.
+        #[clap(short, long)]
+        num_concurrent: Option<usize>,
+
+        /// Account name or email to export messages from
+        account: String,
+
+        /// Path to export the messages to
+        path: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -128,6 +187,180 @@ pub enum DatabaseCommands {
 
     /// Purge expired blobs
     Purge {},
+
+    /// Write a snapshot of the store and blobs to an archive on the server
+    Backup {
+        /// Archive path on the server
+        path: String,
+
+        /// Only include accounts that changed since this archive
+        #[clap(short, long)]
+        incremental: Option<String>,
+    },
+
+    /// Restore the server or a single account from archives on the server
+    Restore {
+        /// Archive paths on the server, the full archive followed by its incremental archives
+        #[clap(required = true)]
+        paths: Vec<String>,
+
+        /// Only restore this account
+        #[clap(short, long)]
+        account: Option<String>,
+    },
+
+    /// Copy the database of a server running another backend into this server
+    Migrate {
+        /// Archive paths on the server, the full archive followed by its incremental archives
+        #[clap(required_unless_present = "source", conflicts_with = "source")]
+        paths: Vec<String>,
+
+        /// Base URL of the server to stream the database from
+        #[clap(short, long)]
+        source: Option<String>,
+
+        /// Administrator credentials of the source server
+        #[clap(long, requires = "source")]
+        source_credentials: Option<String>,
+
+        /// Checkpoint path on the server used to resume the migration
+        #[clap(short, long)]
+        checkpoint: String,
+    },
+
+    /// Display the progress of the running migration
+    MigrateStatus {},
+}
+
+#[derive(Subcommand)]
+pub enum PrincipalCommands {
+    /// Create a new individual or group
+    Create {
+        /// Principal name
+        name: String,
+        /// Principal type
+        #[clap(value_enum)]
+        #[clap(short, long)]
+        #[clap(default_value = "individual")]
+        r#type: PrincipalType,
+        /// Password
+        #[clap(short, long)]
+        password: Option<String>,
+        /// Description or full name
+        #[clap(short, long)]
+        description: Option<String>,
+        /// Disk quota in bytes
+        #[clap(short, long)]
+        quota: Option<u32>,
+        /// Email addresses, the first one being the primary address
+        #[clap(short, long)]
+        email: Vec<String>,
+        /// Groups the principal is a member of
+        #[clap(short, long)]
+        member_of: Vec<String>,
+    },
+
+    /// Update an existing principal
+    Update {
+        /// Principal name
+        name: String,
+        /// New password
+        #[clap(short, long)]
+        password: Option<String>,
+        /// New description or full name
+        #[clap(short, long)]
+        description: Option<String>,
+        /// New disk quota in bytes
+        #[clap(short, long)]
+        quota: Option<u32>,
+    },
+
+    /// Add email addresses or aliases to a principal
+    AddEmail {
+        /// Principal name
+        name: String,
+        #[clap(required = true)]
+        emails: Vec<String>,
+    },
+
+    /// Remove email addresses or aliases from a principal
+    RemoveEmail {
+        /// Principal name
+        name: String,
+        #[clap(required = true)]
+        emails: Vec<String>,
+    },
+
+    /// Add a principal to one or multiple groups
+    AddToGroup {
+        /// Principal name
+        name: String,
+        #[clap(required = true)]
+        groups: Vec<String>,
+    },
+
+    /// Remove a principal from one or multiple groups
+    RemoveFromGroup {
+        /// Principal name
+        name: String,
+        #[clap(required = true)]
+        groups: Vec<String>,
+    },
+
+    /// Delete a principal and its account data
+    Delete {
+        /// Principal name
+        name: String,
+    },
+
+    /// Displays details about a principal
+    Display {
+        /// Principal name
+        name: String,
+    },
+
+    /// List principal names
+    List {
+        /// Filter by principal type
+        #[clap(value_enum)]
+        #[clap(short, long)]
+        r#type: Option<PrincipalType>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DomainCommands {
+    /// Create a new local domain
+    Create {
+        /// Domain name
+        name: String,
+    },
+
+    /// Delete an unused local domain
+    Delete {
+        /// Domain name
+        name: String,
+    },
+
+    /// List local domains
+    List {},
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrincipalType {
+    /// Individual account
+    Individual,
+    /// Group account
+    Group,
+    /// Resource
+    Resource,
+    /// Location
+    Location,
+    /// Other principal
+    Other,
+    /// Individual account with administrator privileges
+    Superuser,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -243,9 +476,40 @@ pub enum ReportCommands {
     },
 }
 
+#[derive(Subcommand)]
+pub enum QuarantineCommands {
+    /// Shows quarantined messages
+    List {
+        /// Number of items to show per page
+        #[clap(short, long)]
+        page_size: Option<usize>,
+    },
+
+    /// Displays the envelope, headers and a preview of a quarantined message
+    Preview {
+        #[clap(required = true)]
+        ids: Vec<String>,
+    },
+
+    /// Release quarantined messages for delivery to their original recipients
+    Release {
+        #[clap(required = true)]
+        ids: Vec<String>,
+    },
+
+    /// Delete quarantined messages
+    Delete {
+        #[clap(required = true)]
+        ids: Vec<String>,
+    },
+}
+
 impl Commands {
     pub fn is_jmap(&self) -> bool {
-        !matches!(self, Commands::Queue(_) | Commands::Report(_))
+        !matches!(
+            self,
+            Commands::Queue(_) | Commands::Report(_) | Commands::Quarantine(_)
+        )
     }
 }
 
@@ -27,6 +27,11 @@ use reqwest::header::AUTHORIZATION;
 use super::{cli::DatabaseCommands, is_localhost, UnwrapResult};
 
 pub async fn cmd_database(url: &str, credentials: Credentials, command: DatabaseCommands) {
+    let has_response = matches!(
+        command,
+        DatabaseCommands::Backup { .. } | DatabaseCommands::MigrateStatus {}
+    );
+    let mut body = None;
     let url = match command {
         DatabaseCommands::Delete { account } => format!("{}/admin/account/delete/{}", url, account),
         DatabaseCommands::Rename {
@@ -34,24 +39,83 @@ pub async fn cmd_database(url: &str, credentials: Credentials, command: Database
             new_account,
         } => format!("{}/admin/account/rename/{}/{}", url, account, new_account),
         DatabaseCommands::Purge {} => format!("{}/admin/blob/purge", url),
+        DatabaseCommands::Backup { path, incremental } => {
+            let mut params = vec![("path", path)];
+            if let Some(previous) = incremental {
+                params.push(("previous", previous));
+            }
+            reqwest::Url::parse_with_params(&format!("{}/admin/store/backup", url), params)
+                .unwrap_result("build URL")
+                .to_string()
+        }
+        DatabaseCommands::Restore { paths, account } => {
+            let mut params = paths
+                .into_iter()
+                .map(|path| ("path", path))
+                .collect::<Vec<_>>();
+            if let Some(account) = account {
+                params.push(("account", account));
+            }
+            reqwest::Url::parse_with_params(&format!("{}/admin/store/restore", url), params)
+                .unwrap_result("build URL")
+                .to_string()
+        }
+        DatabaseCommands::Migrate {
+            paths,
+            source,
+            source_credentials,
+            checkpoint,
+        } => {
+            body = if let Some(source) = source {
+                let source_credentials = source_credentials.unwrap_or_else(|| {
+                    rpassword::prompt_password("\nEnter the source server credentials: ").unwrap()
+                });
+                let (user, secret) = source_credentials
+                    .split_once(':')
+                    .unwrap_or((source_credentials.as_str(), ""));
+                serde_json::json!({
+                    "source": {
+                        "url": source,
+                        "user": user,
+                        "secret": secret,
+                    },
+                    "checkpoint": checkpoint,
+                })
+            } else {
+                serde_json::json!({
+                    "paths": paths,
+                    "checkpoint": checkpoint,
+                })
+            }
+            .into();
+            format!("{}/admin/store/migrate", url)
+        }
+        DatabaseCommands::MigrateStatus {} => format!("{}/admin/store/migrate", url),
     };
 
-    let response = reqwest::Client::builder()
+    let client = reqwest::Client::builder()
         .danger_accept_invalid_certs(is_localhost(&url))
         .build()
-        .unwrap_or_default()
-        .get(url)
-        .header(
-            AUTHORIZATION,
-            match credentials {
-                Credentials::Basic(s) => format!("Basic {s}"),
-                Credentials::Bearer(s) => format!("Bearer {s}"),
-            },
-        )
-        .send()
-        .await
-        .unwrap_result("send GET request");
+        .unwrap_or_default();
+    let response = if let Some(body) = body {
+        client.post(url).body(body.to_string())
+    } else {
+        client.get(url)
+    }
+    .header(
+        AUTHORIZATION,
+        match credentials {
+            Credentials::Basic(s) => format!("Basic {s}"),
+            Credentials::Bearer(s) => format!("Bearer {s}"),
+        },
+    )
+    .send()
+    .await
+    .unwrap_result("send request");
     if response.status().is_success() {
+        if has_response {
+            println!("{}", response.text().await.unwrap_result("fetch text"));
+        }
         eprintln!("Success.");
     } else {
         eprintln!(
@@ -0,0 +1,336 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_client::client::Credentials;
+use prettytable::{Attr, Cell, Row, Table};
+use reqwest::{header::AUTHORIZATION, Method};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    cli::{DomainCommands, PrincipalCommands, PrincipalType},
+    is_localhost, UnwrapResult,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Principal {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub typ: PrincipalType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub quota: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub secrets: Vec<String>,
+    #[serde(default)]
+    pub emails: Vec<String>,
+    #[serde(default)]
+    pub member_of: Vec<String>,
+    #[serde(default)]
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PrincipalUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quota: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub add_emails: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remove_emails: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub add_member_of: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remove_member_of: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RequestError {
+    #[serde(default)]
+    title: Option<String>,
+    detail: String,
+}
+
+pub async fn cmd_principal(url: &str, credentials: Credentials, command: PrincipalCommands) {
+    match command {
+        PrincipalCommands::Create {
+            name,
+            r#type,
+            password,
+            description,
+            quota,
+            email,
+            member_of,
+        } => {
+            directory_request::<String>(
+                Method::POST,
+                &format!("{url}/admin/principal/create"),
+                &credentials,
+                serde_json::to_string(&Principal {
+                    name,
+                    typ: r#type,
+                    description,
+                    quota: quota.unwrap_or_default(),
+                    secrets: password.into_iter().collect(),
+                    emails: email,
+                    member_of,
+                    members: vec![],
+                })
+                .unwrap_result("serialize request")
+                .into(),
+            )
+            .await;
+            eprintln!("Success.");
+        }
+        PrincipalCommands::Update {
+            name,
+            password,
+            description,
+            quota,
+        } => {
+            update_principal(
+                url,
+                &credentials,
+                &name,
+                PrincipalUpdate {
+                    description,
+                    quota,
+                    secret: password,
+                    ..Default::default()
+                },
+            )
+            .await
+        }
+        PrincipalCommands::AddEmail { name, emails } => {
+            update_principal(
+                url,
+                &credentials,
+                &name,
+                PrincipalUpdate {
+                    add_emails: emails,
+                    ..Default::default()
+                },
+            )
+            .await
+        }
+        PrincipalCommands::RemoveEmail { name, emails } => {
+            update_principal(
+                url,
+                &credentials,
+                &name,
+                PrincipalUpdate {
+                    remove_emails: emails,
+                    ..Default::default()
+                },
+            )
+            .await
+        }
+        PrincipalCommands::AddToGroup { name, groups } => {
+            update_principal(
+                url,
+                &credentials,
+                &name,
+                PrincipalUpdate {
+                    add_member_of: groups,
+                    ..Default::default()
+                },
+            )
+            .await
+        }
+        PrincipalCommands::RemoveFromGroup { name, groups } => {
+            update_principal(
+                url,
+                &credentials,
+                &name,
+                PrincipalUpdate {
+                    remove_member_of: groups,
+                    ..Default::default()
+                },
+            )
+            .await
+        }
+        PrincipalCommands::Delete { name } => {
+            directory_request::<String>(
+                Method::GET,
+                &format!("{url}/admin/principal/delete/{name}"),
+                &credentials,
+                None,
+            )
+            .await;
+            eprintln!("Success.");
+        }
+        PrincipalCommands::Display { name } => {
+            let principal = directory_request::<Principal>(
+                Method::GET,
+                &format!("{url}/admin/principal/get/{name}"),
+                &credentials,
+                None,
+            )
+            .await;
+
+            let mut table = Table::new();
+            for (title, value) in [
+                ("Name", principal.name),
+                (
+                    "Type",
+                    serde_json::to_value(principal.typ)
+                        .ok()
+                        .and_then(|v| v.as_str().map(|v| v.to_string()))
+                        .unwrap_or_default(),
+                ),
+                ("Description", principal.description.unwrap_or_default()),
+                ("Quota", principal.quota.to_string()),
+                ("Emails", principal.emails.join("\n")),
+                ("Member of", principal.member_of.join("\n")),
+                ("Members", principal.members.join("\n")),
+            ] {
+                table.add_row(Row::new(vec![
+                    Cell::new(title).with_style(Attr::Bold),
+                    Cell::new(&value),
+                ]));
+            }
+
+            eprintln!();
+            table.printstd();
+            eprintln!();
+        }
+        PrincipalCommands::List { r#type } => {
+            let mut url = format!("{url}/admin/principal/list");
+            if let Some(typ) = r#type.and_then(|typ| {
+                serde_json::to_value(typ)
+                    .ok()
+                    .and_then(|v| v.as_str().map(|v| v.to_string()))
+            }) {
+                url = format!("{url}?type={typ}");
+            }
+            let names =
+                directory_request::<Vec<String>>(Method::GET, &url, &credentials, None).await;
+            for name in &names {
+                println!("{name}");
+            }
+            eprintln!("\n{} principal(s) found.", names.len());
+        }
+    }
+}
+
+pub async fn cmd_domain(url: &str, credentials: Credentials, command: DomainCommands) {
+    match command {
+        DomainCommands::Create { name } => {
+            directory_request::<String>(
+                Method::GET,
+                &format!("{url}/admin/domain/create/{name}"),
+                &credentials,
+                None,
+            )
+            .await;
+            eprintln!("Success.");
+        }
+        DomainCommands::Delete { name } => {
+            directory_request::<String>(
+                Method::GET,
+                &format!("{url}/admin/domain/delete/{name}"),
+                &credentials,
+                None,
+            )
+            .await;
+            eprintln!("Success.");
+        }
+        DomainCommands::List {} => {
+            let domains = directory_request::<Vec<String>>(
+                Method::GET,
+                &format!("{url}/admin/domain/list"),
+                &credentials,
+                None,
+            )
+            .await;
+            for domain in &domains {
+                println!("{domain}");
+            }
+            eprintln!("\n{} domain(s) found.", domains.len());
+        }
+    }
+}
+
+async fn update_principal(
+    url: &str,
+    credentials: &Credentials,
+    name: &str,
+    update: PrincipalUpdate,
+) {
+    directory_request::<String>(
+        Method::POST,
+        &format!("{url}/admin/principal/update/{name}"),
+        credentials,
+        serde_json::to_string(&update)
+            .unwrap_result("serialize request")
+            .into(),
+    )
+    .await;
+    eprintln!("Success.");
+}
+
+async fn directory_request<T: DeserializeOwned>(
+    method: Method,
+    url: &str,
+    credentials: &Credentials,
+    body: Option<String>,
+) -> T {
+    let mut request = reqwest::Client::builder()
+        .danger_accept_invalid_certs(is_localhost(url))
+        .build()
+        .unwrap_or_default()
+        .request(method, url)
+        .header(
+            AUTHORIZATION,
+            match credentials {
+                Credentials::Basic(s) => format!("Basic {s}"),
+                Credentials::Bearer(s) => format!("Bearer {s}"),
+            },
+        );
+    if let Some(body) = body {
+        request = request.body(body);
+    }
+    let response = request.send().await.unwrap_result("send request");
+    let is_success = response.status().is_success();
+    let bytes = response.bytes().await.unwrap_result("fetch bytes");
+
+    if is_success {
+        serde_json::from_slice::<T>(&bytes).unwrap_result("deserialize response")
+    } else {
+        match serde_json::from_slice::<RequestError>(&bytes) {
+            Ok(err) => eprintln!(
+                "Request failed: {} ({})",
+                err.detail,
+                err.title.unwrap_or_default()
+            ),
+            Err(_) => eprintln!("Request failed: {}", String::from_utf8_lossy(&bytes)),
+        }
+        std::process::exit(1);
+    }
+}
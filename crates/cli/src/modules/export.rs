@@ -22,23 +22,36 @@
 */
 
 use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
     path::{Path, PathBuf},
     sync::Arc,
 };
 
+use filetime::FileTime;
 use futures::{stream::FuturesUnordered, StreamExt};
 use jmap_client::{
     client::Client,
     email::{self, Email},
     identity::{self, Identity},
-    mailbox::{self, Mailbox},
+    mailbox::{self, Mailbox, Role},
     sieve::{self, SieveScript},
     vacation_response::{self, VacationResponse},
 };
 use serde::Serialize;
 use tokio::io::AsyncWriteExt;
 
-use super::{cli::ExportCommands, name_to_id, UnwrapResult};
+use super::{
+    cli::{ExportCommands, MailboxFormat},
+    import::build_mailbox_tree,
+    name_to_id, UnwrapResult,
+};
+
+enum ExportFolder {
+    Maildir(PathBuf),
+    Mbox(BufWriter<File>),
+}
 
 pub async fn cmd_export(mut client: Client, command: ExportCommands) {
     match command {
@@ -118,7 +131,241 @@ pub async fn cmd_export(mut client: Client, command: ExportCommands) {
             // Wait for remaining futures
             while futures.next().await.is_some() {}
         }
+        ExportCommands::Messages {
+            format,
+            num_concurrent,
+            account,
+            path,
+        } => {
+            client.set_default_account_id(name_to_id(&client, &account).await);
+            let max_objects_in_get = client
+                .session()
+                .core_capabilities()
+                .map(|c| c.max_objects_in_get())
+                .unwrap_or(500);
+
+            // Create directory
+            let mut path = PathBuf::from(path);
+            if !path.is_dir() {
+                eprintln!("Directory {} does not exist.", path.display());
+                std::process::exit(1);
+            }
+            path.push(&account);
+            if !path.is_dir() {
+                std::fs::create_dir(&path).unwrap_or_else(|_| {
+                    eprintln!("Failed to create directory: {}", path.display());
+                    std::process::exit(1);
+                });
+            }
+
+            // Create folders
+            let mailboxes = fetch_mailboxes(&client, max_objects_in_get).await;
+            let mut folders = HashMap::with_capacity(mailboxes.len());
+            for (name, mailbox) in build_mailbox_tree(&mailboxes) {
+                folders.insert(
+                    mailbox.id().unwrap_result("obtain mailbox id"),
+                    create_folder(
+                        format,
+                        &path,
+                        if mailbox.role() != Role::Inbox {
+                            name.as_slice()
+                        } else {
+                            &[]
+                        },
+                    ),
+                );
+            }
+            eprintln!("Exported {} mailboxes.", folders.len());
+
+            // Export messages
+            let emails = fetch_emails(&client, max_objects_in_get).await;
+            let client = Arc::new(client);
+            let num_concurrent = num_concurrent.unwrap_or_else(num_cpus::get);
+            let mut futures = FuturesUnordered::new();
+            let mut total_exported = 0;
+            eprintln!("Exporting {} emails...", emails.len());
+            for email in emails {
+                let blob_id = if let Some(blob_id) = email.blob_id() {
+                    blob_id.to_string()
+                } else {
+                    eprintln!(
+                        "Warning: email {:?} has no blobId",
+                        email.id().unwrap_or_default()
+                    );
+                    continue;
+                };
+                let client = client.clone();
+
+                futures.push(async move {
+                    let bytes = client
+                        .download(&blob_id)
+                        .await
+                        .unwrap_result("download blob");
+                    (email, bytes)
+                });
+
+                if futures.len() == num_concurrent {
+                    let (email, bytes) = futures.next().await.unwrap();
+                    total_exported += write_message(&mut folders, &email, &bytes);
+                }
+            }
+
+            // Wait for remaining futures
+            while let Some((email, bytes)) = futures.next().await {
+                total_exported += write_message(&mut folders, &email, &bytes);
+            }
+            for folder in folders.values_mut() {
+                if let ExportFolder::Mbox(file) = folder {
+                    file.flush().unwrap_result("write mbox file");
+                }
+            }
+
+            eprintln!("Exported {} emails.", total_exported);
+        }
+    }
+}
+
+fn folder_path(format: MailboxFormat, path: &Path, name: &[&str]) -> PathBuf {
+    let mut path = PathBuf::from(path);
+    match format {
+        MailboxFormat::Maildir => {
+            if !name.is_empty() {
+                path.push(format!(
+                    ".{}",
+                    name.iter()
+                        .map(|name| name.replace(['.', '/'], "_"))
+                        .collect::<Vec<_>>()
+                        .join(".")
+                ));
+            }
+        }
+        MailboxFormat::MaildirNested => {
+            for name in name {
+                path.push(name.replace('/', "_"));
+            }
+        }
+        MailboxFormat::Mbox => {
+            if let Some((name, parents)) = name.split_last() {
+                for parent in parents {
+                    path.push(parent.replace('/', "_"));
+                }
+                path.push(format!("{}.mbox", name.replace('/', "_")));
+            } else {
+                path.push("Inbox.mbox");
+            }
+        }
+    }
+    path
+}
+
+fn create_folder(format: MailboxFormat, path: &Path, name: &[&str]) -> ExportFolder {
+    let path = folder_path(format, path, name);
+    match format {
+        MailboxFormat::Maildir | MailboxFormat::MaildirNested => {
+            for dir in ["cur", "new", "tmp"] {
+                std::fs::create_dir_all(path.join(dir))
+                    .unwrap_result(&format!("create directory {}", path.display()));
+            }
+            if !name.is_empty() && format == MailboxFormat::Maildir {
+                std::fs::write(path.join("maildirfolder"), b"")
+                    .unwrap_result(&format!("create Maildir folder {}", path.display()));
+            }
+            ExportFolder::Maildir(path)
+        }
+        MailboxFormat::Mbox => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .unwrap_result(&format!("create directory {}", parent.display()));
+            }
+            ExportFolder::Mbox(BufWriter::new(
+                File::create(&path).unwrap_result(&format!("create {}", path.display())),
+            ))
+        }
+    }
+}
+
+fn write_message(folders: &mut HashMap<&str, ExportFolder>, email: &Email, bytes: &[u8]) -> usize {
+    let id = email.id().unwrap_or_default();
+    let received_at = email.received_at().unwrap_or_default();
+    let mut num_written = 0;
+
+    for mailbox_id in email.mailbox_ids() {
+        match folders.get_mut(mailbox_id) {
+            Some(ExportFolder::Maildir(path)) => {
+                let mut path = path.join("cur");
+                path.push(format!(
+                    "{received_at}.{id}.stalwart:2,{}",
+                    maildir_flags(&email.keywords())
+                ));
+                std::fs::write(&path, bytes).unwrap_result(&format!("write {}", path.display()));
+                if received_at > 0 {
+                    let _ =
+                        filetime::set_file_mtime(&path, FileTime::from_unix_time(received_at, 0));
+                }
+            }
+            Some(ExportFolder::Mbox(file)) => {
+                write_mbox_message(file, received_at, bytes).unwrap_result("write mbox file");
+            }
+            None => {
+                eprintln!("Warning: email {id:?} belongs to unknown mailbox {mailbox_id:?}");
+                continue;
+            }
+        }
+        num_written += 1;
+    }
+
+    num_written
+}
+
+fn maildir_flags(keywords: &[&str]) -> String {
+    // Maildir flags have to be in ASCII order
+    let mut flags = keywords
+        .iter()
+        .filter_map(|keyword| match keyword.to_ascii_lowercase().as_str() {
+            "$draft" => Some('D'),
+            "$flagged" => Some('F'),
+            "$forwarded" | "$passed" => Some('P'),
+            "$answered" => Some('R'),
+            "$seen" => Some('S'),
+            "$deleted" => Some('T'),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    flags.sort_unstable();
+    flags.dedup();
+    flags.into_iter().collect()
+}
+
+fn write_mbox_message(
+    file: &mut impl Write,
+    received_at: i64,
+    bytes: &[u8],
+) -> std::io::Result<()> {
+    // Write message using the mboxrd format
+    writeln!(
+        file,
+        "From MAILER-DAEMON {}",
+        chrono::NaiveDateTime::from_timestamp_opt(received_at, 0)
+            .unwrap_or_default()
+            .format("%a %b %e %H:%M:%S %Y")
+    )?;
+    for line in bytes.split_inclusive(|&ch| ch == b'\n') {
+        if line
+            .iter()
+            .skip_while(|&&ch| ch == b'>')
+            .take(5)
+            .eq(b"From ".iter())
+        {
+            file.write_all(b">")?;
+        }
+        let line = line
+            .strip_suffix(b"\r\n")
+            .or_else(|| line.strip_suffix(b"\n"))
+            .unwrap_or(line);
+        file.write_all(line)?;
+        file.write_all(b"\n")?;
     }
+    file.write_all(b"\n")
 }
 
 pub async fn fetch_mailboxes(client: &Client, max_objects_in_get: usize) -> Vec<Mailbox> {
@@ -427,3 +674,92 @@ async fn write_file<T: Serialize>(path: &Path, name: &str, contents: Vec<T>) ->
         .unwrap_result(&format!("write to {}", path.display()));
     len
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::modules::cli::MailboxFormat;
+
+    use super::{folder_path, maildir_flags, write_mbox_message};
+
+    #[test]
+    fn maildir_flags_mapping() {
+        assert_eq!(maildir_flags(&[]), "");
+        assert_eq!(
+            maildir_flags(&["$seen", "$Answered", "$flagged", "$draft", "$deleted"]),
+            "DFRST"
+        );
+        assert_eq!(
+            maildir_flags(&["$forwarded", "$passed", "$junk", "custom"]),
+            "P"
+        );
+    }
+
+    #[test]
+    fn folder_hierarchy() {
+        let base = Path::new("/export");
+        for (format, name, expected) in [
+            (MailboxFormat::Maildir, &[][..], "/export"),
+            (MailboxFormat::Maildir, &["Work"][..], "/export/.Work"),
+            (
+                MailboxFormat::Maildir,
+                &["Work", "v1.0", "a/b"][..],
+                "/export/.Work.v1_0.a_b",
+            ),
+            (MailboxFormat::MaildirNested, &[][..], "/export"),
+            (
+                MailboxFormat::MaildirNested,
+                &["Work", "v1.0", "a/b"][..],
+                "/export/Work/v1.0/a_b",
+            ),
+            (MailboxFormat::Mbox, &[][..], "/export/Inbox.mbox"),
+            (MailboxFormat::Mbox, &["Sent"][..], "/export/Sent.mbox"),
+            (
+                MailboxFormat::Mbox,
+                &["Work", "a/b", "Done"][..],
+                "/export/Work/a_b/Done.mbox",
+            ),
+        ] {
+            assert_eq!(
+                folder_path(format, base, name),
+                PathBuf::from(expected),
+                "failed for {name:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn mboxrd_quoting() {
+        let mut mbox = Vec::new();
+        write_mbox_message(
+            &mut mbox,
+            0,
+            concat!(
+                "Subject: test\r\n",
+                "\r\n",
+                "From the start\r\n",
+                ">From quoted\r\n",
+                ">>From twice\r\n",
+                " From indented\r\n",
+                "From"
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(mbox).unwrap(),
+            concat!(
+                "From MAILER-DAEMON Thu Jan  1 00:00:00 1970\n",
+                "Subject: test\n",
+                "\n",
+                ">From the start\n",
+                ">>From quoted\n",
+                ">>>From twice\n",
+                " From indented\n",
+                "From\n",
+                "\n"
+            )
+        );
+    }
+}
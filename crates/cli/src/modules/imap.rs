@@ -0,0 +1,743 @@
+/*
+ * Copyright (c) 2020-2023, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{collections::HashMap, io, path::Path, sync::Arc};
+
+use rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+use tokio_rustls::TlsConnector;
+
+pub trait ImapStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ImapStream for T {}
+
+pub struct ImapClient {
+    stream: BufReader<Box<dyn ImapStream>>,
+    tag: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Atom(String),
+    String(Vec<u8>),
+    List(Vec<Token>),
+    Nil,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Folder {
+    pub name: String,
+    pub path: Vec<String>,
+    pub attributes: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct FetchedMessage {
+    pub uid: u32,
+    pub flags: Vec<String>,
+    pub internal_date: i64,
+    pub contents: Vec<u8>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub folders: HashMap<String, FolderCheckpoint>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FolderCheckpoint {
+    pub uid_validity: u32,
+    pub last_uid: u32,
+    #[serde(default)]
+    pub failed_uids: Vec<u32>,
+}
+
+impl ImapClient {
+    pub async fn connect(host: &str, port: u16, tls: bool) -> io::Result<Self> {
+        let stream = TcpStream::connect((host, port)).await?;
+        if tls {
+            let mut root_cert_store = RootCertStore::empty();
+            root_cert_store.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
+                OwnedTrustAnchor::from_subject_spki_name_constraints(
+                    ta.subject,
+                    ta.spki,
+                    ta.name_constraints,
+                )
+            }));
+            let config = ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(root_cert_store)
+                .with_no_client_auth();
+            let server_name = ServerName::try_from(host)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid hostname."))?;
+            let stream = TlsConnector::from(Arc::new(config))
+                .connect(server_name, stream)
+                .await?;
+            ImapClient::from_stream(Box::new(stream)).await
+        } else {
+            ImapClient::from_stream(Box::new(stream)).await
+        }
+    }
+
+    pub async fn from_stream(stream: Box<dyn ImapStream>) -> io::Result<Self> {
+        let mut client = ImapClient {
+            stream: BufReader::new(stream),
+            tag: 0,
+        };
+        let greeting = client.read_response().await?;
+        if greeting.starts_with(b"* OK") || greeting.starts_with(b"* PREAUTH") {
+            Ok(client)
+        } else {
+            Err(protocol_error(&greeting))
+        }
+    }
+
+    pub async fn login(&mut self, username: &str, password: &str) -> io::Result<()> {
+        self.command(&format!("LOGIN {} {}", quote(username), quote(password)))
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn list(&mut self) -> io::Result<Vec<Folder>> {
+        let mut folders = Vec::new();
+        for response in self.command("LIST \"\" \"*\"").await? {
+            if let [Token::Atom(command), Token::List(attributes), delimiter, name] =
+                response.as_slice()
+            {
+                if !command.eq_ignore_ascii_case("LIST") {
+                    continue;
+                }
+                let name = match name {
+                    Token::Atom(name) => name.clone(),
+                    Token::String(name) => String::from_utf8_lossy(name).into_owned(),
+                    _ => continue,
+                };
+                let delimiter = match delimiter {
+                    Token::String(delimiter) => String::from_utf8_lossy(delimiter).into_owned(),
+                    _ => String::new(),
+                };
+                let decoded_name = utf7_decode(name.as_bytes()).unwrap_or_else(|| name.clone());
+                let path = if !delimiter.is_empty() {
+                    decoded_name
+                        .split(&delimiter)
+                        .map(|part| part.to_string())
+                        .collect()
+                } else {
+                    vec![decoded_name]
+                };
+                folders.push(Folder {
+                    name,
+                    path,
+                    attributes: attributes
+                        .iter()
+                        .filter_map(|attribute| match attribute {
+                            Token::Atom(attribute) => Some(attribute.to_string()),
+                            _ => None,
+                        })
+                        .collect(),
+                });
+            }
+        }
+
+        Ok(folders)
+    }
+
+    pub async fn examine(&mut self, name: &str) -> io::Result<u32> {
+        for response in self.command(&format!("EXAMINE {}", quote(name))).await? {
+            if let [Token::Atom(status), Token::Atom(code), ..] = response.as_slice() {
+                if status.eq_ignore_ascii_case("OK") {
+                    if let Some(uid_validity) = code
+                        .strip_prefix("[UIDVALIDITY ")
+                        .and_then(|code| code.strip_suffix(']'))
+                        .and_then(|code| code.parse().ok())
+                    {
+                        return Ok(uid_validity);
+                    }
+                }
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Server did not return an UIDVALIDITY for folder {name:?}."),
+        ))
+    }
+
+    pub async fn uid_search(&mut self, sequence_set: &str) -> io::Result<Vec<u32>> {
+        let mut uids = Vec::new();
+        for response in self
+            .command(&format!("UID SEARCH UID {sequence_set}"))
+            .await?
+        {
+            if let Some((Token::Atom(command), results)) = response.split_first() {
+                if command.eq_ignore_ascii_case("SEARCH") {
+                    uids.extend(results.iter().filter_map(|uid| match uid {
+                        Token::Atom(uid) => uid.parse::<u32>().ok(),
+                        _ => None,
+                    }));
+                }
+            }
+        }
+        uids.sort_unstable();
+        uids.dedup();
+
+        Ok(uids)
+    }
+
+    pub async fn uid_fetch(&mut self, uids: &[u32]) -> io::Result<Vec<FetchedMessage>> {
+        let mut messages = Vec::with_capacity(uids.len());
+        for response in self
+            .command(&format!(
+                "UID FETCH {} (UID FLAGS INTERNALDATE BODY.PEEK[])",
+                sequence_set(uids)
+            ))
+            .await?
+        {
+            let items = match response.as_slice() {
+                [Token::Atom(_), Token::Atom(command), Token::List(items)]
+                    if command.eq_ignore_ascii_case("FETCH") =>
+                {
+                    items
+                }
+                _ => continue,
+            };
+            let mut message = FetchedMessage::default();
+            for item in items.chunks_exact(2) {
+                match (&item[0], &item[1]) {
+                    (Token::Atom(name), Token::Atom(uid)) if name.eq_ignore_ascii_case("UID") => {
+                        message.uid = uid.parse().unwrap_or_default();
+                    }
+                    (Token::Atom(name), Token::List(flags))
+                        if name.eq_ignore_ascii_case("FLAGS") =>
+                    {
+                        message.flags = flags
+                            .iter()
+                            .filter_map(|flag| match flag {
+                                Token::Atom(flag) => Some(flag.to_string()),
+                                _ => None,
+                            })
+                            .collect();
+                    }
+                    (Token::Atom(name), Token::String(date))
+                        if name.eq_ignore_ascii_case("INTERNALDATE") =>
+                    {
+                        message.internal_date = chrono::DateTime::parse_from_str(
+                            String::from_utf8_lossy(date).trim(),
+                            "%d-%b-%Y %H:%M:%S %z",
+                        )
+                        .map(|dt| dt.timestamp())
+                        .unwrap_or_default();
+                    }
+                    (Token::Atom(name), Token::String(contents))
+                        if name.eq_ignore_ascii_case("BODY[]") =>
+                    {
+                        message.contents = contents.clone();
+                    }
+                    _ => (),
+                }
+            }
+            if message.uid != 0 && uids.contains(&message.uid) {
+                messages.push(message);
+            }
+        }
+
+        Ok(messages)
+    }
+
+    pub async fn logout(&mut self) -> io::Result<()> {
+        self.command("LOGOUT").await.map(|_| ())
+    }
+
+    async fn command(&mut self, command: &str) -> io::Result<Vec<Vec<Token>>> {
+        self.tag += 1;
+        let tag = format!("A{} ", self.tag);
+        let stream = self.stream.get_mut();
+        stream
+            .write_all(format!("{tag}{command}\r\n").as_bytes())
+            .await?;
+        stream.flush().await?;
+
+        let mut responses = Vec::new();
+        loop {
+            let response = self.read_response().await?;
+            if let Some(status) = response.strip_prefix(tag.as_bytes()) {
+                return if status
+                    .get(..2)
+                    .map_or(false, |status| status.eq_ignore_ascii_case(b"OK"))
+                {
+                    Ok(responses)
+                } else {
+                    Err(protocol_error(&response))
+                };
+            } else if let Some(response) = response.strip_prefix(b"* ") {
+                responses.push(tokenize(response));
+            }
+        }
+    }
+
+    async fn read_response(&mut self) -> io::Result<Vec<u8>> {
+        let mut response = Vec::with_capacity(128);
+        loop {
+            if self.stream.read_until(b'\n', &mut response).await? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Connection closed by IMAP server.",
+                ));
+            }
+
+            // Read literals
+            let line = response.strip_suffix(b"\r\n").unwrap_or(&response);
+            if let Some(size) = line
+                .strip_suffix(b"}")
+                .and_then(|line| {
+                    line.iter()
+                        .rposition(|&ch| ch == b'{')
+                        .map(|pos| &line[pos + 1..])
+                })
+                .and_then(|size| std::str::from_utf8(size.strip_suffix(b"+").unwrap_or(size)).ok())
+                .and_then(|size| size.parse::<usize>().ok())
+            {
+                let offset = response.len();
+                response.resize(offset + size, 0);
+                self.stream.read_exact(&mut response[offset..]).await?;
+            } else {
+                return Ok(response);
+            }
+        }
+    }
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Checkpoint::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(
+            &tmp_path,
+            serde_json::to_vec_pretty(self).unwrap_or_default(),
+        )?;
+        std::fs::rename(tmp_path, path)
+    }
+
+    // Returns the sequence set to search for new or previously failed messages,
+    // resetting the folder state when its UIDVALIDITY changed.
+    pub fn search_set(&mut self, folder: &str, uid_validity: u32) -> String {
+        let state = self.folders.entry(folder.to_string()).or_default();
+        if state.uid_validity != uid_validity {
+            if state.uid_validity != 0 {
+                eprintln!(
+                    "UIDVALIDITY of folder {folder:?} changed, importing all messages again."
+                );
+            }
+            *state = FolderCheckpoint {
+                uid_validity,
+                ..Default::default()
+            };
+        }
+
+        if state.failed_uids.is_empty() {
+            format!("{}:*", state.last_uid + 1)
+        } else {
+            format!(
+                "{},{}:*",
+                sequence_set(&state.failed_uids),
+                state.last_uid + 1
+            )
+        }
+    }
+
+    pub fn pending(&self, folder: &str, uids: &[u32]) -> Vec<u32> {
+        let state = self.folders.get(folder);
+        uids.iter()
+            .copied()
+            .filter(|uid| {
+                state.map_or(true, |state| {
+                    *uid > state.last_uid || state.failed_uids.contains(uid)
+                })
+            })
+            .collect()
+    }
+
+    pub fn update(&mut self, folder: &str, imported: &[u32], failed: &[u32]) {
+        let state = self.folders.entry(folder.to_string()).or_default();
+        if let Some(max_uid) = imported.iter().chain(failed.iter()).max() {
+            state.last_uid = std::cmp::max(state.last_uid, *max_uid);
+        }
+        state.failed_uids.retain(|uid| !imported.contains(uid));
+        for uid in failed {
+            if !state.failed_uids.contains(uid) {
+                state.failed_uids.push(*uid);
+            }
+        }
+        state.failed_uids.sort_unstable();
+    }
+}
+
+pub fn tokenize(data: &[u8]) -> Vec<Token> {
+    let mut stack = vec![Vec::new()];
+    let mut iter = data.iter().copied().enumerate().peekable();
+
+    while let Some((pos, ch)) = iter.next() {
+        match ch {
+            b' ' | b'\r' | b'\n' => (),
+            b'(' => stack.push(Vec::new()),
+            b')' => {
+                if stack.len() > 1 {
+                    let list = stack.pop().unwrap();
+                    stack.last_mut().unwrap().push(Token::List(list));
+                }
+            }
+            b'"' => {
+                let mut value = Vec::new();
+                while let Some((_, ch)) = iter.next() {
+                    match ch {
+                        b'"' => break,
+                        b'\\' => {
+                            if let Some((_, ch)) = iter.next() {
+                                value.push(ch);
+                            }
+                        }
+                        _ => value.push(ch),
+                    }
+                }
+                stack.last_mut().unwrap().push(Token::String(value));
+            }
+            b'{' => {
+                let mut size = 0usize;
+                for (_, ch) in iter.by_ref() {
+                    match ch {
+                        b'0'..=b'9' => {
+                            size = size.saturating_mul(10).saturating_add((ch - b'0') as usize)
+                        }
+                        b'}' => break,
+                        _ => (),
+                    }
+                }
+                // Skip CRLF
+                while matches!(iter.peek(), Some((_, b'\r' | b'\n'))) {
+                    iter.next();
+                }
+                let start = iter.peek().map_or(data.len(), |(pos, _)| *pos);
+                let end = std::cmp::min(start + size, data.len());
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .push(Token::String(data[start..end].to_vec()));
+                for _ in start..end {
+                    iter.next();
+                }
+            }
+            _ => {
+                let mut end = pos + 1;
+                let mut depth = u32::from(ch == b'[');
+                while let Some((next_pos, next_ch)) = iter.peek().copied() {
+                    match next_ch {
+                        b'[' => depth += 1,
+                        b']' => depth = depth.saturating_sub(1),
+                        b' ' | b'(' | b')' | b'\r' | b'\n' if depth == 0 => break,
+                        _ => (),
+                    }
+                    end = next_pos + 1;
+                    iter.next();
+                }
+                let atom = String::from_utf8_lossy(&data[pos..end]).into_owned();
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .push(if atom.eq_ignore_ascii_case("NIL") {
+                        Token::Nil
+                    } else {
+                        Token::Atom(atom)
+                    });
+            }
+        }
+    }
+
+    while stack.len() > 1 {
+        let list = stack.pop().unwrap();
+        stack.last_mut().unwrap().push(Token::List(list));
+    }
+    stack.pop().unwrap()
+}
+
+pub fn sequence_set(uids: &[u32]) -> String {
+    let mut set = String::new();
+    let mut uids = uids.iter().copied().peekable();
+    while let Some(start) = uids.next() {
+        let mut end = start;
+        while uids.peek() == Some(&(end + 1)) {
+            end = uids.next().unwrap();
+        }
+        if !set.is_empty() {
+            set.push(',');
+        }
+        if start == end {
+            set.push_str(&start.to_string());
+        } else {
+            set.push_str(&format!("{start}:{end}"));
+        }
+    }
+    set
+}
+
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for ch in value.chars() {
+        if ch == '"' || ch == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(ch);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn protocol_error(response: &[u8]) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        format!(
+            "IMAP server replied: {}",
+            String::from_utf8_lossy(response).trim_end()
+        ),
+    )
+}
+
+// Ported from https://github.com/jstedfast/MailKit/blob/master/MailKit/Net/Imap/ImapEncoding.cs
+// Author: Jeffrey Stedfast <jestedfa@microsoft.com>
+
+static UTF_7_RANK: &[u8] = &[
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 62, 63, 255, 255, 255, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 255,
+    255, 255, 255, 255, 255, 255, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18,
+    19, 20, 21, 22, 23, 24, 25, 255, 255, 255, 255, 255, 255, 26, 27, 28, 29, 30, 31, 32, 33, 34,
+    35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 255, 255, 255, 255, 255,
+];
+
+pub fn utf7_decode(text: &[u8]) -> Option<String> {
+    let mut bytes: Vec<u16> = Vec::with_capacity(text.len());
+    let mut bits = 0;
+    let mut v: u32 = 0;
+    let mut shifted = false;
+    let mut text = text.iter().peekable();
+
+    while let Some(&ch) = text.next() {
+        if shifted {
+            if ch == b'-' {
+                shifted = false;
+                bits = 0;
+                v = 0;
+            } else if ch > 127 {
+                return None;
+            } else {
+                let rank = *UTF_7_RANK.get(ch as usize)?;
+
+                if rank == 0xff {
+                    return None;
+                }
+
+                v = (v << 6) | rank as u32;
+                bits += 6;
+
+                if bits >= 16 {
+                    bytes.push(((v >> (bits - 16)) & 0xffff) as u16);
+                    bits -= 16;
+                }
+            }
+        } else if ch == b'&' {
+            match text.peek() {
+                Some(b'-') => {
+                    bytes.push(b'&' as u16);
+                    text.next();
+                }
+                Some(_) => {
+                    shifted = true;
+                }
+                None => {
+                    bytes.push(ch as u16);
+                }
+            }
+        } else {
+            bytes.push(ch as u16);
+        }
+    }
+
+    String::from_utf16(&bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    use super::{sequence_set, tokenize, Checkpoint, ImapClient, Token};
+
+    #[test]
+    fn tokenize_responses() {
+        assert_eq!(
+            tokenize(b"LIST (\\HasNoChildren \\Sent) \"/\" \"Sent &AMk-l&AOk-ments\"\r\n"),
+            vec![
+                Token::Atom("LIST".to_string()),
+                Token::List(vec![
+                    Token::Atom("\\HasNoChildren".to_string()),
+                    Token::Atom("\\Sent".to_string())
+                ]),
+                Token::String(b"/".to_vec()),
+                Token::String(b"Sent &AMk-l&AOk-ments".to_vec()),
+            ]
+        );
+        assert_eq!(
+            tokenize(b"OK [PERMANENTFLAGS (\\Seen \\*)] Limited\r\n"),
+            vec![
+                Token::Atom("OK".to_string()),
+                Token::Atom("[PERMANENTFLAGS (\\Seen \\*)]".to_string()),
+                Token::Atom("Limited".to_string()),
+            ]
+        );
+        assert_eq!(
+            tokenize(b"1 FETCH (UID 7 BODY[] {5}\r\n(a b)\r\n FLAGS NIL)\r\n"),
+            vec![
+                Token::Atom("1".to_string()),
+                Token::Atom("FETCH".to_string()),
+                Token::List(vec![
+                    Token::Atom("UID".to_string()),
+                    Token::Atom("7".to_string()),
+                    Token::Atom("BODY[]".to_string()),
+                    Token::String(b"(a b)".to_vec()),
+                    Token::Atom("FLAGS".to_string()),
+                    Token::Nil,
+                ]),
+            ]
+        );
+        assert_eq!(sequence_set(&[1, 2, 3, 5, 7, 8]), "1:3,5,7:8");
+    }
+
+    #[test]
+    fn checkpoint_resume() {
+        let mut checkpoint = Checkpoint::default();
+        assert_eq!(checkpoint.search_set("INBOX", 100), "1:*");
+        checkpoint.update("INBOX", &[1, 2, 4], &[3]);
+        assert_eq!(checkpoint.search_set("INBOX", 100), "3,5:*");
+
+        // Servers always return the last message when searching for n:*
+        assert_eq!(checkpoint.pending("INBOX", &[3, 4]), vec![3]);
+        checkpoint.update("INBOX", &[3], &[]);
+        assert_eq!(checkpoint.search_set("INBOX", 100), "5:*");
+
+        // A new UIDVALIDITY restarts the import
+        assert_eq!(checkpoint.search_set("INBOX", 200), "1:*");
+        assert_eq!(checkpoint.pending("INBOX", &[1, 2]), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn imap_client() {
+        // Start a minimal IMAP server stand-in
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            writer.write_all(b"* OK IMAP4rev1 ready\r\n").await.unwrap();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let (tag, command) = line.split_once(' ').unwrap();
+                let response = match command {
+                    "LOGIN \"john\" \"sec\\\"ret\"" => String::new(),
+                    "LIST \"\" \"*\"" => concat!(
+                        "* LIST (\\HasChildren) \".\" INBOX\r\n",
+                        "* LIST (\\HasNoChildren) \".\" \"INBOX.Sub\"\r\n",
+                        "* LIST (\\HasNoChildren \\Sent) \".\" {21}\r\nSent &AMk-l&AOk-ments\r\n",
+                    )
+                    .to_string(),
+                    "EXAMINE \"INBOX\"" => concat!(
+                        "* 2 EXISTS\r\n",
+                        "* OK [UIDVALIDITY 1234] UIDs valid\r\n",
+                        "* OK [UIDNEXT 11] Predicted next UID\r\n",
+                    )
+                    .to_string(),
+                    "UID SEARCH UID 1:*" => "* SEARCH 10 4\r\n".to_string(),
+                    "UID FETCH 4,10 (UID FLAGS INTERNALDATE BODY.PEEK[])" => concat!(
+                        "* 1 FETCH (UID 4 FLAGS (\\Seen $Forwarded) ",
+                        "INTERNALDATE \"17-Jul-1996 02:44:25 -0700\" BODY[] {15}\r\n",
+                        "Subject: a\r\n\r\nb)\r\n",
+                        "* 2 FETCH (FLAGS () UID 10 BODY[] \"Subject: c\")\r\n",
+                    )
+                    .to_string(),
+                    "LOGOUT" => "* BYE\r\n".to_string(),
+                    _ => {
+                        writer
+                            .write_all(format!("{tag} BAD Unexpected command\r\n").as_bytes())
+                            .await
+                            .unwrap();
+                        continue;
+                    }
+                };
+                writer
+                    .write_all(format!("{response}{tag} OK Done\r\n").as_bytes())
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let mut client = ImapClient::connect("127.0.0.1", port, false).await.unwrap();
+        client.login("john", "sec\"ret").await.unwrap();
+
+        let folders = client.list().await.unwrap();
+        assert_eq!(folders.len(), 3);
+        assert_eq!(folders[0].name, "INBOX");
+        assert_eq!(folders[1].path, vec!["INBOX", "Sub"]);
+        assert_eq!(folders[2].name, "Sent &AMk-l&AOk-ments");
+        assert_eq!(folders[2].path, vec!["Sent Éléments"]);
+        assert!(folders[2].attributes.contains(&"\\Sent".to_string()));
+
+        assert_eq!(client.examine("INBOX").await.unwrap(), 1234);
+        let uids = client.uid_search("1:*").await.unwrap();
+        assert_eq!(uids, vec![4, 10]);
+
+        let messages = client.uid_fetch(&uids).await.unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].uid, 4);
+        assert_eq!(messages[0].flags, vec!["\\Seen", "$Forwarded"]);
+        assert_eq!(messages[0].internal_date, 837596665);
+        assert_eq!(messages[0].contents, b"Subject: a\r\n\r\nb");
+        assert_eq!(messages[1].uid, 10);
+        assert!(messages[1].flags.is_empty());
+        assert_eq!(messages[1].contents, b"Subject: c");
+
+        assert!(client.examine("Unknown").await.is_err());
+        client.logout().await.unwrap();
+    }
+}
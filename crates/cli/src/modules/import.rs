@@ -54,9 +54,12 @@ use super::{
         fetch_emails, fetch_identities, fetch_mailboxes, fetch_sieve_scripts,
         fetch_vacation_responses,
     },
+    imap::{Checkpoint, Folder, ImapClient},
     read_file,
 };
 
+const IMAP_FETCH_BATCH: usize = 50;
+
 enum Mailbox {
     Mbox(mbox::MessageIterator<Cursor<Vec<u8>>>),
     Maildir(maildir::MessageIterator),
@@ -431,6 +434,326 @@ pub async fn cmd_import(mut client: Client, command: ImportCommands) {
             import_identities(&client, &path).await;
             import_vacation_responses(&client, &path).await;
         }
+
+        ImportCommands::Imap {
+            num_concurrent,
+            port,
+            no_tls,
+            password,
+            checkpoint,
+            account,
+            host,
+            username,
+        } => {
+            client.set_default_account_id(name_to_id(&client, &account).await);
+            let password = password.unwrap_or_else(|| {
+                rpassword::prompt_password(format!("\nEnter password for {username}@{host}: "))
+                    .unwrap()
+            });
+            let checkpoint_path = PathBuf::from(checkpoint.unwrap_or_else(|| {
+                format!("imap-{username}@{host}.json").replace(['/', '\\'], "_")
+            }));
+            let mut checkpoint =
+                Checkpoint::load(&checkpoint_path).unwrap_result("read checkpoint file");
+
+            // Fetch folders from the IMAP server
+            eprintln!(
+                "{} Fetching folders from IMAP server...",
+                style("[1/3]").bold().dim(),
+            );
+            let mut imap = ImapClient::connect(
+                &host,
+                port.unwrap_or(if no_tls { 143 } else { 993 }),
+                !no_tls,
+            )
+            .await
+            .unwrap_result("connect to IMAP server");
+            imap.login(&username, &password)
+                .await
+                .unwrap_result("authenticate to IMAP server");
+            let mut folders = imap.list().await.unwrap_result("list IMAP folders");
+
+            // Add any parent folders not returned by LIST, making sure parents come first
+            for pos in 0..folders.len() {
+                for len in 1..folders[pos].path.len() {
+                    if !folders
+                        .iter()
+                        .any(|folder| folder.path[..] == folders[pos].path[..len])
+                    {
+                        folders.push(Folder {
+                            name: String::new(),
+                            path: folders[pos].path[..len].to_vec(),
+                            attributes: vec!["\\Noselect".to_string()],
+                        });
+                    }
+                }
+            }
+            folders.sort_by_key(|folder| folder.path.len());
+
+            // Create missing mailboxes
+            eprintln!(
+                "{} Creating missing mailboxes...",
+                style("[2/3]").bold().dim(),
+            );
+            let mailbox_ids = import_imap_mailboxes(&client, &folders).await;
+
+            // Import messages
+            eprintln!("{} Importing messages...", style("[3/3]").bold().dim(),);
+            let client = Arc::new(client);
+            let num_concurrent = num_concurrent.unwrap_or_else(num_cpus::get);
+            let progress_style = ProgressStyle::with_template(
+                "{prefix:.bold.dim} [{bar:40.cyan/blue}] {pos}/{len} {wide_msg}",
+            )
+            .unwrap()
+            .progress_chars("##-");
+            let mut total_imported = 0;
+            let mut failures = Vec::new();
+
+            for (folder, mailbox_id) in folders.iter().zip(mailbox_ids) {
+                if folder.attributes.iter().any(|attribute| {
+                    attribute.eq_ignore_ascii_case("\\Noselect")
+                        || attribute.eq_ignore_ascii_case("\\NonExistent")
+                }) {
+                    continue;
+                }
+
+                // Obtain the messages pending import
+                let uid_validity = imap
+                    .examine(&folder.name)
+                    .await
+                    .unwrap_result("select IMAP folder");
+                let search_set = checkpoint.search_set(&folder.name, uid_validity);
+                let uids = checkpoint.pending(
+                    &folder.name,
+                    &imap
+                        .uid_search(&search_set)
+                        .await
+                        .unwrap_result("search IMAP folder"),
+                );
+                if uids.is_empty() {
+                    continue;
+                }
+                let folder_name = folder.path.join("/");
+                let pb = ProgressBar::new(uids.len() as u64);
+                pb.set_style(progress_style.clone());
+                pb.set_message(folder_name.clone());
+                let mailbox_id = Arc::new(mailbox_id);
+
+                for batch in uids.chunks(IMAP_FETCH_BATCH) {
+                    let mut futures = FuturesUnordered::new();
+                    let mut results = Vec::with_capacity(batch.len());
+
+                    for message in imap
+                        .uid_fetch(batch)
+                        .await
+                        .unwrap_result("fetch IMAP messages")
+                    {
+                        let client = client.clone();
+                        let mailbox_id = mailbox_id.clone();
+                        let keywords = message
+                            .flags
+                            .iter()
+                            .filter_map(|flag| imap_keyword(flag))
+                            .collect::<Vec<_>>();
+
+                        futures.push(async move {
+                            (
+                                message.uid,
+                                client
+                                    .email_import(
+                                        message.contents,
+                                        [mailbox_id.as_str()],
+                                        if !keywords.is_empty() {
+                                            Some(keywords)
+                                        } else {
+                                            None
+                                        },
+                                        if message.internal_date > 0 {
+                                            message.internal_date.into()
+                                        } else {
+                                            None
+                                        },
+                                    )
+                                    .await,
+                            )
+                        });
+
+                        if futures.len() == num_concurrent {
+                            results.push(futures.next().await.unwrap());
+                        }
+                    }
+
+                    // Wait for remaining futures
+                    while let Some(result) = futures.next().await {
+                        results.push(result);
+                    }
+
+                    // Messages expunged since the search are considered imported
+                    let mut failed = Vec::new();
+                    for (uid, result) in results {
+                        if let Err(err) = result {
+                            failures.push(format!(
+                                "Failed to import message with UID {uid} from folder '{folder_name}': {err}"
+                            ));
+                            failed.push(uid);
+                        } else {
+                            total_imported += 1;
+                        }
+                    }
+                    let imported = batch
+                        .iter()
+                        .copied()
+                        .filter(|uid| !failed.contains(uid))
+                        .collect::<Vec<_>>();
+
+                    // Save progress
+                    checkpoint.update(&folder.name, &imported, &failed);
+                    checkpoint
+                        .save(&checkpoint_path)
+                        .unwrap_result("write checkpoint file");
+                    pb.inc(batch.len() as u64);
+                }
+
+                pb.finish_with_message(format!("{folder_name}: Done"));
+            }
+            let _ = imap.logout().await;
+
+            // Done
+            eprintln!("\n\nSuccessfully imported {} messages.\n", total_imported);
+
+            if !failures.is_empty() {
+                eprintln!(
+                    "There were {} failures, run the import again to retry them:\n",
+                    failures.len()
+                );
+                for failure in failures.iter() {
+                    eprintln!("{}", failure);
+                }
+            }
+        }
+    }
+}
+
+async fn import_imap_mailboxes(client: &Client, folders: &[Folder]) -> Vec<String> {
+    // Obtain current mailboxes
+    let existing_mailboxes = fetch_mailboxes(
+        client,
+        client
+            .session()
+            .core_capabilities()
+            .map(|c| c.max_objects_in_get())
+            .unwrap_or(500),
+    )
+    .await;
+    let mut mailbox_ids: Vec<MailboxId> = Vec::with_capacity(folders.len());
+    let mut has_missing_mailboxes = false;
+    let mut request = client.build();
+    let set_request = request.set_mailbox();
+
+    for folder in folders {
+        let role = if folder.name.eq_ignore_ascii_case("INBOX") {
+            Role::Inbox
+        } else {
+            folder
+                .attributes
+                .iter()
+                .find_map(|attribute| match attribute.to_ascii_lowercase().as_str() {
+                    "\\sent" => Some(Role::Sent),
+                    "\\drafts" => Some(Role::Drafts),
+                    "\\trash" => Some(Role::Trash),
+                    "\\junk" => Some(Role::Junk),
+                    "\\archive" => Some(Role::Archive),
+                    _ => None,
+                })
+                .unwrap_or(Role::None)
+        };
+        let name = folder.path.last().map_or("Untitled", |name| name.as_str());
+        let parent_id = if folder.path.len() > 1 {
+            folders
+                .iter()
+                .position(|parent| parent.path[..] == folder.path[..folder.path.len() - 1])
+                .map(|pos| &mailbox_ids[pos])
+        } else {
+            None
+        };
+
+        // Find existing mailbox based on role or by name
+        let existing_mailbox = if !matches!(role, Role::None) {
+            existing_mailboxes.iter().find(|m| m.role() == role)
+        } else {
+            None
+        }
+        .or_else(|| match parent_id {
+            None => existing_mailboxes
+                .iter()
+                .find(|m| m.parent_id().is_none() && m.name() == Some(name)),
+            Some(MailboxId::ExistingId(parent_id)) => existing_mailboxes
+                .iter()
+                .find(|m| m.parent_id() == Some(*parent_id) && m.name() == Some(name)),
+            Some(_) => None,
+        });
+
+        if let Some(existing_mailbox) = existing_mailbox {
+            mailbox_ids.push(MailboxId::ExistingId(
+                existing_mailbox.id().unwrap_result("obtain mailbox id"),
+            ));
+        } else {
+            let create_request = set_request.create().name(name);
+            match parent_id {
+                Some(MailboxId::ExistingId(id)) => {
+                    create_request.parent_id((*id).into());
+                }
+                Some(MailboxId::CreateId(id_ref)) => {
+                    create_request.parent_id_ref(id_ref);
+                }
+                _ => {
+                    create_request.parent_id(None::<String>);
+                }
+            }
+            if !matches!(role, Role::None) {
+                create_request.role(role);
+            }
+            mailbox_ids.push(MailboxId::CreateId(create_request.create_id().unwrap()));
+            has_missing_mailboxes = true;
+        }
+    }
+
+    // Create mailboxes
+    if has_missing_mailboxes {
+        let mut response = request
+            .send_set_mailbox()
+            .await
+            .unwrap_result("create mailboxes");
+        for mailbox_id in mailbox_ids.iter_mut() {
+            if let MailboxId::CreateId(id) = mailbox_id {
+                *id = response
+                    .created(id)
+                    .unwrap_result("create mailbox")
+                    .take_id();
+            }
+        }
+    }
+
+    mailbox_ids
+        .into_iter()
+        .map(|mailbox_id| match mailbox_id {
+            MailboxId::ExistingId(id) => id.to_string(),
+            MailboxId::CreateId(id) => id,
+            MailboxId::None => unreachable!(),
+        })
+        .collect()
+}
+
+fn imap_keyword(flag: &str) -> Option<String> {
+    let flag = flag.to_ascii_lowercase();
+    match flag.as_str() {
+        "\\seen" => Some("$seen".to_string()),
+        "\\answered" => Some("$answered".to_string()),
+        "\\flagged" => Some("$flagged".to_string()),
+        "\\draft" => Some("$draft".to_string()),
+        "\\deleted" => Some("$deleted".to_string()),
+        _ if flag.starts_with('\\') => None,
+        _ => Some(flag),
     }
 }
 
@@ -917,7 +1240,7 @@ async fn import_vacation_responses(client: &Client, path: &Path) {
     }
 }
 
-fn build_mailbox_tree(
+pub fn build_mailbox_tree(
     mailboxes: &[jmap_client::mailbox::Mailbox],
 ) -> HashMap<Vec<&str>, &jmap_client::mailbox::Mailbox> {
     let mut path = Vec::new();
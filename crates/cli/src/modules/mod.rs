@@ -33,8 +33,11 @@ use jmap_client::{
 
 pub mod cli;
 pub mod database;
+pub mod directory;
 pub mod export;
+pub mod imap;
 pub mod import;
+pub mod quarantine;
 pub mod queue;
 pub mod report;
 
@@ -0,0 +1,211 @@
+/*
+ * Copyright (c) 2020-2023, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use super::cli::QuarantineCommands;
+use crate::modules::queue::{deserialize_datetime, smtp_manage_request};
+use console::Term;
+use human_size::{Byte, SpecificSize};
+use jmap_client::client::Credentials;
+use mail_parser::DateTime;
+use prettytable::{format::Alignment, Attr, Cell, Row, Table};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct QuarantinedMessage {
+    pub id: u64,
+    pub return_path: String,
+    pub recipients: Vec<String>,
+    #[serde(deserialize_with = "deserialize_datetime")]
+    pub created: DateTime,
+    pub size: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuarantinePreview {
+    #[serde(flatten)]
+    pub message: QuarantinedMessage,
+    pub headers: String,
+    pub preview: String,
+}
+
+pub async fn cmd_quarantine(url: &str, credentials: Credentials, command: QuarantineCommands) {
+    match command {
+        QuarantineCommands::List { page_size } => {
+            let stdout = Term::buffered_stdout();
+            let messages = smtp_manage_request::<Vec<QuarantinedMessage>>(
+                &format!("{url}/admin/quarantine/list"),
+                &credentials,
+            )
+            .await;
+            let messages_len = messages.len();
+            let page_size = page_size.map(|p| std::cmp::max(p, 1)).unwrap_or(20);
+            let pages_total = (messages_len as f64 / page_size as f64).ceil() as usize;
+            for (page_num, chunk) in messages.chunks(page_size).enumerate() {
+                // Build table
+                let mut table = Table::new();
+                table.add_row(Row::new(
+                    ["ID", "Date", "Return Path", "Recipients", "Reason", "Size"]
+                        .iter()
+                        .map(|p| Cell::new(p).with_style(Attr::Bold))
+                        .collect(),
+                ));
+                for message in chunk {
+                    table.add_row(Row::new(vec![
+                        Cell::new(&message.id.to_string()),
+                        Cell::new(&message.created.to_rfc822()),
+                        Cell::new(message.return_path()),
+                        Cell::new(&message.recipients.join("\n")),
+                        Cell::new(&message.reason),
+                        Cell::new(
+                            &SpecificSize::new(message.size as u32, Byte)
+                                .unwrap()
+                                .to_string(),
+                        ),
+                    ]));
+                }
+
+                eprintln!();
+                table.printstd();
+                eprintln!();
+                if page_num + 1 != pages_total {
+                    eprintln!("\n--- Press any key to continue or 'q' to exit ---");
+                    if let Ok('q' | 'Q') = stdout.read_char() {
+                        break;
+                    }
+                }
+            }
+            eprintln!("\n{messages_len} quarantined message(s) found.")
+        }
+        QuarantineCommands::Preview { ids } => {
+            for (preview, id) in smtp_manage_request::<Vec<Option<QuarantinePreview>>>(
+                &format!("{url}/admin/quarantine/preview?ids={}", ids.join(",")),
+                &credentials,
+            )
+            .await
+            .into_iter()
+            .zip(&ids)
+            {
+                let mut table = Table::new();
+                table.add_row(Row::new(vec![
+                    Cell::new("ID").with_style(Attr::Bold),
+                    Cell::new(id),
+                ]));
+                if let Some(preview) = preview {
+                    let message = &preview.message;
+                    table.add_row(Row::new(vec![
+                        Cell::new("Date").with_style(Attr::Bold),
+                        Cell::new(&message.created.to_rfc822()),
+                    ]));
+                    table.add_row(Row::new(vec![
+                        Cell::new("Return Path").with_style(Attr::Bold),
+                        Cell::new(message.return_path()),
+                    ]));
+                    table.add_row(Row::new(vec![
+                        Cell::new("Recipients").with_style(Attr::Bold),
+                        Cell::new(&message.recipients.join("\n")),
+                    ]));
+                    table.add_row(Row::new(vec![
+                        Cell::new("Reason").with_style(Attr::Bold),
+                        Cell::new(&message.reason),
+                    ]));
+                    table.add_row(Row::new(vec![
+                        Cell::new("Size").with_style(Attr::Bold),
+                        Cell::new(
+                            &SpecificSize::new(message.size as u32, Byte)
+                                .unwrap()
+                                .to_string(),
+                        ),
+                    ]));
+                    table.add_row(Row::new(vec![
+                        Cell::new("Headers").with_style(Attr::Bold),
+                        Cell::new(preview.headers.trim_end()),
+                    ]));
+                    table.add_row(Row::new(vec![
+                        Cell::new("Preview").with_style(Attr::Bold),
+                        Cell::new(preview.preview.trim_end()),
+                    ]));
+                } else {
+                    table.add_row(Row::new(vec![Cell::new_align(
+                        "-- Not found --",
+                        Alignment::CENTER,
+                    )
+                    .with_hspan(2)]));
+                }
+
+                eprintln!();
+                table.printstd();
+                eprintln!();
+            }
+        }
+        QuarantineCommands::Release { ids } => {
+            manage_messages(url, &credentials, "release", ids, "Released").await;
+        }
+        QuarantineCommands::Delete { ids } => {
+            manage_messages(url, &credentials, "delete", ids, "Deleted").await;
+        }
+    }
+}
+
+async fn manage_messages(
+    url: &str,
+    credentials: &Credentials,
+    action: &str,
+    ids: Vec<String>,
+    success_msg: &str,
+) {
+    let mut success_count = 0;
+    let mut failed_list = vec![];
+    for (success, id) in smtp_manage_request::<Vec<bool>>(
+        &format!("{url}/admin/quarantine/{action}?ids={}", ids.join(",")),
+        credentials,
+    )
+    .await
+    .into_iter()
+    .zip(ids)
+    {
+        if success {
+            success_count += 1;
+        } else {
+            failed_list.push(id);
+        }
+    }
+    eprint!("\n{success_msg} {success_count} message(s).");
+    if !failed_list.is_empty() {
+        eprint!(
+            " Unable to {action} message id(s): {}.",
+            failed_list.join(", ")
+        );
+    }
+    eprintln!();
+}
+
+impl QuarantinedMessage {
+    fn return_path(&self) -> &str {
+        if !self.return_path.is_empty() {
+            &self.return_path
+        } else {
+            "<>"
+        }
+    }
+}
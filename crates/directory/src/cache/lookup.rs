@@ -23,7 +23,7 @@
 
 use mail_send::Credentials;
 
-use crate::{Directory, Principal};
+use crate::{AuthResult, Directory, Principal};
 
 use super::CachedDirectory;
 
@@ -36,6 +36,13 @@ impl<T: Directory> Directory for CachedDirectory<T> {
         self.inner.authenticate(credentials).await
     }
 
+    async fn try_authenticate(
+        &self,
+        credentials: &Credentials<String>,
+    ) -> crate::Result<AuthResult> {
+        self.inner.try_authenticate(credentials).await
+    }
+
     async fn principal(&self, name: &str) -> crate::Result<Option<Principal>> {
         self.inner.principal(name).await
     }
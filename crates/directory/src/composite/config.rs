@@ -0,0 +1,76 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use utils::config::{utils::AsKey, Config};
+
+use crate::Directory;
+
+use super::CompositeDirectory;
+
+impl CompositeDirectory {
+    pub fn from_config(
+        config: &Config,
+        prefix: impl AsKey,
+        directories: &AHashMap<String, Arc<dyn Directory>>,
+    ) -> utils::config::Result<Arc<dyn Directory>> {
+        let prefix = prefix.as_key();
+        let get_directory = |id: &str| {
+            directories.get(id).cloned().ok_or_else(|| {
+                format!(
+                    "Directory {id:?} referenced by {prefix:?} does not exist or is a composite directory."
+                )
+            })
+        };
+
+        let mut composite = CompositeDirectory {
+            directories: Vec::new(),
+            routes: AHashMap::new(),
+        };
+        for (_, id) in config.values((&prefix, "directories")) {
+            composite.directories.push(get_directory(id)?);
+        }
+        for id in config.sub_keys((prefix.as_str(), "routing")) {
+            let directory = get_directory(id)?;
+            for (_, domain) in config.values((prefix.as_str(), "routing", id)) {
+                if composite
+                    .routes
+                    .insert(domain.to_lowercase(), directory.clone())
+                    .is_some()
+                {
+                    return Err(format!(
+                        "Domain {domain:?} is routed to multiple directories in {prefix:?}."
+                    ));
+                }
+            }
+        }
+
+        if !composite.directories.is_empty() || !composite.routes.is_empty() {
+            Ok(Arc::new(composite))
+        } else {
+            Err(format!("No directories defined for {prefix:?}."))
+        }
+    }
+}
@@ -0,0 +1,205 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use mail_send::Credentials;
+
+use crate::{AuthResult, Directory, DirectoryError, Principal};
+
+use super::CompositeDirectory;
+
+#[async_trait::async_trait]
+impl Directory for CompositeDirectory {
+    async fn authenticate(
+        &self,
+        credentials: &Credentials<String>,
+    ) -> crate::Result<Option<Principal>> {
+        self.try_authenticate(credentials)
+            .await
+            .map(AuthResult::into_principal)
+    }
+
+    async fn try_authenticate(
+        &self,
+        credentials: &Credentials<String>,
+    ) -> crate::Result<AuthResult> {
+        let username = match credentials {
+            Credentials::Plain { username, .. } | Credentials::XOauth2 { username, .. } => username,
+            Credentials::OAuthBearer { token } => token,
+        };
+        for directory in self.candidates(username) {
+            // The first directory that knows the account has the final say
+            match directory.try_authenticate(credentials).await? {
+                AuthResult::NotFound => (),
+                result => return Ok(result),
+            }
+        }
+        Ok(AuthResult::NotFound)
+    }
+
+    async fn principal(&self, name: &str) -> crate::Result<Option<Principal>> {
+        let mut result = Ok(None);
+        for directory in self.candidates(name) {
+            match directory.principal(name).await {
+                Ok(Some(principal)) => return Ok(Some(principal)),
+                Ok(None) => (),
+                Err(err) => {
+                    result = Err(err);
+                }
+            }
+        }
+        result
+    }
+
+    async fn emails_by_name(&self, name: &str) -> crate::Result<Vec<String>> {
+        let mut result = Merged::default();
+        for directory in &self.directories {
+            result.add(directory.emails_by_name(name).await);
+        }
+        result.finish()
+    }
+
+    async fn names_by_email(&self, address: &str) -> crate::Result<Vec<String>> {
+        let mut result = Ok(vec![]);
+        for directory in self.candidates(address) {
+            match directory.names_by_email(address).await {
+                Ok(names) if !names.is_empty() => return Ok(names),
+                Ok(_) => (),
+                Err(err) => {
+                    result = Err(err);
+                }
+            }
+        }
+        result
+    }
+
+    async fn rcpt(&self, address: &str) -> crate::Result<bool> {
+        let mut result = Ok(false);
+        for directory in self.candidates(address) {
+            match directory.rcpt(address).await {
+                Ok(true) => return Ok(true),
+                Ok(false) => (),
+                Err(err) => {
+                    result = Err(err);
+                }
+            }
+        }
+        result
+    }
+
+    async fn vrfy(&self, address: &str) -> crate::Result<Vec<String>> {
+        let mut result = Merged::default();
+        for directory in self.candidates(address) {
+            result.add(directory.vrfy(address).await);
+        }
+        result.finish()
+    }
+
+    async fn expn(&self, address: &str) -> crate::Result<Vec<String>> {
+        let mut result = Merged::default();
+        for directory in self.candidates(address) {
+            result.add(directory.expn(address).await);
+        }
+        result.finish()
+    }
+
+    async fn query(&self, query: &str, params: &[&str]) -> crate::Result<bool> {
+        let mut result = Err(DirectoryError::unsupported("composite", "query"));
+        for directory in &self.directories {
+            match directory.query(query, params).await {
+                Ok(true) => return Ok(true),
+                Ok(false) => {
+                    result = Ok(false);
+                }
+                Err(err) => {
+                    if result.is_err() {
+                        result = Err(err);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    async fn is_local_domain(&self, domain: &str) -> crate::Result<bool> {
+        if self.routes.contains_key(&domain.to_lowercase()) {
+            return Ok(true);
+        }
+
+        let mut result = Ok(false);
+        for directory in &self.directories {
+            match directory.is_local_domain(domain).await {
+                Ok(true) => return Ok(true),
+                Ok(false) => (),
+                Err(err) => {
+                    result = Err(err);
+                }
+            }
+        }
+        result
+    }
+}
+
+impl CompositeDirectory {
+    fn candidates(&self, address: &str) -> &[Arc<dyn Directory>] {
+        address
+            .rsplit_once('@')
+            .and_then(|(_, domain)| self.routes.get(&domain.to_lowercase()))
+            .map(std::slice::from_ref)
+            .unwrap_or(&self.directories)
+    }
+}
+
+#[derive(Default)]
+struct Merged {
+    items: Vec<String>,
+    error: Option<DirectoryError>,
+    has_results: bool,
+}
+
+impl Merged {
+    fn add(&mut self, result: crate::Result<Vec<String>>) {
+        match result {
+            Ok(items) => {
+                for item in items {
+                    if !self.items.contains(&item) {
+                        self.items.push(item);
+                    }
+                }
+                self.has_results = true;
+            }
+            Err(err) => {
+                self.error = Some(err);
+            }
+        }
+    }
+
+    // Errors are only reported when no directory returned a result
+    fn finish(self) -> crate::Result<Vec<String>> {
+        match self.error {
+            Some(err) if !self.has_results => Err(err),
+            _ => Ok(self.items),
+        }
+    }
+}
@@ -33,8 +33,9 @@ use utils::config::{utils::AsKey, Config};
 use ahash::{AHashMap, AHashSet};
 
 use crate::{
-    imap::ImapDirectory, ldap::LdapDirectory, memory::MemoryDirectory, smtp::SmtpDirectory,
-    sql::SqlDirectory, DirectoryConfig, DirectoryOptions, Lookup,
+    composite::CompositeDirectory, imap::ImapDirectory, internal::InternalDirectory,
+    ldap::LdapDirectory, memory::MemoryDirectory, smtp::SmtpDirectory, sql::SqlDirectory,
+    Directory, DirectoryConfig, DirectoryOptions, Lookup,
 };
 
 pub trait ConfigDirectory {
@@ -47,7 +48,9 @@ impl ConfigDirectory for Config {
         let mut config = DirectoryConfig {
             directories: AHashMap::new(),
             lookups: AHashMap::new(),
+            internal: None,
         };
+        let mut composites = Vec::new();
         for id in self.sub_keys("directory") {
             // Parse directory
             let protocol = self.value_require(("directory", id, "type"))?;
@@ -59,6 +62,19 @@ impl ConfigDirectory for Config {
                 "smtp" => SmtpDirectory::from_config(self, prefix, false)?,
                 "lmtp" => SmtpDirectory::from_config(self, prefix, true)?,
                 "memory" => MemoryDirectory::from_config(self, prefix)?,
+                "internal" => {
+                    if config.internal.is_some() {
+                        return Err("Only one internal directory can be defined".to_string());
+                    }
+                    let directory = InternalDirectory::from_config(self, prefix)?;
+                    config.internal = directory.clone().into();
+                    directory as Arc<dyn Directory>
+                }
+                "composite" => {
+                    // Composite directories are built once all other directories are available
+                    composites.push(id);
+                    continue;
+                }
                 unknown => {
                     return Err(format!("Unknown directory type: {unknown:?}"));
                 }
@@ -103,6 +119,21 @@ impl ConfigDirectory for Config {
             config.directories.insert(id.to_string(), directory);
         }
 
+        // Parse composite directories
+        let directories = config.directories.clone();
+        for id in composites {
+            let directory = CompositeDirectory::from_config(self, ("directory", id), &directories)?;
+            for lookup_id in self.sub_keys(("directory", id, "lookup")) {
+                config.lookups.insert(
+                    format!("{id}/{lookup_id}"),
+                    Arc::new(Lookup::List {
+                        list: self.parse_lookup_list(("directory", id, "lookup", lookup_id))?,
+                    }),
+                );
+            }
+            config.directories.insert(id.to_string(), directory);
+        }
+
         Ok(config)
     }
 
@@ -0,0 +1,233 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use mail_send::Credentials;
+use store::CustomValueKey;
+
+use crate::{
+    to_catch_all_address, unwrap_subaddress, AuthResult, Directory, DirectoryError, Principal, Type,
+};
+
+use super::{DirectoryKey, InternalDirectory, PrincipalValue, KEY_EMAIL};
+
+#[async_trait::async_trait]
+impl Directory for InternalDirectory {
+    async fn authenticate(
+        &self,
+        credentials: &Credentials<String>,
+    ) -> crate::Result<Option<Principal>> {
+        self.try_authenticate(credentials)
+            .await
+            .map(AuthResult::into_principal)
+    }
+
+    async fn try_authenticate(
+        &self,
+        credentials: &Credentials<String>,
+    ) -> crate::Result<AuthResult> {
+        let (username, secret) = match credentials {
+            Credentials::Plain { username, secret } => (username, secret),
+            Credentials::OAuthBearer { token } => (token, token),
+            Credentials::XOauth2 { username, secret } => (username, secret),
+        };
+        Ok(match self.principal(username).await? {
+            Some(principal) if principal.verify_secret(secret).await => {
+                AuthResult::Success(principal)
+            }
+            Some(_) => AuthResult::Failure,
+            None => AuthResult::NotFound,
+        })
+    }
+
+    async fn principal(&self, name: &str) -> crate::Result<Option<Principal>> {
+        Ok(self
+            .principal_value(name)
+            .await?
+            .map(|value| value.into_principal(&name.to_lowercase(), &self.opt)))
+    }
+
+    async fn emails_by_name(&self, name: &str) -> crate::Result<Vec<String>> {
+        Ok(self
+            .principal_value(name)
+            .await?
+            .map(|value| value.emails)
+            .unwrap_or_default())
+    }
+
+    async fn names_by_email(&self, address: &str) -> crate::Result<Vec<String>> {
+        if let Some(name) = self.address_owner(address).await? {
+            // Addresses owned by a group are delivered to its members
+            if matches!(
+                self.principal_value(&name).await?,
+                Some(PrincipalValue {
+                    typ: Type::Group,
+                    ..
+                })
+            ) {
+                self.group_members(&name).await
+            } else {
+                Ok(vec![name])
+            }
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    async fn rcpt(&self, address: &str) -> crate::Result<bool> {
+        self.address_owner(address)
+            .await
+            .map(|owner| owner.is_some())
+    }
+
+    async fn vrfy(&self, address: &str) -> crate::Result<Vec<String>> {
+        let address = unwrap_subaddress(address, self.opt.subaddressing).to_lowercase();
+        let prefix = DirectoryKey::prefix(KEY_EMAIL);
+        let mut end = prefix.clone();
+        end.push(u8::MAX);
+
+        self.store()?
+            .iterate(
+                Vec::new(),
+                CustomValueKey {
+                    value: prefix.clone(),
+                },
+                CustomValueKey { value: end },
+                false,
+                true,
+                move |emails, key, _| {
+                    if let Some(email) = key
+                        .get(prefix.len()..)
+                        .and_then(|key| std::str::from_utf8(key).ok())
+                    {
+                        if !email.starts_with('@') && email.contains(address.as_str()) {
+                            emails.push(email.to_string());
+                        }
+                    }
+                    Ok(true)
+                },
+            )
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn expn(&self, address: &str) -> crate::Result<Vec<String>> {
+        let address = unwrap_subaddress(address, self.opt.subaddressing);
+        let mut result = Vec::new();
+        if let Some(name) = self.email_owner(address.as_ref()).await? {
+            if matches!(
+                self.principal_value(&name).await?,
+                Some(PrincipalValue {
+                    typ: Type::Group,
+                    ..
+                })
+            ) {
+                for member in self.group_members(&name).await? {
+                    if let Some(email) = self
+                        .principal_value(&member)
+                        .await?
+                        .and_then(|value| value.emails.into_iter().next())
+                    {
+                        result.push(email);
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    async fn query(&self, _query: &str, _params: &[&str]) -> crate::Result<bool> {
+        Err(DirectoryError::unsupported("internal", "query"))
+    }
+
+    async fn is_local_domain(&self, domain: &str) -> crate::Result<bool> {
+        self.store()?
+            .get_value::<String>(CustomValueKey {
+                value: DirectoryKey::domain(domain),
+            })
+            .await
+            .map(|value| value.is_some())
+            .map_err(Into::into)
+    }
+}
+
+impl InternalDirectory {
+    pub(crate) async fn principal_value(
+        &self,
+        name: &str,
+    ) -> crate::Result<Option<PrincipalValue>> {
+        self.store()?
+            .get_value::<PrincipalValue>(CustomValueKey {
+                value: DirectoryKey::principal(name),
+            })
+            .await
+            .map_err(Into::into)
+    }
+
+    pub(crate) async fn email_owner(&self, address: &str) -> crate::Result<Option<String>> {
+        self.store()?
+            .get_value::<String>(CustomValueKey {
+                value: DirectoryKey::email(address),
+            })
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn address_owner(&self, address: &str) -> crate::Result<Option<String>> {
+        match self
+            .email_owner(unwrap_subaddress(address, self.opt.subaddressing).as_ref())
+            .await?
+        {
+            Some(name) => Ok(Some(name)),
+            None if self.opt.catch_all => self.email_owner(&to_catch_all_address(address)).await,
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) async fn group_members(&self, group: &str) -> crate::Result<Vec<String>> {
+        let prefix = DirectoryKey::members_prefix(group);
+        let mut end = prefix.clone();
+        end.push(u8::MAX);
+
+        self.store()?
+            .iterate(
+                Vec::new(),
+                CustomValueKey {
+                    value: prefix.clone(),
+                },
+                CustomValueKey { value: end },
+                false,
+                true,
+                move |members, key, _| {
+                    if let Some(member) = key
+                        .get(prefix.len()..)
+                        .and_then(|key| std::str::from_utf8(key).ok())
+                    {
+                        members.push(member.to_string());
+                    }
+                    Ok(true)
+                },
+            )
+            .await
+            .map_err(Into::into)
+    }
+}
@@ -0,0 +1,615 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::fmt::Display;
+
+use argon2::{Argon2, PasswordHasher};
+use password_hash::SaltString;
+use store::{
+    write::{assert::HashedValue, BatchBuilder, Operation, ValueClass},
+    CustomValueKey, Deserialize, Serialize,
+};
+
+use crate::{Directory, DirectoryError, Type};
+
+use super::{
+    DirectoryKey, InternalDirectory, PrincipalValue, KEY_DOMAIN, KEY_EMAIL, KEY_PRINCIPAL,
+};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PrincipalData {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub typ: Type,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub quota: u32,
+    #[serde(skip_serializing)]
+    #[serde(default)]
+    pub secrets: Vec<String>,
+    #[serde(default)]
+    pub emails: Vec<String>,
+    #[serde(default)]
+    pub member_of: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PrincipalUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub quota: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub secret: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub add_emails: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub remove_emails: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub add_member_of: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub remove_member_of: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum ManageError {
+    NotFound(String),
+    AlreadyExists(String),
+    Invalid(String),
+    Internal(String),
+}
+
+impl InternalDirectory {
+    pub async fn list_principals(&self, typ: Option<Type>) -> Result<Vec<String>, ManageError> {
+        let prefix = DirectoryKey::prefix(KEY_PRINCIPAL);
+        let mut end = prefix.clone();
+        end.push(u8::MAX);
+
+        self.store()?
+            .iterate(
+                Vec::new(),
+                CustomValueKey {
+                    value: prefix.clone(),
+                },
+                CustomValueKey { value: end },
+                false,
+                true,
+                move |names, key, value| {
+                    if let Some(name) = key
+                        .get(prefix.len()..)
+                        .and_then(|key| std::str::from_utf8(key).ok())
+                    {
+                        if typ.map_or(true, |typ| {
+                            PrincipalValue::deserialize(value)
+                                .map_or(false, |value| value.typ == typ)
+                        }) {
+                            names.push(name.to_string());
+                        }
+                    }
+                    Ok(true)
+                },
+            )
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn get_principal(&self, name: &str) -> Result<Option<PrincipalData>, ManageError> {
+        if let Some(value) = self.principal_value(name).await? {
+            let members = if value.typ == Type::Group {
+                self.group_members(name).await?
+            } else {
+                vec![]
+            };
+            Ok(Some(PrincipalData {
+                name: name.to_lowercase(),
+                typ: value.typ,
+                description: value.description,
+                quota: value.quota,
+                secrets: vec![],
+                emails: value.emails,
+                member_of: value.member_of,
+                members,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub async fn create_principal(&self, principal: PrincipalData) -> Result<(), ManageError> {
+        let name = principal.name.trim().to_lowercase();
+        if name.is_empty() || name.contains(['\0', '@']) {
+            return Err(ManageError::Invalid(format!(
+                "Invalid principal name {:?}.",
+                principal.name
+            )));
+        } else if self.principal_value(&name).await?.is_some() {
+            return Err(ManageError::AlreadyExists(format!(
+                "Principal {name:?} already exists."
+            )));
+        }
+
+        let mut value = PrincipalValue {
+            typ: principal.typ,
+            description: principal.description.filter(|d| !d.is_empty()),
+            quota: principal.quota,
+            secrets: Vec::with_capacity(principal.secrets.len()),
+            emails: Vec::with_capacity(principal.emails.len()),
+            member_of: Vec::with_capacity(principal.member_of.len()),
+        };
+        for secret in principal.secrets {
+            value.secrets.push(hash_secret(secret).await?);
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(u32::MAX).assert_value(
+            ValueClass::Custom {
+                bytes: DirectoryKey::principal(&name),
+            },
+            (),
+        );
+        for email in principal.emails {
+            let email = self.validate_email(&email).await?;
+            if !value.emails.contains(&email) {
+                batch.assert_value(
+                    ValueClass::Custom {
+                        bytes: DirectoryKey::email(&email),
+                    },
+                    (),
+                );
+                batch.op(Operation::Value {
+                    class: ValueClass::Custom {
+                        bytes: DirectoryKey::email(&email),
+                    },
+                    set: name.as_str().serialize().into(),
+                });
+                value.emails.push(email);
+            }
+        }
+        for group in principal.member_of {
+            let group = self.validate_group(&group).await?;
+            if !value.member_of.contains(&group) {
+                batch.op(Operation::Value {
+                    class: ValueClass::Custom {
+                        bytes: DirectoryKey::member(&group, &name),
+                    },
+                    set: name.as_str().serialize().into(),
+                });
+                value.member_of.push(group);
+            }
+        }
+        batch.op(Operation::Value {
+            class: ValueClass::Custom {
+                bytes: DirectoryKey::principal(&name),
+            },
+            set: value.serialize().into(),
+        });
+
+        self.write(batch).await?;
+
+        tracing::info!(
+            context = "directory",
+            event = "create",
+            protocol = "internal",
+            name = name,
+            "Principal created."
+        );
+
+        Ok(())
+    }
+
+    pub async fn update_principal(
+        &self,
+        name: &str,
+        update: PrincipalUpdate,
+    ) -> Result<(), ManageError> {
+        let name = name.to_lowercase();
+        let current = self
+            .store()?
+            .get_value::<HashedValue<PrincipalValue>>(CustomValueKey {
+                value: DirectoryKey::principal(&name),
+            })
+            .await?
+            .ok_or_else(|| ManageError::NotFound(format!("Principal {name:?} not found.")))?;
+        let mut value = current.inner.clone();
+
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(u32::MAX).assert_value(
+            ValueClass::Custom {
+                bytes: DirectoryKey::principal(&name),
+            },
+            &current,
+        );
+
+        if let Some(description) = update.description {
+            value.description = Some(description).filter(|d| !d.is_empty());
+        }
+        if let Some(quota) = update.quota {
+            value.quota = quota;
+        }
+        if let Some(secret) = update.secret {
+            value.secrets = vec![hash_secret(secret).await?];
+        }
+        for email in update.remove_emails {
+            let email = email.trim().to_lowercase();
+            if let Some(pos) = value.emails.iter().position(|e| e == &email) {
+                value.emails.remove(pos);
+                batch.op(Operation::Value {
+                    class: ValueClass::Custom {
+                        bytes: DirectoryKey::email(&email),
+                    },
+                    set: None,
+                });
+            } else {
+                return Err(ManageError::NotFound(format!(
+                    "Address {email:?} does not belong to principal {name:?}."
+                )));
+            }
+        }
+        for email in update.add_emails {
+            let email = self.validate_email(&email).await?;
+            if !value.emails.contains(&email) {
+                batch.assert_value(
+                    ValueClass::Custom {
+                        bytes: DirectoryKey::email(&email),
+                    },
+                    (),
+                );
+                batch.op(Operation::Value {
+                    class: ValueClass::Custom {
+                        bytes: DirectoryKey::email(&email),
+                    },
+                    set: name.as_str().serialize().into(),
+                });
+                value.emails.push(email);
+            }
+        }
+        for group in update.remove_member_of {
+            let group = group.trim().to_lowercase();
+            if let Some(pos) = value.member_of.iter().position(|g| g == &group) {
+                value.member_of.remove(pos);
+                batch.op(Operation::Value {
+                    class: ValueClass::Custom {
+                        bytes: DirectoryKey::member(&group, &name),
+                    },
+                    set: None,
+                });
+            } else {
+                return Err(ManageError::NotFound(format!(
+                    "Principal {name:?} is not a member of {group:?}."
+                )));
+            }
+        }
+        for group in update.add_member_of {
+            let group = self.validate_group(&group).await?;
+            if group == name {
+                return Err(ManageError::Invalid(
+                    "A group cannot be a member of itself.".to_string(),
+                ));
+            } else if !value.member_of.contains(&group) {
+                batch.op(Operation::Value {
+                    class: ValueClass::Custom {
+                        bytes: DirectoryKey::member(&group, &name),
+                    },
+                    set: name.as_str().serialize().into(),
+                });
+                value.member_of.push(group);
+            }
+        }
+
+        batch.op(Operation::Value {
+            class: ValueClass::Custom {
+                bytes: DirectoryKey::principal(&name),
+            },
+            set: value.serialize().into(),
+        });
+        self.write(batch).await?;
+
+        tracing::info!(
+            context = "directory",
+            event = "update",
+            protocol = "internal",
+            name = name,
+            "Principal updated."
+        );
+
+        Ok(())
+    }
+
+    pub async fn delete_principal(&self, name: &str) -> Result<(), ManageError> {
+        let name = name.to_lowercase();
+        let value = self
+            .principal_value(&name)
+            .await?
+            .ok_or_else(|| ManageError::NotFound(format!("Principal {name:?} not found.")))?;
+
+        // Remove the group from its members
+        if value.typ == Type::Group {
+            for member in self.group_members(&name).await? {
+                self.update_principal(
+                    &member,
+                    PrincipalUpdate {
+                        remove_member_of: vec![name.clone()],
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            }
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(u32::MAX);
+        for email in &value.emails {
+            batch.op(Operation::Value {
+                class: ValueClass::Custom {
+                    bytes: DirectoryKey::email(email),
+                },
+                set: None,
+            });
+        }
+        for group in &value.member_of {
+            batch.op(Operation::Value {
+                class: ValueClass::Custom {
+                    bytes: DirectoryKey::member(group, &name),
+                },
+                set: None,
+            });
+        }
+        batch.op(Operation::Value {
+            class: ValueClass::Custom {
+                bytes: DirectoryKey::principal(&name),
+            },
+            set: None,
+        });
+        self.write(batch).await?;
+
+        tracing::info!(
+            context = "directory",
+            event = "delete",
+            protocol = "internal",
+            name = name,
+            "Principal deleted."
+        );
+
+        Ok(())
+    }
+
+    pub async fn list_domains(&self) -> Result<Vec<String>, ManageError> {
+        self.list_keys(KEY_DOMAIN).await
+    }
+
+    pub async fn create_domain(&self, domain: &str) -> Result<(), ManageError> {
+        let domain = domain.trim().to_lowercase();
+        if domain.is_empty() || domain.contains(['\0', '@']) || !domain.contains('.') {
+            return Err(ManageError::Invalid(format!(
+                "Invalid domain name {domain:?}."
+            )));
+        }
+
+        if self.is_local_domain(&domain).await? {
+            return Err(ManageError::AlreadyExists(format!(
+                "Domain {domain:?} already exists."
+            )));
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(u32::MAX)
+            .assert_value(
+                ValueClass::Custom {
+                    bytes: DirectoryKey::domain(&domain),
+                },
+                (),
+            )
+            .op(Operation::Value {
+                class: ValueClass::Custom {
+                    bytes: DirectoryKey::domain(&domain),
+                },
+                set: domain.as_str().serialize().into(),
+            });
+        self.write(batch).await?;
+
+        tracing::info!(
+            context = "directory",
+            event = "create",
+            protocol = "internal",
+            domain = domain,
+            "Domain created."
+        );
+
+        Ok(())
+    }
+
+    pub async fn delete_domain(&self, domain: &str) -> Result<(), ManageError> {
+        let domain = domain.trim().to_lowercase();
+        if !self.is_local_domain(&domain).await? {
+            return Err(ManageError::NotFound(format!(
+                "Domain {domain:?} not found."
+            )));
+        }
+
+        // Domains can only be removed once no addresses use them
+        let suffix = format!("@{domain}");
+        if let Some(email) = self
+            .list_keys(KEY_EMAIL)
+            .await?
+            .into_iter()
+            .find(|email| email.ends_with(&suffix))
+        {
+            return Err(ManageError::Invalid(format!(
+                "Domain {domain:?} is in use by address {email:?}."
+            )));
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(u32::MAX).op(Operation::Value {
+            class: ValueClass::Custom {
+                bytes: DirectoryKey::domain(&domain),
+            },
+            set: None,
+        });
+        self.write(batch).await?;
+
+        tracing::info!(
+            context = "directory",
+            event = "delete",
+            protocol = "internal",
+            domain = domain,
+            "Domain deleted."
+        );
+
+        Ok(())
+    }
+
+    async fn validate_email(&self, email: &str) -> Result<String, ManageError> {
+        let email = email.trim().to_lowercase();
+        match email.rsplit_once('@') {
+            Some((local_part, domain))
+                if !domain.is_empty() && !local_part.contains(['\0', '@']) =>
+            {
+                if !self.is_local_domain(domain).await? {
+                    Err(ManageError::Invalid(format!(
+                        "Domain {domain:?} is not a local domain."
+                    )))
+                } else if let Some(owner) = self.email_owner(&email).await? {
+                    Err(ManageError::AlreadyExists(format!(
+                        "Address {email:?} already belongs to {owner:?}."
+                    )))
+                } else {
+                    Ok(email)
+                }
+            }
+            _ => Err(ManageError::Invalid(format!(
+                "Invalid email address {email:?}."
+            ))),
+        }
+    }
+
+    async fn validate_group(&self, group: &str) -> Result<String, ManageError> {
+        let group = group.trim().to_lowercase();
+        if group.eq_ignore_ascii_case(&self.opt.superuser_group) {
+            Ok(group)
+        } else {
+            match self.principal_value(&group).await? {
+                Some(value) if value.typ == Type::Group => Ok(group),
+                Some(_) => Err(ManageError::Invalid(format!(
+                    "Principal {group:?} is not a group."
+                ))),
+                None => Err(ManageError::NotFound(format!("Group {group:?} not found."))),
+            }
+        }
+    }
+
+    async fn list_keys(&self, kind: u8) -> Result<Vec<String>, ManageError> {
+        let prefix = DirectoryKey::prefix(kind);
+        let mut end = prefix.clone();
+        end.push(u8::MAX);
+
+        self.store()?
+            .iterate(
+                Vec::new(),
+                CustomValueKey {
+                    value: prefix.clone(),
+                },
+                CustomValueKey { value: end },
+                false,
+                true,
+                move |keys, key, _| {
+                    if let Some(key) = key
+                        .get(prefix.len()..)
+                        .and_then(|key| std::str::from_utf8(key).ok())
+                    {
+                        keys.push(key.to_string());
+                    }
+                    Ok(true)
+                },
+            )
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn write(&self, batch: BatchBuilder) -> Result<(), ManageError> {
+        match self.store()?.write(batch.build()).await {
+            Ok(_) => Ok(()),
+            Err(store::Error::AssertValueFailed) => Err(ManageError::Internal(
+                "Directory was modified concurrently, please try again.".to_string(),
+            )),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+async fn hash_secret(secret: String) -> Result<String, ManageError> {
+    if secret.is_empty() {
+        return Err(ManageError::Invalid("Secret cannot be empty.".to_string()));
+    }
+
+    tokio::task::spawn_blocking(move || {
+        SaltString::encode_b64(&store::rand::random::<[u8; 16]>())
+            .and_then(|salt| {
+                Argon2::default()
+                    .hash_password(secret.as_bytes(), &salt)
+                    .map(|hash| hash.to_string())
+            })
+            .map_err(|err| ManageError::Internal(format!("Failed to hash secret: {err}")))
+    })
+    .await
+    .map_err(|err| ManageError::Internal(format!("Failed to hash secret: {err}")))?
+}
+
+impl From<store::Error> for ManageError {
+    fn from(err: store::Error) -> Self {
+        ManageError::Internal(err.to_string())
+    }
+}
+
+impl From<DirectoryError> for ManageError {
+    fn from(err: DirectoryError) -> Self {
+        ManageError::Internal(match err {
+            DirectoryError::Store(err) => err.to_string(),
+            err => format!("{err:?}"),
+        })
+    }
+}
+
+impl Display for ManageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManageError::NotFound(reason)
+            | ManageError::AlreadyExists(reason)
+            | ManageError::Invalid(reason)
+            | ManageError::Internal(reason) => f.write_str(reason),
+        }
+    }
+}
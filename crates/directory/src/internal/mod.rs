@@ -0,0 +1,219 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::{Arc, OnceLock};
+
+use store::{
+    write::{key::KeySerializer, DeserializeFrom, SerializeInto},
+    Deserialize, Serialize, Store,
+};
+use utils::codec::leb128::{Leb128Iterator, Leb128Vec};
+
+use crate::{DirectoryError, DirectoryOptions, Principal, Type};
+
+pub mod config;
+pub mod lookup;
+pub mod manage;
+
+pub struct InternalDirectory {
+    store: OnceLock<Arc<Store>>,
+    opt: DirectoryOptions,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct PrincipalValue {
+    pub typ: Type,
+    pub description: Option<String>,
+    pub quota: u32,
+    pub secrets: Vec<String>,
+    pub emails: Vec<String>,
+    pub member_of: Vec<String>,
+}
+
+pub(crate) struct DirectoryKey;
+
+const KEY_PRINCIPAL: u8 = 3;
+const KEY_EMAIL: u8 = 4;
+const KEY_DOMAIN: u8 = 5;
+const KEY_MEMBER: u8 = 6;
+
+impl InternalDirectory {
+    pub fn set_store(&self, store: Arc<Store>) {
+        if self.store.set(store).is_err() {
+            tracing::debug!(
+                context = "directory",
+                event = "error",
+                protocol = "internal",
+                "Store already set for internal directory."
+            );
+        }
+    }
+
+    fn store(&self) -> crate::Result<&Store> {
+        self.store.get().map(|store| store.as_ref()).ok_or_else(|| {
+            DirectoryError::Store(store::Error::InternalError(
+                "Internal directory store not initialized".to_string(),
+            ))
+        })
+    }
+}
+
+impl PrincipalValue {
+    pub fn into_principal(self, name: &str, opt: &DirectoryOptions) -> Principal {
+        let mut principal = Principal {
+            name: name.to_string(),
+            secrets: self.secrets,
+            typ: self.typ,
+            description: self.description,
+            quota: self.quota,
+            member_of: Vec::with_capacity(self.member_of.len()),
+        };
+        for group in self.member_of {
+            if !group.eq_ignore_ascii_case(&opt.superuser_group) {
+                principal.member_of.push(group);
+            } else {
+                principal.typ = Type::Superuser;
+            }
+        }
+        principal
+    }
+}
+
+impl DirectoryKey {
+    pub fn principal(name: &str) -> Vec<u8> {
+        Self::new(KEY_PRINCIPAL, name)
+    }
+
+    pub fn email(address: &str) -> Vec<u8> {
+        Self::new(KEY_EMAIL, address)
+    }
+
+    pub fn domain(domain: &str) -> Vec<u8> {
+        Self::new(KEY_DOMAIN, domain)
+    }
+
+    pub fn member(group: &str, member: &str) -> Vec<u8> {
+        let group = group.to_lowercase();
+        let member = member.to_lowercase();
+        KeySerializer::new(group.len() + member.len() + std::mem::size_of::<u32>() + 2)
+            .write(u32::MAX)
+            .write(KEY_MEMBER)
+            .write(group.as_str())
+            .write(0u8)
+            .write(member.as_str())
+            .finalize()
+    }
+
+    pub fn members_prefix(group: &str) -> Vec<u8> {
+        let group = group.to_lowercase();
+        KeySerializer::new(group.len() + std::mem::size_of::<u32>() + 2)
+            .write(u32::MAX)
+            .write(KEY_MEMBER)
+            .write(group.as_str())
+            .write(0u8)
+            .finalize()
+    }
+
+    pub fn prefix(kind: u8) -> Vec<u8> {
+        KeySerializer::new(std::mem::size_of::<u32>() + 1)
+            .write(u32::MAX)
+            .write(kind)
+            .finalize()
+    }
+
+    fn new(kind: u8, value: &str) -> Vec<u8> {
+        let value = value.to_lowercase();
+        KeySerializer::new(value.len() + std::mem::size_of::<u32>() + 1)
+            .write(u32::MAX)
+            .write(kind)
+            .write(value.as_str())
+            .finalize()
+    }
+}
+
+impl Serialize for PrincipalValue {
+    fn serialize(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(64);
+        bytes.push(match self.typ {
+            Type::Individual => 0,
+            Type::Group => 1,
+            Type::Resource => 2,
+            Type::Location => 3,
+            Type::Other => 4,
+            Type::Superuser => 5,
+        });
+        bytes.push_leb128(self.quota);
+        self.description
+            .unwrap_or_default()
+            .serialize_into(&mut bytes);
+        for list in [self.secrets, self.emails, self.member_of] {
+            bytes.push_leb128(list.len());
+            for item in list {
+                item.serialize_into(&mut bytes);
+            }
+        }
+        bytes
+    }
+}
+
+impl Deserialize for PrincipalValue {
+    fn deserialize(bytes: &[u8]) -> store::Result<Self> {
+        let mut bytes = bytes.iter();
+        let typ = match bytes.next() {
+            Some(0) => Type::Individual,
+            Some(1) => Type::Group,
+            Some(2) => Type::Resource,
+            Some(3) => Type::Location,
+            Some(4) => Type::Other,
+            Some(5) => Type::Superuser,
+            _ => return Err(deserialize_error()),
+        };
+        let quota = bytes.next_leb128().ok_or_else(deserialize_error)?;
+        let description = String::deserialize_from(&mut bytes).ok_or_else(deserialize_error)?;
+        let mut lists = [Vec::new(), Vec::new(), Vec::new()];
+        for list in lists.iter_mut() {
+            let len: usize = bytes.next_leb128().ok_or_else(deserialize_error)?;
+            for _ in 0..len {
+                list.push(String::deserialize_from(&mut bytes).ok_or_else(deserialize_error)?);
+            }
+        }
+        let [secrets, emails, member_of] = lists;
+
+        Ok(PrincipalValue {
+            typ,
+            description: if !description.is_empty() {
+                Some(description)
+            } else {
+                None
+            },
+            quota,
+            secrets,
+            emails,
+            member_of,
+        })
+    }
+}
+
+fn deserialize_error() -> store::Error {
+    store::Error::InternalError("Failed to deserialize principal".to_string())
+}
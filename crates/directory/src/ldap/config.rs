@@ -46,16 +46,26 @@ impl LdapDirectory {
             None
         };
 
-        let manager = LdapConnectionManager::new(
-            config.value_require((&prefix, "address"))?.to_string(),
-            LdapConnSettings::new()
-                .set_conn_timeout(config.property_or_static((&prefix, "timeout"), "30s")?)
-                .set_starttls(config.property_or_static((&prefix, "tls"), "false")?)
-                .set_no_tls_verify(
-                    config.property_or_static((&prefix, "allow-invalid-certs"), "false")?,
-                ),
-            bind_dn,
-        );
+        let address = config.value_require((&prefix, "address"))?.to_string();
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(config.property_or_static((&prefix, "timeout"), "30s")?)
+            .set_starttls(config.property_or_static((&prefix, "tls"), "false")?)
+            .set_no_tls_verify(
+                config.property_or_static((&prefix, "allow-invalid-certs"), "false")?,
+            );
+
+        // Authenticate users by binding as their DN rather than comparing secrets
+        let auth_pool = if config.property_or_static((&prefix, "bind.auth.enable"), "false")? {
+            build_pool(
+                config,
+                &prefix,
+                LdapConnectionManager::new(address.clone(), settings.clone(), None),
+            )?
+            .into()
+        } else {
+            None
+        };
+        let manager = LdapConnectionManager::new(address, settings, bind_dn);
 
         let mut mappings = LdapMappings {
             base_dn: config.value_require((&prefix, "base-dn"))?.to_string(),
@@ -121,6 +131,7 @@ impl LdapDirectory {
             LdapDirectory {
                 mappings,
                 pool: build_pool(config, &prefix, manager)?,
+                auth_pool,
                 opt: DirectoryOptions::from_config(config, prefix.as_str())?,
             },
         )
@@ -24,7 +24,7 @@
 use ldap3::{ResultEntry, Scope, SearchEntry};
 use mail_send::Credentials;
 
-use crate::{to_catch_all_address, unwrap_subaddress, Directory, Principal, Type};
+use crate::{to_catch_all_address, unwrap_subaddress, AuthResult, Directory, Principal, Type};
 
 use super::{LdapDirectory, LdapMappings};
 
@@ -34,23 +34,52 @@ impl Directory for LdapDirectory {
         &self,
         credentials: &Credentials<String>,
     ) -> crate::Result<Option<Principal>> {
+        self.try_authenticate(credentials)
+            .await
+            .map(AuthResult::into_principal)
+    }
+
+    async fn try_authenticate(
+        &self,
+        credentials: &Credentials<String>,
+    ) -> crate::Result<AuthResult> {
         let (username, secret) = match credentials {
             Credentials::Plain { username, secret } => (username, secret),
             Credentials::OAuthBearer { token } => (token, token),
             Credentials::XOauth2 { username, secret } => (username, secret),
         };
         match self
-            .find_principal(&self.mappings.filter_name.build(username))
+            .find_principal_and_dn(&self.mappings.filter_name.build(username))
             .await
         {
-            Ok(Some(principal)) => {
-                if principal.verify_secret(secret).await {
-                    Ok(Some(principal))
+            Ok(Some((dn, principal))) => {
+                if let Some(auth_pool) = &self.auth_pool {
+                    // An empty password would result in an unauthenticated bind
+                    if secret.is_empty() {
+                        return Ok(AuthResult::Failure);
+                    }
+                    let result = auth_pool.get().await?.simple_bind(&dn, secret).await?;
+                    if result.rc == 0 {
+                        Ok(AuthResult::Success(principal))
+                    } else {
+                        tracing::debug!(
+                            context = "directory",
+                            event = "bind",
+                            dn = dn.as_str(),
+                            code = result.rc,
+                            reason = result.text.as_str(),
+                            "LDAP bind failed"
+                        );
+                        Ok(AuthResult::Failure)
+                    }
+                } else if principal.verify_secret(secret).await {
+                    Ok(AuthResult::Success(principal))
                 } else {
-                    Ok(None)
+                    Ok(AuthResult::Failure)
                 }
             }
-            result => result,
+            Ok(None) => Ok(AuthResult::NotFound),
+            Err(err) => Err(err),
         }
     }
 
@@ -293,6 +322,15 @@ impl Directory for LdapDirectory {
 
 impl LdapDirectory {
     async fn find_principal(&self, filter: &str) -> crate::Result<Option<Principal>> {
+        self.find_principal_and_dn(filter)
+            .await
+            .map(|result| result.map(|(_, principal)| principal))
+    }
+
+    async fn find_principal_and_dn(
+        &self,
+        filter: &str,
+    ) -> crate::Result<Option<(String, Principal)>> {
         let (rs, _res) = self
             .pool
             .get()
@@ -306,9 +344,9 @@ impl LdapDirectory {
             .await?
             .success()?;
 
-        if let Some(mut principal) = rs.into_iter().next().map(|entry| {
-            self.mappings
-                .entry_to_principal(SearchEntry::construct(entry))
+        if let Some((dn, mut principal)) = rs.into_iter().next().map(|entry| {
+            let entry = SearchEntry::construct(entry);
+            (entry.dn.clone(), self.mappings.entry_to_principal(entry))
         }) {
             // Map groups
             if !principal.member_of.is_empty() {
@@ -351,7 +389,7 @@ impl LdapDirectory {
                 }
                 principal.member_of = names;
             }
-            Ok(Some(principal))
+            Ok(Some((dn, principal)))
         } else {
             Ok(None)
         }
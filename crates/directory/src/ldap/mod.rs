@@ -32,6 +32,7 @@ pub mod pool;
 
 pub struct LdapDirectory {
     pool: Pool<LdapConnectionManager>,
+    auth_pool: Option<Pool<LdapConnectionManager>>,
     mappings: LdapMappings,
     opt: DirectoryOptions,
 }
@@ -26,12 +26,15 @@ use std::{borrow::Cow, fmt::Debug, sync::Arc};
 use ahash::{AHashMap, AHashSet};
 use bb8::RunError;
 use imap::ImapError;
+use internal::InternalDirectory;
 use ldap3::LdapError;
 use mail_send::Credentials;
 
 pub mod cache;
+pub mod composite;
 pub mod config;
 pub mod imap;
+pub mod internal;
 pub mod ldap;
 pub mod memory;
 pub mod secret;
@@ -48,7 +51,8 @@ pub struct Principal {
     pub member_of: Vec<String>,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Type {
     Individual,
     Group,
@@ -59,12 +63,22 @@ pub enum Type {
     Superuser,
 }
 
+/// Outcome of an authentication attempt, telling apart accounts that are
+/// unknown to a directory from accounts that failed to authenticate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthResult {
+    Success(Principal),
+    Failure,
+    NotFound,
+}
+
 #[derive(Debug)]
 pub enum DirectoryError {
     Ldap(LdapError),
     Sql(sqlx::Error),
     Imap(ImapError),
     Smtp(mail_send::Error),
+    Store(store::Error),
     TimedOut,
     Unsupported,
 }
@@ -81,6 +95,47 @@ pub trait Directory: Sync + Send {
     async fn expn(&self, address: &str) -> Result<Vec<String>>;
     async fn query(&self, query: &str, params: &[&str]) -> Result<bool>;
 
+    /// Authenticates the credentials. Directories that can't tell whether the
+    /// account exists report failed attempts as `NotFound`.
+    async fn try_authenticate(&self, credentials: &Credentials<String>) -> Result<AuthResult> {
+        Ok(match self.authenticate(credentials).await? {
+            Some(principal) => AuthResult::Success(principal),
+            None => AuthResult::NotFound,
+        })
+    }
+
+    /// Maps the identity presented in a TLS client certificate to a principal.
+    async fn authenticate_external(
+        &self,
+        identity: &str,
+        authzid: &str,
+    ) -> Result<Option<Principal>> {
+        let principal = if let Some(principal) = self.principal(identity).await? {
+            principal
+        } else {
+            let mut names = self.names_by_email(identity).await?;
+            if names.len() != 1 {
+                return Ok(None);
+            } else if let Some(principal) = self.principal(&names.pop().unwrap()).await? {
+                principal
+            } else {
+                return Ok(None);
+            }
+        };
+
+        // Only allow authorizing as the certificate holder
+        Ok(
+            if authzid.is_empty()
+                || authzid.eq_ignore_ascii_case(identity)
+                || authzid.eq_ignore_ascii_case(&principal.name)
+            {
+                Some(principal)
+            } else {
+                None
+            },
+        )
+    }
+
     fn type_name(&self) -> &'static str {
         std::any::type_name::<Self>()
     }
@@ -137,6 +192,15 @@ impl Principal {
     }
 }
 
+impl AuthResult {
+    pub fn into_principal(self) -> Option<Principal> {
+        match self {
+            AuthResult::Success(principal) => Some(principal),
+            AuthResult::Failure | AuthResult::NotFound => None,
+        }
+    }
+}
+
 impl Debug for dyn Directory {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Directory")
@@ -179,6 +243,7 @@ struct DirectoryOptions {
 pub struct DirectoryConfig {
     pub directories: AHashMap<String, Arc<dyn Directory>>,
     pub lookups: AHashMap<String, Arc<Lookup>>,
+    pub internal: Option<Arc<InternalDirectory>>,
 }
 
 pub type Result<T> = std::result::Result<T, DirectoryError>;
@@ -266,6 +331,20 @@ impl From<mail_send::Error> for DirectoryError {
     }
 }
 
+impl From<store::Error> for DirectoryError {
+    fn from(error: store::Error) -> Self {
+        tracing::warn!(
+            context = "directory",
+            event = "error",
+            protocol = "internal",
+            reason = %error,
+            "Internal directory error"
+        );
+
+        DirectoryError::Store(error)
+    }
+}
+
 impl DirectoryError {
     pub fn unsupported(protocol: &str, method: &str) -> Self {
         tracing::warn!(
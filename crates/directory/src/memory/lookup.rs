@@ -23,7 +23,9 @@
 
 use mail_send::Credentials;
 
-use crate::{to_catch_all_address, unwrap_subaddress, Directory, DirectoryError, Principal};
+use crate::{
+    to_catch_all_address, unwrap_subaddress, AuthResult, Directory, DirectoryError, Principal,
+};
 
 use super::{EmailType, MemoryDirectory};
 
@@ -33,15 +35,27 @@ impl Directory for MemoryDirectory {
         &self,
         credentials: &Credentials<String>,
     ) -> crate::Result<Option<Principal>> {
+        self.try_authenticate(credentials)
+            .await
+            .map(AuthResult::into_principal)
+    }
+
+    async fn try_authenticate(
+        &self,
+        credentials: &Credentials<String>,
+    ) -> crate::Result<AuthResult> {
         let (username, secret) = match credentials {
             Credentials::Plain { username, secret } => (username, secret),
             Credentials::OAuthBearer { token } => (token, token),
             Credentials::XOauth2 { username, secret } => (username, secret),
         };
-        match self.principals.get(username) {
-            Some(principal) if principal.verify_secret(secret).await => Ok(Some(principal.clone())),
-            _ => Ok(None),
-        }
+        Ok(match self.principals.get(username) {
+            Some(principal) if principal.verify_secret(secret).await => {
+                AuthResult::Success(principal.clone())
+            }
+            Some(_) => AuthResult::Failure,
+            None => AuthResult::NotFound,
+        })
     }
 
     async fn principal(&self, name: &str) -> crate::Result<Option<Principal>> {
@@ -25,7 +25,7 @@ use futures::TryStreamExt;
 use mail_send::Credentials;
 use sqlx::{any::AnyRow, Column, Row};
 
-use crate::{to_catch_all_address, unwrap_subaddress, Directory, Principal, Type};
+use crate::{to_catch_all_address, unwrap_subaddress, AuthResult, Directory, Principal, Type};
 
 use super::{SqlDirectory, SqlMappings};
 
@@ -35,17 +35,28 @@ impl Directory for SqlDirectory {
         &self,
         credentials: &Credentials<String>,
     ) -> crate::Result<Option<Principal>> {
+        self.try_authenticate(credentials)
+            .await
+            .map(AuthResult::into_principal)
+    }
+
+    async fn try_authenticate(
+        &self,
+        credentials: &Credentials<String>,
+    ) -> crate::Result<AuthResult> {
         let (username, secret) = match credentials {
             Credentials::Plain { username, secret } => (username, secret),
             Credentials::OAuthBearer { token } => (token, token),
             Credentials::XOauth2 { username, secret } => (username, secret),
         };
 
-        match self.principal(username).await {
-            Ok(Some(principal)) if principal.verify_secret(secret).await => Ok(Some(principal)),
-            Ok(_) => Ok(None),
-            Err(err) => Err(err),
-        }
+        Ok(match self.principal(username).await? {
+            Some(principal) if principal.verify_secret(secret).await => {
+                AuthResult::Success(principal)
+            }
+            Some(_) => AuthResult::Failure,
+            None => AuthResult::NotFound,
+        })
     }
 
     async fn principal(&self, name: &str) -> crate::Result<Option<Principal>> {
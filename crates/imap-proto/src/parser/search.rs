@@ -343,6 +343,16 @@ pub fn parse_filters(
                     filters = Vec::with_capacity(1);
                     operator = Filter::Not;
                     continue;
+                } else if value.eq_ignore_ascii_case(b"FUZZY") {
+                    if filters_stack.len() > 10 {
+                        return Err(Cow::from("Too many nested filters"));
+                    }
+
+                    filters_stack.push((filters, operator, filters_len));
+                    filters_len = 0;
+                    filters = Vec::with_capacity(1);
+                    operator = Filter::Fuzzy;
+                    continue;
                 } else {
                     filters.push(Filter::Sequence(parse_sequence_set(&value)?, false));
                 }
@@ -373,7 +383,7 @@ pub fn parse_filters(
         if !filters_stack.is_empty()
             && (found_parenthesis
                 || (operator == Filter::Or && filters_len == 2)
-                || (operator == Filter::Not && filters_len == 1))
+                || ((operator == Filter::Not || operator == Filter::Fuzzy) && filters_len == 1))
         {
             while let Some((mut prev_filters, prev_operator, prev_filters_len)) =
                 filters_stack.pop()
@@ -430,6 +440,8 @@ impl ResultOption {
             Ok(Self::Save)
         } else if value.eq_ignore_ascii_case(b"context") {
             Ok(Self::Context)
+        } else if value.eq_ignore_ascii_case(b"relevancy") {
+            Ok(Self::Relevancy)
         } else {
             Err(format!("Invalid result option {:?}", String::from_utf8_lossy(value)).into())
         }
@@ -742,6 +754,34 @@ mod tests {
                     sort: None,
                 },
             ),
+            (
+                b"MYTAG1 SEARCH RETURN (RELEVANCY ALL) FUZZY TEXT \"Helo\"\r\n".to_vec(),
+                search::Arguments {
+                    tag: "MYTAG1".to_string(),
+                    result_options: vec![ResultOption::Relevancy, ResultOption::All],
+                    filter: vec![Filter::Fuzzy, Filter::Text("Helo".to_string()), Filter::End],
+                    is_esearch: true,
+                    sort: None,
+                },
+            ),
+            (
+                b"A1 SEARCH UNSEEN FUZZY (SUBJECT \"meting\" NOT FROM \"bob\")\r\n".to_vec(),
+                search::Arguments {
+                    tag: "A1".to_string(),
+                    result_options: vec![],
+                    filter: vec![
+                        Filter::Unseen,
+                        Filter::Fuzzy,
+                        Filter::Subject("meting".to_string()),
+                        Filter::Not,
+                        Filter::From("bob".to_string()),
+                        Filter::End,
+                        Filter::End,
+                    ],
+                    is_esearch: true,
+                    sort: None,
+                },
+            ),
         ] {
             let command_str = String::from_utf8_lossy(&command).into_owned();
             assert_eq!(
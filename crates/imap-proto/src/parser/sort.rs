@@ -127,6 +127,8 @@ impl Sort {
             Ok(Self::DisplayFrom)
         } else if value.eq_ignore_ascii_case(b"DISPLAYTO") {
             Ok(Self::DisplayTo)
+        } else if value.eq_ignore_ascii_case(b"RELEVANCY") {
+            Ok(Self::Relevancy)
         } else {
             Err(format!("Invalid sort criteria {:?}", String::from_utf8_lossy(value)).into())
         }
@@ -239,6 +241,30 @@ mod tests {
                     tag: "E01".to_string(),
                 },
             ),
+            (
+                b"A285 SORT (RELEVANCY REVERSE ARRIVAL) UTF-8 FUZZY BODY \"mesage\"\r\n".to_vec(),
+                Arguments {
+                    sort: vec![
+                        Comparator {
+                            sort: Sort::Relevancy,
+                            ascending: true,
+                        },
+                        Comparator {
+                            sort: Sort::Arrival,
+                            ascending: false,
+                        },
+                    ]
+                    .into(),
+                    filter: vec![
+                        Filter::Fuzzy,
+                        Filter::Body("mesage".to_string()),
+                        Filter::End,
+                    ],
+                    result_options: Vec::new(),
+                    is_esearch: false,
+                    tag: "A285".to_string(),
+                },
+            ),
         ] {
             let command_str = String::from_utf8_lossy(&command).into_owned();
 
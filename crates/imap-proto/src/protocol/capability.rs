@@ -53,6 +53,7 @@ pub enum Capability {
     ListExtended, //LIST-EXTENDED
     ESort,
     SortDisplay,      //SORT=DISPLAY
+    SearchFuzzy,      //SEARCH=FUZZY
     SpecialUse,       //SPECIAL-USE
     CreateSpecialUse, //CREATE-SPECIAL-USEE
     Move,
@@ -105,6 +106,7 @@ impl Capability {
             Capability::ListExtended => b"LIST-EXTENDED",
             Capability::ESort => b"ESORT",
             Capability::SortDisplay => b"SORT=DISPLAY",
+            Capability::SearchFuzzy => b"SEARCH=FUZZY",
             Capability::SpecialUse => b"SPECIAL-USE",
             Capability::CreateSpecialUse => b"CREATE-SPECIAL-USE",
             Capability::Move => b"MOVE",
@@ -141,6 +143,7 @@ impl Capability {
                 Capability::ListExtended,
                 Capability::ESort,
                 Capability::SortDisplay,
+                Capability::SearchFuzzy,
                 Capability::SpecialUse,
                 Capability::CreateSpecialUse,
                 Capability::Move,
@@ -43,6 +43,7 @@ pub enum Sort {
     Subject,
     To,
     DisplayTo,
+    Relevancy,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -61,6 +62,7 @@ pub struct Response {
     pub max: Option<u32>,
     pub count: Option<u32>,
     pub highest_modseq: Option<u64>,
+    pub relevancy: Option<Vec<u32>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -71,6 +73,7 @@ pub enum ResultOption {
     Count,
     Save,
     Context,
+    Relevancy,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -127,6 +130,9 @@ pub enum Filter {
     // RFC 8474 - ObjectID
     EmailId(String),
     ThreadId(String),
+
+    // RFC 6203 - FUZZY
+    Fuzzy,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -173,6 +179,16 @@ impl Response {
                 buf.extend_from_slice(b" ALL ");
                 serialize_sequence(&mut buf, &self.ids);
             }
+            if let Some(relevancy) = self.relevancy.filter(|r| !r.is_empty()) {
+                buf.extend_from_slice(b" RELEVANCY (");
+                for (pos, score) in relevancy.into_iter().enumerate() {
+                    if pos > 0 {
+                        buf.push(b' ');
+                    }
+                    buf.extend_from_slice(score.to_string().as_bytes());
+                }
+                buf.push(b')');
+            }
             if let Some(highest_modseq) = self.highest_modseq {
                 buf.extend_from_slice(b" MODSEQ ");
                 buf.extend_from_slice(highest_modseq.to_string().as_bytes());
@@ -216,6 +232,7 @@ mod tests {
                     max: 11.into(),
                     count: 3.into(),
                     highest_modseq: None,
+                    relevancy: None,
                 },
                 "A283",
                 concat!("* ESEARCH (TAG \"A283\") COUNT 3 MIN 2 MAX 11 ALL 2,10:11\r\n",),
@@ -233,6 +250,7 @@ mod tests {
                     max: None,
                     count: None,
                     highest_modseq: None,
+                    relevancy: None,
                 },
                 "A283",
                 concat!("* ESEARCH (TAG \"A283\") ALL 1:3,5,10:13,90,92:99\r\n",),
@@ -248,6 +266,7 @@ mod tests {
                     max: None,
                     count: None,
                     highest_modseq: None,
+                    relevancy: None,
                 },
                 "A283",
                 concat!("* ESEARCH (TAG \"A283\")\r\n",),
@@ -263,11 +282,28 @@ mod tests {
                     max: None,
                     count: None,
                     highest_modseq: 12345.into(),
+                    relevancy: None,
                 },
                 "A283",
                 concat!("* ESEARCH (TAG \"A283\") ALL 10:13,21 MODSEQ 12345\r\n",),
                 concat!("* SEARCH 10 11 12 13 21 (MODSEQ 12345)\r\n",),
             ),
+            (
+                super::Response {
+                    is_uid: false,
+                    is_esearch: true,
+                    is_sort: false,
+                    ids: vec![1, 5, 10],
+                    min: None,
+                    max: None,
+                    count: None,
+                    highest_modseq: None,
+                    relevancy: vec![4, 99, 42].into(),
+                },
+                "MYTAG1",
+                concat!("* ESEARCH (TAG \"MYTAG1\") ALL 1,5,10 RELEVANCY (4 99 42)\r\n",),
+                concat!("* SEARCH 1 5 10\r\n"),
+            ),
         ] {
             let response_v2 = String::from_utf8(response.clone().serialize(tag)).unwrap();
             response.is_esearch = false;
@@ -98,6 +98,7 @@ pub struct Session<T: AsyncRead> {
     pub version: ProtocolVersion,
     pub state: State,
     pub is_tls: bool,
+    pub tls_identity: Option<String>,
     pub is_condstore: bool,
     pub is_qresync: bool,
     pub writer: mpsc::Sender<writer::Event>,
@@ -29,7 +29,7 @@ use tokio::{
     sync::oneshot,
 };
 use tokio_rustls::server::TlsStream;
-use utils::listener::{SessionData, SessionManager};
+use utils::listener::{tls_client_identity, SessionData, SessionManager};
 
 use super::{writer, ImapSessionManager, Session, State};
 
@@ -128,6 +128,7 @@ impl Session<TcpStream> {
             state: State::NotAuthenticated { auth_failures: 0 },
             writer: writer::spawn_writer(writer::Event::Stream(stream_tx), session.span.clone()),
             is_tls: false,
+            tls_identity: None,
             is_condstore: false,
             is_qresync: false,
             imap: manager.imap,
@@ -163,8 +164,9 @@ impl Session<TcpStream> {
         };
 
         // Upgrade to TLS
-        let (stream_rx, stream_tx) =
-            tokio::io::split(self.instance.tls_accept(stream, &self.span).await?);
+        let stream = self.instance.tls_accept(stream, &self.span).await?;
+        let tls_identity = tls_client_identity(&stream);
+        let (stream_rx, stream_tx) = tokio::io::split(stream);
         if let Err(err) = self.writer.send(writer::Event::StreamTls(stream_tx)).await {
             tracing::debug!("Failed to send stream: {}", err);
             return Err(());
@@ -178,6 +180,7 @@ impl Session<TcpStream> {
             version: self.version,
             state: self.state,
             is_tls: true,
+            tls_identity,
             is_condstore: self.is_condstore,
             is_qresync: self.is_qresync,
             writer: self.writer,
@@ -208,6 +211,7 @@ impl Session<TlsStream<TcpStream>> {
         }
 
         // Spit stream into read and write halves
+        let tls_identity = tls_client_identity(&stream);
         let (stream_rx, stream_tx) = tokio::io::split(stream);
 
         Ok(Session {
@@ -216,6 +220,7 @@ impl Session<TlsStream<TcpStream>> {
             state: State::NotAuthenticated { auth_failures: 0 },
             writer: writer::spawn_writer(writer::Event::StreamTls(stream_tx), span.clone()),
             is_tls: true,
+            tls_identity,
             is_condstore: false,
             is_qresync: false,
             imap: manager.imap,
@@ -362,6 +362,11 @@ impl<T: AsyncRead> Session<T> {
                         }
                     }
 
+                    // Keep the previous and new permissions in order to notify grantees
+                    let acl_current = values.inner.get(&Property::Acl).clone();
+                    let acl_changes = changes.get(&Property::Acl).clone();
+                    let mailbox_name = values.inner.get(&Property::Name).clone();
+
                     // Write changes
                     let mailbox_id = mailbox.mailbox_id.unwrap();
                     let mut batch = BatchBuilder::new();
@@ -387,6 +392,16 @@ impl<T: AsyncRead> Session<T> {
                                                     .with_change(TypeState::Mailbox, change_id),
                                             )
                                             .await;
+                                        data.jmap
+                                            .share_notification_create(
+                                                data.account_id,
+                                                mailbox.account_id,
+                                                mailbox_id,
+                                                &mailbox_name,
+                                                &acl_current,
+                                                &acl_changes,
+                                            )
+                                            .await;
                                     }
                                     Err(_) => {
                                         data.write_bytes(
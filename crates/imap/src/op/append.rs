@@ -142,6 +142,7 @@ impl SessionData {
                     keywords: message.flags.into_iter().map(Keyword::from).collect(),
                     received_at: message.received_at.map(|d| d as u64),
                     skip_duplicates: false,
+                    skip_muted_inbox: false,
                 })
                 .await
             {
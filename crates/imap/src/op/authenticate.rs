@@ -28,6 +28,7 @@ use imap_proto::{
     receiver::{self, Request},
     Command, ResponseCode, StatusResponse,
 };
+use jmap::auth::AccessToken;
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
 use tokio::io::AsyncRead;
@@ -80,6 +81,53 @@ impl<T: AsyncRead> Session<T> {
                         self.write_bytes(b"+ \"\"\r\n".to_vec()).await
                     }
                 }
+                Mechanism::External => {
+                    if let Some(identity) = self.tls_identity.clone() {
+                        if !args.params.is_empty() {
+                            let authzid = args.params.pop().unwrap();
+                            match if authzid == "=" {
+                                Some(String::new())
+                            } else {
+                                base64_decode(authzid.as_bytes())
+                                    .and_then(|authzid| String::from_utf8(authzid).ok())
+                            } {
+                                Some(authzid) => {
+                                    self.authenticate_external(identity, authzid, args.tag)
+                                        .await
+                                }
+                                None => {
+                                    self.write_bytes(
+                                        StatusResponse::no("Failed to decode challenge.")
+                                            .with_tag(args.tag)
+                                            .with_code(ResponseCode::Parse)
+                                            .into_bytes(),
+                                    )
+                                    .await
+                                }
+                            }
+                        } else {
+                            // An empty response from the client yields the "=" placeholder
+                            self.receiver.request = receiver::Request {
+                                tag: args.tag,
+                                command: Command::Authenticate,
+                                tokens: vec![
+                                    receiver::Token::Argument(args.mechanism.into_bytes()),
+                                    receiver::Token::Argument(b"=".to_vec()),
+                                ],
+                            };
+                            self.receiver.state = receiver::State::Argument { last_ch: b' ' };
+                            self.write_bytes(b"+ \"\"\r\n".to_vec()).await
+                        }
+                    } else {
+                        self.write_bytes(
+                            StatusResponse::no("No client certificate was presented.")
+                                .with_tag(args.tag)
+                                .with_code(ResponseCode::Cannot)
+                                .into_bytes(),
+                        )
+                        .await
+                    }
+                }
                 _ => {
                     self.write_bytes(
                         StatusResponse::no("Authentication mechanism not supported.")
@@ -100,18 +148,7 @@ impl<T: AsyncRead> Session<T> {
         tag: String,
     ) -> crate::Result<()> {
         // Throttle authentication requests
-        if self.jmap.is_auth_allowed(self.remote_addr.clone()).is_err() {
-            self.write_bytes(
-                StatusResponse::bye("Too many authentication requests from this IP address.")
-                    .into_bytes(),
-            )
-            .await?;
-            tracing::debug!(parent: &self.span,
-                event = "disconnect",
-                "Too many authentication attempts, disconnecting.",
-            );
-            return Err(());
-        }
+        self.throttle_auth().await?;
 
         // Authenticate
         let access_token = match credentials {
@@ -138,6 +175,45 @@ impl<T: AsyncRead> Session<T> {
             }
         };
 
+        self.finish_authentication(access_token, tag).await
+    }
+
+    pub async fn authenticate_external(
+        &mut self,
+        identity: String,
+        authzid: String,
+        tag: String,
+    ) -> crate::Result<()> {
+        // Throttle authentication requests
+        self.throttle_auth().await?;
+
+        // Authenticate using the client certificate
+        let access_token = self.jmap.authenticate_external(&identity, &authzid).await;
+        self.finish_authentication(access_token, tag).await
+    }
+
+    async fn throttle_auth(&mut self) -> crate::Result<()> {
+        if self.jmap.is_auth_allowed(self.remote_addr.clone()).is_err() {
+            self.write_bytes(
+                StatusResponse::bye("Too many authentication requests from this IP address.")
+                    .into_bytes(),
+            )
+            .await?;
+            tracing::debug!(parent: &self.span,
+                event = "disconnect",
+                "Too many authentication attempts, disconnecting.",
+            );
+            Err(())
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn finish_authentication(
+        &mut self,
+        access_token: Option<AccessToken>,
+        tag: String,
+    ) -> crate::Result<()> {
         if let Some(access_token) = access_token {
             // Enforce concurrency limits
             let in_flight = self
@@ -23,6 +23,7 @@
 
 use std::sync::Arc;
 
+use ahash::AHashMap;
 use imap_proto::{
     protocol::{
         search::{self, Arguments, Filter, Response, ResultOption},
@@ -123,10 +124,27 @@ impl SessionData {
         is_uid: bool,
     ) -> Result<search::Response, StatusResponse> {
         // Run query
-        let (result_set, include_highest_modseq) = self
+        let (result_set, include_highest_modseq, text_conditions) = self
             .query(arguments.filter, &mailbox, &prev_saved_search, is_uid)
             .await?;
 
+        // Obtain relevancy scores
+        let relevancy_scores = if arguments.result_options.contains(&ResultOption::Relevancy) {
+            self.jmap
+                .store
+                .relevance_scores(
+                    mailbox.id.account_id,
+                    Collection::Email,
+                    result_set.results.clone(),
+                    text_conditions.clone(),
+                )
+                .await
+                .map_err(|_| StatusResponse::database_failure())?
+                .into()
+        } else {
+            None
+        };
+
         // Obtain modseq
         let highest_modseq = if include_highest_modseq {
             self.synchronize_messages(&mailbox)
@@ -177,6 +195,13 @@ impl SessionData {
                                 search::Sort::To | search::Sort::DisplayTo => {
                                     query::Comparator::field(Property::To, item.ascending)
                                 }
+                                search::Sort::Relevancy => {
+                                    // RELEVANCY sorts by decreasing relevancy unless reversed
+                                    query::Comparator::relevance(
+                                        text_conditions.clone(),
+                                        !item.ascending,
+                                    )
+                                }
                             })
                             .collect::<Vec<_>>(),
                         Pagination::new(results_len, 0, None, 0),
@@ -212,6 +237,28 @@ impl SessionData {
             false
         };
 
+        // Map relevancy scores to the 1-100 range
+        let relevancy = relevancy_scores.map(|scores| {
+            let max_score = scores.values().copied().fold(0.0, f64::max);
+            let state = mailbox.state.lock();
+            let scores = scores
+                .into_iter()
+                .filter_map(|(document_id, score)| {
+                    let (id, _) = state.map_result_id(document_id, is_uid)?;
+                    let score = if max_score > 0.0 {
+                        ((score / max_score) * 100.0).round().clamp(1.0, 100.0) as u32
+                    } else {
+                        1
+                    };
+                    Some((id, score))
+                })
+                .collect::<AHashMap<_, _>>();
+            imap_ids
+                .iter()
+                .map(|id| scores.get(id).copied().unwrap_or(1))
+                .collect::<Vec<_>>()
+        });
+
         // Save results
         if let (Some(results_tx), Some(saved_results)) = (results_tx, saved_results) {
             let saved_results = Arc::new(saved_results);
@@ -233,6 +280,7 @@ impl SessionData {
             },
             ids: if arguments.result_options.is_empty()
                 || arguments.result_options.contains(&ResultOption::All)
+                || arguments.result_options.contains(&ResultOption::Relevancy)
             {
                 imap_ids
             } else {
@@ -241,6 +289,7 @@ impl SessionData {
             is_sort,
             is_esearch: arguments.is_esearch,
             highest_modseq,
+            relevancy,
         })
     }
 
@@ -250,7 +299,7 @@ impl SessionData {
         mailbox: &SelectedMailbox,
         prev_saved_search: &Option<Option<Arc<Vec<ImapId>>>>,
         is_uid: bool,
-    ) -> Result<(ResultSet, bool), StatusResponse> {
+    ) -> Result<(ResultSet, bool, Vec<query::Filter>), StatusResponse> {
         // Obtain message ids
         let mut filters = Vec::with_capacity(imap_filter.len() + 1);
         let message_ids = if let Some(mailbox_id) = mailbox.id.mailbox_id {
@@ -275,7 +324,10 @@ impl SessionData {
 
         // Convert query
         let mut include_highest_modseq = false;
+        let mut operator_stack = Vec::new();
+        let mut fuzzy_depth = 0;
         for filter in imap_filter {
+            let filters_start = filters.len();
             match filter {
                 search::Filter::Sequence(sequence, uid_filter) => {
                     let mut set = RoaringBitmap::new();
@@ -545,15 +597,26 @@ impl SessionData {
                 }
                 search::Filter::And => {
                     filters.push(query::Filter::And);
+                    operator_stack.push(false);
                 }
                 search::Filter::Or => {
                     filters.push(query::Filter::Or);
+                    operator_stack.push(false);
                 }
                 search::Filter::Not => {
                     filters.push(query::Filter::Not);
+                    operator_stack.push(false);
+                }
+                search::Filter::Fuzzy => {
+                    filters.push(query::Filter::And);
+                    operator_stack.push(true);
+                    fuzzy_depth += 1;
                 }
                 search::Filter::End => {
                     filters.push(query::Filter::End);
+                    if operator_stack.pop().unwrap_or_default() {
+                        fuzzy_depth -= 1;
+                    }
                 }
                 search::Filter::Recent => {
                     filters.push(query::Filter::is_in_bitmap(
@@ -639,13 +702,21 @@ impl SessionData {
                     }
                 }
             }
+
+            // Apply the FUZZY modifier to any full-text conditions
+            if fuzzy_depth > 0 {
+                for filter in filters.iter_mut().skip(filters_start) {
+                    *filter = std::mem::replace(filter, query::Filter::End).with_fuzzy(true);
+                }
+            }
         }
 
         // Run query
+        let text_conditions = query::Filter::text_conditions(&filters);
         self.jmap
             .filter(mailbox.id.account_id, Collection::Email, filters)
             .await
-            .map(|res| (res, include_highest_modseq))
+            .map(|res| (res, include_highest_modseq, text_conditions))
             .map_err(|err| err.into())
     }
 }
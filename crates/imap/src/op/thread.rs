@@ -75,7 +75,7 @@ impl SessionData {
         is_uid: bool,
     ) -> Result<Response, StatusResponse> {
         // Run query
-        let (result_set, _) = self
+        let (result_set, _, _) = self
             .query(arguments.filter, &mailbox, &None, is_uid)
             .await?;
 
@@ -31,7 +31,11 @@ use std::{
 use base64::{engine::general_purpose, Engine};
 use clap::{Parser, ValueEnum};
 use dialoguer::{console::Term, theme::ColorfulTheme, Input, Select};
-use openssl::rsa::Rsa;
+use openssl::{
+    ec::{EcGroup, EcKey},
+    nid::Nid,
+    rsa::Rsa,
+};
 use pwhash::sha512_crypt;
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use rusqlite::{Connection, OpenFlags};
@@ -262,7 +266,8 @@ fn main() -> std::io::Result<()> {
                     .take(64)
                     .map(char::from)
                     .collect::<String>(),
-            );
+            )
+            .replace("__VAPID_KEY__", &generate_vapid_key()?);
 
         directory
     } else {
@@ -725,6 +730,13 @@ fn generate_dkim(path: &Path, domain: &str, hostname: &str) -> std::io::Result<S
     Ok(instructions)
 }
 
+fn generate_vapid_key() -> std::io::Result<String> {
+    // P-256 private key used to sign Web Push requests (RFC 8292)
+    let key = EcKey::generate(&EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?)?;
+
+    Ok(general_purpose::URL_SAFE_NO_PAD.encode(key.private_key().to_vec_padded(32)?))
+}
+
 /*#[cfg(not(target_env = "msvc"))]
 unsafe fn get_uid_gid() -> (libc::uid_t, libc::gid_t) {
     use std::{ffi::CString, process::Command};
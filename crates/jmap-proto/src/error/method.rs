@@ -34,6 +34,7 @@ pub enum MethodError {
     AnchorNotFound,
     UnsupportedFilter(String),
     UnsupportedSort(String),
+    UnknownDataType(String),
     ServerFail(String),
     UnknownMethod(String),
     ServerUnavailable,
@@ -55,6 +56,7 @@ impl Display for MethodError {
             MethodError::AnchorNotFound => write!(f, "Anchor not found"),
             MethodError::UnsupportedFilter(err) => write!(f, "Unsupported filter: {}", err),
             MethodError::UnsupportedSort(err) => write!(f, "Unsupported sort: {}", err),
+            MethodError::UnknownDataType(err) => write!(f, "Unknown data type: {}", err),
             MethodError::ServerFail(err) => write!(f, "Server error: {}", err),
             MethodError::UnknownMethod(err) => write!(f, "Unknown method: {}", err),
             MethodError::ServerUnavailable => write!(f, "Server unavailable"),
@@ -109,6 +111,7 @@ impl Serialize for MethodError {
                 ("unsupportedFilter", description.as_str())
             }
             MethodError::UnsupportedSort(description) => ("unsupportedSort", description.as_str()),
+            MethodError::UnknownDataType(description) => ("unknownDataType", description.as_str()),
             MethodError::ServerFail(_) => ("serverFail", {
                 concat!(
                     "An unexpected error occurred while processing ",
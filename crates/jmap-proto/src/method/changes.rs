@@ -68,6 +68,8 @@ pub enum RequestArguments {
     Thread,
     Identity,
     EmailSubmission,
+    Quota,
+    ShareNotification,
 }
 
 impl JsonObjectParser for ChangesRequest {
@@ -82,6 +84,8 @@ impl JsonObjectParser for ChangesRequest {
                 MethodObject::Thread => RequestArguments::Thread,
                 MethodObject::Identity => RequestArguments::Identity,
                 MethodObject::EmailSubmission => RequestArguments::EmailSubmission,
+                MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::ShareNotification => RequestArguments::ShareNotification,
                 _ => {
                     return Err(Error::Method(MethodError::UnknownMethod(format!(
                         "{}/changes",
@@ -24,13 +24,13 @@
 use crate::{
     error::method::MethodError,
     object::{email, Object},
-    parser::{json::Parser, Error, JsonObjectParser, Token},
+    parser::{json::Parser, Error, Ignore, JsonObjectParser, Token},
     request::{
         method::MethodObject,
         reference::{MaybeReference, ResultReference},
         RequestProperty, RequestPropertyParser,
     },
-    types::{id::Id, property::Property, state::State, value::Value},
+    types::{blob::BlobId, id::Id, property::Property, state::State, value::Value},
 };
 
 #[derive(Debug, Clone)]
@@ -52,6 +52,8 @@ pub enum RequestArguments {
     SieveScript,
     VacationResponse,
     Principal,
+    Quota,
+    ShareNotification,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -69,6 +71,69 @@ pub struct GetResponse {
     pub not_found: Vec<Id>,
 }
 
+#[derive(Debug, Clone)]
+pub struct GetBlobRequest {
+    pub account_id: Id,
+    pub ids: MaybeReference<Vec<MaybeReference<BlobId, String>>, ResultReference>,
+    pub properties: Option<Vec<BlobProperty>>,
+    pub offset: Option<usize>,
+    pub length: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlobProperty {
+    Id,
+    Data,
+    DataAsText,
+    DataAsBase64,
+    DigestSha256,
+    DigestSha512,
+    Size,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GetBlobResponse {
+    #[serde(rename = "accountId")]
+    pub account_id: Id,
+
+    pub list: Vec<BlobObject>,
+
+    #[serde(rename = "notFound")]
+    pub not_found: Vec<BlobId>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct BlobObject {
+    pub id: BlobId,
+
+    #[serde(rename = "data:asText")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_as_text: Option<Value>,
+
+    #[serde(rename = "data:asBase64")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_as_base64: Option<Value>,
+
+    #[serde(rename = "digest:sha-256")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest_sha256: Option<String>,
+
+    #[serde(rename = "digest:sha-512")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest_sha512: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+
+    #[serde(rename = "isEncodingProblem")]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_encoding_problem: bool,
+
+    #[serde(rename = "isTruncated")]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_truncated: bool,
+}
+
 impl JsonObjectParser for GetRequest<RequestArguments> {
     fn parse(parser: &mut Parser<'_>) -> crate::parser::Result<Self>
     where
@@ -85,6 +150,8 @@ impl JsonObjectParser for GetRequest<RequestArguments> {
                 MethodObject::SieveScript => RequestArguments::SieveScript,
                 MethodObject::VacationResponse => RequestArguments::VacationResponse,
                 MethodObject::Principal => RequestArguments::Principal,
+                MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::ShareNotification => RequestArguments::ShareNotification,
                 _ => {
                     return Err(Error::Method(MethodError::UnknownMethod(format!(
                         "{}/get",
@@ -190,3 +257,73 @@ impl<T> GetRequest<T> {
         }
     }
 }
+
+impl JsonObjectParser for GetBlobRequest {
+    fn parse(parser: &mut Parser<'_>) -> crate::parser::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut request = GetBlobRequest {
+            account_id: Id::default(),
+            ids: MaybeReference::Value(Vec::new()),
+            properties: None,
+            offset: None,
+            length: None,
+        };
+
+        parser
+            .next_token::<String>()?
+            .assert_jmap(Token::DictStart)?;
+
+        while let Some(key) = parser.next_dict_key::<RequestProperty>()? {
+            match &key.hash[0] {
+                0x0064_4974_6e75_6f63_6361 if !key.is_ref => {
+                    request.account_id = parser.next_token::<Id>()?.unwrap_string("accountId")?;
+                }
+                0x0073_6469 => {
+                    request.ids = if !key.is_ref {
+                        MaybeReference::Value(<Vec<MaybeReference<BlobId, String>>>::parse(parser)?)
+                    } else {
+                        MaybeReference::Reference(ResultReference::parse(parser)?)
+                    };
+                }
+                0x7365_6974_7265_706f_7270 if !key.is_ref => {
+                    request.properties = <Option<Vec<BlobProperty>>>::parse(parser)?;
+                }
+                0x7465_7366_666f if !key.is_ref => {
+                    request.offset = parser
+                        .next_token::<Ignore>()?
+                        .unwrap_usize_or_null("offset")?;
+                }
+                0x6874_676e_656c if !key.is_ref => {
+                    request.length = parser
+                        .next_token::<Ignore>()?
+                        .unwrap_usize_or_null("length")?;
+                }
+                _ => {
+                    parser.skip_token(parser.depth_array, parser.depth_dict)?;
+                }
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+impl JsonObjectParser for BlobProperty {
+    fn parse(parser: &mut Parser<'_>) -> crate::parser::Result<Self>
+    where
+        Self: Sized,
+    {
+        match u128::parse(parser)? {
+            0x6469 => Ok(BlobProperty::Id),
+            0x6174_6164 => Ok(BlobProperty::Data),
+            0x0074_7865_5473_613a_6174_6164 => Ok(BlobProperty::DataAsText),
+            0x0034_3665_7361_4273_613a_6174_6164 => Ok(BlobProperty::DataAsBase64),
+            0x3635_322d_6168_733a_7473_6567_6964 => Ok(BlobProperty::DigestSha256),
+            0x3231_352d_6168_733a_7473_6567_6964 => Ok(BlobProperty::DigestSha512),
+            0x657a_6973 => Ok(BlobProperty::Size),
+            _ => Err(parser.error_value()),
+        }
+    }
+}
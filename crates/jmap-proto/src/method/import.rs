@@ -52,7 +52,7 @@ pub struct ImportEmailRequest {
 
 #[derive(Debug, Clone)]
 pub struct ImportEmail {
-    pub blob_id: BlobId,
+    pub blob_id: MaybeReference<BlobId, String>,
     pub mailbox_ids: MaybeReference<Vec<MaybeReference<Id, String>>, ResultReference>,
     pub keywords: Vec<Keyword>,
     pub received_at: Option<UTCDate>,
@@ -126,7 +126,7 @@ impl JsonObjectParser for ImportEmail {
         Self: Sized,
     {
         let mut request = ImportEmail {
-            blob_id: BlobId::default(),
+            blob_id: MaybeReference::Value(BlobId::default()),
             mailbox_ids: MaybeReference::Value(vec![]),
             keywords: vec![],
             received_at: None,
@@ -139,7 +139,9 @@ impl JsonObjectParser for ImportEmail {
         while let Some(key) = parser.next_dict_key::<RequestProperty>()? {
             match &key.hash[0] {
                 0x6449_626f_6c62 if !key.is_ref => {
-                    request.blob_id = parser.next_token::<BlobId>()?.unwrap_string("blobId")?;
+                    request.blob_id = parser
+                        .next_token::<MaybeReference<BlobId, String>>()?
+                        .unwrap_string("blobId")?;
                 }
                 0x7364_4978_6f62_6c69_616d => {
                     request.mailbox_ids = if !key.is_ref {
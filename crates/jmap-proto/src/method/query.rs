@@ -113,6 +113,10 @@ pub enum Filter {
     HasAnyRole(bool),
     IsSubscribed(bool),
     IsActive(bool),
+    Scope(String),
+    ResourceType(String),
+    ObjectType(String),
+    ObjectAccountId(Id),
     _T(String),
 
     And,
@@ -149,6 +153,9 @@ pub enum SortProperty {
     HasKeyword,
     AllInThreadHaveKeyword,
     SomeInThreadHaveKeyword,
+    Used,
+    Created,
+    Relevance,
     _T(String),
 }
 
@@ -159,6 +166,8 @@ pub enum RequestArguments {
     EmailSubmission,
     SieveScript,
     Principal,
+    Quota,
+    ShareNotification,
 }
 
 impl JsonObjectParser for QueryRequest<RequestArguments> {
@@ -173,6 +182,8 @@ impl JsonObjectParser for QueryRequest<RequestArguments> {
                 MethodObject::EmailSubmission => RequestArguments::EmailSubmission,
                 MethodObject::SieveScript => RequestArguments::SieveScript,
                 MethodObject::Principal => RequestArguments::Principal,
+                MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::ShareNotification => RequestArguments::ShareNotification,
                 _ => {
                     return Err(Error::Method(MethodError::UnknownMethod(format!(
                         "{}/query",
@@ -428,6 +439,22 @@ pub fn parse_filter(parser: &mut Parser) -> crate::parser::Result<Vec<Filter>> {
                         (0x6576_6974_6341_7369, _) => Filter::IsActive(
                             parser.next_token::<String>()?.unwrap_bool("isActive")?,
                         ),
+                        (0x0065_706f_6373, _) => {
+                            Filter::Scope(parser.next_token::<String>()?.unwrap_string("scope")?)
+                        }
+                        (0x6570_7954_6563_7275_6f73_6572, _) => Filter::ResourceType(
+                            parser
+                                .next_token::<String>()?
+                                .unwrap_string("resourceType")?,
+                        ),
+                        (0x6570_7954_7463_656a_626f, _) => Filter::ObjectType(
+                            parser.next_token::<String>()?.unwrap_string("objectType")?,
+                        ),
+                        (0x0064_4974_6e75_6f63_6341_7463_656a_626f, _) => Filter::ObjectAccountId(
+                            parser
+                                .next_token::<Id>()?
+                                .unwrap_string("objectAccountId")?,
+                        ),
                         _ => {
                             if parser.is_eof || parser.skip_string() {
                                 let filter = Filter::_T(
@@ -569,6 +596,9 @@ impl JsonObjectParser for SortProperty {
             0x6472_6f77_7965_4b73_6168 => Ok(SortProperty::HasKeyword),
             0x4b65_7661_4864_6165_7268_546e_496c_6c61 => Ok(SortProperty::AllInThreadHaveKeyword),
             0x6576_6148_6461_6572_6854_6e49_656d_6f73 => Ok(SortProperty::SomeInThreadHaveKeyword),
+            0x6465_7375 => Ok(SortProperty::Used),
+            0x0064_6574_6165_7263 => Ok(SortProperty::Created),
+            0x0065_636e_6176_656c_6572 => Ok(SortProperty::Relevance),
             _ => {
                 if parser.is_eof || parser.skip_string() {
                     Ok(SortProperty::_T(
@@ -629,6 +659,10 @@ impl Display for Filter {
             Filter::HasAnyRole(_) => "hasAnyRole",
             Filter::IsSubscribed(_) => "isSubscribed",
             Filter::IsActive(_) => "isActive",
+            Filter::Scope(_) => "scope",
+            Filter::ResourceType(_) => "resourceType",
+            Filter::ObjectType(_) => "objectType",
+            Filter::ObjectAccountId(_) => "objectAccountId",
             Filter::_T(v) => v.as_str(),
             Filter::And => "and",
             Filter::Or => "or",
@@ -659,6 +693,9 @@ impl Display for SortProperty {
             SortProperty::HasKeyword => "hasKeyword",
             SortProperty::AllInThreadHaveKeyword => "allInThreadHaveKeyword",
             SortProperty::SomeInThreadHaveKeyword => "someInThreadHaveKeyword",
+            SortProperty::Used => "used",
+            SortProperty::Created => "created",
+            SortProperty::Relevance => "relevance",
             SortProperty::_T(s) => s,
         })
     }
@@ -726,6 +763,7 @@ impl Comparator {
             &self.property,
             SortProperty::SentAt
                 | SortProperty::ReceivedAt
+                | SortProperty::Created
                 | SortProperty::Size
                 | SortProperty::From
                 | SortProperty::To
@@ -70,6 +70,8 @@ pub enum RequestArguments {
     PushSubscription,
     SieveScript(sieve::SetArguments),
     VacationResponse,
+    ShareNotification,
+    Thread,
 }
 
 #[derive(Debug, Clone, Default, serde::Serialize)]
@@ -130,6 +132,8 @@ impl JsonObjectParser for SetRequest<RequestArguments> {
                 MethodObject::PushSubscription => RequestArguments::PushSubscription,
                 MethodObject::VacationResponse => RequestArguments::VacationResponse,
                 MethodObject::SieveScript => RequestArguments::SieveScript(Default::default()),
+                MethodObject::ShareNotification => RequestArguments::ShareNotification,
+                MethodObject::Thread => RequestArguments::Thread,
                 _ => {
                     return Err(Error::Method(MethodError::UnknownMethod(format!(
                         "{}/set",
@@ -259,7 +263,8 @@ impl JsonObjectParser for Object<SetValue> {
                     Property::HasAttachment
                     | Property::IsSubscribed
                     | Property::IsEnabled
-                    | Property::IsActive => parser
+                    | Property::IsActive
+                    | Property::IsMuted => parser
                         .next_token::<String>()?
                         .unwrap_bool_or_null("")?
                         .map(|bool| SetValue::Value(Value::Bool(bool)))
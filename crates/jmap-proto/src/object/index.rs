@@ -560,9 +560,7 @@ fn build_batch(
                 }
             }
             (Value::UnsignedInt(bytes), IndexAs::Quota) => {
-                batch.ops.push(Operation::UpdateQuota {
-                    bytes: if set { *bytes as i64 } else { -(*bytes as i64) },
-                });
+                batch.quota(if set { *bytes as i64 } else { -(*bytes as i64) });
             }
             (value, IndexAs::HasProperty) if value != &Value::Null => {
                 batch.ops.push(Operation::Bitmap {
@@ -45,6 +45,9 @@ pub enum MethodObject {
     VacationResponse,
     SieveScript,
     Principal,
+    Quota,
+    Mdn,
+    ShareNotification,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -58,6 +61,9 @@ pub enum MethodFunction {
     Import,
     Parse,
     Validate,
+    Upload,
+    Lookup,
+    Send,
     Echo,
 }
 
@@ -68,6 +74,7 @@ impl JsonObjectParser for MethodName {
     {
         let mut shift = 0;
         let mut obj_hash: u128 = 0;
+        let mut obj_hash_hi: u128 = 0;
         let mut fnc_hash: u128 = 0;
 
         loop {
@@ -78,6 +85,9 @@ impl JsonObjectParser for MethodName {
                 if shift < 128 {
                     obj_hash |= (ch as u128) << shift;
                     shift += 8;
+                } else if shift < 256 {
+                    obj_hash_hi |= (ch as u128) << (shift - 128);
+                    shift += 8;
                 } else {
                     return Err(parser.error_value());
                 }
@@ -98,6 +108,10 @@ impl JsonObjectParser for MethodName {
 
         Ok(MethodName {
             obj: match obj_hash {
+                0x6f69_7461_6369_6669_746f_4e65_7261_6853 if obj_hash_hi == 0x006e => {
+                    MethodObject::ShareNotification
+                }
+                _ if obj_hash_hi != 0 => return Err(parser.error_value()),
                 0x006c_6961_6d45 => MethodObject::Email,
                 0x0078_6f62_6c69_614d => MethodObject::Mailbox,
                 0x6461_6572_6854 => MethodObject::Thread,
@@ -109,6 +123,8 @@ impl JsonObjectParser for MethodName {
                 0x6e6f_6974_7069_7263_7362_7553_6873_7550 => MethodObject::PushSubscription,
                 0x0074_7069_7263_5365_7665_6953 => MethodObject::SieveScript,
                 0x006c_6170_6963_6e69_7250 => MethodObject::Principal,
+                0x0061_746f_7551 => MethodObject::Quota,
+                0x004e_444d => MethodObject::Mdn,
                 0x6572_6f43 => MethodObject::Core,
                 _ => return Err(parser.error_value()),
             },
@@ -122,6 +138,9 @@ impl JsonObjectParser for MethodName {
                 0x7472_6f70_6d69 => MethodFunction::Import,
                 0x0065_7372_6170 => MethodFunction::Parse,
                 0x6574_6164_696c_6176 => MethodFunction::Validate,
+                0x6461_6f6c_7075 => MethodFunction::Upload,
+                0x7075_6b6f_6f6c => MethodFunction::Lookup,
+                0x646e_6573 => MethodFunction::Send,
                 0x6f68_6365 => MethodFunction::Echo,
                 _ => return Err(parser.error_value()),
             },
@@ -151,6 +170,9 @@ impl MethodName {
         match (self.fnc, self.obj) {
             (MethodFunction::Echo, MethodObject::Core) => "Core/echo",
             (MethodFunction::Copy, MethodObject::Blob) => "Blob/copy",
+            (MethodFunction::Get, MethodObject::Blob) => "Blob/get",
+            (MethodFunction::Upload, MethodObject::Blob) => "Blob/upload",
+            (MethodFunction::Lookup, MethodObject::Blob) => "Blob/lookup",
             (MethodFunction::Get, MethodObject::PushSubscription) => "PushSubscription/get",
             (MethodFunction::Set, MethodObject::PushSubscription) => "PushSubscription/set",
             (MethodFunction::Get, MethodObject::Mailbox) => "Mailbox/get",
@@ -160,6 +182,7 @@ impl MethodName {
             (MethodFunction::Set, MethodObject::Mailbox) => "Mailbox/set",
             (MethodFunction::Get, MethodObject::Thread) => "Thread/get",
             (MethodFunction::Changes, MethodObject::Thread) => "Thread/changes",
+            (MethodFunction::Set, MethodObject::Thread) => "Thread/set",
             (MethodFunction::Get, MethodObject::Email) => "Email/get",
             (MethodFunction::Changes, MethodObject::Email) => "Email/changes",
             (MethodFunction::Query, MethodObject::Email) => "Email/query",
@@ -188,6 +211,20 @@ impl MethodName {
             (MethodFunction::Get, MethodObject::Principal) => "Principal/get",
             (MethodFunction::Set, MethodObject::Principal) => "Principal/set",
             (MethodFunction::Query, MethodObject::Principal) => "Principal/query",
+            (MethodFunction::Get, MethodObject::Quota) => "Quota/get",
+            (MethodFunction::Changes, MethodObject::Quota) => "Quota/changes",
+            (MethodFunction::Query, MethodObject::Quota) => "Quota/query",
+            (MethodFunction::Send, MethodObject::Mdn) => "MDN/send",
+            (MethodFunction::Parse, MethodObject::Mdn) => "MDN/parse",
+            (MethodFunction::Get, MethodObject::ShareNotification) => "ShareNotification/get",
+            (MethodFunction::Changes, MethodObject::ShareNotification) => {
+                "ShareNotification/changes"
+            }
+            (MethodFunction::Set, MethodObject::ShareNotification) => "ShareNotification/set",
+            (MethodFunction::Query, MethodObject::ShareNotification) => "ShareNotification/query",
+            (MethodFunction::QueryChanges, MethodObject::ShareNotification) => {
+                "ShareNotification/queryChanges"
+            }
             _ => "error",
         }
     }
@@ -204,6 +241,9 @@ impl Display for MethodObject {
             MethodObject::PushSubscription => "PushSubscription",
             MethodObject::SieveScript => "SieveScript",
             MethodObject::Principal => "Principal",
+            MethodObject::Quota => "Quota",
+            MethodObject::Mdn => "MDN",
+            MethodObject::ShareNotification => "ShareNotification",
             MethodObject::Core => "Core",
             MethodObject::Mailbox => "Mailbox",
             MethodObject::Thread => "Thread",
@@ -38,13 +38,16 @@ use crate::{
     method::{
         changes::ChangesRequest,
         copy::{self, CopyBlobRequest, CopyRequest},
-        get::{self, GetRequest},
+        get::{self, GetBlobRequest, GetRequest},
         import::ImportEmailRequest,
+        lookup::LookupBlobRequest,
+        mdn::{MdnParseRequest, MdnSendRequest},
         parse::ParseEmailRequest,
         query::{self, QueryRequest},
         query_changes::QueryChangesRequest,
         search_snippet::GetSearchSnippetRequest,
         set::{self, SetRequest},
+        upload::UploadBlobRequest,
         validate::ValidateSieveScriptRequest,
     },
     parser::{json::Parser, JsonObjectParser},
@@ -80,12 +83,17 @@ pub enum RequestMethod {
     Changes(ChangesRequest),
     Copy(CopyRequest<copy::RequestArguments>),
     CopyBlob(CopyBlobRequest),
+    GetBlob(GetBlobRequest),
+    UploadBlob(UploadBlobRequest),
+    LookupBlob(LookupBlobRequest),
     ImportEmail(ImportEmailRequest),
     ParseEmail(ParseEmailRequest),
     QueryChanges(QueryChangesRequest),
     Query(QueryRequest<query::RequestArguments>),
     SearchSnippet(GetSearchSnippetRequest),
     ValidateScript(ValidateSieveScriptRequest),
+    SendMdn(MdnSendRequest),
+    ParseMdn(MdnParseRequest),
     Echo(Echo),
     Error(MethodError),
 }
@@ -31,13 +31,16 @@ use crate::{
     method::{
         changes::ChangesRequest,
         copy::{CopyBlobRequest, CopyRequest},
-        get::GetRequest,
+        get::{GetBlobRequest, GetRequest},
         import::ImportEmailRequest,
+        lookup::LookupBlobRequest,
+        mdn::{MdnParseRequest, MdnSendRequest},
         parse::ParseEmailRequest,
         query::QueryRequest,
         query_changes::QueryChangesRequest,
         search_snippet::GetSearchSnippetRequest,
         set::SetRequest,
+        upload::UploadBlobRequest,
         validate::ValidateSieveScriptRequest,
     },
     parser::{json::Parser, Error, Ignore, JsonObjectParser, Token},
@@ -129,13 +132,15 @@ impl Request {
                         let start_depth_dict = parser.depth_dict;
 
                         let method = match (&method_name.fnc, &method_name.obj) {
+                            (MethodFunction::Get, MethodObject::SearchSnippet) => {
+                                GetSearchSnippetRequest::parse(parser)
+                                    .map(RequestMethod::SearchSnippet)
+                            }
+                            (MethodFunction::Get, MethodObject::Blob) => {
+                                GetBlobRequest::parse(parser).map(RequestMethod::GetBlob)
+                            }
                             (MethodFunction::Get, _) => {
-                                if method_name.obj != MethodObject::SearchSnippet {
-                                    GetRequest::parse(parser).map(RequestMethod::Get)
-                                } else {
-                                    GetSearchSnippetRequest::parse(parser)
-                                        .map(RequestMethod::SearchSnippet)
-                                }
+                                GetRequest::parse(parser).map(RequestMethod::Get)
                             }
                             (MethodFunction::Query, _) => {
                                 QueryRequest::parse(parser).map(RequestMethod::Query)
@@ -155,12 +160,24 @@ impl Request {
                             (MethodFunction::Copy, MethodObject::Blob) => {
                                 CopyBlobRequest::parse(parser).map(RequestMethod::CopyBlob)
                             }
+                            (MethodFunction::Upload, MethodObject::Blob) => {
+                                UploadBlobRequest::parse(parser).map(RequestMethod::UploadBlob)
+                            }
+                            (MethodFunction::Lookup, MethodObject::Blob) => {
+                                LookupBlobRequest::parse(parser).map(RequestMethod::LookupBlob)
+                            }
                             (MethodFunction::Import, MethodObject::Email) => {
                                 ImportEmailRequest::parse(parser).map(RequestMethod::ImportEmail)
                             }
                             (MethodFunction::Parse, MethodObject::Email) => {
                                 ParseEmailRequest::parse(parser).map(RequestMethod::ParseEmail)
                             }
+                            (MethodFunction::Send, MethodObject::Mdn) => {
+                                MdnSendRequest::parse(parser).map(RequestMethod::SendMdn)
+                            }
+                            (MethodFunction::Parse, MethodObject::Mdn) => {
+                                MdnParseRequest::parse(parser).map(RequestMethod::ParseMdn)
+                            }
                             (MethodFunction::Validate, MethodObject::SieveScript) => {
                                 ValidateSieveScriptRequest::parse(parser)
                                     .map(RequestMethod::ValidateScript)
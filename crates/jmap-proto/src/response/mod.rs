@@ -31,13 +31,16 @@ use crate::{
     method::{
         changes::ChangesResponse,
         copy::{CopyBlobResponse, CopyResponse},
-        get::GetResponse,
+        get::{GetBlobResponse, GetResponse},
         import::ImportEmailResponse,
+        lookup::LookupBlobResponse,
+        mdn::{MdnParseResponse, MdnSendResponse},
         parse::ParseEmailResponse,
         query::QueryResponse,
         query_changes::QueryChangesResponse,
         search_snippet::GetSearchSnippetResponse,
         set::SetResponse,
+        upload::UploadBlobResponse,
         validate::ValidateSieveScriptResponse,
     },
     request::{echo::Echo, method::MethodName, Call},
@@ -54,12 +57,17 @@ pub enum ResponseMethod {
     Changes(ChangesResponse),
     Copy(CopyResponse),
     CopyBlob(CopyBlobResponse),
+    GetBlob(GetBlobResponse),
+    UploadBlob(UploadBlobResponse),
+    LookupBlob(LookupBlobResponse),
     ImportEmail(ImportEmailResponse),
     ParseEmail(ParseEmailResponse),
     QueryChanges(QueryChangesResponse),
     Query(QueryResponse),
     SearchSnippet(GetSearchSnippetResponse),
     ValidateScript(ValidateSieveScriptResponse),
+    SendMdn(MdnSendResponse),
+    ParseMdn(MdnParseResponse),
     Echo(Echo),
     Error(MethodError),
 }
@@ -155,6 +163,24 @@ impl From<CopyBlobResponse> for ResponseMethod {
     }
 }
 
+impl From<GetBlobResponse> for ResponseMethod {
+    fn from(get_blob: GetBlobResponse) -> Self {
+        ResponseMethod::GetBlob(get_blob)
+    }
+}
+
+impl From<UploadBlobResponse> for ResponseMethod {
+    fn from(upload_blob: UploadBlobResponse) -> Self {
+        ResponseMethod::UploadBlob(upload_blob)
+    }
+}
+
+impl From<LookupBlobResponse> for ResponseMethod {
+    fn from(lookup_blob: LookupBlobResponse) -> Self {
+        ResponseMethod::LookupBlob(lookup_blob)
+    }
+}
+
 impl From<ImportEmailResponse> for ResponseMethod {
     fn from(import_email: ImportEmailResponse) -> Self {
         ResponseMethod::ImportEmail(import_email)
@@ -191,6 +217,18 @@ impl From<ValidateSieveScriptResponse> for ResponseMethod {
     }
 }
 
+impl From<MdnSendResponse> for ResponseMethod {
+    fn from(send_mdn: MdnSendResponse) -> Self {
+        ResponseMethod::SendMdn(send_mdn)
+    }
+}
+
+impl From<MdnParseResponse> for ResponseMethod {
+    fn from(parse_mdn: MdnParseResponse) -> Self {
+        ResponseMethod::ParseMdn(parse_mdn)
+    }
+}
+
 impl<T: Into<ResponseMethod>> From<Result<T, MethodError>> for ResponseMethod {
     fn from(result: Result<T, MethodError>) -> Self {
         match result {
@@ -27,13 +27,14 @@ use utils::map::vec_map::VecMap;
 
 use crate::{
     error::{method::MethodError, set::SetError},
-    method::{copy::CopyResponse, set::SetResponse},
+    method::{copy::CopyResponse, set::SetResponse, upload::DataSourceObject},
     object::Object,
     request::{
         reference::{MaybeReference, ResultReference},
         RequestMethod,
     },
     types::{
+        blob::BlobId,
         id::Id,
         property::Property,
         value::{MaybePatchValue, SetValue, Value},
@@ -161,8 +162,13 @@ impl Response {
                 }
             }
             RequestMethod::ImportEmail(request) => {
-                // Resolve email mailbox references
                 for email in request.emails.values_mut() {
+                    // Resolve blob creation id references
+                    if let MaybeReference::Reference(ir) = &email.blob_id {
+                        email.blob_id = MaybeReference::Value(self.eval_blob_id_reference(ir)?);
+                    }
+
+                    // Resolve email mailbox references
                     match &mut email.mailbox_ids {
                         MaybeReference::Reference(rr) => {
                             email.mailbox_ids = MaybeReference::Value(
@@ -183,6 +189,30 @@ impl Response {
                     }
                 }
             }
+            RequestMethod::GetBlob(request) => {
+                // Resolve blobId references
+                self.eval_blob_id_references(&mut request.ids)?;
+            }
+            RequestMethod::LookupBlob(request) => {
+                // Resolve blobId references
+                self.eval_blob_id_references(&mut request.ids)?;
+            }
+            RequestMethod::UploadBlob(request) => {
+                // Resolve blob creation id references from previous calls,
+                // references to blobs created in this call are resolved during upload
+                let create_ids = request.create.keys().cloned().collect::<Vec<_>>();
+                for object in request.create.values_mut() {
+                    for data_source in &mut object.data {
+                        if let DataSourceObject::Id { id, .. } = data_source {
+                            if let MaybeReference::Reference(ir) = id {
+                                if !create_ids.contains(ir) {
+                                    *id = MaybeReference::Value(self.eval_blob_id_reference(ir)?);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
             RequestMethod::SearchSnippet(request) => {
                 // Resolve emailIds references
                 if let MaybeReference::Reference(reference) = &request.email_ids {
@@ -278,6 +308,46 @@ impl Response {
         }
     }
 
+    fn eval_blob_id_reference(&self, ir: &str) -> Result<BlobId, MethodError> {
+        for response in &self.method_responses {
+            if let ResponseMethod::UploadBlob(response) = &response.method {
+                if let Some(obj) = response.created.get(ir) {
+                    return Ok(obj.id.clone());
+                }
+            }
+        }
+
+        Err(MethodError::InvalidResultReference(format!(
+            "Blob reference {ir:?} not found."
+        )))
+    }
+
+    fn eval_blob_id_references(
+        &self,
+        ids: &mut MaybeReference<Vec<MaybeReference<BlobId, String>>, ResultReference>,
+    ) -> Result<(), MethodError> {
+        match ids {
+            MaybeReference::Reference(rr) => {
+                *ids = MaybeReference::Value(
+                    self.eval_result_references(rr)
+                        .unwrap_blob_ids(rr)?
+                        .into_iter()
+                        .map(MaybeReference::Value)
+                        .collect(),
+                );
+            }
+            MaybeReference::Value(values) => {
+                for value in values {
+                    if let MaybeReference::Reference(ir) = value {
+                        *value = MaybeReference::Value(self.eval_blob_id_reference(ir)?);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn eval_object_references(
         &self,
         obj: &mut Object<SetValue>,
@@ -420,6 +490,38 @@ impl EvalResult {
         }
     }
 
+    pub fn unwrap_blob_ids(self, rr: &ResultReference) -> Result<Vec<BlobId>, MethodError> {
+        if let EvalResult::Values(values) = self {
+            let mut ids = Vec::with_capacity(values.len());
+            for value in values {
+                match value {
+                    Value::BlobId(id) => ids.push(id),
+                    Value::List(list) => {
+                        for value in list {
+                            if let Value::BlobId(id) = value {
+                                ids.push(id);
+                            } else {
+                                return Err(MethodError::InvalidResultReference(format!(
+                                    "Failed to evaluate {rr} result reference."
+                                )));
+                            }
+                        }
+                    }
+                    _ => {
+                        return Err(MethodError::InvalidResultReference(format!(
+                            "Failed to evaluate {rr} result reference."
+                        )))
+                    }
+                }
+            }
+            Ok(ids)
+        } else {
+            Err(MethodError::InvalidResultReference(format!(
+                "Failed to evaluate {rr} result reference."
+            )))
+        }
+    }
+
     pub fn unwrap_properties(self, rr: &ResultReference) -> Result<Vec<Property>, MethodError> {
         if let EvalResult::Properties(properties) = self {
             Ok(properties)
@@ -33,7 +33,10 @@ use utils::codec::{
     leb128::{Leb128Iterator, Leb128Writer},
 };
 
-use crate::parser::{base32::JsonBase32Reader, json::Parser, JsonObjectParser};
+use crate::{
+    parser::{base32::JsonBase32Reader, json::Parser, JsonObjectParser},
+    request::reference::MaybeReference,
+};
 
 use super::collection::Collection;
 
@@ -111,6 +114,20 @@ impl JsonObjectParser for BlobId {
     }
 }
 
+impl JsonObjectParser for MaybeReference<BlobId, String> {
+    fn parse(parser: &mut Parser<'_>) -> crate::parser::Result<Self>
+    where
+        Self: Sized,
+    {
+        if parser.bytes.get(parser.pos) != Some(&b'#') {
+            BlobId::parse(parser).map(MaybeReference::Value)
+        } else {
+            parser.next_char();
+            String::parse(parser).map(MaybeReference::Reference)
+        }
+    }
+}
+
 impl BlobId {
     pub fn new(kind: BlobKind) -> Self {
         BlobId {
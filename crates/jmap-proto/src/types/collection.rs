@@ -38,7 +38,9 @@ pub enum Collection {
     SieveScript = 5,
     PushSubscription = 6,
     Principal = 7,
-    None = 8,
+    ShareNotification = 8,
+    Quota = 9,
+    None = 10,
 }
 
 impl From<u8> for Collection {
@@ -52,6 +54,8 @@ impl From<u8> for Collection {
             5 => Collection::SieveScript,
             6 => Collection::PushSubscription,
             7 => Collection::Principal,
+            8 => Collection::ShareNotification,
+            9 => Collection::Quota,
             _ => Collection::None,
         }
     }
@@ -68,6 +72,8 @@ impl From<u64> for Collection {
             5 => Collection::SieveScript,
             6 => Collection::PushSubscription,
             7 => Collection::Principal,
+            8 => Collection::ShareNotification,
+            9 => Collection::Quota,
             _ => Collection::None,
         }
     }
@@ -95,6 +101,8 @@ impl TryFrom<Collection> for TypeState {
             Collection::Thread => Ok(TypeState::Thread),
             Collection::Identity => Ok(TypeState::Identity),
             Collection::EmailSubmission => Ok(TypeState::EmailSubmission),
+            Collection::ShareNotification => Ok(TypeState::ShareNotification),
+            Collection::Quota => Ok(TypeState::Quota),
             _ => Err(()),
         }
     }
@@ -111,6 +119,8 @@ impl Display for Collection {
             Collection::EmailSubmission => write!(f, "emailSubmission"),
             Collection::SieveScript => write!(f, "sieveScript"),
             Collection::Principal => write!(f, "principal"),
+            Collection::ShareNotification => write!(f, "shareNotification"),
+            Collection::Quota => write!(f, "quota"),
             Collection::None => write!(f, ""),
         }
     }
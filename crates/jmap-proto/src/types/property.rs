@@ -130,6 +130,22 @@ pub enum Property {
     MayCreateChild,
     MayRename,
     MaySubmit,
+    Used,
+    HardLimit,
+    WarnLimit,
+    SoftLimit,
+    Scope,
+    ResourceType,
+    IsVerified,
+    Created,
+    ChangedBy,
+    ObjectType,
+    ObjectAccountId,
+    ObjectId,
+    OldRights,
+    NewRights,
+    PrincipalId,
+    IsMuted,
     _T(String),
 }
 
@@ -340,6 +356,8 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x63 => Property::Cc,
             0x7465_7372_6168 => Property::Charset,
             0x6469 => Property::Cid,
+            0x6465_7461_6572 => Property::Created,
+            0x7942_6465_676e_6168 => Property::ChangedBy,
             _ => return None,
         },
         b'd' => match hash {
@@ -368,6 +386,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x7372_6564_6165 => Property::Headers,
             0x0079_646f_426c_6d74 => Property::HtmlBody,
             0x6572_7574_616e_6769_536c_6d74 => Property::HtmlSignature,
+            0x7469_6d69_4c64_7261 => Property::HardLimit,
             _ => return None,
         },
         b'i' => match hash {
@@ -377,6 +396,8 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0065_7669_7463_4173 => Property::IsActive,
             0x6465_6c62_616e_4573 => Property::IsEnabled,
             0x0064_6562_6972_6373_6275_5373 => Property::IsSubscribed,
+            0x0064_6569_6669_7265_5673 => Property::IsVerified,
+            0x6465_7475_4d73 => Property::IsMuted,
             _ => return None,
         },
         b'k' => match hash {
@@ -400,6 +421,14 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
         },
         b'n' => match hash {
             0x0065_6d61 => Property::Name,
+            0x7374_6867_6952_7765 => Property::NewRights,
+            _ => return None,
+        },
+        b'o' => match hash {
+            0x0065_7079_5474_6365_6a62 => Property::ObjectType,
+            0x6449_746e_756f_6363_4174_6365_6a62 => Property::ObjectAccountId,
+            0x0064_4974_6365_6a62 => Property::ObjectId,
+            0x7374_6867_6952_646c => Property::OldRights,
             _ => return None,
         },
         b'p' => match hash {
@@ -407,6 +436,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0064_4974_7261 => Property::PartId,
             0x6572_7574_6369 => Property::Picture,
             0x7765_6976_6572 => Property::Preview,
+            0x6449_6c61_7069_636e_6972 => Property::PrincipalId,
             _ => return None,
         },
         b'q' => match hash {
@@ -417,6 +447,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0074_4164_6576_6965_6365 => Property::ReceivedAt,
             0x0073_6563_6e65_7265_6665 => Property::References,
             0x6f54_796c_7065 => Property::ReplyTo,
+            0x0065_7079_5465_6372_756f_7365 => Property::ResourceType,
             0x0065_6c6f => Property::Role,
             _ => return None,
         },
@@ -429,6 +460,8 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x7265_6472_4f74_726f => Property::SortOrder,
             0x7463_656a_6275 => Property::Subject,
             0x7374_7261_5062_7573 => Property::SubParts,
+            0x6570_6f63 => Property::Scope,
+            0x7469_6d69_4c74_666f => Property::SoftLimit,
             _ => return None,
         },
         b't' => match hash {
@@ -449,12 +482,17 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0073_6c69_616d_4564_6165_726e => Property::UnreadEmails,
             0x7364_6165_7268_5464_6165_726e => Property::UnreadThreads,
             0x6c72 => Property::Url,
+            0x0064_6573 => Property::Used,
             _ => return None,
         },
         b'v' => match hash {
             0x0065_646f_436e_6f69_7461_6369_6669_7265 => Property::VerificationCode,
             _ => return None,
         },
+        b'w' => match hash {
+            0x7469_6d69_4c6e_7261 => Property::WarnLimit,
+            _ => return None,
+        },
         _ => return None,
     })
 }
@@ -810,6 +848,22 @@ impl Display for Property {
             Property::MayCreateChild => write!(f, "mayCreateChild"),
             Property::MayRename => write!(f, "mayRename"),
             Property::MaySubmit => write!(f, "maySubmit"),
+            Property::Used => write!(f, "used"),
+            Property::HardLimit => write!(f, "hardLimit"),
+            Property::WarnLimit => write!(f, "warnLimit"),
+            Property::SoftLimit => write!(f, "softLimit"),
+            Property::Scope => write!(f, "scope"),
+            Property::ResourceType => write!(f, "resourceType"),
+            Property::IsVerified => write!(f, "isVerified"),
+            Property::Created => write!(f, "created"),
+            Property::ChangedBy => write!(f, "changedBy"),
+            Property::ObjectType => write!(f, "objectType"),
+            Property::ObjectAccountId => write!(f, "objectAccountId"),
+            Property::ObjectId => write!(f, "objectId"),
+            Property::OldRights => write!(f, "oldRights"),
+            Property::NewRights => write!(f, "newRights"),
+            Property::PrincipalId => write!(f, "principalId"),
+            Property::IsMuted => write!(f, "isMuted"),
             Property::_T(s) => write!(f, "{s}"),
         }
     }
@@ -983,6 +1037,22 @@ impl From<&Property> for u8 {
             Property::Id => 94,
             Property::IdentityId => 95,
             Property::InReplyTo => 96,
+            Property::Used => 98,
+            Property::HardLimit => 99,
+            Property::WarnLimit => 100,
+            Property::SoftLimit => 101,
+            Property::Scope => 102,
+            Property::ResourceType => 103,
+            Property::IsVerified => 104,
+            Property::Created => 105,
+            Property::ChangedBy => 106,
+            Property::ObjectType => 107,
+            Property::ObjectAccountId => 108,
+            Property::ObjectId => 109,
+            Property::OldRights => 110,
+            Property::NewRights => 111,
+            Property::PrincipalId => 112,
+            Property::IsMuted => 113,
             Property::_T(_) => 97,
         }
     }
@@ -1114,6 +1184,22 @@ impl SerializeInto for Property {
             Property::Id => 94,
             Property::IdentityId => 95,
             Property::InReplyTo => 96,
+            Property::Used => 98,
+            Property::HardLimit => 99,
+            Property::WarnLimit => 100,
+            Property::SoftLimit => 101,
+            Property::Scope => 102,
+            Property::ResourceType => 103,
+            Property::IsVerified => 104,
+            Property::Created => 105,
+            Property::ChangedBy => 106,
+            Property::ObjectType => 107,
+            Property::ObjectAccountId => 108,
+            Property::ObjectId => 109,
+            Property::OldRights => 110,
+            Property::NewRights => 111,
+            Property::PrincipalId => 112,
+            Property::IsMuted => 113,
             Property::_T(value) => {
                 buf.push(97);
                 value.serialize_into(buf);
@@ -1227,6 +1313,22 @@ impl DeserializeFrom for Property {
             94 => Some(Property::Id),
             95 => Some(Property::IdentityId),
             96 => Some(Property::InReplyTo),
+            98 => Some(Property::Used),
+            99 => Some(Property::HardLimit),
+            100 => Some(Property::WarnLimit),
+            101 => Some(Property::SoftLimit),
+            102 => Some(Property::Scope),
+            103 => Some(Property::ResourceType),
+            104 => Some(Property::IsVerified),
+            105 => Some(Property::Created),
+            106 => Some(Property::ChangedBy),
+            107 => Some(Property::ObjectType),
+            108 => Some(Property::ObjectAccountId),
+            109 => Some(Property::ObjectId),
+            110 => Some(Property::OldRights),
+            111 => Some(Property::NewRights),
+            112 => Some(Property::PrincipalId),
+            113 => Some(Property::IsMuted),
             97 => String::deserialize_from(bytes).map(Property::_T),
             _ => None,
         }
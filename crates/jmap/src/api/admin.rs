@@ -21,16 +21,45 @@
  * for more details.
 */
 
+use std::{
+    path::{Path, PathBuf},
+    sync::{atomic::Ordering, Arc},
+};
+
+use directory::{
+    internal::manage::{ManageError, PrincipalData, PrincipalUpdate},
+    Type,
+};
+use http_body_util::{combinators::BoxBody, StreamBody};
+use hyper::{
+    body::{Bytes, Frame},
+    header, Method, StatusCode,
+};
 use jmap_proto::{
+    error::request::RequestError,
     object::{index::ObjectIndexBuilder, Object},
     types::{collection::Collection, property::Property, value::Value},
 };
 use store::{
+    backup::BackupManifest,
+    migrate::{MigrationProgress, MigrationReport},
+    parking_lot::Mutex,
     write::{assert::HashedValue, BatchBuilder, Operation, ValueClass},
-    BitmapKey, Serialize, ValueKey,
+    BitmapKey, Serialize, Store, ValueKey,
 };
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use utils::map::ttl_dashmap::TtlMap;
 
-use crate::{auth::authenticate::AccountKey, mailbox::set::SCHEMA, JMAP};
+use crate::{
+    auth::{authenticate::AccountKey, AccessToken},
+    mailbox::set::SCHEMA,
+    JMAP,
+};
+
+use super::{
+    http::{fetch_body, ToHttpResponse},
+    HttpRequest, HttpResponse, JsonResponse,
+};
 
 impl JMAP {
     pub async fn delete_account(&self, account_name: &str, account_id: u32) -> store::Result<()> {
@@ -124,3 +153,488 @@ impl JMAP {
         Ok(())
     }
 }
+
+impl JMAP {
+    pub async fn handle_directory_request(
+        &self,
+        req: &mut HttpRequest,
+        path: (&str, &str, Option<&str>),
+        access_token: &AccessToken,
+    ) -> HttpResponse {
+        let directory = if let Some(directory) = &self.internal_directory {
+            directory
+        } else {
+            return RequestError::blank(
+                StatusCode::BAD_REQUEST.as_u16(),
+                "Not configured",
+                "No internal directory is configured.",
+            )
+            .into_http_response();
+        };
+        let method = req.method().clone();
+
+        let result = match (&method, path) {
+            (&Method::GET, ("principal", "list", _)) => {
+                let typ = req.uri().query().and_then(|q| {
+                    form_urlencoded::parse(q.as_bytes())
+                        .find(|(k, _)| k == "type")
+                        .and_then(|(_, v)| {
+                            serde_json::from_value::<Type>(serde_json::Value::String(
+                                v.into_owned(),
+                            ))
+                            .ok()
+                        })
+                });
+                directory
+                    .list_principals(typ)
+                    .await
+                    .map(|names| JsonResponse::new(names).into_http_response())
+            }
+            (&Method::GET, ("principal", "get", Some(name))) => {
+                match directory.get_principal(name).await {
+                    Ok(Some(principal)) => Ok(JsonResponse::new(principal).into_http_response()),
+                    Ok(None) => Err(ManageError::NotFound(format!(
+                        "Principal {name:?} not found."
+                    ))),
+                    Err(err) => Err(err),
+                }
+            }
+            (&Method::POST, ("principal", "create", _)) => {
+                match parse_body::<PrincipalData>(req, access_token).await {
+                    Ok(principal) => directory.create_principal(principal).await,
+                    Err(err) => Err(err),
+                }
+                .map(|_| success())
+            }
+            (&Method::POST, ("principal", "update", Some(name))) => {
+                match parse_body::<PrincipalUpdate>(req, access_token).await {
+                    Ok(update) => directory.update_principal(name, update).await,
+                    Err(err) => Err(err),
+                }
+                .map(|_| success())
+            }
+            (&Method::GET, ("principal", "delete", Some(name))) => {
+                self.invalidate_account_cache(name).await;
+                match directory.delete_principal(name).await {
+                    Ok(_) => {
+                        // Remove the account data as well
+                        match self.try_get_account_id(name).await {
+                            Ok(Some(account_id)) => self
+                                .delete_account(name, account_id)
+                                .await
+                                .map(|_| success())
+                                .map_err(ManageError::from),
+                            Ok(None) => Ok(success()),
+                            Err(_) => Err(ManageError::Internal(
+                                "Failed to obtain account id.".to_string(),
+                            )),
+                        }
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+            (&Method::GET, ("domain", "list", _)) => directory
+                .list_domains()
+                .await
+                .map(|domains| JsonResponse::new(domains).into_http_response()),
+            (&Method::GET, ("domain", "create", Some(domain))) => {
+                directory.create_domain(domain).await.map(|_| success())
+            }
+            (&Method::GET, ("domain", "delete", Some(domain))) => {
+                directory.delete_domain(domain).await.map(|_| success())
+            }
+            _ => return RequestError::not_found().into_http_response(),
+        };
+
+        // Discard cached sessions and access tokens of modified principals
+        if let (Ok(_), ("principal", "update", Some(name))) = (&result, path) {
+            self.invalidate_account_cache(name).await;
+        }
+
+        match result {
+            Ok(response) => response,
+            Err(err) => {
+                let (status, title) = match &err {
+                    ManageError::NotFound(_) => (StatusCode::NOT_FOUND, "Not found"),
+                    ManageError::AlreadyExists(_) => (StatusCode::CONFLICT, "Already exists"),
+                    ManageError::Invalid(_) => (StatusCode::BAD_REQUEST, "Invalid parameters"),
+                    ManageError::Internal(_) => {
+                        (StatusCode::INTERNAL_SERVER_ERROR, "Directory update failed")
+                    }
+                };
+                RequestError::blank(status.as_u16(), title, err.to_string()).into_http_response()
+            }
+        }
+    }
+
+    pub async fn handle_store_request(&self, req: &HttpRequest, action: &str) -> HttpResponse {
+        let params = req
+            .uri()
+            .query()
+            .map(|q| {
+                form_urlencoded::parse(q.as_bytes())
+                    .into_owned()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let mut paths = params
+            .iter()
+            .filter(|(k, _)| k == "path")
+            .map(|(_, v)| PathBuf::from(v))
+            .collect::<Vec<_>>();
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.as_str())
+        };
+
+        if paths.is_empty() {
+            return RequestError::blank(
+                StatusCode::BAD_REQUEST.as_u16(),
+                "Invalid parameters",
+                "Expected archive path",
+            )
+            .into_http_response();
+        }
+
+        match action {
+            "backup" => {
+                let previous = param("previous").map(PathBuf::from);
+                match self
+                    .store
+                    .backup(paths.pop().unwrap(), previous.as_deref())
+                    .await
+                {
+                    Ok(manifest) => JsonResponse::new(serde_json::json!({
+                        "id": manifest.id,
+                        "baseId": manifest.base_id,
+                        "created": manifest.created,
+                        "accounts": manifest.accounts.len(),
+                        "included": manifest.accounts.values().filter(|a| a.included).count(),
+                    }))
+                    .into_http_response(),
+                    Err(err) => RequestError::blank(
+                        StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                        "Backup failed",
+                        err.to_string(),
+                    )
+                    .into_http_response(),
+                }
+            }
+            "restore" => {
+                let account = if let Some(account_name) = param("account") {
+                    match self.try_get_account_id(account_name).await {
+                        Ok(Some(account_id)) => Some((account_name, account_id)),
+                        Ok(None) => {
+                            return RequestError::blank(
+                                StatusCode::NOT_FOUND.as_u16(),
+                                "Not found",
+                                "Account not found.",
+                            )
+                            .into_http_response()
+                        }
+                        Err(_) => {
+                            return RequestError::internal_server_error().into_http_response()
+                        }
+                    }
+                } else {
+                    None
+                };
+
+                match self
+                    .store
+                    .restore(&paths, account.map(|(_, account_id)| account_id))
+                    .await
+                {
+                    Ok(_) => {
+                        if let Some((account_name, _)) = account {
+                            self.invalidate_account_cache(account_name).await;
+                        }
+                        success()
+                    }
+                    Err(err) => RequestError::blank(
+                        StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                        "Restore failed",
+                        err.to_string(),
+                    )
+                    .into_http_response(),
+                }
+            }
+            _ => RequestError::not_found().into_http_response(),
+        }
+    }
+
+    pub async fn handle_store_export(
+        &self,
+        req: &mut HttpRequest,
+        access_token: &AccessToken,
+    ) -> HttpResponse {
+        // The body contains the manifest of the last migrated snapshot, if any
+        let base = match fetch_body(req, self.config.request_max_size, access_token).await {
+            Some(bytes) if bytes.is_empty() => None,
+            Some(bytes) => match BackupManifest::deserialize(&bytes) {
+                Ok(base) => Some(base),
+                Err(err) => {
+                    return RequestError::blank(
+                        StatusCode::BAD_REQUEST.as_u16(),
+                        "Invalid parameters",
+                        err.to_string(),
+                    )
+                    .into_http_response()
+                }
+            },
+            None => {
+                return RequestError::blank(
+                    StatusCode::BAD_REQUEST.as_u16(),
+                    "Invalid parameters",
+                    "Request body too large.",
+                )
+                .into_http_response()
+            }
+        };
+
+        // The archive is streamed to the client while it is being written
+        let (writer, mut reader) = tokio::io::duplex(STREAM_BUFFER_SIZE);
+        let store = self.store.clone();
+        tokio::spawn(async move {
+            if let Err(err) = store.export(writer, base).await {
+                tracing::warn!(
+                    context = "store",
+                    event = "error",
+                    reason = %err,
+                    "Failed to export store."
+                );
+            }
+        });
+
+        hyper::Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .body(BoxBody::new(StreamBody::new(async_stream::stream! {
+                let mut buf = vec![0u8; STREAM_BUFFER_SIZE];
+                loop {
+                    match reader.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(bytes_read) => {
+                            yield Ok(Frame::data(Bytes::copy_from_slice(&buf[..bytes_read])));
+                        }
+                    }
+                }
+            })))
+            .unwrap()
+    }
+
+    pub async fn handle_store_migrate(
+        &self,
+        req: &mut HttpRequest,
+        access_token: &AccessToken,
+    ) -> HttpResponse {
+        let request = match parse_body::<MigrationRequest>(req, access_token).await {
+            Ok(request) if request.paths.is_empty() != request.source.is_none() => request,
+            Ok(_) => {
+                return RequestError::blank(
+                    StatusCode::BAD_REQUEST.as_u16(),
+                    "Invalid parameters",
+                    "Expected either archive paths or a source server",
+                )
+                .into_http_response()
+            }
+            Err(err) => {
+                return RequestError::blank(
+                    StatusCode::BAD_REQUEST.as_u16(),
+                    "Invalid parameters",
+                    err.to_string(),
+                )
+                .into_http_response()
+            }
+        };
+
+        // Only one migration can run at a time
+        let task = Arc::new(MigrationTask::default());
+        {
+            let mut migration = self.migration.lock();
+            if migration
+                .as_ref()
+                .map_or(false, |task| task.result.lock().is_none())
+            {
+                return RequestError::blank(
+                    StatusCode::CONFLICT.as_u16(),
+                    "Conflict",
+                    "A migration is already running.",
+                )
+                .into_http_response();
+            }
+            *migration = task.clone().into();
+        }
+
+        let store = self.store.clone();
+        tokio::spawn(async move {
+            let result = if let Some(source) = request.source {
+                migrate_from_source(&store, source, &request.checkpoint, &task.progress).await
+            } else {
+                store
+                    .migrate(&request.paths, &request.checkpoint, &task.progress)
+                    .await
+                    .map_err(|err| err.to_string())
+            };
+            match &result {
+                Ok(report) => tracing::info!(
+                    context = "store",
+                    event = "migrate",
+                    records = report.records,
+                    blobs = report.blobs,
+                    mismatched_accounts = report.mismatched_accounts.len(),
+                    mismatched_global = report.mismatched_global,
+                    "Migration completed."
+                ),
+                Err(err) => tracing::warn!(
+                    context = "store",
+                    event = "error",
+                    reason = %err,
+                    "Migration failed."
+                ),
+            }
+            *task.result.lock() = result.into();
+        });
+
+        success()
+    }
+
+    pub fn handle_migration_status(&self) -> HttpResponse {
+        let task = self.migration.lock().clone();
+        JsonResponse::new(if let Some(task) = task {
+            let records = task.progress.records.load(Ordering::Relaxed);
+            let blobs = task.progress.blobs.load(Ordering::Relaxed);
+            let result = task.result.lock();
+            match &*result {
+                None => serde_json::json!({
+                    "status": "running",
+                    "records": records,
+                    "blobs": blobs,
+                }),
+                Some(Ok(report)) => serde_json::json!({
+                    "status": "completed",
+                    "applied": report.applied,
+                    "records": report.records,
+                    "blobs": report.blobs,
+                    "verified": report.mismatched_accounts.is_empty()
+                        && !report.mismatched_global,
+                    "mismatchedAccounts": report.mismatched_accounts,
+                }),
+                Some(Err(err)) => serde_json::json!({
+                    "status": "failed",
+                    "records": records,
+                    "blobs": blobs,
+                    "error": err,
+                }),
+            }
+        } else {
+            serde_json::json!({ "status": "idle" })
+        })
+        .into_http_response()
+    }
+
+    async fn invalidate_account_cache(&self, name: &str) {
+        if let Ok(Some(account_id)) = self.try_get_account_id(name).await {
+            self.access_tokens.remove(&account_id);
+            self.sessions.retain_items(|id| *id != account_id);
+        }
+    }
+}
+
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Default)]
+pub struct MigrationTask {
+    pub progress: MigrationProgress,
+    pub result: Mutex<Option<Result<MigrationReport, String>>>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct MigrationRequest {
+    #[serde(default)]
+    paths: Vec<PathBuf>,
+    source: Option<MigrationSource>,
+    checkpoint: PathBuf,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct MigrationSource {
+    url: String,
+    user: String,
+    secret: String,
+}
+
+async fn migrate_from_source(
+    store: &Store,
+    source: MigrationSource,
+    checkpoint: &Path,
+    progress: &MigrationProgress,
+) -> Result<MigrationReport, String> {
+    // Request the records that changed since the last migrated snapshot
+    let base = store
+        .migration_base(checkpoint)
+        .await
+        .map_err(|err| err.to_string())?;
+    let client_builder = reqwest::Client::builder();
+
+    #[cfg(feature = "test_mode")]
+    let client_builder = client_builder.danger_accept_invalid_certs(true);
+
+    let mut response = client_builder
+        .build()
+        .map_err(|err| err.to_string())?
+        .post(format!(
+            "{}/admin/store/export",
+            source.url.trim_end_matches('/')
+        ))
+        .basic_auth(source.user, Some(source.secret))
+        .body(base.map(|base| base.serialize()).unwrap_or_default())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Failed to connect to the source server: {err}"))?;
+
+    // Records are applied while they are received
+    let (mut writer, reader) = tokio::io::duplex(STREAM_BUFFER_SIZE);
+    let (fetch_result, result) = tokio::join!(
+        async move {
+            while let Some(chunk) = response
+                .chunk()
+                .await
+                .map_err(|err| format!("Failed to read from the source server: {err}"))?
+            {
+                if writer.write_all(&chunk).await.is_err() {
+                    // The migration failed and stopped reading
+                    break;
+                }
+            }
+            Ok::<_, String>(())
+        },
+        store.migrate_stream(reader, checkpoint, progress)
+    );
+
+    match (result, fetch_result) {
+        (Ok(report), _) => Ok(report),
+        (Err(_), Err(err)) => Err(err),
+        (Err(err), _) => Err(err.to_string()),
+    }
+}
+
+async fn parse_body<T: serde::de::DeserializeOwned>(
+    req: &mut HttpRequest,
+    access_token: &AccessToken,
+) -> Result<T, ManageError> {
+    fetch_body(req, 1024 * 1024, access_token)
+        .await
+        .ok_or_else(|| ManageError::Invalid("Request body too large.".to_string()))
+        .and_then(|bytes| {
+            serde_json::from_slice::<T>(&bytes)
+                .map_err(|err| ManageError::Invalid(format!("Invalid request body: {err}")))
+        })
+}
+
+fn success() -> HttpResponse {
+    JsonResponse::new(serde_json::Value::String("success".into())).into_http_response()
+}
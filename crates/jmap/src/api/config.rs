@@ -21,13 +21,15 @@
  * for more details.
 */
 
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
 use store::{
     fts::Language,
     rand::{distributions::Alphanumeric, thread_rng, Rng},
 };
 
+use crate::{identity::signature::Signature, push::vapid::VapidKey};
+
 use super::session::BaseCapabilities;
 
 impl crate::Config {
@@ -37,6 +39,7 @@ impl crate::Config {
                 settings.value("jmap.fts.default-language").unwrap_or("en"),
             )
             .unwrap_or(Language::English),
+            fuzzy_match: settings.property("jmap.fts.fuzzy-match")?.unwrap_or(false),
             query_max_results: settings
                 .property("jmap.protocol.query.max-results")?
                 .unwrap_or(5000),
@@ -92,6 +95,9 @@ impl crate::Config {
             sieve_max_scripts: settings
                 .property("jmap.sieve.limits.max-scripts")?
                 .unwrap_or(256),
+            sieve_vacation_interval: settings
+                .property_or_static::<Duration>("jmap.sieve.vacation.min-interval", "7d")?
+                .as_secs(),
             capabilities: BaseCapabilities::default(),
             session_cache_ttl: settings
                 .property("jmap.session.cache.ttl")?
@@ -101,6 +107,8 @@ impl crate::Config {
             rate_authenticate_req: settings
                 .property_or_static("jmap.rate-limit.authentication", "10/1m")?,
             rate_anonymous: settings.property_or_static("jmap.rate-limit.anonymous", "100/1m")?,
+            rate_identity_verify: settings
+                .property_or_static("jmap.rate-limit.identity-verification", "5/1h")?,
             rate_use_forwarded: settings
                 .property("jmap.rate-limit.use-forwarded")?
                 .unwrap_or(false),
@@ -136,11 +144,44 @@ impl crate::Config {
             web_socket_timeout: settings.property_or_static("jmap.web-socket.timeout", "10m")?,
             web_socket_heartbeat: settings.property_or_static("jmap.web-socket.heartbeat", "1m")?,
             push_max_total: settings.property_or_static("jmap.push.max-total", "100")?,
+            push_vapid: VapidKey::new(settings)?,
             principal_allow_lookups: settings
                 .property("jmap.principal.allow-lookups")?
                 .unwrap_or(true),
+            identity_insert_signature: settings
+                .property("jmap.identity.signature.insert")?
+                .unwrap_or(false),
+            identity_verify_external: settings
+                .property("jmap.identity.external.verify")?
+                .unwrap_or(false),
+            identity_footers: HashMap::new(),
+            quota_warn_threshold: settings
+                .property("jmap.quota.warn-threshold")?
+                .unwrap_or(90),
+            quota_max_messages: settings.property("jmap.quota.max-messages")?.unwrap_or(0),
         };
         config.add_capabilites(settings);
+
+        // Parse per-domain footers
+        for (key, value) in settings.values("jmap.identity.footer") {
+            if let Some((domain, kind)) = key
+                .strip_prefix("jmap.identity.footer.")
+                .and_then(|key| key.rsplit_once('.'))
+            {
+                let footer = config
+                    .identity_footers
+                    .entry(domain.to_lowercase())
+                    .or_insert_with(Signature::default);
+                match kind {
+                    "text" => footer.text = value.to_string().into(),
+                    "html" => footer.html = value.to_string().into(),
+                    _ => {
+                        return Err(format!("Invalid footer property {key:?}."));
+                    }
+                }
+            }
+        }
+
         Ok(config)
     }
 }
@@ -43,10 +43,10 @@ use tokio::{
     io::{AsyncRead, AsyncWrite},
     net::TcpStream,
 };
-use utils::listener::{ServerInstance, SessionData, SessionManager};
+use utils::listener::{tls_client_identity, ServerInstance, SessionData, SessionManager};
 
 use crate::{
-    auth::{oauth::OAuthMetadata, AccessToken},
+    auth::{authenticate::TlsClientIdentity, oauth::OAuthMetadata, AccessToken},
     blob::{DownloadResponse, UploadResponse},
     services::state,
     websocket::upgrade::upgrade_websocket_connection,
@@ -194,6 +194,15 @@ pub async fn parse_jmap_request(
             }
             _ => (),
         },
+        "list" => {
+            if let ("unsubscribe", &Method::POST) = (path.next().unwrap_or(""), req.method()) {
+                let remote_addr = jmap.build_remote_addr(&req, remote_ip);
+                return match jmap.is_anonymous_allowed(remote_addr) {
+                    Ok(_) => jmap.smtp.handle_list_unsubscribe(req.uri()).await,
+                    Err(err) => err.into_http_response(),
+                };
+            }
+        }
         "auth" => {
             let remote_addr = jmap.build_remote_addr(&req, remote_ip);
 
@@ -240,11 +249,11 @@ pub async fn parse_jmap_request(
 
         "admin" => {
             // Make sure the user is a superuser
-            match jmap.authenticate_headers(&req, remote_ip).await {
-                Ok(Some((_, access_token))) if access_token.is_super_user() => (),
+            let access_token = match jmap.authenticate_headers(&req, remote_ip).await {
+                Ok(Some((_, access_token))) if access_token.is_super_user() => access_token,
                 Ok(_) => return RequestError::unauthorized().into_http_response(),
                 Err(err) => return err.into_http_response(),
-            }
+            };
 
             match (
                 path.next().unwrap_or(""),
@@ -340,7 +349,35 @@ pub async fn parse_jmap_request(
                         .into_http_response(),
                     };
                 }
-                (path_1 @ ("queue" | "report"), path_2, &Method::GET) => {
+                ("store", action @ ("backup" | "restore"), &Method::GET) => {
+                    return jmap.handle_store_request(&req, action).await;
+                }
+                ("store", "export", &Method::POST) => {
+                    return jmap.handle_store_export(&mut req, &access_token).await;
+                }
+                ("store", "migrate", &Method::POST) => {
+                    return jmap.handle_store_migrate(&mut req, &access_token).await;
+                }
+                ("store", "migrate", &Method::GET) => {
+                    return jmap.handle_migration_status();
+                }
+                (path_1 @ ("principal" | "domain"), path_2, _) => {
+                    let path_1 = path_1.to_string();
+                    let path_2 = path_2.to_string();
+                    let path_3 = path.next().map(|p| p.to_string());
+                    return jmap
+                        .handle_directory_request(
+                            &mut req,
+                            (&path_1, &path_2, path_3.as_deref()),
+                            &access_token,
+                        )
+                        .await;
+                }
+                (
+                    path_1 @ ("queue" | "report" | "suppression" | "quarantine" | "list"),
+                    path_2,
+                    &Method::GET,
+                ) => {
                     return jmap
                         .smtp
                         .handle_manage_request(req.uri(), req.method(), path_1, path_2)
@@ -363,6 +400,7 @@ impl SessionManager for JmapSessionManager {
                 let span = session.span;
                 match tls_acceptor.accept(session.stream).await {
                     Ok(stream) => {
+                        let tls_identity = tls_client_identity(&stream);
                         handle_request(
                             jmap,
                             SessionData {
@@ -374,6 +412,7 @@ impl SessionManager for JmapSessionManager {
                                 in_flight: session.in_flight,
                                 instance: session.instance,
                             },
+                            tls_identity,
                         )
                         .await;
                     }
@@ -388,7 +427,7 @@ impl SessionManager for JmapSessionManager {
                     }
                 }
             } else {
-                handle_request(jmap, session).await;
+                handle_request(jmap, session, None).await;
             }
         });
     }
@@ -404,6 +443,7 @@ impl SessionManager for JmapSessionManager {
 async fn handle_request<T: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
     jmap: Arc<JMAP>,
     session: SessionData<T>,
+    tls_identity: Option<String>,
 ) {
     let span = session.span;
     let _in_flight = session.in_flight;
@@ -412,10 +452,14 @@ async fn handle_request<T: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
         .keep_alive(true)
         .serve_connection(
             TokioIo::new(session.stream),
-            service_fn(|req: hyper::Request<body::Incoming>| {
+            service_fn(|mut req: hyper::Request<body::Incoming>| {
                 let jmap = jmap.clone();
                 let span = span.clone();
                 let instance = session.instance.clone();
+                if let Some(identity) = &tls_identity {
+                    req.extensions_mut()
+                        .insert(TlsClientIdentity(identity.clone()));
+                }
 
                 async move {
                     tracing::debug!(
@@ -177,6 +177,16 @@ impl JMAP {
                         ));
                     }
                 }
+                get::RequestArguments::Quota => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.quota_get(req).await?.into()
+                }
+                get::RequestArguments::ShareNotification => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.share_notification_get(req).await?.into()
+                }
             },
             RequestMethod::Query(mut req) => match req.take_arguments() {
                 query::RequestArguments::Email(arguments) => {
@@ -212,6 +222,16 @@ impl JMAP {
                         ));
                     }
                 }
+                query::RequestArguments::Quota => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.quota_query(req).await?.into()
+                }
+                query::RequestArguments::ShareNotification => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.share_notification_query(req).await?.into()
+                }
             },
             RequestMethod::Set(mut req) => match req.take_arguments() {
                 set::RequestArguments::Email => {
@@ -219,6 +239,11 @@ impl JMAP {
 
                     self.email_set(req, access_token).await?.into()
                 }
+                set::RequestArguments::Thread => {
+                    access_token.assert_has_access(req.account_id, Collection::Email)?;
+
+                    self.thread_set(req, access_token).await?.into()
+                }
                 set::RequestArguments::Mailbox(arguments) => {
                     access_token.assert_has_access(req.account_id, Collection::Mailbox)?;
 
@@ -229,7 +254,7 @@ impl JMAP {
                 set::RequestArguments::Identity => {
                     access_token.assert_is_member(req.account_id)?;
 
-                    self.identity_set(req, access_token).await?.into()
+                    self.identity_set(req, access_token, instance).await?.into()
                 }
                 set::RequestArguments::EmailSubmission(arguments) => {
                     access_token.assert_is_member(req.account_id)?;
@@ -253,6 +278,11 @@ impl JMAP {
 
                     self.vacation_response_set(req).await?.into()
                 }
+                set::RequestArguments::ShareNotification => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.share_notification_set(req).await?.into()
+                }
             },
             RequestMethod::Changes(req) => self.changes(req, access_token).await?.into(),
             RequestMethod::Copy(req) => {
@@ -263,6 +293,17 @@ impl JMAP {
                 self.email_copy(req, access_token, next_call).await?.into()
             }
             RequestMethod::CopyBlob(req) => self.blob_copy(req, access_token).await?.into(),
+            RequestMethod::GetBlob(req) => self.blob_get(req, access_token).await?.into(),
+            RequestMethod::UploadBlob(req) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.blob_upload_many(req, access_token).await?.into()
+            }
+            RequestMethod::LookupBlob(req) => {
+                access_token.assert_has_access(req.account_id, Collection::Email)?;
+
+                self.blob_lookup(req, access_token).await?.into()
+            }
             RequestMethod::ImportEmail(req) => {
                 access_token.assert_has_access(req.account_id, Collection::Email)?;
 
@@ -284,6 +325,16 @@ impl JMAP {
 
                 self.sieve_script_validate(req, access_token).await?.into()
             }
+            RequestMethod::SendMdn(req) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.mdn_send(req, instance, next_call).await?.into()
+            }
+            RequestMethod::ParseMdn(req) => {
+                access_token.assert_has_access(req.account_id, Collection::Email)?;
+
+                self.mdn_parse(req, access_token).await?.into()
+            }
             RequestMethod::Echo(req) => req.into(),
             RequestMethod::Error(error) => return Err(error),
         })
@@ -81,6 +81,12 @@ pub enum Capabilities {
     VacationResponse(VacationResponseCapabilities),
     WebSocket(WebSocketCapabilities),
     Sieve(SieveCapabilities),
+    Quota(QuotaCapabilities),
+    Blob(BlobCapabilities),
+    Mdn(MdnCapabilities),
+    Principals(PrincipalCapabilities),
+    PrincipalsOwner(PrincipalOwnerCapabilities),
+    WebPushVapid(WebPushVapidCapabilities),
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -158,6 +164,47 @@ pub struct SubmissionCapabilities {
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct VacationResponseCapabilities {}
 
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct QuotaCapabilities {}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MdnCapabilities {}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct PrincipalCapabilities {
+    #[serde(rename(serialize = "currentUserPrincipalId"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current_user_principal_id: Option<Id>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct PrincipalOwnerCapabilities {
+    #[serde(rename(serialize = "accountIdForPrincipal"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account_id_for_principal: Option<Id>,
+    #[serde(rename(serialize = "principalId"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    principal_id: Option<Id>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WebPushVapidCapabilities {
+    #[serde(rename(serialize = "applicationServerKey"))]
+    application_server_key: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BlobCapabilities {
+    #[serde(rename(serialize = "maxSizeBlobSet"))]
+    max_size_blob_set: usize,
+    #[serde(rename(serialize = "maxDataSources"))]
+    max_data_sources: usize,
+    #[serde(rename(serialize = "supportedTypeNames"))]
+    supported_type_names: Vec<String>,
+    #[serde(rename(serialize = "supportedDigestAlgorithms"))]
+    supported_digest_algorithms: Vec<String>,
+}
+
 #[derive(Default)]
 pub struct BaseCapabilities {
     pub capabilities: VecMap<Capability, Capabilities>,
@@ -190,6 +237,28 @@ impl JMAP {
                     .await
                     .map_or(true, |ids| ids.is_empty());
 
+            // Group members can see the quotas of the shared account
+            let capabilities: &[Capability] = if !is_personal {
+                &[
+                    Capability::Core,
+                    Capability::Mail,
+                    Capability::Quota,
+                    Capability::Blob,
+                    Capability::WebSocket,
+                    Capability::Principals,
+                    Capability::PrincipalsOwner,
+                ]
+            } else {
+                &[
+                    Capability::Core,
+                    Capability::Mail,
+                    Capability::Blob,
+                    Capability::WebSocket,
+                    Capability::Principals,
+                    Capability::PrincipalsOwner,
+                ]
+            };
+
             session.add_account(
                 (*id).into(),
                 self.get_account_name(*id)
@@ -198,10 +267,13 @@ impl JMAP {
                     .unwrap_or_else(|| Id::from(*id).to_string()),
                 is_personal,
                 is_readonly,
-                Some(&[Capability::Core, Capability::Mail, Capability::WebSocket]),
+                Some(capabilities),
             );
         }
 
+        // Add principal ids
+        session.set_principal_ids(access_token.primary_id().into());
+
         Ok(session)
     }
 }
@@ -233,6 +305,30 @@ impl crate::Config {
             Capability::Sieve,
             Capabilities::Sieve(SieveCapabilities::new(self, settings)),
         );
+        self.capabilities
+            .capabilities
+            .append(Capability::Quota, Capabilities::Quota(QuotaCapabilities {}));
+        self.capabilities.capabilities.append(
+            Capability::Blob,
+            Capabilities::Blob(BlobCapabilities::new(self)),
+        );
+        self.capabilities
+            .capabilities
+            .append(Capability::Mdn, Capabilities::Mdn(MdnCapabilities {}));
+        self.capabilities.capabilities.append(
+            Capability::Principals,
+            Capabilities::Principals(PrincipalCapabilities::default()),
+        );
+        self.capabilities.capabilities.append(
+            Capability::PrincipalsOwner,
+            Capabilities::PrincipalsOwner(PrincipalOwnerCapabilities::default()),
+        );
+        self.capabilities.capabilities.append(
+            Capability::WebPushVapid,
+            Capabilities::WebPushVapid(WebPushVapidCapabilities {
+                application_server_key: self.push_vapid.public_key().to_string(),
+            }),
+        );
     }
 }
 
@@ -304,6 +400,23 @@ impl Session {
         );
     }
 
+    pub fn set_principal_ids(&mut self, current_principal_id: Id) {
+        for (account_id, account) in self.accounts.iter_mut() {
+            for capabilities in account.account_capabilities.values_mut() {
+                match capabilities {
+                    Capabilities::Principals(capabilities) => {
+                        capabilities.current_user_principal_id = current_principal_id.into();
+                    }
+                    Capabilities::PrincipalsOwner(capabilities) => {
+                        capabilities.account_id_for_principal = (*account_id).into();
+                        capabilities.principal_id = (*account_id).into();
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
     pub fn set_state(&mut self, state: u32) {
         self.state = state;
     }
@@ -421,6 +534,22 @@ impl SieveCapabilities {
     }
 }
 
+impl BlobCapabilities {
+    pub fn new(config: &crate::Config) -> Self {
+        BlobCapabilities {
+            max_size_blob_set: config.upload_max_size,
+            max_data_sources: config.set_max_objects,
+            supported_type_names: vec![
+                "Email".to_string(),
+                "Mailbox".to_string(),
+                "Thread".to_string(),
+                "SieveScript".to_string(),
+            ],
+            supported_digest_algorithms: vec!["sha-256".to_string(), "sha-512".to_string()],
+        }
+    }
+}
+
 impl MailCapabilities {
     pub fn new(config: &crate::Config) -> Self {
         MailCapabilities {
@@ -36,7 +36,7 @@ use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
 use store::{
     write::{key::KeySerializer, BatchBuilder, Operation, ValueClass},
-    CustomValueKey, Serialize,
+    CustomValueKey, Serialize, CUSTOM_ACCOUNT_ID_TO_NAME, CUSTOM_ACCOUNT_NAME_TO_ID,
 };
 use utils::{listener::limiter::InFlight, map::ttl_dashmap::TtlMap};
 
@@ -116,6 +116,21 @@ impl JMAP {
             } else {
                 Ok(None)
             }
+        } else if let Some(TlsClientIdentity(identity)) =
+            req.extensions().get::<TlsClientIdentity>()
+        {
+            // Authenticate using the client certificate
+            if let Some(access_token) = self.authenticate_external(identity, "").await {
+                let access_token = Arc::new(access_token);
+                self.cache_access_token(access_token.clone());
+                Ok(Some((
+                    self.is_account_allowed(&access_token)?,
+                    access_token,
+                )))
+            } else {
+                self.is_anonymous_allowed(self.build_remote_addr(req, remote_ip))?;
+                Ok(None)
+            }
         } else {
             // Enforce anonymous rate limit
             self.is_anonymous_allowed(self.build_remote_addr(req, remote_ip))?;
@@ -294,6 +309,45 @@ impl JMAP {
         }
     }
 
+    pub async fn authenticate_external(
+        &self,
+        identity: &str,
+        authzid: &str,
+    ) -> Option<AccessToken> {
+        let mut principal = match self
+            .directory
+            .authenticate_external(identity, authzid)
+            .await
+        {
+            Ok(Some(principal)) => principal,
+            Ok(None) => {
+                tracing::debug!(
+                    context = "authenticate_external",
+                    identity = identity,
+                    authzid = authzid,
+                    "Certificate identity does not map to a principal."
+                );
+                return None;
+            }
+            Err(_) => return None,
+        };
+
+        // Obtain groups
+        if let (Ok(account_id), Ok(member_of)) = (
+            self.get_account_id(&principal.name).await,
+            self.map_member_of(std::mem::take(&mut principal.member_of))
+                .await,
+        ) {
+            // Create access token
+            self.update_access_token(
+                AccessToken::new(principal, account_id).with_member_of(member_of),
+            )
+            .await
+        } else {
+            None
+        }
+    }
+
     pub async fn get_access_token(&self, account_id: u32) -> Option<AccessToken> {
         let name = self.get_account_name(account_id).await.ok()??;
         let mut principal = self.directory.principal(&name).await.ok()??;
@@ -315,20 +369,23 @@ impl JMAP {
     }
 }
 
+#[derive(Clone)]
+pub struct TlsClientIdentity(pub String);
+
 pub struct AccountKey();
 
 impl AccountKey {
     pub fn name_to_id(name: &str) -> Vec<u8> {
         KeySerializer::new(name.len() + std::mem::size_of::<u32>() + 1)
             .write(u32::MAX)
-            .write(0u8)
+            .write(CUSTOM_ACCOUNT_NAME_TO_ID)
             .write(name)
             .finalize()
     }
     pub fn id_to_name(id: u32) -> Vec<u8> {
         KeySerializer::new(std::mem::size_of::<u32>() * 2 + 1)
             .write(u32::MAX)
-            .write(1u8)
+            .write(CUSTOM_ACCOUNT_ID_TO_NAME)
             .write(id)
             .finalize()
     }
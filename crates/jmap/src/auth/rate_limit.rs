@@ -41,6 +41,7 @@ pub struct AuthenticatedLimiter {
     pub request_limiter: RateLimiter,
     pub concurrent_requests: ConcurrencyLimiter,
     pub concurrent_uploads: ConcurrencyLimiter,
+    pub identity_verifications: RateLimiter,
 }
 
 #[derive(Debug)]
@@ -66,6 +67,10 @@ impl JMAP {
                     concurrent_uploads: ConcurrencyLimiter::new(
                         self.config.upload_max_concurrent as u64,
                     ),
+                    identity_verifications: RateLimiter::new(
+                        self.config.rate_identity_verify.requests,
+                        self.config.rate_identity_verify.period,
+                    ),
                 }));
                 self.rate_limit_auth.insert(account_id, limiter.clone());
                 limiter
@@ -139,6 +144,13 @@ impl JMAP {
         }
     }
 
+    pub fn is_identity_verification_allowed(&self, account_id: u32) -> bool {
+        self.get_authenticated_limiter(account_id)
+            .lock()
+            .identity_verifications
+            .is_allowed()
+    }
+
     pub fn is_auth_allowed(&self, addr: RemoteAddress) -> Result<(), RequestError> {
         if self
             .get_anonymous_limiter(addr)
@@ -158,6 +170,7 @@ impl AuthenticatedLimiter {
         self.request_limiter.is_active()
             || self.concurrent_requests.is_active()
             || self.concurrent_uploads.is_active()
+            || self.identity_verifications.is_active()
     }
 }
 
@@ -0,0 +1,260 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use base64::{engine::general_purpose, Engine};
+use jmap_proto::{
+    error::method::MethodError,
+    method::{
+        get::{BlobObject, BlobProperty, GetBlobRequest, GetBlobResponse},
+        lookup::{BlobInfo, LookupBlobRequest, LookupBlobResponse},
+    },
+    types::{acl::Acl, collection::Collection, id::Id, property::Property, value::Value},
+};
+use sha2::{Digest, Sha256, Sha512};
+use store::BlobKind;
+use utils::map::vec_map::VecMap;
+
+use crate::{auth::AccessToken, JMAP};
+
+impl JMAP {
+    pub async fn blob_get(
+        &self,
+        request: GetBlobRequest,
+        access_token: &AccessToken,
+    ) -> Result<GetBlobResponse, MethodError> {
+        let ids = request.ids.unwrap();
+        if ids.len() > self.config.get_max_objects {
+            return Err(MethodError::RequestTooLarge);
+        }
+        let properties = request
+            .properties
+            .unwrap_or_else(|| vec![BlobProperty::Data, BlobProperty::Size]);
+        let range_from = request.offset.unwrap_or(0);
+        let range_to = request
+            .length
+            .map(|length| range_from.saturating_add(length));
+        let mut response = GetBlobResponse {
+            account_id: request.account_id,
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for blob_id in ids {
+            let blob_id = blob_id.unwrap();
+            let bytes = if let Some(bytes) = self.blob_download(&blob_id, access_token).await? {
+                bytes
+            } else {
+                response.not_found.push(blob_id);
+                continue;
+            };
+
+            // Obtain the requested range
+            let range_end = range_to.map_or(bytes.len(), |to| to.min(bytes.len()));
+            let data = bytes
+                .get(range_from.min(range_end)..range_end)
+                .unwrap_or_default();
+            let mut blob = BlobObject {
+                id: blob_id,
+                is_truncated: range_from > bytes.len()
+                    || range_to.map_or(false, |to| to > bytes.len()),
+                ..Default::default()
+            };
+
+            for property in &properties {
+                match property {
+                    BlobProperty::Data | BlobProperty::DataAsText => {
+                        match std::str::from_utf8(data) {
+                            Ok(text) => {
+                                blob.data_as_text = Value::Text(text.to_string()).into();
+                            }
+                            Err(_) if matches!(property, BlobProperty::Data) => {
+                                blob.data_as_base64 =
+                                    Value::Text(general_purpose::STANDARD.encode(data)).into();
+                            }
+                            Err(_) => {
+                                blob.data_as_text = Value::Null.into();
+                                blob.is_encoding_problem = true;
+                            }
+                        }
+                    }
+                    BlobProperty::DataAsBase64 => {
+                        blob.data_as_base64 =
+                            Value::Text(general_purpose::STANDARD.encode(data)).into();
+                    }
+                    BlobProperty::DigestSha256 => {
+                        blob.digest_sha256 = general_purpose::STANDARD
+                            .encode(Sha256::digest(data))
+                            .into();
+                    }
+                    BlobProperty::DigestSha512 => {
+                        blob.digest_sha512 = general_purpose::STANDARD
+                            .encode(Sha512::digest(data))
+                            .into();
+                    }
+                    BlobProperty::Size => {
+                        blob.size = bytes.len().into();
+                    }
+                    BlobProperty::Id => (),
+                }
+            }
+
+            response.list.push(blob);
+        }
+
+        Ok(response)
+    }
+
+    pub async fn blob_lookup(
+        &self,
+        request: LookupBlobRequest,
+        access_token: &AccessToken,
+    ) -> Result<LookupBlobResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let ids = request.ids.unwrap();
+        if ids.len() > self.config.get_max_objects {
+            return Err(MethodError::RequestTooLarge);
+        }
+        for type_name in &request.type_names {
+            if !matches!(
+                type_name.as_str(),
+                "Email" | "Mailbox" | "Thread" | "SieveScript"
+            ) {
+                return Err(MethodError::UnknownDataType(format!(
+                    "Unsupported data type {type_name:?}."
+                )));
+            }
+        }
+        let shared_mailboxes = if access_token.is_shared(account_id) {
+            Some(
+                self.shared_documents(access_token, account_id, Collection::Mailbox, Acl::Read)
+                    .await?,
+            )
+        } else {
+            None
+        };
+        let mut response = LookupBlobResponse {
+            account_id: request.account_id,
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for blob_id in ids {
+            let blob_id = blob_id.unwrap();
+            if blob_id.account_id() != account_id
+                || !self.has_access_blob(&blob_id, access_token).await?
+            {
+                response.not_found.push(blob_id);
+                continue;
+            }
+
+            // Obtain the object linked to this blob
+            let mut matched_ids = VecMap::with_capacity(request.type_names.len());
+            match &blob_id.kind {
+                BlobKind::LinkedMaildir { document_id, .. } => {
+                    let thread_id = if let Some(thread_id) = self
+                        .get_property::<u32>(
+                            account_id,
+                            Collection::Email,
+                            *document_id,
+                            Property::ThreadId,
+                        )
+                        .await?
+                    {
+                        thread_id
+                    } else {
+                        response.not_found.push(blob_id);
+                        continue;
+                    };
+
+                    for type_name in &request.type_names {
+                        let ids = match type_name.as_str() {
+                            "Email" => vec![Id::from_parts(thread_id, *document_id)],
+                            "Thread" => vec![Id::from(thread_id)],
+                            "Mailbox" => self
+                                .get_property::<Vec<u32>>(
+                                    account_id,
+                                    Collection::Email,
+                                    *document_id,
+                                    Property::MailboxIds,
+                                )
+                                .await?
+                                .unwrap_or_default()
+                                .into_iter()
+                                .filter(|mailbox_id| {
+                                    shared_mailboxes
+                                        .as_ref()
+                                        .map_or(true, |ids| ids.contains(*mailbox_id))
+                                })
+                                .map(Id::from)
+                                .collect(),
+                            _ => vec![],
+                        };
+                        matched_ids.append(type_name.clone(), ids);
+                    }
+                }
+                BlobKind::Linked {
+                    collection,
+                    document_id,
+                    ..
+                } if Collection::from(*collection) == Collection::SieveScript => {
+                    if !self
+                        .get_document_ids(account_id, Collection::SieveScript)
+                        .await?
+                        .map_or(false, |ids| ids.contains(*document_id))
+                    {
+                        response.not_found.push(blob_id);
+                        continue;
+                    }
+
+                    for type_name in &request.type_names {
+                        matched_ids.append(
+                            type_name.clone(),
+                            if type_name == "SieveScript" {
+                                vec![Id::from(*document_id)]
+                            } else {
+                                vec![]
+                            },
+                        );
+                    }
+                }
+                _ => {
+                    if self.get_blob(&blob_id.kind, 0..1).await?.is_none() {
+                        response.not_found.push(blob_id);
+                        continue;
+                    }
+
+                    for type_name in &request.type_names {
+                        matched_ids.append(type_name.clone(), vec![]);
+                    }
+                }
+            }
+
+            response.list.push(BlobInfo {
+                id: blob_id,
+                matched_ids,
+            });
+        }
+
+        Ok(response)
+    }
+}
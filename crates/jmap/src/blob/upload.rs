@@ -24,10 +24,19 @@
 use std::sync::Arc;
 
 use jmap_proto::{
-    error::{method::MethodError, request::RequestError},
+    error::{
+        method::MethodError,
+        request::RequestError,
+        set::{SetError, SetErrorType},
+    },
+    method::upload::{
+        DataSourceObject, UploadBlobRequest, UploadBlobResponse, UploadBlobResponseObject,
+    },
+    request::reference::MaybeReference,
     types::{blob::BlobId, id::Id},
 };
 use store::BlobKind;
+use utils::map::vec_map::VecMap;
 
 use crate::{auth::AccessToken, JMAP};
 
@@ -112,6 +121,158 @@ impl JMAP {
         }
     }
 
+    pub async fn blob_upload_many(
+        &self,
+        request: UploadBlobRequest,
+        access_token: &AccessToken,
+    ) -> Result<UploadBlobResponse, MethodError> {
+        if request.create.len() > self.config.set_max_objects {
+            return Err(MethodError::RequestTooLarge);
+        }
+        let mut response = UploadBlobResponse {
+            account_id: request.account_id,
+            created: VecMap::with_capacity(request.create.len()),
+            not_created: VecMap::new(),
+        };
+        let account_id = request.account_id.document_id();
+
+        // Obtain current temporary blob usage
+        let (mut total_files, mut total_bytes) = self
+            .store
+            .get_tmp_blob_usage(account_id, self.config.upload_tmp_ttl)
+            .await
+            .map_err(|err| {
+                tracing::error!(event = "error",
+                    context = "blob_store",
+                    account_id = account_id,
+                    error = ?err,
+                    "Failed to obtain blob quota");
+                MethodError::ServerPartialFail
+            })?;
+
+        'outer: for (create_id, upload_object) in request.create {
+            if upload_object.data.len() > self.config.set_max_objects {
+                response.not_created.append(
+                    create_id,
+                    SetError::new(SetErrorType::TooLarge).with_description(format!(
+                        "Too many data sources, the maximum is {}.",
+                        self.config.set_max_objects
+                    )),
+                );
+                continue;
+            }
+            let mut data = Vec::new();
+
+            for data_source in upload_object.data {
+                let bytes = match data_source {
+                    DataSourceObject::Value(bytes) => bytes,
+                    DataSourceObject::Id { id, offset, length } => {
+                        // Blobs created earlier in this call can be referenced by their creation id
+                        let blob_id = match id {
+                            MaybeReference::Value(blob_id) => blob_id,
+                            MaybeReference::Reference(ir) => {
+                                if let Some(obj) = response.created.get(&ir) {
+                                    obj.id.clone()
+                                } else {
+                                    response.not_created.append(
+                                        create_id,
+                                        SetError::new(SetErrorType::BlobNotFound).with_description(
+                                            format!("Blob reference {ir:?} not found."),
+                                        ),
+                                    );
+                                    continue 'outer;
+                                }
+                            }
+                        };
+
+                        let bytes = if let Some(bytes) =
+                            self.blob_download(&blob_id, access_token).await?
+                        {
+                            bytes
+                        } else {
+                            response.not_created.append(
+                                create_id,
+                                SetError::new(SetErrorType::BlobNotFound)
+                                    .with_description(format!("BlobId {blob_id} not found.")),
+                            );
+                            continue 'outer;
+                        };
+
+                        let offset = offset.unwrap_or(0);
+                        let end =
+                            length.map_or(bytes.len(), |length| offset.saturating_add(length));
+                        if offset == 0 && end == bytes.len() {
+                            bytes
+                        } else if let Some(bytes) = bytes.get(offset..end) {
+                            bytes.to_vec()
+                        } else {
+                            response.not_created.append(
+                                create_id,
+                                SetError::invalid_properties().with_description(format!(
+                                    "Range {offset}-{end} exceeds the size of blobId {blob_id}."
+                                )),
+                            );
+                            continue 'outer;
+                        }
+                    }
+                };
+
+                if data.len() + bytes.len() > self.config.upload_max_size {
+                    response.not_created.append(
+                        create_id,
+                        SetError::new(SetErrorType::TooLarge).with_description(format!(
+                            "Blob exceeds the maximum size of {} bytes.",
+                            self.config.upload_max_size
+                        )),
+                    );
+                    continue 'outer;
+                } else if data.is_empty() {
+                    data = bytes;
+                } else {
+                    data.extend_from_slice(&bytes);
+                }
+            }
+
+            // Enforce quota
+            let is_over_quota = ((self.config.upload_tmp_quota_size > 0
+                && total_bytes + data.len() > self.config.upload_tmp_quota_size)
+                || (self.config.upload_tmp_quota_amount > 0
+                    && total_files + 1 > self.config.upload_tmp_quota_amount))
+                && !access_token.is_super_user();
+
+            #[cfg(feature = "test_mode")]
+            let is_over_quota =
+                is_over_quota && !DISABLE_UPLOAD_QUOTA.load(std::sync::atomic::Ordering::Relaxed);
+
+            if is_over_quota {
+                response.not_created.append(
+                    create_id,
+                    SetError::over_quota().with_description(format!(
+                        "You have exceeded the blob upload quota of {} files or {} bytes.",
+                        self.config.upload_tmp_quota_amount, self.config.upload_tmp_quota_size
+                    )),
+                );
+                continue;
+            }
+
+            let blob_id = BlobId::temporary(account_id);
+            self.put_blob(&blob_id.kind, &data).await?;
+            total_files += 1;
+            total_bytes += data.len();
+
+            response.created.append(
+                create_id,
+                UploadBlobResponseObject {
+                    id: blob_id,
+                    type_: upload_object.type_,
+                    size: data.len(),
+                },
+            );
+        }
+
+        Ok(response)
+    }
+
     pub async fn put_blob(&self, kind: &BlobKind, data: &[u8]) -> Result<(), MethodError> {
         self.store.put_blob(kind, data).await.map_err(|err| {
             tracing::error!(
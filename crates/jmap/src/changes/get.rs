@@ -62,6 +62,16 @@ impl JMAP {
 
                 Collection::EmailSubmission
             }
+            RequestArguments::ShareNotification => {
+                access_token.assert_is_member(request.account_id)?;
+
+                Collection::ShareNotification
+            }
+            RequestArguments::Quota => {
+                access_token.assert_is_member(request.account_id)?;
+
+                return self.quota_changes(request).await;
+            }
         };
 
         let max_changes = if self.config.changes_max_results > 0
@@ -51,6 +51,9 @@ impl JMAP {
                         query::RequestArguments::EmailSubmission => {
                             changes::RequestArguments::EmailSubmission
                         }
+                        query::RequestArguments::ShareNotification => {
+                            changes::RequestArguments::ShareNotification
+                        }
                         _ => return Err(MethodError::UnknownMethod("Unknown method".to_string())),
                     },
                 },
@@ -97,6 +100,9 @@ impl JMAP {
                 query::RequestArguments::EmailSubmission => {
                     self.email_submission_query(query).await?
                 }
+                query::RequestArguments::ShareNotification => {
+                    self.share_notification_query(query).await?
+                }
                 _ => unreachable!(),
             };
 
@@ -56,7 +56,7 @@ use store::{
 };
 use utils::map::vec_map::VecMap;
 
-use crate::{auth::AccessToken, JMAP};
+use crate::{auth::AccessToken, quota::LogQuotaChange, JMAP};
 
 use super::{
     index::{EmailIndexBuilder, TrimTextValue, MAX_SORT_FIELD_LENGTH},
@@ -107,6 +107,7 @@ impl JMAP {
 
         // Obtain quota
         let account_quota = self.get_quota(access_token, account_id).await?;
+        let mut added_octets = 0;
 
         'create: for (id, create) in request.create {
             let id = id.unwrap();
@@ -234,6 +235,7 @@ impl JMAP {
                 .await?
             {
                 Ok(email) => {
+                    added_octets += email.size;
                     response.created.append(id, email.into());
                 }
                 Err(err) => {
@@ -251,11 +253,23 @@ impl JMAP {
         if !response.created.is_empty() {
             response.new_state = self.get_state(account_id, Collection::Email).await?;
             if let State::Exact(change_id) = &response.new_state {
-                response.state_change = StateChange::new(account_id)
+                let mut state_change = StateChange::new(account_id)
                     .with_change(TypeState::Email, *change_id)
                     .with_change(TypeState::Mailbox, *change_id)
-                    .with_change(TypeState::Thread, *change_id)
-                    .into()
+                    .with_change(TypeState::Thread, *change_id);
+                if self
+                    .has_crossed_quota_warning(
+                        account_id,
+                        account_quota,
+                        added_octets,
+                        response.created.len(),
+                    )
+                    .await
+                    .unwrap_or(false)
+                {
+                    state_change = state_change.with_change(TypeState::Quota, *change_id);
+                }
+                response.state_change = state_change.into()
             }
         }
 
@@ -322,6 +336,10 @@ impl JMAP {
         {
             return Ok(Err(SetError::over_quota()));
         }
+        let max_messages = self.quota_max_messages();
+        if max_messages > 0 && self.get_used_messages(account_id).await? >= max_messages {
+            return Ok(Err(SetError::over_quota()));
+        }
 
         // Set receivedAt
         if let Some(received_at) = received_at {
@@ -418,6 +436,7 @@ impl JMAP {
         email.id = Id::from_parts(thread_id, message_id);
         email.change_id = changes.change_id;
         changes.log_insert(Collection::Email, email.id);
+        changes.log_quota_change();
         for mailbox_id in &mailboxes {
             changes.log_child_update(Collection::Mailbox, *mailbox_id);
         }
@@ -76,6 +76,7 @@ impl JMAP {
             state_change: None,
         };
 
+        let mut added_octets = 0;
         'outer: for (id, email) in request.emails {
             // Validate mailboxIds
             let mailbox_ids = email
@@ -118,13 +119,14 @@ impl JMAP {
             }
 
             // Fetch raw message to import
-            let raw_message = match self.blob_download(&email.blob_id, access_token).await? {
+            let blob_id = email.blob_id.unwrap();
+            let raw_message = match self.blob_download(&blob_id, access_token).await? {
                 Some(raw_message) => raw_message,
                 None => {
                     response.not_created.append(
                         id,
                         SetError::new(SetErrorType::BlobNotFound)
-                            .with_description(format!("BlobId {} not found.", email.blob_id)),
+                            .with_description(format!("BlobId {} not found.", blob_id)),
                     );
                     continue;
                 }
@@ -141,10 +143,12 @@ impl JMAP {
                     keywords: email.keywords,
                     received_at: email.received_at.map(|r| r.into()),
                     skip_duplicates: false,
+                    skip_muted_inbox: false,
                 })
                 .await
             {
                 Ok(email) => {
+                    added_octets += email.size;
                     response.created.append(id, email.into());
                 }
                 Err(IngestError::Permanent { reason, .. }) => {
@@ -170,11 +174,23 @@ impl JMAP {
         if !response.created.is_empty() {
             response.new_state = self.get_state(account_id, Collection::Email).await?;
             if let State::Exact(change_id) = &response.new_state {
-                response.state_change = StateChange::new(account_id)
+                let mut state_change = StateChange::new(account_id)
                     .with_change(TypeState::Email, *change_id)
                     .with_change(TypeState::Mailbox, *change_id)
-                    .with_change(TypeState::Thread, *change_id)
-                    .into()
+                    .with_change(TypeState::Thread, *change_id);
+                if self
+                    .has_crossed_quota_warning(
+                        account_id,
+                        account_quota,
+                        added_octets,
+                        response.created.len(),
+                    )
+                    .await
+                    .unwrap_or(false)
+                {
+                    state_change = state_change.with_change(TypeState::Quota, *change_id);
+                }
+                response.state_change = state_change.into()
             }
         }
 
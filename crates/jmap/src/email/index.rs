@@ -89,7 +89,8 @@ impl IndexMessage for BatchBuilder {
         // Index size
         metadata.append(Property::Size, message.raw_message.len());
         self.value(Property::Size, message.raw_message.len() as u32, F_INDEX)
-            .quota(message.raw_message.len() as i64);
+            .quota(message.raw_message.len() as i64)
+            .message_count(1);
 
         // Index receivedAt
         metadata.append(
@@ -431,7 +432,8 @@ impl IntoOperations for EmailIndexBuilder {
                             size as i64
                         } else {
                             -(size as i64)
-                        });
+                        })
+                        .message_count(if self.set { 1 } else { -1 });
                 }
                 (Property::ReceivedAt | Property::SentAt, Value::Date(date)) => {
                     batch.value(property, date.timestamp() as u64, F_INDEX | options);
@@ -41,6 +41,8 @@ use utils::map::vec_map::VecMap;
 
 use crate::{
     email::index::{IndexMessage, MAX_ID_LENGTH},
+    mailbox::INBOX_ID,
+    quota::LogQuotaChange,
     IngestError, JMAP,
 };
 
@@ -63,6 +65,7 @@ pub struct IngestEmail<'x> {
     pub keywords: Vec<Keyword>,
     pub received_at: Option<u64>,
     pub skip_duplicates: bool,
+    pub skip_muted_inbox: bool,
 }
 
 impl JMAP {
@@ -83,6 +86,16 @@ impl JMAP {
         {
             return Err(IngestError::OverQuota);
         }
+        let max_messages = self.quota_max_messages();
+        if max_messages > 0
+            && self
+                .get_used_messages(params.account_id)
+                .await
+                .map_err(|_| IngestError::Temporary)?
+                >= max_messages
+        {
+            return Err(IngestError::OverQuota);
+        }
 
         // Parse message
         let raw_message = params.raw_message;
@@ -168,6 +181,51 @@ impl JMAP {
             None
         };
 
+        // Keep new messages in muted threads out of the inbox
+        let mut mailbox_ids = params.mailbox_ids;
+        let mut keywords = params.keywords;
+        if let Some(thread_id) =
+            thread_id.filter(|_| params.skip_muted_inbox && mailbox_ids.contains(&INBOX_ID))
+        {
+            if self
+                .store
+                .get_bitmap(BitmapKey::value(
+                    params.account_id,
+                    Collection::Thread,
+                    Property::IsMuted,
+                    (),
+                ))
+                .await
+                .map_err(|err| {
+                    tracing::error!(
+                        event = "error",
+                        context = "email_ingest",
+                        error = ?err,
+                        "Failed to obtain muted threads.");
+                    IngestError::Temporary
+                })?
+                .map_or(false, |muted_ids| muted_ids.contains(thread_id))
+            {
+                mailbox_ids.retain(|mailbox_id| *mailbox_id != INBOX_ID);
+                if mailbox_ids.is_empty() {
+                    // File into the archive or, if there is none, mark as seen
+                    match self
+                        .mailbox_get_by_role(params.account_id, "archive")
+                        .await
+                        .map_err(|_| IngestError::Temporary)?
+                    {
+                        Some(archive_id) => mailbox_ids.push(archive_id),
+                        None => {
+                            mailbox_ids.push(INBOX_ID);
+                            if !keywords.contains(&Keyword::Seen) {
+                                keywords.push(Keyword::Seen);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
         // Obtain a documentId and changeId
         let document_id = self
             .store
@@ -238,7 +296,8 @@ impl JMAP {
         };
         let id = Id::from_parts(thread_id, document_id);
         changes.log_insert(Collection::Email, id);
-        for mailbox_id in &params.mailbox_ids {
+        changes.log_quota_change();
+        for mailbox_id in &mailbox_ids {
             changes.log_child_update(Collection::Mailbox, *mailbox_id);
         }
 
@@ -248,8 +307,8 @@ impl JMAP {
             .create_document(document_id)
             .index_message(
                 message,
-                params.keywords,
-                params.mailbox_ids,
+                keywords,
+                mailbox_ids,
                 params.received_at.unwrap_or_else(now),
                 self.config.default_language,
             )
@@ -387,6 +446,25 @@ impl JMAP {
                     IngestError::Temporary
                 })?;
             let mut changes = ChangeLogBuilder::with_change_id(change_id);
+            let muted_ids = self
+                .store
+                .get_bitmap(BitmapKey::value(
+                    account_id,
+                    Collection::Thread,
+                    Property::IsMuted,
+                    (),
+                ))
+                .await
+                .map_err(|err| {
+                    tracing::error!(
+                        event = "error",
+                        context = "find_or_merge_thread",
+                        error = ?err,
+                        "Failed to obtain muted threads.");
+                    IngestError::Temporary
+                })?
+                .unwrap_or_default();
+            let mut is_muted = false;
             batch
                 .with_account_id(account_id)
                 .with_collection(Collection::Thread);
@@ -394,9 +472,22 @@ impl JMAP {
                 if delete_thread_id != thread_id {
                     batch.delete_document(delete_thread_id);
                     changes.log_delete(Collection::Thread, delete_thread_id);
+
+                    if muted_ids.contains(delete_thread_id) {
+                        batch.bitmap(Property::IsMuted, (), F_CLEAR);
+                        is_muted = true;
+                    }
                 }
             }
 
+            // Keep the merged thread muted if any of its parts was
+            if is_muted && !muted_ids.contains(thread_id) {
+                batch
+                    .update_document(thread_id)
+                    .bitmap(Property::IsMuted, (), 0);
+                changes.log_update(Collection::Thread, thread_id);
+            }
+
             // Move messages to the new threadId
             batch.with_collection(Collection::Email);
             for old_thread_id in thread_ids.into_iter().flatten().collect::<AHashSet<_>>() {
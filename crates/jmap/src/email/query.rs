@@ -239,6 +239,24 @@ impl JMAP {
             }
         }
 
+        // Enable typo-tolerant matching on full-text conditions
+        if self.config.fuzzy_match {
+            filters = filters
+                .into_iter()
+                .map(|filter| filter.with_fuzzy(true))
+                .collect();
+        }
+
+        // Full-text conditions are kept for relevance scoring
+        let mut relevance_filters = if request.sort.as_ref().map_or(false, |sort| {
+            sort.iter()
+                .any(|comparator| comparator.property == SortProperty::Relevance)
+        }) {
+            query::Filter::text_conditions(&filters)
+        } else {
+            vec![]
+        };
+
         let mut result_set = self.filter(account_id, Collection::Email, filters).await?;
         if access_token.is_shared(account_id) {
             result_set.apply_mask(
@@ -308,6 +326,10 @@ impl JMAP {
                     SortProperty::Cc => {
                         query::Comparator::field(Property::Cc, comparator.is_ascending)
                     }
+                    SortProperty::Relevance => query::Comparator::relevance(
+                        std::mem::take(&mut relevance_filters),
+                        comparator.is_ascending,
+                    ),
 
                     other => return Err(MethodError::UnsupportedSort(other.to_string())),
                 });
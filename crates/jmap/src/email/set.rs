@@ -61,7 +61,7 @@ use store::{
     BlobKind, Serialize, ValueKey,
 };
 
-use crate::{auth::AccessToken, IngestError, JMAP};
+use crate::{auth::AccessToken, quota::LogQuotaChange, IngestError, JMAP};
 
 use super::{
     headers::{BuildHeader, ValueToHeader},
@@ -110,6 +110,7 @@ impl JMAP {
 
         // Obtain quota
         let account_quota = self.get_quota(access_token, account_id).await?;
+        let mut added_octets = 0;
 
         // Process creates
         'create: for (id, mut object) in request.unwrap_create() {
@@ -737,10 +738,12 @@ impl JMAP {
                     keywords,
                     received_at,
                     skip_duplicates: false,
+                    skip_muted_inbox: false,
                 })
                 .await
             {
                 Ok(message) => {
+                    added_octets += message.size;
                     response.created.insert(id, message.into());
                 }
                 Err(IngestError::OverQuota) => {
@@ -1029,11 +1032,23 @@ impl JMAP {
                 self.get_state(account_id, Collection::Email).await?
             };
             if let State::Exact(change_id) = &new_state {
-                response.state_change = StateChange::new(account_id)
+                let mut state_change = StateChange::new(account_id)
                     .with_change(TypeState::Email, *change_id)
                     .with_change(TypeState::Mailbox, *change_id)
-                    .with_change(TypeState::Thread, *change_id)
-                    .into();
+                    .with_change(TypeState::Thread, *change_id);
+                if self
+                    .has_crossed_quota_warning(
+                        account_id,
+                        account_quota,
+                        added_octets,
+                        response.created.len(),
+                    )
+                    .await
+                    .unwrap_or(false)
+                {
+                    state_change = state_change.with_change(TypeState::Quota, *change_id);
+                }
+                response.state_change = state_change.into();
             }
 
             response.new_state = new_state.into();
@@ -1150,6 +1165,7 @@ impl JMAP {
 
                 // Log message deletion
                 changes.log_delete(Collection::Email, Id::from_parts(thread_id, document_id));
+                changes.log_quota_change();
             } else {
                 tracing::debug!(
                     event = "error",
@@ -1229,6 +1245,15 @@ impl JMAP {
             batch
                 .with_collection(Collection::Thread)
                 .delete_document(thread_id);
+
+            // Remove muted flag
+            if self
+                .get_tag(account_id, Collection::Thread, Property::IsMuted, ())
+                .await?
+                .map_or(false, |muted_ids| muted_ids.contains(thread_id))
+            {
+                batch.bitmap(Property::IsMuted, (), F_CLEAR);
+            }
         }
 
         // Commit batch
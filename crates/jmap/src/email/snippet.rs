@@ -161,7 +161,12 @@ impl JMAP {
             let mut match_terms = Vec::new();
             for term in &terms {
                 for (word, stemmed_word) in term {
-                    match_terms.push(term_index.get_match_term(word, stemmed_word.as_deref()));
+                    match_terms.push(if self.config.fuzzy_match && !match_phrase {
+                        // Highlight the same typo-tolerant matches returned by Email/query
+                        term_index.get_fuzzy_match_term(word, stemmed_word.as_deref())
+                    } else {
+                        term_index.get_match_term(word, stemmed_word.as_deref())
+                    });
                 }
             }
 
@@ -45,6 +45,7 @@ impl JMAP {
             Property::TextSignature,
             Property::HtmlSignature,
             Property::MayDelete,
+            Property::IsVerified,
         ]);
         let account_id = request.account_id.document_id();
         let identity_ids = self
@@ -100,6 +101,15 @@ impl JMAP {
                     Property::MayDelete => {
                         result.append(Property::MayDelete, Value::Bool(true));
                     }
+                    Property::IsVerified => {
+                        result.append(
+                            Property::IsVerified,
+                            Value::Bool(!push.properties.contains_key(&Property::VerificationCode)),
+                        );
+                    }
+                    Property::VerificationCode => {
+                        result.append(Property::VerificationCode, Value::Null);
+                    }
                     property => {
                         result.append(property.clone(), push.remove(property));
                     }
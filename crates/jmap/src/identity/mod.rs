@@ -23,3 +23,5 @@
 
 pub mod get;
 pub mod set;
+pub mod signature;
+pub mod verify;
@@ -21,8 +21,13 @@
  * for more details.
 */
 
+use std::sync::Arc;
+
 use jmap_proto::{
-    error::{method::MethodError, set::SetError},
+    error::{
+        method::MethodError,
+        set::{SetError, SetErrorType},
+    },
     method::set::{RequestArguments, SetRequest, SetResponse},
     object::Object,
     response::references::EvalObjectReferences,
@@ -33,6 +38,7 @@ use jmap_proto::{
     },
 };
 use store::write::{log::ChangeLogBuilder, BatchBuilder, F_CLEAR, F_VALUE};
+use utils::listener::ServerInstance;
 
 use crate::{auth::AccessToken, JMAP};
 
@@ -41,6 +47,7 @@ impl JMAP {
         &self,
         mut request: SetRequest<RequestArguments>,
         access_token: &AccessToken,
+        instance: &Arc<ServerInstance>,
     ) -> Result<SetResponse, MethodError> {
         let account_id = request.account_id.document_id();
         let mut identity_ids = self
@@ -86,15 +93,47 @@ impl JMAP {
                     .unwrap_or_default()
                     .contains(email)
                 {
-                    response.not_created.append(
-                        id,
-                        SetError::invalid_properties()
-                            .with_property(Property::Email)
-                            .with_description(
-                                "E-mail address not configured for this account.".to_string(),
-                            ),
-                    );
-                    continue 'create;
+                    // External addresses can be added after verifying their ownership
+                    if self.identity_verify_external()
+                        && !self
+                            .directory
+                            .is_local_domain(email.rsplit_once('@').map_or("", |(_, d)| d))
+                            .await
+                            .unwrap_or(true)
+                    {
+                        if !self.is_identity_verification_allowed(account_id) {
+                            response.not_created.append(
+                                id,
+                                SetError::new(SetErrorType::RateLimit).with_description(
+                                    "Too many verification messages sent, try again later.",
+                                ),
+                            );
+                            continue 'create;
+                        }
+
+                        match self
+                            .send_identity_verification(instance, &account_name, email)
+                            .await
+                        {
+                            Ok(code) => {
+                                identity.set(Property::VerificationCode, Value::Text(code));
+                            }
+                            Err(err) => {
+                                response.not_created.append(id, err);
+                                continue 'create;
+                            }
+                        }
+                    } else {
+                        response.not_created.append(
+                            id,
+                            SetError::invalid_properties()
+                                .with_property(Property::Email)
+                                .with_description(
+                                    "E-mail address not configured for this account.".to_string(),
+                                ),
+                        );
+                        continue 'create;
+                    }
                 }
             } else {
                 response.not_created.append(
@@ -227,6 +266,23 @@ fn validate_identity_value(
             Property::TextSignature | Property::HtmlSignature,
             MaybePatchValue::Value(Value::Text(value)),
         ) if value.len() < 2048 => Value::Text(value),
+        (Property::VerificationCode, MaybePatchValue::Value(Value::Text(value)))
+            if current.is_some() =>
+        {
+            match current.map(|identity| identity.get(&Property::VerificationCode)) {
+                Some(Value::Text(code)) if code == &value => Value::Null,
+                Some(Value::Text(_)) => {
+                    return Err(SetError::invalid_properties()
+                        .with_property(Property::VerificationCode)
+                        .with_description("Invalid verification code."));
+                }
+                _ => {
+                    return Err(SetError::invalid_properties()
+                        .with_property(Property::VerificationCode)
+                        .with_description("Identity is already verified."));
+                }
+            }
+        }
         (Property::ReplyTo | Property::Bcc, MaybePatchValue::Value(Value::List(value))) => {
             for addr in &value {
                 let mut is_valid = false;
@@ -0,0 +1,279 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{borrow::Cow, ops::Range};
+
+use base64::{engine::general_purpose, Engine};
+use mail_parser::{
+    decoders::html::html_to_text, Encoding, Message, MessagePart, MimeHeaders, PartType,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct Signature {
+    pub text: Option<String>,
+    pub html: Option<String>,
+}
+
+impl Signature {
+    fn as_text(&self) -> Option<Cow<'_, str>> {
+        self.text
+            .as_deref()
+            .map(Cow::from)
+            .or_else(|| self.html.as_deref().map(|html| html_to_text(html).into()))
+            .filter(|text| !text.trim().is_empty())
+    }
+
+    fn as_html(&self) -> Option<Cow<'_, str>> {
+        self.html
+            .as_deref()
+            .map(Cow::from)
+            .or_else(|| self.text.as_deref().map(|text| text_to_html(text).into()))
+            .filter(|html| !html.trim().is_empty())
+    }
+}
+
+// Appends the identity signature and the domain footer to the main text and HTML parts
+pub fn insert_signature(
+    raw_message: &[u8],
+    signature: Option<&Signature>,
+    footer: Option<&Signature>,
+) -> Option<Vec<u8>> {
+    let message = Message::parse(raw_message)?;
+    let mut part_ids = message
+        .text_body
+        .first()
+        .into_iter()
+        .chain(message.html_body.first())
+        .copied()
+        .collect::<Vec<_>>();
+    part_ids.dedup();
+
+    let mut changes: Vec<(Range<usize>, Vec<u8>)> = Vec::new();
+    for part_id in part_ids {
+        let part = if let Some(part) = message.parts.get(part_id) {
+            part
+        } else {
+            continue;
+        };
+
+        // Avoid mixing charsets
+        let charset = part.content_type().and_then(|ct| ct.attribute("charset"));
+        if let Some(charset) = charset {
+            if !["utf-8", "utf8", "us-ascii"]
+                .iter()
+                .any(|cs| charset.eq_ignore_ascii_case(cs))
+            {
+                continue;
+            }
+        }
+
+        let (body, is_html) = match &part.body {
+            PartType::Text(text) => (text.as_ref(), false),
+            PartType::Html(html) => (html.as_ref(), true),
+            _ => continue,
+        };
+
+        // Build addition, skipping signatures that are already present
+        let mut addition = String::new();
+        if let Some(signature) =
+            signature.and_then(|s| if is_html { s.as_html() } else { s.as_text() })
+        {
+            let signature_crlf = to_crlf(signature.trim());
+            if !body.contains(signature.trim()) && !body.contains(&signature_crlf) {
+                if is_html {
+                    addition.push_str("<div class=\"signature\">-- <br>");
+                    addition.push_str(&signature);
+                    addition.push_str("</div>");
+                } else {
+                    addition.push_str("\r\n\r\n-- \r\n");
+                    addition.push_str(&signature_crlf);
+                }
+            }
+        }
+        if let Some(footer) = footer.and_then(|s| if is_html { s.as_html() } else { s.as_text() }) {
+            let body = body[..insert_position(body.as_bytes(), is_html)].trim_end();
+            if !body.ends_with(footer.trim()) && !body.ends_with(&to_crlf(footer.trim())) {
+                if is_html {
+                    addition.push_str("<div class=\"footer\">");
+                    addition.push_str(&footer);
+                    addition.push_str("</div>");
+                } else {
+                    addition.push_str("\r\n\r\n");
+                    addition.push_str(&to_crlf(footer.trim_end()));
+                }
+            }
+        }
+        if addition.is_empty() {
+            continue;
+        }
+
+        // Non-ASCII text can only be added to UTF-8 parts that are able to carry it
+        if !addition.is_ascii() && (!is_utf8(charset) || !is_8bit_safe(part)) {
+            continue;
+        }
+
+        match part.encoding {
+            Encoding::None => {
+                let raw_body = raw_message.get(part.offset_body..part.offset_end)?;
+                let offset = part.offset_body + insert_position(raw_body, is_html);
+                changes.push((offset..offset, addition.into_bytes()));
+            }
+            Encoding::QuotedPrintable | Encoding::Base64 => {
+                let raw_body = raw_message.get(part.offset_body..part.offset_end)?;
+                let pos = insert_position(body.as_bytes(), is_html);
+                let mut new_body = String::with_capacity(body.len() + addition.len());
+                new_body.push_str(&body[..pos]);
+                new_body.push_str(&addition);
+                new_body.push_str(&body[pos..]);
+
+                let mut encoded = if matches!(part.encoding, Encoding::Base64) {
+                    base64_encode_mime(new_body.as_bytes())
+                } else {
+                    quoted_printable_encode(new_body.as_bytes())
+                };
+                encoded.extend_from_slice(&raw_body[trim_end_len(raw_body)..]);
+                changes.push((part.offset_body..part.offset_end, encoded));
+            }
+        }
+    }
+
+    if !changes.is_empty() {
+        changes.sort_unstable_by_key(|(range, _)| range.start);
+        let mut result = Vec::with_capacity(raw_message.len() + 256);
+        let mut last_offset = 0;
+        for (range, value) in changes {
+            result.extend_from_slice(&raw_message[last_offset..range.start]);
+            result.extend_from_slice(&value);
+            last_offset = range.end;
+        }
+        result.extend_from_slice(&raw_message[last_offset..]);
+        Some(result)
+    } else {
+        None
+    }
+}
+
+fn is_utf8(charset: Option<&str>) -> bool {
+    charset.map_or(false, |charset| {
+        charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("utf8")
+    })
+}
+
+// Unencoded parts have to be declared as 8bit or binary to contain raw UTF-8
+fn is_8bit_safe(part: &MessagePart) -> bool {
+    !matches!(part.encoding, Encoding::None)
+        || part.content_transfer_encoding().map_or(false, |encoding| {
+            encoding.eq_ignore_ascii_case("8bit") || encoding.eq_ignore_ascii_case("binary")
+        })
+}
+
+fn insert_position(body: &[u8], is_html: bool) -> usize {
+    if is_html {
+        if let Some(pos) = body
+            .windows(7)
+            .rposition(|w| w.eq_ignore_ascii_case(b"</body>"))
+        {
+            return pos;
+        }
+    }
+    trim_end_len(body)
+}
+
+fn trim_end_len(body: &[u8]) -> usize {
+    body.len()
+        - body
+            .iter()
+            .rev()
+            .take_while(|ch| ch.is_ascii_whitespace())
+            .count()
+}
+
+fn to_crlf(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\n', "\r\n")
+}
+
+fn text_to_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len() + 16);
+    for ch in text.trim_end().chars() {
+        match ch {
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '&' => result.push_str("&amp;"),
+            '"' => result.push_str("&quot;"),
+            '\n' => result.push_str("<br>"),
+            '\r' => (),
+            _ => result.push(ch),
+        }
+    }
+    result
+}
+
+fn base64_encode_mime(bytes: &[u8]) -> Vec<u8> {
+    let encoded = general_purpose::STANDARD.encode(bytes);
+    let mut result = Vec::with_capacity(encoded.len() + (encoded.len() / 76) * 2);
+    for (pos, chunk) in encoded.as_bytes().chunks(76).enumerate() {
+        if pos > 0 {
+            result.extend_from_slice(b"\r\n");
+        }
+        result.extend_from_slice(chunk);
+    }
+    result
+}
+
+fn quoted_printable_encode(bytes: &[u8]) -> Vec<u8> {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    let mut result = Vec::with_capacity(bytes.len() + bytes.len() / 4);
+    let mut line_len = 0;
+    let mut iter = bytes.iter().peekable();
+
+    while let Some(&ch) = iter.next() {
+        let next_ch = iter.peek().map(|ch| **ch);
+        if ch == b'\n' || (ch == b'\r' && next_ch == Some(b'\n')) {
+            if ch == b'\r' {
+                iter.next();
+            }
+            result.extend_from_slice(b"\r\n");
+            line_len = 0;
+            continue;
+        }
+
+        let is_eol_space =
+            matches!(ch, b' ' | b'\t') && matches!(next_ch, None | Some(b'\r' | b'\n'));
+        let do_encode = ch == b'=' || is_eol_space || !(ch.is_ascii_graphic() || ch == b' ');
+        let len = if do_encode { 3 } else { 1 };
+        if line_len + len > 75 {
+            result.extend_from_slice(b"=\r\n");
+            line_len = 0;
+        }
+        if do_encode {
+            result.push(b'=');
+            result.push(HEX[(ch >> 4) as usize]);
+            result.push(HEX[(ch & 0x0f) as usize]);
+        } else {
+            result.push(ch);
+        }
+        line_len += len;
+    }
+
+    result
+}
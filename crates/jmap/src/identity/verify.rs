@@ -0,0 +1,120 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use jmap_proto::error::set::{SetError, SetErrorType};
+use mail_builder::{headers::HeaderType, mime::make_boundary, MessageBuilder};
+use smtp_proto::{MailFrom, RcptTo};
+use store::rand::{distributions::Alphanumeric, thread_rng, Rng};
+use utils::listener::ServerInstance;
+
+use crate::JMAP;
+
+const VERIFICATION_CODE_LEN: usize = 32;
+
+#[cfg(feature = "test_mode")]
+pub static TEST_VERIFY_EXTERNAL: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
+
+impl JMAP {
+    pub fn identity_verify_external(&self) -> bool {
+        #[cfg(feature = "test_mode")]
+        {
+            if TEST_VERIFY_EXTERNAL.load(std::sync::atomic::Ordering::Relaxed) {
+                return true;
+            }
+        }
+
+        self.config.identity_verify_external
+    }
+
+    pub(crate) async fn send_identity_verification(
+        &self,
+        instance: &Arc<ServerInstance>,
+        account_name: &str,
+        email: &str,
+    ) -> Result<String, SetError> {
+        let code = thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(VERIFICATION_CODE_LEN)
+            .map(char::from)
+            .collect::<String>();
+
+        let message = MessageBuilder::new()
+            .from((
+                "Mail Server",
+                format!("postmaster@{}", instance.hostname).as_str(),
+            ))
+            .to(email)
+            .message_id(format!("<{}@{}>", make_boundary("."), instance.hostname))
+            .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+            .subject("Confirm your e-mail address")
+            .text_body(format!(
+                concat!(
+                    "The user '{}' has requested to send e-mail messages\r\n",
+                    "using the address <{}>.\r\n\r\n",
+                    "To confirm that this address belongs to you, enter\r\n",
+                    "the following code in your e-mail client:\r\n\r\n",
+                    "Verification code: {}\r\n\r\n",
+                    "If you did not make this request, you can safely\r\n",
+                    "ignore this message.\r\n"
+                ),
+                account_name, email, code
+            ))
+            .write_to_vec()
+            .unwrap_or_default();
+
+        // Send the verification message using a null return path
+        let result = self
+            .submit_message(
+                instance,
+                MailFrom {
+                    address: String::new(),
+                    ..Default::default()
+                },
+                vec![RcptTo {
+                    address: email.to_string(),
+                    ..Default::default()
+                }],
+                message,
+            )
+            .await?;
+        if result.queue_id.is_some() {
+            Ok(code)
+        } else {
+            Err(
+                SetError::new(SetErrorType::ForbiddenToSend).with_description(format!(
+                    "Failed to send verification message: {}",
+                    result
+                        .responses
+                        .into_iter()
+                        .next()
+                        .and_then(|(_, response, _)| response)
+                        .unwrap_or_default()
+                        .trim()
+                )),
+            )
+        }
+    }
+}
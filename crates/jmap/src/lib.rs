@@ -21,17 +21,22 @@
  * for more details.
 */
 
-use std::{collections::hash_map::RandomState, sync::Arc, time::Duration};
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    sync::Arc,
+    time::Duration,
+};
 
 use ::sieve::{Compiler, Runtime};
-use api::session::BaseCapabilities;
+use api::{admin::MigrationTask, session::BaseCapabilities};
 use auth::{
     oauth::OAuthCode,
     rate_limit::{AnonymousLimiter, AuthenticatedLimiter, RemoteAddress},
     AccessToken,
 };
 use dashmap::DashMap;
-use directory::{Directory, DirectoryConfig};
+use directory::{internal::InternalDirectory, Directory, DirectoryConfig};
+use identity::signature::Signature;
 use jmap_proto::{
     error::method::MethodError,
     method::{
@@ -40,6 +45,7 @@ use jmap_proto::{
     },
     types::{collection::Collection, property::Property},
 };
+use push::vapid::VapidKey;
 use services::{
     delivery::spawn_delivery_manager,
     housekeeper::{self, init_housekeeper, spawn_housekeeper},
@@ -69,9 +75,12 @@ pub mod changes;
 pub mod email;
 pub mod identity;
 pub mod mailbox;
+pub mod mdn;
 pub mod principal;
 pub mod push;
+pub mod quota;
 pub mod services;
+pub mod share_notification;
 pub mod sieve;
 pub mod submission;
 pub mod thread;
@@ -81,9 +90,10 @@ pub mod websocket;
 pub const LONG_SLUMBER: Duration = Duration::from_secs(60 * 60 * 24);
 
 pub struct JMAP {
-    pub store: Store,
+    pub store: Arc<Store>,
     pub config: Config,
     pub directory: Arc<dyn Directory>,
+    pub internal_directory: Option<Arc<InternalDirectory>>,
 
     pub sessions: TtlDashMap<String, u32>,
     pub access_tokens: TtlDashMap<u32, Arc<AccessToken>>,
@@ -99,10 +109,13 @@ pub struct JMAP {
 
     pub sieve_compiler: Compiler,
     pub sieve_runtime: Runtime,
+
+    pub migration: Mutex<Option<Arc<MigrationTask>>>,
 }
 
 pub struct Config {
     pub default_language: Language,
+    pub fuzzy_match: bool,
     pub query_max_results: usize,
     pub changes_max_results: usize,
 
@@ -128,15 +141,18 @@ pub struct Config {
 
     pub sieve_max_script_name: usize,
     pub sieve_max_scripts: usize,
+    pub sieve_vacation_interval: u64,
 
     pub session_cache_ttl: Duration,
     pub rate_authenticated: Rate,
     pub rate_authenticate_req: Rate,
     pub rate_anonymous: Rate,
+    pub rate_identity_verify: Rate,
     pub rate_use_forwarded: bool,
 
     pub event_source_throttle: Duration,
     pub push_max_total: usize,
+    pub push_vapid: VapidKey,
 
     pub web_socket_throttle: Duration,
     pub web_socket_timeout: Duration,
@@ -152,6 +168,13 @@ pub struct Config {
 
     pub principal_allow_lookups: bool,
 
+    pub identity_insert_signature: bool,
+    pub identity_verify_external: bool,
+    pub identity_footers: HashMap<String, Signature>,
+
+    pub quota_warn_threshold: u64,
+    pub quota_max_messages: u64,
+
     pub capabilities: BaseCapabilities,
 }
 
@@ -180,6 +203,26 @@ impl JMAP {
             .unwrap_or(32)
             .next_power_of_two() as usize;
 
+        // Open the store and share it with the internal directory
+        let store = Arc::new(Store::open(config).await.failed("Unable to open database"));
+        if let Some(internal_directory) = &directory_config.internal {
+            internal_directory.set_store(store.clone());
+        }
+        if smtp.store.set(store.clone()).is_err() {
+            tracing::debug!(
+                context = "smtp",
+                event = "error",
+                "Store already set for SMTP server."
+            );
+        }
+
+        let mut jmap_config = Config::new(config).failed("Invalid configuration file");
+        jmap_config
+            .push_vapid
+            .load_or_store(&store)
+            .await
+            .failed("Unable to load VAPID key");
+
         let jmap_server = Arc::new(JMAP {
             directory: directory_config
                 .directories
@@ -189,8 +232,9 @@ impl JMAP {
                     config.value_require("jmap.directory")?
                 ))
                 .clone(),
-            store: Store::open(config).await.failed("Unable to open database"),
-            config: Config::new(config).failed("Invalid configuration file"),
+            store,
+            internal_directory: directory_config.internal.clone(),
+            config: jmap_config,
             sessions: TtlDashMap::with_capacity(
                 config.property("jmap.session.cache.size")?.unwrap_or(100),
                 shard_amount,
@@ -355,8 +399,12 @@ impl JMAP {
                 .with_env_variable("version", env!("CARGO_PKG_VERSION"))
                 .with_env_variable("location", "MS")
                 .with_env_variable("phase", "during"),
+            migration: Mutex::new(None),
         });
 
+        // Initialize missing message counters before any message is written
+        jmap_server.init_message_counts().await;
+
         // Spawn delivery manager
         spawn_delivery_manager(jmap_server.clone(), delivery_rx);
 
@@ -558,20 +606,7 @@ impl JMAP {
         Ok(if access_token.primary_id == account_id {
             access_token.quota as i64
         } else {
-            self.directory
-                .principal(&access_token.name)
-                .await
-                .map_err(|err| {
-                    tracing::error!(
-                        event = "error",
-                        context = "get_quota",
-                        account_id = account_id,
-                        error = ?err,
-                        "Failed to obtain disk quota for account.");
-                    MethodError::ServerPartialFail
-                })?
-                .map(|p| p.quota as i64)
-                .unwrap_or_default()
+            self.get_account_quota(account_id).await? as i64
         })
     }
 
@@ -28,6 +28,7 @@ use jmap_proto::{
     types::{acl::Acl, collection::Collection, keyword::Keyword, property::Property, value::Value},
 };
 use store::{ahash::AHashSet, query::Filter, roaring::RoaringBitmap};
+use utils::map::bitmap::Bitmap;
 
 use crate::{
     auth::{acl::EffectiveAcl, AccessToken},
@@ -180,27 +181,7 @@ impl JMAP {
                     ),
                     Property::MyRights => {
                         if access_token.is_shared(account_id) {
-                            let acl = values.effective_acl(access_token);
-                            Object::with_capacity(9)
-                                .with_property(Property::MayReadItems, acl.contains(Acl::ReadItems))
-                                .with_property(Property::MayAddItems, acl.contains(Acl::AddItems))
-                                .with_property(
-                                    Property::MayRemoveItems,
-                                    acl.contains(Acl::RemoveItems),
-                                )
-                                .with_property(Property::MaySetSeen, acl.contains(Acl::ModifyItems))
-                                .with_property(
-                                    Property::MaySetKeywords,
-                                    acl.contains(Acl::ModifyItems),
-                                )
-                                .with_property(
-                                    Property::MayCreateChild,
-                                    acl.contains(Acl::CreateChild),
-                                )
-                                .with_property(Property::MayRename, acl.contains(Acl::Modify))
-                                .with_property(Property::MayDelete, acl.contains(Acl::Delete))
-                                .with_property(Property::MaySubmit, acl.contains(Acl::Submit))
-                                .into()
+                            mailbox_rights(&values.effective_acl(access_token)).into()
                         } else {
                             Object::with_capacity(9)
                                 .with_property(Property::MayReadItems, true)
@@ -430,3 +411,16 @@ pub struct ExpandPath<'x> {
     pub path: Vec<&'x str>,
     pub found_names: Vec<(String, u32, u32)>,
 }
+
+pub fn mailbox_rights(acl: &Bitmap<Acl>) -> Object<Value> {
+    Object::with_capacity(9)
+        .with_property(Property::MayReadItems, acl.contains(Acl::ReadItems))
+        .with_property(Property::MayAddItems, acl.contains(Acl::AddItems))
+        .with_property(Property::MayRemoveItems, acl.contains(Acl::RemoveItems))
+        .with_property(Property::MaySetSeen, acl.contains(Acl::ModifyItems))
+        .with_property(Property::MaySetKeywords, acl.contains(Acl::ModifyItems))
+        .with_property(Property::MayCreateChild, acl.contains(Acl::CreateChild))
+        .with_property(Property::MayRename, acl.contains(Acl::Modify))
+        .with_property(Property::MayDelete, acl.contains(Acl::Delete))
+        .with_property(Property::MaySubmit, acl.contains(Acl::Submit))
+}
@@ -113,6 +113,8 @@ impl JMAP {
                     let document_id = self
                         .assign_document_id(account_id, Collection::Mailbox)
                         .await?;
+                    let acl_changes = builder.get(&Property::Acl).clone();
+                    let mailbox_name = builder.get(&Property::Name).clone();
                     batch
                         .with_account_id(account_id)
                         .with_collection(Collection::Mailbox)
@@ -122,6 +124,19 @@ impl JMAP {
                     ctx.mailbox_ids.insert(document_id);
                     self.write_batch(batch).await?;
                     ctx.response.created(id, document_id);
+
+                    // Notify grantees
+                    if acl_changes != Value::Null {
+                        self.share_notification_create(
+                            access_token.primary_id(),
+                            account_id,
+                            document_id,
+                            &mailbox_name,
+                            &Value::Null,
+                            &acl_changes,
+                        )
+                        .await;
+                    }
                 }
                 Err(err) => {
                     ctx.response.not_created.append(id, err);
@@ -174,11 +189,31 @@ impl JMAP {
                     }
                 }
 
+                // Keep the current permissions in order to notify grantees
+                let acl_current = if object.properties.contains_key(&Property::Acl) {
+                    mailbox
+                        .inner
+                        .properties
+                        .get(&Property::Acl)
+                        .cloned()
+                        .unwrap_or(Value::Null)
+                        .into()
+                } else {
+                    None
+                };
+
                 match self
                     .mailbox_set_item(object, (document_id, mailbox).into(), &ctx)
                     .await?
                 {
                     Ok(builder) => {
+                        let acl_changes = acl_current.map(|acl_current| {
+                            (
+                                acl_current,
+                                builder.get(&Property::Acl).clone(),
+                                builder.get(&Property::Name).clone(),
+                            )
+                        });
                         let mut batch = BatchBuilder::new();
                         batch
                             .with_account_id(account_id)
@@ -189,6 +224,21 @@ impl JMAP {
                             match self.store.write(batch.build()).await {
                                 Ok(_) => {
                                     changes.log_update(Collection::Mailbox, document_id);
+
+                                    // Notify grantees
+                                    if let Some((acl_current, acl_changes, mailbox_name)) =
+                                        acl_changes
+                                    {
+                                        self.share_notification_create(
+                                            access_token.primary_id(),
+                                            account_id,
+                                            document_id,
+                                            &mailbox_name,
+                                            &acl_current,
+                                            &acl_changes,
+                                        )
+                                        .await;
+                                    }
                                 }
                                 Err(store::Error::AssertValueFailed) => {
                                     ctx.response.not_updated.append(id, SetError::forbidden().with_description(
@@ -0,0 +1,141 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::mdn::{Disposition, Mdn, MdnParseRequest, MdnParseResponse},
+};
+use mail_parser::{Message, PartType};
+use utils::map::vec_map::VecMap;
+
+use crate::{auth::AccessToken, JMAP};
+
+impl JMAP {
+    pub async fn mdn_parse(
+        &self,
+        request: MdnParseRequest,
+        access_token: &AccessToken,
+    ) -> Result<MdnParseResponse, MethodError> {
+        if request.blob_ids.len() > self.config.mail_parse_max_items {
+            return Err(MethodError::RequestTooLarge);
+        }
+        let mut response = MdnParseResponse {
+            account_id: request.account_id,
+            parsed: VecMap::with_capacity(request.blob_ids.len()),
+            not_parsable: vec![],
+            not_found: vec![],
+        };
+
+        for blob_id in request.blob_ids {
+            // Fetch raw message to parse
+            let raw_message = match self.blob_download(&blob_id, access_token).await? {
+                Some(raw_message) => raw_message,
+                None => {
+                    response.not_found.push(blob_id);
+                    continue;
+                }
+            };
+            if let Some(mdn) = Message::parse(&raw_message).and_then(|message| parse_mdn(&message))
+            {
+                response.parsed.append(blob_id, mdn);
+            } else {
+                response.not_parsable.push(blob_id);
+            }
+        }
+
+        Ok(response)
+    }
+}
+
+fn parse_mdn(message: &Message<'_>) -> Option<Mdn> {
+    let mut mdn = Mdn {
+        subject: message.subject().map(|s| s.to_string()),
+        text_body: message.body_text(0).map(|t| t.into_owned()),
+        ..Default::default()
+    };
+    let mut report = None;
+
+    for part in &message.parts {
+        if part.is_content_type("message", "disposition-notification") {
+            report = match &part.body {
+                PartType::Text(text) => text.as_bytes().into(),
+                PartType::Binary(bytes) | PartType::InlineBinary(bytes) => bytes.as_ref().into(),
+                _ => None,
+            };
+        } else if matches!(part.body, PartType::Message(_)) {
+            mdn.include_original_message = true;
+        }
+    }
+
+    let mut has_disposition = false;
+    for (name, value) in unfold_fields(std::str::from_utf8(report?).ok()?) {
+        if name.eq_ignore_ascii_case("Reporting-UA") {
+            mdn.reporting_ua = value.into();
+        } else if name.eq_ignore_ascii_case("MDN-Gateway") {
+            mdn.mdn_gateway = value.into();
+        } else if name.eq_ignore_ascii_case("Original-Recipient") {
+            mdn.original_recipient = value.into();
+        } else if name.eq_ignore_ascii_case("Final-Recipient") {
+            mdn.final_recipient = value.into();
+        } else if name.eq_ignore_ascii_case("Original-Message-ID") {
+            mdn.original_message_id = value.into();
+        } else if name.eq_ignore_ascii_case("Error") {
+            mdn.error.get_or_insert_with(Vec::new).push(value);
+        } else if name.eq_ignore_ascii_case("Disposition") {
+            let (mode, type_) = value.split_once(';')?;
+            let (action_mode, sending_mode) = mode.split_once('/')?;
+            let type_ = type_.split_once('/').map_or(type_, |(t, _)| t);
+            mdn.disposition = Disposition {
+                action_mode: action_mode.trim().to_lowercase(),
+                sending_mode: sending_mode.trim().to_lowercase(),
+                type_: type_.trim().to_lowercase(),
+            };
+            has_disposition = true;
+        } else {
+            mdn.extension_fields
+                .get_or_insert_with(VecMap::new)
+                .append(name, value);
+        }
+    }
+
+    if has_disposition {
+        Some(mdn)
+    } else {
+        None
+    }
+}
+
+fn unfold_fields(report: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in report.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            fields.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    fields
+}
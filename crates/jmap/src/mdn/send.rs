@@ -0,0 +1,473 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{collections::HashMap, sync::Arc};
+
+use jmap_proto::{
+    error::{
+        method::MethodError,
+        set::{SetError, SetErrorType},
+    },
+    method::{
+        mdn::{Mdn, MdnSendRequest, MdnSendResponse},
+        set::{self, SetRequest},
+    },
+    object::Object,
+    request::{
+        method::{MethodFunction, MethodName, MethodObject},
+        reference::MaybeReference,
+        Call, RequestMethod,
+    },
+    types::{
+        collection::Collection,
+        id::Id,
+        keyword::Keyword,
+        property::{HeaderForm, HeaderProperty, Property},
+        value::{SetValue, Value},
+    },
+};
+use mail_builder::{
+    headers::content_type::ContentType,
+    mime::{make_boundary, BodyPart, MimePart},
+    MessageBuilder,
+};
+use mail_parser::Message;
+use smtp_proto::{MailFrom, RcptTo};
+use store::BlobKind;
+use utils::{listener::ServerInstance, map::vec_map::VecMap};
+
+use crate::{email::headers::HeaderToValue, identity::set::sanitize_email, JMAP};
+
+impl JMAP {
+    pub async fn mdn_send(
+        &self,
+        request: MdnSendRequest,
+        instance: &Arc<ServerInstance>,
+        next_call: &mut Option<Call<RequestMethod>>,
+    ) -> Result<MdnSendResponse, MethodError> {
+        if request.send.len() > self.config.set_max_objects {
+            return Err(MethodError::RequestTooLarge);
+        }
+
+        let account_id = request.account_id.document_id();
+        let mut response = MdnSendResponse {
+            account_id: request.account_id,
+            sent: VecMap::with_capacity(request.send.len()),
+            not_sent: VecMap::new(),
+        };
+
+        // Fetch identity
+        let mut identity = self
+            .get_property::<Object<Value>>(
+                account_id,
+                Collection::Identity,
+                request.identity_id.document_id(),
+                Property::Value,
+            )
+            .await?
+            .ok_or_else(|| {
+                MethodError::InvalidArguments(format!(
+                    "Identity {} not found.",
+                    request.identity_id
+                ))
+            })?;
+        if identity
+            .properties
+            .contains_key(&Property::VerificationCode)
+        {
+            return Err(MethodError::Forbidden(
+                "Identity e-mail address has not been verified.".into(),
+            ));
+        }
+        let from_addr = identity
+            .remove(&Property::Email)
+            .try_unwrap_string()
+            .unwrap_or_default();
+        let from_name = identity
+            .remove(&Property::Name)
+            .try_unwrap_string()
+            .unwrap_or_default();
+
+        // Send MDNs
+        let mut sent_email_ids = HashMap::new();
+        for (id, mdn) in request.send {
+            match self
+                .send_mdn(account_id, mdn, &from_name, &from_addr, instance)
+                .await?
+            {
+                Ok(mdn) => {
+                    sent_email_ids.insert(id.clone(), mdn.for_email_id.unwrap());
+                    response.sent.append(id, mdn);
+                }
+                Err(err) => {
+                    response.not_sent.append(id, err);
+                }
+            }
+        }
+
+        // Set the $mdnsent keyword and apply any onSuccessUpdateEmail changes
+        if !sent_email_ids.is_empty() {
+            let mut update: VecMap<Id, Object<SetValue>> = VecMap::new();
+            for email_id in sent_email_ids.values() {
+                update
+                    .get_mut_or_insert_with(*email_id, || Object {
+                        properties: VecMap::new(),
+                    })
+                    .properties
+                    .append(
+                        Property::Keywords,
+                        SetValue::Patch(vec![Value::Keyword(Keyword::MdnSent), Value::Bool(true)]),
+                    );
+            }
+            for (id, value) in request.on_success_update_email.unwrap_or_default() {
+                let email_id = match id {
+                    MaybeReference::Value(id) => id,
+                    MaybeReference::Reference(id_ref) => {
+                        if let Some(id) = sent_email_ids.get(&id_ref) {
+                            *id
+                        } else {
+                            continue;
+                        }
+                    }
+                };
+                let properties = &mut update
+                    .get_mut_or_insert_with(email_id, || Object {
+                        properties: VecMap::new(),
+                    })
+                    .properties;
+                for (property, value) in value.properties {
+                    properties.append(property, value);
+                }
+            }
+
+            *next_call = Call {
+                id: String::new(),
+                name: MethodName::new(MethodObject::Email, MethodFunction::Set),
+                method: RequestMethod::Set(SetRequest {
+                    account_id: request.account_id,
+                    if_in_state: None,
+                    create: None,
+                    update: update.into(),
+                    destroy: None,
+                    arguments: set::RequestArguments::Email,
+                }),
+            }
+            .into();
+        }
+
+        Ok(response)
+    }
+
+    async fn send_mdn(
+        &self,
+        account_id: u32,
+        mut mdn: Mdn,
+        from_name: &str,
+        from_addr: &str,
+        instance: &Arc<ServerInstance>,
+    ) -> Result<Result<Mdn, SetError>, MethodError> {
+        // Validate disposition
+        if !matches!(
+            mdn.disposition.action_mode.as_str(),
+            "manual-action" | "automatic-action"
+        ) || !matches!(
+            mdn.disposition.sending_mode.as_str(),
+            "mdn-sent-manually" | "mdn-sent-automatically"
+        ) || !matches!(
+            mdn.disposition.type_.as_str(),
+            "deleted" | "dispatched" | "displayed" | "processed"
+        ) {
+            return Ok(Err(SetError::invalid_properties()
+                .with_property(Property::Disposition)
+                .with_description("Invalid disposition.")));
+        }
+
+        // Report fields are written verbatim, so line breaks would inject header lines
+        let has_line_break = |value: &str| value.contains(['\r', '\n']);
+        for (property, value) in [
+            ("reportingUA", &mdn.reporting_ua),
+            ("mdnGateway", &mdn.mdn_gateway),
+            ("originalRecipient", &mdn.original_recipient),
+            ("finalRecipient", &mdn.final_recipient),
+            ("originalMessageId", &mdn.original_message_id),
+        ] {
+            if value.as_deref().map_or(false, has_line_break) {
+                return Ok(Err(SetError::invalid_properties()
+                    .with_property(Property::_T(property.to_string()))
+                    .with_description("Field values may not contain line breaks.")));
+            }
+        }
+        if mdn
+            .error
+            .iter()
+            .flatten()
+            .any(|error| has_line_break(error))
+        {
+            return Ok(Err(SetError::invalid_properties()
+                .with_property(Property::_T("error".to_string()))
+                .with_description("Field values may not contain line breaks.")));
+        }
+        if mdn
+            .extension_fields
+            .iter()
+            .flat_map(|fields| fields.iter())
+            .any(|(name, value)| {
+                name.is_empty()
+                    || !name.bytes().all(|ch| ch.is_ascii_graphic() && ch != b':')
+                    || has_line_break(value)
+            })
+        {
+            return Ok(Err(SetError::invalid_properties()
+                .with_property(Property::_T("extensionFields".to_string()))
+                .with_description("Invalid extension field name or value.")));
+        }
+
+        // Obtain email
+        let email_id = if let Some(email_id) = mdn.for_email_id {
+            email_id
+        } else {
+            return Ok(Err(SetError::invalid_properties()
+                .with_property(Property::_T("forEmailId".to_string()))
+                .with_description("Missing forEmailId.")));
+        };
+        let document_id = email_id.document_id();
+        if let Some(keywords) = self
+            .get_property::<Vec<Keyword>>(
+                account_id,
+                Collection::Email,
+                document_id,
+                Property::Keywords,
+            )
+            .await?
+        {
+            if keywords.contains(&Keyword::MdnSent) {
+                return Ok(Err(SetError::new(SetErrorType::MdnAlreadySent)
+                    .with_description("An MDN has already been sent for this email.")));
+            }
+        } else {
+            return Ok(Err(
+                SetError::not_found().with_description(format!("Email {email_id} not found."))
+            ));
+        }
+        let raw_message = if let Some(raw_message) = self
+            .get_blob(
+                &BlobKind::LinkedMaildir {
+                    account_id,
+                    document_id,
+                },
+                0..u32::MAX,
+            )
+            .await?
+        {
+            raw_message
+        } else {
+            return Ok(Err(SetError::not_found().with_description(format!(
+                "Blob for email {email_id} not found."
+            ))));
+        };
+        let message = if let Some(message) = Message::parse(&raw_message) {
+            message
+        } else {
+            return Ok(Err(SetError::invalid_properties()
+                .with_property(Property::_T("forEmailId".to_string()))
+                .with_description("Failed to parse email.")));
+        };
+
+        // Obtain the address that requested the notification
+        let rcpt_addr = if let Some(rcpt_addr) = message.parts[0]
+            .header_to_value(
+                &Property::Header(HeaderProperty {
+                    form: HeaderForm::Addresses,
+                    header: "Disposition-Notification-To".to_string(),
+                    all: false,
+                }),
+                &raw_message,
+            )
+            .try_unwrap_list()
+            .and_then(|addresses| {
+                addresses.into_iter().find_map(|address| {
+                    address
+                        .try_unwrap_object()?
+                        .remove(&Property::Email)
+                        .try_unwrap_string()
+                        .and_then(|addr| sanitize_email(&addr))
+                })
+            }) {
+            rcpt_addr
+        } else {
+            return Ok(Err(SetError::invalid_properties()
+                .with_property(Property::_T("forEmailId".to_string()))
+                .with_description(
+                    "Email does not request a disposition notification.",
+                )));
+        };
+
+        // Complete MDN fields
+        let domain = from_addr.rsplit_once('@').map_or(from_addr, |(_, d)| d);
+        if mdn.subject.is_none() {
+            mdn.subject = format!(
+                "{}: {}",
+                if mdn.disposition.type_ == "displayed" {
+                    "Read"
+                } else {
+                    "Disposition notification"
+                },
+                message.subject().unwrap_or_default()
+            )
+            .into();
+        }
+        if mdn.text_body.is_none() {
+            mdn.text_body = format!(
+                "The message sent to {} with subject \"{}\" has been {}.",
+                from_addr,
+                message.subject().unwrap_or_default(),
+                mdn.disposition.type_
+            )
+            .into();
+        }
+        if mdn.reporting_ua.is_none() {
+            mdn.reporting_ua = format!("{domain}; Stalwart JMAP").into();
+        }
+        if mdn.final_recipient.is_none() {
+            mdn.final_recipient = format!("rfc822; {from_addr}").into();
+        }
+        if mdn.original_recipient.is_none() {
+            mdn.original_recipient = message.parts[0]
+                .header_to_value(
+                    &Property::Header(HeaderProperty {
+                        form: HeaderForm::Text,
+                        header: "Original-Recipient".to_string(),
+                        all: false,
+                    }),
+                    &raw_message,
+                )
+                .try_unwrap_string();
+        }
+        if mdn.original_message_id.is_none() {
+            mdn.original_message_id = message.message_id().map(|id| format!("<{id}>"));
+        }
+
+        // Build report
+        let mut report = String::with_capacity(256);
+        for (name, value) in [
+            ("Reporting-UA", &mdn.reporting_ua),
+            ("MDN-Gateway", &mdn.mdn_gateway),
+            ("Original-Recipient", &mdn.original_recipient),
+            ("Final-Recipient", &mdn.final_recipient),
+            ("Original-Message-ID", &mdn.original_message_id),
+        ] {
+            if let Some(value) = value {
+                report.push_str(name);
+                report.push_str(": ");
+                report.push_str(value);
+                report.push_str("\r\n");
+            }
+        }
+        report.push_str("Disposition: ");
+        report.push_str(&mdn.disposition.action_mode);
+        report.push('/');
+        report.push_str(&mdn.disposition.sending_mode);
+        report.push_str("; ");
+        report.push_str(&mdn.disposition.type_);
+        report.push_str("\r\n");
+        for error in mdn.error.iter().flatten() {
+            report.push_str("Error: ");
+            report.push_str(error);
+            report.push_str("\r\n");
+        }
+        for (name, value) in mdn.extension_fields.iter().flat_map(|f| f.iter()) {
+            report.push_str(name);
+            report.push_str(": ");
+            report.push_str(value);
+            report.push_str("\r\n");
+        }
+
+        let mut parts = vec![
+            MimePart::new(
+                ContentType::new("text/plain"),
+                BodyPart::Text(mdn.text_body.clone().unwrap_or_default().into()),
+            ),
+            MimePart::new(
+                ContentType::new("message/disposition-notification"),
+                BodyPart::Text(report.into()),
+            ),
+        ];
+        if mdn.include_original_message {
+            parts.push(MimePart::new(
+                ContentType::new("message/rfc822"),
+                BodyPart::Text(String::from_utf8_lossy(&raw_message).into_owned().into()),
+            ));
+        }
+        let mdn_message = MessageBuilder::new()
+            .from((from_name, from_addr))
+            .to(rcpt_addr.as_str())
+            .message_id(format!("<{}@{}>", make_boundary("."), domain))
+            .subject(mdn.subject.clone().unwrap_or_default())
+            .body(MimePart::new(
+                ContentType::new("multipart/report")
+                    .attribute("report-type", "disposition-notification"),
+                BodyPart::Multipart(parts),
+            ))
+            .write_to_vec()
+            .unwrap_or_default();
+        if mdn_message.len() > self.config.mail_max_size {
+            return Ok(Err(SetError::new(SetErrorType::TooLarge).with_description(
+                format!(
+                    "Message exceeds maximum size of {} bytes.",
+                    self.config.mail_max_size
+                ),
+            )));
+        }
+
+        // Submit message
+        match self
+            .submit_message(
+                instance,
+                MailFrom {
+                    address: from_addr.to_string(),
+                    ..Default::default()
+                },
+                vec![RcptTo {
+                    address: rcpt_addr,
+                    ..Default::default()
+                }],
+                mdn_message,
+            )
+            .await
+        {
+            Ok(result) if result.queue_id.is_some() => Ok(Ok(mdn)),
+            Ok(result) => Ok(Err(SetError::new(SetErrorType::ForbiddenToSend)
+                .with_description(format!(
+                    "Server rejected RCPT-TO: {}",
+                    result
+                        .responses
+                        .into_iter()
+                        .next()
+                        .and_then(|(_, response, _)| response)
+                        .unwrap_or_default()
+                        .trim()
+                )))),
+            Err(err) => Ok(Err(err)),
+        }
+    }
+}
@@ -23,21 +23,36 @@
 
 use base64::{engine::general_purpose, Engine};
 use jmap_proto::types::id::Id;
-use store::ahash::{AHashMap, AHashSet};
+use store::{
+    ahash::{AHashMap, AHashSet},
+    write::now,
+};
 use tokio::sync::mpsc;
 use utils::{config::Config, UnwrapFailure};
 
-use crate::{api::StateChangeResponse, services::IPC_CHANNEL_BUFFER, LONG_SLUMBER};
+use crate::{api::StateChangeResponse, services::IPC_CHANNEL_BUFFER, JMAP, LONG_SLUMBER};
 
 use super::{ece::ece_encrypt, EncryptionKeys, Event, PushServer, PushUpdate};
 
-use reqwest::header::{CONTENT_ENCODING, CONTENT_TYPE};
+use reqwest::{
+    header::{AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE},
+    StatusCode,
+};
 use std::{
     collections::hash_map::Entry,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
-pub fn spawn_push_manager(settings: &Config) -> mpsc::Sender<Event> {
+const MAX_BACKOFF_EXPONENT: u32 = 10;
+
+enum DeliveryResult {
+    Delivered,
+    Failed,
+    Gone,
+}
+
+pub fn spawn_push_manager(core: Arc<JMAP>, settings: &Config) -> mpsc::Sender<Event> {
     let (push_tx_, mut push_rx) = mpsc::channel::<Event>(IPC_CHANNEL_BUFFER);
     let push_tx = push_tx_.clone();
 
@@ -98,6 +113,8 @@ pub fn spawn_push_manager(settings: &Config) -> mpsc::Sender<Event> {
                                         })
                                         .unwrap_or(true)
                                     {
+                                        let authorization =
+                                            core.config.push_vapid.authorization(&url);
                                         tokio::spawn(async move {
                                             http_request(
                                                 url,
@@ -111,6 +128,7 @@ pub fn spawn_push_manager(settings: &Config) -> mpsc::Sender<Event> {
                                                     code
                                                 ),
                                                 keys,
+                                                authorization,
                                                 push_timeout,
                                             )
                                             .await;
@@ -128,10 +146,16 @@ pub fn spawn_push_manager(settings: &Config) -> mpsc::Sender<Event> {
                                         continue;
                                     }
                                 }
-                                PushUpdate::Register { id, url, keys } => {
-                                    if let Entry::Vacant(entry) = subscriptions.entry(id) {
+                                PushUpdate::Register {
+                                    id,
+                                    url,
+                                    expires,
+                                    keys,
+                                } => match subscriptions.entry(id) {
+                                    Entry::Vacant(entry) => {
                                         entry.insert(PushServer {
                                             url,
+                                            expires,
                                             keys,
                                             num_attempts: 0,
                                             last_request: Instant::now()
@@ -140,17 +164,29 @@ pub fn spawn_push_manager(settings: &Config) -> mpsc::Sender<Event> {
                                             in_flight: false,
                                         });
                                     }
-                                }
+                                    Entry::Occupied(mut entry) => {
+                                        entry.get_mut().expires = expires;
+                                    }
+                                },
                                 PushUpdate::Unregister { id } => {
                                     subscriptions.remove(&id);
                                 }
                             }
                         }
                     }
-                    Event::Push { ids, state_change } => {
-                        for id in ids {
+                    Event::Push { changes } => {
+                        let current_time = now();
+
+                        for (id, state_change) in changes {
                             if let Some(subscription) = subscriptions.get_mut(&id) {
-                                subscription.state_changes.push(state_change.clone());
+                                if subscription.expires <= current_time {
+                                    tracing::debug!("Push subscription {} has expired.", id);
+                                    subscriptions.remove(&id);
+                                    retry_ids.remove(&id);
+                                    continue;
+                                }
+
+                                subscription.state_changes.push(state_change);
                                 let last_request = subscription.last_request.elapsed();
 
                                 if !subscription.in_flight
@@ -158,9 +194,10 @@ pub fn spawn_push_manager(settings: &Config) -> mpsc::Sender<Event> {
                                         && last_request > push_throttle)
                                         || ((1..push_attempts_max)
                                             .contains(&subscription.num_attempts)
-                                            && last_request > push_attempt_interval))
+                                            && last_request
+                                                > subscription.backoff(push_attempt_interval)))
                                 {
-                                    subscription.send(id, push_tx.clone(), push_timeout);
+                                    subscription.send(id, &core, push_tx.clone(), push_timeout);
                                     retry_ids.remove(&id);
                                 } else {
                                     retry_ids.insert(id);
@@ -189,6 +226,22 @@ pub fn spawn_push_manager(settings: &Config) -> mpsc::Sender<Event> {
                             retry_ids.insert(id);
                         }
                     }
+                    Event::DeliveryGone { id } => {
+                        if let Some(subscription) = subscriptions.remove(&id) {
+                            tracing::debug!(
+                                "Disabling push subscription {} for url {}: Gone.",
+                                id,
+                                subscription.url
+                            );
+                            retry_ids.remove(&id);
+
+                            let core = core.clone();
+                            tokio::spawn(async move {
+                                core.disable_push_subscription(id.prefix_id(), id.document_id())
+                                    .await;
+                            });
+                        }
+                    }
                 },
                 Ok(None) => {
                     break;
@@ -210,10 +263,16 @@ pub fn spawn_push_manager(settings: &Config) -> mpsc::Sender<Event> {
                                 && ((subscription.num_attempts == 0
                                     && last_request >= push_throttle)
                                     || (subscription.num_attempts > 0
-                                        && last_request >= push_attempt_interval))
+                                        && last_request
+                                            >= subscription.backoff(push_attempt_interval)))
                             {
                                 if subscription.num_attempts < push_attempts_max {
-                                    subscription.send(*retry_id, push_tx.clone(), push_timeout);
+                                    subscription.send(
+                                        *retry_id,
+                                        &core,
+                                        push_tx.clone(),
+                                        push_timeout,
+                                    );
                                 } else {
                                     tracing::debug!(
                                         concat!(
@@ -255,9 +314,10 @@ pub fn spawn_push_manager(settings: &Config) -> mpsc::Sender<Event> {
 }
 
 impl PushServer {
-    fn send(&mut self, id: Id, push_tx: mpsc::Sender<Event>, push_timeout: Duration) {
+    fn send(&mut self, id: Id, core: &JMAP, push_tx: mpsc::Sender<Event>, push_timeout: Duration) {
         let url = self.url.clone();
         let keys = self.keys.clone();
+        let authorization = core.config.push_vapid.authorization(&url);
         let state_changes = std::mem::take(&mut self.state_changes);
 
         self.in_flight = true;
@@ -276,31 +336,42 @@ impl PushServer {
 
             push_tx
                 .send(
-                    if http_request(
+                    match http_request(
                         url,
                         serde_json::to_string(&response).unwrap(),
                         keys,
+                        authorization,
                         push_timeout,
                     )
                     .await
                     {
-                        Event::DeliverySuccess { id }
-                    } else {
-                        Event::DeliveryFailure { id, state_changes }
+                        DeliveryResult::Delivered => Event::DeliverySuccess { id },
+                        DeliveryResult::Failed => Event::DeliveryFailure { id, state_changes },
+                        DeliveryResult::Gone => Event::DeliveryGone { id },
                     },
                 )
                 .await
                 .ok();
         });
     }
+
+    fn backoff(&self, attempt_interval: Duration) -> Duration {
+        // Double the wait between consecutive failed attempts
+        attempt_interval
+            * (1 << self
+                .num_attempts
+                .saturating_sub(1)
+                .min(MAX_BACKOFF_EXPONENT))
+    }
 }
 
 async fn http_request(
     url: String,
     mut body: String,
     keys: Option<EncryptionKeys>,
+    authorization: Option<String>,
     push_timeout: Duration,
-) -> bool {
+) -> DeliveryResult {
     let client_builder = reqwest::Client::builder().timeout(push_timeout);
 
     #[cfg(feature = "test_mode")]
@@ -313,6 +384,10 @@ async fn http_request(
         .header(CONTENT_TYPE, "application/json")
         .header("TTL", "86400");
 
+    if let Some(authorization) = authorization {
+        client = client.header(AUTHORIZATION, authorization);
+    }
+
     if let Some(keys) = keys {
         match ece_encrypt(&keys.p256dh, &keys.auth, body.as_bytes())
             .map(|b| general_purpose::URL_SAFE.encode(b))
@@ -324,16 +399,23 @@ async fn http_request(
             Err(err) => {
                 // Do not reattempt if encryption fails.
                 tracing::debug!("Failed to encrypt push subscription to {}: {}", url, err);
-                return true;
+                return DeliveryResult::Delivered;
             }
         }
     }
 
     match client.body(body).send().await {
-        Ok(response) => response.status().is_success(),
+        Ok(response) => match response.status() {
+            status if status.is_success() => DeliveryResult::Delivered,
+            StatusCode::NOT_FOUND | StatusCode::GONE => DeliveryResult::Gone,
+            status => {
+                tracing::debug!("HTTP post to {} failed with status: {}", url, status);
+                DeliveryResult::Failed
+            }
+        },
         Err(err) => {
             tracing::debug!("HTTP post to {} failed with: {}", url, err);
-            false
+            DeliveryResult::Failed
         }
     }
 }
@@ -25,6 +25,7 @@ pub mod ece;
 pub mod get;
 pub mod manager;
 pub mod set;
+pub mod vapid;
 
 use std::time::Instant;
 
@@ -63,8 +64,7 @@ pub enum Event {
         updates: Vec<PushUpdate>,
     },
     Push {
-        ids: Vec<Id>,
-        state_change: StateChange,
+        changes: Vec<(Id, StateChange)>,
     },
     DeliverySuccess {
         id: Id,
@@ -73,6 +73,9 @@ pub enum Event {
         id: Id,
         state_changes: Vec<StateChange>,
     },
+    DeliveryGone {
+        id: Id,
+    },
     Reset,
 }
 
@@ -88,6 +91,7 @@ pub enum PushUpdate {
     Register {
         id: Id,
         url: String,
+        expires: u64,
         keys: Option<EncryptionKeys>,
     },
     Unregister {
@@ -98,6 +102,7 @@ pub enum PushUpdate {
 #[derive(Debug)]
 pub struct PushServer {
     url: String,
+    expires: u64,
     keys: Option<EncryptionKeys>,
     num_attempts: u32,
     last_request: Instant,
@@ -212,6 +212,38 @@ impl JMAP {
 
         Ok(response)
     }
+
+    pub async fn disable_push_subscription(&self, account_id: u32, document_id: u32) {
+        // Expire the subscription so it is no longer loaded, clients may
+        // re-enable it by updating its expiration date.
+        let mut push = if let Ok(Some(push)) = self
+            .get_property::<Object<Value>>(
+                account_id,
+                Collection::PushSubscription,
+                document_id,
+                Property::Value,
+            )
+            .await
+        {
+            push
+        } else {
+            return;
+        };
+        push.set(
+            Property::Expires,
+            Value::Date(UTCDate::from_timestamp(now() as i64)),
+        );
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::PushSubscription)
+            .update_document(document_id)
+            .value(Property::Value, push, F_VALUE);
+        if self.write_batch(batch).await.is_ok() {
+            self.update_push_subscriptions(account_id).await;
+        }
+    }
 }
 
 fn validate_push_value(
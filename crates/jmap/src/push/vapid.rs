@@ -0,0 +1,199 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use base64::{engine::general_purpose, Engine};
+use jmap_proto::types::collection::Collection;
+use p256::{
+    ecdsa::{signature::Signer, Signature, SigningKey},
+    elliptic_curve::{rand_core::OsRng, sec1::ToEncodedPoint},
+};
+use store::{
+    write::{key::KeySerializer, now, BatchBuilder, Operation, ValueClass},
+    CustomValueKey, Serialize, Store, CUSTOM_VAPID_KEY,
+};
+
+/*
+
+ Voluntary Application Server Identification (VAPID) for Web Push, RFC 8292.
+ Push requests are signed with an ES256 JWT bound to the origin of the push
+ service. The public key is advertised to clients through the
+ 'urn:ietf:params:jmap:webpush-vapid' capability (RFC 9749).
+
+*/
+
+const JWT_HEADER: &str = "{\"typ\":\"JWT\",\"alg\":\"ES256\"}";
+const JWT_EXPIRY: u64 = 12 * 3600;
+
+pub struct VapidKey {
+    signing_key: SigningKey,
+    public_key: String,
+    subject: Option<String>,
+    is_generated: bool,
+}
+
+#[derive(serde::Serialize)]
+struct VapidClaims<'x> {
+    aud: &'x str,
+    exp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub: Option<&'x str>,
+}
+
+impl VapidKey {
+    pub fn new(settings: &utils::config::Config) -> Result<Self, String> {
+        let (signing_key, is_generated) = if let Some(key) =
+            settings.value("jmap.push.vapid.private-key")
+        {
+            (
+                decode_key(key).ok_or_else(|| {
+                    "Invalid VAPID private key found in 'jmap.push.vapid.private-key'.".to_string()
+                })?,
+                false,
+            )
+        } else {
+            // Replaced by the key kept in the store once it is opened
+            (SigningKey::random(&mut OsRng), true)
+        };
+
+        Ok(VapidKey {
+            public_key: encode_public_key(&signing_key),
+            signing_key,
+            is_generated,
+            subject: settings
+                .value("jmap.push.vapid.subject")
+                .map(|subject| subject.to_string())
+                .or_else(|| {
+                    settings
+                        .value("server.hostname")
+                        .map(|hostname| format!("mailto:postmaster@{hostname}"))
+                }),
+        })
+    }
+
+    pub async fn load_or_store(&mut self, store: &Store) -> store::Result<()> {
+        // Keys are generated only once and kept in the store, otherwise Web Push
+        // clients would have to resubscribe after every restart.
+        if !self.is_generated {
+            return Ok(());
+        }
+        let key = KeySerializer::new(std::mem::size_of::<u32>() + 1)
+            .write(u32::MAX)
+            .write(CUSTOM_VAPID_KEY)
+            .finalize();
+
+        loop {
+            if let Some(stored_key) = store
+                .get_value::<String>(CustomValueKey { value: key.clone() })
+                .await?
+            {
+                self.signing_key = decode_key(&stored_key).ok_or_else(|| {
+                    store::Error::InternalError("Invalid VAPID private key in store.".to_string())
+                })?;
+                self.public_key = encode_public_key(&self.signing_key);
+                return Ok(());
+            }
+
+            tracing::info!(
+                context = "push",
+                event = "config",
+                "No VAPID private key configured, storing a generated key."
+            );
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(u32::MAX)
+                .with_collection(Collection::Principal)
+                .assert_value(ValueClass::Custom { bytes: key.clone() }, ())
+                .op(Operation::Value {
+                    class: ValueClass::Custom { bytes: key.clone() },
+                    set: general_purpose::URL_SAFE_NO_PAD
+                        .encode(self.signing_key.to_bytes())
+                        .serialize()
+                        .into(),
+                });
+            match store.write(batch.build()).await {
+                Ok(_) => return Ok(()),
+                // Another node stored its key first, use that one instead
+                Err(store::Error::AssertValueFailed) => (),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    pub fn authorization(&self, url: &str) -> Option<String> {
+        let claims = serde_json::to_string(&VapidClaims {
+            aud: audience(url)?,
+            exp: now() + JWT_EXPIRY,
+            sub: self.subject.as_deref(),
+        })
+        .ok()?;
+        let token = format!(
+            "{}.{}",
+            general_purpose::URL_SAFE_NO_PAD.encode(JWT_HEADER),
+            general_purpose::URL_SAFE_NO_PAD.encode(claims)
+        );
+        let signature: Signature = self.signing_key.sign(token.as_bytes());
+
+        Some(format!(
+            "vapid t={}.{}, k={}",
+            token,
+            general_purpose::URL_SAFE_NO_PAD.encode(signature.to_bytes()),
+            self.public_key
+        ))
+    }
+}
+
+fn decode_key(key: &str) -> Option<SigningKey> {
+    general_purpose::URL_SAFE_NO_PAD
+        .decode(key.trim().trim_end_matches('='))
+        .ok()
+        .and_then(|key| SigningKey::from_slice(&key).ok())
+}
+
+fn encode_public_key(signing_key: &SigningKey) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(
+        signing_key
+            .verifying_key()
+            .to_encoded_point(false)
+            .as_bytes(),
+    )
+}
+
+fn audience(url: &str) -> Option<&str> {
+    let authority_start = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .map(|authority| url.len() - authority.len())?;
+    let authority_end = url[authority_start..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |pos| authority_start + pos);
+
+    if authority_end > authority_start {
+        Some(&url[..authority_end])
+    } else {
+        None
+    }
+}
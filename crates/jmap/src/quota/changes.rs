@@ -0,0 +1,67 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::changes::{ChangesRequest, ChangesResponse},
+    types::{collection::Collection, id::Id, property::Property, state::State},
+};
+
+use crate::JMAP;
+
+impl JMAP {
+    pub async fn quota_changes(
+        &self,
+        request: ChangesRequest,
+    ) -> Result<ChangesResponse, MethodError> {
+        // Quota changes are logged whenever messages are added or removed
+        let account_id = request.account_id.document_id();
+        let new_state = self.get_state(account_id, Collection::Quota).await?;
+        let mut response = ChangesResponse {
+            account_id: request.account_id,
+            old_state: request.since_state.clone(),
+            new_state,
+            has_more_changes: false,
+            created: vec![],
+            updated: vec![],
+            destroyed: vec![],
+            updated_properties: None,
+        };
+
+        if response.old_state != response.new_state {
+            let ids = self
+                .get_account_quotas(account_id)
+                .await?
+                .into_iter()
+                .map(|quota| Id::from(quota.id));
+            if response.old_state == State::Initial {
+                response.created.extend(ids);
+            } else {
+                response.updated.extend(ids);
+                response.updated_properties = vec![Property::Used].into();
+            }
+        }
+
+        Ok(response)
+    }
+}
@@ -0,0 +1,91 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::get::{GetRequest, GetResponse, RequestArguments},
+    object::Object,
+    types::{collection::Collection, id::Id, property::Property, value::Value},
+};
+
+use crate::JMAP;
+
+impl JMAP {
+    pub async fn quota_get(
+        &self,
+        mut request: GetRequest<RequestArguments>,
+    ) -> Result<GetResponse, MethodError> {
+        let ids = request.unwrap_ids(self.config.get_max_objects)?;
+        let properties = request.unwrap_properties(&[
+            Property::Id,
+            Property::ResourceType,
+            Property::Used,
+            Property::HardLimit,
+            Property::WarnLimit,
+            Property::Scope,
+            Property::Name,
+            Property::Types,
+        ]);
+        let account_id = request.account_id.document_id();
+        let quotas = self.get_account_quotas(account_id).await?;
+        let ids = if let Some(ids) = ids {
+            ids
+        } else {
+            quotas.iter().map(|quota| Id::from(quota.id)).collect()
+        };
+        let mut response = GetResponse {
+            account_id: request.account_id.into(),
+            state: self.get_state(account_id, Collection::Quota).await?.into(),
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for id in ids {
+            let quota = if let Some(quota) = quotas.iter().find(|q| q.id == id.document_id()) {
+                quota
+            } else {
+                response.not_found.push(id);
+                continue;
+            };
+
+            let mut result = Object::with_capacity(properties.len());
+            for property in &properties {
+                let value = match property {
+                    Property::Id => Value::Id(id),
+                    Property::ResourceType => Value::Text(quota.resource_type.to_string()),
+                    Property::Used => Value::UnsignedInt(quota.used),
+                    Property::HardLimit => Value::UnsignedInt(quota.hard_limit),
+                    Property::WarnLimit => Value::UnsignedInt(quota.warn_limit),
+                    Property::Scope => Value::Text("account".to_string()),
+                    Property::Name => Value::Text(quota.name.to_string()),
+                    Property::Types => Value::List(vec![Value::Text("Email".to_string())]),
+                    _ => Value::Null,
+                };
+                result.append(property.clone(), value);
+            }
+            response.list.push(result);
+        }
+
+        Ok(response)
+    }
+}
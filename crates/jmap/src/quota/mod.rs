@@ -0,0 +1,234 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{error::method::MethodError, types::collection::Collection};
+use store::write::{log::ChangeLogBuilder, BatchBuilder};
+
+use crate::JMAP;
+
+pub mod changes;
+pub mod get;
+pub mod query;
+
+pub const QUOTA_OCTETS_ID: u32 = 0;
+pub const QUOTA_COUNT_ID: u32 = 1;
+
+#[cfg(feature = "test_mode")]
+pub static TEST_MAX_MESSAGES: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+pub trait LogQuotaChange {
+    fn log_quota_change(&mut self);
+}
+
+#[derive(Debug, Clone)]
+pub struct AccountQuota {
+    pub id: u32,
+    pub resource_type: &'static str,
+    pub name: &'static str,
+    pub used: u64,
+    pub hard_limit: u64,
+    pub warn_limit: u64,
+}
+
+impl JMAP {
+    pub async fn get_account_quotas(
+        &self,
+        account_id: u32,
+    ) -> Result<Vec<AccountQuota>, MethodError> {
+        let mut quotas = Vec::with_capacity(2);
+
+        // Disk usage is maintained by the store on every message insert and delete
+        let hard_limit = self.get_account_quota(account_id).await? as u64;
+        if hard_limit > 0 {
+            quotas.push(AccountQuota {
+                id: QUOTA_OCTETS_ID,
+                resource_type: "octets",
+                name: "Mail storage",
+                used: self.get_used_quota(account_id).await?.max(0) as u64,
+                hard_limit,
+                warn_limit: self.quota_warn_limit(hard_limit),
+            });
+        }
+
+        // Message counts are maintained by the store next to the disk usage
+        let hard_limit = self.quota_max_messages();
+        if hard_limit > 0 {
+            quotas.push(AccountQuota {
+                id: QUOTA_COUNT_ID,
+                resource_type: "count",
+                name: "Mail messages",
+                used: self.get_used_messages(account_id).await?,
+                hard_limit,
+                warn_limit: self.quota_warn_limit(hard_limit),
+            });
+        }
+
+        Ok(quotas)
+    }
+
+    pub async fn get_account_quota(&self, account_id: u32) -> Result<u32, MethodError> {
+        if let Some(name) = self.get_account_name(account_id).await? {
+            self.directory
+                .principal(&name)
+                .await
+                .map(|p| p.map(|p| p.quota).unwrap_or_default())
+                .map_err(|err| {
+                    tracing::error!(
+                        event = "error",
+                        context = "get_account_quota",
+                        account_id = account_id,
+                        error = ?err,
+                        "Failed to obtain disk quota for account.");
+                    MethodError::ServerPartialFail
+                })
+        } else {
+            Ok(0)
+        }
+    }
+
+    pub async fn get_used_messages(&self, account_id: u32) -> Result<u64, MethodError> {
+        match self.store.get_message_count(account_id).await {
+            Ok(Some(count)) if count >= 0 => Ok(count as u64),
+            Ok(count) => {
+                // Rebuild missing or negative counters from the Email document ids
+                tracing::warn!(
+                    event = "error",
+                    context = "get_used_messages",
+                    account_id = account_id,
+                    count = ?count,
+                    "Invalid message count for account, rebuilding it."
+                );
+                self.rebuild_message_count(account_id, count.unwrap_or_default())
+                    .await
+            }
+            Err(err) => {
+                tracing::error!(
+                    event = "error",
+                    context = "get_used_messages",
+                    account_id = account_id,
+                    error = ?err,
+                    "Failed to obtain message count for account.");
+                Err(MethodError::ServerPartialFail)
+            }
+        }
+    }
+
+    pub async fn init_message_counts(&self) {
+        // Accounts created before message counts were tracked have no counter,
+        // initialize them from the Email document ids before accepting any writes.
+        let account_ids = match self.get_document_ids(u32::MAX, Collection::Principal).await {
+            Ok(account_ids) => account_ids.unwrap_or_default(),
+            Err(_) => return,
+        };
+
+        for account_id in account_ids {
+            match self.store.get_message_count(account_id).await {
+                Ok(Some(_)) => (),
+                Ok(None) => {
+                    let _ = self.rebuild_message_count(account_id, 0).await;
+                }
+                Err(err) => {
+                    tracing::error!(
+                        event = "error",
+                        context = "init_message_counts",
+                        account_id = account_id,
+                        error = ?err,
+                        "Failed to obtain message count for account.");
+                }
+            }
+        }
+    }
+
+    async fn rebuild_message_count(
+        &self,
+        account_id: u32,
+        current: i64,
+    ) -> Result<u64, MethodError> {
+        let count = self
+            .get_document_ids(account_id, Collection::Email)
+            .await?
+            .map_or(0, |ids| ids.len());
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .message_count(count as i64 - current);
+        self.write_batch(batch).await?;
+
+        Ok(count)
+    }
+
+    pub fn quota_max_messages(&self) -> u64 {
+        #[cfg(feature = "test_mode")]
+        {
+            let max_messages = TEST_MAX_MESSAGES.load(std::sync::atomic::Ordering::Relaxed);
+            if max_messages > 0 {
+                return max_messages;
+            }
+        }
+
+        self.config.quota_max_messages
+    }
+
+    pub fn quota_warn_limit(&self, hard_limit: u64) -> u64 {
+        hard_limit * self.config.quota_warn_threshold / 100
+    }
+
+    pub async fn has_crossed_quota_warning(
+        &self,
+        account_id: u32,
+        account_quota: i64,
+        added_octets: usize,
+        added_messages: usize,
+    ) -> Result<bool, MethodError> {
+        if added_messages == 0 {
+            return Ok(false);
+        }
+
+        if account_quota > 0 {
+            let warn_limit = self.quota_warn_limit(account_quota as u64) as i64;
+            let used = self.get_used_quota(account_id).await?;
+            if used >= warn_limit && used - (added_octets as i64) < warn_limit {
+                return Ok(true);
+            }
+        }
+
+        let max_messages = self.quota_max_messages();
+        if max_messages > 0 {
+            let warn_limit = self.quota_warn_limit(max_messages);
+            let used = self.get_used_messages(account_id).await?;
+            if used >= warn_limit && used.saturating_sub(added_messages as u64) < warn_limit {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+impl LogQuotaChange for ChangeLogBuilder {
+    // Adding or removing a message changes both the disk usage and the message count
+    fn log_quota_change(&mut self) {
+        self.log_update(Collection::Quota, QUOTA_OCTETS_ID);
+        self.log_update(Collection::Quota, QUOTA_COUNT_ID);
+    }
+}
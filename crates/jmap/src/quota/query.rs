@@ -0,0 +1,116 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::cmp::Ordering;
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::query::{Filter, QueryRequest, QueryResponse, RequestArguments, SortProperty},
+    types::collection::Collection,
+};
+use store::{query::ResultSet, roaring::RoaringBitmap};
+
+use crate::{UpdateResults, JMAP};
+
+impl JMAP {
+    pub async fn quota_query(
+        &self,
+        mut request: QueryRequest<RequestArguments>,
+    ) -> Result<QueryResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let mut quotas = self.get_account_quotas(account_id).await?;
+
+        for cond in std::mem::take(&mut request.filter) {
+            match cond {
+                Filter::Name(name) => {
+                    let name = name.to_lowercase();
+                    quotas.retain(|quota| quota.name.to_lowercase().contains(&name));
+                }
+                Filter::Scope(scope) => {
+                    if scope != "account" {
+                        quotas.clear();
+                    }
+                }
+                Filter::ResourceType(resource_type) => {
+                    quotas.retain(|quota| quota.resource_type == resource_type);
+                }
+                Filter::Type(typ) => {
+                    if typ != "Email" {
+                        quotas.clear();
+                    }
+                }
+                other => return Err(MethodError::UnsupportedFilter(other.to_string())),
+            }
+        }
+
+        // Sort quotas
+        let comparators = request.sort.take().unwrap_or_default();
+        for comparator in &comparators {
+            if !matches!(comparator.property, SortProperty::Name | SortProperty::Used) {
+                return Err(MethodError::UnsupportedSort(
+                    comparator.property.to_string(),
+                ));
+            }
+        }
+        quotas.sort_by(|a, b| {
+            for comparator in &comparators {
+                let ordering = match comparator.property {
+                    SortProperty::Name => a.name.cmp(b.name),
+                    SortProperty::Used => a.used.cmp(&b.used),
+                    _ => Ordering::Equal,
+                };
+                let ordering = if comparator.is_ascending {
+                    ordering
+                } else {
+                    ordering.reverse()
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+
+        let result_set = ResultSet {
+            account_id,
+            collection: Collection::Quota.into(),
+            results: quotas
+                .iter()
+                .map(|quota| quota.id)
+                .collect::<RoaringBitmap>(),
+        };
+        let (mut response, paginate) = self.build_query_response(&result_set, &request).await?;
+        response.can_calculate_changes = false;
+
+        if let Some(mut paginate) = paginate {
+            for quota in &quotas {
+                if !paginate.add(0, quota.id) {
+                    break;
+                }
+            }
+            response.update_results(paginate.build())?;
+        }
+
+        Ok(response)
+    }
+}
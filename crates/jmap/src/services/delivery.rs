@@ -35,6 +35,9 @@ pub fn spawn_delivery_manager(core: Arc<JMAP>, mut delivery_rx: mpsc::Receiver<D
                 DeliveryEvent::Ingest { message, result_tx } => {
                     result_tx.send(core.deliver_message(message).await).ok();
                 }
+                DeliveryEvent::Suppression(event) => {
+                    core.handle_suppression_event(event).await;
+                }
                 DeliveryEvent::Stop => break,
             }
         }
@@ -70,6 +70,18 @@ impl JMAP {
                 }
             };
 
+            // Obtain quota
+            let account_quota = match self.directory.principal(name).await {
+                Ok(Some(p)) => p.quota as i64,
+                Ok(None) => 0,
+                Err(_) => {
+                    *status = DeliveryResult::TemporaryFailure {
+                        reason: "Transient server failure.".into(),
+                    };
+                    continue;
+                }
+            };
+
             // Check if there is an active sieve script
             let result = match self.sieve_script_get_active(uid).await {
                 Ok(Some(active_script)) => {
@@ -84,17 +96,6 @@ impl JMAP {
                     .await
                 }
                 Ok(None) => {
-                    let account_quota = match self.directory.principal(name).await {
-                        Ok(Some(p)) => p.quota as i64,
-                        Ok(None) => 0,
-                        Err(_) => {
-                            *status = DeliveryResult::TemporaryFailure {
-                                reason: "Transient server failure.".into(),
-                            };
-                            continue;
-                        }
-                    };
-
                     self.email_ingest(IngestEmail {
                         raw_message: &raw_message,
                         message: Message::parse(&raw_message),
@@ -104,6 +105,7 @@ impl JMAP {
                         keywords: vec![],
                         received_at: None,
                         skip_duplicates: true,
+                        skip_muted_inbox: true,
                     })
                     .await
                 }
@@ -119,14 +121,20 @@ impl JMAP {
                 Ok(ingested_message) => {
                     // Notify state change
                     if ingested_message.change_id != u64::MAX {
-                        self.broadcast_state_change(
-                            StateChange::new(uid)
-                                .with_change(TypeState::EmailDelivery, ingested_message.change_id)
-                                .with_change(TypeState::Email, ingested_message.change_id)
-                                .with_change(TypeState::Mailbox, ingested_message.change_id)
-                                .with_change(TypeState::Thread, ingested_message.change_id),
-                        )
-                        .await;
+                        let mut state_change = StateChange::new(uid)
+                            .with_change(TypeState::EmailDelivery, ingested_message.change_id)
+                            .with_change(TypeState::Email, ingested_message.change_id)
+                            .with_change(TypeState::Mailbox, ingested_message.change_id)
+                            .with_change(TypeState::Thread, ingested_message.change_id);
+                        if self
+                            .has_crossed_quota_warning(uid, account_quota, ingested_message.size, 1)
+                            .await
+                            .unwrap_or(false)
+                        {
+                            state_change = state_change
+                                .with_change(TypeState::Quota, ingested_message.change_id);
+                        }
+                        self.broadcast_state_change(state_change).await;
                     }
                 }
                 Err(err) => match err {
@@ -25,5 +25,6 @@ pub mod delivery;
 pub mod housekeeper;
 pub mod ingest;
 pub mod state;
+pub mod suppression;
 
 pub const IPC_CHANNEL_BUFFER: usize = 1024;
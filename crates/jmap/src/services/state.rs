@@ -92,7 +92,7 @@ pub fn spawn_state_manager(
     settings: &Config,
     mut change_rx: mpsc::Receiver<Event>,
 ) {
-    let push_tx = spawn_push_manager(settings);
+    let push_tx = spawn_push_manager(core.clone(), settings);
 
     tokio::spawn(async move {
         let mut subscribers: AHashMap<u32, AHashMap<u32, Subscriber>> = AHashMap::default();
@@ -198,7 +198,7 @@ pub fn spawn_state_manager(
                             .duration_since(SystemTime::UNIX_EPOCH)
                             .map(|d| d.as_secs())
                             .unwrap_or(0);
-                        let mut push_ids = Vec::new();
+                        let mut push_changes = Vec::new();
 
                         for (owner_account_id, allowed_types) in shared_accounts {
                             if let Some(subscribers) = subscribers.get(owner_account_id) {
@@ -239,9 +239,15 @@ pub fn spawn_state_manager(
                                             SubscriberType::Push { expires }
                                                 if expires > &current_time =>
                                             {
-                                                push_ids.push(Id::from_parts(
-                                                    *owner_account_id,
-                                                    *subscriber_id,
+                                                push_changes.push((
+                                                    Id::from_parts(
+                                                        *owner_account_id,
+                                                        *subscriber_id,
+                                                    ),
+                                                    StateChange {
+                                                        account_id: state_change.account_id,
+                                                        types,
+                                                    },
                                                 ));
                                             }
                                             _ => {
@@ -253,11 +259,10 @@ pub fn spawn_state_manager(
                             }
                         }
 
-                        if !push_ids.is_empty() {
+                        if !push_changes.is_empty() {
                             if let Err(err) = push_tx
                                 .send(crate::push::Event::Push {
-                                    ids: push_ids,
-                                    state_change,
+                                    changes: push_changes,
                                 })
                                 .await
                             {
@@ -332,6 +337,7 @@ pub fn spawn_state_manager(
                                 push_updates.push(crate::push::PushUpdate::Register {
                                     id: Id::from_parts(account_id, verified.id),
                                     url: verified.url,
+                                    expires: verified.expires,
                                     keys: verified.keys,
                                 });
                             }
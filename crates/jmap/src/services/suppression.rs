@@ -0,0 +1,174 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::types::collection::Collection;
+use smtp::reporting::suppression::{SuppressionKey, SuppressionValue};
+use store::{
+    write::{now, BatchBuilder, Operation, ValueClass},
+    CustomValueKey, Deserialize, Serialize,
+};
+use utils::ipc::{SuppressedAddress, SuppressionEvent};
+
+use crate::JMAP;
+
+impl JMAP {
+    pub async fn handle_suppression_event(&self, event: SuppressionEvent) {
+        match event {
+            SuppressionEvent::Add { addresses } => {
+                for address in addresses {
+                    if let Err(err) = self.add_suppressed_address(&address).await {
+                        tracing::error!(
+                            context = "suppression",
+                            event = "error",
+                            domain = address.domain,
+                            address = address.address,
+                            error = ?err,
+                            "Failed to add address to suppression list."
+                        );
+                    }
+                }
+            }
+            SuppressionEvent::List { domain, result_tx } => {
+                let _ = result_tx.send(
+                    self.list_suppressed(domain.as_deref())
+                        .await
+                        .unwrap_or_default(),
+                );
+            }
+            SuppressionEvent::Clear {
+                domain,
+                address,
+                result_tx,
+            } => {
+                let _ = result_tx.send(
+                    self.clear_suppressed(domain.as_deref(), address.as_deref())
+                        .await
+                        .unwrap_or_default(),
+                );
+            }
+        }
+    }
+
+    pub async fn add_suppressed_address(&self, address: &SuppressedAddress) -> store::Result<()> {
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(u32::MAX)
+            .with_collection(Collection::Principal)
+            .op(Operation::Value {
+                class: ValueClass::Custom {
+                    bytes: SuppressionKey::new(&address.domain, &address.address),
+                },
+                set: SuppressionValue {
+                    created: if address.created > 0 {
+                        address.created
+                    } else {
+                        now()
+                    },
+                    reason: address.reason.clone(),
+                }
+                .serialize()
+                .into(),
+            });
+        self.store.write(batch.build()).await?;
+
+        tracing::debug!(
+            context = "suppression",
+            event = "add",
+            domain = address.domain,
+            address = address.address,
+            reason = address.reason,
+            "Address added to suppression list."
+        );
+
+        Ok(())
+    }
+
+    pub async fn list_suppressed(
+        &self,
+        domain: Option<&str>,
+    ) -> store::Result<Vec<SuppressedAddress>> {
+        let prefix = SuppressionKey::prefix(domain);
+        let mut end = prefix.clone();
+        end.push(u8::MAX);
+
+        self.store
+            .iterate(
+                Vec::new(),
+                CustomValueKey {
+                    value: prefix.clone(),
+                },
+                CustomValueKey { value: end },
+                false,
+                true,
+                move |entries, key, value| {
+                    if let Some((domain, address)) = key
+                        .get(SuppressionKey::prefix(None).len()..)
+                        .and_then(|key| std::str::from_utf8(key).ok())
+                        .and_then(|key| key.split_once('\0'))
+                    {
+                        let value = SuppressionValue::deserialize(value)?;
+                        entries.push(SuppressedAddress {
+                            domain: domain.to_string(),
+                            address: address.to_string(),
+                            reason: value.reason,
+                            created: value.created,
+                        });
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+    }
+
+    pub async fn clear_suppressed(
+        &self,
+        domain: Option<&str>,
+        address: Option<&str>,
+    ) -> store::Result<usize> {
+        let entries = self.list_suppressed(domain).await?;
+        let mut batch = BatchBuilder::new();
+        let mut num_cleared = 0;
+
+        batch
+            .with_account_id(u32::MAX)
+            .with_collection(Collection::Principal);
+        for entry in entries {
+            if address.map_or(true, |address| address.eq_ignore_ascii_case(&entry.address)) {
+                batch.op(Operation::Value {
+                    class: ValueClass::Custom {
+                        bytes: SuppressionKey::new(&entry.domain, &entry.address),
+                    },
+                    set: None,
+                });
+                num_cleared += 1;
+            }
+        }
+
+        if num_cleared > 0 {
+            self.store.write(batch.build()).await?;
+        }
+
+        Ok(num_cleared)
+    }
+}
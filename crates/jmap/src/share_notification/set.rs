@@ -0,0 +1,257 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::{method::MethodError, set::SetError},
+    method::set::{RequestArguments, SetRequest, SetResponse},
+    object::{
+        index::{IndexAs, IndexProperty, ObjectIndexBuilder},
+        Object,
+    },
+    types::{
+        acl::Acl, collection::Collection, property::Property, state::StateChange,
+        type_state::TypeState, value::Value,
+    },
+};
+use store::write::{assert::HashedValue, log::ChangeLogBuilder, now, BatchBuilder};
+use utils::map::bitmap::Bitmap;
+
+use crate::{mailbox::get::mailbox_rights, JMAP};
+
+pub static SCHEMA: &[IndexProperty] = &[
+    IndexProperty::new(Property::Created).index_as(IndexAs::LongInteger),
+    IndexProperty::new(Property::ObjectType).index_as(IndexAs::Text {
+        tokenize: false,
+        index: true,
+    }),
+    IndexProperty::new(Property::ObjectAccountId).index_as(IndexAs::Integer),
+];
+
+impl JMAP {
+    pub async fn share_notification_set(
+        &self,
+        mut request: SetRequest<RequestArguments>,
+    ) -> Result<SetResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let mut response = self
+            .prepare_set_response(&request, Collection::ShareNotification)
+            .await?;
+
+        // Share notifications are created by the server and can only be destroyed
+        for (id, _) in request.unwrap_create() {
+            response.not_created.append(
+                id,
+                SetError::forbidden().with_description("Share notifications cannot be created."),
+            );
+        }
+        for (id, _) in request.unwrap_update() {
+            response.not_updated.append(
+                id,
+                SetError::forbidden().with_description("Share notifications cannot be modified."),
+            );
+        }
+
+        // Process deletions
+        let mut changes = ChangeLogBuilder::new();
+        for id in request.unwrap_destroy() {
+            let document_id = id.document_id();
+            if let Some(notification) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account_id,
+                    Collection::ShareNotification,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                let mut batch = BatchBuilder::new();
+                batch
+                    .with_account_id(account_id)
+                    .with_collection(Collection::ShareNotification)
+                    .delete_document(document_id)
+                    .custom(ObjectIndexBuilder::new(SCHEMA).with_current(notification));
+                self.write_batch(batch).await?;
+                changes.log_delete(Collection::ShareNotification, document_id);
+                response.destroyed.push(id);
+            } else {
+                response.not_destroyed.append(id, SetError::not_found());
+            }
+        }
+
+        // Write changes
+        if !changes.is_empty() {
+            let change_id = self.commit_changes(account_id, changes).await?;
+            response.new_state = Some(change_id.into());
+            response.state_change = StateChange::new(account_id)
+                .with_change(TypeState::ShareNotification, change_id)
+                .into();
+        }
+
+        Ok(response)
+    }
+
+    pub async fn share_notification_create(
+        &self,
+        changed_by: u32,
+        account_id: u32,
+        mailbox_id: u32,
+        mailbox_name: &Value,
+        acl_current: &Value,
+        acl_changes: &Value,
+    ) {
+        // Obtain the rights of each grantee before and after the change
+        let mut grantees: Vec<(u32, u64, u64)> = Vec::new();
+        for (acl, is_current) in [(acl_current, true), (acl_changes, false)] {
+            if let Value::List(acl) = acl {
+                for item in acl.chunks_exact(2) {
+                    if let (Some(Value::Id(id)), Some(Value::UnsignedInt(acl_bits))) =
+                        (item.first(), item.last())
+                    {
+                        let grantee_id = id.document_id();
+                        let idx = if let Some(idx) =
+                            grantees.iter().position(|(id, _, _)| *id == grantee_id)
+                        {
+                            idx
+                        } else {
+                            grantees.push((grantee_id, 0, 0));
+                            grantees.len() - 1
+                        };
+                        if is_current {
+                            grantees[idx].1 = *acl_bits;
+                        } else {
+                            grantees[idx].2 = *acl_bits;
+                        }
+                    }
+                }
+            }
+        }
+        grantees.retain(|(grantee_id, old_rights, new_rights)| {
+            old_rights != new_rights && *grantee_id != changed_by && *grantee_id != account_id
+        });
+        if grantees.is_empty() {
+            return;
+        }
+
+        // Obtain the principal that changed the permissions
+        let changed_by_obj = match self.share_notification_principal(changed_by).await {
+            Ok(changed_by) => changed_by,
+            Err(_) => return,
+        };
+
+        for (grantee_id, old_rights, new_rights) in grantees {
+            let notification = Object::with_capacity(8)
+                .with_property(Property::Created, Value::UnsignedInt(now()))
+                .with_property(Property::ChangedBy, changed_by_obj.clone())
+                .with_property(Property::ObjectType, "Mailbox")
+                .with_property(Property::ObjectAccountId, Value::Id(account_id.into()))
+                .with_property(Property::ObjectId, Value::Id(mailbox_id.into()))
+                .with_property(Property::Name, mailbox_name.clone())
+                .with_property(Property::OldRights, share_notification_rights(old_rights))
+                .with_property(Property::NewRights, share_notification_rights(new_rights));
+
+            if let Err(err) = self
+                .share_notification_insert(grantee_id, notification)
+                .await
+            {
+                tracing::debug!(
+                    context = "share_notification",
+                    event = "error",
+                    account_id = grantee_id,
+                    reason = ?err,
+                    "Failed to create share notification."
+                );
+            }
+        }
+    }
+
+    async fn share_notification_principal(
+        &self,
+        principal_id: u32,
+    ) -> Result<Object<Value>, MethodError> {
+        let mut changed_by = Object::with_capacity(3);
+        if let Some(name) = self.get_account_name(principal_id).await? {
+            let principal = self
+                .directory
+                .principal(&name)
+                .await
+                .map_err(|_| MethodError::ServerPartialFail)?;
+            let email = self
+                .directory
+                .emails_by_name(&name)
+                .await
+                .map_err(|_| MethodError::ServerPartialFail)?
+                .into_iter()
+                .next()
+                .map(Value::Text)
+                .unwrap_or(Value::Null);
+            changed_by.append(
+                Property::Name,
+                principal
+                    .and_then(|principal| principal.description)
+                    .unwrap_or(name),
+            );
+            changed_by.append(Property::Email, email);
+        } else {
+            changed_by.append(Property::Name, Value::Null);
+            changed_by.append(Property::Email, Value::Null);
+        }
+        changed_by.append(Property::PrincipalId, Value::Id(principal_id.into()));
+
+        Ok(changed_by)
+    }
+
+    async fn share_notification_insert(
+        &self,
+        account_id: u32,
+        notification: Object<Value>,
+    ) -> Result<(), MethodError> {
+        let document_id = self
+            .assign_document_id(account_id, Collection::ShareNotification)
+            .await?;
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::ShareNotification)
+            .create_document(document_id)
+            .custom(ObjectIndexBuilder::new(SCHEMA).with_changes(notification));
+        self.write_batch(batch).await?;
+
+        let mut changes = ChangeLogBuilder::new();
+        changes.log_insert(Collection::ShareNotification, document_id);
+        let change_id = self.commit_changes(account_id, changes).await?;
+        self.broadcast_state_change(
+            StateChange::new(account_id).with_change(TypeState::ShareNotification, change_id),
+        )
+        .await;
+
+        Ok(())
+    }
+}
+
+fn share_notification_rights(acl_bits: u64) -> Value {
+    if acl_bits != 0 {
+        mailbox_rights(&Bitmap::<Acl>::from(acl_bits)).into()
+    } else {
+        Value::Null
+    }
+}
@@ -449,6 +449,7 @@ impl JMAP {
                         keywords: sieve_message.flags,
                         received_at: None,
                         skip_duplicates: true,
+                        skip_muted_inbox: true,
                     })
                     .await
                 {
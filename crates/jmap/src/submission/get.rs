@@ -28,6 +28,7 @@ use jmap_proto::{
     types::{collection::Collection, property::Property, value::Value},
 };
 use smtp::{core::management::QueueRequest, queue};
+use store::write::now;
 use tokio::sync::oneshot;
 
 use crate::JMAP;
@@ -117,6 +118,12 @@ impl JMAP {
                     .flatten();
             }
 
+            // Held messages remain pending until their release time
+            let is_released = push
+                .get(&Property::SendAt)
+                .as_date()
+                .map_or(true, |send_at| send_at.timestamp() <= now() as i64);
+
             let mut result = Object::with_capacity(properties.len());
             for property in &properties {
                 let value = match property {
@@ -164,13 +171,12 @@ impl JMAP {
                             (_, value) => value,
                         }
                     }
-                    Property::UndoStatus => {
-                        if queued_message.is_some() {
-                            Value::Text("pending".to_string())
-                        } else {
-                            push.remove(property)
+                    Property::UndoStatus => match push.remove(property) {
+                        Value::Text(undo_status) if undo_status == "pending" && is_released => {
+                            Value::Text("final".to_string())
                         }
-                    }
+                        value => value,
+                    },
                     Property::EmailId
                     | Property::IdentityId
                     | Property::ThreadId
@@ -48,8 +48,9 @@ use jmap_proto::{
     },
 };
 use smtp::{
+    config::SuppressionAction,
     core::{management::QueueRequest, NullIo, Session, SessionData, State},
-    queue,
+    queue::{self, QueueId},
 };
 use smtp_proto::{request::parser::Rfc5321Parser, MailFrom, RcptTo};
 use store::{
@@ -59,7 +60,13 @@ use store::{
 use tokio::sync::oneshot;
 use utils::{listener::ServerInstance, map::vec_map::VecMap};
 
-use crate::{identity::set::sanitize_email, JMAP};
+use crate::{
+    identity::{
+        set::sanitize_email,
+        signature::{insert_signature, Signature},
+    },
+    JMAP,
+};
 
 pub static SCHEMA: &[IndexProperty] = &[
     IndexProperty::new(Property::UndoStatus).index_as(IndexAs::Text {
@@ -145,6 +152,13 @@ impl JMAP {
 
             let mut queue_id = u64::MAX;
             let mut undo_status = None;
+            let is_pending = submission.inner.get(&Property::UndoStatus).as_string()
+                == Some("pending")
+                && submission
+                    .inner
+                    .get(&Property::SendAt)
+                    .as_date()
+                    .map_or(false, |send_at| send_at.timestamp() > now() as i64);
 
             for (property, value) in object.properties {
                 let value = match response.eval_object_references(value) {
@@ -174,6 +188,13 @@ impl JMAP {
             }
 
             match undo_status {
+                Some(undo_status) if undo_status == "canceled" && !is_pending => {
+                    response.not_updated.append(
+                        id,
+                        SetError::new(SetErrorType::CannotUnsend)
+                            .with_description("The message has already been sent."),
+                    );
+                }
                 Some(undo_status) if undo_status == "canceled" => {
                     let (result_tx, result_rx) = oneshot::channel();
                     if self
@@ -456,8 +477,8 @@ impl JMAP {
                 )));
         }
 
-        // Fetch identity's mailFrom
-        let identity_mail_from = if let Some(identity_mail_from) = self
+        // Fetch identity's mailFrom and signature
+        let (identity_mail_from, signature) = if let Some(mut identity) = self
             .get_property::<Object<Value>>(
                 account_id,
                 Collection::Identity,
@@ -465,15 +486,46 @@ impl JMAP {
                 Property::Value,
             )
             .await?
-            .and_then(|mut obj| obj.properties.remove(&Property::Email))
-            .and_then(|value| value.try_unwrap_string())
+            .filter(|identity| matches!(identity.get(&Property::Email), Value::Text(_)))
         {
-            identity_mail_from
+            if identity
+                .properties
+                .contains_key(&Property::VerificationCode)
+            {
+                return Ok(Err(SetError::new(SetErrorType::ForbiddenFrom)
+                    .with_description(
+                        "Identity e-mail address has not been verified.",
+                    )));
+            }
+            (
+                identity
+                    .remove(&Property::Email)
+                    .try_unwrap_string()
+                    .unwrap_or_default(),
+                Signature {
+                    text: identity
+                        .remove(&Property::TextSignature)
+                        .try_unwrap_string(),
+                    html: identity
+                        .remove(&Property::HtmlSignature)
+                        .try_unwrap_string(),
+                },
+            )
         } else {
             return Ok(Err(SetError::invalid_properties()
                 .with_property(Property::IdentityId)
                 .with_description("Identity not found.")));
         };
+        let signature = if self.config.identity_insert_signature
+            && (signature.text.is_some() || signature.html.is_some())
+        {
+            Some(signature)
+        } else {
+            None
+        };
+        let footer = identity_mail_from
+            .rsplit_once('@')
+            .and_then(|(_, domain)| self.config.identity_footers.get(domain));
 
         // Make sure the envelope address matches the identity email address
         let mail_from = if let Some(mail_from) = mail_from {
@@ -559,16 +611,14 @@ impl JMAP {
         }
 
         // Update sendAt
-        submission.append(
-            Property::SendAt,
-            UTCDate::from_timestamp(if mail_from.hold_until > 0 {
-                mail_from.hold_until
-            } else if mail_from.hold_for > 0 {
-                mail_from.hold_for + now()
-            } else {
-                now()
-            } as i64),
-        );
+        let send_at = if mail_from.hold_until > 0 {
+            mail_from.hold_until
+        } else if mail_from.hold_for > 0 {
+            mail_from.hold_for + now()
+        } else {
+            now()
+        };
+        submission.append(Property::SendAt, UTCDate::from_timestamp(send_at as i64));
 
         // Obtain raw message
         let message = if let Some(message) = self
@@ -581,6 +631,13 @@ impl JMAP {
             )
             .await?
         {
+            // Insert identity signature and domain footer
+            let message = if signature.is_some() || footer.is_some() {
+                insert_signature(&message, signature.as_ref(), footer).unwrap_or(message)
+            } else {
+                message
+            };
+
             if message.len() > self.config.mail_max_size {
                 return Ok(Err(SetError::new(SetErrorType::InvalidEmail)
                     .with_description(format!(
@@ -596,6 +653,74 @@ impl JMAP {
                 .with_description("Blob for email not found.")));
         };
 
+        // Submit message
+        let result = match self
+            .submit_message(instance, mail_from, rcpt_to, message)
+            .await
+        {
+            Ok(result) => result,
+            Err(err) => return Ok(Err(err)),
+        };
+        let has_success = result.queue_id.is_some();
+        if let Some(queue_id) = result.queue_id {
+            submission.append(Property::MessageId, queue_id);
+        }
+
+        // Set responses, held messages can be canceled until they are released
+        submission.append(
+            Property::UndoStatus,
+            if !has_success {
+                "failed"
+            } else if send_at > now() {
+                "pending"
+            } else {
+                "final"
+            },
+        );
+        submission.append(
+            Property::DeliveryStatus,
+            Object {
+                properties: result
+                    .responses
+                    .into_iter()
+                    .map(|(addr, response, is_suppressed)| {
+                        (
+                            Property::_T(addr),
+                            Value::Object(
+                                Object::with_capacity(3)
+                                    .with_property(
+                                        Property::Delivered,
+                                        if response.is_none() { "unknown" } else { "no" },
+                                    )
+                                    .with_property(
+                                        Property::SmtpReply,
+                                        response.unwrap_or_else(|| {
+                                            if is_suppressed {
+                                                "250 2.1.5 Queued, recipient is on the suppression list"
+                                            } else {
+                                                "250 2.1.5 Queued"
+                                            }
+                                            .to_string()
+                                        }),
+                                    )
+                                    .with_property(Property::Displayed, "unknown"),
+                            ),
+                        )
+                    })
+                    .collect::<VecMap<Property, Value>>(),
+            },
+        );
+
+        Ok(Ok(submission))
+    }
+
+    pub(crate) async fn submit_message(
+        &self,
+        instance: &Arc<ServerInstance>,
+        mail_from: MailFrom<String>,
+        rcpt_to: Vec<RcptTo<String>>,
+        message: Vec<u8>,
+    ) -> Result<SubmissionResult, SetError> {
         // Begin local SMTP session
         let mut session =
             Session::<NullIo>::local(self.smtp.clone(), instance.clone(), SessionData::default());
@@ -603,11 +728,8 @@ impl JMAP {
         // MAIL FROM
         let _ = session.handle_mail_from(mail_from).await;
         if let Some(error) = session.has_failed() {
-            return Ok(Err(SetError::new(SetErrorType::ForbiddenMailFrom)
-                .with_description(format!(
-                    "Server rejected MAIL-FROM: {}",
-                    error.trim()
-                ))));
+            return Err(SetError::new(SetErrorType::ForbiddenMailFrom)
+                .with_description(format!("Server rejected MAIL-FROM: {}", error.trim())));
         }
 
         // RCPT TO
@@ -620,7 +742,20 @@ impl JMAP {
             if response.is_none() {
                 has_success = true;
             }
-            responses.push((addr, response));
+            let is_suppressed = response.is_none()
+                && session.params.rcpt_suppression == SuppressionAction::Warn
+                && self
+                    .smtp
+                    .is_suppressed(
+                        session
+                            .data
+                            .mail_from
+                            .as_ref()
+                            .map_or("", |mail_from| mail_from.domain.as_str()),
+                        &addr,
+                    )
+                    .await;
+            responses.push((addr, response, is_suppressed));
         }
 
         // DATA
@@ -628,51 +763,32 @@ impl JMAP {
             session.data.message = message;
             let response = session.queue_message().await;
             if let State::Accepted(queue_id) = session.state {
-                submission.append(Property::MessageId, queue_id);
+                return Ok(SubmissionResult {
+                    queue_id: queue_id.into(),
+                    responses,
+                });
             } else {
-                return Ok(Err(SetError::new(SetErrorType::ForbiddenToSend)
-                    .with_description(format!(
+                return Err(
+                    SetError::new(SetErrorType::ForbiddenToSend).with_description(format!(
                         "Server rejected DATA: {}",
                         std::str::from_utf8(&response).unwrap().trim()
-                    ))));
+                    )),
+                );
             }
         }
 
-        // Set responses
-        submission.append(
-            Property::UndoStatus,
-            if has_success { "final" } else { "failed" },
-        );
-        submission.append(
-            Property::DeliveryStatus,
-            Object {
-                properties: responses
-                    .into_iter()
-                    .map(|(addr, response)| {
-                        (
-                            Property::_T(addr),
-                            Value::Object(
-                                Object::with_capacity(3)
-                                    .with_property(
-                                        Property::Delivered,
-                                        if response.is_none() { "unknown" } else { "no" },
-                                    )
-                                    .with_property(
-                                        Property::SmtpReply,
-                                        response.unwrap_or_else(|| "250 2.1.5 Queued".to_string()),
-                                    )
-                                    .with_property(Property::Displayed, "unknown"),
-                            ),
-                        )
-                    })
-                    .collect::<VecMap<Property, Value>>(),
-            },
-        );
-
-        Ok(Ok(submission))
+        Ok(SubmissionResult {
+            queue_id: None,
+            responses,
+        })
     }
 }
 
+pub(crate) struct SubmissionResult {
+    pub queue_id: Option<QueueId>,
+    pub responses: Vec<(String, Option<String>, bool)>,
+}
+
 fn parse_envelope_address(envelope: &Value) -> Result<(String, Option<String>), SetError> {
     if let Value::Object(envelope) = envelope {
         if let Some(Value::Text(addr)) = envelope.properties.get(&Property::Email) {
@@ -27,7 +27,10 @@ use jmap_proto::{
     object::Object,
     types::{collection::Collection, id::Id, property::Property},
 };
-use store::query::{sort::Pagination, Comparator, ResultSet};
+use store::{
+    query::{sort::Pagination, Comparator, ResultSet},
+    roaring::RoaringBitmap,
+};
 
 use crate::JMAP;
 
@@ -48,9 +51,21 @@ impl JMAP {
                 .map(Into::into)
                 .collect()
         };
-        let add_email_ids = request
-            .properties
-            .map_or(true, |p| p.unwrap().contains(&Property::EmailIds));
+        // isMuted is an extension, so it is only returned when requested
+        let (add_email_ids, add_is_muted) = request.properties.map_or((true, false), |p| {
+            let p = p.unwrap();
+            (
+                p.contains(&Property::EmailIds),
+                p.contains(&Property::IsMuted),
+            )
+        });
+        let muted_ids = if add_is_muted {
+            self.get_tag(account_id, Collection::Thread, Property::IsMuted, ())
+                .await?
+                .unwrap_or_default()
+        } else {
+            RoaringBitmap::new()
+        };
         let mut response = GetResponse {
             account_id: request.account_id.into(),
             state: self.get_state(account_id, Collection::Thread).await?.into(),
@@ -64,7 +79,7 @@ impl JMAP {
                 .get_tag(account_id, Collection::Email, Property::ThreadId, thread_id)
                 .await?
             {
-                let mut thread = Object::with_capacity(2).with_property(Property::Id, id);
+                let mut thread = Object::with_capacity(3).with_property(Property::Id, id);
                 if add_email_ids {
                     thread.append(
                         Property::EmailIds,
@@ -90,6 +105,9 @@ impl JMAP {
                             .collect::<Vec<_>>(),
                     );
                 }
+                if add_is_muted {
+                    thread.append(Property::IsMuted, muted_ids.contains(thread_id));
+                }
                 response.list.push(thread);
             } else {
                 response.not_found.push(id);
@@ -0,0 +1,404 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::{method::MethodError, set::SetError},
+    method::set::{RequestArguments, SetRequest, SetResponse},
+    response::references::EvalObjectReferences,
+    types::{
+        acl::Acl,
+        collection::Collection,
+        id::Id,
+        keyword::Keyword,
+        property::Property,
+        state::{State, StateChange},
+        type_state::TypeState,
+        value::{MaybePatchValue, Value},
+    },
+};
+use store::{
+    ahash::AHashSet,
+    write::{
+        assert::HashedValue, log::ChangeLogBuilder, BatchBuilder, DeserializeFrom, SerializeInto,
+        ToBitmaps, F_CLEAR, F_VALUE,
+    },
+    Serialize,
+};
+
+use crate::{auth::AccessToken, email::set::TagManager, JMAP};
+
+enum TagUpdate<T> {
+    Set(Vec<T>),
+    Update(T, bool),
+}
+
+impl JMAP {
+    pub async fn thread_set(
+        &self,
+        mut request: SetRequest<RequestArguments>,
+        access_token: &AccessToken,
+    ) -> Result<SetResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let mut response = self
+            .prepare_set_response(&request, Collection::Thread)
+            .await?;
+
+        // Threads are created and destroyed along with their messages
+        for (id, _) in request.unwrap_create() {
+            response.not_created.append(
+                id,
+                SetError::forbidden().with_description("Threads cannot be created."),
+            );
+        }
+        for id in request.unwrap_destroy() {
+            response.not_destroyed.append(
+                id,
+                SetError::forbidden().with_description("Threads cannot be destroyed."),
+            );
+        }
+
+        // Obtain mailboxIds and permissions
+        let mailbox_ids = self.mailbox_get_or_create(account_id).await?;
+        let (
+            can_read_message_ids,
+            can_add_mailbox_ids,
+            can_delete_mailbox_ids,
+            can_modify_message_ids,
+        ) = if access_token.is_shared(account_id) {
+            (
+                self.shared_messages(access_token, account_id, Acl::ReadItems)
+                    .await?
+                    .into(),
+                self.shared_documents(access_token, account_id, Collection::Mailbox, Acl::AddItems)
+                    .await?
+                    .into(),
+                self.shared_documents(
+                    access_token,
+                    account_id,
+                    Collection::Mailbox,
+                    Acl::RemoveItems,
+                )
+                .await?
+                .into(),
+                self.shared_messages(access_token, account_id, Acl::ModifyItems)
+                    .await?
+                    .into(),
+            )
+        } else {
+            (None, None, None, None)
+        };
+        let muted_ids = self
+            .get_tag(account_id, Collection::Thread, Property::IsMuted, ())
+            .await?
+            .unwrap_or_default();
+
+        // Process updates
+        let mut changes = ChangeLogBuilder::new();
+        'update: for (id, object) in request.unwrap_update() {
+            // Obtain the messages in the thread
+            let thread_id = id.document_id();
+            let mut document_ids = if let Some(document_ids) = self
+                .get_tag(account_id, Collection::Email, Property::ThreadId, thread_id)
+                .await?
+            {
+                document_ids
+            } else {
+                response.not_updated.append(id, SetError::not_found());
+                continue 'update;
+            };
+            if let Some(can_read_message_ids) = &can_read_message_ids {
+                document_ids &= can_read_message_ids;
+                if document_ids.is_empty() {
+                    response.not_updated.append(id, SetError::not_found());
+                    continue 'update;
+                }
+            }
+
+            // Parse thread changes
+            let mut mailbox_updates = Vec::new();
+            let mut keyword_updates = Vec::new();
+            let mut is_muted = None;
+            for (property, value) in object.properties {
+                let value = match response.eval_object_references(value) {
+                    Ok(value) => value,
+                    Err(err) => {
+                        response.not_updated.append(id, err);
+                        continue 'update;
+                    }
+                };
+                match (property, value) {
+                    (Property::MailboxIds, MaybePatchValue::Value(Value::List(ids))) => {
+                        mailbox_updates.push(TagUpdate::Set(
+                            ids.into_iter()
+                                .map(|id| id.unwrap_id().document_id())
+                                .collect(),
+                        ));
+                    }
+                    (Property::MailboxIds, MaybePatchValue::Patch(patch)) => {
+                        let mut patch = patch.into_iter();
+                        mailbox_updates.push(TagUpdate::Update(
+                            patch.next().unwrap().unwrap_id().document_id(),
+                            patch.next().unwrap().unwrap_bool(),
+                        ));
+                    }
+                    (Property::Keywords, MaybePatchValue::Value(Value::List(keywords))) => {
+                        keyword_updates.push(TagUpdate::Set(
+                            keywords
+                                .into_iter()
+                                .map(|keyword| keyword.unwrap_keyword())
+                                .collect(),
+                        ));
+                    }
+                    (Property::Keywords, MaybePatchValue::Patch(patch)) => {
+                        let mut patch = patch.into_iter();
+                        keyword_updates.push(TagUpdate::Update(
+                            patch.next().unwrap().unwrap_keyword(),
+                            patch.next().unwrap().unwrap_bool(),
+                        ));
+                    }
+                    (Property::IsMuted, MaybePatchValue::Value(Value::Bool(value))) => {
+                        // Muting changes how the owner's mail is delivered
+                        if access_token.is_shared(account_id) {
+                            response.not_updated.append(
+                                id,
+                                SetError::forbidden()
+                                    .with_property(Property::IsMuted)
+                                    .with_description("Only the account owner can mute threads."),
+                            );
+                            continue 'update;
+                        }
+                        is_muted = value.into();
+                    }
+                    (property, _) => {
+                        response.invalid_property_update(id, property);
+                        continue 'update;
+                    }
+                }
+            }
+
+            // Apply changes to all messages in the thread using a single batch
+            let mut batch = BatchBuilder::new();
+            let mut thread_changes = ChangeLogBuilder::new();
+            let mut changed_mailboxes = AHashSet::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Email);
+
+            if !mailbox_updates.is_empty() || !keyword_updates.is_empty() {
+                for document_id in document_ids {
+                    // Obtain current keywords and mailboxes
+                    let (mut mailboxes, mut keywords) = if let (Some(mailboxes), Some(keywords)) = (
+                        self.get_property::<HashedValue<Vec<u32>>>(
+                            account_id,
+                            Collection::Email,
+                            document_id,
+                            Property::MailboxIds,
+                        )
+                        .await?,
+                        self.get_property::<HashedValue<Vec<Keyword>>>(
+                            account_id,
+                            Collection::Email,
+                            document_id,
+                            Property::Keywords,
+                        )
+                        .await?,
+                    ) {
+                        (TagManager::new(mailboxes), TagManager::new(keywords))
+                    } else {
+                        continue;
+                    };
+                    apply_updates(&mut mailboxes, &mailbox_updates);
+                    apply_updates(&mut keywords, &keyword_updates);
+                    if !mailboxes.has_changes() && !keywords.has_changes() {
+                        continue;
+                    }
+                    batch.update_document(document_id);
+                    thread_changes
+                        .log_update(Collection::Email, Id::from_parts(thread_id, document_id));
+
+                    // Process keywords
+                    if keywords.has_changes() {
+                        // Verify permissions on shared accounts
+                        if matches!(&can_modify_message_ids, Some(ids) if !ids.contains(document_id))
+                        {
+                            response.not_updated.append(
+                                id,
+                                SetError::forbidden()
+                                    .with_description("You are not allowed to modify keywords."),
+                            );
+                            continue 'update;
+                        }
+
+                        // Set all current mailboxes as changed if the Seen tag changed
+                        if keywords
+                            .changed_tags()
+                            .any(|keyword| keyword == &Keyword::Seen)
+                        {
+                            for mailbox_id in mailboxes.current() {
+                                changed_mailboxes.insert(*mailbox_id);
+                            }
+                        }
+
+                        // Update keywords property
+                        keywords.update_batch(&mut batch, Property::Keywords);
+
+                        // Update last change id
+                        if changes.change_id == u64::MAX {
+                            changes.change_id = self.assign_change_id(account_id).await?;
+                        }
+                        batch.value(Property::Cid, changes.change_id, F_VALUE);
+                    }
+
+                    // Process mailboxes
+                    if mailboxes.has_changes() {
+                        // Make sure the message is at least in one mailbox
+                        if !mailboxes.has_tags() {
+                            response.not_updated.append(
+                                id,
+                                SetError::invalid_properties()
+                                    .with_property(Property::MailboxIds)
+                                    .with_description(
+                                        "Messages have to belong to at least one mailbox.",
+                                    ),
+                            );
+                            continue 'update;
+                        }
+
+                        // Make sure all new mailboxIds are valid
+                        for mailbox_id in mailboxes.added() {
+                            if !mailbox_ids.contains(*mailbox_id) {
+                                response.not_updated.append(
+                                    id,
+                                    SetError::invalid_properties()
+                                        .with_property(Property::MailboxIds)
+                                        .with_description(format!(
+                                            "mailboxId {mailbox_id} does not exist."
+                                        )),
+                                );
+                                continue 'update;
+                            } else if matches!(&can_add_mailbox_ids, Some(ids) if !ids.contains(*mailbox_id))
+                            {
+                                response.not_updated.append(
+                                    id,
+                                    SetError::forbidden().with_description(format!(
+                                        "You are not allowed to add messages to mailbox {mailbox_id}."
+                                    )),
+                                );
+                                continue 'update;
+                            }
+                            changed_mailboxes.insert(*mailbox_id);
+                        }
+
+                        // Add all removed mailboxes to change list
+                        for mailbox_id in mailboxes.removed() {
+                            if matches!(&can_delete_mailbox_ids, Some(ids) if !ids.contains(*mailbox_id))
+                            {
+                                response.not_updated.append(
+                                    id,
+                                    SetError::forbidden().with_description(format!(
+                                        "You are not allowed to delete messages from mailbox {mailbox_id}."
+                                    )),
+                                );
+                                continue 'update;
+                            }
+                            changed_mailboxes.insert(*mailbox_id);
+                        }
+
+                        // Update mailboxIds property
+                        mailboxes.update_batch(&mut batch, Property::MailboxIds);
+                    }
+                }
+            }
+
+            // Log mailbox changes
+            for mailbox_id in changed_mailboxes {
+                thread_changes.log_child_update(Collection::Mailbox, mailbox_id);
+            }
+
+            // Mute or unmute the thread
+            if let Some(is_muted) = is_muted {
+                if is_muted != muted_ids.contains(thread_id) {
+                    batch
+                        .with_collection(Collection::Thread)
+                        .update_document(thread_id)
+                        .bitmap(Property::IsMuted, (), if is_muted { 0 } else { F_CLEAR });
+                    thread_changes.log_update(Collection::Thread, thread_id);
+                }
+            }
+
+            // Write changes
+            if !thread_changes.is_empty() {
+                match self.store.write(batch.build()).await {
+                    Ok(_) => {
+                        changes.merge(thread_changes);
+                    }
+                    Err(store::Error::AssertValueFailed) => {
+                        response.not_updated.append(
+                            id,
+                            SetError::forbidden().with_description(
+                                "Another process modified this thread, please try again.",
+                            ),
+                        );
+                        continue 'update;
+                    }
+                    Err(err) => {
+                        tracing::error!(
+                            event = "error",
+                            context = "thread_set",
+                            error = ?err,
+                            "Failed to write thread changes to database.");
+                        return Err(MethodError::ServerPartialFail);
+                    }
+                }
+            }
+            response.updated.append(id, None);
+        }
+
+        // Update state
+        if !changes.is_empty() {
+            let new_state: State = self.commit_changes(account_id, changes).await?.into();
+            if let State::Exact(change_id) = &new_state {
+                response.state_change = StateChange::new(account_id)
+                    .with_change(TypeState::Email, *change_id)
+                    .with_change(TypeState::Mailbox, *change_id)
+                    .with_change(TypeState::Thread, *change_id)
+                    .into();
+            }
+            response.new_state = new_state.into();
+        }
+
+        Ok(response)
+    }
+}
+
+fn apply_updates<T>(tags: &mut TagManager<T>, updates: &[TagUpdate<T>])
+where
+    T: PartialEq + Clone + ToBitmaps + SerializeInto + Serialize + DeserializeFrom + Sync + Send,
+{
+    for update in updates {
+        match update {
+            TagUpdate::Set(values) => tags.set(values.clone()),
+            TagUpdate::Update(value, add) => tags.update(value.clone(), *add),
+        }
+    }
+}
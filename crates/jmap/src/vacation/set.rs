@@ -236,14 +236,14 @@ impl JMAP {
                     .update_document(document_id)
                     .value(Property::EmailIds, (), F_VALUE | F_CLEAR)
                     .custom(obj);
-                change_log.log_insert(Collection::SieveScript, document_id);
+                change_log.log_update(Collection::SieveScript, document_id);
                 document_id
             } else {
                 let document_id = self
                     .assign_document_id(account_id, Collection::SieveScript)
                     .await?;
                 batch.create_document(document_id).custom(obj);
-                change_log.log_update(Collection::SieveScript, document_id);
+                change_log.log_insert(Collection::SieveScript, document_id);
                 document_id
             };
             if !batch.is_empty() {
@@ -306,7 +306,26 @@ impl JMAP {
     fn build_script(&self, obj: &mut ObjectIndexBuilder) -> Result<Vec<u8>, MethodError> {
         // Build Sieve script
         let mut script = Vec::with_capacity(1024);
-        script.extend_from_slice(b"require [\"vacation\", \"relational\", \"date\"];\r\n\r\n");
+        let interval = self.config.sieve_vacation_interval;
+        let use_seconds = interval % 86400 != 0;
+        script.extend_from_slice(if use_seconds {
+            b"require [\"vacation\", \"vacation-seconds\", \"relational\", \"date\"];\r\n\r\n"
+                .as_slice()
+        } else {
+            b"require [\"vacation\", \"relational\", \"date\"];\r\n\r\n".as_slice()
+        });
+
+        // Do not reply to mailing lists, bulk or auto-submitted messages
+        script.extend_from_slice(
+            concat!(
+                "if anyof(exists [\"list-id\", \"list-unsubscribe\", \"list-post\"],\r\n",
+                "         header :is \"precedence\" [\"bulk\", \"list\", \"junk\"],\r\n",
+                "         allof(exists \"auto-submitted\", not header :is \"auto-submitted\" \"no\")) {\r\n",
+                "    stop;\r\n",
+                "}\r\n\r\n"
+            )
+            .as_bytes(),
+        );
         let mut num_blocks = 0;
 
         // Add start date
@@ -326,6 +345,11 @@ impl JMAP {
         }
 
         script.extend_from_slice(b"vacation :mime ");
+        if use_seconds {
+            script.extend_from_slice(format!(":seconds {interval} ").as_bytes());
+        } else {
+            script.extend_from_slice(format!(":days {} ", interval / 86400).as_bytes());
+        }
         if let Value::Text(value) = obj.get(&Property::Subject) {
             script.extend_from_slice(b":subject \"");
             for &ch in value.as_bytes().iter() {
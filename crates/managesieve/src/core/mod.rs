@@ -37,7 +37,7 @@ use tokio::{
     net::TcpStream,
 };
 use tokio_rustls::server::TlsStream;
-use utils::listener::{limiter::InFlight, ServerInstance};
+use utils::listener::{limiter::InFlight, tls_client_identity, ServerInstance};
 
 pub struct Session<T: AsyncRead + AsyncWrite> {
     pub jmap: Arc<JMAP>,
@@ -103,18 +103,27 @@ pub enum Command {
 
 pub trait IsTls {
     fn is_tls(&self) -> bool;
+    fn tls_identity(&self) -> Option<String>;
 }
 
 impl IsTls for TcpStream {
     fn is_tls(&self) -> bool {
         false
     }
+
+    fn tls_identity(&self) -> Option<String> {
+        None
+    }
 }
 
 impl IsTls for TlsStream<TcpStream> {
     fn is_tls(&self) -> bool {
         true
     }
+
+    fn tls_identity(&self) -> Option<String> {
+        tls_client_identity(self)
+    }
 }
 
 impl CommandParser for Command {
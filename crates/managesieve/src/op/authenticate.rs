@@ -47,17 +47,19 @@ impl<T: AsyncRead + AsyncWrite + IsTls> Session<T> {
             .filter_map(|token| token.unwrap_string().ok())
             .collect();
 
-        let credentials = match mechanism {
+        let request = match mechanism {
             Mechanism::Plain | Mechanism::OAuthBearer => {
                 if !params.is_empty() {
                     let challenge = base64_decode(params.pop().unwrap().as_bytes())
                         .ok_or_else(|| StatusResponse::no("Failed to decode challenge."))?;
-                    (if mechanism == Mechanism::Plain {
-                        decode_challenge_plain(&challenge)
-                    } else {
-                        decode_challenge_oauth(&challenge)
-                    }
-                    .map_err(StatusResponse::no))?
+                    AuthRequest::Credentials(
+                        (if mechanism == Mechanism::Plain {
+                            decode_challenge_plain(&challenge)
+                        } else {
+                            decode_challenge_oauth(&challenge)
+                        }
+                        .map_err(StatusResponse::no))?,
+                    )
                 } else {
                     self.receiver.request = receiver::Request {
                         tag: String::new(),
@@ -68,6 +70,37 @@ impl<T: AsyncRead + AsyncWrite + IsTls> Session<T> {
                     return Ok(b"{0}\r\n".to_vec());
                 }
             }
+            Mechanism::External => {
+                let identity = self
+                    .stream
+                    .tls_identity()
+                    .ok_or_else(|| StatusResponse::no("No client certificate was presented."))?;
+                if !params.is_empty() {
+                    let authzid = params.pop().unwrap();
+                    AuthRequest::External {
+                        identity,
+                        authzid: if authzid.is_empty() || authzid == "=" {
+                            String::new()
+                        } else {
+                            base64_decode(authzid.as_bytes())
+                                .and_then(|authzid| String::from_utf8(authzid).ok())
+                                .ok_or_else(|| StatusResponse::no("Failed to decode challenge."))?
+                        },
+                    }
+                } else {
+                    // An empty response from the client yields the "=" placeholder
+                    self.receiver.request = receiver::Request {
+                        tag: String::new(),
+                        command: Command::Authenticate,
+                        tokens: vec![
+                            receiver::Token::Argument(mechanism.into_bytes()),
+                            receiver::Token::Argument(b"=".to_vec()),
+                        ],
+                    };
+                    self.receiver.state = receiver::State::Argument { last_ch: b' ' };
+                    return Ok(b"{0}\r\n".to_vec());
+                }
+            }
             _ => {
                 return Err(StatusResponse::no(
                     "Authentication mechanism not supported.",
@@ -87,11 +120,11 @@ impl<T: AsyncRead + AsyncWrite + IsTls> Session<T> {
         }
 
         // Authenticate
-        let access_token = match credentials {
-            Credentials::Plain { username, secret } | Credentials::XOauth2 { username, secret } => {
-                self.jmap.authenticate_plain(&username, &secret).await
-            }
-            Credentials::OAuthBearer { token } => {
+        let access_token = match request {
+            AuthRequest::Credentials(
+                Credentials::Plain { username, secret } | Credentials::XOauth2 { username, secret },
+            ) => self.jmap.authenticate_plain(&username, &secret).await,
+            AuthRequest::Credentials(Credentials::OAuthBearer { token }) => {
                 match self
                     .jmap
                     .validate_access_token("access_token", &token)
@@ -109,6 +142,9 @@ impl<T: AsyncRead + AsyncWrite + IsTls> Session<T> {
                     }
                 }
             }
+            AuthRequest::External { identity, authzid } => {
+                self.jmap.authenticate_external(&identity, &authzid).await
+            }
         };
 
         if let Some(access_token) = access_token {
@@ -166,3 +202,8 @@ impl<T: AsyncRead + AsyncWrite + IsTls> Session<T> {
         Ok(StatusResponse::ok("Unauthenticate successful.").into_bytes())
     }
 }
+
+enum AuthRequest {
+    Credentials(Credentials<String>),
+    External { identity: String, authzid: String },
+}
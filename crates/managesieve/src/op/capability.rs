@@ -36,6 +36,8 @@ impl<T: AsyncRead + AsyncWrite + IsTls> Session<T> {
         if !self.stream.is_tls() {
             response.extend_from_slice(b"\"SASL\" \"\"\r\n");
             response.extend_from_slice(b"\"STARTTLS\"\r\n");
+        } else if self.stream.tls_identity().is_some() {
+            response.extend_from_slice(b"\"SASL\" \"PLAIN OAUTHBEARER EXTERNAL\"\r\n");
         } else {
             response.extend_from_slice(b"\"SASL\" \"PLAIN OAUTHBEARER\"\r\n");
         };
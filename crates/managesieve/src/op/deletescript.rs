@@ -36,6 +36,11 @@ impl<T: AsyncRead + AsyncWrite> Session<T> {
             .next()
             .and_then(|s| s.unwrap_string().ok())
             .ok_or_else(|| StatusResponse::no("Expected script name as a parameter."))?;
+        if name.trim().eq_ignore_ascii_case("vacation") {
+            return Err(StatusResponse::no(
+                "The 'vacation' script is managed by the server and cannot be deleted.",
+            ));
+        }
 
         let account_id = self.state.access_token().primary_id();
         let document_id = self.get_script_id(account_id, &name).await?;
@@ -53,6 +53,11 @@ impl<T: AsyncRead + AsyncWrite> Session<T> {
         if name == new_name {
             return Ok(StatusResponse::ok("Old and new script names are the same.").into_bytes());
         }
+        if name.eq_ignore_ascii_case("vacation") {
+            return Err(StatusResponse::no(
+                "The 'vacation' script is managed by the server and cannot be renamed.",
+            ));
+        }
         let account_id = self.state.access_token().primary_id();
         let document_id = self.get_script_id(account_id, &name).await?;
         self.validate_name(account_id, &new_name).await?;
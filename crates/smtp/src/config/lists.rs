@@ -0,0 +1,165 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use utils::config::{
+    utils::{AsKey, ParseValue},
+    Config,
+};
+
+use super::{ConfigContext, ListPosting, ListReplyTo, ListSubscription, ListsConfig, MailingList};
+
+pub trait ConfigLists {
+    fn parse_lists(&self, ctx: &ConfigContext) -> super::Result<ListsConfig>;
+    fn parse_list(&self, id: &str, ctx: &ConfigContext) -> super::Result<MailingList>;
+}
+
+impl ConfigLists for Config {
+    fn parse_lists(&self, ctx: &ConfigContext) -> super::Result<ListsConfig> {
+        let mut lists = AHashMap::new();
+        let mut addresses = AHashMap::new();
+        for id in self.sub_keys("list") {
+            let list = self.parse_list(id, ctx)?;
+            if let Some(other_id) = addresses.insert(list.address.clone(), list.id.clone()) {
+                return Err(format!(
+                    "Address {:?} is used by both mailing lists {:?} and {:?}.",
+                    list.address, other_id, list.id
+                ));
+            }
+            lists.insert(id.to_string(), Arc::new(list));
+        }
+
+        if lists.is_empty() {
+            return Ok(ListsConfig::default());
+        }
+
+        Ok(ListsConfig {
+            path: self.property_require("lists.path")?,
+            secret: self.value_require("lists.secret")?.to_string(),
+            unsubscribe_url: self
+                .value("lists.unsubscribe-url")
+                .map(|url| url.trim_end_matches('?').to_string()),
+            bounce_limit: self.property("lists.bounce-limit")?.unwrap_or(5),
+            lists,
+        })
+    }
+
+    fn parse_list(&self, id: &str, ctx: &ConfigContext) -> super::Result<MailingList> {
+        let address = self.value_require(("list", id, "address"))?.to_lowercase();
+        let (local_part, domain) = address
+            .rsplit_once('@')
+            .filter(|(local_part, domain)| !local_part.is_empty() && !domain.is_empty())
+            .map(|(local_part, domain)| (local_part.to_string(), domain.to_string()))
+            .ok_or_else(|| {
+                format!(
+                    "Invalid address {:?} for key {:?}.",
+                    address,
+                    ("list", id, "address").as_key()
+                )
+            })?;
+
+        // Parse DKIM signatures
+        let mut sign = Vec::new();
+        for (pos, signer_id) in self.values(("list", id, "sign")) {
+            if let Some(dkim) = ctx.signers.get(signer_id) {
+                sign.push(dkim.clone());
+            } else {
+                return Err(format!(
+                    "No DKIM signer found with id {:?} for key {:?}.",
+                    signer_id,
+                    ("list", id, "sign", pos).as_key()
+                ));
+            }
+        }
+
+        Ok(MailingList {
+            id: id.to_string(),
+            name: self
+                .value(("list", id, "name"))
+                .unwrap_or(local_part.as_str())
+                .to_string(),
+            posting: self
+                .property(("list", id, "posting"))?
+                .unwrap_or(ListPosting::Members),
+            reply_to: self
+                .property(("list", id, "reply-to"))?
+                .unwrap_or(ListReplyTo::List),
+            subscription: self
+                .property(("list", id, "subscription"))?
+                .unwrap_or(ListSubscription::Confirm),
+            moderators: self
+                .values(("list", id, "moderators"))
+                .map(|(_, address)| address.to_lowercase())
+                .collect(),
+            sign,
+            address,
+            local_part,
+            domain,
+        })
+    }
+}
+
+impl ParseValue for ListPosting {
+    fn parse_value(key: impl AsKey, value: &str) -> super::Result<Self> {
+        match value {
+            "open" => Ok(ListPosting::Open),
+            "members" => Ok(ListPosting::Members),
+            "moderated" => Ok(ListPosting::Moderated),
+            _ => Err(format!(
+                "Invalid posting policy {:?} for key {:?}.",
+                value,
+                key.as_key()
+            )),
+        }
+    }
+}
+
+impl ParseValue for ListReplyTo {
+    fn parse_value(key: impl AsKey, value: &str) -> super::Result<Self> {
+        match value {
+            "list" => Ok(ListReplyTo::List),
+            "sender" => Ok(ListReplyTo::Sender),
+            _ => Err(format!(
+                "Invalid reply-to policy {:?} for key {:?}.",
+                value,
+                key.as_key()
+            )),
+        }
+    }
+}
+
+impl ParseValue for ListSubscription {
+    fn parse_value(key: impl AsKey, value: &str) -> super::Result<Self> {
+        match value {
+            "confirm" => Ok(ListSubscription::Confirm),
+            "closed" => Ok(ListSubscription::Closed),
+            _ => Err(format!(
+                "Invalid subscription policy {:?} for key {:?}.",
+                value,
+                key.as_key()
+            )),
+        }
+    }
+}
@@ -24,13 +24,16 @@
 pub mod auth;
 pub mod condition;
 pub mod if_block;
+pub mod lists;
 pub mod queue;
 pub mod remote;
 pub mod report;
 pub mod resolver;
+pub mod rewrite;
 pub mod scripts;
 pub mod session;
 pub mod throttle;
+pub mod webhook;
 
 use std::{
     net::{Ipv4Addr, Ipv6Addr, SocketAddr},
@@ -231,12 +234,15 @@ pub struct Auth {
 
 pub struct Mail {
     pub script: IfBlock<Option<Arc<Sieve>>>,
+    pub rewrite: IfBlock<Option<Arc<Rewrite>>>,
 }
 
 pub struct Rcpt {
     pub script: IfBlock<Option<Arc<Sieve>>>,
+    pub rewrite: IfBlock<Option<Arc<Rewrite>>>,
     pub relay: IfBlock<bool>,
     pub directory: IfBlock<Option<Arc<dyn Directory>>>,
+    pub suppression: IfBlock<SuppressionAction>,
 
     // Errors
     pub errors_max: IfBlock<usize>,
@@ -248,6 +254,7 @@ pub struct Rcpt {
 
 pub struct Data {
     pub script: IfBlock<Option<Arc<Sieve>>>,
+    pub rewrite: IfBlock<Option<Arc<Rewrite>>>,
     pub pipe_commands: Vec<Pipe>,
     pub milters: Vec<Milter>,
 
@@ -263,6 +270,19 @@ pub struct Data {
     pub add_auth_results: IfBlock<bool>,
     pub add_message_id: IfBlock<bool>,
     pub add_date: IfBlock<bool>,
+
+    // Bounces
+    pub suppress_bounces: IfBlock<bool>,
+}
+
+pub struct Rewrite {
+    pub rules: Vec<RewriteRule>,
+}
+
+pub struct RewriteRule {
+    pub regex: Option<Regex>,
+    pub lookup: Option<Arc<Lookup>>,
+    pub replace: String,
 }
 
 pub struct Pipe {
@@ -330,6 +350,7 @@ pub struct QueueConfig {
     pub next_hop: IfBlock<Option<RelayHost>>,
     pub max_mx: IfBlock<usize>,
     pub max_multihomed: IfBlock<usize>,
+    pub max_pooled: IfBlock<usize>,
     pub ip_strategy: IfBlock<IpLookupStrategy>,
     pub source_ip: QueueOutboundSourceIp,
     pub tls: QueueOutboundTls,
@@ -341,6 +362,7 @@ pub struct QueueConfig {
     // Throttle and Quotas
     pub throttle: QueueThrottle,
     pub quota: QueueQuotas,
+    pub quarantine: QueueQuarantine,
     pub management_lookup: Arc<dyn Directory>,
 }
 
@@ -360,6 +382,7 @@ pub struct ReportConfig {
     pub dmarc: Report,
     pub dmarc_aggregate: AggregateReport,
     pub tls: AggregateReport,
+    pub webhooks: Vec<Arc<Webhook>>,
 }
 
 pub struct ReportAnalysis {
@@ -414,6 +437,54 @@ pub struct QueueOutboundTimeout {
     pub rcpt: IfBlock<Duration>,
     pub data: IfBlock<Duration>,
     pub mta_sts: IfBlock<Duration>,
+    pub idle: IfBlock<Duration>,
+}
+
+pub struct QueueQuarantine {
+    pub path: Option<PathBuf>,
+    pub retention: Duration,
+    pub purge_frequency: Duration,
+}
+
+#[derive(Default)]
+pub struct ListsConfig {
+    pub path: PathBuf,
+    pub secret: String,
+    pub unsubscribe_url: Option<String>,
+    pub bounce_limit: u32,
+    pub lists: AHashMap<String, Arc<MailingList>>,
+}
+
+pub struct MailingList {
+    pub id: String,
+    pub name: String,
+    pub address: String,
+    pub local_part: String,
+    pub domain: String,
+    pub posting: ListPosting,
+    pub reply_to: ListReplyTo,
+    pub subscription: ListSubscription,
+    pub moderators: Vec<String>,
+    pub sign: Vec<Arc<DkimSigner>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListPosting {
+    Open,
+    Members,
+    Moderated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListReplyTo {
+    List,
+    Sender,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListSubscription {
+    Confirm,
+    Closed,
 }
 
 #[derive(Debug)]
@@ -436,6 +507,27 @@ pub struct QueueQuota {
     pub messages: Option<usize>,
 }
 
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    pub client: reqwest::Client,
+    pub events: Vec<WebhookEventType>,
+    pub key: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub attempts_max: u32,
+    pub attempts_interval: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventType {
+    Delivered,
+    Deferred,
+    Bounced,
+    Expired,
+    Rejected,
+}
+
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum AggregateFrequency {
     Hourly,
@@ -527,6 +619,14 @@ pub enum VerifyStrategy {
     Disable,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SuppressionAction {
+    Reject,
+    Warn,
+    #[default]
+    Disable,
+}
+
 #[derive(Default)]
 pub struct ConfigContext<'x> {
     pub servers: &'x [Server],
@@ -535,6 +635,7 @@ pub struct ConfigContext<'x> {
     pub directory: DirectoryConfig,
     pub signers: AHashMap<String, Arc<DkimSigner>>,
     pub sealers: AHashMap<String, Arc<ArcSealer>>,
+    pub rewrites: AHashMap<String, Arc<Rewrite>>,
 }
 
 impl<'x> ConfigContext<'x> {
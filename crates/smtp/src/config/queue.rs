@@ -21,11 +21,13 @@
  * for more details.
 */
 
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
 use directory::memory::MemoryDirectory;
 use mail_send::Credentials;
 
+use crate::core::scripts::QUARANTINE_COMMAND;
+
 use super::{
     condition::ConfigCondition,
     if_block::ConfigIf,
@@ -41,6 +43,7 @@ pub trait ConfigQueue {
     fn parse_queue(&self, ctx: &ConfigContext) -> super::Result<QueueConfig>;
     fn parse_queue_throttle(&self, ctx: &ConfigContext) -> super::Result<QueueThrottle>;
     fn parse_queue_quota(&self, ctx: &ConfigContext) -> super::Result<QueueQuotas>;
+    fn parse_queue_quarantine(&self) -> super::Result<QueueQuarantine>;
     fn parse_queue_quota_item(
         &self,
         prefix: impl AsKey,
@@ -126,6 +129,9 @@ impl ConfigQueue for Config {
             max_multihomed: self
                 .parse_if_block("queue.outbound.limits.multihomed", ctx, &rcpt_envelope_keys)?
                 .unwrap_or_else(|| IfBlock::new(2)),
+            max_pooled: self
+                .parse_if_block("queue.outbound.limits.pool", ctx, &host_envelope_keys)?
+                .unwrap_or_else(|| IfBlock::new(0)),
             ip_strategy: self
                 .parse_if_block("queue.outbound.ip-strategy", ctx, &sender_envelope_keys)?
                 .unwrap_or_else(|| IfBlock::new(IpLookupStrategy::Ipv4thenIpv6)),
@@ -180,6 +186,9 @@ impl ConfigQueue for Config {
                 mta_sts: self
                     .parse_if_block("queue.outbound.timeouts.mta-sts", ctx, &rcpt_envelope_keys)?
                     .unwrap_or_else(|| IfBlock::new(Duration::from_secs(10 * 60))),
+                idle: self
+                    .parse_if_block("queue.outbound.timeouts.idle", ctx, &host_envelope_keys)?
+                    .unwrap_or_else(|| IfBlock::new(Duration::from_secs(60))),
             },
             dsn: Dsn {
                 name: self
@@ -193,6 +202,7 @@ impl ConfigQueue for Config {
                     .unwrap_or_default()
                     .map_if_block(&ctx.signers, "report.dsn.sign", "signature")?,
             },
+            quarantine: self.parse_queue_quarantine()?,
             management_lookup: if let Some(id) = self.value("management.directory") {
                 ctx.directory
                     .directories
@@ -275,6 +285,34 @@ impl ConfigQueue for Config {
         Ok(throttle)
     }
 
+    fn parse_queue_quarantine(&self) -> super::Result<QueueQuarantine> {
+        let path: Option<PathBuf> = self.property("quarantine.path")?;
+
+        // Scripts can only quarantine messages if a quarantine path is configured
+        if path.is_none() {
+            for id in self.sub_keys("sieve.scripts") {
+                if String::from_utf8_lossy(&self.file_contents(("sieve.scripts", id))?)
+                    .to_lowercase()
+                    .contains(QUARANTINE_COMMAND)
+                {
+                    return Err(format!(
+                        "Sieve script {id:?} quarantines messages but \"quarantine.path\" is not configured."
+                    ));
+                }
+            }
+        }
+
+        Ok(QueueQuarantine {
+            path,
+            retention: self
+                .property("quarantine.retention")?
+                .unwrap_or_else(|| Duration::from_secs(30 * 86400)),
+            purge_frequency: self
+                .property("quarantine.purge.frequency")?
+                .unwrap_or_else(|| Duration::from_secs(3600)),
+        })
+    }
+
     fn parse_queue_quota(&self, ctx: &ConfigContext) -> super::Result<QueueQuotas> {
         let mut capacities = QueueQuotas {
             sender: Vec::new(),
@@ -21,9 +21,11 @@
  * for more details.
 */
 
+use std::sync::Arc;
+
 use super::{
-    if_block::ConfigIf, AddressMatch, AggregateFrequency, AggregateReport, ConfigContext,
-    EnvelopeKey, IfBlock, Report, ReportAnalysis, ReportConfig,
+    if_block::ConfigIf, webhook::ConfigWebhook, AddressMatch, AggregateFrequency, AggregateReport,
+    ConfigContext, EnvelopeKey, IfBlock, Report, ReportAnalysis, ReportConfig,
 };
 use utils::config::{
     utils::{AsKey, ParseValue},
@@ -99,6 +101,7 @@ impl ConfigReport for Config {
                 store: self.property("report.analysis.store")?,
                 report_id: 0.into(),
             },
+            webhooks: self.parse_webhooks()?.into_iter().map(Arc::new).collect(),
         })
     }
 
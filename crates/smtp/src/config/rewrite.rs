@@ -0,0 +1,109 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use regex::Regex;
+use utils::config::{utils::AsKey, Config};
+
+use super::{ConfigContext, Rewrite, RewriteRule};
+
+pub trait ConfigRewrite {
+    fn parse_rewrites(&self, ctx: &mut ConfigContext) -> super::Result<()>;
+    fn parse_rewrite(&self, id: &str, ctx: &ConfigContext) -> super::Result<Rewrite>;
+}
+
+impl ConfigRewrite for Config {
+    fn parse_rewrites(&self, ctx: &mut ConfigContext) -> super::Result<()> {
+        for id in self.sub_keys("rewrite") {
+            let rewrite = self.parse_rewrite(id, ctx)?;
+            ctx.rewrites.insert(id.to_string(), Arc::new(rewrite));
+        }
+
+        Ok(())
+    }
+
+    fn parse_rewrite(&self, id: &str, ctx: &ConfigContext) -> super::Result<Rewrite> {
+        let prefix = ("rewrite", id, "rules").as_key();
+        let mut array_pos = self
+            .sub_keys(prefix.as_str())
+            .map(|pos| {
+                pos.parse::<usize>()
+                    .map(|num| (num, pos))
+                    .map_err(|_| format!("Invalid rule position {pos:?} for key {prefix:?}."))
+            })
+            .collect::<super::Result<Vec<_>>>()?;
+        array_pos.sort_unstable_by_key(|(num, _)| *num);
+
+        let mut rules = Vec::with_capacity(array_pos.len());
+        for (_, pos) in array_pos {
+            let regex = if let Some(value) = self.value((prefix.as_str(), pos, "match")) {
+                Regex::new(value)
+                    .map_err(|err| {
+                        format!(
+                            "Failed to compile regular expression {:?} for key {:?}: {}.",
+                            value,
+                            (prefix.as_str(), pos, "match").as_key(),
+                            err
+                        )
+                    })?
+                    .into()
+            } else {
+                None
+            };
+            let lookup = if let Some(value) = self.value((prefix.as_str(), pos, "lookup")) {
+                if let Some(lookup) = ctx.directory.lookups.get(value) {
+                    lookup.clone().into()
+                } else {
+                    return Err(format!(
+                        "Lookup {:?} not found for key {:?}.",
+                        value,
+                        (prefix.as_str(), pos, "lookup").as_key()
+                    ));
+                }
+            } else {
+                None
+            };
+            if regex.is_none() && lookup.is_none() {
+                return Err(format!(
+                    "Rewrite rule {:?} requires either a 'match' or a 'lookup' property.",
+                    (prefix.as_str(), pos).as_key()
+                ));
+            }
+
+            rules.push(RewriteRule {
+                regex,
+                lookup,
+                replace: self
+                    .value_require((prefix.as_str(), pos, "replace"))?
+                    .to_string(),
+            });
+        }
+
+        if !rules.is_empty() {
+            Ok(Rewrite { rules })
+        } else {
+            Err(format!("No rules defined for rewrite {id:?}."))
+        }
+    }
+}
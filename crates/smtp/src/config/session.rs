@@ -296,6 +296,10 @@ impl ConfigSession for Config {
                 .parse_if_block::<Option<String>>("session.mail.script", ctx, &available_keys)?
                 .unwrap_or_default()
                 .map_if_block(&ctx.scripts, "session.mail.script", "script")?,
+            rewrite: self
+                .parse_if_block::<Option<String>>("session.mail.rewrite", ctx, &available_keys)?
+                .unwrap_or_default()
+                .map_if_block(&ctx.rewrites, "session.mail.rewrite", "rewrite")?,
         })
     }
 
@@ -314,6 +318,10 @@ impl ConfigSession for Config {
                 .parse_if_block::<Option<String>>("session.rcpt.script", ctx, &available_keys)?
                 .unwrap_or_default()
                 .map_if_block(&ctx.scripts, "session.rcpt.script", "script")?,
+            rewrite: self
+                .parse_if_block::<Option<String>>("session.rcpt.rewrite", ctx, &available_keys)?
+                .unwrap_or_default()
+                .map_if_block(&ctx.rewrites, "session.rcpt.rewrite", "rewrite")?,
             relay: self
                 .parse_if_block("session.rcpt.relay", ctx, &available_keys)?
                 .unwrap_or_else(|| IfBlock::new(false)),
@@ -325,6 +333,9 @@ impl ConfigSession for Config {
                     "session.rcpt.directory",
                     "lookup list",
                 )?,
+            suppression: self
+                .parse_if_block("session.rcpt.suppression", ctx, &available_keys)?
+                .unwrap_or_default(),
             errors_max: self
                 .parse_if_block("session.rcpt.errors.max", ctx, &available_keys)?
                 .unwrap_or_else(|| IfBlock::new(10)),
@@ -353,6 +364,10 @@ impl ConfigSession for Config {
                 .parse_if_block::<Option<String>>("session.data.script", ctx, &available_keys)?
                 .unwrap_or_default()
                 .map_if_block(&ctx.scripts, "session.data.script", "script")?,
+            rewrite: self
+                .parse_if_block::<Option<String>>("session.data.rewrite", ctx, &available_keys)?
+                .unwrap_or_default()
+                .map_if_block(&ctx.rewrites, "session.data.rewrite", "rewrite")?,
             max_messages: self
                 .parse_if_block("session.data.limits.messages", ctx, &available_keys)?
                 .unwrap_or_else(|| IfBlock::new(10)),
@@ -388,6 +403,9 @@ impl ConfigSession for Config {
             add_date: self
                 .parse_if_block("session.data.add-headers.date", ctx, &available_keys)?
                 .unwrap_or_else(|| IfBlock::new(true)),
+            suppress_bounces: self
+                .parse_if_block("session.data.bounces.suppress", ctx, &available_keys)?
+                .unwrap_or_else(|| IfBlock::new(false)),
             pipe_commands: self.parse_pipes(ctx, &available_keys)?,
             milters: self.parse_milters(ctx, &available_keys)?,
         })
@@ -473,6 +491,21 @@ struct Mechanism {
     mechanism: u64,
 }
 
+impl ParseValue for SuppressionAction {
+    fn parse_value(key: impl AsKey, value: &str) -> super::Result<Self> {
+        match value {
+            "reject" => Ok(SuppressionAction::Reject),
+            "warn" => Ok(SuppressionAction::Warn),
+            "disable" | "false" => Ok(SuppressionAction::Disable),
+            _ => Err(format!(
+                "Invalid suppression action {:?} for key {:?}.",
+                value,
+                key.as_key()
+            )),
+        }
+    }
+}
+
 impl ParseValue for Mechanism {
     fn parse_value(key: impl AsKey, value: &str) -> super::Result<Self> {
         Ok(Mechanism {
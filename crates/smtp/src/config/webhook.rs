@@ -0,0 +1,118 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use utils::config::{
+    utils::{AsKey, ParseValue},
+    Config,
+};
+
+use crate::USER_AGENT;
+
+use super::{Webhook, WebhookEventType};
+
+pub trait ConfigWebhook {
+    fn parse_webhooks(&self) -> super::Result<Vec<Webhook>>;
+    fn parse_webhook(&self, id: &str) -> super::Result<Webhook>;
+}
+
+impl ConfigWebhook for Config {
+    fn parse_webhooks(&self) -> super::Result<Vec<Webhook>> {
+        let mut webhooks = Vec::new();
+        for id in self.sub_keys("webhook") {
+            webhooks.push(self.parse_webhook(id)?);
+        }
+
+        Ok(webhooks)
+    }
+
+    fn parse_webhook(&self, id: &str) -> super::Result<Webhook> {
+        let mut events = Vec::new();
+        for event in self.properties::<WebhookEventType>(("webhook", id, "events")) {
+            events.push(event?.1);
+        }
+        if events.is_empty() {
+            events = vec![
+                WebhookEventType::Delivered,
+                WebhookEventType::Deferred,
+                WebhookEventType::Bounced,
+                WebhookEventType::Expired,
+                WebhookEventType::Rejected,
+            ];
+        }
+
+        let mut headers = Vec::new();
+        for (_, header) in self.values(("webhook", id, "headers")) {
+            if let Some((name, value)) = header.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            } else {
+                return Err(format!(
+                    "Invalid header {header:?} found in webhook {id:?}, expected 'Name: Value'."
+                ));
+            }
+        }
+
+        let timeout = self
+            .property(("webhook", id, "timeout"))?
+            .unwrap_or(Duration::from_secs(30));
+        let tls_allow_invalid_certs = self
+            .property(("webhook", id, "tls.allow-invalid-certs"))?
+            .unwrap_or(false);
+
+        Ok(Webhook {
+            id: id.to_string(),
+            url: self.property_require(("webhook", id, "url"))?,
+            client: reqwest::Client::builder()
+                .user_agent(USER_AGENT)
+                .timeout(timeout)
+                .danger_accept_invalid_certs(tls_allow_invalid_certs)
+                .build()
+                .map_err(|err| format!("Failed to build HTTP client for webhook {id:?}: {err}"))?,
+            events,
+            key: self.property(("webhook", id, "signature-key"))?,
+            headers,
+            attempts_max: self.property(("webhook", id, "attempts.max"))?.unwrap_or(5),
+            attempts_interval: self
+                .property(("webhook", id, "attempts.interval"))?
+                .unwrap_or(Duration::from_secs(60)),
+        })
+    }
+}
+
+impl ParseValue for WebhookEventType {
+    fn parse_value(key: impl AsKey, value: &str) -> super::Result<Self> {
+        match value {
+            "delivered" => Ok(WebhookEventType::Delivered),
+            "deferred" | "delayed" => Ok(WebhookEventType::Deferred),
+            "bounced" | "failed" => Ok(WebhookEventType::Bounced),
+            "expired" => Ok(WebhookEventType::Expired),
+            "rejected" => Ok(WebhookEventType::Rejected),
+            _ => Err(format!(
+                "Invalid webhook event {:?} for key {:?}.",
+                value,
+                key.as_key()
+            )),
+        }
+    }
+}
@@ -44,6 +44,7 @@ use tokio::{
 use utils::listener::{limiter::InFlight, SessionManager};
 
 use crate::{
+    config::{ListsConfig, MailingList},
     queue::{self, instant_to_timestamp, InstantFromTimestamp, QueueId, Status},
     reporting::{
         self,
@@ -156,6 +157,36 @@ pub struct Report {
     pub size: usize,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SuppressedAddress {
+    pub domain: String,
+    pub address: String,
+    pub reason: String,
+    #[serde(deserialize_with = "deserialize_datetime")]
+    #[serde(serialize_with = "serialize_datetime")]
+    pub created: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuarantinedMessage {
+    pub id: QueueId,
+    pub return_path: String,
+    pub recipients: Vec<String>,
+    #[serde(deserialize_with = "deserialize_datetime")]
+    #[serde(serialize_with = "serialize_datetime")]
+    pub created: DateTime,
+    pub size: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuarantinePreview {
+    #[serde(flatten)]
+    pub message: QuarantinedMessage,
+    pub headers: String,
+    pub preview: String,
+}
+
 impl SessionManager for SmtpAdminSessionManager {
     fn spawn(&self, session: utils::listener::SessionData<tokio::net::TcpStream>) {
         let core = self.inner.clone();
@@ -233,6 +264,11 @@ impl SMTP {
         &self,
         req: &hyper::Request<hyper::body::Incoming>,
     ) -> Result<hyper::Response<BoxBody<Bytes, hyper::Error>>, hyper::Error> {
+        // RFC 8058 one-click unsubscribe requests are authenticated by their token
+        if req.method() == Method::POST && req.uri().path() == "/list/unsubscribe" {
+            return Ok(self.handle_list_unsubscribe(req.uri()).await);
+        }
+
         // Authenticate request
         let mut is_authenticated = false;
         if let Some((mechanism, payload)) = req
@@ -655,6 +691,191 @@ impl SMTP {
                     Some(error) => error.into_bad_request(),
                 }
             }
+            #[cfg(feature = "local_delivery")]
+            (&Method::GET, "suppression", "list") => {
+                let mut domain = None;
+                let mut error = None;
+
+                if let Some(query) = uri.query() {
+                    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                        match key.as_ref() {
+                            "domain" => {
+                                if !value.is_empty() {
+                                    domain = value.to_lowercase().into();
+                                }
+                            }
+                            _ => {
+                                error = format!("Invalid parameter {key:?}.").into();
+                                break;
+                            }
+                        }
+                    }
+                }
+
+                match error {
+                    None => {
+                        let (result_tx, result_rx) = oneshot::channel();
+                        self.send_suppression_event(
+                            utils::ipc::SuppressionEvent::List { domain, result_tx },
+                            result_rx,
+                            |addresses| {
+                                addresses
+                                    .into_iter()
+                                    .map(SuppressedAddress::from)
+                                    .collect::<Vec<_>>()
+                            },
+                        )
+                        .await
+                    }
+                    Some(error) => error.into_bad_request(),
+                }
+            }
+            #[cfg(feature = "local_delivery")]
+            (&Method::GET, "suppression", "clear") => {
+                let mut domain = None;
+                let mut address = None;
+                let mut error = None;
+
+                if let Some(query) = uri.query() {
+                    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                        match key.as_ref() {
+                            "domain" => {
+                                if !value.is_empty() {
+                                    domain = value.to_lowercase().into();
+                                }
+                            }
+                            "address" => {
+                                if !value.is_empty() {
+                                    address = value.to_lowercase().into();
+                                }
+                            }
+                            _ => {
+                                error = format!("Invalid parameter {key:?}.").into();
+                                break;
+                            }
+                        }
+                    }
+                }
+
+                match error {
+                    None if domain.is_some() || address.is_some() => {
+                        let (result_tx, result_rx) = oneshot::channel();
+                        self.send_suppression_event(
+                            utils::ipc::SuppressionEvent::Clear {
+                                domain,
+                                address,
+                                result_tx,
+                            },
+                            result_rx,
+                            |num_cleared| num_cleared,
+                        )
+                        .await
+                    }
+                    None => "Missing domain or address parameter."
+                        .to_string()
+                        .into_bad_request(),
+                    Some(error) => error.into_bad_request(),
+                }
+            }
+            (&Method::GET, "quarantine", "list") => {
+                let messages = self
+                    .queue
+                    .quarantine_list()
+                    .await
+                    .into_iter()
+                    .map(QuarantinedMessage::from)
+                    .collect::<Vec<_>>();
+                (
+                    StatusCode::OK,
+                    serde_json::to_string(&Response { data: messages }).unwrap_or_default(),
+                )
+            }
+            (&Method::GET, "quarantine", "preview") => match parse_quarantine_ids(uri) {
+                Ok(ids) => {
+                    let mut previews = Vec::with_capacity(ids.len());
+                    for id in ids {
+                        previews.push(
+                            self.queue
+                                .quarantine_read(id)
+                                .await
+                                .map(QuarantinePreview::from),
+                        );
+                    }
+                    (
+                        StatusCode::OK,
+                        serde_json::to_string(&Response { data: previews }).unwrap_or_default(),
+                    )
+                }
+                Err(error) => error.into_bad_request(),
+            },
+            (&Method::GET, "quarantine", "release") => match parse_quarantine_ids(uri) {
+                Ok(ids) => {
+                    let span = tracing::info_span!("quarantine-release");
+                    let mut results = Vec::with_capacity(ids.len());
+                    for id in ids {
+                        results.push(self.queue.quarantine_release(id, &span).await);
+                    }
+                    (
+                        StatusCode::OK,
+                        serde_json::to_string(&Response { data: results }).unwrap_or_default(),
+                    )
+                }
+                Err(error) => error.into_bad_request(),
+            },
+            (&Method::GET, "quarantine", "delete") => match parse_quarantine_ids(uri) {
+                Ok(ids) => {
+                    let mut results = Vec::with_capacity(ids.len());
+                    for id in ids {
+                        results.push(self.queue.quarantine_delete(id).await);
+                    }
+                    (
+                        StatusCode::OK,
+                        serde_json::to_string(&Response { data: results }).unwrap_or_default(),
+                    )
+                }
+                Err(error) => error.into_bad_request(),
+            },
+            (&Method::GET, "list", "members") => {
+                match parse_list_request(&self.lists.config, uri) {
+                    Ok(request) => {
+                        if let Some(members) = self.lists.members(&request.list).await {
+                            (
+                                StatusCode::OK,
+                                serde_json::to_string(&Response { data: members })
+                                    .unwrap_or_default(),
+                            )
+                        } else {
+                            (
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                "{\"error\": \"internal-error\", \"details\": \"Failed to read member list.\"}"
+                                    .to_string(),
+                            )
+                        }
+                    }
+                    Err(error) => error.into_bad_request(),
+                }
+            }
+            (&Method::GET, "list", action @ ("subscribe" | "unsubscribe")) => {
+                match parse_list_request(&self.lists.config, uri) {
+                    Ok(ListRequest {
+                        list,
+                        address: Some(address),
+                        ..
+                    }) => {
+                        let result = if action == "subscribe" {
+                            self.lists.subscribe(&list, &address).await
+                        } else {
+                            self.lists.unsubscribe(&list, &address).await
+                        };
+                        (
+                            StatusCode::OK,
+                            serde_json::to_string(&Response { data: result }).unwrap_or_default(),
+                        )
+                    }
+                    Ok(_) => "Missing address parameter.".to_string().into_bad_request(),
+                    Err(error) => error.into_bad_request(),
+                }
+            }
             _ => (
                 StatusCode::NOT_FOUND,
                 format!(
@@ -675,6 +896,41 @@ impl SMTP {
             .unwrap()
     }
 
+    pub async fn handle_list_unsubscribe(
+        &self,
+        uri: &Uri,
+    ) -> hyper::Response<BoxBody<Bytes, hyper::Error>> {
+        let (status, response) = match parse_list_request(&self.lists.config, uri) {
+            Ok(ListRequest {
+                list,
+                address: Some(address),
+                token: Some(token),
+            }) if self
+                .lists
+                .config
+                .verify_token(&list, "unsubscribe", &address, &token) =>
+            {
+                self.lists.unsubscribe(&list, &address).await;
+                (
+                    StatusCode::OK,
+                    serde_json::to_string(&Response { data: true }).unwrap_or_default(),
+                )
+            }
+            Ok(_) => "Invalid address or token.".to_string().into_bad_request(),
+            Err(error) => error.into_bad_request(),
+        };
+
+        hyper::Response::builder()
+            .status(status)
+            .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+            .body(
+                Full::new(Bytes::from(response))
+                    .map_err(|never| match never {})
+                    .boxed(),
+            )
+            .unwrap()
+    }
+
     async fn send_queue_event<T: Serialize>(
         &self,
         request: QueueRequest,
@@ -750,6 +1006,107 @@ impl SMTP {
     }
 }
 
+#[cfg(feature = "local_delivery")]
+impl SMTP {
+    async fn send_suppression_event<T, U: Serialize>(
+        &self,
+        event: utils::ipc::SuppressionEvent,
+        rx: oneshot::Receiver<T>,
+        map: impl FnOnce(T) -> U,
+    ) -> (StatusCode, String) {
+        match self
+            .delivery_tx
+            .send(utils::ipc::DeliveryEvent::Suppression(event))
+            .await
+        {
+            Ok(_) => match rx.await {
+                Ok(result) => {
+                    return (
+                        StatusCode::OK,
+                        serde_json::to_string(&Response { data: map(result) }).unwrap_or_default(),
+                    )
+                }
+                Err(_) => {
+                    tracing::debug!(
+                        context = "suppression",
+                        event = "recv-error",
+                        reason = "Failed to receive manage request response."
+                    );
+                }
+            },
+            Err(_) => {
+                tracing::debug!(
+                    context = "suppression",
+                    event = "send-error",
+                    reason = "Failed to send manage request event."
+                );
+            }
+        }
+
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "{\"error\": \"internal-error\", \"details\": \"Resource unavailable, try again later.\"}"
+                .to_string(),
+        )
+    }
+}
+
+impl From<utils::ipc::SuppressedAddress> for SuppressedAddress {
+    fn from(value: utils::ipc::SuppressedAddress) -> Self {
+        SuppressedAddress {
+            domain: value.domain,
+            address: value.address,
+            reason: value.reason,
+            created: DateTime::from_timestamp(value.created as i64),
+        }
+    }
+}
+
+impl From<queue::quarantine::QuarantinedMessage> for QuarantinedMessage {
+    fn from(value: queue::quarantine::QuarantinedMessage) -> Self {
+        QuarantinedMessage {
+            id: value.id,
+            return_path: value.return_path,
+            recipients: value
+                .recipients
+                .into_iter()
+                .map(|rcpt| rcpt.address)
+                .collect(),
+            created: DateTime::from_timestamp(value.created as i64),
+            size: value.size,
+            reason: value.reason,
+        }
+    }
+}
+
+impl From<(queue::quarantine::QuarantinedMessage, Vec<u8>)> for QuarantinePreview {
+    fn from((value, contents): (queue::quarantine::QuarantinedMessage, Vec<u8>)) -> Self {
+        let (headers, preview) = if let Some(message) = mail_parser::Message::parse(&contents) {
+            let root = message.root_part();
+            (
+                String::from_utf8_lossy(
+                    contents
+                        .get(root.offset_header..root.offset_body)
+                        .unwrap_or_default(),
+                )
+                .into_owned(),
+                message
+                    .body_text(0)
+                    .map(|text| text.chars().take(1024).collect())
+                    .unwrap_or_default(),
+            )
+        } else {
+            (String::new(), String::new())
+        };
+
+        QuarantinePreview {
+            message: value.into(),
+            headers,
+            preview,
+        }
+    }
+}
+
 impl From<&queue::Message> for Message {
     fn from(message: &queue::Message) -> Self {
         let now = Instant::now();
@@ -924,6 +1281,65 @@ impl ParseValues for Cow<'_, str> {
     }
 }
 
+fn parse_quarantine_ids(uri: &Uri) -> Result<Vec<QueueId>, String> {
+    let mut ids = Vec::new();
+    if let Some(query) = uri.query() {
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "id" | "ids" => {
+                    ids = value.parse_queue_ids()?;
+                }
+                _ => {
+                    return Err(format!("Invalid parameter {key:?}."));
+                }
+            }
+        }
+    }
+    Ok(ids)
+}
+
+struct ListRequest {
+    list: Arc<MailingList>,
+    address: Option<String>,
+    token: Option<String>,
+}
+
+fn parse_list_request(lists: &ListsConfig, uri: &Uri) -> Result<ListRequest, String> {
+    let mut list = None;
+    let mut address = None;
+    let mut token = None;
+    if let Some(query) = uri.query() {
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "list" => {
+                    list = lists
+                        .lists
+                        .get(&*value)
+                        .ok_or_else(|| format!("Mailing list {value:?} does not exist."))?
+                        .clone()
+                        .into();
+                }
+                "address" => {
+                    if !value.is_empty() {
+                        address = value.to_lowercase().into();
+                    }
+                }
+                "token" => {
+                    token = value.into_owned().into();
+                }
+                _ => {
+                    return Err(format!("Invalid parameter {key:?}."));
+                }
+            }
+        }
+    }
+    Ok(ListRequest {
+        list: list.ok_or_else(|| "Missing list parameter.".to_string())?,
+        address,
+        token,
+    })
+}
+
 trait BadRequest {
     fn into_bad_request(self) -> (StatusCode, String);
 }
@@ -971,14 +1387,14 @@ where
     }
 }
 
-fn serialize_datetime<S>(value: &DateTime, serializer: S) -> Result<S::Ok, S::Error>
+pub(crate) fn serialize_datetime<S>(value: &DateTime, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
     serializer.serialize_str(&value.to_rfc3339())
 }
 
-fn deserialize_datetime<'de, D>(deserializer: D) -> Result<DateTime, D::Error>
+pub(crate) fn deserialize_datetime<'de, D>(deserializer: D) -> Result<DateTime, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -25,7 +25,7 @@ use std::{
     borrow::Cow,
     hash::Hash,
     net::IpAddr,
-    sync::{atomic::AtomicU32, Arc},
+    sync::{atomic::AtomicU32, Arc, OnceLock},
     time::{Duration, Instant},
 };
 
@@ -37,6 +37,7 @@ use sieve::{Runtime, Sieve};
 use smtp_proto::request::receiver::{
     BdatReceiver, DataReceiver, DummyDataReceiver, DummyLineReceiver, LineReceiver, RequestReceiver,
 };
+use store::Store;
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     sync::mpsc,
@@ -50,13 +51,15 @@ use utils::{
 
 use crate::{
     config::{
-        DkimSigner, EnvelopeKey, MailAuthConfig, QueueConfig, ReportConfig, SessionConfig,
-        VerifyStrategy,
+        DkimSigner, EnvelopeKey, MailAuthConfig, QueueConfig, ReportConfig, Rewrite, SessionConfig,
+        SuppressionAction, VerifyStrategy,
     },
     inbound::auth::SaslToken,
+    lists::ListsCore,
     outbound::{
         dane::{DnssecResolver, Tlsa},
         mta_sts,
+        pool::SessionPool,
     },
     queue::{self, DomainPart, QueueId, QuotaLimiter},
     reporting,
@@ -67,6 +70,7 @@ use self::throttle::{Limiter, ThrottleKey, ThrottleKeyHasherBuilder};
 pub mod if_block;
 pub mod management;
 pub mod params;
+pub mod rewrite;
 pub mod scripts;
 pub mod throttle;
 pub mod worker;
@@ -101,6 +105,8 @@ pub struct SMTP {
     pub mail_auth: MailAuthConfig,
     pub report: ReportCore,
     pub sieve: SieveCore,
+    pub lists: ListsCore,
+    pub store: OnceLock<Arc<Store>>,
     #[cfg(feature = "local_delivery")]
     pub delivery_tx: mpsc::Sender<DeliveryEvent>,
 }
@@ -143,11 +149,13 @@ pub struct QueueCore {
     pub tx: mpsc::Sender<queue::Event>,
     pub id_seq: AtomicU32,
     pub connectors: TlsConnectors,
+    pub pool: SessionPool,
 }
 
 pub struct ReportCore {
     pub config: ReportConfig,
     pub tx: mpsc::Sender<reporting::Event>,
+    pub sent_log: reporting::bounce::SentLog,
 }
 
 pub struct TlsConnectors {
@@ -231,12 +239,14 @@ pub struct SessionParameters {
 
     // Rcpt parameters
     pub rcpt_script: Option<Arc<Sieve>>,
+    pub rcpt_rewrite: Option<Arc<Rewrite>>,
     pub rcpt_relay: bool,
     pub rcpt_errors_max: usize,
     pub rcpt_errors_wait: Duration,
     pub rcpt_max: usize,
     pub rcpt_dsn: bool,
     pub rcpt_directory: Option<Arc<dyn Directory>>,
+    pub rcpt_suppression: SuppressionAction,
     pub can_expn: bool,
     pub can_vrfy: bool,
     pub max_message_size: usize,
@@ -410,6 +420,10 @@ impl crate::inbound::IsTls for NullIo {
     fn tls_version_and_cipher(&self) -> (&'static str, &'static str) {
         ("", "")
     }
+
+    fn tls_identity(&self) -> Option<String> {
+        None
+    }
 }
 
 #[cfg(feature = "local_delivery")]
@@ -464,12 +478,14 @@ impl Session<NullIo> {
                 auth_errors_max: Default::default(),
                 auth_errors_wait: Default::default(),
                 rcpt_script: Default::default(),
+                rcpt_rewrite: Default::default(),
                 rcpt_relay: Default::default(),
                 rcpt_errors_max: Default::default(),
                 rcpt_errors_wait: Default::default(),
                 rcpt_max: Default::default(),
                 rcpt_dsn: Default::default(),
                 rcpt_directory: Default::default(),
+                rcpt_suppression: Default::default(),
                 max_message_size: Default::default(),
                 iprev: crate::config::VerifyStrategy::Disable,
                 spf_ehlo: crate::config::VerifyStrategy::Disable,
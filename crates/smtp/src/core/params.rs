@@ -83,11 +83,13 @@ impl<T: AsyncRead + AsyncWrite> Session<T> {
     pub async fn eval_rcpt_params(&mut self) {
         let rc = &self.core.session.config.rcpt;
         self.params.rcpt_script = rc.script.eval(self).await.clone();
+        self.params.rcpt_rewrite = rc.rewrite.eval(self).await.clone();
         self.params.rcpt_relay = *rc.relay.eval(self).await;
         self.params.rcpt_errors_max = *rc.errors_max.eval(self).await;
         self.params.rcpt_errors_wait = *rc.errors_wait.eval(self).await;
         self.params.rcpt_max = *rc.max_recipients.eval(self).await;
         self.params.rcpt_directory = rc.directory.eval(self).await.clone();
+        self.params.rcpt_suppression = *rc.suppression.eval(self).await;
         self.params.rcpt_dsn = *self.core.session.config.extensions.dsn.eval(self).await;
 
         self.params.max_message_size = *self
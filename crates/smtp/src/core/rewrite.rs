@@ -0,0 +1,122 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use mail_parser::{HeaderName, HeaderValue, Message, RfcHeader};
+
+use crate::config::Rewrite;
+
+impl Rewrite {
+    pub async fn rewrite_address(&self, address: &str) -> Option<String> {
+        let (local_part, domain) = address.rsplit_once('@').unwrap_or((address, ""));
+
+        for rule in &self.rules {
+            let captures = if let Some(regex) = &rule.regex {
+                if let Some(captures) = regex.captures(address) {
+                    Some(captures)
+                } else {
+                    continue;
+                }
+            } else {
+                None
+            };
+
+            if let Some(lookup) = &rule.lookup {
+                if !lookup.contains(address).await.unwrap_or(false)
+                    && (domain.is_empty() || !lookup.contains(domain).await.unwrap_or(false))
+                {
+                    continue;
+                }
+            }
+
+            let result = if let Some(captures) = captures {
+                let mut result = String::with_capacity(rule.replace.len() + address.len());
+                captures.expand(&rule.replace, &mut result);
+                result
+            } else if rule.replace.contains('@') {
+                rule.replace.clone()
+            } else {
+                format!("{local_part}@{}", rule.replace)
+            }
+            .to_lowercase();
+
+            return if result != address && !result.is_empty() {
+                Some(result)
+            } else {
+                None
+            };
+        }
+
+        None
+    }
+
+    pub async fn rewrite_headers(&self, raw_message: &[u8]) -> Option<Vec<u8>> {
+        let message = Message::parse(raw_message)?;
+        let mut changes = Vec::new();
+
+        for header in message.parts.first()?.headers.iter() {
+            if !matches!(
+                header.name,
+                HeaderName::Rfc(RfcHeader::From | RfcHeader::Sender | RfcHeader::ReplyTo)
+            ) {
+                continue;
+            }
+            let addresses = match &header.value {
+                HeaderValue::Address(addr) => std::slice::from_ref(addr),
+                HeaderValue::AddressList(addrs) => addrs.as_slice(),
+                _ => continue,
+            };
+
+            let mut value = None;
+            for address in addresses.iter().filter_map(|addr| addr.address.as_deref()) {
+                if let Some(rewritten) = self.rewrite_address(&address.to_lowercase()).await {
+                    let value = value.get_or_insert_with(|| {
+                        String::from_utf8_lossy(
+                            raw_message
+                                .get(header.offset_start..header.offset_end)
+                                .unwrap_or_default(),
+                        )
+                        .into_owned()
+                    });
+                    *value = value.replace(address, &rewritten);
+                }
+            }
+            if let Some(value) = value {
+                changes.push((header.offset_start..header.offset_end, value));
+            }
+        }
+
+        if !changes.is_empty() {
+            let mut result = Vec::with_capacity(raw_message.len() + 64);
+            let mut last_offset = 0;
+            for (range, value) in changes {
+                result.extend_from_slice(&raw_message[last_offset..range.start]);
+                result.extend_from_slice(value.as_bytes());
+                last_offset = range.end;
+            }
+            result.extend_from_slice(&raw_message[last_offset..]);
+            Some(result)
+        } else {
+            None
+        }
+    }
+}
@@ -43,10 +43,15 @@ use crate::queue::{DomainPart, InstantFromTimestamp, Message};
 
 use super::{Session, SMTP};
 
+/// Reserved `execute :binary` command used by scripts to quarantine
+/// the message, i.e. `execute :binary "vnd.stalwart.quarantine" ["<reason>"]`.
+pub const QUARANTINE_COMMAND: &str = "vnd.stalwart.quarantine";
+
 pub enum ScriptResult {
     Accept,
     Replace(Vec<u8>),
     Reject(String),
+    Quarantine { reason: String },
     Discard,
 }
 
@@ -135,6 +140,7 @@ impl SMTP {
         let mut messages: Vec<Vec<u8>> = Vec::new();
 
         let mut reject_reason = None;
+        let mut quarantine = None;
         let mut keep_id = usize::MAX;
 
         // Start event loop
@@ -185,6 +191,18 @@ impl SMTP {
                             }
                         }
                     }
+                    Event::Execute {
+                        command_type: CommandType::Binary,
+                        command,
+                        arguments,
+                    } if command.eq_ignore_ascii_case(QUARANTINE_COMMAND) => {
+                        quarantine = arguments
+                            .into_iter()
+                            .next()
+                            .unwrap_or_else(|| "Quarantined by script".to_string())
+                            .into();
+                        input = true.into();
+                    }
                     Event::Execute {
                         command_type,
                         command,
@@ -442,7 +460,9 @@ impl SMTP {
         // MAX = implicit keep
         // MAX - 1 = discard message
 
-        if keep_id == 0 {
+        if let Some(reason) = quarantine {
+            ScriptResult::Quarantine { reason }
+        } else if keep_id == 0 {
             ScriptResult::Accept
         } else if let Some(mut reject_reason) = reject_reason {
             if !reject_reason.ends_with('\n') {
@@ -23,11 +23,15 @@
 
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
-use smtp_proto::{IntoString, AUTH_LOGIN, AUTH_OAUTHBEARER, AUTH_PLAIN, AUTH_XOAUTH2};
+use smtp_proto::{
+    IntoString, AUTH_EXTERNAL, AUTH_LOGIN, AUTH_OAUTHBEARER, AUTH_PLAIN, AUTH_XOAUTH2,
+};
 use tokio::io::{AsyncRead, AsyncWrite};
 
 use crate::core::Session;
 
+use super::IsTls;
+
 pub struct SaslToken {
     mechanism: u64,
     credentials: Credentials<String>,
@@ -36,7 +40,7 @@ pub struct SaslToken {
 impl SaslToken {
     pub fn from_mechanism(mechanism: u64) -> Option<SaslToken> {
         match mechanism {
-            AUTH_PLAIN | AUTH_LOGIN => SaslToken {
+            AUTH_PLAIN | AUTH_LOGIN | AUTH_EXTERNAL => SaslToken {
                 mechanism,
                 credentials: Credentials::Plain {
                     username: String::new(),
@@ -64,15 +68,17 @@ impl SaslToken {
     }
 }
 
-impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
+impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
     pub async fn handle_sasl_response(
         &mut self,
         token: &mut SaslToken,
         response: &[u8],
     ) -> Result<bool, ()> {
-        if response.is_empty() {
+        if token.mechanism == AUTH_EXTERNAL && response == b"=" {
+            return self.authenticate_external(String::new()).await;
+        } else if response.is_empty() {
             match (token.mechanism, &token.credentials) {
-                (AUTH_PLAIN | AUTH_XOAUTH2 | AUTH_OAUTHBEARER, _) => {
+                (AUTH_PLAIN | AUTH_XOAUTH2 | AUTH_OAUTHBEARER | AUTH_EXTERNAL, _) => {
                     self.write(b"334 Go ahead.\r\n").await?;
                     return Ok(true);
                 }
@@ -86,6 +92,11 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
             }
         } else if let Some(response) = base64_decode(response) {
             match (token.mechanism, &mut token.credentials) {
+                (AUTH_EXTERNAL, _) => {
+                    if let Ok(authzid) = String::from_utf8(response) {
+                        return self.authenticate_external(authzid).await;
+                    }
+                }
                 (AUTH_PLAIN, Credentials::Plain { username, secret }) => {
                     let mut b_username = Vec::new();
                     let mut b_secret = Vec::new();
@@ -214,6 +225,50 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
         Ok(false)
     }
 
+    pub async fn authenticate_external(&mut self, authzid: String) -> Result<bool, ()> {
+        let identity = if let Some(identity) = self.stream.tls_identity() {
+            identity
+        } else {
+            return self
+                .auth_error(b"535 5.7.8 No client certificate was presented.\r\n")
+                .await;
+        };
+
+        if let Some(lookup) = &self.params.auth_directory {
+            if let Ok(principal) = lookup.authenticate_external(&identity, &authzid).await {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "auth",
+                    event = "authenticate",
+                    mechanism = "external",
+                    identity = identity,
+                    result = if principal.is_some() {"success"} else {"failed"}
+                );
+                return if let Some(principal) = principal {
+                    self.data.authenticated_as = principal.name;
+                    self.eval_post_auth_params().await;
+                    self.write(b"235 2.7.0 Authentication succeeded.\r\n")
+                        .await?;
+                    Ok(false)
+                } else {
+                    self.auth_error(b"535 5.7.8 Authentication credentials invalid.\r\n")
+                        .await
+                };
+            }
+        } else {
+            tracing::warn!(
+                parent: &self.span,
+                context = "auth",
+                event = "error",
+                "No lookup list configured for authentication."
+            );
+        }
+        self.write(b"454 4.7.0 Temporary authentication failure\r\n")
+            .await?;
+
+        Ok(false)
+    }
+
     pub async fn auth_error(&mut self, response: &[u8]) -> Result<bool, ()> {
         tokio::time::sleep(self.params.auth_errors_wait).await;
         self.data.auth_errors += 1;
@@ -31,7 +31,7 @@ use std::{
 
 use mail_auth::{
     common::headers::HeaderWriter, dmarc, AuthenticatedMessage, AuthenticationResults, DkimResult,
-    DmarcResult, ReceivedSpf,
+    DmarcResult, ReceivedSpf, SpfResult,
 };
 use mail_builder::headers::{date::Date, message_id::generate_message_id_header};
 use smtp_proto::{
@@ -46,13 +46,30 @@ use crate::{
     config::DNSBL_FROM,
     core::{scripts::ScriptResult, Session, SessionAddress, State},
     queue::{self, DomainPart, Message, SimpleEnvelope},
-    reporting::analysis::AnalyzeReport,
+    reporting::{
+        analysis::AnalyzeReport,
+        bounce::{parse_message_id, AnalyzeBounce},
+    },
 };
 
-use super::IsTls;
+use super::{milter::Modification, IsTls};
 
 impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
     pub async fn queue_message(&mut self) -> Cow<'static, [u8]> {
+        let response = self.process_message().await;
+
+        // Notify webhooks of rejected messages
+        if matches!(response.first(), Some(b'4' | b'5')) {
+            self.send_rejection_webhooks(
+                self.data.rcpt_to.iter().map(|rcpt| rcpt.address.as_str()),
+                response.as_ref(),
+            );
+        }
+
+        response
+    }
+
+    async fn process_message(&mut self) -> Cow<'static, [u8]> {
         // Authenticate message
         let raw_message = Arc::new(std::mem::take(&mut self.data.message));
         let auth_message = if let Some(auth_message) = AuthenticatedMessage::parse(&raw_message) {
@@ -204,6 +221,7 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
         }
 
         // Verify DMARC
+        let mut dmarc_pass = false;
         match &self.data.spf_mail_from {
             Some(spf_output) if dmarc.verify() => {
                 let dmarc_output = self
@@ -232,6 +250,8 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
 
                 // Add to DMARC output to the Authentication-Results header
                 auth_results = auth_results.with_dmarc_result(&dmarc_output);
+                dmarc_pass = matches!(dmarc_output.spf_result(), DmarcResult::Pass)
+                    || matches!(dmarc_output.dkim_result(), DmarcResult::Pass);
 
                 if !rejected {
                     tracing::debug!(parent: &self.span,
@@ -285,6 +305,7 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
         }
 
         // Run Milter filters
+        let mut quarantine_reason = None;
         let mut edited_message = match self.run_milters(&auth_message).await {
             Ok(modifications) => {
                 tracing::debug!(
@@ -301,6 +322,10 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                     }),
                     "Milter filter(s) accepted message.");
 
+                quarantine_reason = modifications.iter().find_map(|m| match m {
+                    Modification::Quarantine { reason } => Some(reason.clone()),
+                    _ => None,
+                });
                 self.data
                     .apply_modifications(modifications, &auth_message)
                     .map(Arc::new)
@@ -416,12 +441,88 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
 
                     return message.into_bytes().into();
                 }
+                ScriptResult::Quarantine { reason } => {
+                    quarantine_reason = reason.into();
+                }
                 ScriptResult::Discard => {
                     return (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into();
                 }
             }
         }
 
+        // Header address rewriting
+        if let Some(rewrite) = dc.rewrite.eval(self).await {
+            if let Some(new_message) = rewrite
+                .rewrite_headers(edited_message.as_ref().unwrap_or(&raw_message))
+                .await
+            {
+                tracing::debug!(parent: &self.span,
+                    context = "data",
+                    event = "rewrite",
+                    "Rewrote header addresses.");
+
+                edited_message = Arc::new(new_message).into();
+            }
+        }
+
+        // Mailing lists, list addresses are always the only recipient
+        if let Some((list, action)) = self
+            .data
+            .rcpt_to
+            .first()
+            .and_then(|rcpt| self.core.lists.config.classify(&rcpt.address_lcase))
+        {
+            self.data.rcpt_to.clear();
+            let sender = &self.data.mail_from.as_ref().unwrap().address_lcase;
+
+            // Senders are verified by authentication or by an aligned From header
+            let verified = !self.data.authenticated_as.is_empty()
+                || (auth_message.from().eq_ignore_ascii_case(sender)
+                    && (dmarc_pass
+                        || self
+                            .data
+                            .spf_mail_from
+                            .as_ref()
+                            .map_or(false, |spf| spf.result() == SpfResult::Pass)));
+            let list_message = edited_message.as_ref().unwrap_or(&raw_message);
+            let result = self
+                .core
+                .handle_list_message(&list, action, sender, verified, list_message, &self.span)
+                .await;
+            return match result {
+                Ok(_) => {
+                    self.data.messages_sent += 1;
+                    (b"250 2.0.0 Message accepted.\r\n"[..]).into()
+                }
+                Err(response) => {
+                    tracing::debug!(parent: &self.span,
+                        context = "list",
+                        event = "error",
+                        list = list.id,
+                        reason = response.trim_end());
+                    response.as_bytes().into()
+                }
+            };
+        }
+
+        // Bounces addressed to local recipients are analyzed once the message is accepted
+        let mut bounce_rcpts = Vec::new();
+        if self.data.mail_from.as_ref().unwrap().address.is_empty()
+            && *dc.suppress_bounces.eval(self).await
+        {
+            for rcpt in &self.data.rcpt_to {
+                if match &self.params.rcpt_directory {
+                    Some(directory) => directory
+                        .is_local_domain(&rcpt.domain)
+                        .await
+                        .unwrap_or(false),
+                    None => true,
+                } {
+                    bounce_rcpts.push(rcpt.address_lcase.clone());
+                }
+            }
+        }
+
         // Build message
         let mail_from = self.data.mail_from.clone().unwrap();
         let rcpt_to = std::mem::take(&mut self.data.rcpt_to);
@@ -477,9 +578,14 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
             headers.extend_from_slice(Date::now().to_rfc822().as_bytes());
             headers.extend_from_slice(b"\r\n");
         }
+        let mut message_id = None;
         if !auth_message.has_message_id_header() && *dc.add_message_id.eval(self).await {
             headers.extend_from_slice(b"Message-ID: ");
+            let offset = headers.len();
             let _ = generate_message_id_header(&mut headers, &self.instance.hostname);
+            message_id = std::str::from_utf8(&headers[offset..])
+                .ok()
+                .map(|id| id.trim().trim_matches(['<', '>']).to_string());
             headers.extend_from_slice(b"\r\n");
         }
 
@@ -507,8 +613,28 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
             }
         }
 
-        // Update size
+        // Update size and keep the Message-ID for correlating bounces
         message.size = raw_message.len() + headers.len();
+        if !self.core.report.sent_log.expire.is_zero() {
+            message.message_id = message_id.or_else(|| parse_message_id(&raw_message));
+        }
+
+        // Quarantine message
+        if let Some(reason) = quarantine_reason {
+            let queue_id = message.id;
+            return if self
+                .core
+                .queue
+                .quarantine_message(message, &headers, &raw_message, reason, &self.span)
+                .await
+            {
+                self.state = State::Accepted(queue_id);
+                self.data.messages_sent += 1;
+                (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into()
+            } else {
+                (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into()
+            };
+        }
 
         // Verify queue quota
         if self.core.queue.has_quota(&mut message).await {
@@ -519,6 +645,9 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                 .queue_message(message, Some(&headers), &raw_message, &self.span)
                 .await
             {
+                if !bounce_rcpts.is_empty() {
+                    self.core.analyze_bounce(raw_message, bounce_rcpts);
+                }
                 self.state = State::Accepted(queue_id);
                 self.data.messages_sent += 1;
                 (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into()
@@ -558,6 +687,7 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
             priority: self.data.priority,
             size: 0,
             env_id: mail_from.dsn_info,
+            message_id: None,
             queue_refs: Vec::with_capacity(0),
         });
 
@@ -169,6 +169,9 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                 if !self.stream.is_tls() {
                     response.auth_mechanisms &= !(AUTH_PLAIN | AUTH_LOGIN);
                 }
+                if self.stream.tls_identity().is_none() {
+                    response.auth_mechanisms &= !AUTH_EXTERNAL;
+                }
                 if response.auth_mechanisms != 0 {
                     response.capabilities |= EXT_AUTH;
                 }
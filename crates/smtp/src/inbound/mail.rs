@@ -297,6 +297,27 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
                 }
             }
 
+            // Address rewriting
+            if let Some(rewrite) = self.core.session.config.mail.rewrite.eval(self).await {
+                let mail_from = self.data.mail_from.as_ref().unwrap();
+                if !mail_from.address_lcase.is_empty() {
+                    if let Some(new_address) =
+                        rewrite.rewrite_address(&mail_from.address_lcase).await
+                    {
+                        tracing::debug!(parent: &self.span,
+                            context = "mail-from",
+                            event = "rewrite",
+                            address = &mail_from.address,
+                            new_address = &new_address);
+
+                        let mail_from = self.data.mail_from.as_mut().unwrap();
+                        mail_from.domain = new_address.domain_part().to_string();
+                        mail_from.address_lcase = new_address.clone();
+                        mail_from.address = new_address;
+                    }
+                }
+            }
+
             tracing::debug!(parent: &self.span,
                 context = "mail-from",
                 event = "success",
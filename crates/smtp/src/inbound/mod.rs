@@ -26,6 +26,7 @@ use mail_auth::{
 };
 use tokio::net::TcpStream;
 use tokio_rustls::server::TlsStream;
+use utils::listener::tls_client_identity;
 
 use crate::config::{ArcSealer, DkimSigner};
 
@@ -43,6 +44,7 @@ pub trait IsTls {
     fn is_tls(&self) -> bool;
     fn write_tls_header(&self, headers: &mut Vec<u8>);
     fn tls_version_and_cipher(&self) -> (&'static str, &'static str);
+    fn tls_identity(&self) -> Option<String>;
 }
 
 impl IsTls for TcpStream {
@@ -55,6 +57,10 @@ impl IsTls for TcpStream {
     fn tls_version_and_cipher(&self) -> (&'static str, &'static str) {
         ("", "")
     }
+
+    fn tls_identity(&self) -> Option<String> {
+        None
+    }
 }
 
 impl IsTls for TlsStream<TcpStream> {
@@ -101,6 +107,10 @@ impl IsTls for TlsStream<TcpStream> {
         headers.extend_from_slice(cipher.as_bytes());
         headers.extend_from_slice(b")\r\n\t");
     }
+
+    fn tls_identity(&self) -> Option<String> {
+        tls_client_identity(self)
+    }
 }
 
 impl ArcSealer {
@@ -27,6 +27,7 @@ use smtp_proto::{
 use tokio::io::{AsyncRead, AsyncWrite};
 
 use crate::{
+    config::SuppressionAction,
     core::{scripts::ScriptResult, Session, SessionAddress},
     queue::DomainPart,
 };
@@ -62,7 +63,7 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
 
         // Build RCPT
         let address_lcase = to.address.to_lowercase();
-        let rcpt = SessionAddress {
+        let mut rcpt = SessionAddress {
             domain: address_lcase.domain_part().to_string(),
             address_lcase,
             address: to.address,
@@ -70,8 +71,53 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
             dsn_info: to.orcpt,
         };
 
-        // Verify address
-        if let Some(directory) = &self.params.rcpt_directory {
+        // Address rewriting
+        if let Some(rewrite) = &self.params.rcpt_rewrite {
+            if let Some(new_address) = rewrite.rewrite_address(&rcpt.address_lcase).await {
+                tracing::debug!(parent: &self.span,
+                    context = "rcpt",
+                    event = "rewrite",
+                    address = &rcpt.address,
+                    new_address = &new_address);
+
+                rcpt.domain = new_address.domain_part().to_string();
+                rcpt.address_lcase = new_address.clone();
+                rcpt.address = new_address;
+            }
+        }
+
+        // Verify address, mailing list addresses are handled locally
+        let is_list = self
+            .core
+            .lists
+            .config
+            .classify(&rcpt.address_lcase)
+            .is_some();
+
+        // Mailing list addresses are accepted in their own transaction,
+        // so that list processing errors are reported on DATA
+        if !self.data.rcpt_to.is_empty()
+            && !self.data.rcpt_to.contains(&rcpt)
+            && (is_list
+                || self.data.rcpt_to.iter().any(|rcpt| {
+                    self.core
+                        .lists
+                        .config
+                        .classify(&rcpt.address_lcase)
+                        .is_some()
+                }))
+        {
+            tracing::debug!(parent: &self.span,
+                context = "rcpt",
+                event = "error",
+                address = &rcpt.address_lcase,
+                "Mailing list recipients must be sent in a separate transaction.");
+            return self
+                .write(b"451 4.5.3 Mailing list recipients must be sent in a separate transaction.\r\n")
+                .await;
+        }
+
+        if let Some(directory) = self.params.rcpt_directory.as_ref().filter(|_| !is_list) {
             if let Ok(is_local_domain) = directory.is_local_domain(&rcpt.domain).await {
                 if is_local_domain {
                     if let Ok(is_local_address) = directory.rcpt(&rcpt.address_lcase).await {
@@ -81,6 +127,10 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
                                             event = "error",
                                             address = &rcpt.address_lcase,
                                             "Mailbox does not exist.");
+                            self.send_rejection_webhooks(
+                                [rcpt.address.as_str()].into_iter(),
+                                b"550 5.1.2 Mailbox does not exist.\r\n",
+                            );
                             return self
                                 .rcpt_error(b"550 5.1.2 Mailbox does not exist.\r\n")
                                 .await;
@@ -101,6 +151,10 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
                         event = "error",
                         address = &rcpt.address_lcase,
                         "Relay not allowed.");
+                    self.send_rejection_webhooks(
+                        [rcpt.address.as_str()].into_iter(),
+                        b"550 5.1.2 Relay not allowed.\r\n",
+                    );
                     return self.rcpt_error(b"550 5.1.2 Relay not allowed.\r\n").await;
                 }
             } else {
@@ -114,15 +168,45 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
                     .write(b"451 4.4.3 Unable to verify address at this time.\r\n")
                     .await;
             }
-        } else if !self.params.rcpt_relay {
+        } else if !self.params.rcpt_relay && !is_list {
             tracing::debug!(parent: &self.span,
                 context = "rcpt", 
                 event = "error",
                 address = &rcpt.address_lcase,
                 "Relay not allowed.");
+            self.send_rejection_webhooks(
+                [rcpt.address.as_str()].into_iter(),
+                b"550 5.1.2 Relay not allowed.\r\n",
+            );
             return self.rcpt_error(b"550 5.1.2 Relay not allowed.\r\n").await;
         }
 
+        // Check suppression list
+        if self.params.rcpt_suppression != SuppressionAction::Disable
+            && self.is_suppressed(&rcpt).await
+        {
+            if self.params.rcpt_suppression == SuppressionAction::Reject {
+                tracing::debug!(parent: &self.span,
+                    context = "rcpt",
+                    event = "error",
+                    address = &rcpt.address_lcase,
+                    "Recipient is on the suppression list.");
+                self.send_rejection_webhooks(
+                    [rcpt.address.as_str()].into_iter(),
+                    b"550 5.1.1 Recipient address is on the suppression list.\r\n",
+                );
+                return self
+                    .rcpt_error(b"550 5.1.1 Recipient address is on the suppression list.\r\n")
+                    .await;
+            } else {
+                tracing::info!(parent: &self.span,
+                    context = "rcpt",
+                    event = "suppressed",
+                    address = &rcpt.address_lcase,
+                    "Recipient is on the suppression list.");
+            }
+        }
+
         if !self.data.rcpt_to.contains(&rcpt) {
             self.data.rcpt_to.push(rcpt);
 
@@ -134,7 +218,11 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
                             event = "sieve-reject",
                             address = &self.data.rcpt_to.last().unwrap().address,
                             reason = message);
-                    self.data.rcpt_to.pop();
+                    let rcpt = self.data.rcpt_to.pop().unwrap();
+                    self.send_rejection_webhooks(
+                        [rcpt.address.as_str()].into_iter(),
+                        message.as_bytes(),
+                    );
                     return self.write(message.as_bytes()).await;
                 }
             }
@@ -155,6 +243,22 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
         self.write(b"250 2.1.5 OK\r\n").await
     }
 
+    async fn is_suppressed(&self, rcpt: &SessionAddress) -> bool {
+        match self
+            .data
+            .mail_from
+            .as_ref()
+            .filter(|mail_from| !mail_from.domain.is_empty())
+        {
+            Some(mail_from) => {
+                self.core
+                    .is_suppressed(&mail_from.domain, &rcpt.address_lcase)
+                    .await
+            }
+            None => false,
+        }
+    }
+
     async fn rcpt_error(&mut self, response: &[u8]) -> Result<(), ()> {
         tokio::time::sleep(self.params.rcpt_errors_wait).await;
         self.data.rcpt_errors += 1;
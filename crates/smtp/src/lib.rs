@@ -24,17 +24,20 @@
 use crate::core::{
     throttle::ThrottleKeyHasherBuilder, QueueCore, ReportCore, SessionCore, TlsConnectors, SMTP,
 };
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use config::{
-    auth::ConfigAuth, queue::ConfigQueue, remote::ConfigHost, report::ConfigReport,
-    resolver::ConfigResolver, scripts::ConfigSieve, session::ConfigSession, ConfigContext, Host,
+    auth::ConfigAuth, lists::ConfigLists, queue::ConfigQueue, remote::ConfigHost,
+    report::ConfigReport, resolver::ConfigResolver, rewrite::ConfigRewrite, scripts::ConfigSieve,
+    session::ConfigSession, ConfigContext, Host,
 };
 use dashmap::DashMap;
 use directory::DirectoryConfig;
+use lists::ListsCore;
+use mail_auth::common::lru::LruCache;
 use mail_send::smtp::tls::build_tls_connector;
 use queue::manager::SpawnQueue;
-use reporting::scheduler::SpawnReport;
+use reporting::{bounce::SentLog, scheduler::SpawnReport};
 use tokio::sync::mpsc;
 use utils::{
     config::{Config, ServerProtocol, Servers},
@@ -44,6 +47,7 @@ use utils::{
 pub mod config;
 pub mod core;
 pub mod inbound;
+pub mod lists;
 pub mod outbound;
 pub mod queue;
 pub mod reporting;
@@ -82,11 +86,13 @@ impl SMTP {
 
         config.parse_remote_hosts(&mut config_ctx)?;
         config.parse_signatures(&mut config_ctx)?;
+        config.parse_rewrites(&mut config_ctx)?;
         let sieve_config = config.parse_sieve(&mut config_ctx)?;
         let session_config = config.parse_session_config(&config_ctx)?;
         let queue_config = config.parse_queue(&config_ctx)?;
         let mail_auth_config = config.parse_mail_auth(&config_ctx)?;
         let report_config = config.parse_reports(&config_ctx)?;
+        let lists_config = config.parse_lists(&config_ctx)?;
 
         // Build core
         let (queue_tx, queue_rx) = mpsc::channel(1024);
@@ -137,13 +143,29 @@ impl SMTP {
                     pki_verify: build_tls_connector(false),
                     dummy_verify: build_tls_connector(true),
                 },
+                pool: Default::default(),
             },
             report: ReportCore {
                 tx: report_tx,
                 config: report_config,
+                sent_log: SentLog {
+                    entries: LruCache::with_capacity(
+                        config
+                            .property("session.data.bounces.sent-log.size")?
+                            .unwrap_or(10000),
+                    ),
+                    expire: config
+                        .property("session.data.bounces.sent-log.expire")?
+                        .unwrap_or_else(|| Duration::from_secs(7 * 86400)),
+                },
             },
             mail_auth: mail_auth_config,
             sieve: sieve_config,
+            lists: ListsCore {
+                config: lists_config,
+                locks: Default::default(),
+            },
+            store: Default::default(),
             #[cfg(feature = "local_delivery")]
             delivery_tx,
         });
@@ -154,6 +176,9 @@ impl SMTP {
         // Spawn report manager
         report_rx.spawn(core.clone(), core.report.read_reports().await);
 
+        // Spawn quarantine housekeeper
+        core.spawn_quarantine_housekeeper();
+
         Ok(core)
     }
 }
@@ -0,0 +1,378 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+pub mod process;
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use dashmap::DashMap;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use store::write::now;
+use tokio::{
+    fs,
+    sync::{Mutex, OwnedMutexGuard},
+};
+
+use crate::{
+    config::{ListsConfig, MailingList},
+    reporting::webhook::sign,
+};
+
+#[derive(Default)]
+pub struct ListsCore {
+    pub config: ListsConfig,
+    pub locks: DashMap<String, Arc<Mutex<()>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListAction {
+    Post,
+    Subscribe,
+    Unsubscribe,
+    Confirm(String),
+    Approve(String),
+    Reject(String),
+    Bounce(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ListMember {
+    pub address: String,
+    pub created: u64,
+    #[serde(default)]
+    pub bounces: u32,
+}
+
+impl ListsConfig {
+    pub fn classify(&self, address: &str) -> Option<(Arc<MailingList>, ListAction)> {
+        let (local_part, domain) = address.rsplit_once('@')?;
+        for list in self.lists.values() {
+            if list.domain != domain {
+                continue;
+            }
+            if list.local_part == local_part {
+                return Some((list.clone(), ListAction::Post));
+            }
+            let command = if let Some(command) = local_part
+                .strip_prefix(list.local_part.as_str())
+                .and_then(|command| command.strip_prefix('-'))
+            {
+                command
+            } else {
+                continue;
+            };
+            let (command, argument) = command.split_once('+').unwrap_or((command, ""));
+            let action = match (command, argument) {
+                ("subscribe", "") => ListAction::Subscribe,
+                ("unsubscribe", "") => ListAction::Unsubscribe,
+                ("confirm", token) if !token.is_empty() => ListAction::Confirm(token.to_string()),
+                ("approve", token) if !token.is_empty() => ListAction::Approve(token.to_string()),
+                ("reject", token) if !token.is_empty() => ListAction::Reject(token.to_string()),
+                ("bounces", verp) => ListAction::Bounce(verp.to_string()),
+                _ => continue,
+            };
+            return Some((list.clone(), action));
+        }
+
+        None
+    }
+
+    pub fn token(&self, list: &MailingList, action: &str, address: &str) -> String {
+        let mut token = sign(&self.secret, &format!("{action}:{}:{address}", list.id));
+        token.truncate(32);
+        token
+    }
+
+    pub fn verify_token(
+        &self,
+        list: &MailingList,
+        action: &str,
+        address: &str,
+        token: &str,
+    ) -> bool {
+        tokens_match(&self.token(list, action, address), token)
+    }
+
+    pub fn verp_address(&self, list: &MailingList, member: &str) -> String {
+        format!(
+            "{}-bounces+{}+{}@{}",
+            list.local_part,
+            member.replace('@', "="),
+            self.verp_token(list, member),
+            list.domain
+        )
+    }
+
+    pub fn verp_member(&self, list: &MailingList, verp: &str) -> Option<String> {
+        let (member, token) = verp.rsplit_once('+')?;
+        let (local_part, domain) = member.rsplit_once('=')?;
+        let member = format!("{local_part}@{domain}");
+        if tokens_match(&self.verp_token(list, &member), token) {
+            Some(member)
+        } else {
+            None
+        }
+    }
+
+    fn verp_token(&self, list: &MailingList, member: &str) -> String {
+        // Shorter token to keep VERP local parts within limits
+        let mut token = self.token(list, "bounce", &member.to_lowercase());
+        token.truncate(16);
+        token
+    }
+
+    pub fn unsubscribe_url(&self, list: &MailingList, address: &str) -> Option<String> {
+        self.unsubscribe_url.as_ref().map(|url| {
+            format!(
+                "{}?list={}&address={}&token={}",
+                url,
+                form_urlencoded::byte_serialize(list.id.as_bytes()).collect::<String>(),
+                form_urlencoded::byte_serialize(address.as_bytes()).collect::<String>(),
+                self.token(list, "unsubscribe", address)
+            )
+        })
+    }
+}
+
+impl MailingList {
+    pub fn command_address(&self, command: &str) -> String {
+        format!("{}-{}@{}", self.local_part, command, self.domain)
+    }
+
+    pub fn list_id(&self) -> String {
+        format!("{}.{}", self.local_part, self.domain)
+    }
+
+    pub fn is_moderator(&self, address: &str) -> bool {
+        self.moderators.iter().any(|moderator| moderator == address)
+    }
+}
+
+impl ListsCore {
+    pub async fn members(&self, list: &MailingList) -> Option<Vec<ListMember>> {
+        let path = self.members_path(list);
+        match read_members(&path).await {
+            Ok(members) => Some(members),
+            Err(err) => {
+                tracing::error!(
+                    context = "list",
+                    event = "error",
+                    list = list.id,
+                    "Failed to read member list {}: {}",
+                    path.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    pub async fn is_member(&self, list: &MailingList, address: &str) -> Option<bool> {
+        self.members(list)
+            .await
+            .map(|members| members.iter().any(|member| member.address == address))
+    }
+
+    pub async fn subscribe(&self, list: &MailingList, address: &str) -> bool {
+        let _lock = self.lock(list).await;
+        let path = self.members_path(list);
+        let mut members = if let Some(members) = self.members(list).await {
+            members
+        } else {
+            return false;
+        };
+        if !members.iter().any(|member| member.address == address) {
+            members.push(ListMember {
+                address: address.to_string(),
+                created: now(),
+                bounces: 0,
+            });
+            if write_members(&path, &members).await {
+                tracing::info!(
+                    context = "list",
+                    event = "subscribe",
+                    list = list.id,
+                    address = address,
+                    "Address subscribed to mailing list."
+                );
+                return true;
+            }
+        }
+        false
+    }
+
+    pub async fn unsubscribe(&self, list: &MailingList, address: &str) -> bool {
+        let _lock = self.lock(list).await;
+        let path = self.members_path(list);
+        let mut members = if let Some(members) = self.members(list).await {
+            members
+        } else {
+            return false;
+        };
+        let num_members = members.len();
+        members.retain(|member| member.address != address);
+        if members.len() != num_members && write_members(&path, &members).await {
+            tracing::info!(
+                context = "list",
+                event = "unsubscribe",
+                list = list.id,
+                address = address,
+                "Address unsubscribed from mailing list."
+            );
+            true
+        } else {
+            false
+        }
+    }
+
+    pub async fn record_bounce(&self, list: &MailingList, address: &str) {
+        let _lock = self.lock(list).await;
+        let path = self.members_path(list);
+        let mut members = if let Some(members) = self.members(list).await {
+            members
+        } else {
+            return;
+        };
+        let bounce_limit = self.config.bounce_limit;
+        let mut found = false;
+        let mut removed = false;
+        members.retain_mut(|member| {
+            if member.address == address {
+                member.bounces += 1;
+                found = true;
+                removed = member.bounces >= bounce_limit;
+                !removed
+            } else {
+                true
+            }
+        });
+        if found && write_members(&path, &members).await {
+            tracing::info!(
+                context = "list",
+                event = if removed {
+                    "bounce-unsubscribe"
+                } else {
+                    "bounce"
+                },
+                list = list.id,
+                address = address,
+                "Bounce received for mailing list member."
+            );
+        }
+    }
+
+    pub async fn hold_post(&self, list: &MailingList, raw_message: &[u8]) -> Option<String> {
+        let token = rand::thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(32)
+            .map(|ch| char::from(ch.to_ascii_lowercase()))
+            .collect::<String>();
+        let path = self.config.path.join(&list.id);
+        let _ = fs::create_dir_all(&path).await;
+        match fs::write(path.join(format!("{token}.eml")), raw_message).await {
+            Ok(_) => Some(token),
+            Err(err) => {
+                tracing::error!(
+                    context = "list",
+                    event = "error",
+                    list = list.id,
+                    "Failed to write held post: {}",
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    pub async fn read_post(&self, list: &MailingList, token: &str) -> Option<Vec<u8>> {
+        fs::read(self.post_path(list, token)?).await.ok()
+    }
+
+    pub async fn take_post(&self, list: &MailingList, token: &str) -> Option<Vec<u8>> {
+        let path = self.post_path(list, token)?;
+        let contents = fs::read(&path).await.ok()?;
+        let _ = fs::remove_file(&path).await;
+        Some(contents)
+    }
+
+    fn post_path(&self, list: &MailingList, token: &str) -> Option<PathBuf> {
+        if token.chars().all(|ch| ch.is_ascii_alphanumeric()) {
+            Some(self.config.path.join(&list.id).join(format!("{token}.eml")))
+        } else {
+            None
+        }
+    }
+
+    fn members_path(&self, list: &MailingList) -> PathBuf {
+        self.config.path.join(format!("{}.json", list.id))
+    }
+
+    // Member list updates are serialized per list
+    async fn lock(&self, list: &MailingList) -> OwnedMutexGuard<()> {
+        let lock = self.locks.entry(list.id.clone()).or_default().clone();
+        lock.lock_owned().await
+    }
+}
+
+async fn read_members(path: &Path) -> std::io::Result<Vec<ListMember>> {
+    match fs::read(path).await {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+fn tokens_match(a: &str, b: &str) -> bool {
+    // Constant time comparison
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+async fn write_members(path: &Path, members: &[ListMember]) -> bool {
+    // Write to a temporary file first so readers never see a partial list
+    let tmp_path = path.with_extension("json.tmp");
+    let result = match fs::write(&tmp_path, serde_json::to_vec(members).unwrap_or_default()).await {
+        Ok(_) => fs::rename(&tmp_path, path).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = result {
+        tracing::error!(
+            context = "list",
+            event = "error",
+            "Failed to write member list {}: {}",
+            path.display(),
+            err
+        );
+        false
+    } else {
+        true
+    }
+}
@@ -0,0 +1,476 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use mail_auth::common::headers::HeaderWriter;
+use mail_builder::{
+    headers::{content_type::ContentType, HeaderType},
+    mime::{make_boundary, BodyPart, MimePart},
+    MessageBuilder,
+};
+use mail_parser::Message as ParsedMessage;
+
+use crate::{
+    config::{ListPosting, ListReplyTo, ListSubscription, MailingList},
+    core::SMTP,
+    queue::Message,
+};
+
+use super::ListAction;
+
+const REPLACED_HEADERS: [&str; 10] = [
+    "List-Id",
+    "List-Post",
+    "List-Help",
+    "List-Owner",
+    "List-Archive",
+    "List-Subscribe",
+    "List-Unsubscribe",
+    "List-Unsubscribe-Post",
+    "Precedence",
+    "Return-Path",
+];
+
+impl SMTP {
+    /// Processes a message addressed to a list. Moderator rights and members-only
+    /// posting require a `verified` sender, that is, an authenticated session or a
+    /// `From` header matching the envelope sender that passed DMARC or SPF.
+    pub async fn handle_list_message(
+        &self,
+        list: &MailingList,
+        action: ListAction,
+        sender: &str,
+        verified: bool,
+        raw_message: &[u8],
+        span: &tracing::Span,
+    ) -> Result<(), &'static str> {
+        let lists = &self.lists;
+
+        if sender.is_empty() && !matches!(action, ListAction::Bounce(_)) {
+            return Err("550 5.7.1 Null sender not allowed.\r\n");
+        }
+
+        match action {
+            ListAction::Post => {
+                let message = ParsedMessage::parse(raw_message)
+                    .ok_or("550 5.7.7 Failed to parse message.\r\n")?;
+                if has_list_id(&message, &list.list_id()) {
+                    tracing::debug!(parent: span,
+                        context = "list",
+                        event = "loop",
+                        list = list.id,
+                        "Mail loop detected.");
+                    return Err("554 5.4.6 Mail loop detected.\r\n");
+                }
+
+                let is_moderator = verified && list.is_moderator(sender);
+                let needs_approval = match list.posting {
+                    ListPosting::Open => false,
+                    ListPosting::Members if is_moderator => false,
+                    ListPosting::Members => {
+                        if !lists
+                            .is_member(list, sender)
+                            .await
+                            .ok_or("451 4.3.5 Unable to accept message at this time.\r\n")?
+                        {
+                            tracing::debug!(parent: span,
+                                context = "list",
+                                event = "reject",
+                                list = list.id,
+                                sender = sender,
+                                "Sender is not a list member.");
+                            return Err("550 5.7.1 Only list members may post to this list.\r\n");
+                        }
+
+                        // Posts from unverified members are held for moderation
+                        !verified
+                    }
+                    ListPosting::Moderated => !is_moderator,
+                };
+
+                if needs_approval {
+                    let token = lists
+                        .hold_post(list, raw_message)
+                        .await
+                        .ok_or("451 4.3.5 Unable to accept message at this time.\r\n")?;
+                    tracing::info!(parent: span,
+                        context = "list",
+                        event = "held",
+                        list = list.id,
+                        sender = sender,
+                        token = token,
+                        "Post held for moderation.");
+                    self.send_moderation_request(list, &token, sender, raw_message, span)
+                        .await;
+                    return Ok(());
+                }
+
+                self.distribute_list_post(list, raw_message, span)
+                    .await
+                    .ok_or("451 4.3.5 Unable to accept message at this time.\r\n")?;
+            }
+            ListAction::Subscribe => {
+                if list.subscription == ListSubscription::Closed {
+                    return Err("550 5.7.1 Subscription to this list is closed.\r\n");
+                }
+                let token = lists.config.token(list, "subscribe", sender);
+                self.send_list_notice(
+                    list,
+                    &list.command_address(&format!("confirm+{token}")),
+                    sender,
+                    format!("Confirm your subscription to {}", list.name),
+                    format!(
+                        concat!(
+                            "A request was received to subscribe {} to the {} mailing list.\r\n\r\n",
+                            "To confirm, reply to this message or send an empty message to {}.\r\n",
+                            "If you did not request this subscription, please ignore this message.\r\n"
+                        ),
+                        sender,
+                        list.name,
+                        list.command_address(&format!("confirm+{token}"))
+                    ),
+                    None,
+                    span,
+                )
+                .await;
+            }
+            ListAction::Unsubscribe => {
+                if lists
+                    .is_member(list, sender)
+                    .await
+                    .ok_or("451 4.3.5 Unable to accept message at this time.\r\n")?
+                {
+                    let token = lists.config.token(list, "unsubscribe", sender);
+                    self.send_list_notice(
+                        list,
+                        &list.command_address(&format!("confirm+{token}")),
+                        sender,
+                        format!("Confirm your unsubscription from {}", list.name),
+                        format!(
+                            concat!(
+                                "A request was received to unsubscribe {} from the {} mailing list.\r\n\r\n",
+                                "To confirm, reply to this message or send an empty message to {}.\r\n"
+                            ),
+                            sender,
+                            list.name,
+                            list.command_address(&format!("confirm+{token}"))
+                        ),
+                        None,
+                        span,
+                    )
+                    .await;
+                }
+            }
+            ListAction::Confirm(token) => {
+                if lists.config.verify_token(list, "subscribe", sender, &token)
+                    && list.subscription != ListSubscription::Closed
+                {
+                    lists.subscribe(list, sender).await;
+                } else if lists
+                    .config
+                    .verify_token(list, "unsubscribe", sender, &token)
+                {
+                    lists.unsubscribe(list, sender).await;
+                } else {
+                    return Err("550 5.7.1 Invalid confirmation token.\r\n");
+                }
+            }
+            ListAction::Approve(token) | ListAction::Reject(token)
+                if !verified || !list.is_moderator(sender) =>
+            {
+                tracing::debug!(parent: span,
+                    context = "list",
+                    event = "reject",
+                    list = list.id,
+                    sender = sender,
+                    token = token,
+                    "Sender is not a list moderator.");
+                return Err("550 5.7.1 Only list moderators may approve or reject posts.\r\n");
+            }
+            ListAction::Approve(token) => {
+                // Held posts are only removed once distributed
+                let raw_message = lists
+                    .read_post(list, &token)
+                    .await
+                    .ok_or("550 5.1.1 Held post not found.\r\n")?;
+                self.distribute_list_post(list, &raw_message, span)
+                    .await
+                    .ok_or("451 4.3.5 Unable to accept message at this time.\r\n")?;
+                lists.take_post(list, &token).await;
+                tracing::info!(parent: span,
+                    context = "list",
+                    event = "approved",
+                    list = list.id,
+                    moderator = sender,
+                    token = token,
+                    "Held post approved.");
+            }
+            ListAction::Reject(token) => {
+                lists
+                    .take_post(list, &token)
+                    .await
+                    .ok_or("550 5.1.1 Held post not found.\r\n")?;
+                tracing::info!(parent: span,
+                    context = "list",
+                    event = "rejected",
+                    list = list.id,
+                    moderator = sender,
+                    token = token,
+                    "Held post rejected.");
+            }
+            ListAction::Bounce(verp) => {
+                if !sender.is_empty() {
+                    return Err("550 5.7.1 Only delivery status notifications are accepted.\r\n");
+                }
+
+                // Only VERP addresses signed by this server are trusted
+                if let Some(member) = lists.config.verp_member(list, &verp) {
+                    lists.record_bounce(list, &member).await;
+                } else if !verp.is_empty() {
+                    tracing::debug!(parent: span,
+                        context = "list",
+                        event = "invalid-verp",
+                        list = list.id,
+                        verp = verp,
+                        "Ignoring bounce with an invalid VERP token.");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn distribute_list_post(
+        &self,
+        list: &MailingList,
+        raw_message: &[u8],
+        span: &tracing::Span,
+    ) -> Option<usize> {
+        // Remove headers that are replaced by the list
+        let mut body = Vec::with_capacity(raw_message.len());
+        let mut last_offset = 0;
+        if let Some(part) = ParsedMessage::parse(raw_message)
+            .as_ref()
+            .and_then(|message| message.parts.first())
+        {
+            for header in &part.headers {
+                let name = header.name.as_str();
+                if REPLACED_HEADERS
+                    .iter()
+                    .any(|replaced| replaced.eq_ignore_ascii_case(name))
+                    || (list.reply_to == ListReplyTo::List && name.eq_ignore_ascii_case("Reply-To"))
+                {
+                    body.extend_from_slice(
+                        raw_message
+                            .get(last_offset..header.offset_field)
+                            .unwrap_or_default(),
+                    );
+                    last_offset = header.offset_end;
+                }
+            }
+        }
+        body.extend_from_slice(raw_message.get(last_offset..).unwrap_or_default());
+
+        // Send one copy per member using a VERP return path
+        let members = self.lists.members(list).await?;
+        let mut num_sent = 0;
+        for member in &members {
+            let mut headers = Vec::with_capacity(512);
+            headers.extend_from_slice(
+                format!(
+                    "List-Id: \"{}\" <{}>\r\n",
+                    list.name.replace(['"', '\\', '\r', '\n'], ""),
+                    list.list_id()
+                )
+                .as_bytes(),
+            );
+            headers
+                .extend_from_slice(format!("List-Post: <mailto:{}>\r\n", list.address).as_bytes());
+            headers.extend_from_slice(
+                format!(
+                    "List-Subscribe: <mailto:{}>\r\n",
+                    list.command_address("subscribe")
+                )
+                .as_bytes(),
+            );
+            headers.extend_from_slice(b"List-Unsubscribe: <mailto:");
+            headers.extend_from_slice(list.command_address("unsubscribe").as_bytes());
+            headers.push(b'>');
+            if let Some(url) = self.lists.config.unsubscribe_url(list, &member.address) {
+                headers.extend_from_slice(b",\r\n\t<");
+                headers.extend_from_slice(url.as_bytes());
+                headers
+                    .extend_from_slice(b">\r\nList-Unsubscribe-Post: List-Unsubscribe=One-Click");
+            }
+            headers.extend_from_slice(b"\r\nPrecedence: list\r\n");
+            if list.reply_to == ListReplyTo::List {
+                headers.extend_from_slice(format!("Reply-To: <{}>\r\n", list.address).as_bytes());
+            }
+            for signer in &list.sign {
+                match signer.sign_chained(&[headers.as_ref(), &body]) {
+                    Ok(signature) => {
+                        signature.write_header(&mut headers);
+                    }
+                    Err(err) => {
+                        tracing::info!(parent: span,
+                            context = "dkim",
+                            event = "sign-failed",
+                            list = list.id,
+                            "Failed to sign message: {}", err);
+                    }
+                }
+            }
+
+            let return_path = lists.config.verp_address(list, &member.address);
+            let mut message =
+                Message::new_boxed(return_path.clone(), return_path, list.domain.clone());
+            message
+                .add_recipient(member.address.clone(), &self.queue.config)
+                .await;
+            if self
+                .queue
+                .queue_message(message, Some(&headers), &body, span)
+                .await
+            {
+                num_sent += 1;
+            }
+        }
+
+        tracing::info!(parent: span,
+            context = "list",
+            event = "distribute",
+            list = list.id,
+            members = members.len(),
+            queued = num_sent,
+            "Post distributed to list members.");
+
+        Some(num_sent)
+    }
+
+    async fn send_moderation_request(
+        &self,
+        list: &MailingList,
+        token: &str,
+        sender: &str,
+        raw_message: &[u8],
+        span: &tracing::Span,
+    ) {
+        let approve_address = list.command_address(&format!("approve+{token}"));
+        let body = format!(
+            concat!(
+                "A post from {} to the {} mailing list requires approval.\r\n\r\n",
+                "To approve it, reply to this message or send an empty message to {}.\r\n",
+                "To reject it, send an empty message to {}.\r\n"
+            ),
+            sender,
+            list.name,
+            approve_address,
+            list.command_address(&format!("reject+{token}"))
+        );
+        for moderator in &list.moderators {
+            self.send_list_notice(
+                list,
+                &approve_address,
+                moderator,
+                format!("Post to {} requires approval", list.name),
+                body.clone(),
+                Some(raw_message),
+                span,
+            )
+            .await;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn send_list_notice(
+        &self,
+        list: &MailingList,
+        from: &str,
+        to: &str,
+        subject: String,
+        body: String,
+        attachment: Option<&[u8]>,
+        span: &tracing::Span,
+    ) {
+        let text_part = MimePart::new(ContentType::new("text/plain"), BodyPart::Text(body.into()));
+        let raw_message = MessageBuilder::new()
+            .from((list.name.as_str(), from))
+            .to(to)
+            .header("Auto-Submitted", HeaderType::Text("auto-replied".into()))
+            .message_id(format!("<{}@{}>", make_boundary("."), list.domain))
+            .subject(subject)
+            .body(if let Some(attachment) = attachment {
+                MimePart::new(
+                    ContentType::new("multipart/mixed"),
+                    BodyPart::Multipart(vec![
+                        text_part,
+                        MimePart::new(
+                            ContentType::new("message/rfc822"),
+                            BodyPart::Text(String::from_utf8_lossy(attachment).into_owned().into()),
+                        ),
+                    ]),
+                )
+            } else {
+                text_part
+            })
+            .write_to_vec()
+            .unwrap_or_default();
+
+        let mut headers = Vec::new();
+        for signer in &list.sign {
+            match signer.sign(&raw_message) {
+                Ok(signature) => {
+                    signature.write_header(&mut headers);
+                }
+                Err(err) => {
+                    tracing::info!(parent: span,
+                        context = "dkim",
+                        event = "sign-failed",
+                        list = list.id,
+                        "Failed to sign message: {}", err);
+                }
+            }
+        }
+
+        let return_path = list.command_address("bounces");
+        let mut message = Message::new_boxed(return_path.clone(), return_path, list.domain.clone());
+        message.add_recipient(to, &self.queue.config).await;
+        self.queue
+            .queue_message(message, Some(&headers), &raw_message, span)
+            .await;
+    }
+}
+
+fn has_list_id(message: &ParsedMessage, list_id: &str) -> bool {
+    message.parts.first().map_or(false, |part| {
+        part.headers.iter().any(|header| {
+            header.name.as_str().eq_ignore_ascii_case("List-Id")
+                && message
+                    .raw_message
+                    .get(header.offset_start..header.offset_end)
+                    .and_then(|value| std::str::from_utf8(value).ok())
+                    .and_then(|value| value.rsplit_once('<'))
+                    .and_then(|(_, id)| id.split_once('>'))
+                    .map_or(false, |(id, _)| id.trim().eq_ignore_ascii_case(list_id))
+        })
+    })
+}
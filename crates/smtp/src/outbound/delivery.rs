@@ -36,7 +36,7 @@ use smtp_proto::MAIL_REQUIRETLS;
 use utils::config::ServerProtocol;
 
 use crate::{
-    config::{AggregateFrequency, TlsStrategy},
+    config::{AggregateFrequency, TlsStrategy, WebhookEventType},
     core::SMTP,
     queue::ErrorDetails,
     reporting::{tls::TlsRptOptions, PolicyType, TlsEvent},
@@ -45,6 +45,7 @@ use crate::{
 use super::{
     lookup::ToNextHop,
     mta_sts,
+    pool::{PoolKey, PoolParams, PoolTlsPolicy},
     session::{read_greeting, say_helo, try_start_tls, SessionParams, StartTlsResult},
     NextHop,
 };
@@ -56,7 +57,7 @@ use crate::queue::{
 impl DeliveryAttempt {
     pub async fn try_deliver(mut self, core: Arc<SMTP>, queue: &mut Queue) {
         // Check that the message still has recipients to be delivered
-        let has_pending_delivery = self.has_pending_delivery();
+        let has_pending_delivery = self.has_pending_delivery(&core);
 
         // Send any due Delivery Status Notifications
         core.queue.send_dsn(&mut self).await;
@@ -121,6 +122,17 @@ impl DeliveryAttempt {
 
             let mut domains = std::mem::take(&mut self.message.domains);
             let mut recipients = std::mem::take(&mut self.message.recipients);
+            let mut attempted_domains = vec![false; domains.len()];
+            let mut remote_domains = vec![false; domains.len()];
+            let pending_rcpts = recipients
+                .iter()
+                .map(|rcpt| {
+                    matches!(
+                        &rcpt.status,
+                        Status::Scheduled | Status::TemporaryFailure(_)
+                    )
+                })
+                .collect::<Vec<_>>();
             'next_domain: for (domain_idx, domain) in domains.iter_mut().enumerate() {
                 // Only process domains due for delivery
                 if !matches!(&domain.status, Status::Scheduled | Status::TemporaryFailure(_)
@@ -128,6 +140,7 @@ impl DeliveryAttempt {
                 {
                     continue;
                 }
+                attempted_domains[domain_idx] = true;
 
                 // Create new span for domain
                 let span = tracing::info_span!(
@@ -185,6 +198,7 @@ impl DeliveryAttempt {
                     ),
                     None => (Vec::with_capacity(0), true),
                 };
+                remote_domains[domain_idx] = true;
 
                 // Prepare TLS strategy
                 let mut tls_strategy = TlsStrategy {
@@ -571,6 +585,131 @@ impl DeliveryAttempt {
                             }
                         }
 
+                        // Obtain session parameters
+                        let max_pooled = *queue_config.max_pooled.eval(&envelope).await;
+                        let pool_key = PoolKey::new(
+                            remote_host,
+                            remote_ip,
+                            source_ip,
+                            PoolTlsPolicy {
+                                require_tls: tls_strategy.is_tls_required()
+                                    || (self.message.flags & MAIL_REQUIRETLS) != 0,
+                                dane: dane_policy.is_some(),
+                                mta_sts: mta_sts_policy.is_some(),
+                            },
+                        );
+                        let params = SessionParams {
+                            span: &span,
+                            credentials: remote_host.credentials(),
+                            is_smtp: remote_host.is_smtp(),
+                            hostname: envelope.mx,
+                            local_hostname: queue_config.hostname.eval(&envelope).await,
+                            timeout_ehlo: *queue_config.timeout.ehlo.eval(&envelope).await,
+                            timeout_mail: *queue_config.timeout.mail.eval(&envelope).await,
+                            timeout_rcpt: *queue_config.timeout.rcpt.eval(&envelope).await,
+                            timeout_data: *queue_config.timeout.data.eval(&envelope).await,
+                            pool: if max_pooled > 0 {
+                                PoolParams {
+                                    pool: &core.queue.pool,
+                                    key: pool_key.clone(),
+                                    max_sessions: max_pooled,
+                                    idle_timeout: *queue_config.timeout.idle.eval(&envelope).await,
+                                }
+                                .into()
+                            } else {
+                                None
+                            },
+                        };
+
+                        // Reuse an idle session to the same host, if available
+                        if max_pooled > 0 {
+                            if let Some(session) = core.queue.pool.checkout(&pool_key, &span).await
+                            {
+                                // Verify DANE
+                                if let Some(dane_policy) = &dane_policy {
+                                    if let Err(status) = dane_policy.verify(
+                                        &span,
+                                        envelope.mx,
+                                        session.peer_certificates(),
+                                    ) {
+                                        // Report DANE verification failure
+                                        if let Some(tls_report) = &tls_report {
+                                            core.schedule_report(TlsEvent {
+                                                policy: dane_policy.into(),
+                                                domain: envelope.domain.to_string(),
+                                                failure: FailureDetails::new(
+                                                    ResultType::ValidationFailure,
+                                                )
+                                                .with_receiving_mx_hostname(envelope.mx)
+                                                .with_receiving_ip(remote_ip)
+                                                .with_failure_reason_code(
+                                                    "No matching certificates found.",
+                                                )
+                                                .into(),
+                                                tls_record: tls_report.record.clone(),
+                                                interval: tls_report.interval,
+                                            })
+                                            .await;
+                                        }
+
+                                        tokio::spawn(session.quit());
+                                        last_status = status;
+                                        continue 'next_host;
+                                    }
+                                }
+
+                                // Report TLS result
+                                if let Some(tls_report) = &tls_report {
+                                    core.schedule_report(TlsEvent {
+                                        policy: (&mta_sts_policy, &dane_policy).into(),
+                                        domain: envelope.domain.to_string(),
+                                        failure: if !session.is_tls() {
+                                            FailureDetails::new(ResultType::StartTlsNotSupported)
+                                                .with_receiving_mx_hostname(envelope.mx)
+                                                .with_receiving_ip(remote_ip)
+                                                .with_failure_reason_code(
+                                                    "STARTTLS was not advertised by host",
+                                                )
+                                                .into()
+                                        } else {
+                                            None
+                                        },
+                                        tls_record: tls_report.record.clone(),
+                                        interval: tls_report.interval,
+                                    })
+                                    .await;
+                                }
+
+                                if let Some(delivery_result) = self
+                                    .message
+                                    .deliver_pooled(
+                                        session,
+                                        recipients
+                                            .iter_mut()
+                                            .filter(|r| r.domain_idx == domain_idx),
+                                        params.clone(),
+                                    )
+                                    .await
+                                {
+                                    // Update status for the current domain and continue with the next one
+                                    domain.set_status(
+                                        delivery_result,
+                                        queue_config.retry.eval(&envelope).await,
+                                    );
+                                    continue 'next_domain;
+                                }
+
+                                // The transaction was not started, retry over a new connection
+                                tracing::debug!(
+                                    parent: &span,
+                                    context = "pool",
+                                    event = "retry",
+                                    mx = envelope.mx,
+                                    "Pooled session could not start a transaction, retrying with a new connection."
+                                );
+                            }
+                        }
+
                         // Connect
                         let mut smtp_client = match if let Some(ip_addr) = source_ip {
                             SmtpClient::connect_using(
@@ -612,19 +751,6 @@ impl DeliveryAttempt {
                             }
                         };
 
-                        // Obtail session parameters
-                        let params = SessionParams {
-                            span: &span,
-                            credentials: remote_host.credentials(),
-                            is_smtp: remote_host.is_smtp(),
-                            hostname: envelope.mx,
-                            local_hostname: queue_config.hostname.eval(&envelope).await,
-                            timeout_ehlo: *queue_config.timeout.ehlo.eval(&envelope).await,
-                            timeout_mail: *queue_config.timeout.mail.eval(&envelope).await,
-                            timeout_rcpt: *queue_config.timeout.rcpt.eval(&envelope).await,
-                            timeout_data: *queue_config.timeout.data.eval(&envelope).await,
-                        };
-
                         // Prepare TLS connector
                         let tls_connector = if !remote_host.allow_invalid_certs() {
                             &core.queue.connectors.pki_verify
@@ -881,6 +1007,18 @@ impl DeliveryAttempt {
             self.message.domains = domains;
             self.message.recipients = recipients;
 
+            // Keep track of remote deliveries, bounces are only accepted for these
+            core.track_sent_message(&self.message, |idx, rcpt| {
+                pending_rcpts[idx]
+                    && remote_domains[rcpt.domain_idx]
+                    && matches!(&rcpt.status, Status::Completed(_))
+            });
+
+            // Notify webhooks of recipients attempted during this run
+            core.send_delivery_webhooks(&self.message, None, |idx, rcpt| {
+                pending_rcpts[idx] && attempted_domains[rcpt.domain_idx]
+            });
+
             // Send Delivery Status Notifications
             core.queue.send_dsn(&mut self).await;
 
@@ -948,9 +1086,10 @@ impl DeliveryAttempt {
     }
 
     /// Marks as failed all domains that reached their expiration time
-    pub fn has_pending_delivery(&mut self) -> bool {
+    pub fn has_pending_delivery(&mut self, core: &SMTP) -> bool {
         let now = Instant::now();
         let mut has_pending_delivery = false;
+        let mut expired_domains = Vec::new();
         let span = self.span.clone();
 
         for (idx, domain) in self.message.domains.iter_mut().enumerate() {
@@ -973,6 +1112,7 @@ impl DeliveryAttempt {
                     domain.status =
                         std::mem::replace(&mut domain.status, Status::Scheduled).into_permanent();
                     domain.changed = true;
+                    expired_domains.push(idx);
                 }
                 Status::Scheduled if domain.expires <= now => {
                     tracing::info!(
@@ -993,6 +1133,7 @@ impl DeliveryAttempt {
                         "Queue rate limit exceeded.".to_string(),
                     ));
                     domain.changed = true;
+                    expired_domains.push(idx);
                 }
                 Status::Completed(_) | Status::PermanentFailure(_) => (),
                 _ => {
@@ -1001,6 +1142,15 @@ impl DeliveryAttempt {
             }
         }
 
+        // Notify webhooks of expired recipients
+        if !expired_domains.is_empty() {
+            core.send_delivery_webhooks(
+                &self.message,
+                WebhookEventType::Expired.into(),
+                |_, rcpt| expired_domains.contains(&rcpt.domain_idx),
+            );
+        }
+
         has_pending_delivery
     }
 }
@@ -38,6 +38,7 @@ pub mod delivery;
 pub mod local;
 pub mod lookup;
 pub mod mta_sts;
+pub mod pool;
 pub mod session;
 
 impl Status<(), Error> {
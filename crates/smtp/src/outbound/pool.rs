@@ -0,0 +1,243 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use mail_send::{smtp::AssertReply, Credentials, SmtpClient};
+use rustls::Certificate;
+use smtp_proto::EhloResponse;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+
+use super::{session::quit, NextHop};
+
+#[derive(Default)]
+pub struct SessionPool {
+    sessions: DashMap<PoolKey, Vec<PooledSession>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    pub hostname: String,
+    pub remote_ip: IpAddr,
+    pub port: u16,
+    pub source_ip: Option<IpAddr>,
+    pub credentials: Option<Credentials<String>>,
+    pub is_smtp: bool,
+    pub tls: PoolTlsPolicy,
+}
+
+/// TLS policy that was enforced when the pooled session was established,
+/// sessions are only reused for messages that require the same policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PoolTlsPolicy {
+    pub require_tls: bool,
+    pub dane: bool,
+    pub mta_sts: bool,
+}
+
+pub struct PooledSession {
+    pub smtp_client: PooledClient,
+    pub capabilities: EhloResponse<String>,
+    pub expires: Instant,
+}
+
+pub enum PooledClient {
+    Plain(SmtpClient<TcpStream>),
+    Tls(SmtpClient<TlsStream<TcpStream>>),
+}
+
+#[derive(Clone)]
+pub struct PoolParams<'x> {
+    pub pool: &'x SessionPool,
+    pub key: PoolKey,
+    pub max_sessions: usize,
+    pub idle_timeout: Duration,
+}
+
+pub trait PoolStream: Sized {
+    fn into_pooled(smtp_client: SmtpClient<Self>) -> PooledClient;
+}
+
+impl PoolKey {
+    pub fn new(
+        remote_host: &NextHop<'_>,
+        remote_ip: IpAddr,
+        source_ip: Option<IpAddr>,
+        tls: PoolTlsPolicy,
+    ) -> Self {
+        PoolKey {
+            hostname: remote_host.hostname().to_lowercase(),
+            remote_ip,
+            port: remote_host.port(),
+            source_ip,
+            credentials: remote_host.credentials().cloned(),
+            is_smtp: remote_host.is_smtp(),
+            tls,
+        }
+    }
+}
+
+impl SessionPool {
+    pub async fn checkout(&self, key: &PoolKey, span: &tracing::Span) -> Option<PooledSession> {
+        loop {
+            let mut session = self.sessions.get_mut(key)?.pop()?;
+
+            if session.expires <= Instant::now() {
+                tokio::spawn(session.smtp_client.quit());
+                continue;
+            }
+
+            // Make sure the session is still alive
+            if session.smtp_client.reset().await {
+                tracing::debug!(
+                    parent: span,
+                    context = "pool",
+                    event = "reuse",
+                    mx = key.hostname,
+                    remote_ip = %key.remote_ip,
+                );
+                return Some(session);
+            }
+
+            tracing::debug!(
+                parent: span,
+                context = "pool",
+                event = "stale",
+                mx = key.hostname,
+                remote_ip = %key.remote_ip,
+            );
+        }
+    }
+
+    pub fn checkin(&self, params: PoolParams<'_>, session: PooledSession) {
+        let mut sessions = self.sessions.entry(params.key).or_default();
+        if sessions.len() < params.max_sessions {
+            sessions.push(session);
+        } else {
+            tokio::spawn(session.smtp_client.quit());
+        }
+    }
+
+    pub fn purge(&self) {
+        let now = Instant::now();
+        self.sessions.retain(|_, sessions| {
+            let mut idx = 0;
+            while idx < sessions.len() {
+                if sessions[idx].expires <= now {
+                    tokio::spawn(sessions.swap_remove(idx).smtp_client.quit());
+                } else {
+                    idx += 1;
+                }
+            }
+            !sessions.is_empty()
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.iter().map(|sessions| sessions.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl PoolParams<'_> {
+    pub fn checkin<T: PoolStream>(
+        self,
+        smtp_client: SmtpClient<T>,
+        capabilities: EhloResponse<String>,
+    ) {
+        let expires = Instant::now() + self.idle_timeout;
+        let pool = self.pool;
+        pool.checkin(
+            self,
+            PooledSession {
+                smtp_client: T::into_pooled(smtp_client),
+                capabilities,
+                expires,
+            },
+        );
+    }
+}
+
+impl PooledSession {
+    pub fn peer_certificates(&self) -> Option<&[Certificate]> {
+        match &self.smtp_client {
+            PooledClient::Plain(_) => None,
+            PooledClient::Tls(smtp_client) => smtp_client.tls_connection().peer_certificates(),
+        }
+    }
+
+    pub fn is_tls(&self) -> bool {
+        matches!(self.smtp_client, PooledClient::Tls(_))
+    }
+
+    pub async fn quit(self) {
+        self.smtp_client.quit().await
+    }
+}
+
+impl PooledClient {
+    async fn reset(&mut self) -> bool {
+        match self {
+            PooledClient::Plain(smtp_client) => reset(smtp_client).await,
+            PooledClient::Tls(smtp_client) => reset(smtp_client).await,
+        }
+    }
+
+    async fn quit(self) {
+        match self {
+            PooledClient::Plain(smtp_client) => quit(smtp_client).await,
+            PooledClient::Tls(smtp_client) => quit(smtp_client).await,
+        }
+    }
+}
+
+async fn reset<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin>(
+    smtp_client: &mut SmtpClient<T>,
+) -> bool {
+    smtp_client.timeout = Duration::from_secs(30);
+    smtp_client
+        .cmd(b"RSET\r\n")
+        .await
+        .and_then(|r| r.assert_positive_completion())
+        .is_ok()
+}
+
+impl PoolStream for TcpStream {
+    fn into_pooled(smtp_client: SmtpClient<Self>) -> PooledClient {
+        PooledClient::Plain(smtp_client)
+    }
+}
+
+impl PoolStream for TlsStream<TcpStream> {
+    fn into_pooled(smtp_client: SmtpClient<Self>) -> PooledClient {
+        PooledClient::Tls(smtp_client)
+    }
+}
@@ -41,8 +41,11 @@ use crate::{
     queue::{ErrorDetails, HostResponse, RCPT_STATUS_CHANGED},
 };
 
+use super::pool::{PoolParams, PoolStream, PooledClient, PooledSession};
+
 use crate::queue::{Error, Message, Recipient, Status};
 
+#[derive(Clone)]
 pub struct SessionParams<'x> {
     pub span: &'x tracing::Span,
     pub hostname: &'x str,
@@ -53,10 +56,11 @@ pub struct SessionParams<'x> {
     pub timeout_mail: Duration,
     pub timeout_rcpt: Duration,
     pub timeout_data: Duration,
+    pub pool: Option<PoolParams<'x>>,
 }
 
 impl Message {
-    pub async fn deliver<T: AsyncRead + AsyncWrite + Unpin>(
+    pub async fn deliver<T: AsyncRead + AsyncWrite + Unpin + PoolStream>(
         &self,
         mut smtp_client: SmtpClient<T>,
         recipients: impl Iterator<Item = &mut Recipient>,
@@ -110,8 +114,74 @@ impl Message {
         }
 
         // MAIL FROM
+        if let Err(status) = self
+            .mail_from(&mut smtp_client, &capabilities, &params)
+            .await
+        {
+            quit(smtp_client).await;
+            return status;
+        }
+
+        self.send_transaction(smtp_client, capabilities, recipients, params)
+            .await
+    }
+
+    /// Delivers the message over a pooled session. Returns `None` when the
+    /// session could not start a new transaction, in which case nothing was
+    /// sent and the message can be retried over a new connection.
+    pub async fn deliver_pooled(
+        &self,
+        session: PooledSession,
+        recipients: impl Iterator<Item = &mut Recipient>,
+        params: SessionParams<'_>,
+    ) -> Option<Status<(), Error>> {
+        match session.smtp_client {
+            PooledClient::Plain(smtp_client) => {
+                self.deliver_reused(smtp_client, session.capabilities, recipients, params)
+                    .await
+            }
+            PooledClient::Tls(smtp_client) => {
+                self.deliver_reused(smtp_client, session.capabilities, recipients, params)
+                    .await
+            }
+        }
+    }
+
+    async fn deliver_reused<T: AsyncRead + AsyncWrite + Unpin + PoolStream>(
+        &self,
+        mut smtp_client: SmtpClient<T>,
+        capabilities: EhloResponse<String>,
+        recipients: impl Iterator<Item = &mut Recipient>,
+        params: SessionParams<'_>,
+    ) -> Option<Status<(), Error>> {
+        // MAIL FROM
+        match self
+            .mail_from(&mut smtp_client, &capabilities, &params)
+            .await
+        {
+            Ok(_) => self
+                .send_transaction(smtp_client, capabilities, recipients, params)
+                .await
+                .into(),
+            Err(status) => {
+                quit(smtp_client).await;
+                if !matches!(status, Status::TemporaryFailure(_)) {
+                    Some(status)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    async fn mail_from<T: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        smtp_client: &mut SmtpClient<T>,
+        capabilities: &EhloResponse<String>,
+        params: &SessionParams<'_>,
+    ) -> Result<(), Status<(), Error>> {
         smtp_client.timeout = params.timeout_mail;
-        let cmd = self.build_mail_from(&capabilities);
+        let cmd = self.build_mail_from(capabilities);
         if let Err(err) = smtp_client
             .cmd(cmd.as_bytes())
             .await
@@ -124,10 +194,19 @@ impl Message {
                 mx = &params.hostname,
                 reason = %err,
             );
-            quit(smtp_client).await;
-            return Status::from_smtp_error(params.hostname, &cmd, err);
+            Err(Status::from_smtp_error(params.hostname, &cmd, err))
+        } else {
+            Ok(())
         }
+    }
 
+    async fn send_transaction<T: AsyncRead + AsyncWrite + Unpin + PoolStream>(
+        &self,
+        mut smtp_client: SmtpClient<T>,
+        capabilities: EhloResponse<String>,
+        recipients: impl Iterator<Item = &mut Recipient>,
+        mut params: SessionParams<'_>,
+    ) -> Status<(), Error> {
         // RCPT TO
         let mut total_rcpt = 0;
         let mut total_completed = 0;
@@ -344,7 +423,19 @@ impl Message {
             }
         }
 
-        quit(smtp_client).await;
+        // Keep the session open for reuse, if enabled
+        if let Some(pool) = params.pool.take() {
+            tracing::debug!(
+                parent: params.span,
+                context = "pool",
+                event = "release",
+                mx = &params.hostname,
+            );
+            pool.checkin(smtp_client, capabilities);
+        } else {
+            quit(smtp_client).await;
+        }
+
         if total_completed == total_rcpt {
             Status::Completed(())
         } else {
@@ -56,6 +56,9 @@ impl SpawnQueue for mpsc::Receiver<Event> {
             loop {
                 let result = tokio::time::timeout(queue.wake_up_time(), self.recv()).await;
 
+                // Close expired outbound sessions
+                core.queue.pool.purge();
+
                 // Deliver scheduled messages
                 while let Some(message) = queue.next_due() {
                     DeliveryAttempt::from(message)
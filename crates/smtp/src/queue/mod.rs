@@ -37,6 +37,7 @@ use crate::core::{management, Envelope};
 
 pub mod dsn;
 pub mod manager;
+pub mod quarantine;
 pub mod quota;
 pub mod serialize;
 pub mod spool;
@@ -89,6 +90,7 @@ pub struct Message {
     pub priority: i16,
 
     pub size: usize,
+    pub message_id: Option<String>, // Not written to the queue file
     pub queue_refs: Vec<UsedQuota>,
 }
 
@@ -115,7 +117,7 @@ pub struct Recipient {
 pub const RCPT_DSN_SENT: u64 = 1 << 32;
 pub const RCPT_STATUS_CHANGED: u64 = 2 << 32;
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Status<T, E> {
     #[serde(rename = "scheduled")]
     Scheduled,
@@ -0,0 +1,344 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+//! Quarantined messages are kept on disk next to the queue rather than in the
+//! data store: releasing a message hands it back to the file based queue, and
+//! the SMTP server may run without a data store when JMAP is not enabled.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::core::{QueueCore, SMTP};
+
+use super::{DomainPart, Message, QueueId};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuarantinedMessage {
+    pub id: QueueId,
+    pub created: u64,
+    pub return_path: String,
+    pub recipients: Vec<QuarantinedRecipient>,
+    pub flags: u64,
+    pub env_id: Option<String>,
+    pub priority: i16,
+    pub size: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuarantinedRecipient {
+    pub address: String,
+    pub flags: u64,
+    pub orcpt: Option<String>,
+}
+
+impl QueueCore {
+    pub async fn quarantine_message(
+        &self,
+        message: Box<Message>,
+        raw_headers: &[u8],
+        raw_message: &[u8],
+        reason: String,
+        span: &tracing::Span,
+    ) -> bool {
+        let path = if let Some(path) = &self.config.quarantine.path {
+            path
+        } else {
+            tracing::error!(
+                parent: span,
+                context = "quarantine",
+                event = "disabled",
+                id = message.id,
+                reason = reason,
+                "Quarantine path not configured, unable to quarantine message."
+            );
+            return false;
+        };
+        let _ = fs::create_dir_all(path).await;
+
+        // Write message contents first, metadata marks the entry as complete
+        let metadata = QuarantinedMessage::from_message(&message, reason);
+        let message_path = quarantine_file(path, metadata.id, "eml");
+        let metadata_path = quarantine_file(path, metadata.id, "json");
+        if let Err(err) = write_file(&message_path, &[raw_headers, raw_message]).await {
+            tracing::error!(
+                parent: span,
+                context = "quarantine",
+                event = "error",
+                "Failed to write to file {}: {}",
+                message_path.display(),
+                err
+            );
+            return false;
+        }
+        if let Err(err) = write_file(
+            &metadata_path,
+            &[&serde_json::to_vec(&metadata).unwrap_or_default()],
+        )
+        .await
+        {
+            tracing::error!(
+                parent: span,
+                context = "quarantine",
+                event = "error",
+                "Failed to write to file {}: {}",
+                metadata_path.display(),
+                err
+            );
+            let _ = fs::remove_file(&message_path).await;
+            return false;
+        }
+
+        tracing::info!(
+            parent: span,
+            context = "quarantine",
+            event = "quarantined",
+            id = metadata.id,
+            from = if !metadata.return_path.is_empty() {
+                metadata.return_path.as_str()
+            } else {
+                "<>"
+            },
+            nrcpts = metadata.recipients.len(),
+            size = metadata.size,
+            reason = metadata.reason,
+            "Message quarantined."
+        );
+
+        true
+    }
+
+    pub async fn quarantine_list(&self) -> Vec<QuarantinedMessage> {
+        let mut messages = Vec::new();
+        if let Some(path) = &self.config.quarantine.path {
+            if let Ok(mut dir) = fs::read_dir(path).await {
+                while let Ok(Some(entry)) = dir.next_entry().await {
+                    let path = entry.path();
+                    if path.extension().map_or(false, |ext| ext == "json") {
+                        if let Some(message) = read_metadata(&path).await {
+                            messages.push(message);
+                        }
+                    }
+                }
+            }
+        }
+        messages.sort_unstable_by_key(|m| m.id);
+        messages
+    }
+
+    pub async fn quarantine_get(&self, id: QueueId) -> Option<QuarantinedMessage> {
+        read_metadata(&quarantine_file(
+            self.config.quarantine.path.as_ref()?,
+            id,
+            "json",
+        ))
+        .await
+    }
+
+    pub async fn quarantine_read(&self, id: QueueId) -> Option<(QuarantinedMessage, Vec<u8>)> {
+        let metadata = self.quarantine_get(id).await?;
+        let path = quarantine_file(self.config.quarantine.path.as_ref()?, id, "eml");
+        match fs::read(&path).await {
+            Ok(contents) => Some((metadata, contents)),
+            Err(err) => {
+                tracing::error!(
+                    context = "quarantine",
+                    event = "error",
+                    "Failed to read file {}: {}",
+                    path.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    pub async fn quarantine_release(&self, id: QueueId, span: &tracing::Span) -> bool {
+        let (metadata, contents) = if let Some(result) = self.quarantine_read(id).await {
+            result
+        } else {
+            return false;
+        };
+
+        // Rebuild the message using the original envelope
+        let return_path_lcase = metadata.return_path.to_lowercase();
+        let return_path_domain = return_path_lcase.domain_part().to_string();
+        let mut message =
+            Message::new_boxed(metadata.return_path, return_path_lcase, return_path_domain);
+        message.flags = metadata.flags;
+        message.env_id = metadata.env_id;
+        message.priority = metadata.priority;
+        for rcpt in metadata.recipients {
+            message.add_recipient(rcpt.address, &self.config).await;
+            if let Some(recipient) = message.recipients.last_mut() {
+                recipient.flags = rcpt.flags;
+                recipient.orcpt = rcpt.orcpt;
+            }
+        }
+
+        message.size = metadata.size;
+        if !self.has_quota(&mut message).await {
+            tracing::warn!(
+                parent: span,
+                context = "quarantine",
+                event = "quota-exceeded",
+                id = id,
+                "Queue quota exceeded, unable to release message."
+            );
+            return false;
+        }
+
+        if self.queue_message(message, None, &contents, span).await {
+            tracing::info!(
+                parent: span,
+                context = "quarantine",
+                event = "released",
+                id = id,
+                "Quarantined message released for delivery."
+            );
+            self.quarantine_delete(id).await
+        } else {
+            false
+        }
+    }
+
+    pub async fn quarantine_delete(&self, id: QueueId) -> bool {
+        if let Some(path) = &self.config.quarantine.path {
+            let metadata_path = quarantine_file(path, id, "json");
+            if fs::remove_file(&metadata_path).await.is_ok() {
+                let message_path = quarantine_file(path, id, "eml");
+                if let Err(err) = fs::remove_file(&message_path).await {
+                    tracing::error!(
+                        context = "quarantine",
+                        event = "error",
+                        "Failed to delete quarantined message {}: {}",
+                        message_path.display(),
+                        err
+                    );
+                }
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub async fn quarantine_purge(&self) {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let retention = self.config.quarantine.retention.as_secs();
+
+        for message in self.quarantine_list().await {
+            if message.created + retention <= now && self.quarantine_delete(message.id).await {
+                tracing::debug!(
+                    context = "quarantine",
+                    event = "expired",
+                    id = message.id,
+                    "Purged expired quarantined message."
+                );
+            }
+        }
+    }
+}
+
+impl SMTP {
+    pub fn spawn_quarantine_housekeeper(self: &Arc<Self>) {
+        if self.queue.config.quarantine.path.is_some() {
+            let core = self.clone();
+            tokio::spawn(async move {
+                let frequency = std::cmp::max(
+                    core.queue.config.quarantine.purge_frequency,
+                    Duration::from_secs(1),
+                );
+                loop {
+                    tokio::time::sleep(frequency).await;
+                    core.queue.quarantine_purge().await;
+                }
+            });
+        }
+    }
+}
+
+impl QuarantinedMessage {
+    fn from_message(message: &Message, reason: String) -> Self {
+        QuarantinedMessage {
+            id: message.id,
+            created: message.created,
+            return_path: message.return_path.clone(),
+            recipients: message
+                .recipients
+                .iter()
+                .map(|rcpt| QuarantinedRecipient {
+                    address: rcpt.address.clone(),
+                    flags: rcpt.flags,
+                    orcpt: rcpt.orcpt.clone(),
+                })
+                .collect(),
+            flags: message.flags,
+            env_id: message.env_id.clone(),
+            priority: message.priority,
+            size: message.size,
+            reason,
+        }
+    }
+}
+
+fn quarantine_file(path: &Path, id: QueueId, extension: &str) -> PathBuf {
+    let mut path = path.to_path_buf();
+    path.push(format!("{id}.{extension}"));
+    path
+}
+
+async fn write_file(path: &Path, contents: &[&[u8]]) -> std::io::Result<()> {
+    let mut file = fs::File::create(path).await?;
+    for bytes in contents {
+        file.write_all(bytes).await?;
+    }
+    file.flush().await
+}
+
+async fn read_metadata(path: &Path) -> Option<QuarantinedMessage> {
+    match fs::read(path).await {
+        Ok(bytes) => match serde_json::from_slice(&bytes) {
+            Ok(message) => Some(message),
+            Err(err) => {
+                tracing::warn!(
+                    context = "quarantine",
+                    event = "error",
+                    "Failed to parse quarantine metadata {}: {}",
+                    path.display(),
+                    err
+                );
+                None
+            }
+        },
+        Err(_) => None,
+    }
+}
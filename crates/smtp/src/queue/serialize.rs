@@ -198,6 +198,7 @@ impl Message {
             size: 0,
             recipients: vec![],
             domains: vec![],
+            message_id: None,
             queue_refs: vec![],
         };
 
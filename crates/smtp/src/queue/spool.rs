@@ -188,6 +188,7 @@ impl Message {
             env_id: None,
             priority: 0,
             size: 0,
+            message_id: None,
             queue_refs: vec![],
         })
     }
@@ -0,0 +1,431 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    borrow::Cow,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ahash::AHashSet;
+use mail_auth::common::lru::{DnsCache, LruCache};
+use mail_parser::{Message, MimeHeaders, PartType};
+
+use crate::{
+    core::SMTP,
+    queue::{self, DomainPart, QueueId},
+};
+
+const MAX_HEADER_SIZE: usize = 65536;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bounce {
+    pub address: String,
+    pub reason: String,
+}
+
+/// Recipients of recently delivered messages, indexed by Message-ID and queue id.
+/// The log is kept in memory so bounces for messages sent before a restart are ignored.
+pub struct SentLog {
+    pub entries: LruCache<String, Arc<Vec<String>>>,
+    pub expire: Duration,
+}
+
+pub trait AnalyzeBounce {
+    fn analyze_bounce(&self, message: Arc<Vec<u8>>, rcpt_to: Vec<String>);
+}
+
+impl AnalyzeBounce for Arc<SMTP> {
+    fn analyze_bounce(&self, message: Arc<Vec<u8>>, rcpt_to: Vec<String>) {
+        let core = self.clone();
+        self.worker_pool.spawn(move || {
+            let message = if let Some(message) = Message::parse(&message) {
+                message
+            } else {
+                tracing::debug!(context = "bounce", "Failed to parse message.");
+                return;
+            };
+
+            // Only suppress recipients of messages that were sent by this server
+            let mut sent_rcpts = AHashSet::new();
+            for reference in parse_references(&message) {
+                if let Some(recipients) = core.report.sent_log.entries.get(&reference) {
+                    sent_rcpts.extend(recipients.iter().cloned());
+                }
+            }
+            if sent_rcpts.is_empty() {
+                tracing::debug!(
+                    context = "bounce",
+                    event = "ignore",
+                    "Bounce does not reference any message sent by this server."
+                );
+                return;
+            }
+
+            // Never suppress the recipients of the bounce itself
+            let mut bounces = parse_bounce(&message);
+            bounces.retain(|bounce| {
+                !rcpt_to.contains(&bounce.address) && sent_rcpts.contains(&bounce.address)
+            });
+            if bounces.is_empty() {
+                tracing::debug!(
+                    context = "bounce",
+                    event = "ignore",
+                    "No hard bounces found in message."
+                );
+                return;
+            }
+
+            let mut domains = rcpt_to
+                .iter()
+                .map(|rcpt| rcpt.domain_part().to_string())
+                .collect::<Vec<_>>();
+            domains.sort_unstable();
+            domains.dedup();
+
+            for bounce in &bounces {
+                tracing::info!(
+                    context = "bounce",
+                    event = "suppress",
+                    domains = ?domains,
+                    address = bounce.address,
+                    reason = bounce.reason,
+                    "Hard bounce received, suppressing address."
+                );
+            }
+
+            #[cfg(feature = "local_delivery")]
+            {
+                let created = std::time::SystemTime::now()
+                    .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs());
+                let addresses = domains
+                    .iter()
+                    .flat_map(|domain| {
+                        bounces
+                            .iter()
+                            .map(move |bounce| utils::ipc::SuppressedAddress {
+                                domain: domain.clone(),
+                                address: bounce.address.clone(),
+                                reason: bounce.reason.clone(),
+                                created,
+                            })
+                    })
+                    .collect();
+                if core
+                    .delivery_tx
+                    .blocking_send(utils::ipc::DeliveryEvent::Suppression(
+                        utils::ipc::SuppressionEvent::Add { addresses },
+                    ))
+                    .is_err()
+                {
+                    tracing::warn!(
+                        context = "bounce",
+                        event = "error",
+                        "Failed to send suppression event: tx channel closed."
+                    );
+                }
+            }
+
+            #[cfg(not(feature = "local_delivery"))]
+            let _ = (core, domains);
+        });
+    }
+}
+
+impl SMTP {
+    pub fn track_sent_message(
+        &self,
+        message: &queue::Message,
+        filter: impl Fn(usize, &queue::Recipient) -> bool,
+    ) {
+        let sent_log = &self.report.sent_log;
+        if sent_log.expire.is_zero() {
+            return;
+        }
+        let recipients = message
+            .recipients
+            .iter()
+            .enumerate()
+            .filter(|(idx, rcpt)| filter(*idx, rcpt))
+            .map(|(_, rcpt)| rcpt.address_lcase.clone())
+            .collect::<Vec<_>>();
+        if recipients.is_empty() {
+            return;
+        }
+
+        let recipients = Arc::new(recipients);
+        let valid_until = Instant::now() + sent_log.expire;
+        if let Some(message_id) = &message.message_id {
+            sent_log
+                .entries
+                .insert(message_id.clone(), recipients.clone(), valid_until);
+        }
+        sent_log
+            .entries
+            .insert(queue_id_reference(message.id), recipients, valid_until);
+    }
+}
+
+fn queue_id_reference(id: QueueId) -> String {
+    format!("{id:X}")
+}
+
+/// Returns the Message-ID of a raw message, only its headers are parsed.
+pub fn parse_message_id(raw_message: &[u8]) -> Option<String> {
+    let headers = &raw_message[..raw_message.len().min(MAX_HEADER_SIZE)];
+    let headers = headers
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map_or(headers, |pos| &headers[..pos + 4]);
+    Message::parse(headers)?
+        .message_id()
+        .map(|id| id.to_string())
+}
+
+/// Returns the Message-IDs and queue ids of the original messages quoted in a bounce.
+pub fn parse_references(message: &Message) -> Vec<String> {
+    let mut references = Vec::new();
+    let mut texts = vec![String::from_utf8_lossy(message.raw_message.as_ref())];
+    for part in &message.parts {
+        match &part.body {
+            PartType::Text(text) => texts.push(Cow::Borrowed(text.as_ref())),
+            PartType::Message(message) => {
+                if let Some(message_id) = message.message_id() {
+                    references.push(message_id.to_string());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    for text in texts {
+        let mut lines = text.lines();
+        while let Some(line) = lines.next() {
+            let line = line.trim_start_matches(['>', ' ']);
+            if line
+                .get(..11)
+                .map_or(false, |name| name.eq_ignore_ascii_case("message-id:"))
+            {
+                // Message-ID headers of the original message
+                let value = line[11..].trim();
+                let value = if value.is_empty() {
+                    lines.next().unwrap_or_default().trim()
+                } else {
+                    value
+                };
+                let value = value
+                    .trim_start_matches('<')
+                    .split('>')
+                    .next()
+                    .unwrap_or_default();
+                if !value.is_empty() {
+                    references.push(value.to_string());
+                }
+            } else if let Some((_, id)) = line
+                .split_once("(Stalwart SMTP) with ")
+                .and_then(|(_, with)| with.split_once(" id "))
+            {
+                // Queue ids from the Received headers added by this server
+                let id = id
+                    .split(|c: char| c == ';' || c.is_whitespace())
+                    .next()
+                    .unwrap_or_default();
+                if !id.is_empty() && id.chars().all(|c| c.is_ascii_hexdigit()) {
+                    references.push(id.to_ascii_uppercase());
+                }
+            }
+        }
+    }
+
+    references.sort_unstable();
+    references.dedup();
+    references
+}
+
+pub fn parse_bounce(message: &Message) -> Vec<Bounce> {
+    let mut bounces = Vec::new();
+    let mut has_dsn = false;
+
+    // Standard delivery status notifications (RFC 3464)
+    for part in &message.parts {
+        if part.is_content_type("message", "delivery-status") {
+            let report = match &part.body {
+                PartType::Text(report) => Cow::Borrowed(report.as_ref()),
+                PartType::Binary(report) | PartType::InlineBinary(report) => {
+                    String::from_utf8_lossy(report.as_ref())
+                }
+                _ => continue,
+            };
+            has_dsn = true;
+            parse_delivery_status(&report, &mut bounces);
+        }
+    }
+
+    // Non-standard bounces
+    if !has_dsn {
+        if let Some(text) = message.body_text(0) {
+            parse_text_bounce(&text, &mut bounces);
+        }
+    }
+
+    bounces
+}
+
+fn parse_delivery_status(report: &str, bounces: &mut Vec<Bounce>) {
+    let report = report.replace("\r\n", "\n");
+    for block in report.split("\n\n") {
+        let mut recipient = None;
+        let mut is_failed = false;
+        let mut status = "";
+        let mut diagnostic = None;
+
+        for (name, value) in parse_fields(block) {
+            match name.to_ascii_lowercase().as_str() {
+                "final-recipient" => {
+                    recipient = value
+                        .split_once(';')
+                        .map_or(value.as_str(), |(_, addr)| addr)
+                        .trim()
+                        .trim_start_matches('<')
+                        .trim_end_matches('>')
+                        .to_lowercase()
+                        .into();
+                }
+                "action" => {
+                    is_failed = value.trim().eq_ignore_ascii_case("failed");
+                }
+                "status" => {
+                    status = value.trim();
+                }
+                "diagnostic-code" => {
+                    diagnostic = value
+                        .split_once(';')
+                        .map_or(value.as_str(), |(_, diag)| diag)
+                        .trim()
+                        .to_string()
+                        .into();
+                }
+                _ => (),
+            }
+        }
+
+        if let Some(address) = recipient.filter(|addr| addr.contains('@')) {
+            if is_failed
+                && (is_hard_status(status)
+                    || (status.starts_with("5.")
+                        && diagnostic.as_deref().map_or(false, has_hard_phrase)))
+                && !bounces.iter().any(|b| b.address == address)
+            {
+                bounces.push(Bounce {
+                    address,
+                    reason: diagnostic.unwrap_or_else(|| format!("Status: {status}")),
+                });
+            }
+        }
+    }
+}
+
+fn parse_text_bounce(text: &str, bounces: &mut Vec<Bounce>) {
+    let text = text.replace("\r\n", "\n");
+    for paragraph in text.split("\n\n") {
+        let reason = if let Some(line) = paragraph.lines().find(|line| {
+            let line = line.to_ascii_lowercase();
+            has_hard_phrase(&line)
+                || line
+                    .split(|c: char| !c.is_ascii_digit() && c != '.')
+                    .any(is_hard_status)
+        }) {
+            line.trim()
+        } else {
+            continue;
+        };
+
+        for word in paragraph.split(|c: char| {
+            c.is_whitespace()
+                || matches!(
+                    c,
+                    '<' | '>' | '(' | ')' | '"' | '\'' | ',' | ';' | '[' | ']'
+                )
+        }) {
+            let word = word.trim_matches(|c: char| matches!(c, '.' | ':'));
+            if let Some((local, domain)) = word.split_once('@') {
+                if !local.is_empty()
+                    && domain.contains('.')
+                    && !local.eq_ignore_ascii_case("mailer-daemon")
+                    && !local.eq_ignore_ascii_case("postmaster")
+                {
+                    let address = word.to_lowercase();
+                    if !bounces.iter().any(|b| b.address == address) {
+                        bounces.push(Bounce {
+                            address,
+                            reason: reason.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn parse_fields(block: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in block.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            fields.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    fields
+}
+
+fn is_hard_status(status: &str) -> bool {
+    // Bad destination mailbox address (5.1.x) or disabled mailbox (5.2.1)
+    let mut parts = status.split('.');
+    matches!(
+        (parts.next(), parts.next(), parts.next(), parts.next()),
+        (Some("5"), Some("1"), Some(detail), None) if !detail.is_empty() && detail.chars().all(|c| c.is_ascii_digit())
+    ) || status == "5.2.1"
+}
+
+fn has_hard_phrase(text: &str) -> bool {
+    let text = text.to_ascii_lowercase();
+    [
+        "user unknown",
+        "unknown user",
+        "no such user",
+        "no such mailbox",
+        "mailbox not found",
+        "mailbox unavailable",
+        "does not exist",
+        "invalid recipient",
+        "recipient address rejected",
+        "account has been disabled",
+    ]
+    .iter()
+    .any(|phrase| text.contains(phrase))
+}
@@ -46,11 +46,14 @@ use crate::{
 use self::scheduler::{ReportKey, ReportValue};
 
 pub mod analysis;
+pub mod bounce;
 pub mod dkim;
 pub mod dmarc;
 pub mod scheduler;
 pub mod spf;
+pub mod suppression;
 pub mod tls;
+pub mod webhook;
 
 #[derive(Debug)]
 pub enum Event {
@@ -0,0 +1,116 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use store::{
+    write::key::{DeserializeBigEndian, KeySerializer},
+    CustomValueKey, Deserialize, Serialize, CUSTOM_SUPPRESSION,
+};
+
+use crate::core::SMTP;
+
+pub struct SuppressionKey();
+
+pub struct SuppressionValue {
+    pub created: u64,
+    pub reason: String,
+}
+
+impl SMTP {
+    pub async fn is_suppressed(&self, domain: &str, address: &str) -> bool {
+        let store = if let Some(store) = self.store.get() {
+            store
+        } else {
+            return false;
+        };
+
+        match store
+            .get_value::<SuppressionValue>(CustomValueKey {
+                value: SuppressionKey::new(domain, address),
+            })
+            .await
+        {
+            Ok(value) => value.is_some(),
+            Err(err) => {
+                tracing::error!(
+                    context = "suppression",
+                    event = "error",
+                    domain = domain,
+                    address = address,
+                    error = ?err,
+                    "Failed to query suppression list."
+                );
+                false
+            }
+        }
+    }
+}
+
+impl SuppressionKey {
+    pub fn new(domain: &str, address: &str) -> Vec<u8> {
+        let domain = domain.to_lowercase();
+        let address = address.to_lowercase();
+        KeySerializer::new(domain.len() + address.len() + std::mem::size_of::<u32>() + 2)
+            .write(u32::MAX)
+            .write(CUSTOM_SUPPRESSION)
+            .write(domain.as_str())
+            .write(0u8)
+            .write(address.as_str())
+            .finalize()
+    }
+
+    pub fn prefix(domain: Option<&str>) -> Vec<u8> {
+        let serializer = KeySerializer::new(std::mem::size_of::<u32>() + 64)
+            .write(u32::MAX)
+            .write(CUSTOM_SUPPRESSION);
+        if let Some(domain) = domain {
+            serializer
+                .write(domain.to_lowercase().as_str())
+                .write(0u8)
+                .finalize()
+        } else {
+            serializer.finalize()
+        }
+    }
+}
+
+impl Serialize for SuppressionValue {
+    fn serialize(self) -> Vec<u8> {
+        KeySerializer::new(std::mem::size_of::<u64>() + self.reason.len())
+            .write(self.created)
+            .write(self.reason.as_str())
+            .finalize()
+    }
+}
+
+impl Deserialize for SuppressionValue {
+    fn deserialize(bytes: &[u8]) -> store::Result<Self> {
+        Ok(SuppressionValue {
+            created: bytes.deserialize_be_u64(0)?,
+            reason: std::str::from_utf8(
+                bytes.get(std::mem::size_of::<u64>()..).unwrap_or_default(),
+            )
+            .unwrap_or_default()
+            .to_string(),
+        })
+    }
+}
@@ -0,0 +1,378 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{net::IpAddr, sync::Arc};
+
+use hmac::{Hmac, Mac};
+use mail_parser::DateTime;
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use smtp_proto::Response;
+use store::write::now;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::{
+    config::{Webhook, WebhookEventType},
+    core::{
+        management::{deserialize_datetime, serialize_datetime},
+        Session, SMTP,
+    },
+    queue::{Error, Message, QueueId, Recipient, Status},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WebhookEvent {
+    #[serde(rename = "type")]
+    pub typ: WebhookEventType,
+    #[serde(deserialize_with = "deserialize_datetime")]
+    #[serde(serialize_with = "serialize_datetime")]
+    pub timestamp: DateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub queue_id: Option<QueueId>,
+    pub return_path: String,
+    pub recipient: String,
+    pub status: Status<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub response: Option<WebhookResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub remote_mta: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub remote_ip: Option<IpAddr>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WebhookResponse {
+    pub code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub enhanced_code: Option<String>,
+    pub message: String,
+}
+
+impl SMTP {
+    pub fn has_webhooks(&self, typ: WebhookEventType) -> bool {
+        self.report
+            .config
+            .webhooks
+            .iter()
+            .any(|webhook| webhook.events.contains(&typ))
+    }
+
+    pub fn send_webhook(&self, event: WebhookEvent) {
+        let mut body = None;
+        for webhook in &self.report.config.webhooks {
+            if webhook.events.contains(&event.typ) {
+                let body = body
+                    .get_or_insert_with(|| {
+                        Arc::new(serde_json::to_string(&event).unwrap_or_default())
+                    })
+                    .clone();
+                let webhook = webhook.clone();
+                tokio::spawn(async move {
+                    webhook.post(body).await;
+                });
+            }
+        }
+    }
+
+    pub fn send_delivery_webhooks(
+        &self,
+        message: &Message,
+        typ: Option<WebhookEventType>,
+        filter: impl Fn(usize, &Recipient) -> bool,
+    ) {
+        if self.report.config.webhooks.is_empty() {
+            return;
+        }
+
+        for (idx, rcpt) in message.recipients.iter().enumerate() {
+            if filter(idx, rcpt) {
+                if let Some(mut event) = message.webhook_event(rcpt) {
+                    if let Some(typ) = typ {
+                        event.typ = typ;
+                    }
+                    if self.has_webhooks(event.typ) {
+                        self.send_webhook(event);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: AsyncWrite + AsyncRead> Session<T> {
+    pub fn send_rejection_webhooks<'x>(
+        &self,
+        rcpts: impl Iterator<Item = &'x str>,
+        response: &[u8],
+    ) {
+        if !self.core.has_webhooks(WebhookEventType::Rejected) {
+            return;
+        }
+
+        let text = String::from_utf8_lossy(response).trim_end().to_string();
+        let response = WebhookResponse::parse(&text);
+        let status = if response.as_ref().map_or(true, |r| r.code >= 500) {
+            Status::PermanentFailure(text)
+        } else {
+            Status::TemporaryFailure(text)
+        };
+        let return_path = self
+            .data
+            .mail_from
+            .as_ref()
+            .map(|mail_from| mail_from.address.clone())
+            .unwrap_or_default();
+
+        for rcpt in rcpts {
+            self.core.send_webhook(WebhookEvent {
+                typ: WebhookEventType::Rejected,
+                timestamp: DateTime::from_timestamp(now() as i64),
+                queue_id: None,
+                return_path: return_path.clone(),
+                recipient: rcpt.to_string(),
+                status: status.clone(),
+                response: response.clone(),
+                remote_mta: if !self.data.helo_domain.is_empty() {
+                    self.data.helo_domain.clone().into()
+                } else {
+                    None
+                },
+                remote_ip: self.data.remote_ip.into(),
+            });
+        }
+    }
+}
+
+impl Message {
+    pub fn webhook_event(&self, rcpt: &Recipient) -> Option<WebhookEvent> {
+        let (typ, status, response, remote_mta) = match &rcpt.status {
+            Status::Completed(response) => (
+                WebhookEventType::Delivered,
+                Status::Completed(response.response.to_string()),
+                Some(WebhookResponse::from(&response.response)),
+                Some(response.hostname.clone()),
+            ),
+            Status::TemporaryFailure(response) => (
+                WebhookEventType::Deferred,
+                Status::TemporaryFailure(response.response.to_string()),
+                Some(WebhookResponse::from(&response.response)),
+                Some(response.hostname.entity.clone()),
+            ),
+            Status::PermanentFailure(response) => (
+                WebhookEventType::Bounced,
+                Status::PermanentFailure(response.response.to_string()),
+                Some(WebhookResponse::from(&response.response)),
+                Some(response.hostname.entity.clone()),
+            ),
+            Status::Scheduled => {
+                // There is no status for this address, use the domain's status.
+                match &self.domains.get(rcpt.domain_idx)?.status {
+                    Status::TemporaryFailure(err) => (
+                        WebhookEventType::Deferred,
+                        Status::TemporaryFailure(err.to_string()),
+                        err.webhook_response(),
+                        err.remote_mta(),
+                    ),
+                    Status::PermanentFailure(err) => (
+                        WebhookEventType::Bounced,
+                        Status::PermanentFailure(err.to_string()),
+                        err.webhook_response(),
+                        err.remote_mta(),
+                    ),
+                    Status::Scheduled | Status::Completed(_) => return None,
+                }
+            }
+        };
+
+        Some(WebhookEvent {
+            typ,
+            timestamp: DateTime::from_timestamp(now() as i64),
+            queue_id: self.id.into(),
+            return_path: self.return_path.clone(),
+            recipient: rcpt.address.clone(),
+            status,
+            response,
+            remote_mta,
+            remote_ip: None,
+        })
+    }
+}
+
+impl Error {
+    fn webhook_response(&self) -> Option<WebhookResponse> {
+        match self {
+            Error::UnexpectedResponse(response) => Some((&response.response).into()),
+            _ => None,
+        }
+    }
+
+    fn remote_mta(&self) -> Option<String> {
+        match self {
+            Error::UnexpectedResponse(response) => Some(response.hostname.entity.clone()),
+            Error::ConnectionError(details)
+            | Error::TlsError(details)
+            | Error::DaneError(details) => Some(details.entity.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl WebhookResponse {
+    pub fn parse(response: &str) -> Option<Self> {
+        let code = response.get(0..3)?.parse::<u16>().ok()?;
+        let message = response.get(3..)?.trim_start_matches([' ', '-']);
+        let (enhanced_code, message) = match message.split_once(' ') {
+            Some((esc, message))
+                if esc.split('.').count() == 3
+                    && esc.split('.').all(|c| c.parse::<u16>().is_ok()) =>
+            {
+                (Some(esc.to_string()), message)
+            }
+            _ => (None, message),
+        };
+
+        Some(WebhookResponse {
+            code,
+            enhanced_code,
+            message: message.trim().to_string(),
+        })
+    }
+}
+
+impl From<&Response<String>> for WebhookResponse {
+    fn from(response: &Response<String>) -> Self {
+        WebhookResponse {
+            code: response.code,
+            enhanced_code: if response.esc[0] > 0 {
+                format!(
+                    "{}.{}.{}",
+                    response.esc[0], response.esc[1], response.esc[2]
+                )
+                .into()
+            } else {
+                None
+            },
+            message: response.message.clone(),
+        }
+    }
+}
+
+impl Webhook {
+    pub async fn post(&self, body: Arc<String>) {
+        let mut attempt = 1;
+
+        loop {
+            match self.try_post(body.as_str()).await {
+                Ok(_) => {
+                    tracing::debug!(
+                        context = "webhook",
+                        event = "success",
+                        id = self.id,
+                        url = self.url,
+                        attempt = attempt,
+                    );
+                    return;
+                }
+                Err(err) if attempt < self.attempts_max => {
+                    tracing::debug!(
+                        context = "webhook",
+                        event = "retry",
+                        id = self.id,
+                        url = self.url,
+                        attempt = attempt,
+                        reason = err,
+                    );
+                    tokio::time::sleep(self.attempts_interval).await;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        context = "webhook",
+                        event = "error",
+                        id = self.id,
+                        url = self.url,
+                        attempt = attempt,
+                        reason = err,
+                        "Failed to deliver webhook event."
+                    );
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn try_post(&self, body: &str) -> Result<(), String> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json");
+
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        // The timestamp is signed along with the body so receivers can reject replays
+        if let Some(key) = &self.key {
+            let timestamp = now();
+            request = request
+                .header("X-Signature-Timestamp", timestamp.to_string())
+                .header(
+                    "X-Signature",
+                    format!("sha256={}", sign(key, &format!("{timestamp}.{body}"))),
+                );
+        }
+
+        let response = request
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|err| format!("HTTP request failed: {err}"))?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "HTTP request failed with status {}",
+                response.status()
+            ))
+        }
+    }
+}
+
+pub fn sign(key: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC can take keys of any size");
+    mac.update(body.as_bytes());
+
+    let mut signature = String::with_capacity(64);
+    for byte in mac.finalize().into_bytes() {
+        signature.push_str(&format!("{byte:02x}"));
+    }
+    signature
+}
@@ -0,0 +1,212 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::{Duration, Instant};
+
+use ahash::AHashMap;
+use foundationdb::{options::StreamingMode, FdbError, KeySelector, RangeOption};
+use tokio::sync::mpsc;
+
+use crate::{
+    backup::BackupRecord,
+    write::key::{DeserializeBigEndian, KeySerializer},
+    Deserialize, Serialize, Store, SUBSPACE_BITMAPS, SUBSPACE_INDEXES, SUBSPACE_LOGS,
+    SUBSPACE_QUOTAS, SUBSPACE_VALUES,
+};
+
+const MAX_COMMIT_ATTEMPTS: u8 = 25;
+const MAX_TRANSACTION_AGE: Duration = Duration::from_millis(2000);
+const MAX_TRANSACTION_SIZE: usize = 1024 * 1024;
+
+impl Store {
+    // FoundationDB transactions are limited to five seconds, so each subspace is read
+    // in chunks and the snapshot is only consistent within every chunk.
+    pub(crate) async fn export_records(&self, tx: mpsc::Sender<BackupRecord>) -> crate::Result<()> {
+        for subspace in [
+            SUBSPACE_LOGS,
+            SUBSPACE_INDEXES,
+            SUBSPACE_BITMAPS,
+            SUBSPACE_QUOTAS,
+            SUBSPACE_VALUES,
+        ] {
+            let mut begin = vec![subspace];
+            let end = vec![subspace + 1];
+            let mut trx = self.db.create_trx()?;
+            let mut trx_age = Instant::now();
+            let mut is_first = true;
+
+            loop {
+                if trx_age.elapsed() > MAX_TRANSACTION_AGE {
+                    trx = self.db.create_trx()?;
+                    trx_age = Instant::now();
+                }
+                let values = trx
+                    .get_range(
+                        &RangeOption {
+                            begin: if is_first {
+                                KeySelector::first_greater_or_equal(&begin)
+                            } else {
+                                KeySelector::first_greater_than(&begin)
+                            },
+                            end: KeySelector::first_greater_or_equal(&end),
+                            mode: StreamingMode::WantAll,
+                            reverse: false,
+                            ..Default::default()
+                        },
+                        1,
+                        true,
+                    )
+                    .await?;
+
+                for value in values.iter() {
+                    let key = &value.key()[1..];
+                    let value = value.value();
+                    let record = if subspace == SUBSPACE_BITMAPS {
+                        let mut words = [0u64; 16];
+                        for (word, bytes) in words.iter_mut().zip(value.chunks_exact(8)) {
+                            *word = u64::from_le_bytes(bytes.try_into().unwrap());
+                        }
+                        if words.iter().all(|word| *word == 0) {
+                            continue;
+                        }
+                        BackupRecord::bitmap(key.to_vec(), words)
+                    } else {
+                        BackupRecord {
+                            subspace,
+                            key: key.to_vec(),
+                            value: value.to_vec(),
+                        }
+                    };
+                    tx.send(record).await.map_err(|_| {
+                        crate::Error::InternalError("Backup writer stopped.".to_string())
+                    })?;
+                }
+
+                if let Some(last) = values.last() {
+                    begin = last.key().to_vec();
+                    is_first = false;
+                }
+                if !values.more() {
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn import_records(&self, records: Vec<BackupRecord>) -> crate::Result<()> {
+        let mut chunk = Vec::new();
+        let mut chunk_size = 0;
+
+        for record in records {
+            if !matches!(
+                record.subspace,
+                SUBSPACE_VALUES
+                    | SUBSPACE_LOGS
+                    | SUBSPACE_INDEXES
+                    | SUBSPACE_BITMAPS
+                    | SUBSPACE_QUOTAS
+            ) {
+                return Err(crate::Error::InternalError(format!(
+                    "Invalid subspace {} in backup.",
+                    record.subspace
+                )));
+            } else if record.subspace == SUBSPACE_BITMAPS {
+                record.bitmap_words()?;
+            } else if record.subspace == SUBSPACE_QUOTAS {
+                record.quota_value()?;
+            }
+
+            let mut key = Vec::with_capacity(record.key.len() + 1);
+            key.push(record.subspace);
+            key.extend_from_slice(&record.key);
+            chunk_size += key.len() + record.value.len();
+            chunk.push((key, record.value));
+
+            if chunk_size >= MAX_TRANSACTION_SIZE {
+                self.import_chunk(std::mem::take(&mut chunk)).await?;
+                chunk_size = 0;
+            }
+        }
+
+        if !chunk.is_empty() {
+            self.import_chunk(chunk).await?;
+        }
+
+        #[cfg(feature = "test_mode")]
+        crate::backend::foundationdb::write::BITMAPS.lock().clear();
+
+        Ok(())
+    }
+
+    async fn import_chunk(&self, chunk: Vec<(Vec<u8>, Vec<u8>)>) -> crate::Result<()> {
+        // Change id counters have to be ahead of the imported change logs
+        let mut change_ids = AHashMap::new();
+        for (key, _) in &chunk {
+            if key.first() == Some(&SUBSPACE_LOGS) {
+                let account_id = key.as_slice().deserialize_be_u32(1)?;
+                let change_id = key
+                    .as_slice()
+                    .deserialize_be_u64(key.len() - std::mem::size_of::<u64>())?;
+                let max_change_id = change_ids.entry(account_id).or_insert(change_id);
+                if *max_change_id < change_id {
+                    *max_change_id = change_id;
+                }
+            }
+        }
+
+        let mut retry_count = 0;
+        loop {
+            let trx = self.db.create_trx()?;
+            for (key, value) in &chunk {
+                trx.set(key, value);
+            }
+            for (account_id, change_id) in &change_ids {
+                let counter = KeySerializer::new(std::mem::size_of::<u32>() + 1)
+                    .write(SUBSPACE_VALUES)
+                    .write(*account_id)
+                    .finalize();
+                let last_change_id = if let Some(bytes) = trx.get(&counter, false).await? {
+                    Some(u64::deserialize(&bytes)?)
+                } else {
+                    None
+                };
+                if last_change_id.map_or(true, |last_change_id| last_change_id < *change_id) {
+                    trx.set(&counter, &change_id.serialize());
+                }
+            }
+            match trx.commit().await {
+                Ok(_) => return Ok(()),
+                Err(err) => {
+                    if retry_count < MAX_COMMIT_ATTEMPTS {
+                        err.on_error().await?;
+                        retry_count += 1;
+                    } else {
+                        return Err(FdbError::from(err).into());
+                    }
+                }
+            }
+        }
+    }
+}
@@ -23,14 +23,29 @@
 
 use foundationdb::FdbError;
 
-use crate::Error;
+use crate::{
+    write::{key::KeySerializer, QuotaClass},
+    Error, SUBSPACE_QUOTAS,
+};
 
+pub mod backup;
 pub mod bitmap;
 pub mod main;
 pub mod purge;
 pub mod read;
 pub mod write;
 
+// Message counts are stored next to the disk usage of the account
+pub(crate) fn quota_key(account_id: u32, class: QuotaClass) -> Vec<u8> {
+    let key = KeySerializer::new(6)
+        .write(SUBSPACE_QUOTAS)
+        .write(account_id);
+    match class {
+        QuotaClass::Octets => key.finalize(),
+        QuotaClass::Messages => key.write(1u8).finalize(),
+    }
+}
+
 impl From<FdbError> for Error {
     fn from(error: FdbError) -> Self {
         Self::InternalError(format!("FoundationDB error: {}", error.message()))
@@ -116,13 +116,18 @@ impl Store {
             }
         }
 
-        // Delete quota key
+        // Delete quota keys
         let trx = self.db.create_trx()?;
-        trx.clear(
+        trx.clear_range(
             &KeySerializer::new(5)
                 .write(SUBSPACE_QUOTAS)
                 .write(account_id)
                 .finalize(),
+            &KeySerializer::new(6)
+                .write(SUBSPACE_QUOTAS)
+                .write(account_id)
+                .write(u8::MAX)
+                .finalize(),
         );
         if let Err(err) = trx.commit().await {
             return Err(FdbError::from(err).into());
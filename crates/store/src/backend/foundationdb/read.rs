@@ -35,12 +35,15 @@ use roaring::RoaringBitmap;
 
 use crate::{
     query::Operator,
-    write::key::{DeserializeBigEndian, KeySerializer},
+    write::{
+        key::{DeserializeBigEndian, KeySerializer},
+        QuotaClass,
+    },
     BitmapKey, Deserialize, IndexKey, IndexKeyPrefix, Key, LogKey, ReadTransaction, Serialize,
     Store, SUBSPACE_INDEXES, SUBSPACE_QUOTAS,
 };
 
-use super::bitmap::DeserializeBlock;
+use super::{bitmap::DeserializeBlock, quota_key};
 
 impl ReadTransaction<'_> {
     #[inline(always)]
@@ -353,26 +356,22 @@ impl ReadTransaction<'_> {
         Ok(None)
     }
 
-    pub async fn get_quota(&self, account_id: u32) -> crate::Result<i64> {
-        if let Some(bytes) = self
-            .trx
-            .get(
-                &KeySerializer::new(5)
-                    .write(SUBSPACE_QUOTAS)
-                    .write(account_id)
-                    .finalize(),
-                true,
-            )
-            .await?
-        {
-            Ok(i64::from_le_bytes(bytes[..].try_into().map_err(|_| {
-                crate::Error::InternalError(format!(
-                    "Invalid quota value for account {}",
-                    account_id
-                ))
-            })?))
+    pub async fn get_quota(
+        &self,
+        account_id: u32,
+        class: QuotaClass,
+    ) -> crate::Result<Option<i64>> {
+        if let Some(bytes) = self.trx.get(&quota_key(account_id, class), true).await? {
+            Ok(Some(i64::from_le_bytes(bytes[..].try_into().map_err(
+                |_| {
+                    crate::Error::InternalError(format!(
+                        "Invalid quota value for account {}",
+                        account_id
+                    ))
+                },
+            )?)))
         } else {
-            Ok(0)
+            Ok(None)
         }
     }
 
@@ -467,7 +466,7 @@ impl Store {
                     SUBSPACE_QUOTAS => {
                         let v = i64::from_le_bytes(value[..].try_into().unwrap());
                         if v != 0 {
-                            let k = u32::from_be_bytes(key[1..].try_into().unwrap());
+                            let k = u32::from_be_bytes(key[1..5].try_into().unwrap());
                             panic!("Table quotas is not empty: {k:?} = {v:?} (key {key:?})");
                         }
                     }
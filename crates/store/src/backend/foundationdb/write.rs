@@ -36,11 +36,13 @@ use crate::{
         key::{DeserializeBigEndian, KeySerializer},
         now, Batch, Operation, ValueClass,
     },
-    AclKey, BitmapKey, Deserialize, IndexKey, LogKey, Serialize, Store, ValueKey, SUBSPACE_QUOTAS,
-    SUBSPACE_VALUES,
+    AclKey, BitmapKey, Deserialize, IndexKey, LogKey, Serialize, Store, ValueKey, SUBSPACE_VALUES,
 };
 
-use super::bitmap::{next_available_index, DenseBitmap, BITS_PER_BLOCK};
+use super::{
+    bitmap::{next_available_index, DenseBitmap, BITS_PER_BLOCK},
+    quota_key,
+};
 
 #[cfg(not(feature = "test_mode"))]
 pub const ID_ASSIGNMENT_EXPIRY: u64 = 60 * 60; // seconds
@@ -222,13 +224,10 @@ impl Store {
                             return Err(crate::Error::AssertValueFailed);
                         }
                     }
-                    Operation::UpdateQuota { bytes } => {
+                    Operation::UpdateQuota { class, value } => {
                         trx.atomic_op(
-                            &KeySerializer::new(5)
-                                .write(SUBSPACE_QUOTAS)
-                                .write(account_id)
-                                .finalize(),
-                            &bytes.to_le_bytes()[..],
+                            &quota_key(account_id, *class),
+                            &value.to_le_bytes()[..],
                             MutationType::Add,
                         );
                     }
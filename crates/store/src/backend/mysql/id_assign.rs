@@ -0,0 +1,79 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use crate::{
+    backend::id_assign::{IdAssigner, IdCacheKey},
+    BitmapKey, Store,
+};
+
+impl Store {
+    pub async fn assign_document_id(
+        &self,
+        account_id: u32,
+        collection: impl Into<u8>,
+    ) -> crate::Result<u32> {
+        let key = IdCacheKey::new(account_id, collection.into());
+        for _ in 0..2 {
+            if let Some(assigner) = self.id_assigner.lock().get_mut(&key) {
+                return Ok(assigner.assign_document_id());
+            }
+            self.build_id_assigner(key).await?;
+        }
+
+        unreachable!()
+    }
+
+    pub async fn assign_change_id(&self, account_id: u32) -> crate::Result<u64> {
+        let collection = u8::MAX;
+        let key = IdCacheKey::new(account_id, collection);
+        for _ in 0..2 {
+            if let Some(assigner) = self.id_assigner.lock().get_mut(&key) {
+                return Ok(assigner.assign_change_id());
+            }
+            self.build_id_assigner(key).await?;
+        }
+
+        unreachable!()
+    }
+
+    async fn build_id_assigner(&self, key: IdCacheKey) -> crate::Result<()> {
+        // Obtain used ids
+        let trx = self.read_transaction().await?;
+        let used_ids = trx
+            .get_bitmap(BitmapKey::document_ids(key.account_id, key.collection))
+            .await?;
+        let next_change_id = trx
+            .get_last_change_id(key.account_id, key.collection)
+            .await?
+            .map(|id| id + 1)
+            .unwrap_or(0);
+
+        // Make sure id assigner was not added by another task
+        let mut id_assigner = self.id_assigner.lock();
+        if id_assigner.get_mut(&key).is_none() {
+            id_assigner.insert(key, IdAssigner::new(used_ids, next_change_id));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use lru_cache::LruCache;
+use parking_lot::Mutex;
+use sqlx::mysql::MySqlPoolOptions;
+use utils::config::Config;
+
+use crate::{
+    blob::BlobStore, Store, SUBSPACE_BITMAPS, SUBSPACE_INDEXES, SUBSPACE_LOGS, SUBSPACE_VALUES,
+};
+
+impl Store {
+    pub async fn open(config: &Config) -> crate::Result<Self> {
+        let address = config.value_require("store.db.address")?;
+        let db = Self {
+            conn_pool: MySqlPoolOptions::new()
+                .max_connections(
+                    config
+                        .property("store.db.pool.max-connections")?
+                        .unwrap_or(10),
+                )
+                .min_connections(
+                    config
+                        .property("store.db.pool.min-connections")?
+                        .unwrap_or(0),
+                )
+                .idle_timeout(config.property("store.db.pool.idle-timeout")?)
+                .connect(address)
+                .await
+                .map_err(|err| {
+                    crate::Error::InternalError(format!(
+                        "Failed to connect to MySQL database {address:?}: {err}"
+                    ))
+                })?,
+            id_assigner: Arc::new(Mutex::new(LruCache::new(
+                config.property_or_static("store.db.cache.size", "1000")?,
+            ))),
+            blob: BlobStore::new(config).await?,
+        };
+        db.create_tables().await?;
+        Ok(db)
+    }
+
+    pub(super) async fn create_tables(&self) -> crate::Result<()> {
+        for table in [SUBSPACE_VALUES, SUBSPACE_LOGS] {
+            let table = char::from(table);
+            sqlx::query(&format!(
+                "CREATE TABLE IF NOT EXISTS {table} (
+                    k VARBINARY(1024) PRIMARY KEY,
+                    v LONGBLOB NOT NULL
+                )"
+            ))
+            .execute(&self.conn_pool)
+            .await?;
+        }
+
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                k VARBINARY(3072) PRIMARY KEY
+            )",
+            char::from(SUBSPACE_INDEXES)
+        ))
+        .execute(&self.conn_pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS q (
+                k BIGINT PRIMARY KEY,
+                v BIGINT NOT NULL DEFAULT 0
+            )",
+        )
+        .execute(&self.conn_pool)
+        .await?;
+
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                z VARBINARY(1024) PRIMARY KEY,
+                a BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                b BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                c BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                d BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                e BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                f BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                g BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                h BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                i BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                j BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                k BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                l BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                m BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                n BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                o BIGINT UNSIGNED NOT NULL DEFAULT 0,
+                p BIGINT UNSIGNED NOT NULL DEFAULT 0
+            )",
+            char::from(SUBSPACE_BITMAPS)
+        ))
+        .execute(&self.conn_pool)
+        .await?;
+
+        Ok(())
+    }
+}
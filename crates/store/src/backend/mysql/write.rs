@@ -0,0 +1,307 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use sqlx::Row;
+
+use crate::{
+    write::{Batch, Operation, ValueClass},
+    AclKey, BitmapKey, IndexKey, Key, LogKey, Serialize, Store, ValueKey,
+};
+
+use super::{BITS_MASK, BITS_PER_BLOCK};
+
+const SET_QUERIES: &[&str] = &[
+    "INSERT INTO b (z, a) VALUES (?, ?) ON DUPLICATE KEY UPDATE a = a | VALUES(a)",
+    "INSERT INTO b (z, b) VALUES (?, ?) ON DUPLICATE KEY UPDATE b = b | VALUES(b)",
+    "INSERT INTO b (z, c) VALUES (?, ?) ON DUPLICATE KEY UPDATE c = c | VALUES(c)",
+    "INSERT INTO b (z, d) VALUES (?, ?) ON DUPLICATE KEY UPDATE d = d | VALUES(d)",
+    "INSERT INTO b (z, e) VALUES (?, ?) ON DUPLICATE KEY UPDATE e = e | VALUES(e)",
+    "INSERT INTO b (z, f) VALUES (?, ?) ON DUPLICATE KEY UPDATE f = f | VALUES(f)",
+    "INSERT INTO b (z, g) VALUES (?, ?) ON DUPLICATE KEY UPDATE g = g | VALUES(g)",
+    "INSERT INTO b (z, h) VALUES (?, ?) ON DUPLICATE KEY UPDATE h = h | VALUES(h)",
+    "INSERT INTO b (z, i) VALUES (?, ?) ON DUPLICATE KEY UPDATE i = i | VALUES(i)",
+    "INSERT INTO b (z, j) VALUES (?, ?) ON DUPLICATE KEY UPDATE j = j | VALUES(j)",
+    "INSERT INTO b (z, k) VALUES (?, ?) ON DUPLICATE KEY UPDATE k = k | VALUES(k)",
+    "INSERT INTO b (z, l) VALUES (?, ?) ON DUPLICATE KEY UPDATE l = l | VALUES(l)",
+    "INSERT INTO b (z, m) VALUES (?, ?) ON DUPLICATE KEY UPDATE m = m | VALUES(m)",
+    "INSERT INTO b (z, n) VALUES (?, ?) ON DUPLICATE KEY UPDATE n = n | VALUES(n)",
+    "INSERT INTO b (z, o) VALUES (?, ?) ON DUPLICATE KEY UPDATE o = o | VALUES(o)",
+    "INSERT INTO b (z, p) VALUES (?, ?) ON DUPLICATE KEY UPDATE p = p | VALUES(p)",
+];
+const CLEAR_QUERIES: &[&str] = &[
+    "UPDATE b SET a = a & ? WHERE z = ?",
+    "UPDATE b SET b = b & ? WHERE z = ?",
+    "UPDATE b SET c = c & ? WHERE z = ?",
+    "UPDATE b SET d = d & ? WHERE z = ?",
+    "UPDATE b SET e = e & ? WHERE z = ?",
+    "UPDATE b SET f = f & ? WHERE z = ?",
+    "UPDATE b SET g = g & ? WHERE z = ?",
+    "UPDATE b SET h = h & ? WHERE z = ?",
+    "UPDATE b SET i = i & ? WHERE z = ?",
+    "UPDATE b SET j = j & ? WHERE z = ?",
+    "UPDATE b SET k = k & ? WHERE z = ?",
+    "UPDATE b SET l = l & ? WHERE z = ?",
+    "UPDATE b SET m = m & ? WHERE z = ?",
+    "UPDATE b SET n = n & ? WHERE z = ?",
+    "UPDATE b SET o = o & ? WHERE z = ?",
+    "UPDATE b SET p = p & ? WHERE z = ?",
+];
+
+impl Store {
+    pub async fn write(&self, batch: Batch) -> crate::Result<()> {
+        let mut account_id = u32::MAX;
+        let mut collection = u8::MAX;
+        let mut document_id = u32::MAX;
+        let mut bitmap_block_num = 0;
+        let mut bitmap_col_num = 0;
+        let mut bitmap_value_set = 0u64;
+        let mut bitmap_value_clear = 0u64;
+        let mut trx = self.conn_pool.begin().await?;
+
+        for op in &batch.ops {
+            match op {
+                Operation::AccountId {
+                    account_id: account_id_,
+                } => {
+                    account_id = *account_id_;
+                }
+                Operation::Collection {
+                    collection: collection_,
+                } => {
+                    collection = *collection_;
+                }
+                Operation::DocumentId {
+                    document_id: document_id_,
+                } => {
+                    document_id = *document_id_;
+                    bitmap_block_num = document_id / BITS_PER_BLOCK;
+                    let index = document_id & BITS_MASK;
+                    bitmap_col_num = (index / 64) as usize;
+                    bitmap_value_set = 1u64 << (index as u64 & 63);
+                    bitmap_value_clear = !(1u64 << (index as u64 & 63));
+                }
+                Operation::Value { class, set } => {
+                    let key = match class {
+                        ValueClass::Property { field, family } => ValueKey {
+                            account_id,
+                            collection,
+                            document_id,
+                            family: *family,
+                            field: *field,
+                        }
+                        .serialize(),
+                        ValueClass::Acl { grant_account_id } => AclKey {
+                            grant_account_id: *grant_account_id,
+                            to_account_id: account_id,
+                            to_collection: collection,
+                            to_document_id: document_id,
+                        }
+                        .serialize(),
+                        ValueClass::Custom { bytes } => bytes.to_vec(),
+                    };
+
+                    if let Some(value) = set {
+                        sqlx::query(concat!(
+                            "INSERT INTO v (k, v) VALUES (?, ?) ",
+                            "ON DUPLICATE KEY UPDATE v = VALUES(v)"
+                        ))
+                        .bind(&key[..])
+                        .bind(&value[..])
+                        .execute(&mut *trx)
+                        .await?;
+                    } else {
+                        sqlx::query("DELETE FROM v WHERE k = ?")
+                            .bind(&key[..])
+                            .execute(&mut *trx)
+                            .await?;
+                    }
+                }
+                Operation::Index { field, key, set } => {
+                    let key = IndexKey {
+                        account_id,
+                        collection,
+                        document_id,
+                        field: *field,
+                        key,
+                    }
+                    .serialize();
+
+                    if *set {
+                        sqlx::query("INSERT IGNORE INTO i (k) VALUES (?)")
+                            .bind(&key[..])
+                            .execute(&mut *trx)
+                            .await?;
+                    } else {
+                        sqlx::query("DELETE FROM i WHERE k = ?")
+                            .bind(&key[..])
+                            .execute(&mut *trx)
+                            .await?;
+                    }
+                }
+                Operation::Bitmap {
+                    family,
+                    field,
+                    key,
+                    set,
+                } => {
+                    let key = BitmapKey {
+                        account_id,
+                        collection,
+                        family: *family,
+                        field: *field,
+                        block_num: bitmap_block_num,
+                        key,
+                    }
+                    .serialize();
+
+                    if *set {
+                        sqlx::query(SET_QUERIES[bitmap_col_num])
+                            .bind(&key[..])
+                            .bind(bitmap_value_set)
+                            .execute(&mut *trx)
+                            .await?;
+                    } else {
+                        sqlx::query(CLEAR_QUERIES[bitmap_col_num])
+                            .bind(bitmap_value_clear)
+                            .bind(&key[..])
+                            .execute(&mut *trx)
+                            .await?;
+                    };
+                }
+                Operation::Log {
+                    collection,
+                    change_id,
+                    set,
+                } => {
+                    let key = LogKey {
+                        account_id,
+                        collection: *collection,
+                        change_id: *change_id,
+                    }
+                    .serialize();
+
+                    sqlx::query(concat!(
+                        "INSERT INTO l (k, v) VALUES (?, ?) ",
+                        "ON DUPLICATE KEY UPDATE v = VALUES(v)"
+                    ))
+                    .bind(&key[..])
+                    .bind(&set[..])
+                    .execute(&mut *trx)
+                    .await?;
+                }
+                Operation::AssertValue {
+                    class,
+                    assert_value,
+                } => {
+                    let key = match class {
+                        ValueClass::Property { field, family } => ValueKey {
+                            account_id,
+                            collection,
+                            document_id,
+                            family: *family,
+                            field: *field,
+                        }
+                        .serialize(),
+                        ValueClass::Acl { grant_account_id } => AclKey {
+                            grant_account_id: *grant_account_id,
+                            to_account_id: account_id,
+                            to_collection: collection,
+                            to_document_id: document_id,
+                        }
+                        .serialize(),
+                        ValueClass::Custom { bytes } => bytes.to_vec(),
+                    };
+                    let matches = match sqlx::query("SELECT v FROM v WHERE k = ? FOR UPDATE")
+                        .bind(&key[..])
+                        .fetch_optional(&mut *trx)
+                        .await?
+                    {
+                        Some(row) => assert_value.matches(row.try_get::<&[u8], _>(0)?),
+                        None => assert_value.is_none(),
+                    };
+                    if !matches {
+                        return Err(crate::Error::AssertValueFailed);
+                    }
+                }
+                Operation::UpdateQuota { class, value } => {
+                    if *value >= 0 {
+                        sqlx::query(concat!(
+                            "INSERT INTO q (k, v) VALUES (?, ?) ",
+                            "ON DUPLICATE KEY UPDATE v = v + VALUES(v)"
+                        ))
+                        .bind(class.key(account_id))
+                        .bind(*value)
+                        .execute(&mut *trx)
+                        .await?;
+                    } else {
+                        sqlx::query("UPDATE q SET v = v + ? WHERE k = ?")
+                            .bind(*value)
+                            .bind(class.key(account_id))
+                            .execute(&mut *trx)
+                            .await?;
+                    }
+                }
+            }
+        }
+
+        trx.commit().await.map_err(Into::into)
+    }
+
+    #[inline(always)]
+    pub async fn set_value(&self, key: impl Key, value: impl Serialize) -> crate::Result<()> {
+        let key = key.serialize();
+        let value = value.serialize();
+
+        sqlx::query(concat!(
+            "INSERT INTO v (k, v) VALUES (?, ?) ",
+            "ON DUPLICATE KEY UPDATE v = VALUES(v)"
+        ))
+        .bind(&key[..])
+        .bind(&value[..])
+        .execute(&self.conn_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "test_mode")]
+    pub async fn destroy(&self) {
+        use crate::{
+            SUBSPACE_BITMAPS, SUBSPACE_INDEXES, SUBSPACE_LOGS, SUBSPACE_QUOTAS, SUBSPACE_VALUES,
+        };
+
+        for table in [
+            SUBSPACE_VALUES,
+            SUBSPACE_LOGS,
+            SUBSPACE_BITMAPS,
+            SUBSPACE_INDEXES,
+            SUBSPACE_QUOTAS,
+        ] {
+            sqlx::query(&format!("DROP TABLE IF EXISTS {}", char::from(table)))
+                .execute(&self.conn_pool)
+                .await
+                .unwrap();
+        }
+        self.create_tables().await.unwrap();
+    }
+}
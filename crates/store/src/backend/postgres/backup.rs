@@ -0,0 +1,177 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use futures::TryStreamExt;
+use sqlx::Row;
+use tokio::sync::mpsc;
+
+use crate::{
+    backup::BackupRecord, Store, SUBSPACE_BITMAPS, SUBSPACE_INDEXES, SUBSPACE_LOGS,
+    SUBSPACE_QUOTAS, SUBSPACE_VALUES,
+};
+
+use super::WORDS_PER_BLOCK;
+
+impl Store {
+    pub(crate) async fn export_records(&self, tx: mpsc::Sender<BackupRecord>) -> crate::Result<()> {
+        // A repeatable read transaction sees a single snapshot of all tables
+        let mut trx = self.conn_pool.begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+            .execute(&mut *trx)
+            .await?;
+
+        for (subspace, query) in [
+            (SUBSPACE_LOGS, "SELECT k, v FROM l ORDER BY k"),
+            (SUBSPACE_INDEXES, "SELECT k FROM i ORDER BY k"),
+        ] {
+            let mut rows = sqlx::query(query).fetch(&mut *trx);
+            while let Some(row) = rows.try_next().await? {
+                send(
+                    &tx,
+                    BackupRecord {
+                        subspace,
+                        key: row.try_get::<Vec<u8>, _>(0)?,
+                        value: if subspace == SUBSPACE_LOGS {
+                            row.try_get(1)?
+                        } else {
+                            vec![]
+                        },
+                    },
+                )
+                .await?;
+            }
+        }
+
+        {
+            let mut rows = sqlx::query(
+                "SELECT z, a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p FROM b ORDER BY z",
+            )
+            .fetch(&mut *trx);
+            while let Some(row) = rows.try_next().await? {
+                let mut words = [0u64; WORDS_PER_BLOCK as usize];
+                for (word_num, word) in words.iter_mut().enumerate() {
+                    *word = row.try_get::<i64, _>(word_num + 1)? as u64;
+                }
+                if words.iter().any(|word| *word != 0) {
+                    send(&tx, BackupRecord::bitmap(row.try_get(0)?, words)).await?;
+                }
+            }
+        }
+
+        {
+            let mut rows = sqlx::query("SELECT k, v FROM q ORDER BY k").fetch(&mut *trx);
+            while let Some(row) = rows.try_next().await? {
+                send(&tx, BackupRecord::quota(row.try_get(0)?, row.try_get(1)?)).await?;
+            }
+        }
+
+        {
+            let mut rows = sqlx::query("SELECT k, v FROM v ORDER BY k").fetch(&mut *trx);
+            while let Some(row) = rows.try_next().await? {
+                send(
+                    &tx,
+                    BackupRecord {
+                        subspace: SUBSPACE_VALUES,
+                        key: row.try_get(0)?,
+                        value: row.try_get(1)?,
+                    },
+                )
+                .await?;
+            }
+        }
+
+        trx.commit().await.map_err(Into::into)
+    }
+
+    pub(crate) async fn import_records(&self, records: Vec<BackupRecord>) -> crate::Result<()> {
+        let mut trx = self.conn_pool.begin().await?;
+
+        for record in records {
+            match record.subspace {
+                SUBSPACE_VALUES | SUBSPACE_LOGS => {
+                    sqlx::query(&format!(
+                        concat!(
+                            "INSERT INTO {} (k, v) VALUES ($1, $2) ",
+                            "ON CONFLICT (k) DO UPDATE SET v = EXCLUDED.v"
+                        ),
+                        char::from(record.subspace)
+                    ))
+                    .bind(&record.key[..])
+                    .bind(&record.value[..])
+                    .execute(&mut *trx)
+                    .await?;
+                }
+                SUBSPACE_INDEXES => {
+                    sqlx::query("INSERT INTO i (k) VALUES ($1) ON CONFLICT (k) DO NOTHING")
+                        .bind(&record.key[..])
+                        .execute(&mut *trx)
+                        .await?;
+                }
+                SUBSPACE_BITMAPS => {
+                    let mut query = sqlx::query(concat!(
+                        "INSERT INTO b (z, a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p) ",
+                        "VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17) ",
+                        "ON CONFLICT (z) DO UPDATE SET a = EXCLUDED.a, b = EXCLUDED.b, c = EXCLUDED.c, ",
+                        "d = EXCLUDED.d, e = EXCLUDED.e, f = EXCLUDED.f, g = EXCLUDED.g, h = EXCLUDED.h, ",
+                        "i = EXCLUDED.i, j = EXCLUDED.j, k = EXCLUDED.k, l = EXCLUDED.l, m = EXCLUDED.m, ",
+                        "n = EXCLUDED.n, o = EXCLUDED.o, p = EXCLUDED.p"
+                    ))
+                    .bind(&record.key[..]);
+                    for word in record.bitmap_words()? {
+                        query = query.bind(word as i64);
+                    }
+                    query.execute(&mut *trx).await?;
+                }
+                SUBSPACE_QUOTAS => {
+                    let (key, value) = record.quota_value()?;
+                    sqlx::query(concat!(
+                        "INSERT INTO q (k, v) VALUES ($1, $2) ",
+                        "ON CONFLICT (k) DO UPDATE SET v = EXCLUDED.v"
+                    ))
+                    .bind(key)
+                    .bind(value)
+                    .execute(&mut *trx)
+                    .await?;
+                }
+                subspace => {
+                    return Err(crate::Error::InternalError(format!(
+                        "Invalid subspace {subspace} in backup."
+                    )));
+                }
+            }
+        }
+
+        trx.commit().await?;
+
+        // Document and change ids have to be recalculated
+        self.id_assigner.lock().clear();
+
+        Ok(())
+    }
+}
+
+async fn send(tx: &mpsc::Sender<BackupRecord>, record: BackupRecord) -> crate::Result<()> {
+    tx.send(record)
+        .await
+        .map_err(|_| crate::Error::InternalError("Backup writer stopped.".to_string()))
+}
@@ -0,0 +1,465 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::ops::BitAndAssign;
+
+use futures::TryStreamExt;
+use roaring::RoaringBitmap;
+use sqlx::Row;
+
+use crate::{
+    query::Operator,
+    write::{
+        key::{DeserializeBigEndian, KeySerializer},
+        QuotaClass,
+    },
+    BitmapKey, Deserialize, IndexKey, IndexKeyPrefix, Key, LogKey, ReadTransaction, Serialize,
+    Store,
+};
+
+use super::{BITS_PER_BLOCK, WORDS_PER_BLOCK, WORD_SIZE_BITS};
+
+impl ReadTransaction<'_> {
+    #[inline(always)]
+    pub async fn get_value<U>(&self, key: impl Key) -> crate::Result<Option<U>>
+    where
+        U: Deserialize,
+    {
+        let key = key.serialize();
+        if let Some(row) = sqlx::query("SELECT v FROM v WHERE k = $1")
+            .bind(&key[..])
+            .fetch_optional(self.conn_pool)
+            .await?
+        {
+            U::deserialize(row.try_get::<&[u8], _>(0)?).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn get_bitmap_<T: AsRef<[u8]>>(
+        &self,
+        mut key: BitmapKey<T>,
+        bm: &mut RoaringBitmap,
+    ) -> crate::Result<()> {
+        let begin = (&key).serialize();
+        key.block_num = u32::MAX;
+        let key_len = begin.len();
+        let end = key.serialize();
+        let mut rows = sqlx::query(
+            "SELECT z, a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p FROM b WHERE z >= $1 AND z <= $2",
+        )
+        .bind(&begin[..])
+        .bind(&end[..])
+        .fetch(self.conn_pool);
+
+        while let Some(row) = rows.try_next().await? {
+            let key = row.try_get::<&[u8], _>(0)?;
+            if key.len() == key_len {
+                let block_num = key.deserialize_be_u32(key.len() - std::mem::size_of::<u32>())?;
+
+                for word_num in 0..WORDS_PER_BLOCK {
+                    match row.try_get::<i64, _>((word_num + 1) as usize)? as u64 {
+                        0 => (),
+                        u64::MAX => {
+                            bm.insert_range(
+                                block_num * BITS_PER_BLOCK + word_num * WORD_SIZE_BITS
+                                    ..(block_num * BITS_PER_BLOCK + word_num * WORD_SIZE_BITS)
+                                        + WORD_SIZE_BITS,
+                            );
+                        }
+                        mut word => {
+                            while word != 0 {
+                                let trailing_zeros = word.trailing_zeros();
+                                bm.insert(
+                                    block_num * BITS_PER_BLOCK
+                                        + word_num * WORD_SIZE_BITS
+                                        + trailing_zeros,
+                                );
+                                word ^= 1 << trailing_zeros;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_bitmap<T: AsRef<[u8]>>(
+        &self,
+        key: BitmapKey<T>,
+    ) -> crate::Result<Option<RoaringBitmap>> {
+        let mut bm = RoaringBitmap::new();
+        self.get_bitmap_(key, &mut bm).await?;
+        Ok(if !bm.is_empty() { Some(bm) } else { None })
+    }
+
+    pub(crate) async fn get_bitmaps_intersection<T: AsRef<[u8]>>(
+        &self,
+        keys: Vec<BitmapKey<T>>,
+    ) -> crate::Result<Option<RoaringBitmap>> {
+        let mut result: Option<RoaringBitmap> = None;
+        for key in keys {
+            if let Some(bitmap) = self.get_bitmap(key).await? {
+                if let Some(result) = &mut result {
+                    result.bitand_assign(&bitmap);
+                    if result.is_empty() {
+                        break;
+                    }
+                } else {
+                    result = Some(bitmap);
+                }
+            } else {
+                return Ok(None);
+            }
+        }
+        Ok(result)
+    }
+
+    pub(crate) async fn get_bitmaps_union<T: AsRef<[u8]>>(
+        &self,
+        keys: Vec<BitmapKey<T>>,
+    ) -> crate::Result<Option<RoaringBitmap>> {
+        let mut bm = RoaringBitmap::new();
+
+        for key in keys {
+            self.get_bitmap_(key, &mut bm).await?;
+        }
+
+        Ok(if !bm.is_empty() { Some(bm) } else { None })
+    }
+
+    pub(crate) async fn range_to_bitmap(
+        &self,
+        account_id: u32,
+        collection: u8,
+        field: u8,
+        value: Vec<u8>,
+        op: Operator,
+    ) -> crate::Result<Option<RoaringBitmap>> {
+        let k1 = KeySerializer::new(
+            std::mem::size_of::<IndexKey<&[u8]>>() + value.len() + 1 + std::mem::size_of::<u32>(),
+        )
+        .write(account_id)
+        .write(collection)
+        .write(field);
+        let k2 = KeySerializer::new(
+            std::mem::size_of::<IndexKey<&[u8]>>() + value.len() + 1 + std::mem::size_of::<u32>(),
+        )
+        .write(account_id)
+        .write(collection)
+        .write(field + matches!(op, Operator::GreaterThan | Operator::GreaterEqualThan) as u8);
+
+        let (query, begin, end) = match op {
+            Operator::LowerThan => (
+                ("SELECT k FROM i WHERE k >= $1 AND k < $2"),
+                (k1.finalize()),
+                (k2.write(&value[..]).write(0u32).finalize()),
+            ),
+            Operator::LowerEqualThan => (
+                ("SELECT k FROM i WHERE k >= $1 AND k <= $2"),
+                (k1.finalize()),
+                (k2.write(&value[..]).write(u32::MAX).finalize()),
+            ),
+            Operator::GreaterThan => (
+                ("SELECT k FROM i WHERE k > $1 AND k <= $2"),
+                (k1.write(&value[..]).write(u32::MAX).finalize()),
+                (k2.finalize()),
+            ),
+            Operator::GreaterEqualThan => (
+                ("SELECT k FROM i WHERE k >= $1 AND k <= $2"),
+                (k1.write(&value[..]).write(0u32).finalize()),
+                (k2.finalize()),
+            ),
+            Operator::Equal => (
+                ("SELECT k FROM i WHERE k >= $1 AND k <= $2"),
+                (k1.write(&value[..]).write(0u32).finalize()),
+                (k2.write(&value[..]).write(u32::MAX).finalize()),
+            ),
+        };
+
+        let mut bm = RoaringBitmap::new();
+        let mut rows = sqlx::query(query)
+            .bind(&begin[..])
+            .bind(&end[..])
+            .fetch(self.conn_pool);
+
+        if op != Operator::Equal {
+            while let Some(row) = rows.try_next().await? {
+                let key = row.try_get::<&[u8], _>(0)?;
+                bm.insert(key.deserialize_be_u32(key.len() - std::mem::size_of::<u32>())?);
+            }
+        } else {
+            let key_len = begin.len();
+            while let Some(row) = rows.try_next().await? {
+                let key = row.try_get::<&[u8], _>(0)?;
+                if key.len() == key_len {
+                    bm.insert(key.deserialize_be_u32(key.len() - std::mem::size_of::<u32>())?);
+                }
+            }
+        }
+
+        Ok(Some(bm))
+    }
+
+    pub(crate) async fn sort_index(
+        &self,
+        account_id: u32,
+        collection: u8,
+        field: u8,
+        ascending: bool,
+        mut cb: impl FnMut(&[u8], u32) -> bool,
+    ) -> crate::Result<()> {
+        let begin = IndexKeyPrefix {
+            account_id,
+            collection,
+            field,
+        }
+        .serialize();
+        let end = IndexKeyPrefix {
+            account_id,
+            collection,
+            field: field + 1,
+        }
+        .serialize();
+        let prefix_len = begin.len();
+        let mut rows = sqlx::query(if ascending {
+            "SELECT k FROM i WHERE k >= $1 AND k < $2 ORDER BY k ASC"
+        } else {
+            "SELECT k FROM i WHERE k >= $1 AND k < $2 ORDER BY k DESC"
+        })
+        .bind(&begin[..])
+        .bind(&end[..])
+        .fetch(self.conn_pool);
+
+        while let Some(row) = rows.try_next().await? {
+            let key = row.try_get::<&[u8], _>(0)?;
+            let id_pos = key.len() - std::mem::size_of::<u32>();
+            debug_assert!(key.starts_with(&begin));
+            if !cb(
+                key.get(prefix_len..id_pos).ok_or_else(|| {
+                    crate::Error::InternalError("Invalid key found in index".to_string())
+                })?,
+                key.deserialize_be_u32(id_pos)?,
+            ) {
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn iterate<T>(
+        &self,
+        mut acc: T,
+        begin: impl Key,
+        end: impl Key,
+        first: bool,
+        ascending: bool,
+        cb: impl Fn(&mut T, &[u8], &[u8]) -> crate::Result<bool> + Sync + Send + 'static,
+    ) -> crate::Result<T> {
+        let table = char::from(begin.subspace());
+        let begin = begin.serialize();
+        let end = end.serialize();
+
+        let query = match (first, ascending) {
+            (true, true) => {
+                format!("SELECT k, v FROM {table} WHERE k >= $1 AND k <= $2 ORDER BY k ASC LIMIT 1")
+            }
+            (true, false) => {
+                format!(
+                    "SELECT k, v FROM {table} WHERE k >= $1 AND k <= $2 ORDER BY k DESC LIMIT 1"
+                )
+            }
+            (false, true) => {
+                format!("SELECT k, v FROM {table} WHERE k >= $1 AND k <= $2 ORDER BY k ASC")
+            }
+            (false, false) => {
+                format!("SELECT k, v FROM {table} WHERE k >= $1 AND k <= $2 ORDER BY k DESC")
+            }
+        };
+        let mut rows = sqlx::query(&query)
+            .bind(&begin[..])
+            .bind(&end[..])
+            .fetch(self.conn_pool);
+
+        while let Some(row) = rows.try_next().await? {
+            let key = row.try_get::<&[u8], _>(0)?;
+            let value = row.try_get::<&[u8], _>(1)?;
+
+            if !cb(&mut acc, key, value)? {
+                return Ok(acc);
+            }
+        }
+
+        Ok(acc)
+    }
+
+    pub(crate) async fn get_last_change_id(
+        &self,
+        account_id: u32,
+        collection: u8,
+    ) -> crate::Result<Option<u64>> {
+        let key = LogKey {
+            account_id,
+            collection,
+            change_id: u64::MAX,
+        }
+        .serialize();
+
+        if let Some(row) = sqlx::query("SELECT k FROM l WHERE k < $1 ORDER BY k DESC LIMIT 1")
+            .bind(&key[..])
+            .fetch_optional(self.conn_pool)
+            .await?
+        {
+            let key = row.try_get::<&[u8], _>(0)?;
+            key.deserialize_be_u64(key.len() - std::mem::size_of::<u64>())
+                .map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub(crate) async fn get_quota(
+        &self,
+        account_id: u32,
+        class: QuotaClass,
+    ) -> crate::Result<Option<i64>> {
+        if let Some(row) = sqlx::query("SELECT v FROM q WHERE k = $1")
+            .bind(class.key(account_id))
+            .fetch_optional(self.conn_pool)
+            .await?
+        {
+            row.try_get::<i64, _>(0).map(Some).map_err(Into::into)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub async fn refresh_if_old(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+impl Store {
+    pub async fn read_transaction(&self) -> crate::Result<ReadTransaction<'_>> {
+        Ok(ReadTransaction {
+            conn_pool: &self.conn_pool,
+        })
+    }
+
+    #[cfg(feature = "test_mode")]
+    pub async fn assert_is_empty(&self) {
+        let mut has_errors = false;
+
+        // Values
+        for row in sqlx::query("SELECT k, v FROM v")
+            .fetch_all(&self.conn_pool)
+            .await
+            .unwrap()
+        {
+            let key = row.try_get::<&[u8], _>(0).unwrap();
+            let value = row.try_get::<&[u8], _>(1).unwrap();
+
+            if key[0..4] != u32::MAX.to_be_bytes() {
+                eprintln!("Table values is not empty: {key:?} {value:?}");
+                has_errors = true;
+            }
+        }
+
+        // Indexes
+        for row in sqlx::query("SELECT k FROM i")
+            .fetch_all(&self.conn_pool)
+            .await
+            .unwrap()
+        {
+            let key = row.try_get::<&[u8], _>(0).unwrap();
+
+            eprintln!(
+                    "Table index is not empty, account {}, collection {}, document {}, property {}, value {:?}: {:?}",
+                    u32::from_be_bytes(key[0..4].try_into().unwrap()),
+                    key[4],
+                    u32::from_be_bytes(key[key.len()-4..].try_into().unwrap()),
+                    key[5],
+                    String::from_utf8_lossy(&key[6..key.len()-4]),
+                    key
+                );
+            has_errors = true;
+        }
+
+        // Bitmaps
+        self.purge_bitmaps().await.unwrap();
+        'outer: for row in
+            sqlx::query("SELECT z, a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p FROM b")
+                .fetch_all(&self.conn_pool)
+                .await
+                .unwrap()
+        {
+            let key = row.try_get::<&[u8], _>(0).unwrap();
+            if key[0..4] != u32::MAX.to_be_bytes() {
+                for bit_pos in 1..=16 {
+                    let bit_value = row.try_get::<i64, _>(bit_pos).unwrap() as u64;
+                    if bit_value != 0 {
+                        eprintln!("Table bitmaps is not empty: {key:?} {bit_pos} {bit_value}");
+                        has_errors = true;
+
+                        continue 'outer;
+                    }
+                }
+                eprintln!("Table bitmaps failed to purge, found key: {key:?}");
+                has_errors = true;
+            }
+        }
+
+        // Quotas
+        for row in sqlx::query("SELECT k, v FROM q")
+            .fetch_all(&self.conn_pool)
+            .await
+            .unwrap()
+        {
+            let key = row.try_get::<i64, _>(0).unwrap();
+            let value = row.try_get::<i64, _>(1).unwrap();
+            if value != 0 {
+                eprintln!(
+                    "Table quota is not empty, account {}, quota: {}",
+                    key, value,
+                );
+                has_errors = true;
+            }
+        }
+
+        // Delete logs
+        sqlx::query("DELETE FROM l")
+            .execute(&self.conn_pool)
+            .await
+            .unwrap();
+
+        if has_errors {
+            panic!("Database is not empty");
+        }
+
+        self.id_assigner.lock().clear();
+    }
+}
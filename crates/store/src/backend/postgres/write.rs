@@ -0,0 +1,307 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use sqlx::Row;
+
+use crate::{
+    write::{Batch, Operation, ValueClass},
+    AclKey, BitmapKey, IndexKey, Key, LogKey, Serialize, Store, ValueKey,
+};
+
+use super::{BITS_MASK, BITS_PER_BLOCK};
+
+const SET_QUERIES: &[&str] = &[
+    "INSERT INTO b (z, a) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET a = b.a | EXCLUDED.a",
+    "INSERT INTO b (z, b) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET b = b.b | EXCLUDED.b",
+    "INSERT INTO b (z, c) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET c = b.c | EXCLUDED.c",
+    "INSERT INTO b (z, d) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET d = b.d | EXCLUDED.d",
+    "INSERT INTO b (z, e) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET e = b.e | EXCLUDED.e",
+    "INSERT INTO b (z, f) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET f = b.f | EXCLUDED.f",
+    "INSERT INTO b (z, g) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET g = b.g | EXCLUDED.g",
+    "INSERT INTO b (z, h) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET h = b.h | EXCLUDED.h",
+    "INSERT INTO b (z, i) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET i = b.i | EXCLUDED.i",
+    "INSERT INTO b (z, j) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET j = b.j | EXCLUDED.j",
+    "INSERT INTO b (z, k) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET k = b.k | EXCLUDED.k",
+    "INSERT INTO b (z, l) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET l = b.l | EXCLUDED.l",
+    "INSERT INTO b (z, m) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET m = b.m | EXCLUDED.m",
+    "INSERT INTO b (z, n) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET n = b.n | EXCLUDED.n",
+    "INSERT INTO b (z, o) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET o = b.o | EXCLUDED.o",
+    "INSERT INTO b (z, p) VALUES ($1, $2) ON CONFLICT (z) DO UPDATE SET p = b.p | EXCLUDED.p",
+];
+const CLEAR_QUERIES: &[&str] = &[
+    "UPDATE b SET a = a & $1 WHERE z = $2",
+    "UPDATE b SET b = b & $1 WHERE z = $2",
+    "UPDATE b SET c = c & $1 WHERE z = $2",
+    "UPDATE b SET d = d & $1 WHERE z = $2",
+    "UPDATE b SET e = e & $1 WHERE z = $2",
+    "UPDATE b SET f = f & $1 WHERE z = $2",
+    "UPDATE b SET g = g & $1 WHERE z = $2",
+    "UPDATE b SET h = h & $1 WHERE z = $2",
+    "UPDATE b SET i = i & $1 WHERE z = $2",
+    "UPDATE b SET j = j & $1 WHERE z = $2",
+    "UPDATE b SET k = k & $1 WHERE z = $2",
+    "UPDATE b SET l = l & $1 WHERE z = $2",
+    "UPDATE b SET m = m & $1 WHERE z = $2",
+    "UPDATE b SET n = n & $1 WHERE z = $2",
+    "UPDATE b SET o = o & $1 WHERE z = $2",
+    "UPDATE b SET p = p & $1 WHERE z = $2",
+];
+
+impl Store {
+    pub async fn write(&self, batch: Batch) -> crate::Result<()> {
+        let mut account_id = u32::MAX;
+        let mut collection = u8::MAX;
+        let mut document_id = u32::MAX;
+        let mut bitmap_block_num = 0;
+        let mut bitmap_col_num = 0;
+        let mut bitmap_value_set = 0i64;
+        let mut bitmap_value_clear = 0i64;
+        let mut trx = self.conn_pool.begin().await?;
+
+        for op in &batch.ops {
+            match op {
+                Operation::AccountId {
+                    account_id: account_id_,
+                } => {
+                    account_id = *account_id_;
+                }
+                Operation::Collection {
+                    collection: collection_,
+                } => {
+                    collection = *collection_;
+                }
+                Operation::DocumentId {
+                    document_id: document_id_,
+                } => {
+                    document_id = *document_id_;
+                    bitmap_block_num = document_id / BITS_PER_BLOCK;
+                    let index = document_id & BITS_MASK;
+                    bitmap_col_num = (index / 64) as usize;
+                    bitmap_value_set = (1u64 << (index as u64 & 63)) as i64;
+                    bitmap_value_clear = (!(1u64 << (index as u64 & 63))) as i64;
+                }
+                Operation::Value { class, set } => {
+                    let key = match class {
+                        ValueClass::Property { field, family } => ValueKey {
+                            account_id,
+                            collection,
+                            document_id,
+                            family: *family,
+                            field: *field,
+                        }
+                        .serialize(),
+                        ValueClass::Acl { grant_account_id } => AclKey {
+                            grant_account_id: *grant_account_id,
+                            to_account_id: account_id,
+                            to_collection: collection,
+                            to_document_id: document_id,
+                        }
+                        .serialize(),
+                        ValueClass::Custom { bytes } => bytes.to_vec(),
+                    };
+
+                    if let Some(value) = set {
+                        sqlx::query(concat!(
+                            "INSERT INTO v (k, v) VALUES ($1, $2) ",
+                            "ON CONFLICT (k) DO UPDATE SET v = EXCLUDED.v"
+                        ))
+                        .bind(&key[..])
+                        .bind(&value[..])
+                        .execute(&mut *trx)
+                        .await?;
+                    } else {
+                        sqlx::query("DELETE FROM v WHERE k = $1")
+                            .bind(&key[..])
+                            .execute(&mut *trx)
+                            .await?;
+                    }
+                }
+                Operation::Index { field, key, set } => {
+                    let key = IndexKey {
+                        account_id,
+                        collection,
+                        document_id,
+                        field: *field,
+                        key,
+                    }
+                    .serialize();
+
+                    if *set {
+                        sqlx::query("INSERT INTO i (k) VALUES ($1) ON CONFLICT (k) DO NOTHING")
+                            .bind(&key[..])
+                            .execute(&mut *trx)
+                            .await?;
+                    } else {
+                        sqlx::query("DELETE FROM i WHERE k = $1")
+                            .bind(&key[..])
+                            .execute(&mut *trx)
+                            .await?;
+                    }
+                }
+                Operation::Bitmap {
+                    family,
+                    field,
+                    key,
+                    set,
+                } => {
+                    let key = BitmapKey {
+                        account_id,
+                        collection,
+                        family: *family,
+                        field: *field,
+                        block_num: bitmap_block_num,
+                        key,
+                    }
+                    .serialize();
+
+                    if *set {
+                        sqlx::query(SET_QUERIES[bitmap_col_num])
+                            .bind(&key[..])
+                            .bind(bitmap_value_set)
+                            .execute(&mut *trx)
+                            .await?;
+                    } else {
+                        sqlx::query(CLEAR_QUERIES[bitmap_col_num])
+                            .bind(bitmap_value_clear)
+                            .bind(&key[..])
+                            .execute(&mut *trx)
+                            .await?;
+                    };
+                }
+                Operation::Log {
+                    collection,
+                    change_id,
+                    set,
+                } => {
+                    let key = LogKey {
+                        account_id,
+                        collection: *collection,
+                        change_id: *change_id,
+                    }
+                    .serialize();
+
+                    sqlx::query(concat!(
+                        "INSERT INTO l (k, v) VALUES ($1, $2) ",
+                        "ON CONFLICT (k) DO UPDATE SET v = EXCLUDED.v"
+                    ))
+                    .bind(&key[..])
+                    .bind(&set[..])
+                    .execute(&mut *trx)
+                    .await?;
+                }
+                Operation::AssertValue {
+                    class,
+                    assert_value,
+                } => {
+                    let key = match class {
+                        ValueClass::Property { field, family } => ValueKey {
+                            account_id,
+                            collection,
+                            document_id,
+                            family: *family,
+                            field: *field,
+                        }
+                        .serialize(),
+                        ValueClass::Acl { grant_account_id } => AclKey {
+                            grant_account_id: *grant_account_id,
+                            to_account_id: account_id,
+                            to_collection: collection,
+                            to_document_id: document_id,
+                        }
+                        .serialize(),
+                        ValueClass::Custom { bytes } => bytes.to_vec(),
+                    };
+                    let matches = match sqlx::query("SELECT v FROM v WHERE k = $1 FOR UPDATE")
+                        .bind(&key[..])
+                        .fetch_optional(&mut *trx)
+                        .await?
+                    {
+                        Some(row) => assert_value.matches(row.try_get::<&[u8], _>(0)?),
+                        None => assert_value.is_none(),
+                    };
+                    if !matches {
+                        return Err(crate::Error::AssertValueFailed);
+                    }
+                }
+                Operation::UpdateQuota { class, value } => {
+                    if *value >= 0 {
+                        sqlx::query(concat!(
+                            "INSERT INTO q (k, v) VALUES ($1, $2) ",
+                            "ON CONFLICT (k) DO UPDATE SET v = q.v + EXCLUDED.v"
+                        ))
+                        .bind(class.key(account_id))
+                        .bind(*value)
+                        .execute(&mut *trx)
+                        .await?;
+                    } else {
+                        sqlx::query("UPDATE q SET v = v + $1 WHERE k = $2")
+                            .bind(*value)
+                            .bind(class.key(account_id))
+                            .execute(&mut *trx)
+                            .await?;
+                    }
+                }
+            }
+        }
+
+        trx.commit().await.map_err(Into::into)
+    }
+
+    #[inline(always)]
+    pub async fn set_value(&self, key: impl Key, value: impl Serialize) -> crate::Result<()> {
+        let key = key.serialize();
+        let value = value.serialize();
+
+        sqlx::query(concat!(
+            "INSERT INTO v (k, v) VALUES ($1, $2) ",
+            "ON CONFLICT (k) DO UPDATE SET v = EXCLUDED.v"
+        ))
+        .bind(&key[..])
+        .bind(&value[..])
+        .execute(&self.conn_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "test_mode")]
+    pub async fn destroy(&self) {
+        use crate::{
+            SUBSPACE_BITMAPS, SUBSPACE_INDEXES, SUBSPACE_LOGS, SUBSPACE_QUOTAS, SUBSPACE_VALUES,
+        };
+
+        for table in [
+            SUBSPACE_VALUES,
+            SUBSPACE_LOGS,
+            SUBSPACE_BITMAPS,
+            SUBSPACE_INDEXES,
+            SUBSPACE_QUOTAS,
+        ] {
+            sqlx::query(&format!("DROP TABLE IF EXISTS {}", char::from(table)))
+                .execute(&self.conn_pool)
+                .await
+                .unwrap();
+        }
+        self.create_tables().await.unwrap();
+    }
+}
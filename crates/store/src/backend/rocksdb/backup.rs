@@ -0,0 +1,278 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use roaring::RoaringBitmap;
+use rocksdb::{Direction, IteratorMode, WriteBatchWithTransaction};
+use tokio::sync::mpsc;
+use utils::codec::leb128::Leb128_;
+
+use crate::{
+    backup::{BackupRecord, RESTORE_BATCH_SIZE},
+    write::key::{DeserializeBigEndian, KeySerializer},
+    Deserialize, Serialize, Store, SUBSPACE_BITMAPS, SUBSPACE_INDEXES, SUBSPACE_LOGS,
+    SUBSPACE_QUOTAS, SUBSPACE_VALUES,
+};
+
+use super::{CF_BITMAPS, CF_INDEXES, CF_LOGS, CF_VALUES};
+
+const WORDS_PER_BLOCK: u32 = 16;
+const BITS_PER_BLOCK: u32 = WORDS_PER_BLOCK * 64;
+
+impl Store {
+    // Account ids are LEB128 encoded in value and bitmap keys and bitmaps are
+    // stored as a single roaring bitmap, so records are converted to the archive
+    // encoding. Quotas are not tracked by this backend.
+    pub(crate) async fn export_records(&self, tx: mpsc::Sender<BackupRecord>) -> crate::Result<()> {
+        // All column families are read from the same snapshot
+        let snapshot = self.db.snapshot();
+
+        for (subspace, cf_name) in [
+            (SUBSPACE_LOGS, CF_LOGS),
+            (SUBSPACE_INDEXES, CF_INDEXES),
+            (SUBSPACE_BITMAPS, CF_BITMAPS),
+            (SUBSPACE_VALUES, CF_VALUES),
+        ] {
+            let mut last_key: Option<Box<[u8]>> = None;
+
+            loop {
+                // Iterators can't be held across await points, read in batches
+                let mut records = Vec::with_capacity(RESTORE_BATCH_SIZE);
+                let mut is_done = true;
+                {
+                    let cf = self.db.cf_handle(cf_name).unwrap();
+                    let mode = if let Some(last_key) = &last_key {
+                        IteratorMode::From(last_key, Direction::Forward)
+                    } else {
+                        IteratorMode::Start
+                    };
+                    for item in snapshot.iterator_cf(&cf, mode) {
+                        let (key, value) = item?;
+                        if last_key.as_deref() == Some(&key[..]) {
+                            continue;
+                        }
+
+                        match subspace {
+                            SUBSPACE_LOGS | SUBSPACE_INDEXES => {
+                                records.push(BackupRecord {
+                                    subspace,
+                                    key: key.to_vec(),
+                                    value: if subspace == SUBSPACE_LOGS {
+                                        value.to_vec()
+                                    } else {
+                                        vec![]
+                                    },
+                                });
+                            }
+                            SUBSPACE_BITMAPS => {
+                                let key_prefix = export_key(&key).ok_or_else(|| {
+                                    crate::Error::InternalError(format!(
+                                        "Invalid bitmap key {key:?}."
+                                    ))
+                                })?;
+                                if let Some(bm) = RoaringBitmap::deserialize(&value) {
+                                    export_bitmap(&key_prefix, &bm, &mut records);
+                                }
+                            }
+                            _ => {
+                                records.push(BackupRecord {
+                                    subspace,
+                                    key: export_value_key(&key).ok_or_else(|| {
+                                        crate::Error::InternalError(format!(
+                                            "Invalid value key {key:?}."
+                                        ))
+                                    })?,
+                                    value: value.to_vec(),
+                                });
+                            }
+                        }
+
+                        if records.len() >= RESTORE_BATCH_SIZE {
+                            last_key = Some(key);
+                            is_done = false;
+                            break;
+                        }
+                    }
+                }
+
+                for record in records {
+                    tx.send(record).await.map_err(|_| {
+                        crate::Error::InternalError("Backup writer stopped.".to_string())
+                    })?;
+                }
+
+                if is_done {
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn import_records(&self, records: Vec<BackupRecord>) -> crate::Result<()> {
+        let cf_values = self.db.cf_handle(CF_VALUES).unwrap();
+        let cf_bitmaps = self.db.cf_handle(CF_BITMAPS).unwrap();
+        let cf_indexes = self.db.cf_handle(CF_INDEXES).unwrap();
+        let cf_logs = self.db.cf_handle(CF_LOGS).unwrap();
+        let mut batch = WriteBatchWithTransaction::<true>::default();
+
+        for record in records {
+            match record.subspace {
+                SUBSPACE_VALUES => {
+                    batch.put_cf(&cf_values, import_value_key(&record.key)?, &record.value);
+                }
+                SUBSPACE_LOGS => {
+                    batch.put_cf(&cf_logs, &record.key, &record.value);
+                }
+                SUBSPACE_INDEXES => {
+                    batch.put_cf(&cf_indexes, &record.key, b"");
+                }
+                SUBSPACE_BITMAPS => {
+                    // Blocks of the same bitmap are merged into a single roaring bitmap
+                    let block_offset = record
+                        .key
+                        .len()
+                        .checked_sub(std::mem::size_of::<u32>())
+                        .ok_or_else(|| {
+                            crate::Error::InternalError("Invalid bitmap key in backup.".to_string())
+                        })?;
+                    let block_num = record.key.deserialize_be_u32(block_offset)?;
+                    let mut bm = RoaringBitmap::new();
+                    for (word_num, mut word) in record.bitmap_words()?.into_iter().enumerate() {
+                        while word != 0 {
+                            let trailing_zeros = word.trailing_zeros();
+                            bm.insert(
+                                block_num * BITS_PER_BLOCK
+                                    + (word_num as u32 * 64)
+                                    + trailing_zeros,
+                            );
+                            word ^= 1 << trailing_zeros;
+                        }
+                    }
+                    batch.merge_cf(
+                        &cf_bitmaps,
+                        import_key(&record.key[..block_offset])?,
+                        bm.serialize(),
+                    );
+                }
+                SUBSPACE_QUOTAS => (),
+                subspace => {
+                    return Err(crate::Error::InternalError(format!(
+                        "Invalid subspace {subspace} in backup."
+                    )));
+                }
+            }
+        }
+
+        self.db.write(batch).map_err(Into::into)
+    }
+}
+
+fn export_bitmap(key_prefix: &[u8], bm: &RoaringBitmap, records: &mut Vec<BackupRecord>) {
+    let mut block: Option<(u32, [u64; WORDS_PER_BLOCK as usize])> = None;
+    for document_id in bm {
+        let block_num = document_id / BITS_PER_BLOCK;
+        if block.as_ref().map_or(false, |(num, _)| *num != block_num) {
+            let (num, words) = block.take().unwrap();
+            records.push(BackupRecord::bitmap(
+                KeySerializer::new(key_prefix.len() + std::mem::size_of::<u32>())
+                    .write(key_prefix)
+                    .write(num)
+                    .finalize(),
+                words,
+            ));
+        }
+        let (_, words) = block.get_or_insert((block_num, [0; WORDS_PER_BLOCK as usize]));
+        let index = document_id & (BITS_PER_BLOCK - 1);
+        words[(index / 64) as usize] |= 1 << (index & 63);
+    }
+    if let Some((num, words)) = block {
+        records.push(BackupRecord::bitmap(
+            KeySerializer::new(key_prefix.len() + std::mem::size_of::<u32>())
+                .write(key_prefix)
+                .write(num)
+                .finalize(),
+            words,
+        ));
+    }
+}
+
+// Replaces the LEB128 account id that prefixes the key with a big-endian one
+fn export_key(key: &[u8]) -> Option<Vec<u8>> {
+    let (account_id, len) = u32::from_leb128_bytes(key)?;
+    Some(
+        KeySerializer::new(key.len() + std::mem::size_of::<u32>())
+            .write(account_id)
+            .write(key.get(len..)?)
+            .finalize(),
+    )
+}
+
+fn import_key(key: &[u8]) -> crate::Result<Vec<u8>> {
+    Ok(KeySerializer::new(key.len())
+        .write_leb128(key.deserialize_be_u32(0)?)
+        .write(&key[std::mem::size_of::<u32>()..])
+        .finalize())
+}
+
+fn export_value_key(key: &[u8]) -> Option<Vec<u8>> {
+    if key.starts_with(&u32::MAX.to_be_bytes()) {
+        // Custom keys use the same encoding in all backends
+        Some(key.to_vec())
+    } else if let Some((grant_account_id, len)) =
+        u32::from_leb128_bytes(key).filter(|(_, len)| key.get(*len) == Some(&u8::MAX))
+    {
+        // ACL keys
+        let key = key.get(len + 1..)?;
+        let (to_account_id, len) = u32::from_leb128_bytes(key)?;
+        let to_collection = *key.get(len)?;
+        let (to_document_id, _) = u32::from_leb128_bytes(key.get(len + 1..)?)?;
+        Some(
+            KeySerializer::new(14)
+                .write(grant_account_id)
+                .write(u8::MAX)
+                .write(to_account_id)
+                .write(to_collection)
+                .write(to_document_id)
+                .finalize(),
+        )
+    } else {
+        export_key(key)
+    }
+}
+
+fn import_value_key(key: &[u8]) -> crate::Result<Vec<u8>> {
+    if key.starts_with(&u32::MAX.to_be_bytes()) {
+        Ok(key.to_vec())
+    } else if key.len() == 14 && key[4] == u8::MAX {
+        Ok(KeySerializer::new(key.len())
+            .write_leb128(key.deserialize_be_u32(0)?)
+            .write(u8::MAX)
+            .write_leb128(key.deserialize_be_u32(5)?)
+            .write(key[9])
+            .write_leb128(key.deserialize_be_u32(10)?)
+            .finalize())
+    } else {
+        import_key(key)
+    }
+}
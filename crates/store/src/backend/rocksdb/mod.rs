@@ -25,6 +25,7 @@ use crate::{
     write::key::KeySerializer, AclKey, BitmapKey, BlobKey, IndexKey, LogKey, Serialize, ValueKey,
 };
 
+pub mod backup;
 pub mod bitmap;
 pub mod log;
 pub mod main;
@@ -0,0 +1,166 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use rusqlite::{params, TransactionBehavior};
+use tokio::sync::mpsc;
+
+use crate::{
+    backup::BackupRecord, Store, SUBSPACE_BITMAPS, SUBSPACE_INDEXES, SUBSPACE_LOGS,
+    SUBSPACE_QUOTAS, SUBSPACE_VALUES,
+};
+
+use super::WORDS_PER_BLOCK;
+
+impl Store {
+    pub(crate) async fn export_records(&self, tx: mpsc::Sender<BackupRecord>) -> crate::Result<()> {
+        let mut conn = self.conn_pool.get()?;
+        self.spawn_worker(move || {
+            // All tables are read from the same WAL snapshot
+            let trx = conn.transaction_with_behavior(TransactionBehavior::Deferred)?;
+            let send = |record: BackupRecord| {
+                tx.blocking_send(record)
+                    .map_err(|_| crate::Error::InternalError("Backup writer stopped.".to_string()))
+            };
+
+            for (subspace, query) in [
+                (SUBSPACE_LOGS, "SELECT k, v FROM l ORDER BY k"),
+                (SUBSPACE_INDEXES, "SELECT k FROM i ORDER BY k"),
+            ] {
+                let mut stmt = trx.prepare(query)?;
+                let mut rows = stmt.query([])?;
+                while let Some(row) = rows.next()? {
+                    send(BackupRecord {
+                        subspace,
+                        key: row.get(0)?,
+                        value: if subspace == SUBSPACE_LOGS {
+                            row.get(1)?
+                        } else {
+                            vec![]
+                        },
+                    })?;
+                }
+            }
+
+            let mut stmt = trx.prepare(
+                "SELECT z, a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p FROM b ORDER BY z",
+            )?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let mut words = [0u64; WORDS_PER_BLOCK as usize];
+                for (word_num, word) in words.iter_mut().enumerate() {
+                    *word = row.get::<_, i64>(word_num + 1)? as u64;
+                }
+                if words.iter().any(|word| *word != 0) {
+                    send(BackupRecord::bitmap(row.get(0)?, words))?;
+                }
+            }
+
+            let mut stmt = trx.prepare("SELECT k, v FROM q ORDER BY k")?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                send(BackupRecord::quota(row.get(0)?, row.get(1)?))?;
+            }
+
+            let mut stmt = trx.prepare("SELECT k, v FROM v ORDER BY k")?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                send(BackupRecord {
+                    subspace: SUBSPACE_VALUES,
+                    key: row.get(0)?,
+                    value: row.get(1)?,
+                })?;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub(crate) async fn import_records(&self, records: Vec<BackupRecord>) -> crate::Result<()> {
+        let mut conn = self.conn_pool.get()?;
+        self.spawn_worker(move || {
+            let trx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+            for record in records {
+                match record.subspace {
+                    SUBSPACE_VALUES | SUBSPACE_LOGS => {
+                        trx.prepare_cached(&format!(
+                            "INSERT OR REPLACE INTO {} (k, v) VALUES (?, ?)",
+                            char::from(record.subspace)
+                        ))?
+                        .execute([&record.key, &record.value])?;
+                    }
+                    SUBSPACE_INDEXES => {
+                        trx.prepare_cached("INSERT OR REPLACE INTO i (k) VALUES (?)")?
+                            .execute([&record.key])?;
+                    }
+                    SUBSPACE_BITMAPS => {
+                        let words = record.bitmap_words()?.map(|word| word as i64);
+                        trx.prepare_cached(concat!(
+                            "INSERT OR REPLACE INTO b ",
+                            "(z, a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p) ",
+                            "VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+                        ))?
+                        .execute(params![
+                            &record.key,
+                            words[0],
+                            words[1],
+                            words[2],
+                            words[3],
+                            words[4],
+                            words[5],
+                            words[6],
+                            words[7],
+                            words[8],
+                            words[9],
+                            words[10],
+                            words[11],
+                            words[12],
+                            words[13],
+                            words[14],
+                            words[15]
+                        ])?;
+                    }
+                    SUBSPACE_QUOTAS => {
+                        let (key, value) = record.quota_value()?;
+                        trx.prepare_cached("INSERT OR REPLACE INTO q (k, v) VALUES (?, ?)")?
+                            .execute([key, value])?;
+                    }
+                    subspace => {
+                        return Err(crate::Error::InternalError(format!(
+                            "Invalid subspace {subspace} in backup."
+                        )));
+                    }
+                }
+            }
+
+            trx.commit().map_err(Into::into)
+        })
+        .await?;
+
+        // Document and change ids have to be recalculated
+        self.id_assigner.lock().clear();
+
+        Ok(())
+    }
+}
@@ -21,75 +21,10 @@
  * for more details.
 */
 
-use roaring::RoaringBitmap;
-
-use crate::{BitmapKey, Store};
-
-#[derive(Clone, Copy, Hash, PartialEq, Eq)]
-pub struct IdCacheKey {
-    pub account_id: u32,
-    pub collection: u8,
-}
-
-impl IdCacheKey {
-    pub fn new(account_id: u32, collection: impl Into<u8>) -> Self {
-        Self {
-            account_id,
-            collection: collection.into(),
-        }
-    }
-}
-
-#[derive(Clone)]
-pub struct IdAssigner {
-    pub freed_document_ids: Option<RoaringBitmap>,
-    pub next_document_id: u32,
-    pub next_change_id: u64,
-}
-
-impl IdAssigner {
-    pub fn new(used_ids: Option<RoaringBitmap>, next_change_id: u64) -> Self {
-        let mut assigner = IdAssigner {
-            freed_document_ids: None,
-            next_document_id: 0,
-            next_change_id,
-        };
-        if let Some(used_ids) = used_ids {
-            if let Some(max) = used_ids.max() {
-                assigner.next_document_id = max + 1;
-                let mut freed_ids =
-                    RoaringBitmap::from_sorted_iter(0..assigner.next_document_id).unwrap();
-                freed_ids ^= used_ids;
-                if !freed_ids.is_empty() {
-                    assigner.freed_document_ids = Some(freed_ids);
-                }
-            }
-        }
-
-        assigner
-    }
-
-    pub fn assign_document_id(&mut self) -> u32 {
-        if let Some(freed_ids) = &mut self.freed_document_ids {
-            let id = freed_ids.min().unwrap();
-            freed_ids.remove(id);
-            if freed_ids.is_empty() {
-                self.freed_document_ids = None;
-            }
-            id
-        } else {
-            let id = self.next_document_id;
-            self.next_document_id += 1;
-            id
-        }
-    }
-
-    pub fn assign_change_id(&mut self) -> u64 {
-        let id = self.next_change_id;
-        self.next_change_id += 1;
-        id
-    }
-}
+use crate::{
+    backend::id_assign::{IdAssigner, IdCacheKey},
+    BitmapKey, Store,
+};
 
 impl Store {
     pub async fn assign_document_id(
@@ -145,30 +80,3 @@ impl Store {
         .await
     }
 }
-
-#[cfg(test)]
-mod tests {
-    use roaring::RoaringBitmap;
-
-    use super::IdAssigner;
-
-    #[test]
-    fn id_assigner() {
-        let mut assigner = IdAssigner::new(None, 0);
-        assert_eq!(assigner.assign_document_id(), 0);
-        assert_eq!(assigner.assign_document_id(), 1);
-        assert_eq!(assigner.assign_document_id(), 2);
-
-        let mut assigner = IdAssigner::new(
-            RoaringBitmap::from_sorted_iter([0, 2, 4, 6])
-                .unwrap()
-                .into(),
-            0,
-        );
-        assert_eq!(assigner.assign_document_id(), 1);
-        assert_eq!(assigner.assign_document_id(), 3);
-        assert_eq!(assigner.assign_document_id(), 5);
-        assert_eq!(assigner.assign_document_id(), 7);
-        assert_eq!(assigner.assign_document_id(), 8);
-    }
-}
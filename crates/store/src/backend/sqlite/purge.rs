@@ -22,8 +22,8 @@
 */
 
 use crate::{
-    write::key::KeySerializer, Store, SUBSPACE_BITMAPS, SUBSPACE_INDEXES, SUBSPACE_LOGS,
-    SUBSPACE_VALUES,
+    write::{key::KeySerializer, QuotaClass},
+    Store, SUBSPACE_BITMAPS, SUBSPACE_INDEXES, SUBSPACE_LOGS, SUBSPACE_VALUES,
 };
 
 impl Store {
@@ -81,8 +81,10 @@ impl Store {
                 ))?
                 .execute([&from_key, &to_key])?;
             }
-            conn.prepare_cached("DELETE FROM q WHERE k = ?")?
-                .execute([account_id as i64])?;
+            for class in [QuotaClass::Octets, QuotaClass::Messages] {
+                conn.prepare_cached("DELETE FROM q WHERE k = ?")?
+                    .execute([class.key(account_id)])?;
+            }
 
             Ok(())
         })
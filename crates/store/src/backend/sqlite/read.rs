@@ -28,7 +28,10 @@ use rusqlite::OptionalExtension;
 
 use crate::{
     query::Operator,
-    write::key::{DeserializeBigEndian, KeySerializer},
+    write::{
+        key::{DeserializeBigEndian, KeySerializer},
+        QuotaClass,
+    },
     BitmapKey, Deserialize, IndexKey, IndexKeyPrefix, Key, LogKey, ReadTransaction, Serialize,
     Store,
 };
@@ -335,14 +338,18 @@ impl ReadTransaction<'_> {
     }
 
     #[maybe_async::maybe_async]
-    pub(crate) async fn get_quota(&self, account_id: u32) -> crate::Result<i64> {
+    pub(crate) async fn get_quota(
+        &self,
+        account_id: u32,
+        class: QuotaClass,
+    ) -> crate::Result<Option<i64>> {
         match self
             .conn
             .prepare_cached("SELECT v FROM q WHERE k = ?")?
-            .query_row([account_id as i64], |row| row.get::<_, i64>(0))
+            .query_row([class.key(account_id)], |row| row.get::<_, i64>(0))
         {
-            Ok(value) => Ok(value),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(0),
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
@@ -245,16 +245,16 @@ impl Store {
                             return Err(crate::Error::AssertValueFailed);
                         }
                     }
-                    Operation::UpdateQuota { bytes } => {
-                        if *bytes >= 0 {
+                    Operation::UpdateQuota { class, value } => {
+                        if *value >= 0 {
                             trx.prepare_cached(concat!(
                                 "INSERT INTO q (k, v) VALUES (?, ?) ",
                                 "ON CONFLICT(k) DO UPDATE SET v = v + excluded.v"
                             ))?
-                            .execute(params![account_id, *bytes])?;
+                            .execute(params![class.key(account_id), *value])?;
                         } else {
                             trx.prepare_cached("UPDATE q SET v = v + ? WHERE k = ?")?
-                                .execute(params![*bytes, account_id])?;
+                                .execute(params![*value, class.key(account_id)])?;
                         }
                     }
                 }
@@ -0,0 +1,869 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{io::SeekFrom, path::Path};
+
+use ahash::AHashMap;
+use roaring::RoaringBitmap;
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter},
+    sync::mpsc,
+};
+
+use crate::{
+    write::{
+        key::{DeserializeBigEndian, KeySerializer},
+        now,
+    },
+    BlobKind, Store, BM_DOCUMENT_IDS, SUBSPACE_BITMAPS, SUBSPACE_INDEXES, SUBSPACE_LOGS,
+    SUBSPACE_QUOTAS, SUBSPACE_VALUES,
+};
+
+const MAGIC: &[u8] = b"STWBAK";
+const VERSION: u8 = 3;
+// Magic, version and the id of the base archive
+const HEADER_LEN: u64 = (MAGIC.len() + 1 + std::mem::size_of::<u64>()) as u64;
+
+pub(crate) const BLOB_RECORD: u8 = b'o';
+const MANIFEST_RECORD: u8 = b'm';
+
+pub(crate) const RESTORE_BATCH_SIZE: usize = 1000;
+const NO_CHANGE_ID: u64 = u64::MAX;
+const BITS_PER_BLOCK: u32 = 16 * 64;
+
+// Maildir blobs always belong to the email collection
+const MAILDIR_COLLECTION: u8 = 0;
+
+// Store records use a backend-independent encoding: keys never carry the
+// subspace prefix, bitmap values are 16 little-endian u64 words per block
+// and quota values are little-endian i64 counters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupRecord {
+    pub subspace: u8,
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+impl BackupRecord {
+    pub fn bitmap(key: Vec<u8>, words: [u64; 16]) -> Self {
+        let mut value = Vec::with_capacity(words.len() * std::mem::size_of::<u64>());
+        for word in words {
+            value.extend_from_slice(&word.to_le_bytes());
+        }
+        BackupRecord {
+            subspace: SUBSPACE_BITMAPS,
+            key,
+            value,
+        }
+    }
+
+    // Quota keys are the account id, followed by a class byte for message counts
+    pub fn quota(key: i64, value: i64) -> Self {
+        let mut bytes = (key as u32).to_be_bytes().to_vec();
+        if key >> 32 != 0 {
+            bytes.push((key >> 32) as u8);
+        }
+        BackupRecord {
+            subspace: SUBSPACE_QUOTAS,
+            key: bytes,
+            value: value.to_le_bytes().to_vec(),
+        }
+    }
+
+    pub fn bitmap_words(&self) -> crate::Result<[u64; 16]> {
+        let mut words = [0u64; 16];
+        if self.value.len() == words.len() * std::mem::size_of::<u64>() {
+            for (word, bytes) in words.iter_mut().zip(self.value.chunks_exact(8)) {
+                *word = u64::from_le_bytes(bytes.try_into().unwrap());
+            }
+            Ok(words)
+        } else {
+            Err(crate::Error::InternalError(
+                "Invalid bitmap block in backup.".to_string(),
+            ))
+        }
+    }
+
+    pub fn quota_value(&self) -> crate::Result<(i64, i64)> {
+        match (
+            self.key.as_slice().deserialize_be_u32(0),
+            self.key.get(4..),
+            <[u8; 8]>::try_from(self.value.as_slice()),
+        ) {
+            (Ok(account_id), Some(class @ ([] | [_])), Ok(value)) => Ok((
+                ((class.first().copied().unwrap_or(0) as i64) << 32) | account_id as i64,
+                i64::from_le_bytes(value),
+            )),
+            _ => Err(crate::Error::InternalError(
+                "Invalid quota record in backup.".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackupManifest {
+    pub id: u64,
+    pub base_id: u64,
+    pub created: u64,
+    pub digest: [u8; 32],
+    pub accounts: AHashMap<u32, AccountState>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountState {
+    pub change_id: u64,
+    pub included: bool,
+    pub digest: [u8; 32],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RecordScope {
+    Account(u32),
+    Acl(u32),
+    Global,
+}
+
+impl Store {
+    /// Writes a snapshot of the store and blob store to `path`. When `previous` is
+    /// provided, only accounts whose change log advanced since that archive was
+    /// taken are written, together with all server-wide records.
+    ///
+    /// Temporary blobs are not archived, they are short-lived uploads that are not
+    /// referenced by any record and expire on their own.
+    pub async fn backup(
+        &self,
+        path: impl AsRef<Path>,
+        previous: Option<&Path>,
+    ) -> crate::Result<BackupManifest> {
+        let base = if let Some(previous) = previous {
+            ArchiveReader::open(previous).await?.manifest.into()
+        } else {
+            None
+        };
+        let (manifest, file) = self
+            .export(File::create(path.as_ref()).await?, base)
+            .await?;
+        file.sync_all().await?;
+
+        Ok(manifest)
+    }
+
+    /// Writes a snapshot in the archive format to `writer`, which can be a file or
+    /// a stream to a server running another backend. Records are written while they
+    /// are read from the store and the manifest is written last.
+    pub async fn export<W: AsyncWrite + Unpin>(
+        &self,
+        writer: W,
+        base: Option<BackupManifest>,
+    ) -> crate::Result<(BackupManifest, W)> {
+        let base_id = base.as_ref().map_or(0, |base: &BackupManifest| base.id);
+        let mut backup = ArchiveBuilder {
+            manifest: BackupManifest {
+                id: rand::random::<u64>().max(1),
+                base_id,
+                created: now(),
+                digest: [0; 32],
+                accounts: AHashMap::new(),
+            },
+            base,
+            writer: ArchiveWriter::new(writer, base_id).await?,
+            pending_logs: Vec::new(),
+            digests: RecordDigest::default(),
+            documents: AHashMap::new(),
+        };
+
+        // Stream the store snapshot into the archive
+        let (tx, mut rx) = mpsc::channel::<BackupRecord>(RESTORE_BATCH_SIZE);
+        let (export_result, write_result) = tokio::join!(self.export_records(tx), async {
+            while let Some(record) = rx.recv().await {
+                backup.add(record).await?;
+            }
+            backup.flush_logs().await
+        });
+        write_result?;
+        export_result?;
+
+        // The blob store has no snapshots, so blobs are reconciled against the document ids
+        // in the snapshot: blobs of documents created afterwards are skipped and documents
+        // deleted before their blob was copied are reported.
+        let mut copied: AHashMap<(u32, u8), RoaringBitmap> = AHashMap::new();
+        for kind in self.list_blobs().await? {
+            let document = match &kind {
+                BlobKind::LinkedMaildir {
+                    account_id,
+                    document_id,
+                } => (*account_id, MAILDIR_COLLECTION, *document_id),
+                BlobKind::Linked {
+                    account_id,
+                    collection,
+                    document_id,
+                } => (*account_id, *collection, *document_id),
+                BlobKind::Temporary { .. } => continue,
+            };
+            if !backup
+                .documents
+                .get(&(document.0, document.1))
+                .map_or(false, |document_ids| document_ids.contains(document.2))
+            {
+                tracing::debug!("Skipping blob {kind:?} created after the backup snapshot.");
+                continue;
+            }
+
+            let key = serialize_blob_kind(&kind);
+            if !backup.is_included(backup.manifest.scope(BLOB_RECORD, &key)) {
+                continue;
+            }
+            if let Some(bytes) = self.get_blob(&kind, 0..u32::MAX).await? {
+                backup.writer.write(BLOB_RECORD, &key, &bytes).await?;
+                copied
+                    .entry((document.0, document.1))
+                    .or_default()
+                    .insert(document.2);
+            }
+        }
+
+        // Every email has a blob, any email left without one was deleted during the backup
+        let mut num_missing = 0;
+        for ((account_id, collection), document_ids) in &backup.documents {
+            if *collection == MAILDIR_COLLECTION
+                && backup.is_included(RecordScope::Account(*account_id))
+            {
+                num_missing += copied
+                    .get(&(*account_id, *collection))
+                    .map_or(document_ids.len(), |copied| {
+                        document_ids.difference_len(copied)
+                    });
+            }
+        }
+        if num_missing > 0 {
+            tracing::warn!(
+                "{num_missing} emails were deleted during the backup before their blobs could be copied."
+            );
+        }
+
+        backup.digests.finalize(&mut backup.manifest);
+        let writer = backup.writer.finish(&backup.manifest).await?;
+
+        Ok((backup.manifest, writer))
+    }
+
+    /// Restores a full archive followed by any number of incremental archives,
+    /// in the order they were taken. Without an account id the whole server is
+    /// rebuilt, which expects an empty store. With an account id only that
+    /// account is replaced.
+    pub async fn restore(
+        &self,
+        paths: &[impl AsRef<Path>],
+        account_id: Option<u32>,
+    ) -> crate::Result<()> {
+        // Validate the archive chain
+        let mut manifests: Vec<BackupManifest> = Vec::with_capacity(paths.len());
+        for path in paths {
+            let manifest = ArchiveReader::open(path.as_ref()).await?.manifest;
+            let expected_base = manifests.last().map_or(0, |m| m.id);
+            if manifest.base_id != expected_base {
+                return Err(crate::Error::InternalError(format!(
+                    "Backup archive {} does not follow the previous archive.",
+                    path.as_ref().display()
+                )));
+            }
+            manifests.push(manifest);
+        }
+        let last = if let Some(last) = manifests.len().checked_sub(1) {
+            last
+        } else {
+            return Ok(());
+        };
+
+        // Each account is restored from the most recent archive that holds its data
+        let mut sources = AHashMap::new();
+        for account_id in manifests[last].accounts.keys() {
+            if let Some(pos) = manifests.iter().rposition(|m| {
+                m.accounts
+                    .get(account_id)
+                    .map_or(false, |state| state.included)
+            }) {
+                sources.insert(*account_id, pos);
+            }
+        }
+        if let Some(account_id) = account_id {
+            if !sources.contains_key(&account_id) {
+                return Err(crate::Error::InternalError(format!(
+                    "Account {account_id} not found in backup."
+                )));
+            }
+            self.purge_account(account_id).await?;
+            self.delete_account_blobs(account_id).await?;
+        }
+
+        for (pos, path) in paths.iter().enumerate() {
+            if pos != last && !sources.values().any(|source| *source == pos) {
+                continue;
+            }
+
+            let manifest = &manifests[pos];
+            let mut reader = ArchiveReader::open(path.as_ref()).await?;
+            let mut batch = Vec::with_capacity(RESTORE_BATCH_SIZE);
+            while let Some(record) = reader.next().await? {
+                let restore = match (manifest.scope(record.subspace, &record.key), account_id) {
+                    (RecordScope::Account(id), filter) => {
+                        filter.map_or(true, |filter| filter == id)
+                            && sources
+                                .get(&id)
+                                .map_or(pos == last, |source| *source == pos)
+                    }
+                    (RecordScope::Acl(id), Some(filter)) => id == filter && pos == last,
+                    (RecordScope::Global, Some(_)) => false,
+                    (_, None) => pos == last,
+                };
+                if !restore {
+                    continue;
+                }
+
+                if record.subspace == BLOB_RECORD {
+                    self.put_blob(&deserialize_blob_kind(&record.key)?, &record.value)
+                        .await?;
+                } else {
+                    batch.push(record);
+                    if batch.len() == RESTORE_BATCH_SIZE {
+                        self.import_records(std::mem::take(&mut batch)).await?;
+                    }
+                }
+            }
+            if !batch.is_empty() {
+                self.import_records(batch).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct ArchiveBuilder<W: AsyncWrite + Unpin> {
+    manifest: BackupManifest,
+    base: Option<BackupManifest>,
+    writer: ArchiveWriter<W>,
+    pending_logs: Vec<BackupRecord>,
+    digests: RecordDigest,
+    documents: AHashMap<(u32, u8), RoaringBitmap>,
+}
+
+// Digests cover every record of an account, whether it was written to the archive or not,
+// so the latest manifest describes the complete state of the store.
+#[derive(Default)]
+pub(crate) struct RecordDigest {
+    accounts: AHashMap<u32, blake3::Hasher>,
+    global: blake3::Hasher,
+}
+
+impl<W: AsyncWrite + Unpin> ArchiveBuilder<W> {
+    async fn add(&mut self, record: BackupRecord) -> crate::Result<()> {
+        // Change logs are exported first, an account is written when its change id differs
+        // from the base archive.
+        if record.subspace == SUBSPACE_LOGS {
+            let account_id = account_prefix(&record.key, 0).ok_or_else(|| {
+                crate::Error::InternalError("Invalid change log key.".to_string())
+            })?;
+            let change_id = (&record.key[..])
+                .deserialize_be_u64(record.key.len().saturating_sub(std::mem::size_of::<u64>()))?;
+            if self
+                .pending_logs
+                .first()
+                .map_or(false, |pending| !pending.key.starts_with(&record.key[..4]))
+            {
+                self.flush_logs().await?;
+            }
+            let state = self.account(account_id);
+            if state.change_id == NO_CHANGE_ID || state.change_id < change_id {
+                state.change_id = change_id;
+            }
+            self.digests.add(RecordScope::Account(account_id), &record);
+            self.pending_logs.push(record);
+            return Ok(());
+        } else if !self.pending_logs.is_empty() {
+            self.flush_logs().await?;
+        }
+
+        if matches!(
+            record.subspace,
+            SUBSPACE_INDEXES | SUBSPACE_BITMAPS | SUBSPACE_QUOTAS
+        ) {
+            if let Some(account_id) = account_prefix(&record.key, 0) {
+                if !self.manifest.accounts.contains_key(&account_id) {
+                    let included = self.is_changed(account_id, NO_CHANGE_ID);
+                    self.manifest.accounts.insert(
+                        account_id,
+                        AccountState {
+                            change_id: NO_CHANGE_ID,
+                            included,
+                            digest: [0; 32],
+                        },
+                    );
+                }
+            }
+        }
+
+        // Document ids in the snapshot are kept to reconcile blobs against
+        if record.subspace == SUBSPACE_BITMAPS
+            && record.key.len() == 11
+            && record.key[5] == BM_DOCUMENT_IDS
+            && record.key[6] == u8::MAX
+        {
+            let account_id = record.key.deserialize_be_u32(0)?;
+            let block_num = record.key.deserialize_be_u32(7)?;
+            let document_ids = self
+                .documents
+                .entry((account_id, record.key[4]))
+                .or_default();
+            for (word_num, mut word) in record.bitmap_words()?.into_iter().enumerate() {
+                while word != 0 {
+                    let trailing_zeros = word.trailing_zeros();
+                    document_ids.insert(
+                        block_num * BITS_PER_BLOCK + (word_num as u32 * 64) + trailing_zeros,
+                    );
+                    word ^= 1 << trailing_zeros;
+                }
+            }
+        }
+
+        let scope = self.manifest.scope(record.subspace, &record.key);
+        self.digests.add(scope, &record);
+        if self.is_included(scope) {
+            self.writer
+                .write(record.subspace, &record.key, &record.value)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn flush_logs(&mut self) -> crate::Result<()> {
+        if let Some(account_id) = self
+            .pending_logs
+            .first()
+            .and_then(|record| account_prefix(&record.key, 0))
+        {
+            let change_id = self.account(account_id).change_id;
+            let included = self.is_changed(account_id, change_id);
+            self.account(account_id).included = included;
+            for record in std::mem::take(&mut self.pending_logs) {
+                if included {
+                    self.writer
+                        .write(record.subspace, &record.key, &record.value)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn account(&mut self, account_id: u32) -> &mut AccountState {
+        self.manifest
+            .accounts
+            .entry(account_id)
+            .or_insert(AccountState {
+                change_id: NO_CHANGE_ID,
+                included: true,
+                digest: [0; 32],
+            })
+    }
+
+    fn is_changed(&self, account_id: u32, change_id: u64) -> bool {
+        change_id == NO_CHANGE_ID
+            || self.base.as_ref().map_or(true, |base| {
+                base.accounts
+                    .get(&account_id)
+                    .map_or(true, |state| state.change_id != change_id)
+            })
+    }
+
+    fn is_included(&self, scope: RecordScope) -> bool {
+        match scope {
+            RecordScope::Account(account_id) => self
+                .manifest
+                .accounts
+                .get(&account_id)
+                .map_or(true, |state| state.included),
+            RecordScope::Acl(_) | RecordScope::Global => true,
+        }
+    }
+}
+
+impl RecordDigest {
+    pub(crate) fn add(&mut self, scope: RecordScope, record: &BackupRecord) {
+        // FoundationDB change id counters are not present in other backends
+        if record.subspace == SUBSPACE_VALUES && record.key.len() == std::mem::size_of::<u32>() {
+            return;
+        }
+
+        let hasher = match scope {
+            RecordScope::Account(account_id) => self.accounts.entry(account_id).or_default(),
+            RecordScope::Acl(_) | RecordScope::Global => &mut self.global,
+        };
+        hasher.update(&[record.subspace]);
+        hasher.update(&(record.key.len() as u32).to_be_bytes());
+        hasher.update(&record.key);
+        hasher.update(&(record.value.len() as u32).to_be_bytes());
+        hasher.update(&record.value);
+    }
+
+    pub(crate) fn finalize(&self, manifest: &mut BackupManifest) {
+        for (account_id, state) in manifest.accounts.iter_mut() {
+            state.digest = self.account_digest(*account_id);
+        }
+        manifest.digest = self.global_digest();
+    }
+
+    pub(crate) fn account_digest(&self, account_id: u32) -> [u8; 32] {
+        self.accounts
+            .get(&account_id)
+            .map_or([0; 32], |hasher| *hasher.finalize().as_bytes())
+    }
+
+    pub(crate) fn global_digest(&self) -> [u8; 32] {
+        *self.global.finalize().as_bytes()
+    }
+}
+
+impl BackupManifest {
+    pub(crate) fn scope(&self, subspace: u8, key: &[u8]) -> RecordScope {
+        match subspace {
+            SUBSPACE_LOGS | SUBSPACE_INDEXES | SUBSPACE_BITMAPS | SUBSPACE_QUOTAS => {
+                account_prefix(key, 0).map_or(RecordScope::Global, RecordScope::Account)
+            }
+            BLOB_RECORD => account_prefix(key, 1)
+                .filter(|account_id| self.accounts.contains_key(account_id))
+                .map_or(RecordScope::Global, RecordScope::Account),
+            _ => match account_prefix(key, 0) {
+                // ACL keys are prefixed by the grantee and are kept with the server-wide records
+                Some(account_id) if key.len() == 14 && key[4] == u8::MAX => {
+                    RecordScope::Acl(account_id)
+                }
+                Some(account_id) if self.accounts.contains_key(&account_id) => {
+                    RecordScope::Account(account_id)
+                }
+                _ => RecordScope::Global,
+            },
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut serializer = KeySerializer::new(
+            (std::mem::size_of::<u64>() * 3)
+                + 32
+                + std::mem::size_of::<u32>()
+                + (self.accounts.len() * 45),
+        )
+        .write(self.id)
+        .write(self.base_id)
+        .write(self.created)
+        .write(&self.digest[..])
+        .write(self.accounts.len() as u32);
+        for (account_id, state) in &self.accounts {
+            serializer = serializer
+                .write(*account_id)
+                .write(state.change_id)
+                .write(u8::from(state.included))
+                .write(&state.digest[..]);
+        }
+        serializer.finalize()
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> crate::Result<Self> {
+        let mut manifest = BackupManifest {
+            id: bytes.deserialize_be_u64(0)?,
+            base_id: bytes.deserialize_be_u64(8)?,
+            created: bytes.deserialize_be_u64(16)?,
+            digest: read_digest(bytes, 24)?,
+            accounts: AHashMap::new(),
+        };
+        let num_accounts = bytes.deserialize_be_u32(56)? as usize;
+        for pos in 0..num_accounts {
+            let offset = 60 + (pos * 45);
+            manifest.accounts.insert(
+                bytes.deserialize_be_u32(offset)?,
+                AccountState {
+                    change_id: bytes.deserialize_be_u64(offset + 4)?,
+                    included: *bytes.get(offset + 12).ok_or_else(|| {
+                        crate::Error::InternalError("Corrupted backup manifest.".to_string())
+                    })? != 0,
+                    digest: read_digest(bytes, offset + 13)?,
+                },
+            );
+        }
+        Ok(manifest)
+    }
+}
+
+struct ArchiveWriter<W: AsyncWrite + Unpin> {
+    file: BufWriter<W>,
+    offset: u64,
+}
+
+impl<W: AsyncWrite + Unpin> ArchiveWriter<W> {
+    async fn new(writer: W, base_id: u64) -> crate::Result<Self> {
+        let mut file = BufWriter::new(writer);
+        file.write_all(MAGIC).await?;
+        file.write_u8(VERSION).await?;
+        file.write_u64(base_id).await?;
+        Ok(ArchiveWriter {
+            file,
+            offset: HEADER_LEN,
+        })
+    }
+
+    async fn write(&mut self, tag: u8, key: &[u8], value: &[u8]) -> crate::Result<()> {
+        let key_len = u32::try_from(key.len())
+            .map_err(|_| crate::Error::InternalError("Backup key too large.".to_string()))?;
+        let value_len = u32::try_from(value.len())
+            .map_err(|_| crate::Error::InternalError("Backup value too large.".to_string()))?;
+        self.file.write_u8(tag).await?;
+        self.file.write_u32(key_len).await?;
+        self.file.write_all(key).await?;
+        self.file.write_u32(value_len).await?;
+        self.file.write_all(value).await?;
+        self.offset += 9 + key.len() as u64 + value.len() as u64;
+        Ok(())
+    }
+
+    async fn finish(mut self, manifest: &BackupManifest) -> crate::Result<W> {
+        let offset = self.offset;
+        self.write(MANIFEST_RECORD, &[], &manifest.serialize())
+            .await?;
+        self.file.write_u64(offset).await?;
+        self.file.flush().await?;
+        Ok(self.file.into_inner())
+    }
+}
+
+pub(crate) struct ArchiveReader {
+    file: BufReader<File>,
+    pub manifest: BackupManifest,
+    pub offset: u64,
+}
+
+impl ArchiveReader {
+    pub async fn open(path: &Path) -> crate::Result<Self> {
+        let mut file = File::open(path).await?;
+        let mut header = [0u8; HEADER_LEN as usize];
+        file.read_exact(&mut header).await?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(crate::Error::InternalError(format!(
+                "{} is not a backup archive.",
+                path.display()
+            )));
+        } else if header[MAGIC.len()] != VERSION {
+            return Err(crate::Error::InternalError(format!(
+                "Unsupported backup archive version {}.",
+                header[MAGIC.len()]
+            )));
+        }
+
+        // The manifest offset is stored at the end of the archive
+        file.seek(SeekFrom::End(-(std::mem::size_of::<u64>() as i64)))
+            .await?;
+        let offset = file.read_u64().await?;
+        let mut file = BufReader::new(file);
+        file.seek(SeekFrom::Start(offset)).await?;
+        let record = read_record(&mut file).await?;
+        if record.subspace != MANIFEST_RECORD {
+            return Err(crate::Error::InternalError(format!(
+                "Backup archive {} is truncated.",
+                path.display()
+            )));
+        }
+        let manifest = BackupManifest::deserialize(&record.value)?;
+        if manifest.base_id != read_base_id(&header) {
+            return Err(crate::Error::InternalError(format!(
+                "Backup archive {} is corrupted.",
+                path.display()
+            )));
+        }
+        file.seek(SeekFrom::Start(HEADER_LEN)).await?;
+
+        Ok(ArchiveReader {
+            file,
+            manifest,
+            offset: HEADER_LEN,
+        })
+    }
+
+    pub async fn seek(&mut self, offset: u64) -> crate::Result<()> {
+        self.file
+            .seek(SeekFrom::Start(offset.max(HEADER_LEN)))
+            .await?;
+        self.offset = offset.max(HEADER_LEN);
+        Ok(())
+    }
+
+    pub async fn next(&mut self) -> crate::Result<Option<BackupRecord>> {
+        let record = read_record(&mut self.file).await?;
+        Ok(if record.subspace != MANIFEST_RECORD {
+            self.offset += 9 + record.key.len() as u64 + record.value.len() as u64;
+            Some(record)
+        } else {
+            None
+        })
+    }
+}
+
+// Reads an archive sequentially, the manifest is only available once all records were read
+pub(crate) struct ArchiveStream<R: AsyncRead + Unpin> {
+    reader: BufReader<R>,
+    pub base_id: u64,
+    pub manifest: Option<BackupManifest>,
+}
+
+impl<R: AsyncRead + Unpin> ArchiveStream<R> {
+    pub async fn open(reader: R) -> crate::Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let mut header = [0u8; HEADER_LEN as usize];
+        reader.read_exact(&mut header).await?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(crate::Error::InternalError(
+                "Invalid backup stream.".to_string(),
+            ));
+        } else if header[MAGIC.len()] != VERSION {
+            return Err(crate::Error::InternalError(format!(
+                "Unsupported backup stream version {}.",
+                header[MAGIC.len()]
+            )));
+        }
+
+        Ok(ArchiveStream {
+            reader,
+            base_id: read_base_id(&header),
+            manifest: None,
+        })
+    }
+
+    pub async fn next(&mut self) -> crate::Result<Option<BackupRecord>> {
+        if self.manifest.is_some() {
+            return Ok(None);
+        }
+        let record = read_record(&mut self.reader).await?;
+        if record.subspace != MANIFEST_RECORD {
+            Ok(Some(record))
+        } else {
+            let manifest = BackupManifest::deserialize(&record.value)?;
+            if manifest.base_id != self.base_id {
+                return Err(crate::Error::InternalError(
+                    "Backup stream is corrupted.".to_string(),
+                ));
+            }
+            self.manifest = manifest.into();
+            Ok(None)
+        }
+    }
+}
+
+async fn read_record<R: AsyncRead + Unpin>(file: &mut BufReader<R>) -> crate::Result<BackupRecord> {
+    let subspace = file.read_u8().await?;
+    let mut key = vec![0u8; file.read_u32().await? as usize];
+    file.read_exact(&mut key).await?;
+    let mut value = vec![0u8; file.read_u32().await? as usize];
+    file.read_exact(&mut value).await?;
+    Ok(BackupRecord {
+        subspace,
+        key,
+        value,
+    })
+}
+
+fn read_base_id(header: &[u8]) -> u64 {
+    header
+        .get(MAGIC.len() + 1..)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map_or(0, u64::from_be_bytes)
+}
+
+fn read_digest(bytes: &[u8], offset: usize) -> crate::Result<[u8; 32]> {
+    bytes
+        .get(offset..offset + 32)
+        .and_then(|digest| digest.try_into().ok())
+        .ok_or_else(|| crate::Error::InternalError("Corrupted backup manifest.".to_string()))
+}
+
+fn account_prefix(key: &[u8], offset: usize) -> Option<u32> {
+    key.deserialize_be_u32(offset).ok()
+}
+
+fn serialize_blob_kind(kind: &BlobKind) -> Vec<u8> {
+    match kind {
+        BlobKind::LinkedMaildir {
+            account_id,
+            document_id,
+        } => KeySerializer::new(9)
+            .write(0u8)
+            .write(*account_id)
+            .write(*document_id)
+            .finalize(),
+        BlobKind::Linked {
+            account_id,
+            collection,
+            document_id,
+        } => KeySerializer::new(10)
+            .write(1u8)
+            .write(*account_id)
+            .write(*collection)
+            .write(*document_id)
+            .finalize(),
+        BlobKind::Temporary {
+            account_id,
+            timestamp,
+            seq,
+        } => KeySerializer::new(17)
+            .write(2u8)
+            .write(*account_id)
+            .write(*timestamp)
+            .write(*seq)
+            .finalize(),
+    }
+}
+
+pub(crate) fn deserialize_blob_kind(bytes: &[u8]) -> crate::Result<BlobKind> {
+    match bytes.first() {
+        Some(0) => Ok(BlobKind::LinkedMaildir {
+            account_id: bytes.deserialize_be_u32(1)?,
+            document_id: bytes.deserialize_be_u32(5)?,
+        }),
+        Some(1) => Ok(BlobKind::Linked {
+            account_id: bytes.deserialize_be_u32(1)?,
+            collection: *bytes.get(5).ok_or_else(|| {
+                crate::Error::InternalError("Corrupted blob key in backup.".to_string())
+            })?,
+            document_id: bytes.deserialize_be_u32(6)?,
+        }),
+        Some(2) => Ok(BlobKind::Temporary {
+            account_id: bytes.deserialize_be_u32(1)?,
+            timestamp: bytes.deserialize_be_u64(5)?,
+            seq: bytes.deserialize_be_u32(13)?,
+        }),
+        _ => Err(crate::Error::InternalError(
+            "Corrupted blob key in backup.".to_string(),
+        )),
+    }
+}
@@ -21,7 +21,11 @@
  * for more details.
 */
 
-use std::{io::SeekFrom, ops::Range};
+use std::{
+    io::SeekFrom,
+    ops::Range,
+    path::{Path, PathBuf},
+};
 
 use tokio::{
     fs::{self, File},
@@ -98,4 +102,99 @@ impl Store {
             }
         }
     }
+
+    pub async fn list_blobs(&self) -> crate::Result<Vec<BlobKind>> {
+        let mut blobs = Vec::new();
+
+        match &self.blob {
+            BlobStore::Local(base_path) => {
+                // emails/{account_id}/Maildir/cur/{document_id}
+                for (account_id, mut path) in list_hex_entries(&base_path.path_email).await? {
+                    path.push("Maildir");
+                    path.push("cur");
+                    for (document_id, _) in list_hex_entries(&path).await? {
+                        blobs.push(BlobKind::LinkedMaildir {
+                            account_id,
+                            document_id,
+                        });
+                    }
+                }
+
+                // blobs/{account_id}/{collection}/{document_id}
+                for (account_id, path) in list_hex_entries(&base_path.path_other).await? {
+                    for (collection, path) in list_hex_entries(&path).await? {
+                        for (document_id, _) in list_hex_entries(&path).await? {
+                            blobs.push(BlobKind::Linked {
+                                account_id,
+                                collection: collection as u8,
+                                document_id,
+                            });
+                        }
+                    }
+                }
+            }
+            BlobStore::Remote(bucket) => {
+                for object in bucket
+                    .list(String::new(), None)
+                    .await?
+                    .into_iter()
+                    .flat_map(|result| result.contents)
+                {
+                    let path = object
+                        .key
+                        .trim_start_matches('/')
+                        .split('/')
+                        .map(|part| u32::from_str_radix(part, 16).ok())
+                        .collect::<Option<Vec<_>>>();
+                    match path.as_deref() {
+                        Some([account_id, document_id]) => {
+                            blobs.push(BlobKind::LinkedMaildir {
+                                account_id: *account_id,
+                                document_id: *document_id,
+                            });
+                        }
+                        Some([account_id, collection, document_id]) if *collection <= 0xff => {
+                            blobs.push(BlobKind::Linked {
+                                account_id: *account_id,
+                                collection: *collection as u8,
+                                document_id: *document_id,
+                            });
+                        }
+                        _ => {
+                            if !object.key.trim_start_matches('/').starts_with("tmp/") {
+                                tracing::debug!(
+                                    "Unexpected S3 object while listing: {}",
+                                    object.key
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(blobs)
+    }
+}
+
+async fn list_hex_entries(path: &Path) -> crate::Result<Vec<(u32, PathBuf)>> {
+    let mut entries = Vec::new();
+    if fs::metadata(path).await.is_ok() {
+        let mut dir = fs::read_dir(path).await?;
+        while let Some(item) = dir.next_entry().await? {
+            if let Some(id) = item
+                .file_name()
+                .to_str()
+                .and_then(|name| u32::from_str_radix(name, 16).ok())
+            {
+                entries.push((id, item.path()));
+            } else {
+                tracing::debug!(
+                    "Found invalid blob filename while listing: {}",
+                    item.path().display()
+                );
+            }
+        }
+    }
+    Ok(entries)
 }
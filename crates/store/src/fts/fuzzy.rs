@@ -0,0 +1,279 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use roaring::RoaringBitmap;
+
+use crate::{
+    fts::{builder::MAX_TOKEN_LENGTH, stemmer::Stemmer, tokenizers::space::SpaceTokenizer},
+    BitmapKey, ReadTransaction, ValueKey, HASH_EXACT, HASH_STEMMED,
+};
+
+use super::{
+    term_index::{MatchTerm, TermIndex},
+    Language,
+};
+
+// Maximum number of term indexes fetched for typo-tolerant matching
+pub const MAX_FUZZY_CANDIDATES: u64 = 5_000;
+
+impl ReadTransaction<'_> {
+    #[maybe_async::maybe_async]
+    pub(crate) async fn fts_fuzzy_query(
+        &mut self,
+        account_id: u32,
+        collection: u8,
+        field: u8,
+        text: &str,
+        language: Language,
+        within: Option<&RoaringBitmap>,
+    ) -> crate::Result<Option<RoaringBitmap>> {
+        let tokens = query_tokens(text, language);
+        if tokens.is_empty() {
+            return Ok(None);
+        }
+
+        // Obtain the documents containing each token verbatim or stemmed
+        let mut exact_matches = Vec::with_capacity(tokens.len());
+        for (word, stemmed_word) in &tokens {
+            let token1 = BitmapKey::hash(word, account_id, collection, HASH_EXACT, field);
+            let token2 = BitmapKey::hash(
+                stemmed_word.as_deref().unwrap_or(word),
+                account_id,
+                collection,
+                HASH_STEMMED,
+                field,
+            );
+
+            self.refresh_if_old().await?;
+            exact_matches.push(
+                self.get_bitmaps_union(vec![token1, token2])
+                    .await?
+                    .unwrap_or_default(),
+            );
+        }
+
+        // Documents containing all tokens do not require a term index lookup
+        let mut results = exact_matches[0].clone();
+        for bitmap in &exact_matches[1..] {
+            results &= bitmap;
+        }
+
+        // Short tokens are never matched fuzzily, restrict the candidates to documents containing them
+        let mut candidates = if let Some(within) = within {
+            within.clone()
+        } else {
+            self.get_bitmap(BitmapKey::document_ids(account_id, collection))
+                .await?
+                .unwrap_or_default()
+        };
+        for ((word, _), bitmap) in tokens.iter().zip(exact_matches.iter()) {
+            if max_edit_distance(word) == 0 {
+                candidates &= bitmap;
+            }
+        }
+        candidates -= &results;
+
+        // Too many candidates to inspect, fall back to exact matching
+        if candidates.len() > MAX_FUZZY_CANDIDATES {
+            candidates.clear();
+        }
+
+        for document_id in candidates {
+            self.refresh_if_old().await?;
+            let term_index = if let Some(term_index) = self
+                .get_value::<TermIndex>(ValueKey::term_index(account_id, collection, document_id))
+                .await?
+            {
+                term_index
+            } else {
+                continue;
+            };
+
+            let mut is_match = true;
+            for ((word, _), bitmap) in tokens.iter().zip(exact_matches.iter()) {
+                if bitmap.contains(document_id) {
+                    continue;
+                }
+                let match_terms = term_index.get_fuzzy_match_terms(word);
+                if match_terms.is_empty()
+                    || term_index
+                        .match_terms(&match_terms, field.into(), false, false, false)
+                        .map_err(|e| {
+                            crate::Error::InternalError(format!(
+                                "TermIndex match_terms failed for {account_id}/{collection}/{document_id}: {e:?}"
+                            ))
+                        })?
+                        .is_none()
+                {
+                    is_match = false;
+                    break;
+                }
+            }
+
+            if is_match {
+                results.insert(document_id);
+            }
+        }
+
+        if !results.is_empty() {
+            Ok(Some(results))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl TermIndex {
+    /// Returns the terms in this index that are within the maximum edit distance
+    /// allowed for `word`, closest matches first.
+    pub fn get_fuzzy_match_terms(&self, word: &str) -> Vec<MatchTerm> {
+        let max_distance = max_edit_distance(word);
+        if max_distance == 0 {
+            return vec![];
+        }
+
+        let mut matches = self
+            .token_map
+            .iter()
+            .filter_map(|(token, id)| {
+                edit_distance(word, token, max_distance).map(|distance| (distance, *id))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_unstable();
+        matches.truncate(64);
+        matches
+            .into_iter()
+            .map(|(_, id)| MatchTerm { id, id_stemmed: id })
+            .collect()
+    }
+
+    /// Same as `get_match_term` but falls back to the closest term within the
+    /// allowed edit distance when the word is not present in the index.
+    pub fn get_fuzzy_match_term(&self, word: &str, stemmed_word: Option<&str>) -> MatchTerm {
+        let match_term = self.get_match_term(word, stemmed_word);
+        if match_term.id == u32::MAX {
+            self.get_fuzzy_match_terms(word)
+                .into_iter()
+                .next()
+                .unwrap_or(match_term)
+        } else {
+            match_term
+        }
+    }
+}
+
+pub(crate) fn query_tokens(text: &str, language: Language) -> Vec<(String, Option<String>)> {
+    if language != Language::None {
+        Stemmer::new(text, language, MAX_TOKEN_LENGTH)
+            .map(|token| {
+                (
+                    token.word.into_owned(),
+                    token.stemmed_word.map(|w| w.into_owned()),
+                )
+            })
+            .collect()
+    } else {
+        SpaceTokenizer::new(text, MAX_TOKEN_LENGTH)
+            .map(|word| (word, None))
+            .collect()
+    }
+}
+
+/// Maximum number of typos tolerated for a word, based on its length.
+pub fn max_edit_distance(word: &str) -> usize {
+    match word.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Optimal string alignment distance between `a` and `b`, or `None` when
+/// it exceeds `max_distance`.
+pub fn edit_distance(a: &str, b: &str, max_distance: usize) -> Option<usize> {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    if a.len().abs_diff(b.len()) > max_distance {
+        return None;
+    }
+
+    let mut prev_prev = vec![0; b.len() + 1];
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        let mut row_min = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (prev[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(prev_prev[j - 2] + 1);
+            }
+            current[j] = distance;
+            row_min = row_min.min(distance);
+        }
+        if row_min > max_distance {
+            return None;
+        }
+        std::mem::swap(&mut prev_prev, &mut prev);
+        std::mem::swap(&mut prev, &mut current);
+    }
+
+    let distance = prev[b.len()];
+    if distance <= max_distance {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn fuzzy_distance() {
+        for (a, b, max_distance, expected) in [
+            ("hello", "hello", 1, Some(0)),
+            ("hello", "helo", 1, Some(1)),
+            ("hello", "hallo", 1, Some(1)),
+            ("hello", "hlelo", 1, Some(1)),
+            ("hello", "help", 1, None),
+            ("hello", "help", 2, Some(2)),
+            ("kitten", "sitting", 2, None),
+            ("kitten", "sitting", 3, Some(3)),
+            ("straße", "strase", 1, Some(1)),
+            ("", "abc", 3, Some(3)),
+        ] {
+            assert_eq!(edit_distance(a, b, max_distance), expected, "{a} -> {b}");
+        }
+
+        for (word, expected) in [("the", 0), ("helo", 1), ("meeting", 1), ("document", 2)] {
+            assert_eq!(max_edit_distance(word), expected, "{word}");
+        }
+    }
+}
@@ -32,8 +32,10 @@ pub mod lang;
 //pub mod pdf;
 pub mod bloom;
 pub mod builder;
+pub mod fuzzy;
 pub mod ngram;
 pub mod query;
+pub mod relevance;
 pub mod search_snippet;
 pub mod stemmer;
 pub mod term_index;
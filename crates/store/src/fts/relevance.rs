@@ -0,0 +1,320 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use ahash::AHashMap;
+use roaring::RoaringBitmap;
+
+use crate::{
+    query::{Filter, TextMatch},
+    BitmapKey, ReadTransaction, Store, ValueKey, HASH_EXACT, HASH_STEMMED,
+};
+
+use super::{fuzzy::query_tokens, term_index::TermIndex, Language};
+
+// Okapi BM25 parameters
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+// Term frequency weight of typo-tolerant matches
+const FUZZY_WEIGHT: f64 = 0.5;
+
+// Maximum number of documents scored, larger result sets are left unsorted
+pub const MAX_SCORED_DOCUMENTS: u64 = 10_000;
+
+struct RelevanceTerm {
+    field: u8,
+    word: String,
+    stemmed_word: Option<String>,
+    is_fuzzy: bool,
+    doc_freq: u64,
+}
+
+impl ReadTransaction<'_> {
+    #[maybe_async::maybe_async]
+    pub(crate) async fn relevance_scores(
+        &mut self,
+        account_id: u32,
+        collection: u8,
+        document_ids: &RoaringBitmap,
+        filters: &[Filter],
+    ) -> crate::Result<AHashMap<u32, f64>> {
+        let mut terms = relevance_terms(filters);
+        if terms.is_empty() || document_ids.is_empty() || document_ids.len() > MAX_SCORED_DOCUMENTS
+        {
+            return Ok(AHashMap::new());
+        }
+
+        // Obtain the collection size and document frequencies
+        let total_docs = self
+            .get_bitmap(BitmapKey::document_ids(account_id, collection))
+            .await?
+            .map_or(0, |bm| bm.len());
+        for term in terms.iter_mut() {
+            self.refresh_if_old().await?;
+            term.doc_freq = self
+                .get_bitmaps_union(vec![
+                    BitmapKey::hash(&term.word, account_id, collection, HASH_EXACT, term.field),
+                    BitmapKey::hash(
+                        term.stemmed_word.as_deref().unwrap_or(&term.word),
+                        account_id,
+                        collection,
+                        HASH_STEMMED,
+                        term.field,
+                    ),
+                ])
+                .await?
+                .map_or(0, |bm| bm.len());
+        }
+
+        // Obtain term frequencies and field lengths from the term index of each document
+        let mut fields = terms.iter().map(|t| t.field).collect::<Vec<_>>();
+        fields.sort_unstable();
+        fields.dedup();
+        let mut documents = Vec::with_capacity(document_ids.len() as usize);
+        let mut fuzzy_doc_freq = vec![0u64; terms.len()];
+        for document_id in document_ids {
+            self.refresh_if_old().await?;
+            let term_index = if let Some(term_index) = self
+                .get_value::<TermIndex>(ValueKey::term_index(account_id, collection, document_id))
+                .await?
+            {
+                term_index
+            } else {
+                continue;
+            };
+
+            let field_lengths = fields
+                .iter()
+                .map(|field| {
+                    term_index
+                        .items
+                        .iter()
+                        .filter(|item| item.field_id == *field)
+                        .map(|item| item.terms_len)
+                        .sum::<usize>() as f64
+                })
+                .collect::<Vec<_>>();
+            let mut term_freqs = vec![0.0; terms.len()];
+
+            for (pos, term) in terms.iter().enumerate() {
+                let mut match_term =
+                    term_index.get_match_term(&term.word, term.stemmed_word.as_deref());
+                let mut weight = 1.0;
+                if match_term.id == u32::MAX {
+                    if term.is_fuzzy {
+                        match_term = term_index.get_fuzzy_match_term(&term.word, None);
+                        weight = FUZZY_WEIGHT;
+                    }
+                    if match_term.id == u32::MAX {
+                        continue;
+                    }
+                }
+
+                if let Some(groups) = term_index
+                    .match_terms(&[match_term], term.field.into(), false, true, false)
+                    .map_err(|e| {
+                        crate::Error::InternalError(format!(
+                            "TermIndex match_terms failed for {account_id}/{collection}/{document_id}: {e:?}"
+                        ))
+                    })?
+                {
+                    term_freqs[pos] =
+                        groups.iter().map(|g| g.terms.len()).sum::<usize>() as f64 * weight;
+                    if weight < 1.0 {
+                        fuzzy_doc_freq[pos] += 1;
+                    }
+                }
+            }
+
+            documents.push((document_id, field_lengths, term_freqs));
+        }
+
+        if documents.is_empty() {
+            return Ok(AHashMap::new());
+        }
+
+        // Average field lengths are calculated over the scored documents
+        let mut avg_lengths = vec![0.0; fields.len()];
+        for (_, field_lengths, _) in &documents {
+            for (avg_length, length) in avg_lengths.iter_mut().zip(field_lengths) {
+                *avg_length += length;
+            }
+        }
+        for avg_length in avg_lengths.iter_mut() {
+            *avg_length = (*avg_length / documents.len() as f64).max(1.0);
+        }
+
+        let idfs = terms
+            .iter()
+            .zip(fuzzy_doc_freq)
+            .map(|(term, fuzzy_doc_freq)| {
+                let doc_freq = (term.doc_freq + fuzzy_doc_freq) as f64;
+                let total_docs = (total_docs as f64).max(doc_freq);
+                (1.0 + (total_docs - doc_freq + 0.5) / (doc_freq + 0.5)).ln()
+            })
+            .collect::<Vec<_>>();
+        let field_pos = terms
+            .iter()
+            .map(|term| fields.binary_search(&term.field).unwrap_or_default())
+            .collect::<Vec<_>>();
+
+        Ok(documents
+            .into_iter()
+            .map(|(document_id, field_lengths, term_freqs)| {
+                let mut score = 0.0;
+                for (pos, term_freq) in term_freqs.into_iter().enumerate() {
+                    if term_freq > 0.0 {
+                        let field = field_pos[pos];
+                        let norm =
+                            1.0 - BM25_B + BM25_B * field_lengths[field] / avg_lengths[field];
+                        score += idfs[pos] * (term_freq * (BM25_K1 + 1.0))
+                            / (term_freq + BM25_K1 * norm);
+                    }
+                }
+                (document_id, score)
+            })
+            .collect())
+    }
+}
+
+impl Store {
+    /// Returns the BM25 relevance score of each document for the full-text
+    /// conditions present in `filters`.
+    pub async fn relevance_scores(
+        &self,
+        account_id: u32,
+        collection: impl Into<u8>,
+        document_ids: RoaringBitmap,
+        filters: Vec<Filter>,
+    ) -> crate::Result<AHashMap<u32, f64>> {
+        let collection = collection.into();
+        #[cfg(not(feature = "is_sync"))]
+        {
+            self.read_transaction()
+                .await?
+                .relevance_scores(account_id, collection, &document_ids, &filters)
+                .await
+        }
+
+        #[cfg(feature = "is_sync")]
+        {
+            let mut trx = self.read_transaction()?;
+            self.spawn_worker(move || {
+                trx.relevance_scores(account_id, collection, &document_ids, &filters)
+            })
+            .await
+        }
+    }
+}
+
+impl Filter {
+    /// Returns the full-text conditions that contribute to relevance scoring,
+    /// negated conditions are skipped.
+    pub fn text_conditions(filters: &[Filter]) -> Vec<Filter> {
+        let mut conditions = Vec::new();
+        let mut stack = Vec::new();
+        let mut negated = 0;
+
+        for filter in filters {
+            match filter {
+                Filter::HasText { .. } if negated == 0 => {
+                    conditions.push(filter.clone());
+                }
+                Filter::And | Filter::Or => {
+                    stack.push(false);
+                }
+                Filter::Not => {
+                    stack.push(true);
+                    negated += 1;
+                }
+                Filter::End => {
+                    if stack.pop().unwrap_or_default() {
+                        negated -= 1;
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        conditions
+    }
+}
+
+fn relevance_terms(filters: &[Filter]) -> Vec<RelevanceTerm> {
+    let mut terms: Vec<RelevanceTerm> = Vec::new();
+
+    for filter in Filter::text_conditions(filters) {
+        if let Filter::HasText { field, text, op } = filter {
+            let (tokens, is_fuzzy) = match op {
+                TextMatch::Exact(language) | TextMatch::Stemmed(language) => {
+                    (query_tokens(&text, language), false)
+                }
+                TextMatch::Fuzzy(language) => (query_tokens(&text, language), true),
+                TextMatch::Tokenized => (query_tokens(&text, Language::None), false),
+                TextMatch::Raw => (vec![(text, None)], false),
+            };
+
+            for (word, stemmed_word) in tokens {
+                // Limit the number of scored terms
+                if terms.len() == 64 {
+                    return terms;
+                } else if !terms.iter().any(|t| t.field == field && t.word == word) {
+                    terms.push(RelevanceTerm {
+                        field,
+                        word,
+                        stemmed_word,
+                        is_fuzzy,
+                        doc_freq: 0,
+                    });
+                }
+            }
+        }
+    }
+
+    terms
+}
+
+pub(crate) fn sort_by_relevance(
+    document_ids: &RoaringBitmap,
+    scores: &AHashMap<u32, f64>,
+    ascending: bool,
+) -> Vec<(u32, f64)> {
+    let mut sorted_ids = document_ids
+        .iter()
+        .map(|document_id| {
+            (
+                document_id,
+                scores.get(&document_id).copied().unwrap_or_default(),
+            )
+        })
+        .collect::<Vec<_>>();
+    sorted_ids.sort_by(|a, b| {
+        if ascending {
+            a.1.total_cmp(&b.1)
+        } else {
+            b.1.total_cmp(&a.1)
+        }
+        .then_with(|| b.0.cmp(&a.0))
+    });
+    sorted_ids
+}
@@ -26,8 +26,10 @@ use std::fmt::Display;
 use blob::BlobStore;
 
 pub mod backend;
+pub mod backup;
 pub mod blob;
 pub mod fts;
+pub mod migrate;
 pub mod query;
 pub mod write;
 
@@ -62,10 +64,7 @@ pub struct Store {
     conn_pool: r2d2::Pool<backend::sqlite::pool::SqliteConnectionManager>,
     id_assigner: std::sync::Arc<
         parking_lot::Mutex<
-            lru_cache::LruCache<
-                backend::sqlite::id_assign::IdCacheKey,
-                backend::sqlite::id_assign::IdAssigner,
-            >,
+            lru_cache::LruCache<backend::id_assign::IdCacheKey, backend::id_assign::IdAssigner>,
         >,
     >,
     worker_pool: rayon::ThreadPool,
@@ -78,6 +77,38 @@ pub struct ReadTransaction<'x> {
     _p: std::marker::PhantomData<&'x ()>,
 }
 
+#[cfg(feature = "postgres")]
+pub struct Store {
+    conn_pool: sqlx::PgPool,
+    id_assigner: std::sync::Arc<
+        parking_lot::Mutex<
+            lru_cache::LruCache<backend::id_assign::IdCacheKey, backend::id_assign::IdAssigner>,
+        >,
+    >,
+    blob: BlobStore,
+}
+
+#[cfg(feature = "postgres")]
+pub struct ReadTransaction<'x> {
+    conn_pool: &'x sqlx::PgPool,
+}
+
+#[cfg(feature = "mysql")]
+pub struct Store {
+    conn_pool: sqlx::MySqlPool,
+    id_assigner: std::sync::Arc<
+        parking_lot::Mutex<
+            lru_cache::LruCache<backend::id_assign::IdCacheKey, backend::id_assign::IdAssigner>,
+        >,
+    >,
+    blob: BlobStore,
+}
+
+#[cfg(feature = "mysql")]
+pub struct ReadTransaction<'x> {
+    conn_pool: &'x sqlx::MySqlPool,
+}
+
 #[cfg(not(feature = "backend"))]
 #[allow(dead_code)]
 pub struct Store {
@@ -141,6 +172,13 @@ pub struct CustomValueKey {
     pub value: Vec<u8>,
 }
 
+// Custom value kinds, written after the u32::MAX prefix
+// (kinds 3 to 6 are used by the internal directory)
+pub const CUSTOM_ACCOUNT_NAME_TO_ID: u8 = 0;
+pub const CUSTOM_ACCOUNT_ID_TO_NAME: u8 = 1;
+pub const CUSTOM_SUPPRESSION: u8 = 2;
+pub const CUSTOM_VAPID_KEY: u8 = 7;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct AclKey {
     pub grant_account_id: u32,
@@ -268,6 +306,20 @@ impl Store {
         unimplemented!("No backend selected")
     }
 
+    pub(crate) async fn export_records(
+        &self,
+        _tx: tokio::sync::mpsc::Sender<backup::BackupRecord>,
+    ) -> crate::Result<()> {
+        unimplemented!("No backend selected")
+    }
+
+    pub(crate) async fn import_records(
+        &self,
+        _records: Vec<backup::BackupRecord>,
+    ) -> crate::Result<()> {
+        unimplemented!("No backend selected")
+    }
+
     #[cfg(feature = "test_mode")]
     pub async fn destroy(&self) {
         unimplemented!("No backend selected")
@@ -351,7 +403,11 @@ impl ReadTransaction<'_> {
         unimplemented!("No backend selected")
     }
 
-    pub(crate) async fn get_quota(&self, _account_id: u32) -> crate::Result<i64> {
+    pub(crate) async fn get_quota(
+        &self,
+        _account_id: u32,
+        _class: write::QuotaClass,
+    ) -> crate::Result<Option<i64>> {
         unimplemented!("No backend selected")
     }
 
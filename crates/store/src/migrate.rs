@@ -0,0 +1,464 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use ahash::AHashSet;
+use tokio::{fs, io::AsyncRead, sync::mpsc};
+
+use crate::{
+    backup::{
+        deserialize_blob_kind, AccountState, ArchiveReader, ArchiveStream, BackupManifest,
+        BackupRecord, RecordDigest, RecordScope, BLOB_RECORD, RESTORE_BATCH_SIZE,
+    },
+    write::{
+        key::{DeserializeBigEndian, KeySerializer},
+        BatchBuilder, Operation, ValueClass,
+    },
+    Store, SUBSPACE_VALUES,
+};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub applied: Vec<u64>,
+    pub records: usize,
+    pub blobs: usize,
+    pub mismatched_accounts: Vec<u32>,
+    pub mismatched_global: bool,
+}
+
+// Updated while a migration runs so it can be reported by a background task
+#[derive(Debug, Default)]
+pub struct MigrationProgress {
+    pub records: AtomicUsize,
+    pub blobs: AtomicUsize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct MigrationCheckpoint {
+    applied_id: u64,
+    pending_id: u64,
+    offset: u64,
+    purged: bool,
+    manifest: Option<BackupManifest>,
+}
+
+struct RecordImporter<'x> {
+    store: &'x Store,
+    batch: Vec<BackupRecord>,
+    progress: &'x MigrationProgress,
+}
+
+impl Store {
+    /// Copies the contents of backup archives taken from another server, possibly
+    /// running a different backend, into this store. Archives are passed in the
+    /// order they were taken and the ones already applied according to the checkpoint
+    /// are skipped, which allows catching up with a source that is still online by
+    /// applying incremental archives. Once all archives are applied the store contents
+    /// are verified against the digests of the latest archive.
+    pub async fn migrate(
+        &self,
+        paths: &[impl AsRef<Path>],
+        checkpoint_path: &Path,
+        progress: &MigrationProgress,
+    ) -> crate::Result<MigrationReport> {
+        let mut checkpoint = MigrationCheckpoint::load(checkpoint_path).await?;
+        let mut report = MigrationReport::default();
+        let mut is_applied = checkpoint.applied_id != 0;
+        let mut previous: Option<BackupManifest> = None;
+
+        for path in paths {
+            let path = path.as_ref();
+            let mut reader = ArchiveReader::open(path).await?;
+            let manifest = reader.manifest.clone();
+            if manifest.base_id != previous.as_ref().map_or(0, |m| m.id) {
+                return Err(crate::Error::InternalError(format!(
+                    "Backup archive {} does not follow the previous archive.",
+                    path.display()
+                )));
+            }
+
+            // Skip archives that were applied by a previous run
+            if is_applied {
+                if manifest.id == checkpoint.applied_id {
+                    is_applied = false;
+                }
+                previous = manifest.into();
+                continue;
+            }
+
+            if checkpoint.pending_id != manifest.id {
+                checkpoint.pending_id = manifest.id;
+                checkpoint.offset = 0;
+                checkpoint.purged = false;
+            }
+
+            // Incremental archives replace the accounts that changed or were removed
+            if let (Some(base), false) = (&previous, checkpoint.purged) {
+                for (account_id, state) in &base.accounts {
+                    if manifest
+                        .accounts
+                        .get(account_id)
+                        .map_or(true, |state| state.included)
+                    {
+                        tracing::debug!(
+                            context = "migrate",
+                            event = "purge",
+                            account_id = account_id,
+                            change_id = state.change_id
+                        );
+                        self.purge_account(*account_id).await?;
+                        self.delete_account_blobs(*account_id).await?;
+                    }
+                }
+                checkpoint.purged = true;
+                checkpoint.save(checkpoint_path).await?;
+            }
+
+            // Copy records, the checkpoint is updated after each batch is committed
+            reader.seek(checkpoint.offset).await?;
+            let mut importer = RecordImporter::new(self, progress);
+            while let Some(record) = reader.next().await? {
+                if importer.add(record).await? {
+                    checkpoint.offset = reader.offset;
+                    checkpoint.save(checkpoint_path).await?;
+                }
+            }
+            importer.flush().await?;
+
+            // Records removed from the source are removed here as well
+            let mut archive_keys = AHashSet::new();
+            let mut reader = ArchiveReader::open(path).await?;
+            while let Some(record) = reader.next().await? {
+                if is_global_value(&manifest, &record) {
+                    archive_keys.insert(record.key);
+                }
+            }
+            self.purge_stale_records(&manifest, &archive_keys).await?;
+
+            report.applied.push(manifest.id);
+            tracing::info!(
+                context = "migrate",
+                event = "applied",
+                path = %path.display(),
+                records = progress.records.load(Ordering::Relaxed),
+                blobs = progress.blobs.load(Ordering::Relaxed)
+            );
+            checkpoint = MigrationCheckpoint::applied(manifest);
+            checkpoint.save(checkpoint_path).await?;
+
+            previous = checkpoint.manifest.clone();
+        }
+
+        if is_applied {
+            return Err(crate::Error::InternalError(
+                "The checkpoint does not match any of the backup archives.".to_string(),
+            ));
+        }
+
+        // Verify the store against the latest archive
+        if let Some(manifest) = previous {
+            self.verify_migration(&manifest, &mut report).await?;
+        }
+        report.records = progress.records.load(Ordering::Relaxed);
+        report.blobs = progress.blobs.load(Ordering::Relaxed);
+
+        Ok(report)
+    }
+
+    /// Applies an archive streamed from another server while it is being read, avoiding
+    /// the need for an intermediate file. The manifest of the last applied stream is
+    /// kept in the checkpoint and should be sent to the source as the base of the next
+    /// stream, which then only carries the accounts that changed since. An interrupted
+    /// stream is restarted relative to the last applied manifest.
+    pub async fn migrate_stream<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        checkpoint_path: &Path,
+        progress: &MigrationProgress,
+    ) -> crate::Result<MigrationReport> {
+        let checkpoint = MigrationCheckpoint::load(checkpoint_path).await?;
+        let mut stream = ArchiveStream::open(reader).await?;
+        if stream.base_id != checkpoint.manifest.as_ref().map_or(0, |base| base.id) {
+            return Err(crate::Error::InternalError(
+                "Backup stream does not follow the last applied migration.".to_string(),
+            ));
+        }
+
+        // The manifest is only available at the end of the stream, so the scope of each
+        // value is resolved from the accounts seen so far and the accounts in the base,
+        // which matches the way the source resolved it.
+        let mut scope = BackupManifest {
+            accounts: checkpoint
+                .manifest
+                .as_ref()
+                .map(|base| base.accounts.clone())
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        let mut importer = RecordImporter::new(self, progress);
+        let mut purged = AHashSet::new();
+        let mut stream_keys = AHashSet::new();
+        while let Some(record) = stream.next().await? {
+            if record.subspace != SUBSPACE_VALUES && record.subspace != BLOB_RECORD {
+                if let Ok(account_id) = record.key.as_slice().deserialize_be_u32(0) {
+                    scope.accounts.entry(account_id).or_insert(AccountState {
+                        change_id: 0,
+                        included: true,
+                        digest: [0; 32],
+                    });
+                }
+            }
+
+            match scope.scope(record.subspace, &record.key) {
+                RecordScope::Account(account_id) => {
+                    // Accounts in the stream replace any previous copy
+                    if purged.insert(account_id) {
+                        importer.flush().await?;
+                        self.purge_account(account_id).await?;
+                        self.delete_account_blobs(account_id).await?;
+                    }
+                }
+                _ if record.subspace == SUBSPACE_VALUES => {
+                    stream_keys.insert(record.key.clone());
+                }
+                _ => {}
+            }
+
+            importer.add(record).await?;
+        }
+        importer.flush().await?;
+
+        let manifest = stream.manifest.take().ok_or_else(|| {
+            crate::Error::InternalError("Backup stream is truncated.".to_string())
+        })?;
+        self.purge_stale_records(&manifest, &stream_keys).await?;
+
+        let mut report = MigrationReport {
+            applied: vec![manifest.id],
+            ..Default::default()
+        };
+        self.verify_migration(&manifest, &mut report).await?;
+        report.records = progress.records.load(Ordering::Relaxed);
+        report.blobs = progress.blobs.load(Ordering::Relaxed);
+        MigrationCheckpoint::applied(manifest)
+            .save(checkpoint_path)
+            .await?;
+
+        Ok(report)
+    }
+
+    /// Returns the manifest of the last archive or stream applied by a migration, which
+    /// is used as the base when requesting an incremental stream from the source.
+    pub async fn migration_base(
+        &self,
+        checkpoint_path: &Path,
+    ) -> crate::Result<Option<BackupManifest>> {
+        MigrationCheckpoint::load(checkpoint_path)
+            .await
+            .map(|checkpoint| checkpoint.manifest)
+    }
+
+    async fn purge_stale_records(
+        &self,
+        manifest: &BackupManifest,
+        archive_keys: &AHashSet<Vec<u8>>,
+    ) -> crate::Result<()> {
+        // Find the accounts and server-wide keys that are no longer present in the source
+        let (tx, mut rx) = mpsc::channel::<BackupRecord>(RESTORE_BATCH_SIZE);
+        let (export_result, (stale_accounts, stale_keys)) =
+            tokio::join!(self.export_records(tx), async {
+                let mut stale_accounts = AHashSet::new();
+                let mut stale_keys = Vec::new();
+                while let Some(record) = rx.recv().await {
+                    match manifest.scope(record.subspace, &record.key) {
+                        RecordScope::Account(account_id) => {
+                            if !manifest.accounts.contains_key(&account_id) {
+                                stale_accounts.insert(account_id);
+                            }
+                        }
+                        _ => {
+                            if record.subspace == SUBSPACE_VALUES
+                                && !archive_keys.contains(&record.key)
+                            {
+                                stale_keys.push(record.key);
+                            }
+                        }
+                    }
+                }
+                (stale_accounts, stale_keys)
+            });
+        export_result?;
+
+        for account_id in stale_accounts {
+            tracing::debug!(
+                context = "migrate",
+                event = "purge",
+                account_id = account_id,
+                reason = "removed"
+            );
+            self.purge_account(account_id).await?;
+            self.delete_account_blobs(account_id).await?;
+        }
+
+        for keys in stale_keys.chunks(RESTORE_BATCH_SIZE) {
+            let mut batch = BatchBuilder::new();
+            for key in keys {
+                batch.op(Operation::Value {
+                    class: ValueClass::Custom { bytes: key.clone() },
+                    set: None,
+                });
+            }
+            self.write(batch.build()).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn verify_migration(
+        &self,
+        manifest: &BackupManifest,
+        report: &mut MigrationReport,
+    ) -> crate::Result<()> {
+        let digests = self.digest_records(manifest).await?;
+        for (account_id, state) in &manifest.accounts {
+            if digests.account_digest(*account_id) != state.digest {
+                report.mismatched_accounts.push(*account_id);
+            }
+        }
+        report.mismatched_accounts.sort_unstable();
+        report.mismatched_global = digests.global_digest() != manifest.digest;
+        Ok(())
+    }
+
+    async fn digest_records(&self, manifest: &BackupManifest) -> crate::Result<RecordDigest> {
+        let (tx, mut rx) = mpsc::channel::<BackupRecord>(RESTORE_BATCH_SIZE);
+        let (export_result, digests) = tokio::join!(self.export_records(tx), async {
+            let mut digests = RecordDigest::default();
+            while let Some(record) = rx.recv().await {
+                digests.add(manifest.scope(record.subspace, &record.key), &record);
+            }
+            digests
+        });
+        export_result.map(|_| digests)
+    }
+}
+
+impl<'x> RecordImporter<'x> {
+    fn new(store: &'x Store, progress: &'x MigrationProgress) -> Self {
+        RecordImporter {
+            store,
+            batch: Vec::with_capacity(RESTORE_BATCH_SIZE),
+            progress,
+        }
+    }
+
+    // Returns true when the pending batch was committed
+    async fn add(&mut self, record: BackupRecord) -> crate::Result<bool> {
+        if record.subspace == BLOB_RECORD {
+            self.store
+                .put_blob(&deserialize_blob_kind(&record.key)?, &record.value)
+                .await?;
+            self.progress.blobs.fetch_add(1, Ordering::Relaxed);
+            Ok(false)
+        } else {
+            self.batch.push(record);
+            if self.batch.len() == RESTORE_BATCH_SIZE {
+                self.flush().await?;
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        }
+    }
+
+    async fn flush(&mut self) -> crate::Result<()> {
+        if !self.batch.is_empty() {
+            let batch = std::mem::replace(&mut self.batch, Vec::with_capacity(RESTORE_BATCH_SIZE));
+            self.progress
+                .records
+                .fetch_add(batch.len(), Ordering::Relaxed);
+            self.store.import_records(batch).await?;
+        }
+        Ok(())
+    }
+}
+
+fn is_global_value(manifest: &BackupManifest, record: &BackupRecord) -> bool {
+    record.subspace == SUBSPACE_VALUES
+        && !matches!(
+            manifest.scope(record.subspace, &record.key),
+            RecordScope::Account(_)
+        )
+}
+
+impl MigrationCheckpoint {
+    fn applied(manifest: BackupManifest) -> Self {
+        MigrationCheckpoint {
+            applied_id: manifest.id,
+            manifest: manifest.into(),
+            ..Default::default()
+        }
+    }
+
+    async fn load(path: &Path) -> crate::Result<Self> {
+        match fs::read(path).await {
+            Ok(bytes) => {
+                let bytes = bytes.as_slice();
+                Ok(MigrationCheckpoint {
+                    applied_id: bytes.deserialize_be_u64(0)?,
+                    pending_id: bytes.deserialize_be_u64(8)?,
+                    offset: bytes.deserialize_be_u64(16)?,
+                    purged: bytes.get(24).map_or(false, |purged| *purged != 0),
+                    manifest: match bytes.get(25..) {
+                        Some(manifest) if !manifest.is_empty() => {
+                            BackupManifest::deserialize(manifest)?.into()
+                        }
+                        _ => None,
+                    },
+                })
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn save(&self, path: &Path) -> crate::Result<()> {
+        let mut tmp_path = path.to_path_buf();
+        tmp_path.set_extension("tmp");
+        let mut bytes = KeySerializer::new(25)
+            .write(self.applied_id)
+            .write(self.pending_id)
+            .write(self.offset)
+            .write(u8::from(self.purged))
+            .finalize();
+        if let Some(manifest) = &self.manifest {
+            bytes.extend_from_slice(&manifest.serialize());
+        }
+        fs::write(&tmp_path, bytes).await?;
+        fs::rename(tmp_path, path).await.map_err(Into::into)
+    }
+}
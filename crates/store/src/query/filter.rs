@@ -80,6 +80,16 @@ impl ReadTransaction<'_> {
                         self.fts_query(account_id, collection, field, &text, language, false)
                             .await?
                     }
+                    TextMatch::Fuzzy(language) => {
+                        // Within an AND, only documents already matched can be part of the result
+                        let within = if matches!(state.op, Filter::And) {
+                            state.bm.as_ref()
+                        } else {
+                            None
+                        };
+                        self.fts_fuzzy_query(account_id, collection, field, &text, language, within)
+                            .await?
+                    }
                     TextMatch::Tokenized => {
                         self.get_bitmaps_intersection(
                             SpaceTokenizer::new(&text, MAX_TOKEN_LENGTH)
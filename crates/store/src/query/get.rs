@@ -23,7 +23,7 @@
 
 use roaring::RoaringBitmap;
 
-use crate::{BitmapKey, Deserialize, Key, Store};
+use crate::{write::QuotaClass, BitmapKey, Deserialize, Key, Store};
 
 impl Store {
     pub async fn get_value<U>(&self, key: impl Key) -> crate::Result<Option<U>>
@@ -98,15 +98,34 @@ impl Store {
     }
 
     pub async fn get_quota(&self, account_id: u32) -> crate::Result<i64> {
+        self.get_quota_counter(account_id, QuotaClass::Octets)
+            .await
+            .map(|quota| quota.unwrap_or_default())
+    }
+
+    pub async fn get_message_count(&self, account_id: u32) -> crate::Result<Option<i64>> {
+        self.get_quota_counter(account_id, QuotaClass::Messages)
+            .await
+    }
+
+    async fn get_quota_counter(
+        &self,
+        account_id: u32,
+        class: QuotaClass,
+    ) -> crate::Result<Option<i64>> {
         #[cfg(not(feature = "is_sync"))]
         {
-            self.read_transaction().await?.get_quota(account_id).await
+            self.read_transaction()
+                .await?
+                .get_quota(account_id, class)
+                .await
         }
 
         #[cfg(feature = "is_sync")]
         {
             let trx = self.read_transaction()?;
-            self.spawn_worker(move || trx.get_quota(account_id)).await
+            self.spawn_worker(move || trx.get_quota(account_id, class))
+                .await
         }
     }
 
@@ -41,7 +41,7 @@ pub enum Operator {
     Equal,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Filter {
     MatchValue {
         field: u8,
@@ -65,18 +65,29 @@ pub enum Filter {
     End,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum TextMatch {
     Exact(Language),
     Stemmed(Language),
+    Fuzzy(Language),
     Tokenized,
     Raw,
 }
 
 #[derive(Debug)]
 pub enum Comparator {
-    Field { field: u8, ascending: bool },
-    DocumentSet { set: RoaringBitmap, ascending: bool },
+    Field {
+        field: u8,
+        ascending: bool,
+    },
+    DocumentSet {
+        set: RoaringBitmap,
+        ascending: bool,
+    },
+    Relevance {
+        filters: Vec<Filter>,
+        ascending: bool,
+    },
 }
 
 #[derive(Debug)]
@@ -185,6 +196,31 @@ impl Filter {
         }
     }
 
+    /// Enables typo-tolerant matching on stemmed and tokenized text conditions.
+    pub fn with_fuzzy(self, fuzzy: bool) -> Self {
+        match self {
+            Filter::HasText {
+                field,
+                text,
+                op: TextMatch::Stemmed(language),
+            } if fuzzy => Filter::HasText {
+                field,
+                text,
+                op: TextMatch::Fuzzy(language),
+            },
+            Filter::HasText {
+                field,
+                text,
+                op: TextMatch::Tokenized,
+            } if fuzzy => Filter::HasText {
+                field,
+                text,
+                op: TextMatch::Fuzzy(Language::None),
+            },
+            filter => filter,
+        }
+    }
+
     pub fn has_raw_text(field: impl Into<u8>, text: impl Into<String>) -> Self {
         Filter::HasText {
             field: field.into(),
@@ -222,6 +258,10 @@ impl Comparator {
         Self::DocumentSet { set, ascending }
     }
 
+    pub fn relevance(filters: Vec<Filter>, ascending: bool) -> Self {
+        Self::Relevance { filters, ascending }
+    }
+
     pub fn ascending(field: impl Into<u8>) -> Self {
         Self::Field {
             field: field.into(),
@@ -25,7 +25,7 @@ use std::cmp::Ordering;
 
 use ahash::{AHashMap, AHashSet};
 
-use crate::{ReadTransaction, Store, ValueKey};
+use crate::{fts::relevance::sort_by_relevance, ReadTransaction, Store, ValueKey};
 
 use super::{Comparator, ResultSet, SortedResultSet};
 
@@ -91,6 +91,23 @@ impl ReadTransaction<'_> {
                         }
                     }
                 }
+                Comparator::Relevance { filters, ascending } => {
+                    let scores = self
+                        .relevance_scores(
+                            result_set.account_id,
+                            result_set.collection,
+                            &result_set.results,
+                            &filters,
+                        )
+                        .await?;
+                    for (document_id, _) in
+                        sort_by_relevance(&result_set.results, &scores, ascending)
+                    {
+                        if !paginate.add(0, document_id) {
+                            break;
+                        }
+                    }
+                }
             }
 
             // Obtain prefixes
@@ -174,6 +191,28 @@ impl ReadTransaction<'_> {
                             }
                         }
                     }
+                    Comparator::Relevance { filters, ascending } => {
+                        let scores = self
+                            .relevance_scores(
+                                result_set.account_id,
+                                result_set.collection,
+                                &result_set.results,
+                                &filters,
+                            )
+                            .await?;
+                        let mut prev_score = None;
+                        let mut idx = 0;
+
+                        for (document_id, score) in
+                            sort_by_relevance(&result_set.results, &scores, ascending)
+                        {
+                            if prev_score != Some(score) {
+                                idx += 1;
+                                prev_score = Some(score);
+                            }
+                            sorted_ids.entry(document_id).or_insert([0u32; 4])[pos] = idx;
+                        }
+                    }
                 }
             }
 
@@ -25,7 +25,7 @@ use crate::BM_DOCUMENT_IDS;
 
 use super::{
     assert::ToAssertValue, Batch, BatchBuilder, BitmapFamily, HasFlag, IntoOperations, Operation,
-    Serialize, ToBitmaps, ValueClass, F_BITMAP, F_CLEAR, F_INDEX, F_VALUE,
+    QuotaClass, Serialize, ToBitmaps, ValueClass, F_BITMAP, F_CLEAR, F_INDEX, F_VALUE,
 };
 
 impl BatchBuilder {
@@ -142,7 +142,18 @@ impl BatchBuilder {
     }
 
     pub fn quota(&mut self, bytes: i64) -> &mut Self {
-        self.ops.push(Operation::UpdateQuota { bytes });
+        self.ops.push(Operation::UpdateQuota {
+            class: QuotaClass::Octets,
+            value: bytes,
+        });
+        self
+    }
+
+    pub fn message_count(&mut self, messages: i64) -> &mut Self {
+        self.ops.push(Operation::UpdateQuota {
+            class: QuotaClass::Messages,
+            value: messages,
+        });
         self
     }
 
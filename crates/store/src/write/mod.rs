@@ -82,7 +82,8 @@ pub enum Operation {
         set: bool,
     },
     UpdateQuota {
-        bytes: i64,
+        class: QuotaClass,
+        value: i64,
     },
     Log {
         change_id: u64,
@@ -91,6 +92,12 @@ pub enum Operation {
     },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuotaClass {
+    Octets,
+    Messages,
+}
+
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub enum ValueClass {
     Property { field: u8, family: u8 },
@@ -374,6 +381,16 @@ impl Operation {
     }
 }
 
+impl QuotaClass {
+    // Counters share the quota table, message counts are keyed above the account id range
+    pub(crate) fn key(&self, account_id: u32) -> i64 {
+        match self {
+            QuotaClass::Octets => account_id as i64,
+            QuotaClass::Messages => (1 << 32) | account_id as i64,
+        }
+    }
+}
+
 #[inline(always)]
 pub fn now() -> u64 {
     SystemTime::now()
@@ -30,9 +30,13 @@ use rustls::{
         TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256, TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
         TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384, TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256,
     },
-    server::{NoClientAuth, ResolvesServerCertUsingSni},
+    server::{
+        AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, NoClientAuth,
+        ResolvesServerCertUsingSni,
+    },
     sign::{any_supported_type, CertifiedKey},
-    ServerConfig, SupportedCipherSuite, ALL_CIPHER_SUITES, ALL_KX_GROUPS, ALL_VERSIONS,
+    RootCertStore, ServerConfig, SupportedCipherSuite, ALL_CIPHER_SUITES, ALL_KX_GROUPS,
+    ALL_VERSIONS,
 };
 use tokio::net::TcpSocket;
 
@@ -157,6 +161,43 @@ impl Config {
                 sct_list: None,
             }));
 
+            // Build client certificate verifier
+            let client_verifier = if self
+                .property_or_default(
+                    ("server.listener", id, "tls.client-auth.enable"),
+                    "server.tls.client-auth.enable",
+                )?
+                .unwrap_or(false)
+            {
+                let ca_id = self
+                    .value_or_default(
+                        ("server.listener", id, "tls.client-auth.ca-certificate"),
+                        "server.tls.client-auth.ca-certificate",
+                    )
+                    .ok_or_else(|| {
+                        format!("Undefined client CA certificate id for listener {id:?}.")
+                    })?;
+                let mut roots = RootCertStore::empty();
+                for cert in self.rustls_certificate(ca_id)? {
+                    roots.add(&cert).map_err(|err| {
+                        format!("Invalid client CA certificate id {ca_id:?}: {err}")
+                    })?;
+                }
+                if self
+                    .property_or_default(
+                        ("server.listener", id, "tls.client-auth.required"),
+                        "server.tls.client-auth.required",
+                    )?
+                    .unwrap_or(false)
+                {
+                    AllowAnyAuthenticatedClient::new(roots).boxed()
+                } else {
+                    AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed()
+                }
+            } else {
+                NoClientAuth::boxed()
+            };
+
             // Build server config
             let mut config = ServerConfig::builder()
                 .with_cipher_suites(if !ciphers.is_empty() {
@@ -173,7 +214,7 @@ impl Config {
                     TLS12_VERSION
                 })
                 .map_err(|err| format!("Failed to build TLS config: {err}"))?
-                .with_client_cert_verifier(NoClientAuth::boxed())
+                .with_client_cert_verifier(client_verifier)
                 .with_cert_resolver(Arc::new(CertificateResolver {
                     resolver: if has_sni { resolver.into() } else { None },
                     default_cert,
@@ -31,9 +31,34 @@ pub enum DeliveryEvent {
         message: IngestMessage,
         result_tx: oneshot::Sender<Vec<DeliveryResult>>,
     },
+    Suppression(SuppressionEvent),
     Stop,
 }
 
+#[derive(Debug)]
+pub enum SuppressionEvent {
+    Add {
+        addresses: Vec<SuppressedAddress>,
+    },
+    List {
+        domain: Option<String>,
+        result_tx: oneshot::Sender<Vec<SuppressedAddress>>,
+    },
+    Clear {
+        domain: Option<String>,
+        address: Option<String>,
+        result_tx: oneshot::Sender<usize>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuppressedAddress {
+    pub domain: String,
+    pub address: String,
+    pub reason: String,
+    pub created: u64,
+}
+
 #[derive(Debug)]
 pub struct IngestMessage {
     pub sender_address: String,
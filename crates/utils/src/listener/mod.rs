@@ -28,7 +28,11 @@ use tokio::{
     net::TcpStream,
     sync::watch,
 };
-use tokio_rustls::TlsAcceptor;
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+use x509_parser::{
+    extensions::GeneralName,
+    prelude::{FromDer, X509Certificate},
+};
 
 use crate::config::ServerProtocol;
 
@@ -63,3 +67,32 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
     fn spawn(&self, session: SessionData<TcpStream>);
     fn shutdown(&self);
 }
+
+/// Returns the identity presented in the client certificate, if any. The first
+/// e-mail address found in the certificate is preferred over its common name.
+pub fn tls_client_identity<T>(stream: &TlsStream<T>) -> Option<String> {
+    let (_, cert) = X509Certificate::from_der(
+        stream
+            .get_ref()
+            .1
+            .peer_certificates()?
+            .first()?
+            .0
+            .as_slice(),
+    )
+    .ok()?;
+
+    if let Ok(Some(san)) = cert.subject_alternative_name() {
+        for name in &san.value.general_names {
+            if let GeneralName::RFC822Name(email) = name {
+                return Some(email.to_lowercase());
+            }
+        }
+    }
+
+    cert.subject()
+        .iter_email()
+        .chain(cert.subject().iter_common_name())
+        .find_map(|attr| attr.as_str().ok())
+        .map(|identity| identity.to_lowercase())
+}
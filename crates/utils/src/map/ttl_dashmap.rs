@@ -28,6 +28,7 @@ pub trait TtlMap<K, V>: Sized {
         Q: Hash + Eq;
     fn insert_with_ttl(&self, name: K, value: V, valid_until: Instant) -> V;
     fn cleanup(&self);
+    fn retain_items(&self, f: impl Fn(&V) -> bool);
 }
 
 impl<K: Hash + Eq, V: Clone> TtlMap<K, V> for TtlDashMap<K, V> {
@@ -64,4 +65,8 @@ impl<K: Hash + Eq, V: Clone> TtlMap<K, V> for TtlDashMap<K, V> {
     fn cleanup(&self) {
         self.retain(|_, entry| entry.valid_until >= Instant::now());
     }
+
+    fn retain_items(&self, f: impl Fn(&V) -> bool) {
+        self.retain(|_, entry| f(&entry.item));
+    }
 }
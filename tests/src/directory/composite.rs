@@ -0,0 +1,173 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use directory::{config::ConfigDirectory, AuthResult};
+use mail_send::Credentials;
+use utils::config::Config;
+
+const CONFIG: &str = r#"
+[directory."primary"]
+type = "memory"
+
+[[directory."primary".users]]
+name = "john"
+secret = "12345"
+email = "john@example.org"
+
+[[directory."primary".users]]
+name = "bill"
+secret = "bill123"
+email = "bill@example.org"
+
+[directory."secondary"]
+type = "memory"
+
+[[directory."secondary".users]]
+name = "john"
+secret = "abcde"
+email = "john@example.net"
+
+[[directory."secondary".users]]
+name = "jane"
+secret = "qwerty"
+email = "jane@example.net"
+email-list = ["info@example.net"]
+
+[directory."secondary".lookup]
+domains = ["example.com"]
+
+[directory."composite"]
+type = "composite"
+directories = ["primary", "secondary"]
+
+[directory."composite".routing]
+secondary = ["example.net"]
+"#;
+
+#[tokio::test]
+async fn composite_directory() {
+    let directories = Config::parse(CONFIG).unwrap().parse_directory().unwrap();
+    let handle = directories.directories.get("composite").unwrap();
+
+    // Directories are tried in order, the first one to know the account decides
+    for (username, secret, expect) in [
+        ("john", "12345", Some("john")),
+        ("john", "abcde", None),
+        ("jane", "qwerty", Some("jane")),
+        ("jane", "wrong", None),
+        ("bill", "bill123", Some("bill")),
+        ("unknown", "12345", None),
+    ] {
+        assert_eq!(
+            handle
+                .authenticate(&Credentials::Plain {
+                    username: username.to_string(),
+                    secret: secret.to_string(),
+                })
+                .await
+                .unwrap()
+                .map(|p| p.name),
+            expect.map(|name| name.to_string()),
+            "{username}/{secret}"
+        );
+    }
+    for (username, secret, expect) in [
+        ("john", "abcde", AuthResult::Failure),
+        ("unknown", "12345", AuthResult::NotFound),
+    ] {
+        assert_eq!(
+            handle
+                .try_authenticate(&Credentials::Plain {
+                    username: username.to_string(),
+                    secret: secret.to_string(),
+                })
+                .await
+                .unwrap(),
+            expect,
+            "{username}/{secret}"
+        );
+    }
+    assert_eq!(
+        handle.principal("jane").await.unwrap().map(|p| p.name),
+        Some("jane".to_string())
+    );
+
+    // Results are merged across directories
+    assert_eq!(
+        handle.emails_by_name("john").await.unwrap(),
+        vec![
+            "john@example.org".to_string(),
+            "john@example.net".to_string()
+        ]
+    );
+    assert_eq!(
+        handle.vrfy("john").await.unwrap(),
+        vec![
+            "john@example.org".to_string(),
+            "john@example.net".to_string()
+        ]
+    );
+
+    // Routed domains are only looked up in their directory
+    assert_eq!(
+        handle.names_by_email("john@example.net").await.unwrap(),
+        vec!["john".to_string()]
+    );
+    assert_eq!(
+        handle.names_by_email("john@example.org").await.unwrap(),
+        vec!["john".to_string()]
+    );
+    assert!(handle.rcpt("jane@example.net").await.unwrap());
+    assert!(handle.rcpt("bill@example.org").await.unwrap());
+    assert!(!handle.rcpt("bill@example.net").await.unwrap());
+    assert_eq!(
+        handle.expn("info@example.net").await.unwrap(),
+        vec!["jane@example.net".to_string()]
+    );
+
+    // Local domains
+    for (domain, expect) in [
+        ("example.org", true),
+        ("example.net", true),
+        ("example.com", true),
+        ("other.org", false),
+    ] {
+        assert_eq!(
+            handle.is_local_domain(domain).await.unwrap(),
+            expect,
+            "{domain}"
+        );
+    }
+
+    // Composites can only reference existing non-composite directories
+    for invalid in [
+        "[directory.\"composite\"]\ntype = \"composite\"\ndirectories = [\"missing\"]\n",
+        concat!(
+            "[directory.\"a\"]\ntype = \"composite\"\ndirectories = [\"b\"]\n",
+            "[directory.\"b\"]\ntype = \"memory\"\n",
+            "[directory.\"c\"]\ntype = \"composite\"\ndirectories = [\"a\"]\n"
+        ),
+    ] {
+        assert!(Config::parse(invalid).unwrap().parse_directory().is_err());
+    }
+}
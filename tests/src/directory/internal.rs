@@ -0,0 +1,270 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use directory::{
+    config::ConfigDirectory,
+    internal::manage::{ManageError, PrincipalData, PrincipalUpdate},
+    Principal, Type,
+};
+use mail_send::Credentials;
+use store::Store;
+use utils::config::Config;
+
+use crate::store::TempDir;
+
+const CONFIG: &str = r#"
+store.blob.type = "local"
+store.blob.local.path = "{PATH}"
+store.db.path = "{PATH}/sqlite.db"
+
+[directory."internal"]
+type = "internal"
+
+[directory."internal".options]
+catch-all = true
+subaddressing = true
+"#;
+
+#[tokio::test]
+async fn internal_directory() {
+    let temp_dir = TempDir::new("internal_directory_tests", true);
+    let config = Config::parse(&CONFIG.replace("{PATH}", temp_dir.path.to_str().unwrap())).unwrap();
+    let store = Arc::new(Store::open(&config).await.unwrap());
+    store.destroy().await;
+    let mut directories = config.parse_directory().unwrap();
+    let manager = directories.internal.take().unwrap();
+    manager.set_store(store);
+    let handle = directories.directories.remove("internal").unwrap();
+
+    // Addresses require a local domain
+    assert!(matches!(
+        manager
+            .create_principal(PrincipalData {
+                name: "john".to_string(),
+                typ: Type::Individual,
+                emails: vec!["john@example.org".to_string()],
+                ..Default::default()
+            })
+            .await,
+        Err(ManageError::Invalid(_))
+    ));
+    manager.create_domain("example.org").await.unwrap();
+    assert!(matches!(
+        manager.create_domain("example.org").await,
+        Err(ManageError::AlreadyExists(_))
+    ));
+    assert!(handle.is_local_domain("example.org").await.unwrap());
+    assert!(!handle.is_local_domain("other.org").await.unwrap());
+
+    // Create principals
+    manager
+        .create_principal(PrincipalData {
+            name: "sales".to_string(),
+            typ: Type::Group,
+            description: "Sales department".to_string().into(),
+            emails: vec!["sales@example.org".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    for (name, secret, emails) in [
+        (
+            "john",
+            "12345",
+            vec!["john@example.org", "john.doe@example.org"],
+        ),
+        ("jane", "abcde", vec!["jane@example.org"]),
+    ] {
+        manager
+            .create_principal(PrincipalData {
+                name: name.to_string(),
+                typ: Type::Individual,
+                secrets: vec![secret.to_string()],
+                emails: emails.into_iter().map(|e| e.to_string()).collect(),
+                member_of: vec!["sales".to_string()],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
+    assert!(matches!(
+        manager
+            .create_principal(PrincipalData {
+                name: "bill".to_string(),
+                typ: Type::Individual,
+                emails: vec!["john@example.org".to_string()],
+                ..Default::default()
+            })
+            .await,
+        Err(ManageError::AlreadyExists(_))
+    ));
+    assert!(matches!(
+        manager
+            .create_principal(PrincipalData {
+                name: "bill".to_string(),
+                typ: Type::Individual,
+                member_of: vec!["john".to_string()],
+                ..Default::default()
+            })
+            .await,
+        Err(ManageError::Invalid(_))
+    ));
+
+    // Test authentication
+    let mut principal = handle
+        .authenticate(&Credentials::Plain {
+            username: "john".to_string(),
+            secret: "12345".to_string(),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(principal.secrets[0].starts_with("$argon2"));
+    principal.secrets.clear();
+    assert_eq!(
+        principal,
+        Principal {
+            name: "john".to_string(),
+            typ: Type::Individual,
+            member_of: vec!["sales".to_string()],
+            ..Default::default()
+        }
+    );
+    assert!(handle
+        .authenticate(&Credentials::Plain {
+            username: "john".to_string(),
+            secret: "abcde".to_string(),
+        })
+        .await
+        .unwrap()
+        .is_none());
+
+    // Test lookups
+    assert_eq!(
+        handle.emails_by_name("john").await.unwrap(),
+        vec![
+            "john@example.org".to_string(),
+            "john.doe@example.org".to_string()
+        ]
+    );
+    assert_eq!(
+        handle
+            .names_by_email("john.doe+alias@example.org")
+            .await
+            .unwrap(),
+        vec!["john".to_string()]
+    );
+    let mut names = handle.names_by_email("sales@example.org").await.unwrap();
+    names.sort_unstable();
+    assert_eq!(names, vec!["jane".to_string(), "john".to_string()]);
+    let mut emails = handle.expn("sales@example.org").await.unwrap();
+    emails.sort_unstable();
+    assert_eq!(
+        emails,
+        vec![
+            "jane@example.org".to_string(),
+            "john@example.org".to_string()
+        ]
+    );
+    assert_eq!(
+        handle.vrfy("jane").await.unwrap(),
+        vec!["jane@example.org".to_string()]
+    );
+    assert!(handle.rcpt("jane+alias@example.org").await.unwrap());
+    assert!(!handle.rcpt("unknown@example.org").await.unwrap());
+
+    // Test updates
+    manager
+        .update_principal(
+            "john",
+            PrincipalUpdate {
+                description: "John Doe".to_string().into(),
+                quota: 1024.into(),
+                secret: "54321".to_string().into(),
+                add_emails: vec!["@example.org".to_string()],
+                remove_emails: vec!["john.doe@example.org".to_string()],
+                add_member_of: vec!["superusers".to_string()],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let principal = handle
+        .authenticate(&Credentials::Plain {
+            username: "john".to_string(),
+            secret: "54321".to_string(),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(principal.typ, Type::Superuser);
+    assert_eq!(principal.quota, 1024);
+    assert_eq!(principal.description(), Some("John Doe"));
+    assert!(handle.rcpt("anything@example.org").await.unwrap());
+    assert_eq!(
+        handle.names_by_email("anything@example.org").await.unwrap(),
+        vec!["john".to_string()]
+    );
+    assert_eq!(
+        manager
+            .list_principals(Some(Type::Individual))
+            .await
+            .unwrap(),
+        vec!["jane".to_string(), "john".to_string()]
+    );
+    assert_eq!(
+        manager
+            .get_principal("sales")
+            .await
+            .unwrap()
+            .unwrap()
+            .members,
+        vec!["jane".to_string(), "john".to_string()]
+    );
+
+    // Test deletions
+    assert!(matches!(
+        manager.delete_domain("example.org").await,
+        Err(ManageError::Invalid(_))
+    ));
+    manager.delete_principal("sales").await.unwrap();
+    assert!(handle.principal("sales").await.unwrap().is_none());
+    assert_eq!(
+        handle.names_by_email("sales@example.org").await.unwrap(),
+        vec!["john".to_string()]
+    );
+    assert_eq!(
+        handle.principal("jane").await.unwrap().unwrap().member_of,
+        Vec::<String>::new()
+    );
+    for name in ["john", "jane"] {
+        manager.delete_principal(name).await.unwrap();
+    }
+    assert!(manager.list_principals(None).await.unwrap().is_empty());
+    manager.delete_domain("example.org").await.unwrap();
+    assert!(manager.list_domains().await.unwrap().is_empty());
+
+    temp_dir.delete();
+}
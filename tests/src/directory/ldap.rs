@@ -23,10 +23,10 @@
 
 use std::fmt::Debug;
 
-use directory::{Principal, Type};
+use directory::{config::ConfigDirectory, Principal, Type};
 use mail_send::Credentials;
 
-use crate::directory::parse_config;
+use crate::directory::{parse_config, CONFIG};
 
 #[tokio::test]
 async fn ldap_directory() {
@@ -214,6 +214,48 @@ async fn ldap_directory() {
         handle.expn("john@example.org").await.unwrap(),
         Vec::<String>::new(),
     );
+
+    // Test bind authentication
+    let handle = utils::config::Config::parse(&format!(
+        "{}\n[directory.\"ldap\".bind.auth]\nenable = true\n",
+        CONFIG
+    ))
+    .unwrap()
+    .parse_directory()
+    .unwrap()
+    .directories
+    .remove("ldap")
+    .unwrap();
+    assert_eq!(
+        handle
+            .authenticate(&Credentials::Plain {
+                username: "john".to_string(),
+                secret: "12345".to_string()
+            })
+            .await
+            .unwrap()
+            .unwrap()
+            .name,
+        "john"
+    );
+    for secret in ["invalid", ""] {
+        assert!(handle
+            .authenticate(&Credentials::Plain {
+                username: "john".to_string(),
+                secret: secret.to_string()
+            })
+            .await
+            .unwrap()
+            .is_none());
+    }
+    assert!(handle
+        .authenticate(&Credentials::Plain {
+            username: "unknown".to_string(),
+            secret: "12345".to_string()
+        })
+        .await
+        .unwrap()
+        .is_none());
 }
 
 fn compare_sorted<T: Eq + Debug>(v1: Vec<T>, v2: Vec<T>) {
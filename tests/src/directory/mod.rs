@@ -21,7 +21,9 @@
  * for more details.
 */
 
+pub mod composite;
 pub mod imap;
+pub mod internal;
 pub mod ldap;
 pub mod smtp;
 pub mod sql;
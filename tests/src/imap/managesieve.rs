@@ -147,6 +147,18 @@ pub async fn test() {
     sieve.send("DELETESCRIPT \"minimalist script\"").await;
     sieve.assert_read(ResponseType::Ok).await;
 
+    // The vacation script is managed through VacationResponse/set
+    sieve.send("DELETESCRIPT \"vacation\"").await;
+    sieve
+        .assert_read(ResponseType::No)
+        .await
+        .assert_contains("managed by the server");
+    sieve.send("RENAMESCRIPT \"vacation\" \"holidays\"").await;
+    sieve
+        .assert_read(ResponseType::No)
+        .await
+        .assert_contains("managed by the server");
+
     sieve.send("LISTSCRIPTS").await;
     sieve
         .assert_read(ResponseType::Ok)
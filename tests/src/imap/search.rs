@@ -135,4 +135,58 @@ pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
     imap.assert_read(Type::Tagged, ResponseType::Ok)
         .await
         .assert_contains("COUNT 10 ALL 6,4:5,1,10,9,3,7:8,2");
+
+    // Fuzzy search
+    imap_check.send("UID SEARCH SUBJECT argentina").await;
+    let expected = imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .into_iter()
+        .next()
+        .unwrap();
+    assert_ne!(expected, "* SEARCH");
+    imap_check.send("UID SEARCH SUBJECT argentna").await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals("* SEARCH");
+    imap_check.send("UID SEARCH FUZZY SUBJECT argentna").await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals(&expected);
+    imap_check
+        .send("UID SEARCH FUZZY (SUBJECT argentna NOT FROM nathaniel)")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals(&expected);
+    imap_check
+        .send("UID SEARCH UID 1:* FUZZY SUBJECT argentna")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals(&expected);
+
+    // Relevancy
+    imap.send("UID SEARCH RETURN (RELEVANCY) FUZZY SUBJECT argentna")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains(&format!(
+            "UID ALL {} RELEVANCY (100)",
+            expected.trim_start_matches("* SEARCH ")
+        ));
+    imap_check
+        .send("UID SORT (RELEVANCY) UTF-8 FUZZY SUBJECT argentna")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals(&format!(
+            "* SORT {}",
+            expected.trim_start_matches("* SEARCH ")
+        ));
 }
@@ -45,7 +45,10 @@ use crate::{
     directory::sql::{
         add_to_group, create_test_group_with_email, create_test_user_with_email, remove_from_group,
     },
-    jmap::{mailbox::destroy_all_mailboxes, test_account_login},
+    jmap::{
+        mailbox::destroy_all_mailboxes, share_notification::destroy_all_share_notifications,
+        test_account_login,
+    },
 };
 
 pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
@@ -774,6 +777,7 @@ pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
 
     // Destroy test account data
     for id in [john_id, bill_id, jane_id, sales_id] {
+        destroy_all_share_notifications(&id.to_string()).await;
         admin_client.set_default_account_id(&id.to_string());
         destroy_all_mailboxes(admin_client).await;
     }
@@ -0,0 +1,188 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use jmap::{mailbox::INBOX_ID, JMAP};
+use jmap_client::client::Client;
+use jmap_proto::types::id::Id;
+
+use crate::{
+    directory::sql::create_test_user_with_email,
+    jmap::{jmap_request, mailbox::destroy_all_mailboxes},
+};
+
+pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
+    println!("Running blob tests...");
+    let directory = server.directory.as_ref();
+    create_test_user_with_email(directory, "jdoe@example.com", "12345", "John Doe").await;
+    let account_id = Id::from(server.get_account_id("jdoe@example.com").await.unwrap());
+    server
+        .store
+        .delete_account_blobs(account_id.document_id())
+        .await
+        .unwrap();
+
+    let inbox_id = Id::from(INBOX_ID).to_string();
+
+    // Blob/upload with inline data and references to blobs created in the same call,
+    // followed by Blob/get and Email/import referencing the created blobs
+    let response = jmap_request(
+        "jdoe@example.com",
+        "12345",
+        serde_json::json!({
+            "using": [
+                "urn:ietf:params:jmap:core",
+                "urn:ietf:params:jmap:mail",
+                "urn:ietf:params:jmap:blob"
+            ],
+            "methodCalls": [
+                ["Blob/upload", {
+                    "accountId": account_id.to_string(),
+                    "create": {
+                        "a_hello": {"data": [{"data:asText": "Hello, "}]},
+                        "b_world": {
+                            "type": "text/plain",
+                            "data": [{"blobId": "#a_hello"}, {"data:asBase64": "d29ybGQh"}]
+                        },
+                        "c_message": {
+                            "type": "message/rfc822",
+                            "data": [
+                                {"data:asText": "From: john@example.com\r\nSubject: test\r\n\r\n"},
+                                {"blobId": "#b_world", "offset": 7}
+                            ]
+                        },
+                        "d_invalid": {"data": [{"blobId": "#z_unknown"}]}
+                    }
+                }, "0"],
+                ["Blob/get", {
+                    "accountId": account_id.to_string(),
+                    "ids": ["#b_world"],
+                    "properties": ["data:asText", "digest:sha-256", "size"]
+                }, "1"],
+                ["Blob/get", {
+                    "accountId": account_id.to_string(),
+                    "ids": ["#b_world"],
+                    "properties": ["data:asBase64"],
+                    "offset": 7,
+                    "length": 100
+                }, "2"],
+                ["Email/import", {
+                    "accountId": account_id.to_string(),
+                    "emails": {
+                        "i": {
+                            "blobId": "#c_message",
+                            "mailboxIds": {(&inbox_id): true}
+                        }
+                    }
+                }, "3"]
+            ]
+        }),
+    )
+    .await;
+    let upload = &response["methodResponses"][0][1];
+    assert_eq!(upload["created"]["a_hello"]["size"], 7);
+    assert_eq!(upload["created"]["b_world"]["size"], 13);
+    assert_eq!(upload["created"]["b_world"]["type"], "text/plain");
+    assert_eq!(upload["created"]["c_message"]["size"], 47);
+    assert_eq!(upload["notCreated"]["d_invalid"]["type"], "blobNotFound");
+    let blob = &response["methodResponses"][1][1]["list"][0];
+    assert_eq!(blob["id"], upload["created"]["b_world"]["id"]);
+    assert_eq!(blob["data:asText"], "Hello, world!");
+    assert_eq!(
+        blob["digest:sha-256"],
+        "MV9b23bQeMQ7isAGTkoBZGErH853yGk0W/yUx1iU7dM="
+    );
+    assert_eq!(blob["size"], 13);
+    let blob = &response["methodResponses"][2][1]["list"][0];
+    assert_eq!(blob["data:asBase64"], "d29ybGQh");
+    assert_eq!(blob["isTruncated"], true);
+    let email_id = response["methodResponses"][3][1]["created"]["i"]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let thread_id = response["methodResponses"][3][1]["created"]["i"]["threadId"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Blob/lookup using a result reference to the imported email's blobId
+    let response = jmap_request(
+        "jdoe@example.com",
+        "12345",
+        serde_json::json!({
+            "using": [
+                "urn:ietf:params:jmap:core",
+                "urn:ietf:params:jmap:mail",
+                "urn:ietf:params:jmap:blob"
+            ],
+            "methodCalls": [
+                ["Email/get", {
+                    "accountId": account_id.to_string(),
+                    "ids": [&email_id],
+                    "properties": ["blobId"]
+                }, "0"],
+                ["Blob/lookup", {
+                    "accountId": account_id.to_string(),
+                    "typeNames": ["Email", "Mailbox", "Thread"],
+                    "#ids": {
+                        "resultOf": "0",
+                        "name": "Email/get",
+                        "path": "/list/*/blobId"
+                    }
+                }, "1"],
+                ["Blob/lookup", {
+                    "accountId": account_id.to_string(),
+                    "typeNames": ["Email", "Calendar"],
+                    "ids": []
+                }, "2"]
+            ]
+        }),
+    )
+    .await;
+    let lookup = &response["methodResponses"][1][1]["list"][0];
+    assert_eq!(
+        lookup["id"],
+        response["methodResponses"][0][1]["list"][0]["blobId"]
+    );
+    assert_eq!(lookup["matchedIds"]["Email"], serde_json::json!([email_id]));
+    assert_eq!(
+        lookup["matchedIds"]["Thread"],
+        serde_json::json!([thread_id])
+    );
+    assert_eq!(
+        lookup["matchedIds"]["Mailbox"],
+        serde_json::json!([inbox_id])
+    );
+    assert_eq!(response["methodResponses"][2][1]["type"], "unknownDataType");
+
+    // Remove test data
+    admin_client.set_default_account_id(account_id.to_string());
+    destroy_all_mailboxes(admin_client).await;
+    server
+        .store
+        .delete_account_blobs(account_id.document_id())
+        .await
+        .unwrap();
+    server.store.assert_is_empty().await;
+}
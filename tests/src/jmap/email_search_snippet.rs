@@ -28,7 +28,7 @@ use jmap_client::{client::Client, core::query, email::query::Filter};
 use jmap_proto::types::id::Id;
 use store::ahash::AHashMap;
 
-use crate::jmap::mailbox::destroy_all_mailboxes;
+use crate::jmap::{jmap_request, mailbox::destroy_all_mailboxes};
 
 pub async fn test(server: Arc<JMAP>, client: &mut Client) {
     println!("Running SearchSnippet tests...");
@@ -177,6 +177,45 @@ pub async fn test(server: Arc<JMAP>, client: &mut Client) {
         );
     }
 
+    // Sort by relevance
+    for (is_ascending, expected) in [
+        (false, ["text_plain", "mixed"]),
+        (true, ["mixed", "text_plain"]),
+    ] {
+        let response = jmap_request(
+            "admin",
+            "secret",
+            serde_json::json!({
+                "using": [
+                    "urn:ietf:params:jmap:core",
+                    "urn:ietf:params:jmap:mail"
+                ],
+                "methodCalls": [
+                    ["Email/query", {
+                        "accountId": Id::from(1u64).to_string(),
+                        "filter": {
+                            "operator": "OR",
+                            "conditions": [
+                                {"text": "abidjan"},
+                                {"text": "babel"}
+                            ]
+                        },
+                        "sort": [{"property": "relevance", "isAscending": is_ascending}]
+                    }, "0"]
+                ]
+            }),
+        )
+        .await;
+        assert_eq!(
+            response["methodResponses"][0][1]["ids"],
+            serde_json::json!(expected
+                .iter()
+                .map(|name| email_ids.get(name).unwrap())
+                .collect::<Vec<_>>()),
+            "{response}"
+        );
+    }
+
     // Destroy test data
     destroy_all_mailboxes(client).await;
     server.store.assert_is_empty().await;
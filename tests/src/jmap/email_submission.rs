@@ -307,10 +307,7 @@ pub async fn test(server: Arc<JMAP>, client: &mut Client) {
         .await
         .unwrap()
         .unwrap();
-    assert_eq!(
-        email_submission.undo_status().unwrap(),
-        &UndoStatus::Pending
-    );
+    assert_eq!(email_submission.undo_status().unwrap(), &UndoStatus::Final);
     assert_eq!(
         email_submission.delivery_status().unwrap(),
         &AHashMap::from_iter([
@@ -349,45 +346,15 @@ pub async fn test(server: Arc<JMAP>, client: &mut Client) {
         ])
     );
 
-    // Cancel submission
-    client
+    // Submissions that were already released can't be canceled
+    match client
         .email_submission_change_status(&email_submission_id, UndoStatus::Canceled)
         .await
-        .unwrap();
-    let email_submission = client
-        .email_submission_get(&email_submission_id, None)
-        .await
-        .unwrap()
-        .unwrap();
-    assert_eq!(
-        email_submission.undo_status().unwrap(),
-        &UndoStatus::Canceled
-    );
-    assert_eq!(
-        email_submission.delivery_status().unwrap(),
-        &AHashMap::from_iter([
-            (
-                "nonexistant@example.com".to_string(),
-                DeliveryStatus::new(
-                    "550 5.1.2 Mailbox does not exist.",
-                    Delivered::No,
-                    Displayed::Unknown
-                )
-            ),
-            (
-                "delay@other_domain.com".to_string(),
-                DeliveryStatus::new("250 2.1.5 Queued", Delivered::Unknown, Displayed::Unknown)
-            ),
-            (
-                "fail@test.com".to_string(),
-                DeliveryStatus::new("250 2.1.5 Queued", Delivered::Unknown, Displayed::Unknown)
-            ),
-            (
-                "tim@foobar.com".to_string(),
-                DeliveryStatus::new("250 2.1.5 Queued", Delivered::Unknown, Displayed::Unknown)
-            ),
-        ])
-    );
+        .unwrap_err()
+    {
+        Error::Set(err) => assert_eq!(err.error(), &SetErrorType::CannotUnsend),
+        err => panic!("Unexpected error: {:?}", err),
+    }
 
     // Confirm that the sendAt property is updated when using FUTURERELEASE
     let hold_until = DateTime::parse_rfc3339("2079-11-20T05:00:00Z")
@@ -422,6 +389,39 @@ pub async fn test(server: Arc<JMAP>, client: &mut Client) {
         ),])
     );
 
+    // Cancel the held submission before its release time
+    client
+        .email_submission_change_status(&email_submission_id, UndoStatus::Canceled)
+        .await
+        .unwrap();
+    let email_submission = client
+        .email_submission_get(&email_submission_id, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        email_submission.undo_status().unwrap(),
+        &UndoStatus::Canceled
+    );
+    assert_eq!(
+        email_submission.delivery_status().unwrap(),
+        &AHashMap::from_iter([(
+            "jane_smith@remote.org".to_string(),
+            DeliveryStatus::new("250 2.1.5 Queued", Delivered::Unknown, Displayed::Unknown)
+        ),])
+    );
+    expect_nothing(&mut smtp_rx).await;
+
+    // Canceling it again should fail
+    match client
+        .email_submission_change_status(&email_submission_id, UndoStatus::Canceled)
+        .await
+        .unwrap_err()
+    {
+        Error::Set(err) => assert_eq!(err.error(), &SetErrorType::CannotUnsend),
+        err => panic!("Unexpected error: {:?}", err),
+    }
+
     // Verify onSuccessUpdateEmail action
     let mut request = client.build();
     let set_request = request.set_email_submission();
@@ -0,0 +1,377 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{sync::Arc, time::Instant};
+
+use jmap::{identity::verify::TEST_VERIFY_EXTERNAL, mailbox::INBOX_ID, JMAP};
+use jmap_client::{
+    client::Client, core::set::SetErrorType, email_submission::query::Filter, Error,
+};
+use jmap_proto::types::id::Id;
+
+use crate::{
+    directory::sql::create_test_user_with_email,
+    jmap::{
+        email_submission::{expect_message_delivery, spawn_mock_smtp_server},
+        jmap_request,
+        mailbox::destroy_all_mailboxes,
+    },
+};
+
+pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
+    println!("Running Identity tests...");
+    // Start mock SMTP server
+    let (mut smtp_rx, smtp_settings) = spawn_mock_smtp_server();
+    server.smtp.resolvers.dns.ipv4_add(
+        "localhost",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + std::time::Duration::from_secs(10),
+    );
+    TEST_VERIFY_EXTERNAL.store(true, std::sync::atomic::Ordering::Relaxed);
+
+    // Create a test account
+    let directory = server.directory.as_ref();
+    create_test_user_with_email(directory, "jane@example.net", "12345", "Jane Doe").await;
+    let account_id = Id::from(server.get_account_id("jane@example.net").await.unwrap()).to_string();
+    admin_client.set_default_account_id(&account_id);
+    let inbox_id = Id::from(INBOX_ID).to_string();
+
+    // Create an identity with a signature, one using an external address
+    // and one using a local address that belongs to someone else
+    let response = identity_request(serde_json::json!([
+        ["Identity/set", {
+            "accountId": &account_id,
+            "create": {
+                "i1": {
+                    "name": "Jane Doe",
+                    "email": "jane@example.net",
+                    "textSignature": "Jane Doe\nHead of TPS Reports",
+                    "htmlSignature": "<b>Jane Doe</b><br>Head of TPS Reports"
+                },
+                "i2": {
+                    "name": "Jane Doe",
+                    "email": "jane.doe@remote.org"
+                },
+                "i3": {
+                    "name": "John Doe",
+                    "email": "john@example.net"
+                }
+            }
+        }, "0"]
+    ]))
+    .await;
+    let created = &response["methodResponses"][0][1]["created"];
+    let signed_id = created["i1"]["id"].as_str().unwrap().to_string();
+    let external_id = created["i2"]["id"].as_str().unwrap().to_string();
+    assert_eq!(
+        response["methodResponses"][0][1]["notCreated"]["i3"]["type"],
+        "invalidProperties"
+    );
+    let response = identity_request(serde_json::json!([
+        ["Identity/get", {
+            "accountId": &account_id,
+            "ids": [&signed_id, &external_id],
+            "properties": ["email", "isVerified", "verificationCode"]
+        }, "0"]
+    ]))
+    .await;
+    let list = &response["methodResponses"][0][1]["list"];
+    assert_eq!(list[0]["isVerified"], true);
+    assert_eq!(list[1]["isVerified"], false);
+    assert_eq!(list[1]["verificationCode"], serde_json::Value::Null);
+
+    // A verification code should have been sent to the external address
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_eq!(message.mail_from, "<>");
+    assert_eq!(message.rcpt_to, vec!["<jane.doe@remote.org>".to_string()]);
+    let code = message
+        .message
+        .split_once("Verification code: ")
+        .and_then(|(_, code)| code.split_whitespace().next())
+        .unwrap()
+        .to_string();
+
+    // Verification messages are throttled per account
+    let create = (1..=5)
+        .map(|n| {
+            (
+                format!("i{n}"),
+                serde_json::json!({
+                    "name": "Jane Doe",
+                    "email": format!("jane{n}@remote.org")
+                }),
+            )
+        })
+        .collect::<serde_json::Map<String, serde_json::Value>>();
+    let response = identity_request(serde_json::json!([
+        ["Identity/set", {
+            "accountId": &account_id,
+            "create": create
+        }, "0"]
+    ]))
+    .await;
+    let response = &response["methodResponses"][0][1];
+    let throttled_ids = response["created"]
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|identity| identity["id"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(throttled_ids.len(), 4);
+    assert_eq!(response["notCreated"]["i5"]["type"], "rateLimit");
+    for _ in 0..4 {
+        expect_message_delivery(&mut smtp_rx).await;
+    }
+
+    // Submissions using unverified identities should fail
+    let email_id = admin_client
+        .email_import(
+            concat!(
+                "From: jane.doe@remote.org\r\n",
+                "To: bill@remote.org\r\n",
+                "Subject: TPS reports\r\n",
+                "\r\n",
+                "Did you get the memo?\r\n"
+            )
+            .as_bytes()
+            .to_vec(),
+            [&inbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    match admin_client
+        .email_submission_create(&email_id, &external_id)
+        .await
+        .unwrap_err()
+    {
+        Error::Set(err) => assert_eq!(err.error(), &SetErrorType::ForbiddenFrom),
+        err => panic!("Unexpected error: {:?}", err),
+    }
+
+    // Verify the external address
+    for (code, expected_error) in [
+        ("invalid", Some("invalidProperties")),
+        (code.as_str(), None),
+        (code.as_str(), Some("invalidProperties")),
+    ] {
+        let response = identity_request(serde_json::json!([
+            ["Identity/set", {
+                "accountId": &account_id,
+                "update": {
+                    &external_id: {
+                        "verificationCode": code
+                    }
+                }
+            }, "0"]
+        ]))
+        .await;
+        let response = &response["methodResponses"][0][1];
+        if let Some(expected_error) = expected_error {
+            assert_eq!(response["notUpdated"][&external_id]["type"], expected_error);
+        } else {
+            assert!(response["updated"]
+                .as_object()
+                .unwrap()
+                .contains_key(&external_id));
+        }
+    }
+
+    // Verified identities can be used for submission
+    admin_client
+        .email_submission_create(&email_id, &external_id)
+        .await
+        .unwrap();
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_eq!(message.mail_from, "<jane.doe@remote.org>");
+    assert!(message.message.contains("Did you get the memo?"));
+    assert!(!message.message.contains("-- "));
+
+    // Signatures and domain footers should be added to both text and HTML parts
+    let email_id = admin_client
+        .email_import(
+            concat!(
+                "From: jane@example.net\r\n",
+                "To: bill@remote.org\r\n",
+                "Subject: TPS reports\r\n",
+                "Content-Type: multipart/alternative; boundary=\"bnd\"\r\n",
+                "\r\n",
+                "--bnd\r\n",
+                "Content-Type: text/plain; charset=utf-8\r\n",
+                "\r\n",
+                "Did you get the memo?\r\n",
+                "--bnd\r\n",
+                "Content-Type: text/html; charset=utf-8\r\n",
+                "\r\n",
+                "<html><body><p>Did you get the memo?</p></body></html>\r\n",
+                "--bnd--\r\n"
+            )
+            .as_bytes()
+            .to_vec(),
+            [&inbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    admin_client
+        .email_submission_create(&email_id, &signed_id)
+        .await
+        .unwrap();
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_eq!(message.mail_from, "<jane@example.net>");
+    for expected in [
+        concat!(
+            "Did you get the memo?\r\n\r\n",
+            "-- \r\nJane Doe\r\nHead of TPS Reports\r\n\r\n",
+            "Example Net Inc. -- Confidential\r\n",
+            "--bnd\r\n"
+        ),
+        concat!(
+            "<p>Did you get the memo?</p>",
+            "<div class=\"signature\">-- <br><b>Jane Doe</b><br>Head of TPS Reports</div>",
+            "<div class=\"footer\"><p>Example Net Inc. -- Confidential</p></div>",
+            "</body></html>"
+        ),
+    ] {
+        assert!(
+            message.message.contains(expected),
+            "missing {expected:?} in {}",
+            message.message
+        );
+    }
+
+    // Non-ASCII signatures are not added to 7bit parts
+    let response = identity_request(serde_json::json!([
+        ["Identity/set", {
+            "accountId": &account_id,
+            "create": {
+                "i1": {
+                    "name": "Jane Doe",
+                    "email": "jane@example.net",
+                    "textSignature": "Jane Doé"
+                }
+            }
+        }, "0"]
+    ]))
+    .await;
+    let accented_id = response["methodResponses"][0][1]["created"]["i1"]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let email_id = admin_client
+        .email_import(
+            concat!(
+                "From: jane@example.net\r\n",
+                "To: bill@remote.org\r\n",
+                "Subject: TPS reports\r\n",
+                "Content-Type: text/plain; charset=utf-8\r\n",
+                "Content-Transfer-Encoding: 7bit\r\n",
+                "\r\n",
+                "Did you get the memo?\r\n"
+            )
+            .as_bytes()
+            .to_vec(),
+            [&inbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    admin_client
+        .email_submission_create(&email_id, &accented_id)
+        .await
+        .unwrap();
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert!(message.message.contains("Did you get the memo?"));
+    assert!(!message.message.contains("-- \r\n"));
+
+    // Signatures and footers already present should not be added again
+    let email_id = admin_client
+        .email_import(
+            concat!(
+                "From: jane@example.net\r\n",
+                "To: bill@remote.org\r\n",
+                "Subject: Re: TPS reports\r\n",
+                "\r\n",
+                "Yeah, I got the memo.\r\n\r\n",
+                "-- \r\nJane Doe\r\nHead of TPS Reports\r\n\r\n",
+                "Example Net Inc. -- Confidential\r\n"
+            )
+            .as_bytes()
+            .to_vec(),
+            [&inbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    smtp_settings.lock().do_stop = true;
+    admin_client
+        .email_submission_create(&email_id, &signed_id)
+        .await
+        .unwrap();
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_eq!(message.message.matches("Head of TPS Reports").count(), 1);
+    assert_eq!(message.message.matches("Confidential").count(), 1);
+
+    // Remove test data
+    admin_client.identity_destroy(&signed_id).await.unwrap();
+    admin_client.identity_destroy(&external_id).await.unwrap();
+    admin_client.identity_destroy(&accented_id).await.unwrap();
+    for id in throttled_ids {
+        admin_client.identity_destroy(&id).await.unwrap();
+    }
+    for id in admin_client
+        .email_submission_query(None::<Filter>, None::<Vec<_>>)
+        .await
+        .unwrap()
+        .take_ids()
+    {
+        admin_client.email_submission_destroy(&id).await.unwrap();
+    }
+    destroy_all_mailboxes(admin_client).await;
+    TEST_VERIFY_EXTERNAL.store(false, std::sync::atomic::Ordering::Relaxed);
+    server.store.assert_is_empty().await;
+}
+
+async fn identity_request(method_calls: serde_json::Value) -> serde_json::Value {
+    jmap_request(
+        "jane@example.net",
+        "12345",
+        serde_json::json!({
+            "using": [
+                "urn:ietf:params:jmap:core",
+                "urn:ietf:params:jmap:mail",
+                "urn:ietf:params:jmap:submission"
+            ],
+            "methodCalls": method_calls
+        }),
+    )
+    .await
+}
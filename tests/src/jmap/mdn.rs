@@ -0,0 +1,313 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{sync::Arc, time::Instant};
+
+use jmap::{mailbox::INBOX_ID, JMAP};
+use jmap_client::client::Client;
+use jmap_proto::types::id::Id;
+
+use crate::{
+    directory::sql::create_test_user_with_email,
+    jmap::{
+        email_set::assert_email_properties,
+        email_submission::{expect_message_delivery, spawn_mock_smtp_server},
+        jmap_request,
+        mailbox::destroy_all_mailboxes,
+    },
+};
+
+pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
+    println!("Running MDN tests...");
+    // Start mock SMTP server
+    let (mut smtp_rx, smtp_settings) = spawn_mock_smtp_server();
+    server.smtp.resolvers.dns.ipv4_add(
+        "localhost",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + std::time::Duration::from_secs(10),
+    );
+
+    // Create a test account with an identity
+    let directory = server.directory.as_ref();
+    create_test_user_with_email(directory, "jdoe@example.com", "12345", "John Doe").await;
+    let account_id = Id::from(server.get_account_id("jdoe@example.com").await.unwrap());
+    admin_client.set_default_account_id(account_id.to_string());
+    let identity_id = admin_client
+        .identity_create("John Doe", "jdoe@example.com")
+        .await
+        .unwrap()
+        .take_id();
+    let inbox_id = Id::from(INBOX_ID).to_string();
+
+    // Import an email requesting a read receipt, and one that does not
+    let email_id = admin_client
+        .email_import(
+            concat!(
+                "From: Bill <bill@remote.org>\r\n",
+                "To: jdoe@example.com\r\n",
+                "Subject: Did you read this?\r\n",
+                "Message-ID: <read-receipt@remote.org>\r\n",
+                "Disposition-Notification-To: Bill <bill@remote.org>\r\n",
+                "\r\n",
+                "Please let me know.\r\n"
+            )
+            .as_bytes()
+            .to_vec(),
+            [&inbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    let no_mdn_email_id = admin_client
+        .email_import(
+            b"From: bill@remote.org\r\nSubject: No receipt\r\n\r\ntest\r\n".to_vec(),
+            [&inbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+
+    // Send an MDN and flag the email as seen on success
+    let response = jmap_request(
+        "jdoe@example.com",
+        "12345",
+        serde_json::json!({
+            "using": [
+                "urn:ietf:params:jmap:core",
+                "urn:ietf:params:jmap:mail",
+                "urn:ietf:params:jmap:mdn"
+            ],
+            "methodCalls": [
+                ["MDN/send", {
+                    "accountId": account_id.to_string(),
+                    "identityId": &identity_id,
+                    "send": {
+                        "k1": {
+                            "forEmailId": &email_id,
+                            "textBody": "I have read your message.",
+                            "includeOriginalMessage": false,
+                            "disposition": {
+                                "actionMode": "manual-action",
+                                "sendingMode": "mdn-sent-manually",
+                                "type": "displayed"
+                            },
+                            "extensionFields": {"X-Test": "hello"}
+                        },
+                        "k2": {
+                            "forEmailId": &no_mdn_email_id,
+                            "disposition": {
+                                "actionMode": "manual-action",
+                                "sendingMode": "mdn-sent-manually",
+                                "type": "displayed"
+                            }
+                        },
+                        "k3": {
+                            "forEmailId": &email_id,
+                            "disposition": {
+                                "actionMode": "manual-action",
+                                "sendingMode": "mdn-sent-manually",
+                                "type": "unknown"
+                            }
+                        },
+                        "k4": {
+                            "forEmailId": &email_id,
+                            "reportingUA": "evil\r\nBcc: victim@remote.org",
+                            "disposition": {
+                                "actionMode": "manual-action",
+                                "sendingMode": "mdn-sent-manually",
+                                "type": "displayed"
+                            }
+                        },
+                        "k5": {
+                            "forEmailId": &email_id,
+                            "disposition": {
+                                "actionMode": "manual-action",
+                                "sendingMode": "mdn-sent-manually",
+                                "type": "displayed"
+                            },
+                            "extensionFields": {"X-Test": "hello\nBcc: victim@remote.org"}
+                        },
+                        "k6": {
+                            "forEmailId": &email_id,
+                            "disposition": {
+                                "actionMode": "manual-action",
+                                "sendingMode": "mdn-sent-manually",
+                                "type": "displayed"
+                            },
+                            "error": ["failed\r\n"]
+                        }
+                    },
+                    "onSuccessUpdateEmail": {
+                        "#k1": {"keywords/$seen": true}
+                    }
+                }, "0"]
+            ]
+        }),
+    )
+    .await;
+    let send = &response["methodResponses"][0][1];
+    assert_eq!(send["sent"]["k1"]["subject"], "Read: Did you read this?");
+    assert_eq!(
+        send["sent"]["k1"]["finalRecipient"],
+        "rfc822; jdoe@example.com"
+    );
+    assert_eq!(
+        send["sent"]["k1"]["originalMessageId"],
+        "<read-receipt@remote.org>"
+    );
+    assert_eq!(send["notSent"]["k2"]["type"], "invalidProperties");
+    assert_eq!(send["notSent"]["k3"]["type"], "invalidProperties");
+    for (id, property) in [
+        ("k4", "reportingUA"),
+        ("k5", "extensionFields"),
+        ("k6", "error"),
+    ] {
+        assert_eq!(send["notSent"][id]["type"], "invalidProperties");
+        assert_eq!(
+            send["notSent"][id]["properties"],
+            serde_json::json!([property])
+        );
+    }
+    let update = &response["methodResponses"][1];
+    assert_eq!(update[0], "Email/set");
+    assert!(update[1]["updated"]
+        .as_object()
+        .unwrap()
+        .contains_key(email_id.as_str()));
+
+    // Verify the delivered report
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    smtp_settings.lock().do_stop = true;
+    assert_eq!(message.mail_from, "<jdoe@example.com>");
+    assert_eq!(message.rcpt_to, vec!["<bill@remote.org>".to_string()]);
+    for expected in [
+        "report-type=\"disposition-notification\"",
+        "message/disposition-notification",
+        "Final-Recipient: rfc822; jdoe@example.com",
+        "Original-Message-ID: <read-receipt@remote.org>",
+        "Disposition: manual-action/mdn-sent-manually; displayed",
+        "X-Test: hello",
+    ] {
+        assert!(
+            message.message.contains(expected),
+            "missing {expected:?} in {}",
+            message.message
+        );
+    }
+    assert_email_properties(
+        admin_client,
+        &email_id,
+        &[&inbox_id],
+        &["$mdnsent", "$seen"],
+    )
+    .await;
+
+    // Sending a second MDN for the same email should fail
+    let response = jmap_request(
+        "jdoe@example.com",
+        "12345",
+        serde_json::json!({
+            "using": [
+                "urn:ietf:params:jmap:core",
+                "urn:ietf:params:jmap:mail",
+                "urn:ietf:params:jmap:mdn"
+            ],
+            "methodCalls": [
+                ["MDN/send", {
+                    "accountId": account_id.to_string(),
+                    "identityId": &identity_id,
+                    "send": {
+                        "k1": {
+                            "forEmailId": &email_id,
+                            "disposition": {
+                                "actionMode": "manual-action",
+                                "sendingMode": "mdn-sent-manually",
+                                "type": "displayed"
+                            }
+                        }
+                    }
+                }, "0"]
+            ]
+        }),
+    )
+    .await;
+    assert_eq!(
+        response["methodResponses"][0][1]["notSent"]["k1"]["type"],
+        "mdnAlreadySent"
+    );
+
+    // Parse the delivered report
+    let blob_id = admin_client
+        .upload(None, message.message.into_bytes(), None)
+        .await
+        .unwrap()
+        .take_blob_id();
+    let response = jmap_request(
+        "jdoe@example.com",
+        "12345",
+        serde_json::json!({
+            "using": [
+                "urn:ietf:params:jmap:core",
+                "urn:ietf:params:jmap:mail",
+                "urn:ietf:params:jmap:mdn"
+            ],
+            "methodCalls": [
+                ["MDN/parse", {
+                    "accountId": account_id.to_string(),
+                    "blobIds": [&blob_id]
+                }, "0"]
+            ]
+        }),
+    )
+    .await;
+    let parse = &response["methodResponses"][0][1];
+    let mdn = &parse["parsed"][&blob_id];
+    assert_eq!(mdn["subject"], "Read: Did you read this?");
+    assert_eq!(mdn["textBody"], "I have read your message.");
+    assert_eq!(mdn["finalRecipient"], "rfc822; jdoe@example.com");
+    assert_eq!(mdn["originalMessageId"], "<read-receipt@remote.org>");
+    assert_eq!(mdn["includeOriginalMessage"], false);
+    assert_eq!(
+        mdn["disposition"],
+        serde_json::json!({
+            "actionMode": "manual-action",
+            "sendingMode": "mdn-sent-manually",
+            "type": "displayed"
+        })
+    );
+    assert_eq!(mdn["extensionFields"]["X-Test"], "hello");
+
+    // Remove test data
+    admin_client.identity_destroy(&identity_id).await.unwrap();
+    destroy_all_mailboxes(admin_client).await;
+    server
+        .store
+        .delete_account_blobs(account_id.document_id())
+        .await
+        .unwrap();
+    server.store.assert_is_empty().await;
+}
@@ -40,6 +40,7 @@ use crate::{
 pub mod auth_acl;
 pub mod auth_limits;
 pub mod auth_oauth;
+pub mod blob;
 pub mod delivery;
 pub mod email_changes;
 pub mod email_copy;
@@ -51,13 +52,17 @@ pub mod email_search_snippet;
 pub mod email_set;
 pub mod email_submission;
 pub mod event_source;
+pub mod identity;
 pub mod mailbox;
+pub mod mdn;
 pub mod push_subscription;
 pub mod quota;
+pub mod share_notification;
 pub mod sieve_script;
 pub mod stress_test;
 pub mod thread_get;
 pub mod thread_merge;
+pub mod thread_set;
 pub mod vacation_response;
 pub mod websocket;
 
@@ -172,6 +177,13 @@ throttle = "500ms"
 throttle = "500ms"
 attempts.interval = "500ms"
 
+[jmap.identity]
+signature.insert = true
+
+[jmap.identity.footer."example.net"]
+text = "Example Net Inc. -- Confidential"
+html = "<p>Example Net Inc. -- Confidential</p>"
+
 [directory."sql"]
 type = "sql"
 address = "sqlite::memory:"
@@ -235,6 +247,7 @@ pub async fn jmap_tests() {
     email_copy::test(params.server.clone(), &mut params.client).await;
     thread_get::test(params.server.clone(), &mut params.client).await;
     thread_merge::test(params.server.clone(), &mut params.client).await;
+    thread_set::test(params.server.clone(), &mut params.client).await;
     mailbox::test(params.server.clone(), &mut params.client).await;
     delivery::test(params.server.clone(), &mut params.client).await;
     auth_acl::test(params.server.clone(), &mut params.client).await;
@@ -245,8 +258,12 @@ pub async fn jmap_tests() {
     sieve_script::test(params.server.clone(), &mut params.client).await;
     vacation_response::test(params.server.clone(), &mut params.client).await;
     email_submission::test(params.server.clone(), &mut params.client).await;
+    identity::test(params.server.clone(), &mut params.client).await;
     websocket::test(params.server.clone(), &mut params.client).await;
     quota::test(params.server.clone(), &mut params.client).await;
+    blob::test(params.server.clone(), &mut params.client).await;
+    mdn::test(params.server.clone(), &mut params.client).await;
+    share_notification::test(params.server.clone(), &mut params.client).await;
 
     if delete {
         params.temp_dir.delete();
@@ -399,3 +416,26 @@ pub async fn test_account_login(login: &str, secret: &str) -> Client {
         .await
         .unwrap()
 }
+
+pub async fn jmap_request(
+    login: &str,
+    secret: &str,
+    request: serde_json::Value,
+) -> serde_json::Value {
+    serde_json::from_slice(
+        &reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap_or_default()
+            .post("https://127.0.0.1:8899/jmap/")
+            .basic_auth(login, Some(secret))
+            .body(serde_json::to_vec(&request).unwrap())
+            .send()
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap(),
+    )
+    .unwrap()
+}
@@ -40,13 +40,19 @@ use jmap::{
         HtmlResponse, StateChangeResponse,
     },
     auth::AccessToken,
-    push::ece::ece_encrypt,
+    push::{ece::ece_encrypt, vapid::VapidKey},
     JMAP,
 };
 use jmap_client::{client::Client, mailbox::Role, push_subscription::Keys};
-use jmap_proto::types::{id::Id, type_state::TypeState};
-use reqwest::header::CONTENT_ENCODING;
-use store::ahash::AHashSet;
+use jmap_proto::{
+    object::Object,
+    types::{
+        collection::Collection, id::Id, property::Property, type_state::TypeState, value::Value,
+    },
+};
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use reqwest::header::{AUTHORIZATION, CONTENT_ENCODING};
+use store::{ahash::AHashSet, parking_lot::Mutex, write::now};
 use tokio::{net::TcpStream, sync::mpsc};
 use utils::listener::SessionData;
 
@@ -101,6 +107,8 @@ pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
         auth_secret: auth_secret.to_vec(),
         tx: event_tx,
         fail_requests: false.into(),
+        gone_requests: false.into(),
+        authorization: Mutex::new(None),
     });
 
     // Start mock push server
@@ -125,6 +133,24 @@ pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
     let verification = expect_push(&mut event_rx).await.unwrap_verification();
     assert_eq!(verification.push_subscription_id, push_id);
 
+    // Push requests should be signed with VAPID
+    assert_vapid(
+        push_server.authorization.lock().take().unwrap().as_str(),
+        server.config.push_vapid.public_key(),
+    );
+
+    // Generated VAPID keys are kept in the store and reused after a restart
+    let mut vapid_key = VapidKey::new(&utils::config::Config::parse("").unwrap()).unwrap();
+    assert_ne!(
+        vapid_key.public_key(),
+        server.config.push_vapid.public_key()
+    );
+    vapid_key.load_or_store(&server.store).await.unwrap();
+    assert_eq!(
+        vapid_key.public_key(),
+        server.config.push_vapid.public_key()
+    );
+
     // Update verification code
     client
         .push_subscription_verify(&push_id, verification.verification_code)
@@ -212,6 +238,63 @@ pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
     assert_state(&mut event_rx, &account_id, &[TypeState::Mailbox]).await;
     expect_nothing(&mut event_rx).await;
 
+    // Only the subscribed types should be pushed
+    client
+        .push_subscription_update_types(&push_id, [jmap_client::TypeState::Mailbox].into())
+        .await
+        .unwrap();
+    client
+        .email_import(
+            concat!(
+                "From: bill@example.com\r\n",
+                "To: jdoe@example.com\r\n",
+                "Subject: TPS Report\r\n",
+                "\r\n",
+                "I'm going to need those TPS reports ASAP.",
+            )
+            .as_bytes()
+            .to_vec(),
+            [&mailbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_state(&mut event_rx, &account_id, &[TypeState::Mailbox]).await;
+    assert_vapid(
+        push_server.authorization.lock().take().unwrap().as_str(),
+        server.config.push_vapid.public_key(),
+    );
+
+    // Subscriptions rejected with 404/410 should be disabled
+    push_server.gone_requests.store(true, Ordering::Relaxed);
+    client
+        .mailbox_update_sort_order(&mailbox_id, 200)
+        .await
+        .unwrap();
+    expect_nothing(&mut event_rx).await;
+    push_server.gone_requests.store(false, Ordering::Relaxed);
+    client
+        .mailbox_update_sort_order(&mailbox_id, 201)
+        .await
+        .unwrap();
+    expect_nothing(&mut event_rx).await;
+    let push = server
+        .get_property::<Object<Value>>(
+            account_id.document_id(),
+            Collection::PushSubscription,
+            Id::from_bytes(push_id.as_bytes()).unwrap().document_id(),
+            Property::Value,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(
+        push.get(&Property::Expires).as_date().unwrap().timestamp() <= now() as i64,
+        "{:?}",
+        push
+    );
+
     // Destroy mailbox
     client.push_subscription_destroy(&push_id).await.unwrap();
     client.mailbox_destroy(&mailbox_id, true).await.unwrap();
@@ -237,6 +320,8 @@ pub struct PushServer {
     auth_secret: Vec<u8>,
     tx: mpsc::Sender<PushMessage>,
     fail_requests: AtomicBool,
+    gone_requests: AtomicBool,
+    authorization: Mutex<Option<String>>,
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -299,6 +384,17 @@ impl utils::listener::SessionManager for SessionManager {
                         let push = push.clone();
 
                         async move {
+                            *push.authorization.lock() = req
+                                .headers()
+                                .get(AUTHORIZATION)
+                                .map(|value| value.to_str().unwrap().to_string());
+                            if push.gone_requests.load(Ordering::Relaxed) {
+                                return Ok(HtmlResponse::with_status(
+                                    StatusCode::GONE,
+                                    "gone".to_string(),
+                                )
+                                .into_http_response());
+                            }
                             if push.fail_requests.load(Ordering::Relaxed) {
                                 return Ok(HtmlResponse::with_status(
                                     StatusCode::TOO_MANY_REQUESTS,
@@ -380,6 +476,34 @@ async fn assert_state(event_rx: &mut mpsc::Receiver<PushMessage>, id: &Id, state
     );
 }
 
+fn assert_vapid(authorization: &str, public_key: &str) {
+    let (token, key) = authorization
+        .strip_prefix("vapid t=")
+        .and_then(|value| value.split_once(", k="))
+        .unwrap();
+    assert_eq!(key, public_key);
+
+    let (message, signature) = token.rsplit_once('.').unwrap();
+    let claims = serde_json::from_slice::<serde_json::Value>(
+        &general_purpose::URL_SAFE_NO_PAD
+            .decode(message.split_once('.').unwrap().1)
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(claims["aud"], "https://127.0.0.1:9000");
+    assert!(claims["exp"].as_u64().unwrap() > now());
+    assert!(claims["sub"].as_str().unwrap().starts_with("mailto:"));
+
+    VerifyingKey::from_sec1_bytes(&general_purpose::URL_SAFE_NO_PAD.decode(key).unwrap())
+        .unwrap()
+        .verify(
+            message.as_bytes(),
+            &Signature::from_slice(&general_purpose::URL_SAFE_NO_PAD.decode(signature).unwrap())
+                .unwrap(),
+        )
+        .unwrap();
+}
+
 #[test]
 fn ece_roundtrip() {
     for len in [1, 2, 5, 16, 256, 1024, 2048, 4096, 1024 * 1024] {
@@ -21,19 +21,23 @@
  * for more details.
 */
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use jmap::{blob::upload::DISABLE_UPLOAD_QUOTA, mailbox::INBOX_ID, JMAP};
+use jmap::{blob::upload::DISABLE_UPLOAD_QUOTA, mailbox::INBOX_ID, quota::TEST_MAX_MESSAGES, JMAP};
 use jmap_client::{
     client::Client,
     core::set::{SetErrorType, SetObject},
     email::EmailBodyPart,
 };
-use jmap_proto::types::{collection::Collection, id::Id};
+use jmap_proto::types::{collection::Collection, id::Id, type_state::TypeState};
+use store::write::BatchBuilder;
+use utils::map::bitmap::Bitmap;
 
 use crate::{
     directory::sql::{add_to_group, create_test_user_with_email, set_test_quota},
-    jmap::{delivery::SmtpConnection, mailbox::destroy_all_mailboxes, test_account_login},
+    jmap::{
+        delivery::SmtpConnection, jmap_request, mailbox::destroy_all_mailboxes, test_account_login,
+    },
 };
 
 pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
@@ -294,6 +298,229 @@ pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
             .len(),
         1,
     );
+
+    // Test the message count quota
+    assert_eq!(
+        server
+            .get_used_messages(account_id.document_id())
+            .await
+            .unwrap(),
+        1
+    );
+    TEST_MAX_MESSAGES.store(2, std::sync::atomic::Ordering::Relaxed);
+    let quotas = server
+        .get_account_quotas(account_id.document_id())
+        .await
+        .unwrap();
+    assert_eq!(quotas.len(), 2);
+    assert_eq!(quotas[1].resource_type, "count");
+    assert_eq!(quotas[1].used, 1);
+    assert_eq!(quotas[1].hard_limit, 2);
+    let message_id = client
+        .email_import(
+            create_message_with_size("jane@example.com", "robert@example.com", "Count 1", 100),
+            vec![&inbox_id],
+            None::<Vec<String>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    assert_over_quota(
+        client
+            .email_import(
+                create_message_with_size("jane@example.com", "robert@example.com", "Count 2", 100),
+                vec![&inbox_id],
+                None::<Vec<String>>,
+                None,
+            )
+            .await,
+    );
+    TEST_MAX_MESSAGES.store(0, std::sync::atomic::Ordering::Relaxed);
+    client.email_destroy(&message_id).await.unwrap();
+    assert_eq!(
+        server
+            .get_used_messages(account_id.document_id())
+            .await
+            .unwrap(),
+        1
+    );
+
+    // Negative message counts are rebuilt from the Email document ids
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(account_id.document_id())
+        .message_count(-10);
+    server.store.write(batch.build()).await.unwrap();
+    assert_eq!(
+        server
+            .get_used_messages(account_id.document_id())
+            .await
+            .unwrap(),
+        1
+    );
+
+    // Test Quota/get and Quota/query
+    let quotas = server
+        .get_account_quotas(account_id.document_id())
+        .await
+        .unwrap();
+    assert_eq!(quotas.len(), 1);
+    assert_eq!(quotas[0].resource_type, "octets");
+    assert_eq!(quotas[0].hard_limit, 1024);
+    assert_eq!(quotas[0].used, quota as u64);
+    set_test_quota(directory, "jdoe@example.com", 4096).await;
+    let response = jmap_request(
+        "robert@example.com",
+        "aabbcc",
+        serde_json::json!({
+            "using": ["urn:ietf:params:jmap:core", "urn:ietf:params:jmap:quota"],
+            "methodCalls": [
+                ["Quota/get", {"accountId": account_id.to_string(), "ids": null}, "0"],
+                ["Quota/query", {
+                    "accountId": account_id.to_string(),
+                    "filter": {"resourceType": "octets"},
+                    "sort": [{"property": "used"}]
+                }, "1"],
+                ["Quota/get", {"accountId": other_account_id.to_string(), "ids": null}, "2"]
+            ]
+        }),
+    )
+    .await;
+    let quota_get = &response["methodResponses"][0][1];
+    assert_eq!(quota_get["list"][0]["id"], Id::from(0u32).to_string());
+    assert_eq!(quota_get["list"][0]["used"], quota);
+    assert_eq!(quota_get["list"][0]["hardLimit"], 1024);
+    assert_eq!(quota_get["list"][0]["warnLimit"], 921);
+    assert_eq!(quota_get["list"][0]["scope"], "account");
+    assert_eq!(
+        response["methodResponses"][1][1]["ids"][0],
+        Id::from(0u32).to_string()
+    );
+    // Group members may read the quotas of the group account
+    assert_eq!(response["methodResponses"][2][0], "Quota/get");
+    let group_quota = &response["methodResponses"][2][1]["list"][0];
+    assert_eq!(group_quota["hardLimit"], 4096);
+    assert_eq!(
+        group_quota["used"],
+        server
+            .get_used_quota(other_account_id.document_id())
+            .await
+            .unwrap()
+    );
+
+    // Changes below the warning limit do not push a Quota state change
+    let mut quota_rx = server
+        .subscribe_state_manager(
+            account_id.document_id(),
+            account_id.document_id(),
+            Bitmap::from_iter([TypeState::Quota]),
+        )
+        .await
+        .unwrap();
+    assert!(quota + 200 < 921, "Quota is {}", quota);
+    let message_id = client
+        .email_import(
+            create_message_with_size("jane@example.com", "robert@example.com", "Below", 100),
+            vec![&inbox_id],
+            None::<Vec<String>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    assert!(
+        tokio::time::timeout(Duration::from_millis(500), quota_rx.recv())
+            .await
+            .is_err(),
+        "Unexpected Quota state change"
+    );
+
+    // Quota/changes reports the quotas whose usage changed
+    let state = quota_get["state"].as_str().unwrap().to_string();
+    let response = jmap_request(
+        "robert@example.com",
+        "aabbcc",
+        serde_json::json!({
+            "using": ["urn:ietf:params:jmap:core", "urn:ietf:params:jmap:quota"],
+            "methodCalls": [
+                ["Quota/changes", {"accountId": account_id.to_string(), "sinceState": state}, "0"]
+            ]
+        }),
+    )
+    .await;
+    let changes = &response["methodResponses"][0][1];
+    assert_eq!(changes["oldState"], state);
+    assert_ne!(changes["newState"], state);
+    assert_eq!(
+        changes["updated"],
+        serde_json::json!([Id::from(0u32).to_string()])
+    );
+    assert_eq!(changes["updatedProperties"], serde_json::json!(["used"]));
+    let state = changes["newState"].as_str().unwrap().to_string();
+    let response = jmap_request(
+        "robert@example.com",
+        "aabbcc",
+        serde_json::json!({
+            "using": ["urn:ietf:params:jmap:core", "urn:ietf:params:jmap:quota"],
+            "methodCalls": [
+                ["Quota/changes", {"accountId": account_id.to_string(), "sinceState": state}, "0"]
+            ]
+        }),
+    )
+    .await;
+    let changes = &response["methodResponses"][0][1];
+    assert_eq!(changes["newState"], state);
+    assert_eq!(changes["updated"], serde_json::json!([]));
+
+    // Keyword changes do not change the Quota state
+    client
+        .email_set_keyword(&message_id, "$seen", true)
+        .await
+        .unwrap();
+    let response = jmap_request(
+        "robert@example.com",
+        "aabbcc",
+        serde_json::json!({
+            "using": ["urn:ietf:params:jmap:core", "urn:ietf:params:jmap:quota"],
+            "methodCalls": [
+                ["Quota/changes", {"accountId": account_id.to_string(), "sinceState": state}, "0"]
+            ]
+        }),
+    )
+    .await;
+    let changes = &response["methodResponses"][0][1];
+    assert_eq!(changes["newState"], state);
+    assert_eq!(changes["updated"], serde_json::json!([]));
+
+    // Crossing the warning limit pushes a Quota state change
+    let used = server
+        .get_used_quota(account_id.document_id())
+        .await
+        .unwrap();
+    client
+        .email_import(
+            create_message_with_size(
+                "jane@example.com",
+                "robert@example.com",
+                "Above",
+                (921 - used) as usize + 1,
+            ),
+            vec![&inbox_id],
+            None::<Vec<String>>,
+            None,
+        )
+        .await
+        .unwrap();
+    let state_change = tokio::time::timeout(Duration::from_secs(1), quota_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(state_change.account_id, account_id.document_id());
+    assert!(state_change
+        .types
+        .iter()
+        .any(|(type_state, _)| *type_state == TypeState::Quota));
     DISABLE_UPLOAD_QUOTA.store(true, std::sync::atomic::Ordering::Relaxed);
 
     // Remove test data
@@ -0,0 +1,360 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use jmap::JMAP;
+use jmap_client::client::Client;
+use jmap_proto::types::id::Id;
+
+use crate::{
+    directory::sql::create_test_user_with_email,
+    jmap::{jmap_request, mailbox::destroy_all_mailboxes},
+};
+
+pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
+    println!("Running ShareNotification tests...");
+
+    // Create test accounts
+    let directory = server.directory.as_ref();
+    create_test_user_with_email(directory, "milton@example.com", "12345", "Milton Waddams").await;
+    create_test_user_with_email(
+        directory,
+        "samir@example.com",
+        "12345",
+        "Samir Nagheenanajar",
+    )
+    .await;
+    let milton_id =
+        Id::from(server.get_account_id("milton@example.com").await.unwrap()).to_string();
+    let samir_id = Id::from(server.get_account_id("samir@example.com").await.unwrap()).to_string();
+
+    // The session should include the principal capabilities
+    let session: serde_json::Value = serde_json::from_slice(
+        &reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap_or_default()
+            .get("https://127.0.0.1:8899/.well-known/jmap")
+            .basic_auth("samir@example.com", Some("12345"))
+            .send()
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+    let account_capabilities = &session["accounts"][&samir_id]["accountCapabilities"];
+    assert_eq!(
+        account_capabilities["urn:ietf:params:jmap:principals"]["currentUserPrincipalId"],
+        samir_id.as_str()
+    );
+    assert_eq!(
+        account_capabilities["urn:ietf:params:jmap:principals:owner"]["accountIdForPrincipal"],
+        samir_id.as_str()
+    );
+    assert_eq!(
+        account_capabilities["urn:ietf:params:jmap:principals:owner"]["principalId"],
+        samir_id.as_str()
+    );
+
+    // Milton creates a mailbox shared with Samir
+    let response = share_request(
+        "milton@example.com",
+        serde_json::json!([
+            ["Mailbox/set", {
+                "accountId": &milton_id,
+                "create": {
+                    "m1": {
+                        "name": "Red Staplers",
+                        "acl": {
+                            "samir@example.com": ["read", "readItems"]
+                        }
+                    }
+                }
+            }, "0"]
+        ]),
+    )
+    .await;
+    let mailbox_id = response["methodResponses"][0][1]["created"]["m1"]["id"]
+        .as_str()
+        .unwrap_or_else(|| panic!("Unexpected response: {response}"))
+        .to_string();
+
+    // Samir should have received a share notification
+    let response = share_request(
+        "samir@example.com",
+        serde_json::json!([
+            ["ShareNotification/get", {
+                "accountId": &samir_id
+            }, "0"]
+        ]),
+    )
+    .await;
+    let state = response["methodResponses"][0][1]["state"]
+        .as_str()
+        .unwrap_or_else(|| panic!("Unexpected response: {response}"))
+        .to_string();
+    let list = response["methodResponses"][0][1]["list"]
+        .as_array()
+        .unwrap();
+    assert_eq!(list.len(), 1, "{response}");
+    let notification = &list[0];
+    assert_eq!(notification["objectType"], "Mailbox");
+    assert_eq!(notification["objectAccountId"], milton_id.as_str());
+    assert_eq!(notification["objectId"], mailbox_id.as_str());
+    assert_eq!(notification["name"], "Red Staplers");
+    assert_eq!(notification["changedBy"]["name"], "Milton Waddams");
+    assert_eq!(notification["changedBy"]["email"], "milton@example.com");
+    assert_eq!(notification["changedBy"]["principalId"], milton_id.as_str());
+    assert!(notification["created"].is_string(), "{notification}");
+    assert!(notification["oldRights"].is_null(), "{notification}");
+    assert_eq!(notification["newRights"]["mayReadItems"], true);
+    assert_eq!(notification["newRights"]["mayAddItems"], false);
+    let first_id = notification["id"].as_str().unwrap().to_string();
+
+    // Milton does not get notified of his own changes
+    let response = share_request(
+        "milton@example.com",
+        serde_json::json!([
+            ["ShareNotification/get", {
+                "accountId": &milton_id
+            }, "0"]
+        ]),
+    )
+    .await;
+    assert_eq!(
+        response["methodResponses"][0][1]["list"],
+        serde_json::json!([]),
+        "{response}"
+    );
+
+    // Grant Samir additional rights
+    let response = share_request(
+        "milton@example.com",
+        serde_json::json!([
+            ["Mailbox/set", {
+                "accountId": &milton_id,
+                "update": {
+                    &mailbox_id: {
+                        "acl/samir@example.com": ["read", "readItems", "addItems"]
+                    }
+                }
+            }, "0"]
+        ]),
+    )
+    .await;
+    assert!(
+        response["methodResponses"][0][1]["updated"][&mailbox_id].is_null()
+            && response["methodResponses"][0][1]["notUpdated"].is_null(),
+        "{response}"
+    );
+
+    // Fetch changes and query the notifications, newest first
+    let response = share_request(
+        "samir@example.com",
+        serde_json::json!([
+            ["ShareNotification/changes", {
+                "accountId": &samir_id,
+                "sinceState": &state
+            }, "0"],
+            ["ShareNotification/query", {
+                "accountId": &samir_id,
+                "filter": {
+                    "objectType": "Mailbox",
+                    "objectAccountId": &milton_id
+                },
+                "sort": [{"property": "created", "isAscending": false}]
+            }, "1"],
+            ["ShareNotification/query", {
+                "accountId": &samir_id,
+                "filter": {
+                    "objectAccountId": &samir_id
+                }
+            }, "2"]
+        ]),
+    )
+    .await;
+    let created = response["methodResponses"][0][1]["created"]
+        .as_array()
+        .unwrap_or_else(|| panic!("Unexpected response: {response}"));
+    assert_eq!(created.len(), 1, "{response}");
+    let second_id = created[0].as_str().unwrap().to_string();
+    assert_ne!(first_id, second_id);
+    assert_eq!(
+        response["methodResponses"][1][1]["ids"],
+        serde_json::json!([&second_id, &first_id]),
+        "{response}"
+    );
+    assert_eq!(
+        response["methodResponses"][2][1]["ids"],
+        serde_json::json!([]),
+        "{response}"
+    );
+
+    // The new notification includes the old and new rights
+    let response = share_request(
+        "samir@example.com",
+        serde_json::json!([
+            ["ShareNotification/get", {
+                "accountId": &samir_id,
+                "ids": [&second_id],
+                "properties": ["oldRights", "newRights"]
+            }, "0"]
+        ]),
+    )
+    .await;
+    let notification = &response["methodResponses"][0][1]["list"][0];
+    assert_eq!(
+        notification["oldRights"]["mayAddItems"], false,
+        "{response}"
+    );
+    assert_eq!(notification["newRights"]["mayAddItems"], true, "{response}");
+    assert_eq!(
+        notification["newRights"]["mayReadItems"], true,
+        "{response}"
+    );
+
+    // Notifications cannot be created or modified, only destroyed
+    let response = share_request(
+        "samir@example.com",
+        serde_json::json!([
+            ["ShareNotification/set", {
+                "accountId": &samir_id,
+                "create": {
+                    "n1": {
+                        "objectType": "Mailbox"
+                    }
+                },
+                "update": {
+                    &first_id: {
+                        "name": "Red Staplers"
+                    }
+                },
+                "destroy": [&first_id, &second_id]
+            }, "0"]
+        ]),
+    )
+    .await;
+    let result = &response["methodResponses"][0][1];
+    assert_eq!(
+        result["notCreated"]["n1"]["type"], "forbidden",
+        "{response}"
+    );
+    assert_eq!(
+        result["notUpdated"][&first_id]["type"], "forbidden",
+        "{response}"
+    );
+    assert_eq!(
+        result["destroyed"],
+        serde_json::json!([&first_id, &second_id]),
+        "{response}"
+    );
+
+    // Revoking access is also notified
+    share_request(
+        "milton@example.com",
+        serde_json::json!([
+            ["Mailbox/set", {
+                "accountId": &milton_id,
+                "update": {
+                    &mailbox_id: {
+                        "acl": {}
+                    }
+                }
+            }, "0"]
+        ]),
+    )
+    .await;
+    let response = share_request(
+        "samir@example.com",
+        serde_json::json!([
+            ["ShareNotification/get", {
+                "accountId": &samir_id
+            }, "0"]
+        ]),
+    )
+    .await;
+    let list = response["methodResponses"][0][1]["list"]
+        .as_array()
+        .unwrap();
+    assert_eq!(list.len(), 1, "{response}");
+    assert_eq!(list[0]["oldRights"]["mayAddItems"], true, "{response}");
+    assert!(list[0]["newRights"].is_null(), "{response}");
+
+    // Remove test data
+    destroy_all_share_notifications(&samir_id).await;
+    for account_id in [&milton_id, &samir_id] {
+        admin_client.set_default_account_id(account_id);
+        destroy_all_mailboxes(admin_client).await;
+    }
+    server.store.assert_is_empty().await;
+}
+
+pub async fn destroy_all_share_notifications(account_id: &str) {
+    let response = jmap_request(
+        "admin",
+        "secret",
+        serde_json::json!({
+            "using": [
+                "urn:ietf:params:jmap:core",
+                "urn:ietf:params:jmap:principals"
+            ],
+            "methodCalls": [
+                ["ShareNotification/query", {
+                    "accountId": account_id
+                }, "0"],
+                ["ShareNotification/set", {
+                    "accountId": account_id,
+                    "#destroy": {
+                        "resultOf": "0",
+                        "name": "ShareNotification/query",
+                        "path": "/ids"
+                    }
+                }, "1"]
+            ]
+        }),
+    )
+    .await;
+    assert!(
+        response["methodResponses"][1][1]["notDestroyed"].is_null(),
+        "{response}"
+    );
+}
+
+async fn share_request(login: &str, method_calls: serde_json::Value) -> serde_json::Value {
+    jmap_request(
+        login,
+        "12345",
+        serde_json::json!({
+            "using": [
+                "urn:ietf:params:jmap:core",
+                "urn:ietf:params:jmap:mail",
+                "urn:ietf:params:jmap:principals"
+            ],
+            "methodCalls": method_calls
+        }),
+    )
+    .await
+}
@@ -0,0 +1,411 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use jmap::JMAP;
+use jmap_client::client::Client;
+use jmap_proto::types::id::Id;
+
+use crate::{
+    directory::sql::create_test_user_with_email,
+    jmap::{
+        delivery::SmtpConnection, jmap_request, mailbox::destroy_all_mailboxes,
+        share_notification::destroy_all_share_notifications,
+    },
+};
+
+pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
+    println!("Running Thread/set tests...");
+
+    // Create test account
+    let directory = server.directory.as_ref();
+    create_test_user_with_email(directory, "peter@example.com", "12345", "Peter Gibbons").await;
+    let account_id =
+        Id::from(server.get_account_id("peter@example.com").await.unwrap()).to_string();
+    let inbox_id = Id::new(0).to_string();
+
+    // Create an archive mailbox
+    let response = thread_request(serde_json::json!([
+        ["Mailbox/set", {
+            "accountId": &account_id,
+            "create": {
+                "a1": {
+                    "name": "Archive",
+                    "role": "archive"
+                }
+            }
+        }, "0"]
+    ]))
+    .await;
+    let archive_id = response["methodResponses"][0][1]["created"]["a1"]["id"]
+        .as_str()
+        .unwrap_or_else(|| panic!("Unexpected response: {response}"))
+        .to_string();
+
+    // Deliver a conversation of two messages
+    let mut lmtp = SmtpConnection::connect().await;
+    lmtp.ingest(
+        "bill@example.com",
+        &["peter@example.com"],
+        concat!(
+            "From: bill@example.com\r\n",
+            "To: peter@example.com\r\n",
+            "Message-ID: <tps-1@example.com>\r\n",
+            "Subject: TPS Reports\r\n",
+            "\r\n",
+            "Did you get the memo about the new cover sheets?"
+        ),
+    )
+    .await;
+    lmtp.ingest(
+        "bill@example.com",
+        &["peter@example.com"],
+        concat!(
+            "From: bill@example.com\r\n",
+            "To: peter@example.com\r\n",
+            "Message-ID: <tps-2@example.com>\r\n",
+            "In-Reply-To: <tps-1@example.com>\r\n",
+            "References: <tps-1@example.com>\r\n",
+            "Subject: Re: TPS Reports\r\n",
+            "\r\n",
+            "I'll go ahead and make sure you get another copy of that memo."
+        ),
+    )
+    .await;
+    let emails = get_emails(&account_id).await;
+    assert_eq!(emails.len(), 2, "{emails:?}");
+    let thread_id = emails[0]["threadId"].as_str().unwrap().to_string();
+    assert_eq!(emails[1]["threadId"], thread_id.as_str());
+
+    // Mark the whole conversation as seen
+    let response = thread_request(serde_json::json!([
+        ["Thread/set", {
+            "accountId": &account_id,
+            "update": {
+                &thread_id: {
+                    "keywords/$seen": true
+                }
+            }
+        }, "0"]
+    ]))
+    .await;
+    assert!(
+        response["methodResponses"][0][1]["updated"]
+            .as_object()
+            .map_or(false, |updated| updated.contains_key(&thread_id)),
+        "{response}"
+    );
+    for email in get_emails(&account_id).await {
+        assert_eq!(email["keywords"], serde_json::json!({"$seen": true}));
+    }
+
+    // Move the conversation to the archive
+    let response = thread_request(serde_json::json!([
+        ["Thread/set", {
+            "accountId": &account_id,
+            "update": {
+                &thread_id: {
+                    format!("mailboxIds/{inbox_id}"): null,
+                    format!("mailboxIds/{archive_id}"): true
+                }
+            }
+        }, "0"]
+    ]))
+    .await;
+    assert!(
+        response["methodResponses"][0][1]["notUpdated"].is_null(),
+        "{response}"
+    );
+    for email in get_emails(&account_id).await {
+        assert_eq!(
+            email["mailboxIds"],
+            serde_json::json!({archive_id.as_str(): true})
+        );
+    }
+
+    // Messages cannot be left without a mailbox
+    let response = thread_request(serde_json::json!([
+        ["Thread/set", {
+            "accountId": &account_id,
+            "update": {
+                &thread_id: {
+                    "mailboxIds": {}
+                }
+            }
+        }, "0"]
+    ]))
+    .await;
+    assert_eq!(
+        response["methodResponses"][0][1]["notUpdated"][&thread_id]["type"], "invalidProperties",
+        "{response}"
+    );
+    for email in get_emails(&account_id).await {
+        assert_eq!(
+            email["mailboxIds"],
+            serde_json::json!({archive_id.as_str(): true})
+        );
+    }
+
+    // Threads cannot be created or destroyed
+    let response = thread_request(serde_json::json!([
+        ["Thread/set", {
+            "accountId": &account_id,
+            "create": {
+                "t1": {}
+            },
+            "destroy": [&thread_id]
+        }, "0"]
+    ]))
+    .await;
+    assert_eq!(
+        response["methodResponses"][0][1]["notCreated"]["t1"]["type"], "forbidden",
+        "{response}"
+    );
+    assert_eq!(
+        response["methodResponses"][0][1]["notDestroyed"][&thread_id]["type"], "forbidden",
+        "{response}"
+    );
+
+    // Mute the conversation
+    let response = thread_request(serde_json::json!([
+        ["Thread/set", {
+            "accountId": &account_id,
+            "update": {
+                &thread_id: {
+                    "isMuted": true
+                }
+            }
+        }, "0"],
+        ["Thread/get", {
+            "accountId": &account_id,
+            "ids": [&thread_id]
+        }, "1"],
+        ["Thread/get", {
+            "accountId": &account_id,
+            "ids": [&thread_id],
+            "properties": ["emailIds", "isMuted"]
+        }, "2"]
+    ]))
+    .await;
+    assert_eq!(
+        response["methodResponses"][1][1]["list"][0]["isMuted"],
+        serde_json::Value::Null,
+        "{response}"
+    );
+    assert_eq!(
+        response["methodResponses"][2][1]["list"][0]["isMuted"], true,
+        "{response}"
+    );
+
+    // New replies to a muted conversation skip the inbox
+    lmtp.ingest(
+        "bill@example.com",
+        &["peter@example.com"],
+        concat!(
+            "From: bill@example.com\r\n",
+            "To: peter@example.com\r\n",
+            "Message-ID: <tps-3@example.com>\r\n",
+            "In-Reply-To: <tps-2@example.com>\r\n",
+            "References: <tps-1@example.com> <tps-2@example.com>\r\n",
+            "Subject: Re: TPS Reports\r\n",
+            "\r\n",
+            "Yeah. It's just we're putting new cover sheets on all the TPS reports."
+        ),
+    )
+    .await;
+    let emails = get_emails(&account_id).await;
+    assert_eq!(emails.len(), 3, "{emails:?}");
+    for email in emails {
+        assert_eq!(email["threadId"], thread_id.as_str());
+        assert_eq!(
+            email["mailboxIds"],
+            serde_json::json!({archive_id.as_str(): true})
+        );
+    }
+
+    // Unmuted conversations are delivered to the inbox again
+    let response = thread_request(serde_json::json!([
+        ["Thread/set", {
+            "accountId": &account_id,
+            "update": {
+                &thread_id: {
+                    "isMuted": false
+                }
+            }
+        }, "0"],
+        ["Thread/get", {
+            "accountId": &account_id,
+            "ids": [&thread_id],
+            "properties": ["isMuted"]
+        }, "1"]
+    ]))
+    .await;
+    assert_eq!(
+        response["methodResponses"][1][1]["list"][0]["isMuted"], false,
+        "{response}"
+    );
+    lmtp.ingest(
+        "bill@example.com",
+        &["peter@example.com"],
+        concat!(
+            "From: bill@example.com\r\n",
+            "To: peter@example.com\r\n",
+            "Message-ID: <tps-4@example.com>\r\n",
+            "In-Reply-To: <tps-3@example.com>\r\n",
+            "References: <tps-1@example.com> <tps-3@example.com>\r\n",
+            "Subject: Re: TPS Reports\r\n",
+            "\r\n",
+            "Did you see the memo about this?"
+        ),
+    )
+    .await;
+    let emails = get_emails(&account_id).await;
+    assert_eq!(emails.len(), 4, "{emails:?}");
+    assert_eq!(
+        emails
+            .iter()
+            .filter(|email| email["mailboxIds"] == serde_json::json!({inbox_id.as_str(): true}))
+            .count(),
+        1,
+        "{emails:?}"
+    );
+
+    // Users the archive is shared with cannot mute the conversation
+    create_test_user_with_email(directory, "michael@example.com", "12345", "Michael Bolton").await;
+    let michael_id =
+        Id::from(server.get_account_id("michael@example.com").await.unwrap()).to_string();
+    let response = thread_request(serde_json::json!([
+        ["Mailbox/set", {
+            "accountId": &account_id,
+            "update": {
+                &archive_id: {
+                    "acl": {
+                        "michael@example.com": ["read", "readItems", "modifyItems"]
+                    }
+                }
+            }
+        }, "0"]
+    ]))
+    .await;
+    assert!(
+        response["methodResponses"][0][1]["notUpdated"].is_null(),
+        "{response}"
+    );
+    let response = thread_request_as(
+        "michael@example.com",
+        serde_json::json!([
+            ["Thread/set", {
+                "accountId": &account_id,
+                "update": {
+                    &thread_id: {
+                        "isMuted": true
+                    }
+                }
+            }, "0"]
+        ]),
+    )
+    .await;
+    assert_eq!(
+        response["methodResponses"][0][1]["notUpdated"][&thread_id]["type"], "forbidden",
+        "{response}"
+    );
+    let response = thread_request(serde_json::json!([
+        ["Thread/get", {
+            "accountId": &account_id,
+            "ids": [&thread_id],
+            "properties": ["isMuted"]
+        }, "0"]
+    ]))
+    .await;
+    assert_eq!(
+        response["methodResponses"][0][1]["list"][0]["isMuted"], false,
+        "{response}"
+    );
+
+    // Mute the conversation again before removing the test data
+    let response = thread_request(serde_json::json!([
+        ["Thread/set", {
+            "accountId": &account_id,
+            "update": {
+                &thread_id: {
+                    "isMuted": true
+                }
+            }
+        }, "0"]
+    ]))
+    .await;
+    assert!(
+        response["methodResponses"][0][1]["notUpdated"].is_null(),
+        "{response}"
+    );
+
+    // Remove test data
+    destroy_all_share_notifications(&michael_id).await;
+    for account_id in [&account_id, &michael_id] {
+        admin_client.set_default_account_id(account_id);
+        destroy_all_mailboxes(admin_client).await;
+    }
+    server.store.assert_is_empty().await;
+}
+
+async fn get_emails(account_id: &str) -> Vec<serde_json::Value> {
+    let response = thread_request(serde_json::json!([
+        ["Email/query", {
+            "accountId": account_id
+        }, "0"],
+        ["Email/get", {
+            "accountId": account_id,
+            "#ids": {
+                "resultOf": "0",
+                "name": "Email/query",
+                "path": "/ids"
+            },
+            "properties": ["threadId", "mailboxIds", "keywords"]
+        }, "1"]
+    ]))
+    .await;
+    response["methodResponses"][1][1]["list"]
+        .as_array()
+        .unwrap_or_else(|| panic!("Unexpected response: {response}"))
+        .clone()
+}
+
+async fn thread_request(method_calls: serde_json::Value) -> serde_json::Value {
+    thread_request_as("peter@example.com", method_calls).await
+}
+
+async fn thread_request_as(login: &str, method_calls: serde_json::Value) -> serde_json::Value {
+    jmap_request(
+        login,
+        "12345",
+        serde_json::json!({
+            "using": [
+                "urn:ietf:params:jmap:core",
+                "urn:ietf:params:jmap:mail"
+            ],
+            "methodCalls": method_calls
+        }),
+    )
+    .await
+}
@@ -126,6 +126,33 @@ pub async fn test(server: Arc<JMAP>, client: &mut Client) {
 
     expect_nothing(&mut smtp_rx).await;
 
+    // Mailing lists, bulk and auto-submitted messages should not
+    // trigger a vacation response
+    for headers in [
+        "List-Id: <tps.remote.org>\r\n",
+        "Precedence: bulk\r\n",
+        "Auto-Submitted: auto-replied\r\n",
+    ] {
+        lmtp.ingest(
+            "lumbergh@remote.org",
+            &["jdoe@example.com"],
+            &format!(
+                concat!(
+                    "From: lumbergh@remote.org\r\n",
+                    "To: jdoe@example.com\r\n",
+                    "{}",
+                    "Subject: Did you get the memo?\r\n",
+                    "\r\n",
+                    "We're putting new cover sheets on all the TPS reports.",
+                ),
+                headers
+            ),
+        )
+        .await;
+
+        expect_nothing(&mut smtp_rx).await;
+    }
+
     // Vacation responses should honor the configured date ranges
     client
         .vacation_response_set_dates((Utc::now() + Duration::days(1)).timestamp().into(), None)
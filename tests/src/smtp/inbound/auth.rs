@@ -22,7 +22,7 @@
 */
 
 use directory::config::ConfigDirectory;
-use smtp_proto::{AUTH_LOGIN, AUTH_PLAIN};
+use smtp_proto::{AUTH_EXTERNAL, AUTH_LOGIN, AUTH_PLAIN};
 use utils::config::Config;
 
 use crate::smtp::{
@@ -78,7 +78,7 @@ async fn auth() {
     config.mechanisms = format!(
         "[{{if = 'remote-ip', eq = '10.0.0.1', then = {}}},
     {{else = 0}}]",
-        AUTH_PLAIN | AUTH_LOGIN
+        AUTH_PLAIN | AUTH_LOGIN | AUTH_EXTERNAL
     )
     .as_str()
     .parse_if(&ctx);
@@ -152,6 +152,37 @@ async fn auth() {
     session.cmd("amFuZQ==", "334").await;
     session.cmd("cDRzc3cwcmQ=", "235 2.7.0").await;
 
+    // EXTERNAL should only be advertised when a client certificate was presented
+    session.data.authenticated_as.clear();
+    session
+        .ehlo("mx.foobar.org")
+        .await
+        .assert_not_contains(" EXTERNAL");
+    session.cmd("AUTH EXTERNAL =", "535 5.7.8").await;
+    session.data.auth_errors = 0;
+
+    // Certificates that do not map to a principal should be rejected
+    session.stream.tls_identity = Some("bill@example.org".to_string());
+    session
+        .ehlo("mx.foobar.org")
+        .await
+        .assert_contains(" EXTERNAL");
+    session.cmd("AUTH EXTERNAL =", "535 5.7.8").await;
+    session.data.auth_errors = 0;
+
+    // Authorization identity must match the certificate
+    session.stream.tls_identity = Some("jane@example.org".to_string());
+    session.cmd("AUTH EXTERNAL am9obg==", "535 5.7.8").await;
+    session.data.auth_errors = 0;
+
+    // Successful EXTERNAL authentication
+    session.cmd("AUTH EXTERNAL", "334").await;
+    session.cmd("=", "235 2.7.0").await;
+    assert_eq!(session.data.authenticated_as, "jane");
+    session.data.authenticated_as.clear();
+    session.cmd("AUTH EXTERNAL amFuZQ==", "235 2.7.0").await;
+    session.stream.tls_identity = None;
+
     // Login should not be advertised to 10.0.0.2
     session.data.remote_ip = "10.0.0.2".parse().unwrap();
     session.eval_session_params().await;
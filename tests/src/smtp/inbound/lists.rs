@@ -0,0 +1,423 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use hyper::Uri;
+use smtp::{
+    config::{lists::ConfigLists, ConfigContext, IfBlock},
+    core::{Session, SMTP},
+    lists::{ListAction, ListsCore},
+};
+use utils::config::Config;
+
+use crate::smtp::{
+    inbound::{TestMessage, TestQueueEvent},
+    session::{TestSession, VerifyResponse},
+    TestConfig, TestSMTP,
+};
+
+const CONFIG: &str = r#"
+[lists]
+path = "{PATH}"
+secret = "list-secret"
+unsubscribe-url = "https://mail.foobar.org/list/unsubscribe"
+bounce-limit = 1
+
+[list."dev"]
+address = "dev@foobar.org"
+name = "Developers"
+moderators = ["admin@foobar.org"]
+
+[list."news"]
+address = "news@foobar.org"
+name = "News"
+posting = "moderated"
+reply-to = "sender"
+moderators = ["admin@foobar.org"]
+"#;
+
+#[tokio::test]
+async fn mailing_lists() {
+    /*tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(tracing::Level::TRACE)
+            .finish(),
+    )
+    .unwrap();*/
+
+    // Prepare config
+    let mut core = SMTP::test();
+    let mut qr = core.init_test_queue("smtp_lists_test");
+    let lists_path = qr._temp_dir.temp_dir.join("lists");
+    std::fs::create_dir_all(&lists_path).unwrap();
+    let config = Config::parse(&CONFIG.replace("{PATH}", lists_path.to_str().unwrap())).unwrap();
+    core.lists = ListsCore {
+        config: config.parse_lists(&ConfigContext::new(&[])).unwrap(),
+        locks: Default::default(),
+    };
+    core.session.config.rcpt.relay = IfBlock::new(true);
+
+    // Test address classification
+    let lists = &core.lists.config;
+    for (address, expected) in [
+        ("dev@foobar.org", Some(("dev", ListAction::Post))),
+        (
+            "dev-subscribe@foobar.org",
+            Some(("dev", ListAction::Subscribe)),
+        ),
+        (
+            "news-unsubscribe@foobar.org",
+            Some(("news", ListAction::Unsubscribe)),
+        ),
+        (
+            "dev-confirm+abc@foobar.org",
+            Some(("dev", ListAction::Confirm("abc".to_string()))),
+        ),
+        (
+            "dev-bounces+jane=example.org@foobar.org",
+            Some(("dev", ListAction::Bounce("jane=example.org".to_string()))),
+        ),
+        ("dev-confirm@foobar.org", None),
+        ("dev-other@foobar.org", None),
+        ("dev@example.org", None),
+    ] {
+        assert_eq!(
+            lists
+                .classify(address)
+                .map(|(list, action)| (list.id.clone(), action)),
+            expected.map(|(id, action)| (id.to_string(), action)),
+            "failed for {address}"
+        );
+    }
+    let dev = lists.lists.get("dev").unwrap().clone();
+    let news = lists.lists.get("news").unwrap().clone();
+
+    // Non-members cannot post
+    let mut session = Session::test(core);
+    session.data.remote_ip = "10.0.0.1".parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.example.org").await;
+    session
+        .send_message(
+            "jane@example.org",
+            &["dev@foobar.org"],
+            "From: jane@example.org\r\nSubject: Hi\r\n\r\nHello",
+            "550 5.7.1",
+        )
+        .await;
+    qr.assert_empty_queue();
+
+    // Subscribe with confirmation
+    session
+        .send_message(
+            "jane@example.org",
+            &["dev-subscribe@foobar.org"],
+            "From: jane@example.org\r\nSubject: subscribe\r\n\r\n",
+            "250",
+        )
+        .await;
+    let confirm_address = dev.command_address(&format!(
+        "confirm+{}",
+        session
+            .core
+            .lists
+            .config
+            .token(&dev, "subscribe", "jane@example.org")
+    ));
+    let message = qr.read_event().await.unwrap_message();
+    assert_eq!(message.return_path, "dev-bounces@foobar.org");
+    assert_eq!(message.recipients[0].address, "jane@example.org");
+    message
+        .read_lines()
+        .assert_contains(&confirm_address)
+        .assert_contains("Subject: Confirm your subscription to Developers");
+    session
+        .send_message(
+            "bill@example.org",
+            &[confirm_address.as_str()],
+            "From: bill@example.org\r\nSubject: confirm\r\n\r\n",
+            "550 5.7.1",
+        )
+        .await;
+    session
+        .send_message(
+            "jane@example.org",
+            &[confirm_address.as_str()],
+            "From: jane@example.org\r\nSubject: Re: confirm\r\n\r\n",
+            "250",
+        )
+        .await;
+    assert!(session
+        .core
+        .lists
+        .is_member(&dev, "jane@example.org")
+        .await
+        .unwrap());
+    assert!(session.core.lists.subscribe(&dev, "bill@example.org").await);
+    assert!(!session.core.lists.subscribe(&dev, "bill@example.org").await);
+
+    // Posts from unverified members are held for moderation
+    session
+        .send_message(
+            "jane@example.org",
+            &["dev@foobar.org"],
+            "From: jane@example.org\r\nSubject: Hi\r\n\r\nHello",
+            "250",
+        )
+        .await;
+    let message = qr.read_event().await.unwrap_message();
+    assert_eq!(message.recipients[0].address, "admin@foobar.org");
+    message
+        .read_lines()
+        .assert_contains("Subject: Post to Developers requires approval");
+    qr.assert_empty_queue();
+
+    // Verified members can post, each member receives a copy with a VERP return path
+    session.data.authenticated_as = "jane".to_string();
+    session
+        .send_message(
+            "jane@example.org",
+            &["dev@foobar.org"],
+            concat!(
+                "From: jane@example.org\r\n",
+                "Reply-To: jane@example.org\r\n",
+                "List-Id: Other <other.example.org>\r\n",
+                "Subject: Hi\r\n\r\nHello"
+            ),
+            "250",
+        )
+        .await;
+    for member in ["jane@example.org", "bill@example.org"] {
+        let message = qr.read_event().await.unwrap_message();
+        assert_eq!(
+            message.return_path,
+            session.core.lists.config.verp_address(&dev, member)
+        );
+        assert_eq!(message.recipients[0].address, member);
+        message
+            .read_lines()
+            .assert_contains("List-Id: \"Developers\" <dev.foobar.org>")
+            .assert_contains("List-Post: <mailto:dev@foobar.org>")
+            .assert_contains("List-Unsubscribe: <mailto:dev-unsubscribe@foobar.org>")
+            .assert_contains(&format!(
+                "token={}",
+                session.core.lists.config.token(&dev, "unsubscribe", member)
+            ))
+            .assert_contains("List-Unsubscribe-Post: List-Unsubscribe=One-Click")
+            .assert_contains("Reply-To: <dev@foobar.org>")
+            .assert_not_contains("Reply-To: jane@example.org")
+            .assert_not_contains("other.example.org")
+            .assert_contains("Precedence: list");
+    }
+
+    // Mail loops are rejected
+    session
+        .send_message(
+            "jane@example.org",
+            &["dev@foobar.org"],
+            "From: jane@example.org\r\nList-Id: <dev.foobar.org>\r\nSubject: Hi\r\n\r\nHello",
+            "554 5.4.6",
+        )
+        .await;
+    qr.assert_empty_queue();
+
+    // List addresses cannot be mixed with other recipients
+    session.mail_from("jane@example.org", "250").await;
+    session.rcpt_to("dev@foobar.org", "250").await;
+    session.rcpt_to("john@foobar.org", "451 4.5.3").await;
+    session.rset().await;
+    session.mail_from("jane@example.org", "250").await;
+    session.rcpt_to("john@foobar.org", "250").await;
+    session.rcpt_to("dev@foobar.org", "451 4.5.3").await;
+    session.rset().await;
+
+    // Bounces must have a null sender and a valid VERP token
+    let verp_address = session
+        .core
+        .lists
+        .config
+        .verp_address(&dev, "bill@example.org");
+    session
+        .send_message(
+            "jane@example.org",
+            &[verp_address.as_str()],
+            "From: jane@example.org\r\nSubject: Undeliverable\r\n\r\nFailed",
+            "550 5.7.1",
+        )
+        .await;
+    session
+        .send_message(
+            "<>",
+            &["dev-bounces+bill=example.org+0123456789abcdef@foobar.org"],
+            "From: MAILER-DAEMON@example.org\r\nSubject: Undeliverable\r\n\r\nFailed",
+            "250",
+        )
+        .await;
+    session
+        .send_message(
+            "<>",
+            &["dev-bounces+bill=example.org@foobar.org"],
+            "From: MAILER-DAEMON@example.org\r\nSubject: Undeliverable\r\n\r\nFailed",
+            "250",
+        )
+        .await;
+    assert!(session
+        .core
+        .lists
+        .is_member(&dev, "bill@example.org")
+        .await
+        .unwrap());
+
+    // Bounces to VERP addresses unsubscribe members
+    session
+        .send_message(
+            "<>",
+            &[verp_address.as_str()],
+            "From: MAILER-DAEMON@example.org\r\nSubject: Undeliverable\r\n\r\nFailed",
+            "250",
+        )
+        .await;
+    assert!(!session
+        .core
+        .lists
+        .is_member(&dev, "bill@example.org")
+        .await
+        .unwrap());
+
+    // Posts to moderated lists are held until approved
+    session.data.authenticated_as.clear();
+    session
+        .core
+        .lists
+        .subscribe(&news, "bill@example.org")
+        .await;
+    session
+        .send_message(
+            "jane@example.org",
+            &["news@foobar.org"],
+            "From: jane@example.org\r\nReply-To: jane@example.org\r\nSubject: News\r\n\r\nNews",
+            "250",
+        )
+        .await;
+    let message = qr.read_event().await.unwrap_message();
+    assert_eq!(message.recipients[0].address, "admin@foobar.org");
+    message
+        .read_lines()
+        .assert_contains("Subject: Post to News requires approval");
+    let token = std::fs::read_dir(lists_path.join("news"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path()
+        .file_stem()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let approve_address = news.command_address(&format!("approve+{token}"));
+    session
+        .send_message(
+            "jane@example.org",
+            &[approve_address.as_str()],
+            "From: jane@example.org\r\nSubject: approve\r\n\r\n",
+            "550 5.7.1",
+        )
+        .await;
+
+    // Moderators must be verified
+    session
+        .send_message(
+            "admin@foobar.org",
+            &[approve_address.as_str()],
+            "From: admin@foobar.org\r\nSubject: approve\r\n\r\n",
+            "550 5.7.1",
+        )
+        .await;
+    session.data.authenticated_as = "admin".to_string();
+    session
+        .send_message(
+            "admin@foobar.org",
+            &[approve_address.as_str()],
+            "From: admin@foobar.org\r\nSubject: approve\r\n\r\n",
+            "250",
+        )
+        .await;
+    let message = qr.read_event().await.unwrap_message();
+    assert_eq!(message.recipients[0].address, "bill@example.org");
+    message
+        .read_lines()
+        .assert_contains("List-Id: \"News\" <news.foobar.org>")
+        .assert_contains("Reply-To: jane@example.org")
+        .assert_contains("Subject: News");
+    session
+        .send_message(
+            "admin@foobar.org",
+            &[approve_address.as_str()],
+            "From: admin@foobar.org\r\nSubject: approve\r\n\r\n",
+            "550 5.1.1",
+        )
+        .await;
+
+    // One-click unsubscribe
+    let url = session
+        .core
+        .lists
+        .config
+        .unsubscribe_url(&dev, "jane@example.org")
+        .unwrap();
+    let invalid_url = format!("{}token=invalid", url.rsplit_once("token=").unwrap().0);
+    let response = session
+        .core
+        .handle_list_unsubscribe(&invalid_url.parse::<Uri>().unwrap())
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert!(session
+        .core
+        .lists
+        .is_member(&dev, "jane@example.org")
+        .await
+        .unwrap());
+    let response = session
+        .core
+        .handle_list_unsubscribe(&url.parse::<Uri>().unwrap())
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(!session
+        .core
+        .lists
+        .is_member(&dev, "jane@example.org")
+        .await
+        .unwrap());
+
+    // Unreadable member lists are never overwritten
+    let members_path = lists_path.join("dev.json");
+    std::fs::write(&members_path, b"{invalid").unwrap();
+    assert!(!session.core.lists.subscribe(&dev, "bill@example.org").await);
+    assert!(session
+        .core
+        .lists
+        .is_member(&dev, "jane@example.org")
+        .await
+        .is_none());
+    assert_eq!(std::fs::read(&members_path).unwrap(), b"{invalid");
+}
@@ -39,9 +39,12 @@ pub mod dmarc;
 pub mod dnsrbl;
 pub mod ehlo;
 pub mod limits;
+pub mod lists;
 pub mod mail;
 pub mod milter;
+pub mod quarantine;
 pub mod rcpt;
+pub mod rewrite;
 pub mod scripts;
 pub mod sign;
 pub mod throttle;
@@ -0,0 +1,198 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use crate::smtp::{
+    inbound::{TestMessage, TestQueueEvent},
+    session::{TestSession, VerifyResponse},
+    TestConfig, TestSMTP,
+};
+use smtp::{
+    config::{queue::ConfigQueue, scripts::ConfigSieve, ConfigContext, IfBlock},
+    core::{Session, SMTP},
+};
+use utils::config::Config;
+
+const CONFIG: &str = r#"
+[sieve]
+hostname = "mx.foobar.org"
+
+[sieve.scripts]
+data = '''
+require ["envelope", "vnd.stalwart.execute"];
+
+if envelope :localpart :is "to" "suspect" {
+    execute :binary "vnd.stalwart.quarantine" ["Suspicious attachment"];
+}
+'''
+"#;
+
+#[tokio::test]
+async fn quarantine() {
+    /*tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(tracing::Level::TRACE)
+            .finish(),
+    )
+    .unwrap();*/
+
+    // Prepare config
+    let mut core = SMTP::test();
+    let mut qr = core.init_test_queue("smtp_quarantine_test");
+    let mut ctx = ConfigContext::new(&[]);
+    let config = Config::parse(CONFIG).unwrap();
+    core.sieve = config.parse_sieve(&mut ctx).unwrap();
+    core.queue.config.quarantine.path = qr._temp_dir.temp_dir.join("quarantine").into();
+    let config = &mut core.session.config;
+    config.data.script = IfBlock::new(ctx.scripts.get("data").cloned());
+    config.rcpt.relay = IfBlock::new(true);
+
+    // Messages not matching the script are queued
+    let mut session = Session::test(core);
+    session.data.remote_ip = "10.0.0.1".parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+    session
+        .send_message("john@doe.org", &["jane@foobar.org"], "test:no_dkim", "250")
+        .await;
+    qr.read_event()
+        .await
+        .unwrap_message()
+        .read_lines()
+        .assert_contains("Subject: Is dinner ready?");
+    qr.assert_empty_queue();
+
+    // Messages matching the script are quarantined along with their envelope
+    session
+        .send_message(
+            "john@doe.org",
+            &["jane@foobar.org", "suspect@foobar.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    qr.assert_empty_queue();
+    let core = session.core.clone();
+    let quarantined = core.queue.quarantine_list().await;
+    assert_eq!(quarantined.len(), 1);
+    let message = quarantined.into_iter().next().unwrap();
+    assert_eq!(message.return_path, "john@doe.org");
+    assert_eq!(message.reason, "Suspicious attachment");
+    assert_eq!(
+        message
+            .recipients
+            .iter()
+            .map(|r| r.address.as_str())
+            .collect::<Vec<_>>(),
+        vec!["jane@foobar.org", "suspect@foobar.org"]
+    );
+    let (_, contents) = core.queue.quarantine_read(message.id).await.unwrap();
+    assert!(String::from_utf8_lossy(&contents).contains("Subject: Is dinner ready?"));
+
+    // Release the message to its original recipients
+    assert!(
+        core.queue
+            .quarantine_release(message.id, &tracing::Span::none())
+            .await
+    );
+    let released = qr.read_event().await.unwrap_message();
+    assert_eq!(released.return_path, "john@doe.org");
+    assert_eq!(released.recipients.len(), 2);
+    released
+        .read_lines()
+        .assert_contains("Subject: Is dinner ready?");
+    qr.assert_empty_queue();
+    assert!(core.queue.quarantine_list().await.is_empty());
+    assert!(
+        !core
+            .queue
+            .quarantine_release(message.id, &tracing::Span::none())
+            .await
+    );
+
+    // Delete a quarantined message
+    session
+        .send_message(
+            "john@doe.org",
+            &["suspect@foobar.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    let message = core.queue.quarantine_list().await.pop().unwrap();
+    assert!(core.queue.quarantine_delete(message.id).await);
+    assert!(!core.queue.quarantine_delete(message.id).await);
+    assert!(core.queue.quarantine_list().await.is_empty());
+    qr.assert_empty_queue();
+
+    // Expired messages are purged
+    session
+        .send_message(
+            "john@doe.org",
+            &["suspect@foobar.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    assert_eq!(core.queue.quarantine_list().await.len(), 1);
+    core.queue.quarantine_purge().await;
+    assert_eq!(core.queue.quarantine_list().await.len(), 1);
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let mut core = SMTP::test();
+    core.queue.config.quarantine.path = qr._temp_dir.temp_dir.join("quarantine").into();
+    core.queue.config.quarantine.retention = Duration::from_secs(1);
+    core.queue.quarantine_purge().await;
+    assert!(core.queue.quarantine_list().await.is_empty());
+
+    // Scripts that quarantine messages require a quarantine path
+    let config = Config::parse(CONFIG).unwrap();
+    assert!(config.parse_queue_quarantine().is_err());
+    assert!(
+        Config::parse(&format!("{CONFIG}\n[quarantine]\npath = \"/tmp\"\n"))
+            .unwrap()
+            .parse_queue_quarantine()
+            .is_ok()
+    );
+
+    // Messages can't be quarantined without a path
+    let mut core = SMTP::test();
+    let mut qr = core.init_test_queue("smtp_quarantine_no_path");
+    core.sieve = config.parse_sieve(&mut ctx).unwrap();
+    let config = &mut core.session.config;
+    config.data.script = IfBlock::new(ctx.scripts.get("data").cloned());
+    config.rcpt.relay = IfBlock::new(true);
+    let mut session = Session::test(core);
+    session.data.remote_ip = "10.0.0.1".parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+    session
+        .send_message(
+            "john@doe.org",
+            &["suspect@foobar.org"],
+            "test:no_dkim",
+            "451",
+        )
+        .await;
+    qr.assert_empty_queue();
+}
@@ -0,0 +1,163 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use directory::config::ConfigDirectory;
+use utils::config::Config;
+
+use crate::smtp::{
+    inbound::{TestMessage, TestQueueEvent},
+    session::{TestSession, VerifyResponse},
+    TestConfig, TestSMTP,
+};
+use smtp::{
+    config::{rewrite::ConfigRewrite, ConfigContext, IfBlock},
+    core::{Session, SMTP},
+};
+
+const CONFIG: &str = r#"
+[directory."local"]
+type = "memory"
+
+[[directory."local".users]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = "john@foobar.org"
+
+[[directory."local".users]]
+name = "jane"
+description = "Jane Doe"
+secret = "p4ssw0rd"
+email = "jane@foobar.org"
+
+[directory."local".lookup]
+domains = ["foobar.org"]
+legacy = ["legacy.org", "old.legacy.org"]
+
+[rewrite."sender"]
+rules = [{match = "^(.+)\\+.+@(.+)$", replace = "$1@$2"},
+         {lookup = "local/legacy", replace = "foobar.org"}]
+
+[rewrite."rcpt"]
+rules = [{lookup = "local/legacy", replace = "foobar.org"},
+         {match = "^postmaster@", replace = "john@foobar.org"}]
+"#;
+
+#[tokio::test]
+async fn address_rewrite() {
+    let mut core = SMTP::test();
+
+    // Create temp dir for queue
+    let mut qr = core.init_test_queue("smtp_rewrite_test");
+    let config = Config::parse(CONFIG).unwrap();
+    let mut ctx = ConfigContext::new(&[]);
+    ctx.directory = config.parse_directory().unwrap();
+    config.parse_rewrites(&mut ctx).unwrap();
+
+    // Test rewrite rules
+    let sender = ctx.rewrites.get("sender").unwrap().clone();
+    let rcpt = ctx.rewrites.get("rcpt").unwrap().clone();
+    for (rewrite, address, expected) in [
+        (&sender, "john+lists@foobar.org", Some("john@foobar.org")),
+        (&sender, "jane@legacy.org", Some("jane@foobar.org")),
+        (&sender, "jane@old.legacy.org", Some("jane@foobar.org")),
+        (&sender, "jane@foobar.org", None),
+        (&rcpt, "postmaster@example.org", Some("john@foobar.org")),
+        (&rcpt, "jane@legacy.org", Some("jane@foobar.org")),
+        (&rcpt, "jane@example.org", None),
+    ] {
+        assert_eq!(
+            rewrite.rewrite_address(address).await.as_deref(),
+            expected,
+            "failed for {address}"
+        );
+    }
+
+    let config = &mut core.session.config;
+    config.rcpt.directory = IfBlock::new(Some(
+        ctx.directory.directories.get("local").unwrap().clone(),
+    ));
+    config.mail.rewrite = IfBlock::new(Some(sender.clone()));
+    config.rcpt.rewrite = IfBlock::new(Some(rcpt));
+    config.data.rewrite = IfBlock::new(Some(sender));
+
+    // Envelope and header addresses should be rewritten
+    let mut session = Session::test(core);
+    session.data.remote_ip = "10.0.0.1".parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.foobar.org").await;
+    session.mail_from("john+lists@foobar.org", "250").await;
+    assert_eq!(
+        session.data.mail_from.as_ref().unwrap().address_lcase,
+        "john@foobar.org"
+    );
+    session.rcpt_to("jane@legacy.org", "250").await;
+    session.rcpt_to("postmaster@foobar.org", "250").await;
+    session.rcpt_to("bill@foobar.org", "550 5.1.2").await;
+    session
+        .data(
+            concat!(
+                "From: John <john+lists@foobar.org>\r\n",
+                "Reply-To: jane@legacy.org, bill@example.org\r\n",
+                "To: jane@legacy.org\r\n",
+                "Subject: Rewrite test\r\n",
+                "\r\n",
+                "Test message"
+            ),
+            "250",
+        )
+        .await;
+    let message = qr.read_event().await.unwrap_message();
+    assert_eq!(message.return_path_lcase, "john@foobar.org");
+    assert_eq!(
+        message
+            .recipients
+            .iter()
+            .map(|r| r.address_lcase.as_str())
+            .collect::<Vec<_>>(),
+        ["jane@foobar.org", "john@foobar.org"]
+    );
+    message
+        .read_lines()
+        .assert_contains("From: John <john@foobar.org>")
+        .assert_contains("Reply-To: jane@foobar.org, bill@example.org")
+        .assert_contains("To: jane@legacy.org");
+
+    // Invalid rewrite configurations
+    for invalid in [
+        "[rewrite.\"none\"]\nrules = [{replace = \"foobar.org\"}]\n",
+        "[rewrite.\"bad\"]\nrules = [{match = \"(\", replace = \"foobar.org\"}]\n",
+        "[rewrite.\"missing\"]\nrules = [{lookup = \"local/missing\", replace = \"foobar.org\"}]\n",
+        "[rewrite.\"replace\"]\nrules = [{lookup = \"local/legacy\"}]\n",
+    ] {
+        let mut ctx = ConfigContext::new(&[]);
+        ctx.directory = Config::parse(CONFIG).unwrap().parse_directory().unwrap();
+        assert!(
+            Config::parse(invalid)
+                .unwrap()
+                .parse_rewrites(&mut ctx)
+                .is_err(),
+            "{invalid}"
+        );
+    }
+}
@@ -42,14 +42,16 @@ use smtp::{
         AggregateReport, ArcAuthConfig, Auth, ConfigContext, Connect, Data, DkimAuthConfig,
         DmarcAuthConfig, DnsBlConfig, Dsn, Ehlo, EnvelopeKey, Extensions, IfBlock, IpRevAuthConfig,
         Mail, MailAuthConfig, Milter, QueueConfig, QueueOutboundSourceIp, QueueOutboundTimeout,
-        QueueOutboundTls, QueueQuotas, QueueThrottle, Rcpt, Report, ReportAnalysis, ReportConfig,
-        SessionConfig, SessionThrottle, SpfAuthConfig, Throttle, VerifyStrategy,
+        QueueOutboundTls, QueueQuarantine, QueueQuotas, QueueThrottle, Rcpt, Report,
+        ReportAnalysis, ReportConfig, SessionConfig, SessionThrottle, SpfAuthConfig,
+        SuppressionAction, Throttle, VerifyStrategy,
     },
     core::{
         throttle::ThrottleKeyHasherBuilder, QueueCore, ReportCore, Resolvers, SessionCore,
         SieveConfig, SieveCore, TlsConnectors, SMTP,
     },
     outbound::dane::DnssecResolver,
+    reporting::bounce::SentLog,
 };
 use utils::config::{utils::ParseValues, Config};
 
@@ -181,6 +183,8 @@ impl TestConfig for SMTP {
             mail_auth: MailAuthConfig::test(),
             report: ReportCore::test(),
             sieve: SieveCore::test(),
+            lists: Default::default(),
+            store: Default::default(),
             delivery_tx: mpsc::channel(1).0,
         }
     }
@@ -239,17 +243,21 @@ impl TestConfig for SessionConfig {
             },
             mail: Mail {
                 script: IfBlock::new(None),
+                rewrite: IfBlock::new(None),
             },
             rcpt: Rcpt {
                 script: IfBlock::new(None),
+                rewrite: IfBlock::new(None),
                 relay: IfBlock::new(false),
                 directory: IfBlock::new(None),
+                suppression: IfBlock::new(SuppressionAction::Disable),
                 errors_max: IfBlock::new(3),
                 errors_wait: IfBlock::new(Duration::from_secs(1)),
                 max_recipients: IfBlock::new(3),
             },
             data: Data {
                 script: IfBlock::new(None),
+                rewrite: IfBlock::new(None),
                 max_messages: IfBlock::new(10),
                 max_message_size: IfBlock::new(1024 * 1024),
                 max_received_headers: IfBlock::new(10),
@@ -259,6 +267,7 @@ impl TestConfig for SessionConfig {
                 add_auth_results: IfBlock::new(true),
                 add_message_id: IfBlock::new(true),
                 add_date: IfBlock::new(true),
+                suppress_bounces: IfBlock::new(false),
                 pipe_commands: vec![],
                 milters: vec![],
             },
@@ -286,6 +295,7 @@ impl TestConfig for QueueCore {
                 pki_verify: build_tls_connector(false),
                 dummy_verify: build_tls_connector(true),
             },
+            pool: Default::default(),
         }
     }
 }
@@ -302,6 +312,7 @@ impl TestConfig for QueueConfig {
             next_hop: Default::default(),
             max_mx: IfBlock::new(5),
             max_multihomed: IfBlock::new(5),
+            max_pooled: IfBlock::new(0),
             source_ip: QueueOutboundSourceIp {
                 ipv4: IfBlock::new(vec![]),
                 ipv6: IfBlock::new(vec![]),
@@ -326,6 +337,7 @@ impl TestConfig for QueueConfig {
                 rcpt: IfBlock::new(Duration::from_secs(1)),
                 data: IfBlock::new(Duration::from_secs(1)),
                 mta_sts: IfBlock::new(Duration::from_secs(1)),
+                idle: IfBlock::new(Duration::from_secs(1)),
             },
             throttle: QueueThrottle {
                 sender: vec![],
@@ -337,6 +349,11 @@ impl TestConfig for QueueConfig {
                 rcpt: vec![],
                 rcpt_domain: vec![],
             },
+            quarantine: QueueQuarantine {
+                path: None,
+                retention: Duration::from_secs(30 * 86400),
+                purge_frequency: Duration::from_secs(3600),
+            },
             management_lookup: Arc::new(MemoryDirectory::default()),
         }
     }
@@ -377,6 +394,10 @@ impl TestConfig for ReportCore {
         Self {
             config: ReportConfig::test(),
             tx: mpsc::channel(1024).0,
+            sent_log: SentLog {
+                entries: LruCache::with_capacity(128),
+                expire: Duration::from_secs(86400),
+            },
         }
     }
 }
@@ -398,6 +419,7 @@ impl TestConfig for ReportConfig {
             dmarc: Report::test(),
             dmarc_aggregate: AggregateReport::test(),
             tls: AggregateReport::test(),
+            webhooks: vec![],
         }
     }
 }
@@ -34,6 +34,7 @@ pub mod dane;
 pub mod extensions;
 pub mod lmtp;
 pub mod mta_sts;
+pub mod pool;
 pub mod smtp;
 pub mod throttle;
 
@@ -0,0 +1,152 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::smtp::{
+    inbound::TestQueueEvent, outbound::start_test_server, session::TestSession, ParseTestConfig,
+    TestConfig, TestSMTP,
+};
+use smtp::{
+    config::{remote::ConfigHost, ConfigContext, IfBlock},
+    core::{Session, SMTP},
+    queue::{manager::Queue, DeliveryAttempt},
+};
+use utils::config::{Config, ServerProtocol};
+
+const REMOTE: &str = "
+[remote.lmtp]
+address = lmtp.foobar.org
+port = 9924
+protocol = 'lmtp'
+
+[remote.lmtp.tls]
+implicit = true
+allow-invalid-certs = true
+";
+
+#[tokio::test]
+#[serial_test::serial]
+async fn session_pool() {
+    /*tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(tracing::Level::TRACE)
+            .finish(),
+    )
+    .unwrap();*/
+
+    // Start test server
+    let mut core = SMTP::test();
+    core.session.config.rcpt.relay = IfBlock::new(true);
+    core.session.config.data.max_messages = IfBlock::new(3);
+    let mut remote_qr = core.init_test_queue("smtp_pool_remote");
+    let _rx = start_test_server(core.into(), &[ServerProtocol::Lmtp]);
+
+    // Add mock DNS entries
+    let mut core = SMTP::test();
+    core.resolvers.dns.ipv4_add(
+        "lmtp.foobar.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+    let mut local_qr = core.init_test_queue("smtp_pool_local");
+
+    // Enable session pooling
+    let mut ctx = ConfigContext::new(&[]);
+    let config = Config::parse(REMOTE).unwrap();
+    config.parse_remote_hosts(&mut ctx).unwrap();
+    core.queue.config.next_hop = "[{if = 'rcpt-domain', eq = 'foobar.org', then = 'lmtp'},
+    {else = false}]"
+        .parse_if::<Option<String>>(&ctx)
+        .into_relay_host(&ctx)
+        .unwrap();
+    core.queue.config.max_pooled = IfBlock::new(1);
+    core.queue.config.tls.start = "[{if = 'sender', eq = 'secure@test.org', then = 'require'},
+    {else = 'optional'}]"
+        .parse_if(&ctx);
+    core.queue.config.timeout.idle = IfBlock::new(Duration::from_millis(500));
+    core.session.config.rcpt.relay = IfBlock::new(true);
+
+    let core = Arc::new(core);
+    let mut queue = Queue::default();
+    let mut session = Session::test(core.clone());
+    session.data.remote_ip = "10.0.0.1".parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+
+    // Messages to the same host should be delivered over a single session
+    for rcpt in ["bill@foobar.org", "jane@foobar.org", "john@foobar.org"] {
+        session
+            .send_message("john@test.org", &[rcpt], "test:no_dkim", "250")
+            .await;
+        DeliveryAttempt::from(local_qr.read_event().await.unwrap_message())
+            .try_deliver(core.clone(), &mut queue)
+            .await;
+        local_qr.read_event().await.unwrap_done();
+        assert_eq!(
+            remote_qr.read_event().await.unwrap_message().recipients[0].address,
+            rcpt
+        );
+        assert_eq!(core.queue.pool.len(), 1);
+    }
+    remote_qr.assert_empty_queue();
+
+    // A pooled session that can't start a new transaction should be retried over a new connection
+    session
+        .send_message("john@test.org", &["jim@foobar.org"], "test:no_dkim", "250")
+        .await;
+    DeliveryAttempt::from(local_qr.read_event().await.unwrap_message())
+        .try_deliver(core.clone(), &mut queue)
+        .await;
+    local_qr.read_event().await.unwrap_done();
+    assert_eq!(
+        remote_qr.read_event().await.unwrap_message().recipients[0].address,
+        "jim@foobar.org"
+    );
+    assert_eq!(core.queue.pool.len(), 1);
+
+    // Sessions are not shared between messages with different TLS policies
+    session
+        .send_message(
+            "secure@test.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    DeliveryAttempt::from(local_qr.read_event().await.unwrap_message())
+        .try_deliver(core.clone(), &mut queue)
+        .await;
+    local_qr.read_event().await.unwrap_done();
+    remote_qr.read_event().await.unwrap_message();
+    assert_eq!(core.queue.pool.len(), 2);
+    remote_qr.assert_empty_queue();
+
+    // Idle sessions should be closed once expired
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    core.queue.pool.purge();
+    assert!(core.queue.pool.is_empty());
+}
@@ -96,6 +96,7 @@ async fn generate_dsn() {
         env_id: None,
         priority: 0,
 
+        message_id: None,
         queue_refs: vec![],
     });
     let mut attempt = DeliveryAttempt {
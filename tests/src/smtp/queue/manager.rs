@@ -141,6 +141,7 @@ pub fn new_message(id: u64) -> Box<Message> {
         flags: 0,
         env_id: None,
         priority: 0,
+        message_id: None,
         queue_refs: vec![],
     })
 }
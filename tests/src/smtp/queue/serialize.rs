@@ -94,6 +94,7 @@ async fn queue_serialize() {
         env_id: "hello".to_string().into(),
         priority: -1,
 
+        message_id: None,
         queue_refs: vec![],
     };
 
@@ -0,0 +1,309 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use mail_auth::common::lru::DnsCache;
+use tokio::sync::mpsc;
+use utils::{
+    config::Config,
+    ipc::{DeliveryEvent, SuppressedAddress, SuppressionEvent},
+};
+
+use crate::{
+    smtp::{
+        inbound::TestQueueEvent,
+        session::{load_test_message, TestSession},
+        ParseTestConfig, TestConfig, TestSMTP,
+    },
+    store::TempDir,
+};
+use jmap_proto::types::collection::Collection;
+use smtp::{
+    config::{ConfigContext, IfBlock},
+    core::{Session, SMTP},
+    reporting::{
+        bounce::{parse_bounce, parse_references, Bounce},
+        suppression::{SuppressionKey, SuppressionValue},
+    },
+};
+use store::{
+    write::{now, BatchBuilder, Operation, ValueClass},
+    Serialize, Store,
+};
+
+const TEXT_BOUNCE: &str = r#"From: Mail Delivery System <MAILER-DAEMON@mx.example.org>
+To: sender@foobar.org
+Subject: Undelivered Mail Returned to Sender
+
+This is the mail system at host mx.example.org.
+
+I'm sorry to have to inform you that your message could not
+be delivered to one or more recipients.
+
+<jdoe@example.org>: host mx.example.org[10.0.0.2] said: 550 5.1.1
+    <jdoe@example.org>: Recipient address rejected: User unknown
+
+<busy@example.org>: host mx.example.org[10.0.0.2] said: 452 4.2.2
+    Mailbox full, try again later
+
+--- Original message headers ---
+Received: from foobar.org (unknown [10.0.0.1])
+	by mx.foobar.org (Stalwart SMTP) with ESMTPS id 1A2B3C;
+	Thu, 1 Jun 2023 10:00:00 +0000
+Subject: Lunch
+"#;
+
+#[test]
+fn bounce_parse() {
+    for (message, expected) in [
+        (
+            load_test_message("failure", "dsn"),
+            vec![Bounce {
+                address: "foobar@example.org".to_string(),
+                reason: "550 User does not exist".to_string(),
+            }],
+        ),
+        (
+            load_test_message("mixed", "dsn"),
+            vec![Bounce {
+                address: "foobar@example.org".to_string(),
+                reason: "550 User does not exist".to_string(),
+            }],
+        ),
+        (load_test_message("delay", "dsn"), vec![]),
+        (load_test_message("success", "dsn"), vec![]),
+        (
+            TEXT_BOUNCE.replace('\n', "\r\n"),
+            vec![Bounce {
+                address: "jdoe@example.org".to_string(),
+                reason: "<jdoe@example.org>: host mx.example.org[10.0.0.2] said: 550 5.1.1"
+                    .to_string(),
+            }],
+        ),
+    ] {
+        let message = mail_parser::Message::parse(message.as_bytes()).unwrap();
+        assert_eq!(parse_bounce(&message), expected);
+    }
+
+    // References to the original message
+    for (message, expected) in [
+        (
+            load_test_message("failure", "dsn"),
+            vec!["01HEGJ0WNBY28Y95LN@mr.timeplex.com"],
+        ),
+        (TEXT_BOUNCE.replace('\n', "\r\n"), vec!["1A2B3C"]),
+    ] {
+        let message = mail_parser::Message::parse(message.as_bytes()).unwrap();
+        assert_eq!(parse_references(&message), expected);
+    }
+}
+
+#[tokio::test]
+async fn bounce_suppression() {
+    let mut core = SMTP::test();
+
+    // Create temp dir for queue
+    let mut qr = core.init_test_queue("smtp_bounce_suppression_test");
+
+    let (delivery_tx, mut delivery_rx) = mpsc::channel(128);
+    core.delivery_tx = delivery_tx;
+    let mut config = &mut core.session.config.rcpt;
+    config.relay = IfBlock::new(true);
+    config.suppression = r"[{if = 'sender-domain', eq = 'foobar.org', then = 'reject'},
+    {else = 'warn'}]"
+        .parse_if(&ConfigContext::new(&[]));
+    let mut config = &mut core.session.config.data;
+    config.suppress_bounces = IfBlock::new(true);
+    config.max_messages = IfBlock::new(1024);
+
+    let core = Arc::new(core);
+    let mut session = Session::test(core.clone());
+    session.data.remote_ip = "10.0.0.1".parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.example.org").await;
+
+    // Bounces for messages not sent by this server are ignored
+    session
+        .send_message(
+            "<>",
+            &["sender@foobar.org"],
+            &load_test_message("failure", "dsn"),
+            "250",
+        )
+        .await;
+    qr.read_event().await.unwrap_message();
+    expect_no_suppression(&mut delivery_rx).await;
+
+    // Only the recipients of the original message can be suppressed
+    let valid_until = Instant::now() + Duration::from_secs(60);
+    core.report.sent_log.entries.insert(
+        "01HEGJ0WNBY28Y95LN@mr.timeplex.com".to_string(),
+        Arc::new(vec!["other@example.org".to_string()]),
+        valid_until,
+    );
+    session
+        .send_message(
+            "<>",
+            &["sender@foobar.org"],
+            &load_test_message("failure", "dsn"),
+            "250",
+        )
+        .await;
+    qr.read_event().await.unwrap_message();
+    expect_no_suppression(&mut delivery_rx).await;
+
+    // Hard bounces addressed to local senders are added to the suppression list
+    core.report.sent_log.entries.insert(
+        "01HEGJ0WNBY28Y95LN@mr.timeplex.com".to_string(),
+        Arc::new(vec!["foobar@example.org".to_string()]),
+        valid_until,
+    );
+    core.report.sent_log.entries.insert(
+        "1A2B3C".to_string(),
+        Arc::new(vec![
+            "jdoe@example.org".to_string(),
+            "busy@example.org".to_string(),
+        ]),
+        valid_until,
+    );
+    session
+        .send_message(
+            "<>",
+            &["sender@foobar.org"],
+            &load_test_message("failure", "dsn"),
+            "250",
+        )
+        .await;
+    qr.read_event().await.unwrap_message();
+    assert_eq!(
+        expect_suppression(&mut delivery_rx).await,
+        vec![("foobar.org".to_string(), "foobar@example.org".to_string())]
+    );
+    session
+        .send_message("<>", &["sender@foobar.org"], TEXT_BOUNCE, "250")
+        .await;
+    qr.read_event().await.unwrap_message();
+    assert_eq!(
+        expect_suppression(&mut delivery_rx).await,
+        vec![("foobar.org".to_string(), "jdoe@example.org".to_string())]
+    );
+
+    // Delays and messages with a return path are ignored
+    session
+        .send_message(
+            "<>",
+            &["sender@foobar.org"],
+            &load_test_message("delay", "dsn"),
+            "250",
+        )
+        .await;
+    qr.read_event().await.unwrap_message();
+    session
+        .send_message(
+            "john@example.org",
+            &["sender@foobar.org"],
+            &load_test_message("failure", "dsn"),
+            "250",
+        )
+        .await;
+    qr.read_event().await.unwrap_message();
+    expect_no_suppression(&mut delivery_rx).await;
+
+    // Suppression lookups are read from the store
+    let temp_dir = TempDir::new("smtp_bounce_suppression_store", true);
+    let config = Config::parse(&format!(
+        concat!(
+            "store.blob.type = \"local\"\n",
+            "store.blob.local.path = \"{}\"\n",
+            "store.db.path = \"{}/sqlite.db\"\n",
+        ),
+        temp_dir.path.display(),
+        temp_dir.path.display(),
+    ))
+    .unwrap();
+    let store = Arc::new(Store::open(&config).await.unwrap());
+    store.destroy().await;
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(u32::MAX)
+        .with_collection(Collection::Principal);
+    for domain in ["foobar.org", "test.org"] {
+        batch.op(Operation::Value {
+            class: ValueClass::Custom {
+                bytes: SuppressionKey::new(domain, "jdoe@example.org"),
+            },
+            set: SuppressionValue {
+                created: now(),
+                reason: "550 User does not exist".to_string(),
+            }
+            .serialize()
+            .into(),
+        });
+    }
+    store.write(batch.build()).await.unwrap();
+    assert!(core.store.set(store).is_ok());
+
+    // Suppressed recipients are rejected
+    session.mail_from("sender@foobar.org", "250").await;
+    session.rcpt_to("jdoe@example.org", "550 5.1.1").await;
+    session.rcpt_to("jane@example.org", "250").await;
+    session.data("test:no_dkim", "250").await;
+    qr.read_event().await.unwrap_message();
+
+    // Or accepted with a warning
+    session.mail_from("sender@test.org", "250").await;
+    session.rcpt_to("jdoe@example.org", "250").await;
+    session.data("test:no_dkim", "250").await;
+    qr.read_event().await.unwrap_message();
+
+    temp_dir.delete();
+}
+
+async fn expect_suppression(
+    delivery_rx: &mut mpsc::Receiver<DeliveryEvent>,
+) -> Vec<(String, String)> {
+    match tokio::time::timeout(Duration::from_millis(500), delivery_rx.recv()).await {
+        Ok(Some(DeliveryEvent::Suppression(SuppressionEvent::Add { addresses }))) => addresses
+            .into_iter()
+            .map(
+                |SuppressedAddress {
+                     domain, address, ..
+                 }| (domain, address),
+            )
+            .collect(),
+        result => panic!("Unexpected result: {result:?}"),
+    }
+}
+
+async fn expect_no_suppression(delivery_rx: &mut mpsc::Receiver<DeliveryEvent>) {
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), delivery_rx.recv())
+            .await
+            .is_err(),
+        "Unexpected suppression event"
+    );
+}
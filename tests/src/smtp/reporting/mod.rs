@@ -22,6 +22,8 @@
 */
 
 pub mod analyze;
+pub mod bounce;
 pub mod dmarc;
 pub mod scheduler;
 pub mod tls;
+pub mod webhook;
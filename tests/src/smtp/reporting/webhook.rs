@@ -0,0 +1,324 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use http_body_util::{BodyExt, Full};
+use hyper::{body, server::conn::http1, service::service_fn, StatusCode};
+use hyper_util::rt::TokioIo;
+use tokio::{net::TcpListener, sync::mpsc};
+
+use crate::smtp::{
+    inbound::TestQueueEvent, outbound::start_test_server, session::TestSession, ParseTestConfig,
+    TestConfig, TestSMTP,
+};
+use smtp::{
+    config::{
+        remote::ConfigHost, webhook::ConfigWebhook, ConfigContext, IfBlock, WebhookEventType,
+    },
+    core::{Session, SMTP},
+    queue::{manager::Queue, DeliveryAttempt, Event, Status, WorkerResult},
+    reporting::webhook::{sign, WebhookEvent},
+};
+use store::write::now;
+use utils::config::{Config, ServerProtocol};
+
+const REMOTE: &str = "
+[remote.lmtp]
+address = lmtp.foobar.org
+port = 9924
+protocol = 'lmtp'
+concurrency = 5
+
+[remote.lmtp.tls]
+implicit = true
+allow-invalid-certs = true
+";
+
+const WEBHOOK: &str = "
+[webhook.test]
+url = 'http://127.0.0.1:{PORT}/events'
+signature-key = 'secret'
+headers = ['X-Test: webhook']
+timeout = '1s'
+events = ['delivered', 'deferred', 'bounced', 'expired', 'rejected']
+
+[webhook.test.attempts]
+max = 3
+interval = '100ms'
+";
+
+#[tokio::test]
+#[serial_test::serial]
+async fn webhooks() {
+    /*tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(tracing::Level::DEBUG)
+            .finish(),
+    )
+    .unwrap();*/
+
+    // Start webhook server
+    let (mut webhook_rx, port) = spawn_webhook_server().await;
+
+    // Start test server
+    let mut core = SMTP::test();
+    core.session.config.rcpt.relay = IfBlock::new(true);
+    let mut remote_qr = core.init_test_queue("smtp_webhook_remote");
+    let _rx = start_test_server(core.into(), &[ServerProtocol::Lmtp]);
+
+    // Add mock DNS entries
+    let mut core = SMTP::test();
+    core.resolvers.dns.ipv4_add(
+        "lmtp.foobar.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+    let mut local_qr = core.init_test_queue("smtp_webhook_local");
+
+    let mut ctx = ConfigContext::new(&[]);
+    let config = Config::parse(REMOTE).unwrap();
+    config.parse_remote_hosts(&mut ctx).unwrap();
+    core.queue.config.next_hop = "[{if = 'rcpt-domain', eq = 'foobar.org', then = 'lmtp'},
+    {else = false}]"
+        .parse_if::<Option<String>>(&ctx)
+        .into_relay_host(&ctx)
+        .unwrap();
+    core.report.config.webhooks = Config::parse(&WEBHOOK.replace("{PORT}", &port.to_string()))
+        .unwrap()
+        .parse_webhooks()
+        .unwrap()
+        .into_iter()
+        .map(Arc::new)
+        .collect();
+    core.session.config.rcpt.max_recipients = IfBlock::new(100);
+    let config = &mut core.queue.config;
+    config.retry = IfBlock::new(vec![Duration::from_millis(100)]);
+    config.expire = IfBlock::new(Duration::from_millis(400));
+    config.timeout.data = IfBlock::new(Duration::from_millis(50));
+    let core = Arc::new(core);
+
+    // Rejected recipients should be reported, the first request
+    // fails and has to be retried
+    let mut session = Session::test(core.clone());
+    session.data.remote_ip = "10.0.0.1".parse().unwrap();
+    session.eval_session_params().await;
+    session.params.rcpt_relay = false;
+    session.ehlo("mx.test.org").await;
+    session.mail_from("john@test.org", "250").await;
+    session.rcpt_to("jane@example.org", "550 5.1.2").await;
+    let event = expect_webhook(&mut webhook_rx).await;
+    assert_eq!(event.typ, WebhookEventType::Rejected);
+    assert_eq!(event.queue_id, None);
+    assert_eq!(event.return_path, "john@test.org");
+    assert_eq!(event.recipient, "jane@example.org");
+    assert_eq!(event.remote_mta.as_deref(), Some("mx.test.org"));
+    assert_eq!(event.remote_ip, Some("10.0.0.1".parse().unwrap()));
+    assert!(matches!(event.status, Status::PermanentFailure(_)));
+    let response = event.response.unwrap();
+    assert_eq!(response.code, 550);
+    assert_eq!(response.enhanced_code.as_deref(), Some("5.1.2"));
+    assert_eq!(response.message, "Relay not allowed.");
+
+    // Deliver message and report each recipient status
+    let mut session = Session::test(core.clone());
+    session.data.remote_ip = "10.0.0.1".parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+    session
+        .send_message(
+            "john@test.org",
+            &[
+                "<bill@foobar.org>",
+                "<delay@foobar.org>",
+                "<fail@foobar.org>",
+            ],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    let mut queue = Queue::default();
+    DeliveryAttempt::from(local_qr.read_event().await.unwrap_message())
+        .try_deliver(core.clone(), &mut queue)
+        .await;
+    loop {
+        match local_qr.try_read_event().await {
+            Some(Event::Queue(_)) => (),
+            Some(Event::Done(wr)) => match wr {
+                WorkerResult::Done => {
+                    break;
+                }
+                WorkerResult::Retry(retry) => {
+                    queue.schedule(retry);
+                }
+                WorkerResult::OnHold(_) => unreachable!(),
+            },
+            None | Some(Event::Stop) => break,
+            Some(Event::Manage(_)) => unreachable!(),
+        }
+
+        if !queue.scheduled.is_empty() {
+            tokio::time::sleep(queue.wake_up_time()).await;
+            DeliveryAttempt::from(queue.next_due().unwrap())
+                .try_deliver(core.clone(), &mut queue)
+                .await;
+        }
+    }
+    assert!(queue.scheduled.is_empty());
+    remote_qr.read_event().await.unwrap_message();
+    remote_qr.assert_empty_queue();
+
+    // Collect webhook events
+    let mut events = Vec::new();
+    while let Ok(Some(event)) =
+        tokio::time::timeout(Duration::from_millis(500), webhook_rx.recv()).await
+    {
+        assert!(event.queue_id.is_some());
+        assert_eq!(event.return_path, "john@test.org");
+        events.push((event.typ, event.recipient, event.remote_mta));
+    }
+    let lmtp_host = Some("lmtp.foobar.org".to_string());
+    for expected_event in [
+        (
+            WebhookEventType::Delivered,
+            "bill@foobar.org".to_string(),
+            lmtp_host.clone(),
+        ),
+        (
+            WebhookEventType::Bounced,
+            "fail@foobar.org".to_string(),
+            lmtp_host.clone(),
+        ),
+        (
+            WebhookEventType::Deferred,
+            "delay@foobar.org".to_string(),
+            lmtp_host.clone(),
+        ),
+        (
+            WebhookEventType::Expired,
+            "delay@foobar.org".to_string(),
+            lmtp_host.clone(),
+        ),
+    ] {
+        assert!(
+            events.contains(&expected_event),
+            "Missing {expected_event:?} in {events:?}"
+        );
+    }
+    assert!(!events
+        .iter()
+        .any(|(typ, rcpt, _)| rcpt != "delay@foobar.org" && *typ == WebhookEventType::Deferred));
+}
+
+async fn expect_webhook(rx: &mut mpsc::Receiver<WebhookEvent>) -> WebhookEvent {
+    match tokio::time::timeout(Duration::from_secs(2), rx.recv()).await {
+        Ok(Some(event)) => event,
+        result => panic!("Timeout waiting for webhook: {result:?}"),
+    }
+}
+
+async fn spawn_webhook_server() -> (mpsc::Receiver<WebhookEvent>, u16) {
+    let (tx, rx) = mpsc::channel(100);
+    let fail_next = Arc::new(AtomicBool::new(true));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let tx = tx.clone();
+            let fail_next = fail_next.clone();
+
+            tokio::spawn(async move {
+                let _ = http1::Builder::new()
+                    .keep_alive(false)
+                    .serve_connection(
+                        TokioIo::new(stream),
+                        service_fn(|req: hyper::Request<body::Incoming>| {
+                            let tx = tx.clone();
+                            let fail_next = fail_next.clone();
+
+                            async move {
+                                assert_eq!(req.uri().path(), "/events");
+                                assert_eq!(
+                                    req.headers().get("X-Test").unwrap().to_str().unwrap(),
+                                    "webhook"
+                                );
+                                let signature = req
+                                    .headers()
+                                    .get("X-Signature")
+                                    .unwrap()
+                                    .to_str()
+                                    .unwrap()
+                                    .to_string();
+                                let timestamp = req
+                                    .headers()
+                                    .get("X-Signature-Timestamp")
+                                    .unwrap()
+                                    .to_str()
+                                    .unwrap()
+                                    .parse::<u64>()
+                                    .unwrap();
+                                assert!(now().abs_diff(timestamp) < 60);
+                                let body = String::from_utf8(
+                                    req.into_body().collect().await.unwrap().to_bytes().to_vec(),
+                                )
+                                .unwrap();
+                                assert_eq!(
+                                    signature,
+                                    format!(
+                                        "sha256={}",
+                                        sign("secret", &format!("{timestamp}.{body}"))
+                                    )
+                                );
+
+                                let status = if fail_next.swap(false, Ordering::Relaxed) {
+                                    StatusCode::INTERNAL_SERVER_ERROR
+                                } else {
+                                    tx.send(serde_json::from_str::<WebhookEvent>(&body).unwrap())
+                                        .await
+                                        .unwrap();
+                                    StatusCode::OK
+                                };
+
+                                Ok::<_, hyper::Error>(
+                                    hyper::Response::builder()
+                                        .status(status)
+                                        .body(Full::new(body::Bytes::from_static(b"")))
+                                        .unwrap(),
+                                )
+                            }
+                        }),
+                    )
+                    .await;
+            });
+        }
+    });
+
+    (rx, port)
+}
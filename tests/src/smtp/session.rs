@@ -43,6 +43,7 @@ pub struct DummyIo {
     pub tx_buf: Vec<u8>,
     pub rx_buf: Vec<u8>,
     pub tls: bool,
+    pub tls_identity: Option<String>,
 }
 
 impl AsyncRead for DummyIo {
@@ -96,6 +97,10 @@ impl IsTls for DummyIo {
     fn tls_version_and_cipher(&self) -> (&'static str, &'static str) {
         ("", "")
     }
+
+    fn tls_identity(&self) -> Option<String> {
+        self.tls_identity.clone()
+    }
 }
 
 impl Unpin for DummyIo {}
@@ -128,6 +133,7 @@ impl TestSession for Session<DummyIo> {
                 rx_buf: vec![],
                 tx_buf: vec![],
                 tls: false,
+                tls_identity: None,
             },
             data: SessionData::new(
                 "127.0.0.1".parse().unwrap(),
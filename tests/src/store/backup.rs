@@ -0,0 +1,393 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::atomic::Ordering;
+
+use store::{
+    migrate::MigrationProgress,
+    write::{BatchBuilder, Operation, ValueClass},
+    BitmapKey, BlobKind, CustomValueKey, Store, ValueKey,
+};
+use utils::config::Config;
+
+use crate::store::TempDir;
+
+const CONFIG: &str = r#"
+[store.db]
+path = "{TMP}/_backup_test.db?mode=rwc"
+
+[store.blob]
+type = "local"
+
+[store.blob.local]
+path = "{TMP}"
+
+"#;
+
+const SETTING: &[u8] = b"\x07backup-setting";
+
+#[tokio::test]
+pub async fn backup_tests() {
+    let source_dir = TempDir::new("backup_tests_source", true);
+    let restore_dir = TempDir::new("backup_tests_restore", true);
+    let archive_dir = TempDir::new("backup_tests_archive", true);
+    let full_path = archive_dir.path.join("full.bak");
+    let incremental_path = archive_dir.path.join("incremental.bak");
+    let archives = [full_path.clone(), incremental_path.clone()];
+
+    // Populate two accounts and a server-wide setting
+    let source = open_store(&source_dir).await;
+    for account_id in [1, 2] {
+        write_document(&source, account_id, 0, "first revision").await;
+    }
+    write_setting(&source, "full").await;
+
+    // Blobs without a document in the snapshot are not archived
+    let orphan_blob = BlobKind::LinkedMaildir {
+        account_id: 1,
+        document_id: 9,
+    };
+    source.put_blob(&orphan_blob, b"orphan").await.unwrap();
+
+    // Full backup
+    let manifest = source.backup(&full_path, None).await.unwrap();
+    assert_eq!(manifest.base_id, 0);
+    assert!(manifest.accounts[&1].included);
+    assert!(manifest.accounts[&2].included);
+
+    // Only account 2 changes before the incremental backup
+    write_document(&source, 2, 1, "second revision").await;
+    write_setting(&source, "incremental").await;
+    let incremental = source
+        .backup(&incremental_path, Some(&full_path))
+        .await
+        .unwrap();
+    assert_eq!(incremental.base_id, manifest.id);
+    assert!(!incremental.accounts[&1].included);
+    assert!(incremental.accounts[&2].included);
+
+    // Restoring an incremental archive without its base must fail
+    let target = open_store(&restore_dir).await;
+    assert!(target
+        .restore(&[incremental_path.clone()], None)
+        .await
+        .is_err());
+
+    // Rebuild the whole server
+    target.restore(&archives, None).await.unwrap();
+    assert_document(&target, 1, 0, "first revision").await;
+    assert_document(&target, 2, 0, "first revision").await;
+    assert_document(&target, 2, 1, "second revision").await;
+    assert!(target
+        .get_blob(&orphan_blob, 0..u32::MAX)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        target
+            .get_value::<String>(CustomValueKey {
+                value: SETTING.to_vec()
+            })
+            .await
+            .unwrap()
+            .unwrap(),
+        "incremental"
+    );
+
+    // Restore a single account over local modifications
+    write_document(&target, 1, 0, "modified").await;
+    write_document(&target, 2, 2, "kept").await;
+    target.restore(&archives, 1.into()).await.unwrap();
+    assert_document(&target, 1, 0, "first revision").await;
+    assert_document(&target, 2, 2, "kept").await;
+    assert!(target.restore(&archives, 3.into()).await.is_err());
+
+    // Migrate the full archive first and catch up with the incremental one
+    let migrate_dir = TempDir::new("backup_tests_migrate", true);
+    let checkpoint_path = archive_dir.path.join("migrate.checkpoint");
+    let target = open_store(&migrate_dir).await;
+    let progress = MigrationProgress::default();
+    let report = target
+        .migrate(&[full_path.clone()], &checkpoint_path, &progress)
+        .await
+        .unwrap();
+    assert_eq!(report.applied, vec![manifest.id]);
+    assert!(report.mismatched_accounts.is_empty());
+    assert!(!report.mismatched_global);
+    assert_document(&target, 2, 0, "first revision").await;
+    let report = target
+        .migrate(&archives, &checkpoint_path, &progress)
+        .await
+        .unwrap();
+    assert_eq!(report.applied, vec![incremental.id]);
+    assert!(report.mismatched_accounts.is_empty());
+    assert!(!report.mismatched_global);
+    assert_document(&target, 1, 0, "first revision").await;
+    assert_document(&target, 2, 1, "second revision").await;
+
+    // Local changes are detected by the verification
+    write_document(&target, 1, 0, "modified").await;
+    let report = target
+        .migrate(&archives, &checkpoint_path, &progress)
+        .await
+        .unwrap();
+    assert!(report.applied.is_empty());
+    assert_eq!(report.mismatched_accounts, vec![1]);
+
+    // Resume an interrupted migration from the offset in the checkpoint, which points
+    // to the first value record so the preceding bitmaps are not copied.
+    let resume_dir = TempDir::new("backup_tests_resume", true);
+    let resume_checkpoint = archive_dir.path.join("resume.checkpoint");
+    let archive = std::fs::read(&full_path).unwrap();
+    let mut offset = 7;
+    while archive[offset] != b'v' {
+        let key_len =
+            u32::from_be_bytes(archive[offset + 1..offset + 5].try_into().unwrap()) as usize;
+        let value_len = u32::from_be_bytes(
+            archive[offset + 5 + key_len..offset + 9 + key_len]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        offset += 9 + key_len + value_len;
+    }
+    let mut checkpoint = Vec::new();
+    checkpoint.extend_from_slice(&0u64.to_be_bytes());
+    checkpoint.extend_from_slice(&manifest.id.to_be_bytes());
+    checkpoint.extend_from_slice(&(offset as u64).to_be_bytes());
+    checkpoint.push(1);
+    std::fs::write(&resume_checkpoint, checkpoint).unwrap();
+    let target = open_store(&resume_dir).await;
+    let report = target
+        .migrate(
+            &[full_path.clone()],
+            &resume_checkpoint,
+            &MigrationProgress::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(report.applied, vec![manifest.id]);
+    assert_eq!(report.mismatched_accounts, vec![1, 2]);
+    assert_eq!(
+        target
+            .get_value::<String>(ValueKey::new(1, 0u8, 0, 0u8))
+            .await
+            .unwrap()
+            .unwrap(),
+        "first revision"
+    );
+    assert!(target
+        .get_bitmap(BitmapKey::document_ids(1, 0u8))
+        .await
+        .unwrap()
+        .map_or(true, |document_ids| document_ids.is_empty()));
+
+    // Stream the source directly into a store holding stale records
+    let stream_dir = TempDir::new("backup_tests_stream", true);
+    let stream_checkpoint = archive_dir.path.join("stream.checkpoint");
+    let target = open_store(&stream_dir).await;
+    write_document(&target, 7, 0, "stale").await;
+    let stale_setting = CustomValueKey {
+        value: b"\x07stale-setting".to_vec(),
+    };
+    let mut batch = BatchBuilder::new();
+    batch.op(Operation::Value {
+        class: ValueClass::Custom {
+            bytes: stale_setting.value.clone(),
+        },
+        set: b"stale".to_vec().into(),
+    });
+    target.write(batch.build()).await.unwrap();
+    let progress = MigrationProgress::default();
+    let report = stream_migrate(&source, &target, &stream_checkpoint, &progress).await;
+    assert!(report.mismatched_accounts.is_empty());
+    assert!(!report.mismatched_global);
+    assert_eq!(report.records, progress.records.load(Ordering::Relaxed));
+    assert!(report.records > 0);
+    assert_eq!(report.blobs, 3);
+    assert_document(&target, 1, 0, "first revision").await;
+    assert_document(&target, 2, 1, "second revision").await;
+    assert!(target
+        .get_bitmap(BitmapKey::document_ids(7, 0u8))
+        .await
+        .unwrap()
+        .map_or(true, |document_ids| document_ids.is_empty()));
+    assert!(target
+        .get_value::<String>(ValueKey::new(7, 0u8, 0, 0u8))
+        .await
+        .unwrap()
+        .is_none());
+    assert!(target
+        .get_value::<String>(stale_setting)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Catch up with the changes made since, only the modified account is streamed
+    write_document(&source, 2, 2, "third revision").await;
+    write_setting(&source, "streamed").await;
+    let progress = MigrationProgress::default();
+    let report = stream_migrate(&source, &target, &stream_checkpoint, &progress).await;
+    assert!(report.mismatched_accounts.is_empty());
+    assert!(!report.mismatched_global);
+    assert_eq!(report.blobs, 3);
+    assert_document(&target, 1, 0, "first revision").await;
+    assert_document(&target, 2, 2, "third revision").await;
+    assert_eq!(
+        target
+            .get_value::<String>(CustomValueKey {
+                value: SETTING.to_vec()
+            })
+            .await
+            .unwrap()
+            .unwrap(),
+        "streamed"
+    );
+
+    // A stream that does not follow the last applied migration is rejected up front
+    write_setting(&source, "rejected").await;
+    let (writer, reader) = tokio::io::duplex(1024);
+    let (_, migrate_result) = tokio::join!(
+        source.export(writer, None),
+        target.migrate_stream(reader, &stream_checkpoint, &MigrationProgress::default())
+    );
+    assert!(migrate_result.is_err());
+    assert_eq!(
+        target
+            .get_value::<String>(CustomValueKey {
+                value: SETTING.to_vec()
+            })
+            .await
+            .unwrap()
+            .unwrap(),
+        "streamed"
+    );
+
+    source_dir.delete();
+    restore_dir.delete();
+    migrate_dir.delete();
+    resume_dir.delete();
+    stream_dir.delete();
+    archive_dir.delete();
+}
+
+async fn stream_migrate(
+    source: &Store,
+    target: &Store,
+    checkpoint: &std::path::Path,
+    progress: &MigrationProgress,
+) -> store::migrate::MigrationReport {
+    let base = target.migration_base(checkpoint).await.unwrap();
+    let (writer, reader) = tokio::io::duplex(1024);
+    let (export_result, migrate_result) = tokio::join!(
+        source.export(writer, base),
+        target.migrate_stream(reader, checkpoint, progress)
+    );
+    let (manifest, _) = export_result.unwrap();
+    let report = migrate_result.unwrap();
+    assert_eq!(report.applied, vec![manifest.id]);
+    report
+}
+
+async fn open_store(temp_dir: &TempDir) -> Store {
+    Store::open(
+        &Config::parse(&CONFIG.replace("{TMP}", temp_dir.path.as_path().to_str().unwrap()))
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+}
+
+async fn write_document(store: &Store, account_id: u32, document_id: u32, text: &str) {
+    let change_id = store.assign_change_id(account_id).await.unwrap();
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(account_id)
+        .with_collection(0u8)
+        .create_document(document_id)
+        .op(Operation::Value {
+            class: ValueClass::Property {
+                field: 0,
+                family: 0,
+            },
+            set: text.as_bytes().to_vec().into(),
+        })
+        .quota(text.len() as i64)
+        .op(Operation::Log {
+            change_id,
+            collection: 0,
+            set: document_id.to_be_bytes().to_vec(),
+        });
+    store.write(batch.build()).await.unwrap();
+    store
+        .put_blob(
+            &BlobKind::LinkedMaildir {
+                account_id,
+                document_id,
+            },
+            text.as_bytes(),
+        )
+        .await
+        .unwrap();
+}
+
+async fn write_setting(store: &Store, value: &str) {
+    let mut batch = BatchBuilder::new();
+    batch.op(Operation::Value {
+        class: ValueClass::Custom {
+            bytes: SETTING.to_vec(),
+        },
+        set: value.as_bytes().to_vec().into(),
+    });
+    store.write(batch.build()).await.unwrap();
+}
+
+async fn assert_document(store: &Store, account_id: u32, document_id: u32, text: &str) {
+    assert_eq!(
+        store
+            .get_value::<String>(ValueKey::new(account_id, 0u8, document_id, 0u8))
+            .await
+            .unwrap()
+            .unwrap(),
+        text
+    );
+    assert!(store
+        .get_bitmap(BitmapKey::document_ids(account_id, 0u8))
+        .await
+        .unwrap()
+        .unwrap()
+        .contains(document_id));
+    assert_eq!(
+        store
+            .get_blob(
+                &BlobKind::LinkedMaildir {
+                    account_id,
+                    document_id,
+                },
+                0..u32::MAX
+            )
+            .await
+            .unwrap()
+            .unwrap(),
+        text.as_bytes()
+    );
+}